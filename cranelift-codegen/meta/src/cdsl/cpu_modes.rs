@@ -2,7 +2,7 @@ use std::collections::{hash_map, HashMap, HashSet};
 use std::iter::FromIterator;
 
 use crate::cdsl::encodings::Encoding;
-use crate::cdsl::types::{LaneType, ValueType};
+use crate::cdsl::types::{LaneType, ReferenceType, ValueType};
 use crate::cdsl::xform::{TransformGroup, TransformGroupIndex};
 
 pub(crate) struct CpuMode {
@@ -43,6 +43,16 @@ impl CpuMode {
             .insert(lane_type.into().into(), group.id)
             .is_none());
     }
+    pub fn legalize_reference_type(
+        &mut self,
+        reference_type: impl Into<ReferenceType>,
+        group: &TransformGroup,
+    ) {
+        assert!(self
+            .typed_legalize
+            .insert(reference_type.into().into(), group.id)
+            .is_none());
+    }
 
     pub fn get_default_legalize_code(&self) -> TransformGroupIndex {
         self.default_legalize
@@ -409,6 +409,9 @@ fn substitute(map: &HashMap<&TypeVar, TypeVar>, external_type_var: &TypeVar) ->
 /// - half/double pairs collapse
 ///     {HALF,DOUBLE}WIDTH({DOUBLE,HALF}WIDTH(base)) -> base
 ///     {HALF,DOUBLE}VECTOR({DOUBLE,HALF}VECTOR(base)) -> base
+///
+/// - split/merge pairs collapse
+///     {SPLIT,MERGE}LANES({MERGE,SPLIT}LANES(base)) -> base
 fn canonicalize_derivations(tv: TypeVar) -> TypeVar {
     let base = match &tv.base {
         Some(base) => base,
@@ -423,7 +426,9 @@ fn canonicalize_derivations(tv: TypeVar) -> TypeVar {
             (DerivedFunc::HalfWidth, DerivedFunc::DoubleWidth)
             | (DerivedFunc::DoubleWidth, DerivedFunc::HalfWidth)
             | (DerivedFunc::HalfVector, DerivedFunc::DoubleVector)
-            | (DerivedFunc::DoubleVector, DerivedFunc::HalfVector) => {
+            | (DerivedFunc::DoubleVector, DerivedFunc::HalfVector)
+            | (DerivedFunc::SplitLanes, DerivedFunc::MergeLanes)
+            | (DerivedFunc::MergeLanes, DerivedFunc::SplitLanes) => {
                 // Cancelling bijective transformations. This doesn't hide any overflow issues
                 // since derived type sets are checked upon derivaion, and base typesets are only
                 // allowed to shrink.
@@ -193,6 +193,42 @@ impl TypeVar {
                     "can't double 256 lanes"
                 );
             }
+            DerivedFunc::SplitLanes => {
+                assert!(
+                    ts.ints.is_empty() || *ts.ints.iter().min().unwrap() > 8,
+                    "can't halve all integer types"
+                );
+                assert!(
+                    ts.floats.is_empty() || *ts.floats.iter().min().unwrap() > 32,
+                    "can't halve all float types"
+                );
+                assert!(
+                    ts.bools.is_empty() || *ts.bools.iter().min().unwrap() > 8,
+                    "can't halve all boolean types"
+                );
+                assert!(
+                    *ts.lanes.iter().max().unwrap() < MAX_LANES,
+                    "can't double 256 lanes"
+                );
+            }
+            DerivedFunc::MergeLanes => {
+                assert!(
+                    ts.ints.is_empty() || *ts.ints.iter().max().unwrap() < MAX_BITS,
+                    "can't double all integer types"
+                );
+                assert!(
+                    ts.floats.is_empty() || *ts.floats.iter().max().unwrap() < MAX_FLOAT_BITS,
+                    "can't double all float types"
+                );
+                assert!(
+                    ts.bools.is_empty() || *ts.bools.iter().max().unwrap() < MAX_BITS,
+                    "can't double all boolean types"
+                );
+                assert!(
+                    *ts.lanes.iter().min().unwrap() > 1,
+                    "can't halve a scalar type"
+                );
+            }
             DerivedFunc::LaneOf | DerivedFunc::AsBool => { /* no particular assertions */ }
         }
 
@@ -227,6 +263,12 @@ impl TypeVar {
     pub fn double_vector(&self) -> TypeVar {
         self.derived(DerivedFunc::DoubleVector)
     }
+    pub fn split_lanes(&self) -> TypeVar {
+        self.derived(DerivedFunc::SplitLanes)
+    }
+    pub fn merge_lanes(&self) -> TypeVar {
+        self.derived(DerivedFunc::MergeLanes)
+    }
 
     /// Constrain the range of types this variable can assume to a subset of those in the typeset
     /// ts.
@@ -258,19 +300,16 @@ impl TypeVar {
 
     /// Get a Rust expression that computes the type of this type variable.
     pub fn to_rust_code(&self) -> String {
+        if let Some(singleton) = self.singleton_type() {
+            return singleton.rust_name();
+        }
         match &self.base {
             Some(base) => format!(
                 "{}.{}()",
                 base.type_var.to_rust_code(),
                 base.derived_func.name()
             ),
-            None => {
-                if let Some(singleton) = self.singleton_type() {
-                    singleton.rust_name()
-                } else {
-                    self.name.clone()
-                }
-            }
+            None => self.name.clone(),
         }
     }
 }
@@ -333,6 +372,8 @@ pub(crate) enum DerivedFunc {
     DoubleWidth,
     HalfVector,
     DoubleVector,
+    SplitLanes,
+    MergeLanes,
 }
 
 impl DerivedFunc {
@@ -344,6 +385,8 @@ impl DerivedFunc {
             DerivedFunc::DoubleWidth => "double_width",
             DerivedFunc::HalfVector => "half_vector",
             DerivedFunc::DoubleVector => "double_vector",
+            DerivedFunc::SplitLanes => "split_lanes",
+            DerivedFunc::MergeLanes => "merge_lanes",
         }
     }
 
@@ -354,6 +397,8 @@ impl DerivedFunc {
             DerivedFunc::DoubleWidth => Some(DerivedFunc::HalfWidth),
             DerivedFunc::HalfVector => Some(DerivedFunc::DoubleVector),
             DerivedFunc::DoubleVector => Some(DerivedFunc::HalfVector),
+            DerivedFunc::SplitLanes => Some(DerivedFunc::MergeLanes),
+            DerivedFunc::MergeLanes => Some(DerivedFunc::SplitLanes),
             _ => None,
         }
     }
@@ -438,6 +483,8 @@ impl TypeSet {
             DerivedFunc::DoubleWidth => self.double_width(),
             DerivedFunc::HalfVector => self.half_vector(),
             DerivedFunc::DoubleVector => self.double_vector(),
+            DerivedFunc::SplitLanes => self.half_width().double_vector(),
+            DerivedFunc::MergeLanes => self.double_width().half_vector(),
         }
     }
 
@@ -577,6 +624,8 @@ impl TypeSet {
             DerivedFunc::DoubleWidth => self.half_width(),
             DerivedFunc::HalfVector => self.double_vector(),
             DerivedFunc::DoubleVector => self.half_vector(),
+            DerivedFunc::SplitLanes => self.double_width().half_vector(),
+            DerivedFunc::MergeLanes => self.half_width().double_vector(),
         }
     }
 
@@ -1000,6 +1049,26 @@ fn test_forward_images() {
         TypeSetBuilder::new().bools(32..64).build().double_width(),
         TypeSetBuilder::new().bools(64..128).build()
     );
+
+    // Split lanes.
+    assert_eq!(
+        TypeSetBuilder::new()
+            .ints(16..32)
+            .simd_lanes(4..8)
+            .build()
+            .image(DerivedFunc::SplitLanes),
+        TypeSetBuilder::new().ints(8..16).simd_lanes(8..16).build()
+    );
+
+    // Merge lanes.
+    assert_eq!(
+        TypeSetBuilder::new()
+            .ints(8..16)
+            .simd_lanes(8..16)
+            .build()
+            .image(DerivedFunc::MergeLanes),
+        TypeSetBuilder::new().ints(16..32).simd_lanes(4..8).build()
+    );
 }
 
 #[test]
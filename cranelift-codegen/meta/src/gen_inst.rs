@@ -516,6 +516,13 @@ fn gen_opcodes(all_inst: &AllInstructions, fmt: &mut Formatter) {
             "Does this instruction have other side effects besides can_* flags?",
             fmt,
         );
+        gen_bool_accessor(
+            all_inst,
+            |inst| inst.clobbers_all_regs,
+            "clobbers_all_regs",
+            "Does this instruction clobber all of the caller-saved registers, like a call?",
+            fmt,
+        );
         gen_bool_accessor(
             all_inst,
             |inst| inst.writes_cpu_flags,
@@ -0,0 +1,627 @@
+use crate::cdsl::encodings::{Encoding, EncodingBuilder};
+use crate::cdsl::instructions::{
+    Bindable, InstSpec, InstructionGroup, InstructionPredicateRegistry,
+};
+use crate::cdsl::recipes::{EncodingRecipeNumber, Recipes};
+use crate::cdsl::types::LaneType;
+
+use crate::shared::types::Bool::B1;
+use crate::shared::types::Float::{F32, F64};
+use crate::shared::types::Int::{I16, I32, I8};
+use crate::shared::Definitions as SharedDefinitions;
+
+use super::recipes::RecipeGroup;
+
+pub(crate) struct PerCpuModeEncodings<'defs> {
+    pub inst_pred_reg: InstructionPredicateRegistry,
+    pub enc: Vec<Encoding>,
+    recipes: &'defs Recipes,
+}
+
+impl<'defs> PerCpuModeEncodings<'defs> {
+    fn new(recipes: &'defs Recipes) -> Self {
+        Self {
+            inst_pred_reg: InstructionPredicateRegistry::new(),
+            enc: Vec::new(),
+            recipes,
+        }
+    }
+    fn enc(
+        &self,
+        inst: impl Into<InstSpec>,
+        recipe: EncodingRecipeNumber,
+        bits: u16,
+    ) -> EncodingBuilder {
+        EncodingBuilder::new(inst.into(), recipe, bits)
+    }
+    fn add(&mut self, encoding: EncodingBuilder) {
+        self.enc
+            .push(encoding.build(self.recipes, &mut self.inst_pred_reg));
+    }
+}
+
+// The 32-bit T32 instructions don't fit in the 16 encoding bits. The functions below compress an
+// instruction, as found in the Arm Architecture Reference Manual with all its register and
+// immediate fields cleared and written as `first_halfword << 16 | second_halfword`, into the
+// encoding bits expected by the `put_*` functions in `isa/arm32/binemit.rs`. The 16-bit
+// instructions and the ones without operand fields in their second halfword use their first
+// halfword directly.
+
+/// Data processing instructions with a shifted register operand: the first halfword.
+fn dp_bits(inst: u32) -> u16 {
+    assert_eq!(
+        inst & 0xfe00_0000,
+        0xea00_0000,
+        "{:#x} is not a dp inst",
+        inst
+    );
+    assert_eq!(inst & 0xffff, 0, "{:#x} has register fields set", inst);
+    (inst >> 16) as u16
+}
+
+/// Data processing instructions with a modified immediate operand: the first halfword, without
+/// the `i` bit of the immediate and the Rn field.
+fn modimm_bits(inst: u32) -> u16 {
+    assert_eq!(
+        inst & 0xfa00_8000,
+        0xf000_0000,
+        "{:#x} is not a modimm inst",
+        inst
+    );
+    ((inst >> 16) & 0xfbf0) as u16
+}
+
+/// Register-controlled data processing, multiplications and divisions:
+/// `inst[31:20] | inst[7:4]`. The Rn field at bits 19:16 is dropped, and the Ra field at bits
+/// 15:12 too, so the instructions which use them as opcode fields must pass 0b1111.
+fn rrr_bits(inst: u32) -> u16 {
+    assert_eq!(inst & 0x0f0f, 0, "{:#x} has register fields set", inst);
+    (((inst >> 16) & 0xfff0) | ((inst >> 4) & 0xf)) as u16
+}
+
+/// Loads and stores of a general purpose register: the first halfword of the 12-bit immediate
+/// form.
+fn ldst_bits(inst: u32) -> u16 {
+    assert_eq!(
+        inst & 0xfe80_ffff,
+        0xf880_0000,
+        "{:#x} is not a ldst inst",
+        inst
+    );
+    (inst >> 16) as u16
+}
+
+/// Loads and stores of a VFP register: the first halfword, with the `sz` bit of the second
+/// halfword in bit 0.
+fn vldst_bits(inst: u32) -> u16 {
+    assert_eq!(
+        inst & 0xff2f_feff,
+        0xed00_0a00,
+        "{:#x} is not vldr/vstr",
+        inst
+    );
+    (((inst >> 16) & 0xfff0) | ((inst >> 8) & 1)) as u16
+}
+
+/// VFP data processing instructions: `inst[23:16] | (inst[7:6] << 8) | (inst[8] << 10)`,
+/// without the D bit at 22.
+fn vfp_bits(inst: u32) -> u16 {
+    assert_eq!(
+        inst & 0xff00_0e00,
+        0xee00_0a00,
+        "{:#x} is not a vfp inst",
+        inst
+    );
+    (((inst >> 16) & 0xbf) | (((inst >> 6) & 3) << 8) | (((inst >> 8) & 1) << 10)) as u16
+}
+
+pub(crate) fn define<'defs>(
+    shared_defs: &'defs SharedDefinitions,
+    isa_insts: &InstructionGroup,
+    recipes: &'defs RecipeGroup,
+) -> PerCpuModeEncodings<'defs> {
+    // Instructions shorthands.
+    let shared = &shared_defs.instructions;
+
+    let adjust_sp_down_imm = shared.by_name("adjust_sp_down_imm");
+    let adjust_sp_up_imm = shared.by_name("adjust_sp_up_imm");
+    let band = shared.by_name("band");
+    let band_imm = shared.by_name("band_imm");
+    let band_not = shared.by_name("band_not");
+    let bconst = shared.by_name("bconst");
+    let bint = shared.by_name("bint");
+    let bitcast = shared.by_name("bitcast");
+    let bitrev = shared.by_name("bitrev");
+    let bnot = shared.by_name("bnot");
+    let bor = shared.by_name("bor");
+    let bor_imm = shared.by_name("bor_imm");
+    let bor_not = shared.by_name("bor_not");
+    let brff = shared.by_name("brff");
+    let brif = shared.by_name("brif");
+    let brnz = shared.by_name("brnz");
+    let brz = shared.by_name("brz");
+    let bxor = shared.by_name("bxor");
+    let bxor_imm = shared.by_name("bxor_imm");
+    let call = shared.by_name("call");
+    let call_indirect = shared.by_name("call_indirect");
+    let clz = shared.by_name("clz");
+    let copy = shared.by_name("copy");
+    let copy_nop = shared.by_name("copy_nop");
+    let copy_special = shared.by_name("copy_special");
+    let copy_to_ssa = shared.by_name("copy_to_ssa");
+    let ctz = shared.by_name("ctz");
+    let debugtrap = shared.by_name("debugtrap");
+    let f32const = shared.by_name("f32const");
+    let f64const = shared.by_name("f64const");
+    let fabs = shared.by_name("fabs");
+    let fadd = shared.by_name("fadd");
+    let fcmp = shared.by_name("fcmp");
+    let fcopysign = shared.by_name("fcopysign");
+    let fcvt_from_sint = shared.by_name("fcvt_from_sint");
+    let fcvt_from_uint = shared.by_name("fcvt_from_uint");
+    let fcvt_to_sint = shared.by_name("fcvt_to_sint");
+    let fcvt_to_sint_sat = shared.by_name("fcvt_to_sint_sat");
+    let fcvt_to_uint = shared.by_name("fcvt_to_uint");
+    let fcvt_to_uint_sat = shared.by_name("fcvt_to_uint_sat");
+    let fdemote = shared.by_name("fdemote");
+    let fdiv = shared.by_name("fdiv");
+    let ffcmp = shared.by_name("ffcmp");
+    let fill = shared.by_name("fill");
+    let fill_nop = shared.by_name("fill_nop");
+    let fma = shared.by_name("fma");
+    let fmax = shared.by_name("fmax");
+    let fmin = shared.by_name("fmin");
+    let fmul = shared.by_name("fmul");
+    let fneg = shared.by_name("fneg");
+    let fpromote = shared.by_name("fpromote");
+    let fsub = shared.by_name("fsub");
+    let func_addr = shared.by_name("func_addr");
+    let iadd = shared.by_name("iadd");
+    let iadd_ifcin = shared.by_name("iadd_ifcin");
+    let iadd_ifcout = shared.by_name("iadd_ifcout");
+    let iadd_imm = shared.by_name("iadd_imm");
+    let icmp = shared.by_name("icmp");
+    let icmp_imm = shared.by_name("icmp_imm");
+    let iconst = shared.by_name("iconst");
+    let ifcmp = shared.by_name("ifcmp");
+    let ifcmp_imm = shared.by_name("ifcmp_imm");
+    let imul = shared.by_name("imul");
+    let indirect_jump_table_br = shared.by_name("indirect_jump_table_br");
+    let ineg = shared.by_name("ineg");
+    let ireduce = shared.by_name("ireduce");
+    let irsub_imm = shared.by_name("irsub_imm");
+    let ishl = shared.by_name("ishl");
+    let ishl_imm = shared.by_name("ishl_imm");
+    let istore16 = shared.by_name("istore16");
+    let istore8 = shared.by_name("istore8");
+    let isub = shared.by_name("isub");
+    let isub_ifbin = shared.by_name("isub_ifbin");
+    let isub_ifbout = shared.by_name("isub_ifbout");
+    let jump = shared.by_name("jump");
+    let jump_table_base = shared.by_name("jump_table_base");
+    let jump_table_entry = shared.by_name("jump_table_entry");
+    let load = shared.by_name("load");
+    let regfill = shared.by_name("regfill");
+    let regmove = shared.by_name("regmove");
+    let regspill = shared.by_name("regspill");
+    let resumable_trap = shared.by_name("resumable_trap");
+    let return_ = shared.by_name("return");
+    let rotl = shared.by_name("rotl");
+    let rotl_imm = shared.by_name("rotl_imm");
+    let rotr = shared.by_name("rotr");
+    let rotr_imm = shared.by_name("rotr_imm");
+    let sdiv = shared.by_name("sdiv");
+    let selectif = shared.by_name("selectif");
+    let sextend = shared.by_name("sextend");
+    let sload16 = shared.by_name("sload16");
+    let sload8 = shared.by_name("sload8");
+    let smulhi = shared.by_name("smulhi");
+    let spill = shared.by_name("spill");
+    let sqrt = shared.by_name("sqrt");
+    let srem = shared.by_name("srem");
+    let sshr = shared.by_name("sshr");
+    let sshr_imm = shared.by_name("sshr_imm");
+    let stack_addr = shared.by_name("stack_addr");
+    let store = shared.by_name("store");
+    let symbol_value = shared.by_name("symbol_value");
+    let trap = shared.by_name("trap");
+    let trapff = shared.by_name("trapff");
+    let trapif = shared.by_name("trapif");
+    let trueff = shared.by_name("trueff");
+    let trueif = shared.by_name("trueif");
+    let udiv = shared.by_name("udiv");
+    let uextend = shared.by_name("uextend");
+    let uload16 = shared.by_name("uload16");
+    let uload8 = shared.by_name("uload8");
+    let umulhi = shared.by_name("umulhi");
+    let urem = shared.by_name("urem");
+    let ushr = shared.by_name("ushr");
+    let ushr_imm = shared.by_name("ushr_imm");
+
+    // ARM32 specific instructions.
+    let arm32_pop = isa_insts.by_name("arm32_pop");
+    let arm32_pop_pair = isa_insts.by_name("arm32_pop_pair");
+    let arm32_push = isa_insts.by_name("arm32_push");
+    let arm32_push_pair = isa_insts.by_name("arm32_push_pair");
+
+    // Recipes shorthands, prefixed with r_.
+    let r_adjustsp = recipes.by_name("adjustsp");
+    let r_alu = recipes.by_name("alu");
+    let r_alu_fin = recipes.by_name("alu_fin");
+    let r_alu_fout = recipes.by_name("alu_fout");
+    let r_alu_imm = recipes.by_name("alu_imm");
+    let r_alu_rm = recipes.by_name("alu_rm");
+    let r_bconst = recipes.by_name("bconst");
+    let r_bool1 = recipes.by_name("bool1");
+    let r_bkpt = recipes.by_name("bkpt");
+    let r_brff = recipes.by_name("brff");
+    let r_brff_far = recipes.by_name("brff_far");
+    let r_brff_n = recipes.by_name("brff_n");
+    let r_brif = recipes.by_name("brif");
+    let r_brif_far = recipes.by_name("brif_far");
+    let r_brif_n = recipes.by_name("brif_n");
+    let r_brz = recipes.by_name("brz");
+    let r_brz_far = recipes.by_name("brz_far");
+    let r_brz_n = recipes.by_name("brz_n");
+    let r_call = recipes.by_name("call");
+    let r_call_r = recipes.by_name("call_r");
+    let r_copysp = recipes.by_name("copysp");
+    let r_copytossa = recipes.by_name("copytossa");
+    let r_ctz = recipes.by_name("ctz");
+    let r_dp = recipes.by_name("dp");
+    let r_dp_r = recipes.by_name("dp_r");
+    let r_ext = recipes.by_name("ext");
+    let r_fill = recipes.by_name("fill");
+    let r_fillnull = recipes.by_name("fillnull");
+    let r_fnaddr = recipes.by_name("fnaddr");
+    let r_gvaddr = recipes.by_name("gvaddr");
+    let r_icscc = recipes.by_name("icscc");
+    let r_icscc_imm = recipes.by_name("icscc_imm");
+    let r_indirect_jmp = recipes.by_name("indirect_jmp");
+    let r_jmp = recipes.by_name("jmp");
+    let r_jmp_n = recipes.by_name("jmp_n");
+    let r_jt_base = recipes.by_name("jt_base");
+    let r_jt_entry = recipes.by_name("jt_entry");
+    let r_ld = recipes.by_name("ld");
+    let r_mov = recipes.by_name("mov");
+    let r_movimm = recipes.by_name("movimm");
+    let r_mulhi = recipes.by_name("mulhi");
+    let r_neg = recipes.by_name("neg");
+    let r_null = recipes.by_name("null");
+    let r_pop = recipes.by_name("pop");
+    let r_pop_pair = recipes.by_name("pop_pair");
+    let r_push = recipes.by_name("push");
+    let r_push_pair = recipes.by_name("push_pair");
+    let r_rcmp = recipes.by_name("rcmp");
+    let r_rcmp_imm = recipes.by_name("rcmp_imm");
+    let r_regfill = recipes.by_name("regfill");
+    let r_regspill = recipes.by_name("regspill");
+    let r_rem = recipes.by_name("rem");
+    let r_ret = recipes.by_name("ret");
+    let r_rmov = recipes.by_name("rmov");
+    let r_rotl = recipes.by_name("rotl");
+    let r_rotl_imm = recipes.by_name("rotl_imm");
+    let r_sdiv = recipes.by_name("sdiv");
+    let r_sel = recipes.by_name("sel");
+    let r_setf = recipes.by_name("setf");
+    let r_seti = recipes.by_name("seti");
+    let r_shift = recipes.by_name("shift");
+    let r_shift_imm = recipes.by_name("shift_imm");
+    let r_spaddr = recipes.by_name("spaddr");
+    let r_spill = recipes.by_name("spill");
+    let r_st = recipes.by_name("st");
+    let r_stacknull = recipes.by_name("stacknull");
+    let r_trap = recipes.by_name("trap");
+    let r_trapff = recipes.by_name("trapff");
+    let r_trapif = recipes.by_name("trapif");
+    let r_udiv = recipes.by_name("udiv");
+    let r_vconst_d = recipes.by_name("vconst_d");
+    let r_vconst_s = recipes.by_name("vconst_s");
+    let r_vcvt_ds = recipes.by_name("vcvt_ds");
+    let r_vcvt_sd = recipes.by_name("vcvt_sd");
+    let r_vmov_rs = recipes.by_name("vmov_rs");
+    let r_vmov_sr = recipes.by_name("vmov_sr");
+    let r_vpop = recipes.by_name("vpop");
+    let r_vpush = recipes.by_name("vpush");
+
+    // Definitions.
+    let mut e = PerCpuModeEncodings::new(&recipes.recipes);
+
+    // Integer arithmetic and bitwise operations, with the shifted register forms using a shift
+    // amount of zero, and the modified immediate forms.
+    for &(inst, inst_imm, reg, imm) in &[
+        (iadd, Some(iadd_imm), 0xeb00_0000, 0xf100_0000), // add.w
+        (isub, None, 0xeba0_0000, 0xf1a0_0000),           // sub.w
+        (band, Some(band_imm), 0xea00_0000, 0xf000_0000), // and.w
+        (bor, Some(bor_imm), 0xea40_0000, 0xf040_0000),   // orr.w
+        (bxor, Some(bxor_imm), 0xea80_0000, 0xf080_0000), // eor.w
+        (band_not, None, 0xea20_0000, 0xf020_0000),       // bic.w
+        (bor_not, None, 0xea60_0000, 0xf060_0000),        // orn
+    ] {
+        e.add(e.enc(inst.bind(I32), r_alu, dp_bits(reg)));
+        if let Some(inst_imm) = inst_imm {
+            e.add(e.enc(inst_imm.bind(I32), r_alu_imm, modimm_bits(imm)));
+        }
+    }
+    e.add(e.enc(irsub_imm.bind(I32), r_alu_imm, modimm_bits(0xf1c0_0000))); // rsb.w
+
+    // Booleans are 0 or 1 in a register, which the bitwise operations preserve.
+    for &(inst, reg) in &[
+        (band, 0xea00_0000), // and.w
+        (bor, 0xea40_0000),  // orr.w
+        (bxor, 0xea80_0000), // eor.w
+    ] {
+        e.add(e.enc(inst.bind(B1), r_alu, dp_bits(reg)));
+    }
+
+    e.add(e.enc(ineg.bind(I32), r_neg, modimm_bits(0xf1c0_0000))); // rsb.w
+    e.add(e.enc(bnot.bind(I32), r_alu_rm, dp_bits(0xea6f_0000))); // mvn.w
+    e.add(e.enc(bnot.bind(B1), r_bool1, modimm_bits(0xf080_0000))); // eor.w
+
+    // Shifts by a register, and by an immediate using the shift types of `mov.w`. Only the low
+    // bits of the amount register are used, so the amount can also be one of the narrow types
+    // used by the i8 and i16 shifts widened to 32 bits.
+    for &(inst, inst_imm, reg, shift_type) in &[
+        (ishl, ishl_imm, 0xfa00_f000, 0b00), // lsl.w
+        (ushr, ushr_imm, 0xfa20_f000, 0b01), // lsr.w
+        (sshr, sshr_imm, 0xfa40_f000, 0b10), // asr.w
+    ] {
+        for &amt_ty in &[I8, I16, I32] {
+            e.add(e.enc(inst.bind(I32).bind(amt_ty), r_shift, rrr_bits(reg)));
+        }
+        e.add(e.enc(inst_imm.bind(I32), r_shift_imm, shift_type));
+    }
+    // `ror` already uses the rotation amount modulo 32.
+    for &amt_ty in &[I8, I16, I32] {
+        e.add(e.enc(rotr.bind(I32).bind(amt_ty), r_dp, rrr_bits(0xfa60_f000)));
+        e.add(e.enc(rotl.bind(I32).bind(amt_ty), r_rotl, rrr_bits(0xfa60_f000)));
+    }
+    e.add(e.enc(rotr_imm.bind(I32), r_shift_imm, 0b11));
+    e.add(e.enc(rotl_imm.bind(I32), r_rotl_imm, 0b11));
+
+    // Multiplication and division, with the checks required by the trapping clif semantics.
+    e.add(e.enc(imul.bind(I32), r_dp, rrr_bits(0xfb00_f000))); // mul
+    e.add(e.enc(umulhi.bind(I32), r_mulhi, rrr_bits(0xfba0_0000))); // umull
+    e.add(e.enc(smulhi.bind(I32), r_mulhi, rrr_bits(0xfb80_0000))); // smull
+    e.add(e.enc(udiv.bind(I32), r_udiv, rrr_bits(0xfbb0_f0f0)));
+    e.add(e.enc(sdiv.bind(I32), r_sdiv, rrr_bits(0xfb90_f0f0)));
+    e.add(e.enc(urem.bind(I32), r_rem, rrr_bits(0xfbb0_f0f0)));
+    e.add(e.enc(srem.bind(I32), r_rem, rrr_bits(0xfb90_f0f0)));
+
+    // Bit counting.
+    e.add(e.enc(clz.bind(I32), r_dp_r, rrr_bits(0xfab0_f080)));
+    e.add(e.enc(bitrev.bind(I32), r_dp_r, rrr_bits(0xfa90_f0a0))); // rbit
+    e.add(e.enc(ctz.bind(I32), r_ctz, rrr_bits(0xfa90_f0a0)));
+
+    // Integer constants.
+    for &ty in &[I8, I16, I32] {
+        e.add(e.enc(iconst.bind(ty), r_movimm, 0));
+    }
+    e.add(e.enc(bconst.bind(B1), r_bconst, 0));
+
+    // Extensions and reductions.
+    e.add(e.enc(uextend.bind(I32).bind(I8), r_ext, rrr_bits(0xfa5f_f080))); // uxtb.w
+    e.add(e.enc(uextend.bind(I32).bind(I16), r_ext, rrr_bits(0xfa1f_f080))); // uxth.w
+    e.add(e.enc(sextend.bind(I32).bind(I8), r_ext, rrr_bits(0xfa4f_f080))); // sxtb.w
+    e.add(e.enc(sextend.bind(I32).bind(I16), r_ext, rrr_bits(0xfa0f_f080))); // sxth.w
+
+    e.add(e.enc(ireduce.bind(I8).bind(I16), r_null, 0));
+    e.add(e.enc(ireduce.bind(I8).bind(I32), r_null, 0));
+    e.add(e.enc(ireduce.bind(I16).bind(I32), r_null, 0));
+
+    e.add(e.enc(bint.bind(I32).bind(B1), r_bool1, modimm_bits(0xf000_0000))); // and.w
+
+    // Comparisons. `cmp` is an alias of `subs` with r15 as destination.
+    e.add(e.enc(icmp.bind(I32), r_icscc, dp_bits(0xebb0_0000)));
+    e.add(e.enc(icmp_imm.bind(I32), r_icscc_imm, modimm_bits(0xf1b0_0000)));
+    e.add(e.enc(ifcmp.bind(I32), r_rcmp, dp_bits(0xebb0_0000)));
+    e.add(e.enc(ifcmp_imm.bind(I32), r_rcmp_imm, modimm_bits(0xf1b0_0000)));
+    e.add(e.enc(trueif, r_seti, 0));
+    e.add(e.enc(trapif, r_trapif, 0));
+    e.add(e.enc(selectif.bind(I32), r_sel, 0));
+
+    // Arithmetic with carry, used for narrowing 64-bit operations.
+    e.add(e.enc(iadd_ifcout.bind(I32), r_alu_fout, dp_bits(0xeb10_0000))); // adds.w
+    e.add(e.enc(isub_ifbout.bind(I32), r_alu_fout, dp_bits(0xebb0_0000))); // subs.w
+    e.add(e.enc(iadd_ifcin.bind(I32), r_alu_fin, dp_bits(0xeb40_0000))); // adc.w
+    e.add(e.enc(isub_ifbin.bind(I32), r_alu_fin, dp_bits(0xeb60_0000))); // sbc.w
+
+    // Loads and stores.
+    for &(inst, recipe, opcode) in &[
+        (load, r_ld, 0xf8d0_0000),     // ldr.w
+        (uload8, r_ld, 0xf890_0000),   // ldrb.w
+        (sload8, r_ld, 0xf990_0000),   // ldrsb.w
+        (uload16, r_ld, 0xf8b0_0000),  // ldrh.w
+        (sload16, r_ld, 0xf9b0_0000),  // ldrsh.w
+        (store, r_st, 0xf8c0_0000),    // str.w
+        (istore8, r_st, 0xf880_0000),  // strb.w
+        (istore16, r_st, 0xf8a0_0000), // strh.w
+    ] {
+        e.add(e.enc(inst.bind(I32).bind(I32), recipe, ldst_bits(opcode)));
+    }
+
+    // Spills and fills. Spill slots are at least 4 bytes, so the small types use 32-bit accesses.
+    for &ty in &[LaneType::from(B1), I8.into(), I16.into(), I32.into()] {
+        e.add(e.enc(spill.bind(ty), r_spill, ldst_bits(0xf8c0_0000)));
+        e.add(e.enc(regspill.bind(ty), r_regspill, ldst_bits(0xf8c0_0000)));
+        e.add(e.enc(fill.bind(ty), r_fill, ldst_bits(0xf8d0_0000)));
+        e.add(e.enc(regfill.bind(ty), r_regfill, ldst_bits(0xf8d0_0000)));
+        e.add(e.enc(fill_nop.bind(ty), r_fillnull, 0));
+    }
+
+    // Register copies, using the 16-bit `mov` which doesn't set the flags.
+    for &ty in &[LaneType::from(B1), I8.into(), I16.into(), I32.into()] {
+        e.add(e.enc(copy.bind(ty), r_mov, 0));
+        e.add(e.enc(regmove.bind(ty), r_rmov, 0));
+        e.add(e.enc(copy_to_ssa.bind(ty), r_copytossa, 0));
+    }
+
+    // Stack-slot-to-the-same-stack-slot copy, which is guaranteed to turn into a no-op.
+    for &ty in &[
+        LaneType::from(B1),
+        I8.into(),
+        I16.into(),
+        I32.into(),
+        F32.into(),
+        F64.into(),
+    ] {
+        e.add(e.enc(copy_nop.bind(ty), r_stacknull, 0));
+    }
+
+    // Stack manipulation.
+    e.add(e.enc(stack_addr.bind(I32), r_spaddr, modimm_bits(0xf100_0000))); // add.w
+    e.add(e.enc(adjust_sp_down_imm, r_adjustsp, modimm_bits(0xf1a0_0000))); // sub.w
+    e.add(e.enc(adjust_sp_up_imm, r_adjustsp, modimm_bits(0xf100_0000))); // add.w
+    e.add(e.enc(copy_special, r_copysp, 0));
+
+    e.add(e.enc(arm32_push.bind(I32), r_push, 0xf84d)); // str.w rt, [sp, #-8]!
+    e.add(e.enc(arm32_pop.bind(I32), r_pop, 0xf85d)); // ldr.w rt, [sp], #8
+    e.add(e.enc(arm32_push_pair.bind(I32), r_push_pair, 0xe92d)); // stmdb sp!
+    e.add(e.enc(arm32_pop_pair.bind(I32), r_pop_pair, 0xe8bd)); // ldmia sp!
+    e.add(e.enc(arm32_push.bind(F64), r_vpush, 0xed2d)); // vstmdb sp!
+    e.add(e.enc(arm32_pop.bind(F64), r_vpop, 0xecbd)); // vldmia sp!
+
+    // Addresses of functions and global values.
+    e.add(e.enc(func_addr.bind(I32), r_fnaddr, 0));
+    e.add(e.enc(symbol_value.bind(I32), r_gvaddr, 0));
+
+    // Control flow. The short branches are listed first, so they are picked before the branch
+    // relaxation pass extends the ones that don't reach their destination.
+    e.add(e.enc(jump, r_jmp_n, 0));
+    e.add(e.enc(jump, r_jmp, 0));
+    for &recipe in &[r_brif_n, r_brif, r_brif_far] {
+        e.add(e.enc(brif, recipe, 0));
+    }
+    for &recipe in &[r_brff_n, r_brff, r_brff_far] {
+        e.add(e.enc(brff, recipe, 0));
+    }
+    // The encoding bits hold the `eq` or `ne` condition code.
+    for &ty in &[LaneType::from(B1), I32.into()] {
+        for &recipe in &[r_brz_n, r_brz, r_brz_far] {
+            e.add(e.enc(brz.bind(ty), recipe, 0b0000));
+            e.add(e.enc(brnz.bind(ty), recipe, 0b0001));
+        }
+    }
+
+    e.add(e.enc(jump_table_base.bind(I32), r_jt_base, 0));
+    e.add(e.enc(
+        jump_table_entry.bind(I32),
+        r_jt_entry,
+        ldst_bits(0xf8d0_0000),
+    )); // ldr.w
+    e.add(e.enc(indirect_jump_table_br.bind(I32), r_indirect_jmp, 0));
+
+    e.add(e.enc(call, r_call, 0)); // bl
+    e.add(e.enc(call_indirect.bind(I32), r_call_r, 0x4780)); // blx
+    e.add(e.enc(return_, r_ret, 0x4700)); // bx
+
+    e.add(e.enc(trap, r_trap, 0));
+    e.add(e.enc(resumable_trap, r_trap, 0));
+    e.add(e.enc(debugtrap, r_bkpt, 0));
+
+    // Floating point instructions, using the recipes for the `S` or `D` register class. The
+    // single precision instructions are listed, the double precision variants only differ in the
+    // `sz` bit.
+    for &(ty, sz, suffix) in &[(F32, 0, "s"), (F64, 1, "d")] {
+        let r = |name: &str| recipes.by_name(&format!("{}_{}", name, suffix));
+        let sz = sz << 8;
+
+        for &(inst, opcode) in &[
+            (fadd, 0xee30_0a00), // vadd
+            (fsub, 0xee30_0a40), // vsub
+            (fmul, 0xee20_0a00), // vmul
+            (fdiv, 0xee80_0a00), // vdiv
+        ] {
+            e.add(e.enc(inst.bind(ty), r("vrrr"), vfp_bits(opcode | sz)));
+        }
+        for &(inst, opcode) in &[
+            (sqrt, 0xeeb1_0ac0), // vsqrt
+            (fabs, 0xeeb0_0ac0), // vabs
+            (fneg, 0xeeb1_0a40), // vneg
+        ] {
+            e.add(e.enc(inst.bind(ty), r("vrr"), vfp_bits(opcode | sz)));
+        }
+        e.add(e.enc(fma.bind(ty), r("vfma"), vfp_bits(0xeea0_0a00 | sz)));
+
+        // Loads, stores, spills and fills.
+        let vldr = vldst_bits(0xed10_0a00 | sz);
+        let vstr = vldst_bits(0xed00_0a00 | sz);
+        e.add(e.enc(load.bind(ty).bind(I32), r("vld"), vldr));
+        e.add(e.enc(store.bind(ty).bind(I32), r("vst"), vstr));
+        e.add(e.enc(spill.bind(ty), r("vspill"), vstr));
+        e.add(e.enc(regspill.bind(ty), r("vregspill"), vstr));
+        e.add(e.enc(fill.bind(ty), r("vfill"), vldr));
+        e.add(e.enc(regfill.bind(ty), r("vregfill"), vldr));
+        e.add(e.enc(fill_nop.bind(ty), r("vfillnull"), 0));
+
+        // Register copies.
+        let vmov = vfp_bits(0xeeb0_0a40 | sz);
+        e.add(e.enc(copy.bind(ty), r("vrr"), vmov));
+        e.add(e.enc(regmove.bind(ty), r("vmov"), vmov));
+        e.add(e.enc(copy_to_ssa.bind(ty), r("vcopytossa"), vmov));
+
+        // Sign manipulations and NaN-propagating minimum and maximum, which expand into
+        // sequences of moves.
+        e.add(e.enc(fcopysign.bind(ty), r("vcopysign"), vmov));
+        e.add(e.enc(fmin.bind(ty), r("vminmax"), vmov));
+        e.add(e.enc(fmax.bind(ty), r("vminmax"), vmov));
+
+        // Comparisons.
+        let vcmp = vfp_bits(0xeeb4_0a40 | sz);
+        e.add(e.enc(fcmp.bind(ty), r("vcscc"), vcmp));
+        e.add(e.enc(fcmp.bind(ty), r("vcscc2"), vcmp));
+        e.add(e.enc(ffcmp.bind(ty), r("vcmp"), vcmp));
+
+        // Conversions between 32-bit integers and floating point values, through a single
+        // precision register. The conversions to integers round towards zero, saturate, and
+        // convert NaN to 0.
+        let from_sint = vfp_bits(0xeeb8_0ac0 | sz); // vcvt.f*.s32
+        let from_uint = vfp_bits(0xeeb8_0a40 | sz); // vcvt.f*.u32
+        let to_sint = vfp_bits(0xeebd_0ac0 | sz); // vcvt.s32.f*
+        let to_uint = vfp_bits(0xeebc_0ac0 | sz); // vcvt.u32.f*
+        e.add(e.enc(
+            fcvt_from_sint.bind(ty).bind(I32),
+            r("vcvt_from_int"),
+            from_sint,
+        ));
+        e.add(e.enc(
+            fcvt_from_uint.bind(ty).bind(I32),
+            r("vcvt_from_int"),
+            from_uint,
+        ));
+        e.add(e.enc(
+            fcvt_to_sint_sat.bind(I32).bind(ty),
+            r("vcvt_to_int"),
+            to_sint,
+        ));
+        e.add(e.enc(
+            fcvt_to_uint_sat.bind(I32).bind(ty),
+            r("vcvt_to_int"),
+            to_uint,
+        ));
+        e.add(e.enc(fcvt_to_sint.bind(I32).bind(ty), r("vcvt_trap"), to_sint));
+        e.add(e.enc(fcvt_to_uint.bind(I32).bind(ty), r("vcvt_trap"), to_uint));
+    }
+    e.add(e.enc(
+        fpromote.bind(F64).bind(F32),
+        r_vcvt_ds,
+        vfp_bits(0xeeb7_0ac0),
+    )); // vcvt.f64.f32
+    e.add(e.enc(
+        fdemote.bind(F32).bind(F64),
+        r_vcvt_sd,
+        vfp_bits(0xeeb7_0bc0),
+    )); // vcvt.f32.f64
+    e.add(e.enc(trueff, r_setf, 0));
+    e.add(e.enc(trapff, r_trapff, 0));
+
+    // Floating point constants.
+    e.add(e.enc(f32const, r_vconst_s, 0));
+    e.add(e.enc(f64const, r_vconst_d, 0));
+
+    // Moves between register banks.
+    e.add(e.enc(bitcast.bind(I32).bind(F32), r_vmov_rs, 0));
+    e.add(e.enc(bitcast.bind(F32).bind(I32), r_vmov_sr, 0));
+
+    e
+}
@@ -0,0 +1,95 @@
+#![allow(non_snake_case)]
+
+use crate::cdsl::instructions::{
+    AllInstructions, InstructionBuilder as Inst, InstructionGroup, InstructionGroupBuilder,
+};
+use crate::cdsl::operands::Operand;
+use crate::cdsl::typevar::{TypeSetBuilder, TypeVar};
+
+use crate::shared::formats::Formats;
+
+pub(crate) fn define(
+    mut all_instructions: &mut AllInstructions,
+    formats: &Formats,
+) -> InstructionGroup {
+    let mut ig = InstructionGroupBuilder::new(&mut all_instructions);
+
+    let Reg = &TypeVar::new(
+        "Reg",
+        "A 32-bit integer or a double precision floating point register value",
+        TypeSetBuilder::new().ints(32..32).floats(64..64).build(),
+    );
+    let x = &Operand::new("x", Reg);
+    let y = &Operand::new("y", Reg);
+
+    ig.push(
+        Inst::new(
+            "arm32_push",
+            r#"
+    Pushes a value onto the stack.
+
+    Decrements the stack pointer by 8 and stores the specified value at the
+    new top of the stack. The stack pointer must stay 8-byte aligned at
+    public interfaces, so an integer leaves the upper half of the slot
+    unused.
+    "#,
+            &formats.unary,
+        )
+        .operands_in(vec![x])
+        .other_side_effects(true)
+        .can_store(true),
+    );
+
+    ig.push(
+        Inst::new(
+            "arm32_pop",
+            r#"
+    Pops a value from the stack.
+
+    Loads a value from the top of the stack and then increments the stack
+    pointer by 8.
+    "#,
+            &formats.nullary,
+        )
+        .operands_out(vec![x])
+        .other_side_effects(true)
+        .can_load(true),
+    );
+
+    ig.push(
+        Inst::new(
+            "arm32_push_pair",
+            r#"
+    Pushes a pair of values onto the stack.
+
+    Decrements the stack pointer by 8 and stores `x` at the new top of the
+    stack and `y` right above it. This is the `push {x, y}` instruction,
+    which requires the register of `x` to be numbered lower than the
+    register of `y`.
+    "#,
+            &formats.binary,
+        )
+        .operands_in(vec![x, y])
+        .other_side_effects(true)
+        .can_store(true),
+    );
+
+    ig.push(
+        Inst::new(
+            "arm32_pop_pair",
+            r#"
+    Pops a pair of values from the stack.
+
+    Loads `x` from the top of the stack and `y` from right above it, then
+    increments the stack pointer by 8. This is the `pop {x, y}`
+    instruction.
+    "#,
+            &formats.nullary,
+        )
+        .operands_out(vec![x, y])
+        .other_side_effects(true)
+        .can_load(true),
+    );
+
+    ig.build()
+}
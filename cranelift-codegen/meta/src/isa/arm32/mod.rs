@@ -1,12 +1,17 @@
 use crate::cdsl::cpu_modes::CpuMode;
-use crate::cdsl::instructions::{InstructionGroupBuilder, InstructionPredicateMap};
 use crate::cdsl::isa::TargetIsa;
-use crate::cdsl::recipes::Recipes;
 use crate::cdsl::regs::{IsaRegs, IsaRegsBuilder, RegBankBuilder, RegClassBuilder};
 use crate::cdsl::settings::{SettingGroup, SettingGroupBuilder};
 
+use crate::shared::types::Bool::B1;
+use crate::shared::types::Float::{F32, F64};
+use crate::shared::types::Int::{I16, I32, I8};
 use crate::shared::Definitions as SharedDefinitions;
 
+mod encodings;
+mod instructions;
+mod recipes;
+
 fn define_settings(_shared: &SettingGroup) -> SettingGroup {
     let setting = SettingGroupBuilder::new("arm32");
     setting.build()
@@ -35,7 +40,7 @@ fn define_regs() -> IsaRegs {
     regs.add_class(builder);
 
     let builder = RegClassBuilder::new_toplevel("D", float_regs).width(2);
-    regs.add_class(builder);
+    let d = regs.add_class(builder);
 
     let builder = RegClassBuilder::new_toplevel("Q", float_regs).width(4);
     regs.add_class(builder);
@@ -46,6 +51,10 @@ fn define_regs() -> IsaRegs {
     let builder = RegClassBuilder::new_toplevel("FLAG", flag_reg);
     regs.add_class(builder);
 
+    // The double precision registers whose halves are single precision registers.
+    let builder = RegClassBuilder::subclass_of("D16", d, 0, 16);
+    regs.add_class(builder);
+
     regs.build()
 }
 
@@ -53,24 +62,33 @@ pub(crate) fn define(shared_defs: &mut SharedDefinitions) -> TargetIsa {
     let settings = define_settings(&shared_defs.settings);
     let regs = define_regs();
 
-    let inst_group = InstructionGroupBuilder::new(&mut shared_defs.all_instructions).build();
+    let inst_group = instructions::define(&mut shared_defs.all_instructions, &shared_defs.formats);
 
-    // CPU modes for 32-bit ARM and Thumb2.
-    let mut a32 = CpuMode::new("A32");
+    // Only Thumb2 has encodings; `isa::lookup` rejects triples that would select A32.
     let mut t32 = CpuMode::new("T32");
 
-    // TODO refine these.
+    let expand_flags = shared_defs.transform_groups.by_name("expand_flags");
     let narrow_flags = shared_defs.transform_groups.by_name("narrow_flags");
-    a32.legalize_default(narrow_flags);
+    let widen = shared_defs.transform_groups.by_name("widen");
+
+    t32.legalize_monomorphic(expand_flags);
     t32.legalize_default(narrow_flags);
+    t32.legalize_type(B1, expand_flags);
+    t32.legalize_type(I8, widen);
+    t32.legalize_type(I16, widen);
+    t32.legalize_type(I32, expand_flags);
+    t32.legalize_type(F32, expand_flags);
+    t32.legalize_type(F64, expand_flags);
+
+    let recipes = recipes::define(shared_defs, &regs);
 
-    let cpu_modes = vec![a32, t32];
+    let encodings = encodings::define(shared_defs, &inst_group, &recipes);
+    t32.set_encodings(encodings.enc);
+    let encodings_predicates = encodings.inst_pred_reg.extract();
 
-    // TODO implement arm32 recipes.
-    let recipes = Recipes::new();
+    let recipes = recipes.collect();
 
-    // TODO implement arm32 encodings and predicates.
-    let encodings_predicates = InstructionPredicateMap::new();
+    let cpu_modes = vec![t32];
 
     TargetIsa::new(
         "arm32",
@@ -0,0 +1,1341 @@
+use std::collections::HashMap;
+
+use crate::cdsl::ast::Literal;
+use crate::cdsl::formats::InstructionFormat;
+use crate::cdsl::instructions::InstructionPredicate;
+use crate::cdsl::recipes::{
+    EncodingRecipeBuilder, EncodingRecipeNumber, OperandConstraint, Recipes, Register, Stack,
+};
+use crate::cdsl::regs::IsaRegs;
+use crate::shared::Definitions as SharedDefinitions;
+
+/// An helper to create recipes and use them when defining the ARM32 encodings.
+pub(crate) struct RecipeGroup {
+    /// The actual list of recipes explicitly created in this file.
+    pub recipes: Recipes,
+
+    /// Provides fast lookup from a name to an encoding recipe.
+    name_to_recipe: HashMap<String, EncodingRecipeNumber>,
+}
+
+impl RecipeGroup {
+    fn new() -> Self {
+        Self {
+            recipes: Recipes::new(),
+            name_to_recipe: HashMap::new(),
+        }
+    }
+
+    fn push(&mut self, builder: EncodingRecipeBuilder) {
+        assert!(
+            self.name_to_recipe.get(&builder.name).is_none(),
+            format!("arm32 recipe '{}' created twice", builder.name)
+        );
+        let name = builder.name.clone();
+        let number = self.recipes.push(builder.build());
+        self.name_to_recipe.insert(name, number);
+    }
+
+    pub fn by_name(&self, name: &str) -> EncodingRecipeNumber {
+        *self
+            .name_to_recipe
+            .get(name)
+            .unwrap_or_else(|| panic!("unknown arm32 recipe name {}", name))
+    }
+
+    pub fn collect(self) -> Recipes {
+        self.recipes
+    }
+}
+
+/// Returns a predicate that checks that the `cond` field of `format` is one of the float
+/// condition codes that map onto a single ARM condition code after a `vcmp`.
+///
+/// The remaining two, `one` and `ueq`, need to test two conditions.
+fn single_floatcc_predicate(
+    shared_defs: &SharedDefinitions,
+    format: &InstructionFormat,
+) -> InstructionPredicate {
+    let floatcc = &shared_defs.imm.floatcc;
+    [
+        "ord", "uno", "eq", "ne", "lt", "le", "gt", "ge", "ult", "ule", "ugt", "uge",
+    ]
+    .iter()
+    .map(|name| Literal::enumerator_for(floatcc, name))
+    .fold(InstructionPredicate::new(), |pred, literal| {
+        pred.or(InstructionPredicate::new_is_field_equal(
+            format,
+            "cond",
+            literal.to_rust_code(),
+        ))
+    })
+}
+
+/// Returns a predicate that checks that the `cond` field of `format` is `one` or `ueq`.
+fn double_floatcc_predicate(
+    shared_defs: &SharedDefinitions,
+    format: &InstructionFormat,
+) -> InstructionPredicate {
+    let floatcc = &shared_defs.imm.floatcc;
+    ["one", "ueq"]
+        .iter()
+        .map(|name| Literal::enumerator_for(floatcc, name))
+        .fold(InstructionPredicate::new(), |pred, literal| {
+            pred.or(InstructionPredicate::new_is_field_equal(
+                format,
+                "cond",
+                literal.to_rust_code(),
+            ))
+        })
+}
+
+// Only the T32 (Thumb-2) instruction set has recipes so far. Most T32 instructions are made of
+// two 16-bit halfwords, and the encoding bits of the recipes below hold a compressed form of the
+// instruction with all the register and immediate fields cleared. The compression depends on the
+// instruction class, and each `put_*` function in `isa/arm32/binemit.rs` documents the one it
+// expects. The `*_bits` functions in `encodings.rs` compute them from the full instructions found
+// in the Arm Architecture Reference Manual, written as `first_halfword << 16 | second_halfword`.
+//
+// Register r12 (the intra-procedure-call scratch register IP) is not allocatable, so recipes that
+// need a temporary general purpose register use it freely.
+//
+// Floating point recipes come in two flavors, one for each of the single precision `S` and the
+// double precision `D` register classes, with a `_s` or `_d` suffix. The `sreg` and `dreg`
+// functions compute the register fields of VFP instructions for the two classes.
+
+pub(crate) fn define(shared_defs: &SharedDefinitions, regs: &IsaRegs) -> RecipeGroup {
+    let formats = &shared_defs.formats;
+
+    // Register classes shorthands.
+    let gpr = regs.class_by_name("GPR");
+    let s = regs.class_by_name("S");
+    let d = regs.class_by_name("D");
+    let d16 = regs.class_by_name("D16");
+    let flag = regs.class_by_name("FLAG");
+
+    // Operand constraints shorthands.
+    let reg_nzcv = Register::new(flag, regs.regunit_by_name(flag, "nzcv"));
+    let stack_gpr = Stack::new(gpr);
+
+    // Definitions.
+    let mut recipes = RecipeGroup::new();
+
+    // Data processing with two registers, using the shifted register form with a shift amount
+    // of zero.
+    recipes.push(
+        EncodingRecipeBuilder::new("alu", &formats.binary, 4)
+            .operands_in(vec![gpr, gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit("put_dp_reg(bits, in_reg0, in_reg1, out_reg0, sink);"),
+    );
+
+    // Same with a single source register, for `mvn` which has no first operand.
+    recipes.push(
+        EncodingRecipeBuilder::new("alu_rm", &formats.unary, 4)
+            .operands_in(vec![gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit("put_dp_reg(bits, 0, in_reg0, out_reg0, sink);"),
+    );
+
+    // Negation: `rsb.w rd, rn, #0`.
+    recipes.push(
+        EncodingRecipeBuilder::new("neg", &formats.unary, 4)
+            .operands_in(vec![gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit("put_modimm(bits, in_reg0, 0, out_reg0, sink);"),
+    );
+
+    // Data processing with a register and an immediate. Immediates that can't be encoded in the
+    // instruction, or in a related instruction with an inverted or negated immediate, are
+    // materialized in ip.
+    recipes.push(
+        EncodingRecipeBuilder::new("alu_imm", &formats.binary_imm, 4)
+            .operands_in(vec![gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .compute_size("size_for_alu_imm")
+            .emit("put_dp_imm(bits, in_reg0, imm.into(), out_reg0, sink);"),
+    );
+
+    // Register-controlled data processing with two source registers, such as `mul` or `ror`.
+    recipes.push(
+        EncodingRecipeBuilder::new("dp", &formats.binary, 4)
+            .operands_in(vec![gpr, gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit("put_rrr(bits, in_reg0, in_reg1, out_reg0, sink);"),
+    );
+
+    // Same with one source register, for `clz` and `rbit` which repeat it in the Rn and Rm
+    // fields.
+    recipes.push(
+        EncodingRecipeBuilder::new("dp_r", &formats.unary, 4)
+            .operands_in(vec![gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit("put_rrr(bits, in_reg0, in_reg0, out_reg0, sink);"),
+    );
+
+    // Extensions, which have the Rn field set to 0b1111.
+    recipes.push(
+        EncodingRecipeBuilder::new("ext", &formats.unary, 4)
+            .operands_in(vec![gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit("put_rrr(bits, RU::r15 as RegUnit, in_reg0, out_reg0, sink);"),
+    );
+
+    // Count trailing zeros: reverse the bits, then count leading zeros.
+    recipes.push(
+        EncodingRecipeBuilder::new("ctz", &formats.unary, 8)
+            .operands_in(vec![gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    // `bits` encodes `rbit`.
+                    put_rrr(bits, in_reg0, in_reg0, SCRATCH, sink);
+                    put_rrr(CLZ, SCRATCH, SCRATCH, out_reg0, sink);
+                "#,
+            ),
+    );
+
+    // Shifts by a register. The clif instructions use the shift amount modulo 32, while the
+    // instructions use the whole low byte of the register, so the amount is masked in ip first.
+    recipes.push(
+        EncodingRecipeBuilder::new("shift", &formats.binary, 8)
+            .operands_in(vec![gpr, gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    // and.w ip, in_reg1, #31
+                    put_modimm(AND, in_reg1, 31, SCRATCH, sink);
+                    put_rrr(bits, in_reg0, SCRATCH, out_reg0, sink);
+                "#,
+            ),
+    );
+
+    // Rotate left, which is a rotate right by the negated amount. `ror` already uses the amount
+    // modulo 32.
+    recipes.push(
+        EncodingRecipeBuilder::new("rotl", &formats.binary, 8)
+            .operands_in(vec![gpr, gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    // rsb.w ip, in_reg1, #0
+                    put_modimm(RSB, in_reg1, 0, SCRATCH, sink);
+                    put_rrr(bits, in_reg0, SCRATCH, out_reg0, sink);
+                "#,
+            ),
+    );
+
+    // Shifts and rotations by an immediate, which are aliases of `mov.w` with a shifted register
+    // operand. The encoding bits hold the shift type.
+    recipes.push(
+        EncodingRecipeBuilder::new("shift_imm", &formats.binary_imm, 4)
+            .operands_in(vec![gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit("put_shift_imm(bits, in_reg0, imm.into(), out_reg0, sink);"),
+    );
+
+    recipes.push(
+        EncodingRecipeBuilder::new("rotl_imm", &formats.binary_imm, 4)
+            .operands_in(vec![gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    let amount: i64 = imm.into();
+                    put_shift_imm(bits, in_reg0, 32 - (amount & 31), out_reg0, sink);
+                "#,
+            ),
+    );
+
+    // High half of a 32-bit multiplication: a long multiplication with the low half in ip.
+    recipes.push(
+        EncodingRecipeBuilder::new("mulhi", &formats.binary, 4)
+            .operands_in(vec![gpr, gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit("put_rrrr(bits, in_reg0, in_reg1, SCRATCH, out_reg0, sink);"),
+    );
+
+    // Unsigned division, which traps on a zero divisor. The hardware divide instructions return
+    // 0 instead.
+    recipes.push(
+        EncodingRecipeBuilder::new("udiv", &formats.binary, 12)
+            .operands_in(vec![gpr, gpr])
+            .operands_out(vec![gpr])
+            .emit(
+                r#"
+                    put_div_by_zero_check(in_reg1, func.srclocs[inst], sink);
+                    put_rrr(bits, in_reg0, in_reg1, out_reg0, sink);
+                "#,
+            ),
+    );
+
+    // Signed division, which also traps when the quotient overflows.
+    recipes.push(
+        EncodingRecipeBuilder::new("sdiv", &formats.binary, 26)
+            .operands_in(vec![gpr, gpr])
+            .operands_out(vec![gpr])
+            .emit(
+                r#"
+                    put_div_by_zero_check(in_reg1, func.srclocs[inst], sink);
+                    put_sdiv_overflow_check(in_reg0, in_reg1, func.srclocs[inst], sink);
+                    put_rrr(bits, in_reg0, in_reg1, out_reg0, sink);
+                "#,
+            ),
+    );
+
+    // Remainder: divide into ip, then multiply back and subtract. The signed division doesn't
+    // trap on overflow and `srem` produces the expected 0 in that case.
+    recipes.push(
+        EncodingRecipeBuilder::new("rem", &formats.binary, 16)
+            .operands_in(vec![gpr, gpr])
+            .operands_out(vec![gpr])
+            .emit(
+                r#"
+                    put_div_by_zero_check(in_reg1, func.srclocs[inst], sink);
+                    put_rrr(bits, in_reg0, in_reg1, SCRATCH, sink);
+                    // mls out_reg0, ip, in_reg1, in_reg0
+                    put_rrrr(MLS, SCRATCH, in_reg1, in_reg0, out_reg0, sink);
+                "#,
+            ),
+    );
+
+    // Integer constants, materialized by `mov.w`, `mvn`, `movw` or a `movw` + `movt` pair.
+    recipes.push(
+        EncodingRecipeBuilder::new("movimm", &formats.unary_imm, 4)
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .compute_size("size_for_movimm")
+            .emit("put_movimm(out_reg0, imm.into(), sink);"),
+    );
+
+    // Boolean constants.
+    recipes.push(
+        EncodingRecipeBuilder::new("bconst", &formats.unary_bool, 4)
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit("put_movimm(out_reg0, if imm { 1 } else { 0 }, sink);"),
+    );
+
+    // Operate on the bit of a boolean with the immediate 1: `and.w rd, rn, #1` converts it to an
+    // integer, `eor.w rd, rn, #1` negates it.
+    recipes.push(
+        EncodingRecipeBuilder::new("bool1", &formats.unary, 4)
+            .operands_in(vec![gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit("put_modimm(bits, in_reg0, 1, out_reg0, sink);"),
+    );
+
+    // No-op used for reductions, which only look at the low bits of a register.
+    recipes.push(
+        EncodingRecipeBuilder::new("null", &formats.unary, 0)
+            .operands_in(vec![gpr])
+            .operands_out(vec![0])
+            .clobbers_flags(false)
+            .emit(""),
+    );
+
+    // Register copies, using the 16-bit `mov` which can access all the registers and doesn't
+    // set the flags.
+    recipes.push(
+        EncodingRecipeBuilder::new("mov", &formats.unary, 2)
+            .operands_in(vec![gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit("put_mov(in_reg0, out_reg0, sink);"),
+    );
+
+    recipes.push(
+        EncodingRecipeBuilder::new("rmov", &formats.reg_move, 2)
+            .operands_in(vec![gpr])
+            .clobbers_flags(false)
+            .emit("put_mov(src, dst, sink);"),
+    );
+
+    // Same for copy-to-SSA -- GPR regmove.
+    recipes.push(
+        EncodingRecipeBuilder::new("copytossa", &formats.copy_to_ssa, 2)
+            // No operands_in to mention, because a source register is specified directly.
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit("put_mov(src, out_reg0, sink);"),
+    );
+
+    // Copy the stack pointer, or from it.
+    recipes.push(
+        EncodingRecipeBuilder::new("copysp", &formats.copy_special, 2)
+            .clobbers_flags(false)
+            .emit("put_mov(src, dst, sink);"),
+    );
+
+    // Compare two registers and set the flags: `cmp.w rn, rm`.
+    recipes.push(
+        EncodingRecipeBuilder::new("rcmp", &formats.binary, 4)
+            .operands_in(vec![gpr, gpr])
+            .operands_out(vec![reg_nzcv])
+            .emit("put_dp_reg(bits, in_reg0, in_reg1, RU::r15 as RegUnit, sink);"),
+    );
+
+    // Compare a register with an immediate and set the flags.
+    recipes.push(
+        EncodingRecipeBuilder::new("rcmp_imm", &formats.binary_imm, 4)
+            .operands_in(vec![gpr])
+            .operands_out(vec![reg_nzcv])
+            .compute_size("size_for_alu_imm")
+            .emit("put_dp_imm(bits, in_reg0, imm.into(), RU::r15 as RegUnit, sink);"),
+    );
+
+    // Compare two registers and materialize the condition as a boolean.
+    recipes.push(
+        EncodingRecipeBuilder::new("icscc", &formats.int_compare, 14)
+            .operands_in(vec![gpr, gpr])
+            .operands_out(vec![gpr])
+            .emit(
+                r#"
+                    put_dp_reg(bits, in_reg0, in_reg1, RU::r15 as RegUnit, sink);
+                    put_cset(icc2cond(cond), out_reg0, sink);
+                "#,
+            ),
+    );
+
+    // Same with an immediate.
+    recipes.push(
+        EncodingRecipeBuilder::new("icscc_imm", &formats.int_compare_imm, 14)
+            .operands_in(vec![gpr])
+            .operands_out(vec![gpr])
+            .compute_size("size_for_icscc_imm")
+            .emit(
+                r#"
+                    put_dp_imm(bits, in_reg0, imm.into(), RU::r15 as RegUnit, sink);
+                    put_cset(icc2cond(cond), out_reg0, sink);
+                "#,
+            ),
+    );
+
+    // Test integer flags and set a register.
+    recipes.push(
+        EncodingRecipeBuilder::new("seti", &formats.int_cond, 10)
+            .operands_in(vec![reg_nzcv])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit("put_cset(icc2cond(cond), out_reg0, sink);"),
+    );
+
+    // Conditional select on integer flags: `ite cond; mov rd, rx; mov rd, ry`.
+    recipes.push(
+        EncodingRecipeBuilder::new("sel", &formats.int_select, 6)
+            .operands_in(vec![
+                OperandConstraint::FixedReg(reg_nzcv),
+                OperandConstraint::RegClass(gpr),
+                OperandConstraint::RegClass(gpr),
+            ])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    put_ite(icc2cond(cond), sink);
+                    put_mov(in_reg1, out_reg0, sink);
+                    put_mov(in_reg2, out_reg0, sink);
+                "#,
+            ),
+    );
+
+    // Arithmetic producing the carry flag: `adds.w` and `subs.w`.
+    recipes.push(
+        EncodingRecipeBuilder::new("alu_fout", &formats.binary, 4)
+            .operands_in(vec![gpr, gpr])
+            .operands_out(vec![
+                OperandConstraint::RegClass(gpr),
+                OperandConstraint::FixedReg(reg_nzcv),
+            ])
+            .emit("put_dp_reg(bits, in_reg0, in_reg1, out_reg0, sink);"),
+    );
+
+    // Arithmetic consuming the carry flag: `adc.w` and `sbc.w`.
+    recipes.push(
+        EncodingRecipeBuilder::new("alu_fin", &formats.ternary, 4)
+            .operands_in(vec![
+                OperandConstraint::RegClass(gpr),
+                OperandConstraint::RegClass(gpr),
+                OperandConstraint::FixedReg(reg_nzcv),
+            ])
+            .operands_out(vec![gpr])
+            .emit("put_dp_reg(bits, in_reg0, in_reg1, out_reg0, sink);"),
+    );
+
+    // Unconditional trap, encoded as a permanently undefined instruction.
+    recipes.push(
+        EncodingRecipeBuilder::new("trap", &formats.trap, 2)
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    sink.trap(code, func.srclocs[inst]);
+                    sink.put2(UDF);
+                "#,
+            ),
+    );
+
+    // Breakpoint: `bkpt #0`.
+    recipes.push(
+        EncodingRecipeBuilder::new("bkpt", &formats.nullary, 2)
+            .clobbers_flags(false)
+            .emit("sink.put2(BKPT);"),
+    );
+
+    // Conditional branch over an undefined instruction.
+    recipes.push(
+        EncodingRecipeBuilder::new("trapif", &formats.int_cond_trap, 4)
+            .operands_in(vec![reg_nzcv])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    put_bcond_n(icc2cond(cond) ^ 1, 0, sink);
+                    sink.trap(code, func.srclocs[inst]);
+                    sink.put2(UDF);
+                "#,
+            ),
+    );
+
+    recipes.push(
+        EncodingRecipeBuilder::new("trapff", &formats.float_cond_trap, 4)
+            .operands_in(vec![reg_nzcv])
+            .clobbers_flags(false)
+            .inst_predicate(single_floatcc_predicate(
+                shared_defs,
+                &*formats.float_cond_trap,
+            ))
+            .emit(
+                r#"
+                    put_bcond_n(fcc2cond(cond) ^ 1, 0, sink);
+                    sink.trap(code, func.srclocs[inst]);
+                    sink.put2(UDF);
+                "#,
+            ),
+    );
+
+    // Branches. Each branch comes with a 16-bit encoding with a short range, a 32-bit encoding
+    // with a longer range, and for the conditional branches a third one that inverts the
+    // condition to branch over a `b.w`. The branch relaxation pass picks the smallest one that
+    // reaches the destination, so the short ones must be listed first in the encodings.
+    //
+    // The displacements are relative to the address of the branch instruction plus 4.
+
+    // Unconditional branch: `b.n` with an 11-bit halfword offset and `b.w` with a 24-bit one.
+    recipes.push(
+        EncodingRecipeBuilder::new("jmp_n", &formats.jump, 2)
+            .branch_range((4, 12))
+            .clobbers_flags(false)
+            .emit("put_b_n(branch_disp(destination, func, sink), sink);"),
+    );
+
+    recipes.push(
+        EncodingRecipeBuilder::new("jmp", &formats.jump, 4)
+            .branch_range((4, 25))
+            .clobbers_flags(false)
+            .emit("put_b_w(branch_disp(destination, func, sink), sink);"),
+    );
+
+    // Conditional branches on integer and float flags: `b<c>.n` with an 8-bit halfword offset,
+    // `b<c>.w` with a 20-bit one, and the inverted `b<!c>.n` over a `b.w`.
+    for &(format, cond, suffix) in &[
+        (&formats.branch_int, "icc2cond(cond)", "if"),
+        (&formats.branch_float, "fcc2cond(cond)", "ff"),
+    ] {
+        let pred = if suffix == "ff" {
+            Some(single_floatcc_predicate(shared_defs, &*format))
+        } else {
+            None
+        };
+        for &(name, size, range, emit) in &[
+            (
+                "br{}_n",
+                2,
+                (4, 9),
+                "put_bcond_n({cond}, branch_disp(destination, func, sink), sink);",
+            ),
+            (
+                "br{}",
+                4,
+                (4, 21),
+                "put_bcond_w({cond}, branch_disp(destination, func, sink), sink);",
+            ),
+            (
+                "br{}_far",
+                6,
+                (6, 25),
+                r#"
+                    put_bcond_n({cond} ^ 1, 2, sink);
+                    put_b_w(branch_disp(destination, func, sink), sink);
+                "#,
+            ),
+        ] {
+            let mut builder = EncodingRecipeBuilder::new(name.replace("{}", suffix), format, size)
+                .operands_in(vec![reg_nzcv])
+                .branch_range(range)
+                .clobbers_flags(false)
+                .emit(emit.replace("{cond}", cond));
+            if let Some(pred) = &pred {
+                builder = builder.inst_predicate(pred.clone());
+            }
+            recipes.push(builder);
+        }
+    }
+
+    // Compare a register with zero and branch. The encoding bits hold the condition code, `eq`
+    // or `ne`.
+    for &(name, size, range, emit) in &[
+        (
+            "brz_n",
+            6,
+            (8, 9),
+            "put_bcond_n(u32::from(bits), branch_disp(destination, func, sink), sink);",
+        ),
+        (
+            "brz",
+            8,
+            (8, 21),
+            "put_bcond_w(u32::from(bits), branch_disp(destination, func, sink), sink);",
+        ),
+        (
+            "brz_far",
+            10,
+            (10, 25),
+            r#"
+                put_bcond_n(u32::from(bits) ^ 1, 2, sink);
+                put_b_w(branch_disp(destination, func, sink), sink);
+            "#,
+        ),
+    ] {
+        recipes.push(
+            EncodingRecipeBuilder::new(name, &formats.branch, size)
+                .operands_in(vec![gpr])
+                .branch_range(range)
+                .emit(format!(
+                    r#"
+                        // cmp.w in_reg0, #0
+                        put_modimm(CMP, in_reg0, 0, RU::r15 as RegUnit, sink);
+                        {}
+                    "#,
+                    emit
+                )),
+        );
+    }
+
+    // Direct call, with the offset to be filled in by the linker.
+    recipes.push(
+        EncodingRecipeBuilder::new("call", &formats.call, 4)
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    sink.reloc_external(Reloc::Arm32Call,
+                                        &func.dfg.ext_funcs[func_ref].name,
+                                        0);
+                    put_bl(0, sink);
+                "#,
+            ),
+    );
+
+    // Indirect call: `blx rm`.
+    recipes.push(
+        EncodingRecipeBuilder::new("call_r", &formats.call_indirect, 2)
+            .operands_in(vec![gpr])
+            .clobbers_flags(false)
+            .emit("put_branch_reg(bits, in_reg0, sink);"),
+    );
+
+    // Return to the address in the link register: `bx lr`.
+    recipes.push(
+        EncodingRecipeBuilder::new("ret", &formats.multiary, 2)
+            .clobbers_flags(false)
+            .emit("put_branch_reg(bits, RU::r14 as RegUnit, sink);"),
+    );
+
+    // Indirect jump: `mov pc, rm`. Unlike `bx`, it stays in the Thumb state regardless of the
+    // low bit of the address.
+    recipes.push(
+        EncodingRecipeBuilder::new("indirect_jmp", &formats.indirect_jump, 2)
+            .operands_in(vec![gpr])
+            .clobbers_flags(false)
+            .emit("put_mov(in_reg0, RU::r15 as RegUnit, sink);"),
+    );
+
+    // Address of a jump table, which is emitted right after the function body: a `movw` + `movt`
+    // pair with the offset to the table, followed by `add rd, pc`.
+    recipes.push(
+        EncodingRecipeBuilder::new("jt_base", &formats.branch_table_base, 10)
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    // The PC reads as the address of the `add` plus 4.
+                    let pc = sink.offset() + 12;
+                    let disp = func.jt_offsets[table].wrapping_sub(pc);
+                    put_movw_movt(out_reg0, disp, sink);
+                    put_add_pc(out_reg0, sink);
+                "#,
+            ),
+    );
+
+    // Load a jump table entry: `ldr.w rd, [rn, rm, lsl #2]`. The table follows the code and may
+    // only be 2-byte aligned, which is fine for `ldr` on ARMv7.
+    recipes.push(
+        EncodingRecipeBuilder::new("jt_entry", &formats.branch_table_entry, 4)
+            .operands_in(vec![gpr, gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .inst_predicate(InstructionPredicate::new_is_field_equal(
+                &*formats.branch_table_entry,
+                "imm",
+                "4".into(),
+            ))
+            .emit("put_ldst_reg(bits, out_reg0, in_reg1, in_reg0, 2, sink);"),
+    );
+
+    // Address of a function, loaded from a literal pool placed inline.
+    recipes.push(
+        EncodingRecipeBuilder::new("fnaddr", &formats.func_addr, 12)
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    let padding = put_ldr_literal_skip(out_reg0, sink);
+                    sink.reloc_external(Reloc::Abs4,
+                                        &func.dfg.ext_funcs[func_ref].name,
+                                        0);
+                    sink.put4(0);
+                    put_literal_padding(!padding, sink);
+                "#,
+            ),
+    );
+
+    // Address of a global value, loaded from a literal pool placed inline.
+    recipes.push(
+        EncodingRecipeBuilder::new("gvaddr", &formats.unary_global_value, 12)
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    let padding = put_ldr_literal_skip(out_reg0, sink);
+                    sink.reloc_external(Reloc::Abs4,
+                                        &func.global_values[global_value].symbol_name(),
+                                        0);
+                    sink.put4(0);
+                    put_literal_padding(!padding, sink);
+                "#,
+            ),
+    );
+
+    // Address of a stack slot.
+    recipes.push(
+        EncodingRecipeBuilder::new("spaddr", &formats.stack_load, 4)
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .compute_size("size_for_spaddr")
+            .emit(
+                r#"
+                    let sp = StackRef::sp(stack_slot, &func.stack_slots);
+                    let offset: i32 = offset.into();
+                    put_dp_imm(
+                        bits,
+                        RU::r13 as RegUnit,
+                        i64::from(sp.offset.checked_add(offset).unwrap()),
+                        out_reg0,
+                        sink,
+                    );
+                "#,
+            ),
+    );
+
+    // Adjust the stack pointer by an immediate.
+    recipes.push(
+        EncodingRecipeBuilder::new("adjustsp", &formats.unary_imm, 4)
+            .clobbers_flags(false)
+            .compute_size("size_for_adjustsp")
+            .emit(
+                r#"
+                    put_dp_imm(
+                        bits,
+                        RU::r13 as RegUnit,
+                        imm.into(),
+                        RU::r13 as RegUnit,
+                        sink,
+                    );
+                "#,
+            ),
+    );
+
+    // Push and pop, used for saving callee-saved registers in the prologue and epilogue.
+    recipes.push(
+        EncodingRecipeBuilder::new("push", &formats.unary, 4)
+            .operands_in(vec![gpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    sink.trap(TrapCode::StackOverflow, func.srclocs[inst]);
+                    // str.w rt, [sp, #-8]!
+                    put_ldst_sp_writeback(bits, in_reg0, 0xd08, sink);
+                "#,
+            ),
+    );
+
+    recipes.push(
+        EncodingRecipeBuilder::new("pop", &formats.nullary, 4)
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    // ldr.w rt, [sp], #8
+                    put_ldst_sp_writeback(bits, out_reg0, 0xb08, sink);
+                "#,
+            ),
+    );
+
+    recipes.push(
+        EncodingRecipeBuilder::new("push_pair", &formats.binary, 4)
+            .operands_in(vec![gpr, gpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    sink.trap(TrapCode::StackOverflow, func.srclocs[inst]);
+                    put_ldstm(bits, in_reg0, in_reg1, sink);
+                "#,
+            ),
+    );
+
+    recipes.push(
+        EncodingRecipeBuilder::new("pop_pair", &formats.nullary, 4)
+            .operands_out(vec![gpr, gpr])
+            .clobbers_flags(false)
+            .emit("put_ldstm(bits, out_reg0, out_reg1, sink);"),
+    );
+
+    recipes.push(
+        EncodingRecipeBuilder::new("vpush", &formats.unary, 4)
+            .operands_in(vec![d])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    sink.trap(TrapCode::StackOverflow, func.srclocs[inst]);
+                    put_vldstm(bits, dreg(in_reg0), sink);
+                "#,
+            ),
+    );
+
+    recipes.push(
+        EncodingRecipeBuilder::new("vpop", &formats.nullary, 4)
+            .operands_out(vec![d])
+            .clobbers_flags(false)
+            .emit("put_vldstm(bits, dreg(out_reg0), sink);"),
+    );
+
+    // Loads and stores with a base register and an immediate offset. Offsets that don't fit in
+    // the unsigned 12-bit or the negative 8-bit immediate forms are materialized in ip.
+    recipes.push(
+        EncodingRecipeBuilder::new("ld", &formats.load, 4)
+            .operands_in(vec![gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .compute_size("size_for_load")
+            .emit(
+                r#"
+                    let trap = if flags.notrap() {
+                        None
+                    } else {
+                        Some((TrapCode::HeapOutOfBounds, func.srclocs[inst]))
+                    };
+                    put_ldst(bits, out_reg0, in_reg0, offset.into(), trap, sink);
+                "#,
+            ),
+    );
+
+    recipes.push(
+        EncodingRecipeBuilder::new("st", &formats.store, 4)
+            .operands_in(vec![gpr, gpr])
+            .clobbers_flags(false)
+            .compute_size("size_for_store")
+            .emit(
+                r#"
+                    let trap = if flags.notrap() {
+                        None
+                    } else {
+                        Some((TrapCode::HeapOutOfBounds, func.srclocs[inst]))
+                    };
+                    put_ldst(bits, in_reg0, in_reg1, offset.into(), trap, sink);
+                "#,
+            ),
+    );
+
+    // Spills and fills, addressed relative to the stack pointer.
+    recipes.push(
+        EncodingRecipeBuilder::new("spill", &formats.unary, 4)
+            .operands_in(vec![gpr])
+            .operands_out(vec![stack_gpr])
+            .clobbers_flags(false)
+            .compute_size("size_for_spill")
+            .emit(
+                r#"
+                    put_ldst(
+                        bits,
+                        in_reg0,
+                        RU::r13 as RegUnit,
+                        out_stk0.offset.into(),
+                        Some((TrapCode::StackOverflow, func.srclocs[inst])),
+                        sink,
+                    );
+                "#,
+            ),
+    );
+
+    recipes.push(
+        EncodingRecipeBuilder::new("fill", &formats.unary, 4)
+            .operands_in(vec![stack_gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .compute_size("size_for_fill")
+            .emit(
+                r#"
+                    put_ldst(
+                        bits,
+                        out_reg0,
+                        RU::r13 as RegUnit,
+                        in_stk0.offset.into(),
+                        None,
+                        sink,
+                    );
+                "#,
+            ),
+    );
+
+    recipes.push(
+        EncodingRecipeBuilder::new("regspill", &formats.reg_spill, 4)
+            .operands_in(vec![gpr])
+            .clobbers_flags(false)
+            .compute_size("size_for_regspill")
+            .emit(
+                r#"
+                    let dst = StackRef::sp(dst, &func.stack_slots);
+                    put_ldst(
+                        bits,
+                        src,
+                        RU::r13 as RegUnit,
+                        dst.offset.into(),
+                        Some((TrapCode::StackOverflow, func.srclocs[inst])),
+                        sink,
+                    );
+                "#,
+            ),
+    );
+
+    recipes.push(
+        EncodingRecipeBuilder::new("regfill", &formats.reg_fill, 4)
+            .operands_in(vec![stack_gpr])
+            .clobbers_flags(false)
+            .compute_size("size_for_regfill")
+            .emit(
+                r#"
+                    let src = StackRef::sp(src, &func.stack_slots);
+                    put_ldst(bits, dst, RU::r13 as RegUnit, src.offset.into(), None, sink);
+                "#,
+            ),
+    );
+
+    // Stack-slot to same stack-slot copy, which is guaranteed to turn into a no-op.
+    recipes.push(
+        EncodingRecipeBuilder::new("stacknull", &formats.unary, 0)
+            .operands_in(vec![stack_gpr])
+            .operands_out(vec![stack_gpr])
+            .clobbers_flags(false)
+            .emit(""),
+    );
+
+    // No-op fills, created by late-stage redundant-fill removal.
+    recipes.push(
+        EncodingRecipeBuilder::new("fillnull", &formats.unary, 0)
+            .operands_in(vec![stack_gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit(""),
+    );
+
+    // Moves between general purpose and single precision registers: `vmov sn, rt` and
+    // `vmov rt, sn`.
+    recipes.push(
+        EncodingRecipeBuilder::new("vmov_sr", &formats.unary, 4)
+            .operands_in(vec![gpr])
+            .operands_out(vec![s])
+            .clobbers_flags(false)
+            .emit("put_vmov_core(false, sreg(out_reg0), in_reg0, sink);"),
+    );
+
+    recipes.push(
+        EncodingRecipeBuilder::new("vmov_rs", &formats.unary, 4)
+            .operands_in(vec![s])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit("put_vmov_core(true, sreg(in_reg0), out_reg0, sink);"),
+    );
+
+    // Floating point constants, built in ip and moved over.
+    recipes.push(
+        EncodingRecipeBuilder::new("vconst_s", &formats.unary_ieee32, 8)
+            .operands_out(vec![s])
+            .clobbers_flags(false)
+            .compute_size("size_for_vconst")
+            .emit(
+                r#"
+                    put_movimm(SCRATCH, i64::from(imm.bits()), sink);
+                    put_vmov_core(false, sreg(out_reg0), SCRATCH, sink);
+                "#,
+            ),
+    );
+
+    // The two halves of a double precision register are the single precision registers with
+    // the same register units, which only exist for d0-d15.
+    recipes.push(
+        EncodingRecipeBuilder::new("vconst_d", &formats.unary_ieee64, 16)
+            .operands_out(vec![d16])
+            .clobbers_flags(false)
+            .compute_size("size_for_vconst")
+            .emit(
+                r#"
+                    let bits = imm.bits();
+                    put_movimm(SCRATCH, i64::from(bits as u32), sink);
+                    put_vmov_core(false, sreg(out_reg0), SCRATCH, sink);
+                    put_movimm(SCRATCH, i64::from((bits >> 32) as u32), sink);
+                    put_vmov_core(false, sreg(out_reg0 + 1), SCRATCH, sink);
+                "#,
+            ),
+    );
+
+    // Conversions between the single and double precision registers.
+    recipes.push(
+        EncodingRecipeBuilder::new("vcvt_ds", &formats.unary, 4)
+            .operands_in(vec![s])
+            .operands_out(vec![d])
+            .clobbers_flags(false)
+            .emit("put_vfp(bits, dreg(out_reg0), 0, sreg(in_reg0), sink);"),
+    );
+
+    recipes.push(
+        EncodingRecipeBuilder::new("vcvt_sd", &formats.unary, 4)
+            .operands_in(vec![d])
+            .operands_out(vec![s])
+            .clobbers_flags(false)
+            .emit("put_vfp(bits, sreg(out_reg0), 0, dreg(in_reg0), sink);"),
+    );
+
+    // Test float flags and set a register.
+    recipes.push(
+        EncodingRecipeBuilder::new("setf", &formats.float_cond, 10)
+            .operands_in(vec![reg_nzcv])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .inst_predicate(single_floatcc_predicate(shared_defs, &*formats.float_cond))
+            .emit("put_cset(fcc2cond(cond), out_reg0, sink);"),
+    );
+
+    // Floating point recipes for each register class.
+    // The `halves` class is the part of the register class whose registers have single precision
+    // halves.
+    for &(suffix, class, halves) in &[("s", s, s), ("d", d, d16)] {
+        let stack = Stack::new(class);
+        let name = |base: &str| format!("{}_{}", base, suffix);
+        let reg = |code: &str| code.replace("vreg", &format!("{}reg", suffix));
+
+        // Data processing with two source registers.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("vrrr"), &formats.binary, 4)
+                .operands_in(vec![class, class])
+                .operands_out(vec![class])
+                .clobbers_flags(false)
+                .emit(reg(
+                    "put_vfp(bits, vreg(out_reg0), vreg(in_reg0), vreg(in_reg1), sink);",
+                )),
+        );
+
+        // Data processing with one source register.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("vrr"), &formats.unary, 4)
+                .operands_in(vec![class])
+                .operands_out(vec![class])
+                .clobbers_flags(false)
+                .emit(reg(
+                    "put_vfp(bits, vreg(out_reg0), 0, vreg(in_reg0), sink);",
+                )),
+        );
+
+        // Copy the sign of a value, with the `vmov` of the right precision in the encoding bits.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("vcopysign"), &formats.binary, 18)
+                .operands_in(vec![class, halves])
+                .operands_out(vec![class])
+                .emit(reg(r#"
+                        let sign = sign_sreg(bits, in_reg1);
+                        put_vcopysign(bits, vreg(in_reg0), sign, vreg(out_reg0), sink);
+                    "#)),
+        );
+
+        // Minimum and maximum, with the `vmov` of the right precision in the encoding bits.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("vminmax"), &formats.binary, 48)
+                .operands_in(vec![halves, class])
+                .operands_out(vec![class])
+                .emit(reg(r#"
+                        let (first, second) = if opcode == Opcode::Fmin {
+                            (in_reg0, in_reg1)
+                        } else {
+                            (in_reg1, in_reg0)
+                        };
+                        put_vminmax(
+                            bits,
+                            vreg(in_reg0),
+                            vreg(in_reg1),
+                            vreg(first),
+                            vreg(second),
+                            sign_sreg(bits, in_reg0),
+                            vreg(out_reg0),
+                            sink,
+                        );
+                    "#)),
+        );
+
+        // Fused multiply-add: `vfma` accumulates in its destination, which is tied to the
+        // addend.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("vfma"), &formats.ternary, 4)
+                .operands_in(vec![class, class, class])
+                .operands_out(vec![2])
+                .clobbers_flags(false)
+                .emit(reg(
+                    "put_vfp(bits, vreg(in_reg2), vreg(in_reg0), vreg(in_reg1), sink);",
+                )),
+        );
+
+        // Register moves.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("vmov"), &formats.reg_move, 4)
+                .operands_in(vec![class])
+                .clobbers_flags(false)
+                .emit(reg("put_vfp(bits, vreg(dst), 0, vreg(src), sink);")),
+        );
+
+        recipes.push(
+            EncodingRecipeBuilder::new(name("vcopytossa"), &formats.copy_to_ssa, 4)
+                .operands_out(vec![class])
+                .clobbers_flags(false)
+                .emit(reg("put_vfp(bits, vreg(out_reg0), 0, vreg(src), sink);")),
+        );
+
+        // Compare two registers, and copy the resulting flags to the APSR.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("vcmp"), &formats.binary, 8)
+                .operands_in(vec![class, class])
+                .operands_out(vec![reg_nzcv])
+                .emit(reg(r#"
+                        put_vfp(bits, vreg(in_reg0), 0, vreg(in_reg1), sink);
+                        put_vmrs_apsr_nzcv(sink);
+                    "#)),
+        );
+
+        // Compare two registers and materialize the condition as a boolean.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("vcscc"), &formats.float_compare, 18)
+                .operands_in(vec![class, class])
+                .operands_out(vec![gpr])
+                .inst_predicate(single_floatcc_predicate(
+                    shared_defs,
+                    &*formats.float_compare,
+                ))
+                .emit(reg(r#"
+                        put_vfp(bits, vreg(in_reg0), 0, vreg(in_reg1), sink);
+                        put_vmrs_apsr_nzcv(sink);
+                        put_cset(fcc2cond(cond), out_reg0, sink);
+                    "#)),
+        );
+
+        // Same for the two float condition codes that need two conditions to be tested.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("vcscc2"), &formats.float_compare, 24)
+                .operands_in(vec![class, class])
+                .operands_out(vec![gpr])
+                .inst_predicate(double_floatcc_predicate(
+                    shared_defs,
+                    &*formats.float_compare,
+                ))
+                .emit(reg(r#"
+                        put_vfp(bits, vreg(in_reg0), 0, vreg(in_reg1), sink);
+                        put_vmrs_apsr_nzcv(sink);
+                        put_cset_double_fcc(cond, out_reg0, sink);
+                    "#)),
+        );
+
+        // Conversions from an integer: move it to the low single precision half of the
+        // destination and convert in place.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("vcvt_from_int"), &formats.unary, 8)
+                .operands_in(vec![gpr])
+                .operands_out(vec![halves])
+                .clobbers_flags(false)
+                .emit(reg(r#"
+                        put_vmov_core(false, sreg(out_reg0), in_reg0, sink);
+                        put_vfp(bits, vreg(out_reg0), 0, sreg(out_reg0), sink);
+                    "#)),
+        );
+
+        // Saturating conversions to an integer, which convert NaN to 0.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("vcvt_to_int"), &formats.unary, 16)
+                .operands_in(vec![class])
+                .operands_out(vec![gpr])
+                .clobbers_flags(false)
+                .emit(reg(
+                    "put_vcvt_to_int(bits, vreg(in_reg0), in_reg0, out_reg0, sink);",
+                )),
+        );
+
+        // Trapping conversions to an integer. NaN inputs are detected with an unordered
+        // self-comparison, and out-of-range inputs by the cumulative invalid operation flag in
+        // the FPSCR.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("vcvt_trap"), &formats.unary, 52)
+                .operands_in(vec![class])
+                .operands_out(vec![gpr])
+                .emit(reg(r#"
+                        put_vcmp_self(bits, vreg(in_reg0), sink);
+                        put_bcond_n(COND_VS ^ 1, 0, sink);
+                        sink.trap(TrapCode::BadConversionToInteger, func.srclocs[inst]);
+                        sink.put2(UDF);
+                        put_clear_ioc(out_reg0, sink);
+                        put_vcvt_to_int(bits, vreg(in_reg0), in_reg0, out_reg0, sink);
+                        put_ioc_check(func.srclocs[inst], sink);
+                    "#)),
+        );
+
+        // Loads and stores. The offsets must be multiples of 4, up to 1020. Other offsets are
+        // added to the base address in ip.
+        recipes.push(
+            EncodingRecipeBuilder::new(name("vld"), &formats.load, 4)
+                .operands_in(vec![gpr])
+                .operands_out(vec![class])
+                .clobbers_flags(false)
+                .compute_size("size_for_vload")
+                .emit(reg(r#"
+                        let trap = if flags.notrap() {
+                            None
+                        } else {
+                            Some((TrapCode::HeapOutOfBounds, func.srclocs[inst]))
+                        };
+                        put_vldst(bits, vreg(out_reg0), in_reg0, offset.into(), trap, sink);
+                    "#)),
+        );
+
+        recipes.push(
+            EncodingRecipeBuilder::new(name("vst"), &formats.store, 4)
+                .operands_in(vec![class, gpr])
+                .clobbers_flags(false)
+                .compute_size("size_for_vstore")
+                .emit(reg(r#"
+                        let trap = if flags.notrap() {
+                            None
+                        } else {
+                            Some((TrapCode::HeapOutOfBounds, func.srclocs[inst]))
+                        };
+                        put_vldst(bits, vreg(in_reg0), in_reg1, offset.into(), trap, sink);
+                    "#)),
+        );
+
+        recipes.push(
+            EncodingRecipeBuilder::new(name("vspill"), &formats.unary, 4)
+                .operands_in(vec![class])
+                .operands_out(vec![stack])
+                .clobbers_flags(false)
+                .compute_size("size_for_vspill")
+                .emit(reg(r#"
+                        put_vldst(
+                            bits,
+                            vreg(in_reg0),
+                            RU::r13 as RegUnit,
+                            out_stk0.offset.into(),
+                            Some((TrapCode::StackOverflow, func.srclocs[inst])),
+                            sink,
+                        );
+                    "#)),
+        );
+
+        recipes.push(
+            EncodingRecipeBuilder::new(name("vfill"), &formats.unary, 4)
+                .operands_in(vec![stack])
+                .operands_out(vec![class])
+                .clobbers_flags(false)
+                .compute_size("size_for_vfill")
+                .emit(reg(r#"
+                        put_vldst(
+                            bits,
+                            vreg(out_reg0),
+                            RU::r13 as RegUnit,
+                            in_stk0.offset.into(),
+                            None,
+                            sink,
+                        );
+                    "#)),
+        );
+
+        recipes.push(
+            EncodingRecipeBuilder::new(name("vregspill"), &formats.reg_spill, 4)
+                .operands_in(vec![class])
+                .clobbers_flags(false)
+                .compute_size("size_for_vregspill")
+                .emit(reg(r#"
+                        let dst = StackRef::sp(dst, &func.stack_slots);
+                        put_vldst(
+                            bits,
+                            vreg(src),
+                            RU::r13 as RegUnit,
+                            dst.offset.into(),
+                            Some((TrapCode::StackOverflow, func.srclocs[inst])),
+                            sink,
+                        );
+                    "#)),
+        );
+
+        recipes.push(
+            EncodingRecipeBuilder::new(name("vregfill"), &formats.reg_fill, 4)
+                .operands_in(vec![stack])
+                .clobbers_flags(false)
+                .compute_size("size_for_vregfill")
+                .emit(reg(r#"
+                        let src = StackRef::sp(src, &func.stack_slots);
+                        put_vldst(
+                            bits,
+                            vreg(dst),
+                            RU::r13 as RegUnit,
+                            src.offset.into(),
+                            None,
+                            sink,
+                        );
+                    "#)),
+        );
+
+        recipes.push(
+            EncodingRecipeBuilder::new(name("vfillnull"), &formats.unary, 0)
+                .operands_in(vec![stack])
+                .operands_out(vec![class])
+                .clobbers_flags(false)
+                .emit(""),
+        );
+    }
+
+    recipes
+}
@@ -0,0 +1,729 @@
+use crate::cdsl::encodings::{Encoding, EncodingBuilder};
+use crate::cdsl::instructions::{
+    Bindable, InstSpec, InstructionGroup, InstructionPredicateRegistry,
+};
+use crate::cdsl::recipes::{EncodingRecipeNumber, Recipes};
+use crate::cdsl::types::LaneType;
+
+use crate::shared::types::Bool::B1;
+use crate::shared::types::Float::{F32, F64};
+use crate::shared::types::Int::{I16, I32, I64, I8};
+use crate::shared::types::Reference::R64;
+use crate::shared::Definitions as SharedDefinitions;
+
+use super::recipes::RecipeGroup;
+
+pub(crate) struct PerCpuModeEncodings<'defs> {
+    pub inst_pred_reg: InstructionPredicateRegistry,
+    pub enc: Vec<Encoding>,
+    recipes: &'defs Recipes,
+}
+
+impl<'defs> PerCpuModeEncodings<'defs> {
+    fn new(recipes: &'defs Recipes) -> Self {
+        Self {
+            inst_pred_reg: InstructionPredicateRegistry::new(),
+            enc: Vec::new(),
+            recipes,
+        }
+    }
+    fn enc(
+        &self,
+        inst: impl Into<InstSpec>,
+        recipe: EncodingRecipeNumber,
+        bits: u16,
+    ) -> EncodingBuilder {
+        EncodingBuilder::new(inst.into(), recipe, bits)
+    }
+    fn add(&mut self, encoding: EncodingBuilder) {
+        self.enc
+            .push(encoding.build(self.recipes, &mut self.inst_pred_reg));
+    }
+}
+
+// All A64 instructions are 32 bits wide, which doesn't fit in the 16 encoding bits. The functions
+// below compress an instruction word, as found in the Arm Architecture Reference Manual with all
+// its register and immediate fields cleared, into the encoding bits expected by the `put_*`
+// functions in `isa/arm64/binemit.rs`.
+
+/// Most integer instructions, branches and bitwise vector instructions:
+/// `inst[31:21] | (inst[14:10] << 11)`. Bits 20:16 and 9:0 hold register operands and are
+/// dropped.
+fn word_bits(inst: u32) -> u16 {
+    assert_eq!(
+        inst & 0x0000_8000,
+        0,
+        "{:#x} doesn't fit in the encoding bits",
+        inst
+    );
+    ((inst >> 21) | (((inst >> 10) & 0x1f) << 11)) as u16
+}
+
+/// Data processing instructions with three source registers: `inst[31:21] | (inst[15] << 11)`.
+fn rrrr_bits(inst: u32) -> u16 {
+    assert_eq!(inst & 0x001f_7fff, 0, "{:#x} has register fields set", inst);
+    ((inst >> 21) | (((inst >> 15) & 1) << 11)) as u16
+}
+
+/// Scalar floating point instructions: `inst[24:10] | (inst[31] << 15)`.
+fn fp_bits(inst: u32) -> u16 {
+    assert_eq!(
+        inst & 0x7e00_0000,
+        0x1e00_0000,
+        "{:#x} is not a scalar fp inst",
+        inst
+    );
+    (((inst >> 10) & 0x7fff) | ((inst >> 31) << 15)) as u16
+}
+
+/// Loads and stores of a single register: `(size << 3) | (V << 2) | opc`, taken from the unsigned
+/// offset form of the instruction.
+fn ldst_bits(inst: u32) -> u16 {
+    assert_eq!(
+        inst & 0x3b00_0000,
+        0x3900_0000,
+        "{:#x} is not a load/store",
+        inst
+    );
+    let size = inst >> 30;
+    let v = (inst >> 26) & 1;
+    let opc = (inst >> 22) & 3;
+    ((size << 3) | (v << 2) | opc) as u16
+}
+
+/// Loads and stores of a pair of registers: `(opc << 3) | (V << 2) | L`, taken from the signed
+/// offset form of the instruction.
+fn ldst_pair_bits(inst: u32) -> u16 {
+    assert_eq!(
+        inst & 0x3b80_0000,
+        0x2900_0000,
+        "{:#x} is not a load/store pair",
+        inst
+    );
+    let opc = inst >> 30;
+    let v = (inst >> 26) & 1;
+    let l = (inst >> 22) & 1;
+    ((opc << 3) | (v << 2) | l) as u16
+}
+
+pub(crate) fn define<'defs>(
+    shared_defs: &'defs SharedDefinitions,
+    isa_insts: &InstructionGroup,
+    recipes: &'defs RecipeGroup,
+) -> PerCpuModeEncodings<'defs> {
+    // Instructions shorthands.
+    let shared = &shared_defs.instructions;
+
+    let adjust_sp_down_imm = shared.by_name("adjust_sp_down_imm");
+    let adjust_sp_up_imm = shared.by_name("adjust_sp_up_imm");
+    let band = shared.by_name("band");
+    let band_not = shared.by_name("band_not");
+    let bconst = shared.by_name("bconst");
+    let bint = shared.by_name("bint");
+    let bitcast = shared.by_name("bitcast");
+    let bitrev = shared.by_name("bitrev");
+    let bnot = shared.by_name("bnot");
+    let bor = shared.by_name("bor");
+    let bor_not = shared.by_name("bor_not");
+    let br_icmp = shared.by_name("br_icmp");
+    let brff = shared.by_name("brff");
+    let brif = shared.by_name("brif");
+    let brnz = shared.by_name("brnz");
+    let brz = shared.by_name("brz");
+    let bxor = shared.by_name("bxor");
+    let bxor_not = shared.by_name("bxor_not");
+    let call = shared.by_name("call");
+    let call_indirect = shared.by_name("call_indirect");
+    let ceil = shared.by_name("ceil");
+    let cls = shared.by_name("cls");
+    let clz = shared.by_name("clz");
+    let copy = shared.by_name("copy");
+    let copy_nop = shared.by_name("copy_nop");
+    let copy_special = shared.by_name("copy_special");
+    let copy_to_ssa = shared.by_name("copy_to_ssa");
+    let ctz = shared.by_name("ctz");
+    let debugtrap = shared.by_name("debugtrap");
+    let fabs = shared.by_name("fabs");
+    let fadd = shared.by_name("fadd");
+    let fcmp = shared.by_name("fcmp");
+    let fcvt_from_sint = shared.by_name("fcvt_from_sint");
+    let fcvt_from_uint = shared.by_name("fcvt_from_uint");
+    let fcvt_to_sint = shared.by_name("fcvt_to_sint");
+    let fcvt_to_sint_sat = shared.by_name("fcvt_to_sint_sat");
+    let fcvt_to_uint = shared.by_name("fcvt_to_uint");
+    let fcvt_to_uint_sat = shared.by_name("fcvt_to_uint_sat");
+    let fdemote = shared.by_name("fdemote");
+    let fdiv = shared.by_name("fdiv");
+    let ffcmp = shared.by_name("ffcmp");
+    let fill = shared.by_name("fill");
+    let fill_nop = shared.by_name("fill_nop");
+    let floor = shared.by_name("floor");
+    let fma = shared.by_name("fma");
+    let fmax = shared.by_name("fmax");
+    let fmin = shared.by_name("fmin");
+    let fmul = shared.by_name("fmul");
+    let fneg = shared.by_name("fneg");
+    let fpromote = shared.by_name("fpromote");
+    let fsub = shared.by_name("fsub");
+    let func_addr = shared.by_name("func_addr");
+    let iadd = shared.by_name("iadd");
+    let iadd_ifcin = shared.by_name("iadd_ifcin");
+    let iadd_ifcout = shared.by_name("iadd_ifcout");
+    let iadd_imm = shared.by_name("iadd_imm");
+    let icmp = shared.by_name("icmp");
+    let icmp_imm = shared.by_name("icmp_imm");
+    let iconst = shared.by_name("iconst");
+    let ifcmp = shared.by_name("ifcmp");
+    let ifcmp_imm = shared.by_name("ifcmp_imm");
+    let imul = shared.by_name("imul");
+    let indirect_jump_table_br = shared.by_name("indirect_jump_table_br");
+    let ineg = shared.by_name("ineg");
+    let ireduce = shared.by_name("ireduce");
+    let ishl = shared.by_name("ishl");
+    let istore16 = shared.by_name("istore16");
+    let istore32 = shared.by_name("istore32");
+    let istore8 = shared.by_name("istore8");
+    let isub = shared.by_name("isub");
+    let isub_ifbin = shared.by_name("isub_ifbin");
+    let isub_ifbout = shared.by_name("isub_ifbout");
+    let jump = shared.by_name("jump");
+    let jump_table_base = shared.by_name("jump_table_base");
+    let jump_table_entry = shared.by_name("jump_table_entry");
+    let load = shared.by_name("load");
+    let nearest = shared.by_name("nearest");
+    let popcnt = shared.by_name("popcnt");
+    let regfill = shared.by_name("regfill");
+    let regmove = shared.by_name("regmove");
+    let regspill = shared.by_name("regspill");
+    let resumable_trap = shared.by_name("resumable_trap");
+    let return_ = shared.by_name("return");
+    let rotl = shared.by_name("rotl");
+    let rotr = shared.by_name("rotr");
+    let sdiv = shared.by_name("sdiv");
+    let selectif = shared.by_name("selectif");
+    let sextend = shared.by_name("sextend");
+    let sload16 = shared.by_name("sload16");
+    let sload32 = shared.by_name("sload32");
+    let sload8 = shared.by_name("sload8");
+    let smulhi = shared.by_name("smulhi");
+    let spill = shared.by_name("spill");
+    let sqrt = shared.by_name("sqrt");
+    let srem = shared.by_name("srem");
+    let sshr = shared.by_name("sshr");
+    let stack_addr = shared.by_name("stack_addr");
+    let store = shared.by_name("store");
+    let symbol_value = shared.by_name("symbol_value");
+    let trap = shared.by_name("trap");
+    let trapff = shared.by_name("trapff");
+    let trapif = shared.by_name("trapif");
+    let trueff = shared.by_name("trueff");
+    let trueif = shared.by_name("trueif");
+    let trunc = shared.by_name("trunc");
+    let udiv = shared.by_name("udiv");
+    let uextend = shared.by_name("uextend");
+    let uload16 = shared.by_name("uload16");
+    let uload32 = shared.by_name("uload32");
+    let uload8 = shared.by_name("uload8");
+    let umulhi = shared.by_name("umulhi");
+    let urem = shared.by_name("urem");
+    let ushr = shared.by_name("ushr");
+
+    // ARM64 specific instructions.
+    let arm64_pop = isa_insts.by_name("arm64_pop");
+    let arm64_pop_pair = isa_insts.by_name("arm64_pop_pair");
+    let arm64_push = isa_insts.by_name("arm64_push");
+    let arm64_push_pair = isa_insts.by_name("arm64_push_pair");
+
+    // Recipes shorthands, prefixed with r_.
+    let r_adjustsp = recipes.by_name("adjustsp");
+    let r_bconst = recipes.by_name("bconst");
+    let r_bint = recipes.by_name("bint");
+    let r_bricmp = recipes.by_name("bricmp");
+    let r_brff = recipes.by_name("brff");
+    let r_brif = recipes.by_name("brif");
+    let r_brk = recipes.by_name("brk");
+    let r_call = recipes.by_name("call");
+    let r_call_r = recipes.by_name("call_r");
+    let r_cbz = recipes.by_name("cbz");
+    let r_copysp = recipes.by_name("copysp");
+    let r_copytossa = recipes.by_name("copytossa");
+    let r_csel = recipes.by_name("csel");
+    let r_ctz = recipes.by_name("ctz");
+    let r_fcmp = recipes.by_name("fcmp");
+    let r_fcopytossa = recipes.by_name("fcopytossa");
+    let r_fcscc = recipes.by_name("fcscc");
+    let r_fcscc2 = recipes.by_name("fcscc2");
+    let r_fcvt_trap = recipes.by_name("fcvt_trap");
+    let r_ffill = recipes.by_name("ffill");
+    let r_ffillnull = recipes.by_name("ffillnull");
+    let r_fill = recipes.by_name("fill");
+    let r_fillnull = recipes.by_name("fillnull");
+    let r_fld = recipes.by_name("fld");
+    let r_fnaddr = recipes.by_name("fnaddr");
+    let r_fpop = recipes.by_name("fpop");
+    let r_fpop_pair = recipes.by_name("fpop_pair");
+    let r_fpush = recipes.by_name("fpush");
+    let r_fpush_pair = recipes.by_name("fpush_pair");
+    let r_fregfill = recipes.by_name("fregfill");
+    let r_fregspill = recipes.by_name("fregspill");
+    let r_frgp = recipes.by_name("frgp");
+    let r_frmov = recipes.by_name("frmov");
+    let r_frr = recipes.by_name("frr");
+    let r_frrr = recipes.by_name("frrr");
+    let r_frrrr = recipes.by_name("frrrr");
+    let r_fspill = recipes.by_name("fspill");
+    let r_fst = recipes.by_name("fst");
+    let r_gpfr = recipes.by_name("gpfr");
+    let r_gvaddr = recipes.by_name("gvaddr");
+    let r_icscc = recipes.by_name("icscc");
+    let r_icscc_imm = recipes.by_name("icscc_imm");
+    let r_indirect_jmp = recipes.by_name("indirect_jmp");
+    let r_jmp = recipes.by_name("jmp");
+    let r_jt_base = recipes.by_name("jt_base");
+    let r_jt_entry = recipes.by_name("jt_entry");
+    let r_ld = recipes.by_name("ld");
+    let r_movimm = recipes.by_name("movimm");
+    let r_mul = recipes.by_name("mul");
+    let r_mulhi32 = recipes.by_name("mulhi32");
+    let r_null = recipes.by_name("null");
+    let r_pop = recipes.by_name("pop");
+    let r_pop_pair = recipes.by_name("pop_pair");
+    let r_popcnt = recipes.by_name("popcnt");
+    let r_push = recipes.by_name("push");
+    let r_push_pair = recipes.by_name("push_pair");
+    let r_rcmp = recipes.by_name("rcmp");
+    let r_rcmp_imm = recipes.by_name("rcmp_imm");
+    let r_regfill = recipes.by_name("regfill");
+    let r_regspill = recipes.by_name("regspill");
+    let r_rem = recipes.by_name("rem");
+    let r_ret = recipes.by_name("ret");
+    let r_ri = recipes.by_name("ri");
+    let r_rmov = recipes.by_name("rmov");
+    let r_rotl = recipes.by_name("rotl");
+    let r_rr = recipes.by_name("rr");
+    let r_rrr = recipes.by_name("rrr");
+    let r_rrr_fin = recipes.by_name("rrr_fin");
+    let r_rrr_fout = recipes.by_name("rrr_fout");
+    let r_rzr = recipes.by_name("rzr");
+    let r_sdiv = recipes.by_name("sdiv");
+    let r_seti = recipes.by_name("seti");
+    let r_setf = recipes.by_name("setf");
+    let r_spaddr = recipes.by_name("spaddr");
+    let r_spill = recipes.by_name("spill");
+    let r_st = recipes.by_name("st");
+    let r_stacknull = recipes.by_name("stacknull");
+    let r_trap = recipes.by_name("trap");
+    let r_trapff = recipes.by_name("trapff");
+    let r_trapif = recipes.by_name("trapif");
+    let r_udiv = recipes.by_name("udiv");
+    let r_vrrr = recipes.by_name("vrrr");
+
+    // Definitions.
+    let mut e = PerCpuModeEncodings::new(&recipes.recipes);
+
+    // Integer arithmetic and bitwise operations, with the shifted register forms using a shift
+    // amount of zero. The tuples hold the 32-bit and 64-bit variants of each instruction.
+    for &(inst, w, x) in &[
+        (iadd, 0x0b00_0000, 0x8b00_0000),     // add
+        (isub, 0x4b00_0000, 0xcb00_0000),     // sub
+        (band, 0x0a00_0000, 0x8a00_0000),     // and
+        (bor, 0x2a00_0000, 0xaa00_0000),      // orr
+        (bxor, 0x4a00_0000, 0xca00_0000),     // eor
+        (band_not, 0x0a20_0000, 0x8a20_0000), // bic
+        (bor_not, 0x2a20_0000, 0xaa20_0000),  // orn
+        (bxor_not, 0x4a20_0000, 0xca20_0000), // eon
+    ] {
+        e.add(e.enc(inst.bind(I32), r_rrr, word_bits(w)));
+        e.add(e.enc(inst.bind(I64), r_rrr, word_bits(x)));
+    }
+
+    // Booleans are 0 or 1 in a register, which the bitwise operations preserve.
+    for &(inst, w) in &[
+        (band, 0x0a00_0000), // and
+        (bor, 0x2a00_0000),  // orr
+        (bxor, 0x4a00_0000), // eor
+    ] {
+        e.add(e.enc(inst.bind(B1), r_rrr, word_bits(w)));
+    }
+
+    // Negation and bitwise not use the zero register as their first operand.
+    e.add(e.enc(ineg.bind(I32), r_rzr, word_bits(0x4b00_0000)));
+    e.add(e.enc(ineg.bind(I64), r_rzr, word_bits(0xcb00_0000)));
+    e.add(e.enc(bnot.bind(I32), r_rzr, word_bits(0x2a20_0000)));
+    e.add(e.enc(bnot.bind(I64), r_rzr, word_bits(0xaa20_0000)));
+
+    // Add a 12-bit unsigned immediate. Other immediates are materialized in a register.
+    e.add(e.enc(iadd_imm.bind(I32), r_ri, word_bits(0x1100_0000)));
+    e.add(e.enc(iadd_imm.bind(I64), r_ri, word_bits(0x9100_0000)));
+
+    // Dynamic shifts use the shift amount modulo the register width, like the clif instructions.
+    // Only the low bits of the amount register are read, so the amount can have any integer type,
+    // which is needed for the i8 and i16 shifts widened to 32 bits.
+    for &(inst, w, x) in &[
+        (ishl, 0x1ac0_2000, 0x9ac0_2000), // lslv
+        (ushr, 0x1ac0_2400, 0x9ac0_2400), // lsrv
+        (sshr, 0x1ac0_2800, 0x9ac0_2800), // asrv
+        (rotr, 0x1ac0_2c00, 0x9ac0_2c00), // rorv
+    ] {
+        for &amt_ty in &[I8, I16, I32, I64] {
+            e.add(e.enc(inst.bind(I32).bind(amt_ty), r_rrr, word_bits(w)));
+            e.add(e.enc(inst.bind(I64).bind(amt_ty), r_rrr, word_bits(x)));
+        }
+    }
+    for &amt_ty in &[I8, I16, I32, I64] {
+        e.add(e.enc(rotl.bind(I32).bind(amt_ty), r_rotl, word_bits(0x1ac0_2c00)));
+        e.add(e.enc(rotl.bind(I64).bind(amt_ty), r_rotl, word_bits(0x9ac0_2c00)));
+    }
+
+    // Multiplication.
+    e.add(e.enc(imul.bind(I32), r_mul, rrrr_bits(0x1b00_0000))); // madd
+    e.add(e.enc(imul.bind(I64), r_mul, rrrr_bits(0x9b00_0000)));
+    e.add(e.enc(umulhi.bind(I64), r_mul, rrrr_bits(0x9bc0_0000))); // umulh
+    e.add(e.enc(smulhi.bind(I64), r_mul, rrrr_bits(0x9b40_0000))); // smulh
+    e.add(e.enc(umulhi.bind(I32), r_mulhi32, rrrr_bits(0x9ba0_0000))); // umull
+    e.add(e.enc(smulhi.bind(I32), r_mulhi32, rrrr_bits(0x9b20_0000))); // smull
+
+    // Division and remainder, with the checks required by the trapping clif semantics.
+    for &(ty, udiv_w, sdiv_w) in &[
+        (I32, 0x1ac0_0800, 0x1ac0_0c00),
+        (I64, 0x9ac0_0800, 0x9ac0_0c00),
+    ] {
+        e.add(e.enc(udiv.bind(ty), r_udiv, word_bits(udiv_w)));
+        e.add(e.enc(sdiv.bind(ty), r_sdiv, word_bits(sdiv_w)));
+        e.add(e.enc(urem.bind(ty), r_rem, word_bits(udiv_w)));
+        e.add(e.enc(srem.bind(ty), r_rem, word_bits(sdiv_w)));
+    }
+
+    // Bit counting.
+    for &(inst, w, x) in &[
+        (clz, 0x5ac0_1000, 0xdac0_1000),    // clz
+        (cls, 0x5ac0_1400, 0xdac0_1400),    // cls
+        (bitrev, 0x5ac0_0000, 0xdac0_0000), // rbit
+    ] {
+        e.add(e.enc(inst.bind(I32), r_rr, word_bits(w)));
+        e.add(e.enc(inst.bind(I64), r_rr, word_bits(x)));
+    }
+    e.add(e.enc(ctz.bind(I32), r_ctz, word_bits(0x5ac0_0000)));
+    e.add(e.enc(ctz.bind(I64), r_ctz, word_bits(0xdac0_0000)));
+    e.add(e.enc(popcnt.bind(I32), r_popcnt, fp_bits(0x1e27_0000))); // fmov s31, wn
+    e.add(e.enc(popcnt.bind(I64), r_popcnt, fp_bits(0x9e67_0000))); // fmov d31, xn
+
+    // Integer constants. The encoding bits tell whether the destination is a 64-bit register.
+    for &ty in &[I8, I16, I32] {
+        e.add(e.enc(iconst.bind(ty), r_movimm, 0));
+    }
+    e.add(e.enc(iconst.bind(I64), r_movimm, 1));
+    e.add(e.enc(bconst.bind(B1), r_bconst, 0));
+
+    // Extensions and reductions. Writing a 32-bit register clears the upper half of the 64-bit
+    // register, so zero-extending to 64 bits is the same as zero-extending to 32 bits.
+    for &ty in &[I32, I64] {
+        e.add(e.enc(uextend.bind(ty).bind(I8), r_rr, word_bits(0x5300_1c00))); // uxtb
+        e.add(e.enc(uextend.bind(ty).bind(I16), r_rr, word_bits(0x5300_3c00))); // uxth
+    }
+    e.add(e.enc(uextend.bind(I64).bind(I32), r_rzr, word_bits(0x2a00_0000))); // mov wd, wn
+    e.add(e.enc(sextend.bind(I32).bind(I8), r_rr, word_bits(0x1300_1c00))); // sxtb
+    e.add(e.enc(sextend.bind(I32).bind(I16), r_rr, word_bits(0x1300_3c00))); // sxth
+    e.add(e.enc(sextend.bind(I64).bind(I8), r_rr, word_bits(0x9340_1c00))); // sxtb
+    e.add(e.enc(sextend.bind(I64).bind(I16), r_rr, word_bits(0x9340_3c00))); // sxth
+    e.add(e.enc(sextend.bind(I64).bind(I32), r_rr, word_bits(0x9340_7c00))); // sxtw
+
+    e.add(e.enc(ireduce.bind(I8).bind(I16), r_null, 0));
+    e.add(e.enc(ireduce.bind(I8).bind(I32), r_null, 0));
+    e.add(e.enc(ireduce.bind(I16).bind(I32), r_null, 0));
+    e.add(e.enc(ireduce.bind(I8).bind(I64), r_null, 0));
+    e.add(e.enc(ireduce.bind(I16).bind(I64), r_null, 0));
+    e.add(e.enc(ireduce.bind(I32).bind(I64), r_null, 0));
+
+    e.add(e.enc(bint.bind(I32).bind(B1), r_bint, 0));
+    e.add(e.enc(bint.bind(I64).bind(B1), r_bint, 0));
+
+    // Comparisons. `cmp` is an alias of `subs` with the zero register as destination.
+    for &(ty, subs, subs_imm) in &[
+        (I32, 0x6b00_0000, 0x7100_0000),
+        (I64, 0xeb00_0000, 0xf100_0000),
+    ] {
+        e.add(e.enc(icmp.bind(ty), r_icscc, word_bits(subs)));
+        e.add(e.enc(icmp_imm.bind(ty), r_icscc_imm, word_bits(subs_imm)));
+        e.add(e.enc(ifcmp.bind(ty), r_rcmp, word_bits(subs)));
+        e.add(e.enc(ifcmp_imm.bind(ty), r_rcmp_imm, word_bits(subs_imm)));
+        e.add(e.enc(br_icmp.bind(ty), r_bricmp, word_bits(subs)));
+    }
+    e.add(e.enc(trueif, r_seti, 0));
+    e.add(e.enc(trapif, r_trapif, 0));
+    e.add(e.enc(selectif.bind(I32), r_csel, word_bits(0x1a80_0000)));
+    e.add(e.enc(selectif.bind(I64), r_csel, word_bits(0x9a80_0000)));
+
+    // Arithmetic with carry, used for narrowing 128-bit operations.
+    for &(inst, recipe, w, x) in &[
+        (iadd_ifcout, r_rrr_fout, 0x2b00_0000, 0xab00_0000), // adds
+        (isub_ifbout, r_rrr_fout, 0x6b00_0000, 0xeb00_0000), // subs
+        (iadd_ifcin, r_rrr_fin, 0x1a00_0000, 0x9a00_0000),   // adc
+        (isub_ifbin, r_rrr_fin, 0x5a00_0000, 0xda00_0000),   // sbc
+    ] {
+        e.add(e.enc(inst.bind(I32), recipe, word_bits(w)));
+        e.add(e.enc(inst.bind(I64), recipe, word_bits(x)));
+    }
+
+    // Loads and stores.
+    for &(inst, ty, recipe, opcode) in &[
+        (load, I32, r_ld, 0xb940_0000),     // ldr w
+        (load, I64, r_ld, 0xf940_0000),     // ldr x
+        (uload8, I32, r_ld, 0x3940_0000),   // ldrb
+        (uload8, I64, r_ld, 0x3940_0000),   // ldrb
+        (sload8, I32, r_ld, 0x39c0_0000),   // ldrsb w
+        (sload8, I64, r_ld, 0x3980_0000),   // ldrsb x
+        (uload16, I32, r_ld, 0x7940_0000),  // ldrh
+        (uload16, I64, r_ld, 0x7940_0000),  // ldrh
+        (sload16, I32, r_ld, 0x79c0_0000),  // ldrsh w
+        (sload16, I64, r_ld, 0x7980_0000),  // ldrsh x
+        (store, I32, r_st, 0xb900_0000),    // str w
+        (store, I64, r_st, 0xf900_0000),    // str x
+        (istore8, I32, r_st, 0x3900_0000),  // strb
+        (istore8, I64, r_st, 0x3900_0000),  // strb
+        (istore16, I32, r_st, 0x7900_0000), // strh
+        (istore16, I64, r_st, 0x7900_0000), // strh
+    ] {
+        e.add(e.enc(inst.bind(ty).bind(I64), recipe, ldst_bits(opcode)));
+    }
+    // The 32-bit extending loads only have an address type.
+    e.add(e.enc(uload32.bind(I64), r_ld, ldst_bits(0xb940_0000))); // ldr w
+    e.add(e.enc(sload32.bind(I64), r_ld, ldst_bits(0xb980_0000))); // ldrsw
+    e.add(e.enc(istore32.bind(I64).bind(I64), r_st, ldst_bits(0xb900_0000))); // str w
+    e.add(e.enc(load.bind(R64).bind(I64), r_ld, ldst_bits(0xf940_0000))); // ldr x
+    e.add(e.enc(store.bind(R64).bind(I64), r_st, ldst_bits(0xf900_0000))); // str x
+    for &(ty, ldr, str_) in &[
+        (F32, 0xbd40_0000, 0xbd00_0000),
+        (F64, 0xfd40_0000, 0xfd00_0000),
+    ] {
+        e.add(e.enc(load.bind(ty).bind(I64), r_fld, ldst_bits(ldr)));
+        e.add(e.enc(store.bind(ty).bind(I64), r_fst, ldst_bits(str_)));
+    }
+
+    // Spills and fills. Spill slots are at least 4 bytes, so the small types use 32-bit accesses.
+    for &(ty, ldr, str_) in &[
+        (LaneType::from(B1), 0xb940_0000, 0xb900_0000),
+        (I8.into(), 0xb940_0000, 0xb900_0000),
+        (I16.into(), 0xb940_0000, 0xb900_0000),
+        (I32.into(), 0xb940_0000, 0xb900_0000),
+        (I64.into(), 0xf940_0000, 0xf900_0000),
+    ] {
+        e.add(e.enc(spill.bind(ty), r_spill, ldst_bits(str_)));
+        e.add(e.enc(regspill.bind(ty), r_regspill, ldst_bits(str_)));
+        e.add(e.enc(fill.bind(ty), r_fill, ldst_bits(ldr)));
+        e.add(e.enc(regfill.bind(ty), r_regfill, ldst_bits(ldr)));
+        e.add(e.enc(fill_nop.bind(ty), r_fillnull, 0));
+    }
+    e.add(e.enc(spill.bind(R64), r_spill, ldst_bits(0xf900_0000)));
+    e.add(e.enc(regspill.bind(R64), r_regspill, ldst_bits(0xf900_0000)));
+    e.add(e.enc(fill.bind(R64), r_fill, ldst_bits(0xf940_0000)));
+    e.add(e.enc(regfill.bind(R64), r_regfill, ldst_bits(0xf940_0000)));
+    e.add(e.enc(fill_nop.bind(R64), r_fillnull, 0));
+    for &(ty, ldr, str_) in &[
+        (F32, 0xbd40_0000, 0xbd00_0000),
+        (F64, 0xfd40_0000, 0xfd00_0000),
+    ] {
+        e.add(e.enc(spill.bind(ty), r_fspill, ldst_bits(str_)));
+        e.add(e.enc(regspill.bind(ty), r_fregspill, ldst_bits(str_)));
+        e.add(e.enc(fill.bind(ty), r_ffill, ldst_bits(ldr)));
+        e.add(e.enc(regfill.bind(ty), r_fregfill, ldst_bits(ldr)));
+        e.add(e.enc(fill_nop.bind(ty), r_ffillnull, 0));
+    }
+
+    // Register copies, using `mov`, an alias of `orr` with the zero register.
+    for &ty in &[LaneType::from(B1), I8.into(), I16.into(), I32.into()] {
+        e.add(e.enc(copy.bind(ty), r_rzr, word_bits(0x2a00_0000)));
+        e.add(e.enc(regmove.bind(ty), r_rmov, word_bits(0x2a00_0000)));
+        e.add(e.enc(copy_to_ssa.bind(ty), r_copytossa, word_bits(0x2a00_0000)));
+    }
+    e.add(e.enc(copy.bind(I64), r_rzr, word_bits(0xaa00_0000)));
+    e.add(e.enc(regmove.bind(I64), r_rmov, word_bits(0xaa00_0000)));
+    e.add(e.enc(copy_to_ssa.bind(I64), r_copytossa, word_bits(0xaa00_0000)));
+    e.add(e.enc(copy.bind(R64), r_rzr, word_bits(0xaa00_0000)));
+    e.add(e.enc(regmove.bind(R64), r_rmov, word_bits(0xaa00_0000)));
+    e.add(e.enc(copy_to_ssa.bind(R64), r_copytossa, word_bits(0xaa00_0000)));
+    for &(ty, fmov) in &[(F32, 0x1e20_4000), (F64, 0x1e60_4000)] {
+        e.add(e.enc(copy.bind(ty), r_frr, fp_bits(fmov)));
+        e.add(e.enc(regmove.bind(ty), r_frmov, fp_bits(fmov)));
+        e.add(e.enc(copy_to_ssa.bind(ty), r_fcopytossa, fp_bits(fmov)));
+    }
+
+    // Stack-slot-to-the-same-stack-slot copy, which is guaranteed to turn into a no-op.
+    for &ty in &[
+        LaneType::from(B1),
+        I8.into(),
+        I16.into(),
+        I32.into(),
+        I64.into(),
+        F32.into(),
+        F64.into(),
+    ] {
+        e.add(e.enc(copy_nop.bind(ty), r_stacknull, 0));
+    }
+    e.add(e.enc(copy_nop.bind(R64), r_stacknull, 0));
+
+    // Stack manipulation. The stack pointer is encoded as register 31 in these instructions.
+    e.add(e.enc(stack_addr.bind(I64), r_spaddr, word_bits(0x9100_0000)));
+    e.add(e.enc(adjust_sp_down_imm, r_adjustsp, word_bits(0xd100_0000)));
+    e.add(e.enc(adjust_sp_up_imm, r_adjustsp, word_bits(0x9100_0000)));
+    e.add(e.enc(copy_special, r_copysp, word_bits(0x9100_0000)));
+
+    e.add(e.enc(arm64_push.bind(I64), r_push, ldst_bits(0xf900_0000)));
+    e.add(e.enc(arm64_pop.bind(I64), r_pop, ldst_bits(0xf940_0000)));
+    e.add(e.enc(
+        arm64_push_pair.bind(I64),
+        r_push_pair,
+        ldst_pair_bits(0xa900_0000),
+    ));
+    e.add(e.enc(
+        arm64_pop_pair.bind(I64),
+        r_pop_pair,
+        ldst_pair_bits(0xa940_0000),
+    ));
+    e.add(e.enc(arm64_push.bind(F64), r_fpush, ldst_bits(0xfd00_0000)));
+    e.add(e.enc(arm64_pop.bind(F64), r_fpop, ldst_bits(0xfd40_0000)));
+    e.add(e.enc(
+        arm64_push_pair.bind(F64),
+        r_fpush_pair,
+        ldst_pair_bits(0x6d00_0000),
+    ));
+    e.add(e.enc(
+        arm64_pop_pair.bind(F64),
+        r_fpop_pair,
+        ldst_pair_bits(0x6d40_0000),
+    ));
+
+    // Addresses of functions and global values.
+    e.add(e.enc(func_addr.bind(I64), r_fnaddr, 0));
+    e.add(e.enc(symbol_value.bind(I64), r_gvaddr, 0));
+
+    // Control flow.
+    e.add(e.enc(jump, r_jmp, word_bits(0x1400_0000))); // b
+    e.add(e.enc(brif, r_brif, 0));
+    e.add(e.enc(brff, r_brff, 0));
+    for &(ty, cbz, cbnz) in &[
+        (LaneType::from(B1), 0x3400_0000, 0x3500_0000),
+        (I32.into(), 0x3400_0000, 0x3500_0000),
+        (I64.into(), 0xb400_0000, 0xb500_0000),
+    ] {
+        e.add(e.enc(brz.bind(ty), r_cbz, word_bits(cbz)));
+        e.add(e.enc(brnz.bind(ty), r_cbz, word_bits(cbnz)));
+    }
+
+    e.add(e.enc(jump_table_base.bind(I64), r_jt_base, 0));
+    e.add(e.enc(
+        jump_table_entry.bind(I64),
+        r_jt_entry,
+        ldst_bits(0xb980_0000),
+    )); // ldrsw
+    e.add(e.enc(
+        indirect_jump_table_br.bind(I64),
+        r_indirect_jmp,
+        word_bits(0xd61f_0000),
+    )); // br
+
+    e.add(e.enc(call, r_call, word_bits(0x9400_0000))); // bl
+    e.add(e.enc(call_indirect.bind(I64), r_call_r, word_bits(0xd63f_0000))); // blr
+    e.add(e.enc(return_, r_ret, word_bits(0xd65f_0000))); // ret
+
+    e.add(e.enc(trap, r_trap, 0));
+    e.add(e.enc(resumable_trap, r_trap, 0));
+    e.add(e.enc(debugtrap, r_brk, 0));
+
+    // Floating point arithmetic. The single precision instructions are listed, the double
+    // precision variants only differ in the `type` field.
+    let double = |inst: u32| inst | 0x0040_0000;
+    for &(inst, opcode) in &[
+        (fadd, 0x1e20_2800),
+        (fsub, 0x1e20_3800),
+        (fmul, 0x1e20_0800),
+        (fdiv, 0x1e20_1800),
+        (fmax, 0x1e20_4800),
+        (fmin, 0x1e20_5800),
+    ] {
+        e.add(e.enc(inst.bind(F32), r_frrr, fp_bits(opcode)));
+        e.add(e.enc(inst.bind(F64), r_frrr, fp_bits(double(opcode))));
+    }
+    for &(inst, opcode) in &[
+        (sqrt, 0x1e21_c000),
+        (fabs, 0x1e20_c000),
+        (fneg, 0x1e21_4000),
+        (nearest, 0x1e24_4000), // frintn
+        (ceil, 0x1e24_c000),    // frintp
+        (floor, 0x1e25_4000),   // frintm
+        (trunc, 0x1e25_c000),   // frintz
+    ] {
+        e.add(e.enc(inst.bind(F32), r_frr, fp_bits(opcode)));
+        e.add(e.enc(inst.bind(F64), r_frr, fp_bits(double(opcode))));
+    }
+    e.add(e.enc(fma.bind(F32), r_frrrr, fp_bits(0x1f00_0000))); // fmadd
+    e.add(e.enc(fma.bind(F64), r_frrrr, fp_bits(0x1f40_0000)));
+    e.add(e.enc(fpromote.bind(F64).bind(F32), r_frr, fp_bits(0x1e22_c000))); // fcvt
+    e.add(e.enc(fdemote.bind(F32).bind(F64), r_frr, fp_bits(0x1e62_4000))); // fcvt
+
+    // Bitwise operations on floating point values use the 64-bit vector instructions, which
+    // clear the upper half of the destination register.
+    for &(inst, opcode) in &[
+        (band, 0x0e20_1c00),     // and
+        (bor, 0x0ea0_1c00),      // orr
+        (bxor, 0x2e20_1c00),     // eor
+        (band_not, 0x0e60_1c00), // bic
+    ] {
+        e.add(e.enc(inst.bind(F32), r_vrrr, word_bits(opcode)));
+        e.add(e.enc(inst.bind(F64), r_vrrr, word_bits(opcode)));
+    }
+
+    // Floating point comparisons.
+    for &(ty, opcode) in &[(F32, 0x1e20_2000), (F64, 0x1e60_2000)] {
+        e.add(e.enc(fcmp.bind(ty), r_fcscc, fp_bits(opcode)));
+        e.add(e.enc(fcmp.bind(ty), r_fcscc2, fp_bits(opcode)));
+        e.add(e.enc(ffcmp.bind(ty), r_fcmp, fp_bits(opcode)));
+    }
+    e.add(e.enc(trueff, r_setf, 0));
+    e.add(e.enc(trapff, r_trapff, 0));
+
+    // Moves between register banks.
+    e.add(e.enc(bitcast.bind(I32).bind(F32), r_frgp, fp_bits(0x1e26_0000))); // fmov w, s
+    e.add(e.enc(bitcast.bind(I64).bind(F64), r_frgp, fp_bits(0x9e66_0000))); // fmov x, d
+    e.add(e.enc(bitcast.bind(F32).bind(I32), r_gpfr, fp_bits(0x1e27_0000))); // fmov s, w
+    e.add(e.enc(bitcast.bind(F64).bind(I64), r_gpfr, fp_bits(0x9e67_0000))); // fmov d, x
+
+    // Conversions between integers and floating point values. The `sf` bit selects the width of
+    // the integer register and the `type` field the precision of the floating point register.
+    let int_width = |ty| if ty == I64 { 0x8000_0000 } else { 0 };
+    let fp_type = |ty| if ty == F64 { 0x0040_0000 } else { 0 };
+    for &fty in &[F32, F64] {
+        for &ity in &[I32, I64] {
+            let ty_bits = int_width(ity) | fp_type(fty);
+            let scvtf = 0x1e22_0000 | ty_bits;
+            let ucvtf = 0x1e23_0000 | ty_bits;
+            let fcvtzs = 0x1e38_0000 | ty_bits;
+            let fcvtzu = 0x1e39_0000 | ty_bits;
+            e.add(e.enc(fcvt_from_sint.bind(fty).bind(ity), r_gpfr, fp_bits(scvtf)));
+            e.add(e.enc(fcvt_from_uint.bind(fty).bind(ity), r_gpfr, fp_bits(ucvtf)));
+            // The conversion instructions saturate and convert NaN to 0.
+            e.add(e.enc(
+                fcvt_to_sint_sat.bind(ity).bind(fty),
+                r_frgp,
+                fp_bits(fcvtzs),
+            ));
+            e.add(e.enc(
+                fcvt_to_uint_sat.bind(ity).bind(fty),
+                r_frgp,
+                fp_bits(fcvtzu),
+            ));
+            e.add(e.enc(
+                fcvt_to_sint.bind(ity).bind(fty),
+                r_fcvt_trap,
+                fp_bits(fcvtzs),
+            ));
+            e.add(e.enc(
+                fcvt_to_uint.bind(ity).bind(fty),
+                r_fcvt_trap,
+                fp_bits(fcvtzu),
+            ));
+        }
+    }
+
+    e
+}
@@ -0,0 +1,93 @@
+#![allow(non_snake_case)]
+
+use crate::cdsl::instructions::{
+    AllInstructions, InstructionBuilder as Inst, InstructionGroup, InstructionGroupBuilder,
+};
+use crate::cdsl::operands::Operand;
+use crate::cdsl::typevar::{TypeSetBuilder, TypeVar};
+
+use crate::shared::formats::Formats;
+
+pub(crate) fn define(
+    mut all_instructions: &mut AllInstructions,
+    formats: &Formats,
+) -> InstructionGroup {
+    let mut ig = InstructionGroupBuilder::new(&mut all_instructions);
+
+    let Reg64 = &TypeVar::new(
+        "Reg64",
+        "A 64-bit integer or floating point register value",
+        TypeSetBuilder::new().ints(64..64).floats(64..64).build(),
+    );
+    let x = &Operand::new("x", Reg64);
+    let y = &Operand::new("y", Reg64);
+
+    ig.push(
+        Inst::new(
+            "arm64_push",
+            r#"
+    Pushes a value onto the stack.
+
+    Decrements the stack pointer by 16 and stores the specified value at the
+    new top of the stack. The stack pointer must stay 16-byte aligned, so the
+    upper half of the slot is left unused.
+    "#,
+            &formats.unary,
+        )
+        .operands_in(vec![x])
+        .other_side_effects(true)
+        .can_store(true),
+    );
+
+    ig.push(
+        Inst::new(
+            "arm64_pop",
+            r#"
+    Pops a value from the stack.
+
+    Loads a value from the top of the stack and then increments the stack
+    pointer by 16.
+    "#,
+            &formats.nullary,
+        )
+        .operands_out(vec![x])
+        .other_side_effects(true)
+        .can_load(true),
+    );
+
+    ig.push(
+        Inst::new(
+            "arm64_push_pair",
+            r#"
+    Pushes a pair of values onto the stack.
+
+    Decrements the stack pointer by 16 and stores `x` at the new top of the
+    stack and `y` right above it. This is the `stp x, y, [sp, #-16]!`
+    instruction.
+    "#,
+            &formats.binary,
+        )
+        .operands_in(vec![x, y])
+        .other_side_effects(true)
+        .can_store(true),
+    );
+
+    ig.push(
+        Inst::new(
+            "arm64_pop_pair",
+            r#"
+    Pops a pair of values from the stack.
+
+    Loads `x` from the top of the stack and `y` from right above it, then
+    increments the stack pointer by 16. This is the `ldp x, y, [sp], #16`
+    instruction.
+    "#,
+            &formats.nullary,
+        )
+        .operands_out(vec![x, y])
+        .other_side_effects(true)
+        .can_load(true),
+    );
+
+    ig.build()
+}
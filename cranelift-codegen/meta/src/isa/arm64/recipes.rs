@@ -0,0 +1,1032 @@
+use std::collections::HashMap;
+
+use crate::cdsl::ast::Literal;
+use crate::cdsl::formats::InstructionFormat;
+use crate::cdsl::instructions::InstructionPredicate;
+use crate::cdsl::recipes::{
+    EncodingRecipeBuilder, EncodingRecipeNumber, OperandConstraint, Recipes, Register, Stack,
+};
+use crate::cdsl::regs::IsaRegs;
+use crate::shared::Definitions as SharedDefinitions;
+
+/// An helper to create recipes and use them when defining the ARM64 encodings.
+pub(crate) struct RecipeGroup {
+    /// The actual list of recipes explicitly created in this file.
+    pub recipes: Recipes,
+
+    /// Provides fast lookup from a name to an encoding recipe.
+    name_to_recipe: HashMap<String, EncodingRecipeNumber>,
+}
+
+impl RecipeGroup {
+    fn new() -> Self {
+        Self {
+            recipes: Recipes::new(),
+            name_to_recipe: HashMap::new(),
+        }
+    }
+
+    fn push(&mut self, builder: EncodingRecipeBuilder) {
+        assert!(
+            self.name_to_recipe.get(&builder.name).is_none(),
+            format!("arm64 recipe '{}' created twice", builder.name)
+        );
+        let name = builder.name.clone();
+        let number = self.recipes.push(builder.build());
+        self.name_to_recipe.insert(name, number);
+    }
+
+    pub fn by_name(&self, name: &str) -> EncodingRecipeNumber {
+        *self
+            .name_to_recipe
+            .get(name)
+            .unwrap_or_else(|| panic!("unknown arm64 recipe name {}", name))
+    }
+
+    pub fn collect(self) -> Recipes {
+        self.recipes
+    }
+}
+
+/// Returns a predicate that checks that the `cond` field of `format` is one of the float
+/// condition codes that map onto a single A64 condition code after an `fcmp`.
+///
+/// The remaining two, `one` and `ueq`, need to test two conditions.
+fn single_floatcc_predicate(
+    shared_defs: &SharedDefinitions,
+    format: &InstructionFormat,
+) -> InstructionPredicate {
+    let floatcc = &shared_defs.imm.floatcc;
+    [
+        "ord", "uno", "eq", "ne", "lt", "le", "gt", "ge", "ult", "ule", "ugt", "uge",
+    ]
+    .iter()
+    .map(|name| Literal::enumerator_for(floatcc, name))
+    .fold(InstructionPredicate::new(), |pred, literal| {
+        pred.or(InstructionPredicate::new_is_field_equal(
+            format,
+            "cond",
+            literal.to_rust_code(),
+        ))
+    })
+}
+
+/// Returns a predicate that checks that the `cond` field of `format` is `one` or `ueq`.
+fn double_floatcc_predicate(
+    shared_defs: &SharedDefinitions,
+    format: &InstructionFormat,
+) -> InstructionPredicate {
+    let floatcc = &shared_defs.imm.floatcc;
+    ["one", "ueq"]
+        .iter()
+        .map(|name| Literal::enumerator_for(floatcc, name))
+        .fold(InstructionPredicate::new(), |pred, literal| {
+            pred.or(InstructionPredicate::new_is_field_equal(
+                format,
+                "cond",
+                literal.to_rust_code(),
+            ))
+        })
+}
+
+// The encoding bits of the recipes below are a 16-bit compression of the 32-bit A64 instruction
+// word with all the register and immediate fields cleared. The compression depends on the
+// instruction class, and each `put_*` function in `isa/arm64/binemit.rs` documents the one it
+// expects. The `*_bits` functions in `encodings.rs` compute them from the full instruction words
+// found in the Arm Architecture Reference Manual.
+//
+// Registers x16 and x17 (the intra-procedure-call scratch registers IP0 and IP1) and v31 are not
+// allocatable, so recipes that need a temporary use them freely.
+
+pub(crate) fn define(shared_defs: &SharedDefinitions, regs: &IsaRegs) -> RecipeGroup {
+    let formats = &shared_defs.formats;
+
+    // Register classes shorthands.
+    let gpr = regs.class_by_name("GPR");
+    let fpr = regs.class_by_name("FPR");
+    let flag = regs.class_by_name("FLAG");
+
+    // Operand constraints shorthands.
+    let reg_nzcv = Register::new(flag, regs.regunit_by_name(flag, "nzcv"));
+    let stack_gpr = Stack::new(gpr);
+    let stack_fpr = Stack::new(fpr);
+
+    // Definitions.
+    let mut recipes = RecipeGroup::new();
+
+    // Integer data processing with three register operands.
+    recipes.push(
+        EncodingRecipeBuilder::new("rrr", &formats.binary, 4)
+            .operands_in(vec![gpr, gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit("put_rrr(bits, in_reg0, in_reg1, out_reg0, sink);"),
+    );
+
+    // Integer data processing with one source register, such as `clz` or the extensions which are
+    // aliases of `ubfm` and `sbfm`.
+    recipes.push(
+        EncodingRecipeBuilder::new("rr", &formats.unary, 4)
+            .operands_in(vec![gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit("put_rrr(bits, in_reg0, 0, out_reg0, sink);"),
+    );
+
+    // Same as `rrr`, but the first operand is the zero register. Used for `mov`, `mvn` and `neg`.
+    recipes.push(
+        EncodingRecipeBuilder::new("rzr", &formats.unary, 4)
+            .operands_in(vec![gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit("put_rrr(bits, RU::x31 as RegUnit, in_reg0, out_reg0, sink);"),
+    );
+
+    // Same as `rzr` for a register move.
+    recipes.push(
+        EncodingRecipeBuilder::new("rmov", &formats.reg_move, 4)
+            .operands_in(vec![gpr])
+            .clobbers_flags(false)
+            .emit("put_rrr(bits, RU::x31 as RegUnit, src, dst, sink);"),
+    );
+
+    // Same for copy-to-SSA -- GPR regmove.
+    recipes.push(
+        EncodingRecipeBuilder::new("copytossa", &formats.copy_to_ssa, 4)
+            // No operands_in to mention, because a source register is specified directly.
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit("put_rrr(bits, RU::x31 as RegUnit, src, out_reg0, sink);"),
+    );
+
+    // Copy the stack pointer, or from it. This is `add dst, src, #0`.
+    recipes.push(
+        EncodingRecipeBuilder::new("copysp", &formats.copy_special, 4)
+            .clobbers_flags(false)
+            .emit("put_addsub_imm(bits, src, 0, dst, sink);"),
+    );
+
+    // Count trailing zeros: reverse the bits, then count leading zeros.
+    recipes.push(
+        EncodingRecipeBuilder::new("ctz", &formats.unary, 8)
+            .operands_in(vec![gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    // `bits` encodes `rbit`, and `clz` only differs in the opcode field.
+                    put_rrr(bits, in_reg0, 0, out_reg0, sink);
+                    put_rrr(bits | (0b00100 << 11), out_reg0, 0, out_reg0, sink);
+                "#,
+            ),
+    );
+
+    // Population count, which only exists as a vector instruction.
+    recipes.push(
+        EncodingRecipeBuilder::new("popcnt", &formats.unary, 16)
+            .operands_in(vec![gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    // `bits` encodes the `fmov` from the general purpose register to v31.
+                    put_fp(bits, in_reg0, 0, 0, RU::v31 as RegUnit, sink);
+                    put_popcnt_v31(out_reg0, sink);
+                "#,
+            ),
+    );
+
+    // Rotate left, which is a rotate right by the negated amount.
+    recipes.push(
+        EncodingRecipeBuilder::new("rotl", &formats.binary, 8)
+            .operands_in(vec![gpr, gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    // neg x16, in_reg1
+                    put_rrr(0x658, RU::x31 as RegUnit, in_reg1, RU::x16 as RegUnit, sink);
+                    put_rrr(bits, in_reg0, RU::x16 as RegUnit, out_reg0, sink);
+                "#,
+            ),
+    );
+
+    // Integer data processing with three source registers, used for multiplications. The addend
+    // is the zero register.
+    recipes.push(
+        EncodingRecipeBuilder::new("mul", &formats.binary, 4)
+            .operands_in(vec![gpr, gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit("put_rrrr(bits, in_reg0, in_reg1, RU::x31 as RegUnit, out_reg0, sink);"),
+    );
+
+    // High half of a 32-bit multiplication: a widening multiplication followed by a shift.
+    recipes.push(
+        EncodingRecipeBuilder::new("mulhi32", &formats.binary, 8)
+            .operands_in(vec![gpr, gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    put_rrrr(bits, in_reg0, in_reg1, RU::x31 as RegUnit, out_reg0, sink);
+                    put_lsr_imm(32, out_reg0, out_reg0, sink);
+                "#,
+            ),
+    );
+
+    // Unsigned division, which traps on a zero divisor.
+    recipes.push(
+        EncodingRecipeBuilder::new("udiv", &formats.binary, 12)
+            .operands_in(vec![gpr, gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    put_div_by_zero_check(bits, in_reg1, func.srclocs[inst], sink);
+                    put_rrr(bits, in_reg0, in_reg1, out_reg0, sink);
+                "#,
+            ),
+    );
+
+    // Signed division, which also traps when the quotient overflows.
+    recipes.push(
+        EncodingRecipeBuilder::new("sdiv", &formats.binary, 28)
+            .operands_in(vec![gpr, gpr])
+            .operands_out(vec![gpr])
+            .emit(
+                r#"
+                    put_div_by_zero_check(bits, in_reg1, func.srclocs[inst], sink);
+                    put_sdiv_overflow_check(bits, in_reg0, in_reg1, func.srclocs[inst], sink);
+                    put_rrr(bits, in_reg0, in_reg1, out_reg0, sink);
+                "#,
+            ),
+    );
+
+    // Remainder: divide into x16, then multiply back and subtract.
+    recipes.push(
+        EncodingRecipeBuilder::new("rem", &formats.binary, 16)
+            .operands_in(vec![gpr, gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    put_div_by_zero_check(bits, in_reg1, func.srclocs[inst], sink);
+                    put_rrr(bits, in_reg0, in_reg1, RU::x16 as RegUnit, sink);
+                    // msub out_reg0, x16, in_reg1, in_reg0
+                    put_rrrr(
+                        (bits & 0x400) | 0x0d8 | (1 << 11),
+                        RU::x16 as RegUnit,
+                        in_reg1,
+                        in_reg0,
+                        out_reg0,
+                        sink,
+                    );
+                "#,
+            ),
+    );
+
+    // Add/subtract with a 12-bit unsigned immediate.
+    recipes.push(
+        EncodingRecipeBuilder::new("ri", &formats.binary_imm, 4)
+            .operands_in(vec![gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .inst_predicate(InstructionPredicate::new_is_unsigned_int(
+                &*formats.binary_imm,
+                "imm",
+                12,
+                0,
+            ))
+            .emit("put_addsub_imm(bits, in_reg0, imm.into(), out_reg0, sink);"),
+    );
+
+    // Integer constants, materialized by a `movz` or `movn` followed by up to three `movk`.
+    recipes.push(
+        EncodingRecipeBuilder::new("movimm", &formats.unary_imm, 4)
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .compute_size("size_for_movimm")
+            .emit("put_movimm(bits != 0, out_reg0, imm.into(), sink);"),
+    );
+
+    // Boolean constants.
+    recipes.push(
+        EncodingRecipeBuilder::new("bconst", &formats.unary_bool, 4)
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit("put_movimm(false, out_reg0, if imm { 1 } else { 0 }, sink);"),
+    );
+
+    // Convert a boolean to an integer: `and wd, wn, #1`.
+    recipes.push(
+        EncodingRecipeBuilder::new("bint", &formats.unary, 4)
+            .operands_in(vec![gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit("put_and_one(in_reg0, out_reg0, sink);"),
+    );
+
+    // No-op used for reductions, which only look at the low bits of a register.
+    recipes.push(
+        EncodingRecipeBuilder::new("null", &formats.unary, 0)
+            .operands_in(vec![gpr])
+            .operands_out(vec![0])
+            .clobbers_flags(false)
+            .emit(""),
+    );
+
+    // Compare two registers and set the flags: `subs xzr, xn, xm`.
+    recipes.push(
+        EncodingRecipeBuilder::new("rcmp", &formats.binary, 4)
+            .operands_in(vec![gpr, gpr])
+            .operands_out(vec![reg_nzcv])
+            .emit("put_rrr(bits, in_reg0, in_reg1, RU::x31 as RegUnit, sink);"),
+    );
+
+    // Compare a register with a 12-bit unsigned immediate and set the flags.
+    recipes.push(
+        EncodingRecipeBuilder::new("rcmp_imm", &formats.binary_imm, 4)
+            .operands_in(vec![gpr])
+            .operands_out(vec![reg_nzcv])
+            .inst_predicate(InstructionPredicate::new_is_unsigned_int(
+                &*formats.binary_imm,
+                "imm",
+                12,
+                0,
+            ))
+            .emit("put_addsub_imm(bits, in_reg0, imm.into(), RU::x31 as RegUnit, sink);"),
+    );
+
+    // Compare two registers and materialize the condition as a boolean with `cset`.
+    recipes.push(
+        EncodingRecipeBuilder::new("icscc", &formats.int_compare, 8)
+            .operands_in(vec![gpr, gpr])
+            .operands_out(vec![gpr])
+            .emit(
+                r#"
+                    put_rrr(bits, in_reg0, in_reg1, RU::x31 as RegUnit, sink);
+                    put_cset(icc2cond(cond), out_reg0, sink);
+                "#,
+            ),
+    );
+
+    // Same with a 12-bit unsigned immediate.
+    recipes.push(
+        EncodingRecipeBuilder::new("icscc_imm", &formats.int_compare_imm, 8)
+            .operands_in(vec![gpr])
+            .operands_out(vec![gpr])
+            .inst_predicate(InstructionPredicate::new_is_unsigned_int(
+                &*formats.int_compare_imm,
+                "imm",
+                12,
+                0,
+            ))
+            .emit(
+                r#"
+                    put_addsub_imm(bits, in_reg0, imm.into(), RU::x31 as RegUnit, sink);
+                    put_cset(icc2cond(cond), out_reg0, sink);
+                "#,
+            ),
+    );
+
+    // Test integer flags and set a register.
+    recipes.push(
+        EncodingRecipeBuilder::new("seti", &formats.int_cond, 4)
+            .operands_in(vec![reg_nzcv])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit("put_cset(icc2cond(cond), out_reg0, sink);"),
+    );
+
+    // Conditional select on integer flags.
+    recipes.push(
+        EncodingRecipeBuilder::new("csel", &formats.int_select, 4)
+            .operands_in(vec![
+                OperandConstraint::FixedReg(reg_nzcv),
+                OperandConstraint::RegClass(gpr),
+                OperandConstraint::RegClass(gpr),
+            ])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit("put_csel(bits, in_reg1, in_reg2, icc2cond(cond), out_reg0, sink);"),
+    );
+
+    // Arithmetic producing the carry flag: `adds` and `subs`.
+    recipes.push(
+        EncodingRecipeBuilder::new("rrr_fout", &formats.binary, 4)
+            .operands_in(vec![gpr, gpr])
+            .operands_out(vec![
+                OperandConstraint::RegClass(gpr),
+                OperandConstraint::FixedReg(reg_nzcv),
+            ])
+            .emit("put_rrr(bits, in_reg0, in_reg1, out_reg0, sink);"),
+    );
+
+    // Arithmetic consuming the carry flag: `adc` and `sbc`.
+    recipes.push(
+        EncodingRecipeBuilder::new("rrr_fin", &formats.ternary, 4)
+            .operands_in(vec![
+                OperandConstraint::RegClass(gpr),
+                OperandConstraint::RegClass(gpr),
+                OperandConstraint::FixedReg(reg_nzcv),
+            ])
+            .operands_out(vec![gpr])
+            .emit("put_rrr(bits, in_reg0, in_reg1, out_reg0, sink);"),
+    );
+
+    // Unconditional trap, encoded as a permanently undefined instruction.
+    recipes.push(
+        EncodingRecipeBuilder::new("trap", &formats.trap, 4)
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    sink.trap(code, func.srclocs[inst]);
+                    sink.put4(UDF);
+                "#,
+            ),
+    );
+
+    // Breakpoint: `brk #0`.
+    recipes.push(
+        EncodingRecipeBuilder::new("brk", &formats.nullary, 4)
+            .clobbers_flags(false)
+            .emit("sink.put4(BRK);"),
+    );
+
+    // Conditional branch over an undefined instruction.
+    recipes.push(
+        EncodingRecipeBuilder::new("trapif", &formats.int_cond_trap, 8)
+            .operands_in(vec![reg_nzcv])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    put_bcond(icc2cond(cond.inverse()), 8, sink);
+                    sink.trap(code, func.srclocs[inst]);
+                    sink.put4(UDF);
+                "#,
+            ),
+    );
+
+    recipes.push(
+        EncodingRecipeBuilder::new("trapff", &formats.float_cond_trap, 8)
+            .operands_in(vec![reg_nzcv])
+            .clobbers_flags(false)
+            .inst_predicate(single_floatcc_predicate(
+                shared_defs,
+                &*formats.float_cond_trap,
+            ))
+            .emit(
+                r#"
+                    put_bcond(fcc2cond(cond.inverse()), 8, sink);
+                    sink.trap(code, func.srclocs[inst]);
+                    sink.put4(UDF);
+                "#,
+            ),
+    );
+
+    // Unconditional branch with a 26-bit word offset.
+    recipes.push(
+        EncodingRecipeBuilder::new("jmp", &formats.jump, 4)
+            .branch_range((0, 28))
+            .clobbers_flags(false)
+            .emit("put_b(bits, branch_disp(destination, func, sink), sink);"),
+    );
+
+    // Conditional branch on integer flags.
+    recipes.push(
+        EncodingRecipeBuilder::new("brif", &formats.branch_int, 4)
+            .operands_in(vec![reg_nzcv])
+            .branch_range((0, 21))
+            .clobbers_flags(false)
+            .emit("put_bcond(icc2cond(cond), branch_disp(destination, func, sink), sink);"),
+    );
+
+    // Conditional branch on float flags.
+    recipes.push(
+        EncodingRecipeBuilder::new("brff", &formats.branch_float, 4)
+            .operands_in(vec![reg_nzcv])
+            .branch_range((0, 21))
+            .clobbers_flags(false)
+            .inst_predicate(single_floatcc_predicate(
+                shared_defs,
+                &*formats.branch_float,
+            ))
+            .emit("put_bcond(fcc2cond(cond), branch_disp(destination, func, sink), sink);"),
+    );
+
+    // Compare two registers and branch.
+    recipes.push(
+        EncodingRecipeBuilder::new("bricmp", &formats.branch_icmp, 8)
+            .operands_in(vec![gpr, gpr])
+            .branch_range((4, 21))
+            .emit(
+                r#"
+                    put_rrr(bits, in_reg0, in_reg1, RU::x31 as RegUnit, sink);
+                    put_bcond(icc2cond(cond), branch_disp(destination, func, sink), sink);
+                "#,
+            ),
+    );
+
+    // Compare a register with zero and branch: `cbz` and `cbnz`.
+    recipes.push(
+        EncodingRecipeBuilder::new("cbz", &formats.branch, 4)
+            .operands_in(vec![gpr])
+            .branch_range((0, 21))
+            .clobbers_flags(false)
+            .emit("put_cbz(bits, in_reg0, branch_disp(destination, func, sink), sink);"),
+    );
+
+    // Direct call, with a 26-bit word offset to be filled in by the linker.
+    recipes.push(
+        EncodingRecipeBuilder::new("call", &formats.call, 4)
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    sink.reloc_external(Reloc::Arm64Call,
+                                        &func.dfg.ext_funcs[func_ref].name,
+                                        0);
+                    put_b(bits, 0, sink);
+                "#,
+            ),
+    );
+
+    // Indirect call: `blr`.
+    recipes.push(
+        EncodingRecipeBuilder::new("call_r", &formats.call_indirect, 4)
+            .operands_in(vec![gpr])
+            .clobbers_flags(false)
+            .emit("put_branch_reg(bits, in_reg0, sink);"),
+    );
+
+    // Return to the address in the link register.
+    recipes.push(
+        EncodingRecipeBuilder::new("ret", &formats.multiary, 4)
+            .clobbers_flags(false)
+            .emit("put_branch_reg(bits, RU::x30 as RegUnit, sink);"),
+    );
+
+    // Indirect jump: `br`.
+    recipes.push(
+        EncodingRecipeBuilder::new("indirect_jmp", &formats.indirect_jump, 4)
+            .operands_in(vec![gpr])
+            .clobbers_flags(false)
+            .emit("put_branch_reg(bits, in_reg0, sink);"),
+    );
+
+    // Address of a jump table, which is emitted right after the function body: `adr`.
+    recipes.push(
+        EncodingRecipeBuilder::new("jt_base", &formats.branch_table_base, 4)
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    let disp = func.jt_offsets[table].wrapping_sub(sink.offset()) as i32;
+                    put_adr(out_reg0, disp, sink);
+                "#,
+            ),
+    );
+
+    // Load a 32-bit jump table entry and sign-extend it: `ldrsw xd, [xn, xm, lsl #2]`.
+    recipes.push(
+        EncodingRecipeBuilder::new("jt_entry", &formats.branch_table_entry, 4)
+            .operands_in(vec![gpr, gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .inst_predicate(InstructionPredicate::new_is_field_equal(
+                &*formats.branch_table_entry,
+                "imm",
+                "4".into(),
+            ))
+            .emit("put_ldst_reg(bits, out_reg0, in_reg1, in_reg0, true, sink);"),
+    );
+
+    // Address of a function, loaded from a literal pool placed inline.
+    recipes.push(
+        EncodingRecipeBuilder::new("fnaddr", &formats.func_addr, 16)
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    put_ldr_literal_skip(out_reg0, sink);
+                    sink.reloc_external(Reloc::Abs8,
+                                        &func.dfg.ext_funcs[func_ref].name,
+                                        0);
+                    sink.put8(0);
+                "#,
+            ),
+    );
+
+    // Address of a global value, loaded from a literal pool placed inline.
+    recipes.push(
+        EncodingRecipeBuilder::new("gvaddr", &formats.unary_global_value, 16)
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    put_ldr_literal_skip(out_reg0, sink);
+                    sink.reloc_external(Reloc::Abs8,
+                                        &func.global_values[global_value].symbol_name(),
+                                        0);
+                    sink.put8(0);
+                "#,
+            ),
+    );
+
+    // Address of a stack slot.
+    recipes.push(
+        EncodingRecipeBuilder::new("spaddr", &formats.stack_load, 4)
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .compute_size("size_for_spaddr")
+            .emit(
+                r#"
+                    let sp = StackRef::sp(stack_slot, &func.stack_slots);
+                    let offset: i32 = offset.into();
+                    put_add_large_imm(
+                        bits,
+                        RU::x31 as RegUnit,
+                        i64::from(sp.offset.checked_add(offset).unwrap()),
+                        out_reg0,
+                        sink,
+                    );
+                "#,
+            ),
+    );
+
+    // Adjust the stack pointer by an immediate.
+    recipes.push(
+        EncodingRecipeBuilder::new("adjustsp", &formats.unary_imm, 4)
+            .clobbers_flags(false)
+            .compute_size("size_for_adjustsp")
+            .emit(
+                r#"
+                    put_add_large_imm(
+                        bits,
+                        RU::x31 as RegUnit,
+                        imm.into(),
+                        RU::x31 as RegUnit,
+                        sink,
+                    );
+                "#,
+            ),
+    );
+
+    // Push and pop, used for saving callee-saved registers in the prologue and epilogue.
+    recipes.push(
+        EncodingRecipeBuilder::new("push", &formats.unary, 4)
+            .operands_in(vec![gpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    sink.trap(TrapCode::StackOverflow, func.srclocs[inst]);
+                    put_ldst_pre(bits, in_reg0, -16, sink);
+                "#,
+            ),
+    );
+
+    recipes.push(
+        EncodingRecipeBuilder::new("pop", &formats.nullary, 4)
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit("put_ldst_post(bits, out_reg0, 16, sink);"),
+    );
+
+    recipes.push(
+        EncodingRecipeBuilder::new("push_pair", &formats.binary, 4)
+            .operands_in(vec![gpr, gpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    sink.trap(TrapCode::StackOverflow, func.srclocs[inst]);
+                    put_ldst_pair_pre(bits, in_reg0, in_reg1, -16, sink);
+                "#,
+            ),
+    );
+
+    recipes.push(
+        EncodingRecipeBuilder::new("pop_pair", &formats.nullary, 4)
+            .operands_out(vec![gpr, gpr])
+            .clobbers_flags(false)
+            .emit("put_ldst_pair_post(bits, out_reg0, out_reg1, 16, sink);"),
+    );
+
+    recipes.push(
+        EncodingRecipeBuilder::new("fpush", &formats.unary, 4)
+            .operands_in(vec![fpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    sink.trap(TrapCode::StackOverflow, func.srclocs[inst]);
+                    put_ldst_pre(bits, in_reg0, -16, sink);
+                "#,
+            ),
+    );
+
+    recipes.push(
+        EncodingRecipeBuilder::new("fpop", &formats.nullary, 4)
+            .operands_out(vec![fpr])
+            .clobbers_flags(false)
+            .emit("put_ldst_post(bits, out_reg0, 16, sink);"),
+    );
+
+    recipes.push(
+        EncodingRecipeBuilder::new("fpush_pair", &formats.binary, 4)
+            .operands_in(vec![fpr, fpr])
+            .clobbers_flags(false)
+            .emit(
+                r#"
+                    sink.trap(TrapCode::StackOverflow, func.srclocs[inst]);
+                    put_ldst_pair_pre(bits, in_reg0, in_reg1, -16, sink);
+                "#,
+            ),
+    );
+
+    recipes.push(
+        EncodingRecipeBuilder::new("fpop_pair", &formats.nullary, 4)
+            .operands_out(vec![fpr, fpr])
+            .clobbers_flags(false)
+            .emit("put_ldst_pair_post(bits, out_reg0, out_reg1, 16, sink);"),
+    );
+
+    // Loads and stores with a base register and an immediate offset. Offsets that don't fit the
+    // scaled 12-bit or the unscaled 9-bit immediate forms are materialized in x16.
+    for &(name, class) in &[("ld", gpr), ("fld", fpr)] {
+        recipes.push(
+            EncodingRecipeBuilder::new(name, &formats.load, 4)
+                .operands_in(vec![gpr])
+                .operands_out(vec![class])
+                .clobbers_flags(false)
+                .compute_size("size_for_load")
+                .emit(
+                    r#"
+                        if !flags.notrap() {
+                            sink.trap(TrapCode::HeapOutOfBounds, func.srclocs[inst]);
+                        }
+                        put_ldst(bits, out_reg0, in_reg0, offset.into(), sink);
+                    "#,
+                ),
+        );
+    }
+
+    for &(name, class) in &[("st", gpr), ("fst", fpr)] {
+        recipes.push(
+            EncodingRecipeBuilder::new(name, &formats.store, 4)
+                .operands_in(vec![class, gpr])
+                .clobbers_flags(false)
+                .compute_size("size_for_store")
+                .emit(
+                    r#"
+                        if !flags.notrap() {
+                            sink.trap(TrapCode::HeapOutOfBounds, func.srclocs[inst]);
+                        }
+                        put_ldst(bits, in_reg0, in_reg1, offset.into(), sink);
+                    "#,
+                ),
+        );
+    }
+
+    // Spills and fills, addressed relative to the stack pointer.
+    for &(name, class, stack) in &[("spill", gpr, stack_gpr), ("fspill", fpr, stack_fpr)] {
+        recipes.push(
+            EncodingRecipeBuilder::new(name, &formats.unary, 4)
+                .operands_in(vec![class])
+                .operands_out(vec![stack])
+                .clobbers_flags(false)
+                .compute_size("size_for_spill")
+                .emit(
+                    r#"
+                        sink.trap(TrapCode::StackOverflow, func.srclocs[inst]);
+                        put_ldst(
+                            bits,
+                            in_reg0,
+                            RU::x31 as RegUnit,
+                            out_stk0.offset.into(),
+                            sink,
+                        );
+                    "#,
+                ),
+        );
+    }
+
+    for &(name, class, stack) in &[("fill", gpr, stack_gpr), ("ffill", fpr, stack_fpr)] {
+        recipes.push(
+            EncodingRecipeBuilder::new(name, &formats.unary, 4)
+                .operands_in(vec![stack])
+                .operands_out(vec![class])
+                .clobbers_flags(false)
+                .compute_size("size_for_fill")
+                .emit(
+                    r#"
+                        put_ldst(
+                            bits,
+                            out_reg0,
+                            RU::x31 as RegUnit,
+                            in_stk0.offset.into(),
+                            sink,
+                        );
+                    "#,
+                ),
+        );
+    }
+
+    for &(name, class) in &[("regspill", gpr), ("fregspill", fpr)] {
+        recipes.push(
+            EncodingRecipeBuilder::new(name, &formats.reg_spill, 4)
+                .operands_in(vec![class])
+                .clobbers_flags(false)
+                .compute_size("size_for_regspill")
+                .emit(
+                    r#"
+                        sink.trap(TrapCode::StackOverflow, func.srclocs[inst]);
+                        let dst = StackRef::sp(dst, &func.stack_slots);
+                        put_ldst(bits, src, RU::x31 as RegUnit, dst.offset.into(), sink);
+                    "#,
+                ),
+        );
+    }
+
+    for &(name, stack) in &[("regfill", stack_gpr), ("fregfill", stack_fpr)] {
+        recipes.push(
+            EncodingRecipeBuilder::new(name, &formats.reg_fill, 4)
+                .operands_in(vec![stack])
+                .clobbers_flags(false)
+                .compute_size("size_for_regfill")
+                .emit(
+                    r#"
+                        let src = StackRef::sp(src, &func.stack_slots);
+                        put_ldst(bits, dst, RU::x31 as RegUnit, src.offset.into(), sink);
+                    "#,
+                ),
+        );
+    }
+
+    // Stack-slot to same stack-slot copy, which is guaranteed to turn into a no-op.
+    recipes.push(
+        EncodingRecipeBuilder::new("stacknull", &formats.unary, 0)
+            .operands_in(vec![stack_gpr])
+            .operands_out(vec![stack_gpr])
+            .clobbers_flags(false)
+            .emit(""),
+    );
+
+    // No-op fills, created by late-stage redundant-fill removal.
+    recipes.push(
+        EncodingRecipeBuilder::new("fillnull", &formats.unary, 0)
+            .operands_in(vec![stack_gpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit(""),
+    );
+
+    recipes.push(
+        EncodingRecipeBuilder::new("ffillnull", &formats.unary, 0)
+            .operands_in(vec![stack_fpr])
+            .operands_out(vec![fpr])
+            .clobbers_flags(false)
+            .emit(""),
+    );
+
+    // Floating point data processing with two source registers.
+    recipes.push(
+        EncodingRecipeBuilder::new("frrr", &formats.binary, 4)
+            .operands_in(vec![fpr, fpr])
+            .operands_out(vec![fpr])
+            .clobbers_flags(false)
+            .emit("put_fp(bits, in_reg0, in_reg1, 0, out_reg0, sink);"),
+    );
+
+    // Floating point data processing with one source register.
+    recipes.push(
+        EncodingRecipeBuilder::new("frr", &formats.unary, 4)
+            .operands_in(vec![fpr])
+            .operands_out(vec![fpr])
+            .clobbers_flags(false)
+            .emit("put_fp(bits, in_reg0, 0, 0, out_reg0, sink);"),
+    );
+
+    // Fused multiply-add.
+    recipes.push(
+        EncodingRecipeBuilder::new("frrrr", &formats.ternary, 4)
+            .operands_in(vec![fpr, fpr, fpr])
+            .operands_out(vec![fpr])
+            .clobbers_flags(false)
+            .emit("put_fp(bits, in_reg0, in_reg1, in_reg2, out_reg0, sink);"),
+    );
+
+    // Bitwise operations on floating point values, using the vector instructions.
+    recipes.push(
+        EncodingRecipeBuilder::new("vrrr", &formats.binary, 4)
+            .operands_in(vec![fpr, fpr])
+            .operands_out(vec![fpr])
+            .clobbers_flags(false)
+            .emit("put_rrr(bits, in_reg0, in_reg1, out_reg0, sink);"),
+    );
+
+    // Register moves between floating point registers.
+    recipes.push(
+        EncodingRecipeBuilder::new("frmov", &formats.reg_move, 4)
+            .operands_in(vec![fpr])
+            .clobbers_flags(false)
+            .emit("put_fp(bits, src, 0, 0, dst, sink);"),
+    );
+
+    recipes.push(
+        EncodingRecipeBuilder::new("fcopytossa", &formats.copy_to_ssa, 4)
+            .operands_out(vec![fpr])
+            .clobbers_flags(false)
+            .emit("put_fp(bits, src, 0, 0, out_reg0, sink);"),
+    );
+
+    // Conversions from a floating point register to a general purpose register.
+    recipes.push(
+        EncodingRecipeBuilder::new("frgp", &formats.unary, 4)
+            .operands_in(vec![fpr])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .emit("put_fp(bits, in_reg0, 0, 0, out_reg0, sink);"),
+    );
+
+    // Conversions from a general purpose register to a floating point register.
+    recipes.push(
+        EncodingRecipeBuilder::new("gpfr", &formats.unary, 4)
+            .operands_in(vec![gpr])
+            .operands_out(vec![fpr])
+            .clobbers_flags(false)
+            .emit("put_fp(bits, in_reg0, 0, 0, out_reg0, sink);"),
+    );
+
+    // Trapping conversion from floating point to integer. NaN inputs are detected with an
+    // unordered self-comparison, and out-of-range inputs by the cumulative invalid operation
+    // flag in FPSR.
+    recipes.push(
+        EncodingRecipeBuilder::new("fcvt_trap", &formats.unary, 32)
+            .operands_in(vec![fpr])
+            .operands_out(vec![gpr])
+            .emit(
+                r#"
+                    put_fcmp_self(bits, in_reg0, sink);
+                    put_bcond(COND_VC, 8, sink);
+                    sink.trap(TrapCode::BadConversionToInteger, func.srclocs[inst]);
+                    sink.put4(UDF);
+                    put_clear_fpsr(sink);
+                    put_fp(bits, in_reg0, 0, 0, out_reg0, sink);
+                    put_ioc_check(func.srclocs[inst], sink);
+                "#,
+            ),
+    );
+
+    // Compare two floating point registers and set the flags.
+    recipes.push(
+        EncodingRecipeBuilder::new("fcmp", &formats.binary, 4)
+            .operands_in(vec![fpr, fpr])
+            .operands_out(vec![reg_nzcv])
+            .emit("put_fp(bits, in_reg0, in_reg1, 0, 0, sink);"),
+    );
+
+    // Compare two floating point registers and materialize the condition as a boolean.
+    recipes.push(
+        EncodingRecipeBuilder::new("fcscc", &formats.float_compare, 8)
+            .operands_in(vec![fpr, fpr])
+            .operands_out(vec![gpr])
+            .inst_predicate(single_floatcc_predicate(
+                shared_defs,
+                &*formats.float_compare,
+            ))
+            .emit(
+                r#"
+                    put_fp(bits, in_reg0, in_reg1, 0, 0, sink);
+                    put_cset(fcc2cond(cond), out_reg0, sink);
+                "#,
+            ),
+    );
+
+    // Same for the two float condition codes that need two conditions to be tested.
+    recipes.push(
+        EncodingRecipeBuilder::new("fcscc2", &formats.float_compare, 12)
+            .operands_in(vec![fpr, fpr])
+            .operands_out(vec![gpr])
+            .inst_predicate(double_floatcc_predicate(
+                shared_defs,
+                &*formats.float_compare,
+            ))
+            .emit(
+                r#"
+                    put_fp(bits, in_reg0, in_reg1, 0, 0, sink);
+                    put_cset_double_fcc(cond, out_reg0, sink);
+                "#,
+            ),
+    );
+
+    // Test float flags and set a register.
+    recipes.push(
+        EncodingRecipeBuilder::new("setf", &formats.float_cond, 4)
+            .operands_in(vec![reg_nzcv])
+            .operands_out(vec![gpr])
+            .clobbers_flags(false)
+            .inst_predicate(single_floatcc_predicate(shared_defs, &*formats.float_cond))
+            .emit("put_cset(fcc2cond(cond), out_reg0, sink);"),
+    );
+
+    recipes
+}
@@ -1,5 +1,6 @@
 use crate::cdsl::ast::{Apply, Expr, Literal, VarPool};
 use crate::cdsl::encodings::{Encoding, EncodingBuilder};
+use crate::cdsl::instructions::BindParameter::Any;
 use crate::cdsl::instructions::{
     Bindable, BoundInstruction, InstSpec, InstructionPredicateNode, InstructionPredicateRegistry,
 };
@@ -139,8 +140,11 @@ pub(crate) fn define<'defs>(
     let ishl_imm = shared.by_name("ishl_imm");
     let isub = shared.by_name("isub");
     let jump = shared.by_name("jump");
+    let load = shared.by_name("load");
     let regmove = shared.by_name("regmove");
     let spill = shared.by_name("spill");
+    let stack_addr = shared.by_name("stack_addr");
+    let store = shared.by_name("store");
     let sshr = shared.by_name("sshr");
     let sshr_imm = shared.by_name("sshr_imm");
     let ushr = shared.by_name("ushr");
@@ -155,13 +159,16 @@ pub(crate) fn define<'defs>(
     let r_ii = recipes.by_name("Ii");
     let r_iicmp = recipes.by_name("Iicmp");
     let r_iret = recipes.by_name("Iret");
+    let r_iload = recipes.by_name("Iload");
     let r_irmov = recipes.by_name("Irmov");
+    let r_ispaddr = recipes.by_name("Ispaddr");
     let r_iz = recipes.by_name("Iz");
     let r_gp_sp = recipes.by_name("GPsp");
     let r_gp_fi = recipes.by_name("GPfi");
     let r_r = recipes.by_name("R");
     let r_ricmp = recipes.by_name("Ricmp");
     let r_rshamt = recipes.by_name("Rshamt");
+    let r_s = recipes.by_name("S");
     let r_sb = recipes.by_name("SB");
     let r_sb_zero = recipes.by_name("SBzero");
     let r_stacknull = recipes.by_name("stacknull");
@@ -376,6 +383,18 @@ pub(crate) fn define<'defs>(
     e.add32(e.enc(call_indirect.bind(I32), r_icall, jalr_bits()));
     e.add64(e.enc(call_indirect.bind(I64), r_icall, jalr_bits()));
 
+    // Loads and stores.
+    e.add32(e.enc(load.bind(I32).bind(Any), r_iload, load_bits(0b010)));
+    e.add64(e.enc(load.bind(I32).bind(Any), r_iload, load_bits(0b010)));
+    e.add64(e.enc(load.bind(I64).bind(Any), r_iload, load_bits(0b011)));
+    e.add32(e.enc(store.bind(I32).bind(Any), r_s, store_bits(0b010)));
+    e.add64(e.enc(store.bind(I32).bind(Any), r_s, store_bits(0b010)));
+    e.add64(e.enc(store.bind(I64).bind(Any), r_s, store_bits(0b011)));
+
+    // Stack slot addresses are computed with an `addi` from the stack pointer.
+    e.add32(e.enc(stack_addr.bind(I32), r_ispaddr, opimm_bits(0b000, 0)));
+    e.add64(e.enc(stack_addr.bind(I64), r_ispaddr, opimm_bits(0b000, 0)));
+
     // Spill and fill.
     e.add32(e.enc(spill.bind(I32), r_gp_sp, store_bits(0b010)));
     e.add64(e.enc(spill.bind(I32), r_gp_sp, store_bits(0b010)));
@@ -154,6 +154,48 @@ pub(crate) fn define(shared_defs: &SharedDefinitions, regs: &IsaRegs) -> RecipeG
             ),
     );
 
+    // I-type encoding of a load with an immediate offset from the address register.
+    recipes.push(
+        EncodingRecipeBuilder::new("Iload", &formats.load, 4)
+            .operands_in(vec![gpr])
+            .operands_out(vec![gpr])
+            .inst_predicate(InstructionPredicate::new_is_signed_int(
+                &formats.load,
+                "offset",
+                12,
+                0,
+            ))
+            .emit(
+                r#"
+                    if !flags.notrap() {
+                        sink.trap(TrapCode::HeapOutOfBounds, func.srclocs[inst]);
+                    }
+                    put_i(bits, in_reg0, offset.into(), out_reg0, sink);
+                "#,
+            ),
+    );
+
+    // I-type encoding of `addi` computing the address of a stack slot from the stack pointer.
+    recipes.push(
+        EncodingRecipeBuilder::new("Ispaddr", &formats.stack_load, 4)
+            .operands_out(vec![gpr])
+            .emit(
+                r#"
+                    let sp = StackRef::sp(stack_slot, &func.stack_slots);
+                    let offset: i32 = offset.into();
+                    let imm = i64::from(sp.offset.checked_add(offset).unwrap());
+                    debug_assert!(is_signed_int(imm, 12, 0), "Ispaddr out of range {:#x}", imm);
+                    put_i(
+                        bits,
+                        2, // rs1 = %x2: stack pointer.
+                        imm,
+                        out_reg0,
+                        sink,
+                    );
+                "#,
+            ),
+    );
+
     // Copy of a GPR is implemented as addi x, 0.
     recipes.push(
         EncodingRecipeBuilder::new("Icopy", &formats.unary, 4)
@@ -213,6 +255,26 @@ pub(crate) fn define(shared_defs: &SharedDefinitions, regs: &IsaRegs) -> RecipeG
                 "#,
     ));
 
+    // S-type stores: the value is `in_reg0` and the address register is `in_reg1`.
+    recipes.push(
+        EncodingRecipeBuilder::new("S", &formats.store, 4)
+            .operands_in(vec![gpr, gpr])
+            .inst_predicate(InstructionPredicate::new_is_signed_int(
+                &formats.store,
+                "offset",
+                12,
+                0,
+            ))
+            .emit(
+                r#"
+                    if !flags.notrap() {
+                        sink.trap(TrapCode::HeapOutOfBounds, func.srclocs[inst]);
+                    }
+                    put_s(bits, offset.into(), in_reg1, in_reg0, sink);
+                "#,
+            ),
+    );
+
     // SB-type branch instructions.
     recipes.push(
         EncodingRecipeBuilder::new("SB", &formats.branch_icmp, 4)
@@ -3,6 +3,7 @@
 use cranelift_codegen_shared::condcodes::IntCC;
 use std::collections::HashMap;
 
+use crate::cdsl::ast::Literal;
 use crate::cdsl::encodings::{Encoding, EncodingBuilder};
 use crate::cdsl::instructions::{
     vector, Bindable, Immediate, InstSpec, Instruction, InstructionGroup, InstructionPredicate,
@@ -734,6 +735,8 @@ fn define_memory(
     let uload8_complex = shared.by_name("uload8_complex");
     let x86_pop = x86.by_name("x86_pop");
     let x86_push = x86.by_name("x86_push");
+    let x86_stack_probe = x86.by_name("x86_stack_probe");
+    let x86_stack_probe_loop = x86.by_name("x86_stack_probe_loop");
 
     // Shorthands for recipes.
     let rec_adjustsp = r.template("adjustsp");
@@ -754,6 +757,9 @@ fn define_memory(
     let rec_regspill32 = r.template("regspill32");
     let rec_spillSib32 = r.template("spillSib32");
     let rec_st = r.template("st");
+    let rec_stack_probe = r.template("stack_probe");
+    let rec_stack_probe_loop32 = r.recipe("stack_probe_loop32");
+    let rec_stack_probe_loop64 = r.recipe("stack_probe_loop64");
     let rec_stacknull = r.recipe("stacknull");
     let rec_stDisp32 = r.template("stDisp32");
     let rec_stDisp32_abcd = r.template("stDisp32_abcd");
@@ -975,6 +981,107 @@ fn define_memory(
         adjust_sp_down_imm,
         rec_adjustsp_id.opcodes(&CMP_IMM).rrr(5).rex().w(),
     );
+
+    // Inline stack probes.
+    e.enc32(x86_stack_probe, rec_stack_probe.opcodes(&CMP_IMM8).rrr(1));
+    e.enc64(
+        x86_stack_probe,
+        rec_stack_probe.opcodes(&CMP_IMM8).rrr(1).rex().w(),
+    );
+    e.enc32_rec(x86_stack_probe_loop, rec_stack_probe_loop32, 0);
+    e.enc64_rec(x86_stack_probe_loop, rec_stack_probe_loop64, 0);
+}
+
+#[inline(never)]
+fn define_atomics(e: &mut PerCpuModeEncodings, shared_defs: &SharedDefinitions, r: &RecipeGroup) {
+    let shared = &shared_defs.instructions;
+    let formats = &shared_defs.formats;
+    let imm = &shared_defs.imm;
+
+    // Shorthands for instructions.
+    let atomic_cas = shared.by_name("atomic_cas");
+    let atomic_load = shared.by_name("atomic_load");
+    let atomic_rmw = shared.by_name("atomic_rmw");
+    let fence = shared.by_name("fence");
+
+    // Shorthands for recipes.
+    let rec_fence = r.template("fence");
+    let rec_ld = r.template("ld");
+    let rec_ldDisp32 = r.template("ldDisp32");
+    let rec_ldDisp8 = r.template("ldDisp8");
+    let rec_lock_cas = r.template("lock_cas");
+    let rec_lock_rmw = r.template("lock_rmw");
+
+    // Aligned loads are atomic, and locked instructions order them with respect to the atomic
+    // stores. Narrow loads zero-extend into the full register.
+    for recipe in &[rec_ld, rec_ldDisp8, rec_ldDisp32] {
+        e.enc_i32_i64_ld_st(atomic_load, true, recipe.opcodes(&MOV_LOAD));
+        for &(ty, opcodes) in &[(I16, &MOVZX_WORD), (I8, &MOVZX_BYTE)] {
+            e.enc32(atomic_load.bind(ty).bind(Any), recipe.opcodes(opcodes));
+            e.enc_x86_64(atomic_load.bind(ty).bind(Any), recipe.opcodes(opcodes));
+        }
+    }
+
+    // Only addition and exchange have x86 instructions returning the old value. The other
+    // operations are legalized to a `lock cmpxchg` loop.
+    for &(op, opcodes, opcodes_16, opcodes_byte) in &[
+        ("add", &XADD[..], &XADD_16[..], &XADD_BYTE[..]),
+        ("xchg", &XCHG[..], &XCHG_16[..], &XCHG_BYTE[..]),
+    ] {
+        let is_op = InstructionPredicate::new_is_field_equal(
+            &*formats.atomic_rmw,
+            "op",
+            Literal::enumerator_for(&imm.atomic_rmw_op, op).to_rust_code(),
+        );
+        let template = rec_lock_rmw.opcodes(opcodes);
+        e.enc32_instp(
+            atomic_rmw.bind(I32).bind(Any),
+            template.clone(),
+            is_op.clone(),
+        );
+        e.enc_x86_64_instp(
+            atomic_rmw.bind(I32).bind(Any),
+            template.clone(),
+            is_op.clone(),
+        );
+        e.enc64_instp(
+            atomic_rmw.bind(I64).bind(Any),
+            template.rex().w(),
+            is_op.clone(),
+        );
+
+        let template = rec_lock_rmw.opcodes(opcodes_16);
+        e.enc_both_instp(atomic_rmw.bind(I16).bind(Any), template, is_op.clone());
+
+        // A REX prefix is required to address the low byte of every register, so byte
+        // operations are only available in 64-bit mode.
+        let template = rec_lock_rmw.opcodes(opcodes_byte);
+        e.enc64_instp(atomic_rmw.bind(I8).bind(Any), template.rex(), is_op);
+    }
+
+    e.enc32(
+        atomic_cas.bind(I32).bind(Any),
+        rec_lock_cas.opcodes(&CMPXCHG),
+    );
+    e.enc_x86_64(
+        atomic_cas.bind(I32).bind(Any),
+        rec_lock_cas.opcodes(&CMPXCHG),
+    );
+    e.enc64(
+        atomic_cas.bind(I64).bind(Any),
+        rec_lock_cas.opcodes(&CMPXCHG).rex().w(),
+    );
+    e.enc_both(
+        atomic_cas.bind(I16).bind(Any),
+        rec_lock_cas.opcodes(&CMPXCHG_16),
+    );
+    e.enc64(
+        atomic_cas.bind(I8).bind(Any),
+        rec_lock_cas.opcodes(&CMPXCHG_BYTE).rex(),
+    );
+
+    e.enc32(fence, rec_fence.opcodes(&MFENCE));
+    e.enc64(fence, rec_fence.opcodes(&MFENCE));
 }
 
 #[inline(never)]
@@ -1546,8 +1653,10 @@ fn define_simd(
     let bxor = shared.by_name("bxor");
     let copy = shared.by_name("copy");
     let copy_nop = shared.by_name("copy_nop");
+    let avg_round = shared.by_name("avg_round");
     let fadd = shared.by_name("fadd");
     let fcmp = shared.by_name("fcmp");
+    let fcvt_from_sint = shared.by_name("fcvt_from_sint");
     let fdiv = shared.by_name("fdiv");
     let fill = shared.by_name("fill");
     let fill_nop = shared.by_name("fill_nop");
@@ -1567,14 +1676,27 @@ fn define_simd(
     let sadd_sat = shared.by_name("sadd_sat");
     let scalar_to_vector = shared.by_name("scalar_to_vector");
     let spill = shared.by_name("spill");
+    let sload8x8 = shared.by_name("sload8x8");
+    let sload16x4 = shared.by_name("sload16x4");
+    let sload32x2 = shared.by_name("sload32x2");
+    let snarrow = shared.by_name("snarrow");
     let sqrt = shared.by_name("sqrt");
     let sshr_imm = shared.by_name("sshr_imm");
     let ssub_sat = shared.by_name("ssub_sat");
     let store = shared.by_name("store");
+    let swiden_low = shared.by_name("swiden_low");
     let uadd_sat = shared.by_name("uadd_sat");
+    let uload8x8 = shared.by_name("uload8x8");
+    let uload16x4 = shared.by_name("uload16x4");
+    let uload32x2 = shared.by_name("uload32x2");
+    let unarrow = shared.by_name("unarrow");
     let ushr_imm = shared.by_name("ushr_imm");
     let usub_sat = shared.by_name("usub_sat");
+    let uwiden_low = shared.by_name("uwiden_low");
     let vconst = shared.by_name("vconst");
+    let x86_cvtt2si = x86.by_name("x86_cvtt2si");
+    let x86_fmax = x86.by_name("x86_fmax");
+    let x86_fmin = x86.by_name("x86_fmin");
     let x86_insertps = x86.by_name("x86_insertps");
     let x86_movlhps = x86.by_name("x86_movlhps");
     let x86_movsd = x86.by_name("x86_movsd");
@@ -1584,6 +1706,7 @@ fn define_simd(
     let x86_pmaxu = x86.by_name("x86_pmaxu");
     let x86_pmins = x86.by_name("x86_pmins");
     let x86_pminu = x86.by_name("x86_pminu");
+    let x86_pmuludq = x86.by_name("x86_pmuludq");
     let x86_pshufb = x86.by_name("x86_pshufb");
     let x86_pshufd = x86.by_name("x86_pshufd");
     let x86_psll = x86.by_name("x86_psll");
@@ -1842,6 +1965,23 @@ fn define_simd(
         e.enc_32_64_rec(bound_copy_nop, rec_stacknull, 0);
     }
 
+    // SIMD load extend: PMOVSX*/PMOVZX* read 64 bits from memory and extend each lane.
+    for (inst, opcodes) in &[
+        (uload8x8, &PMOVZXBW),
+        (uload16x4, &PMOVZXWD),
+        (uload32x2, &PMOVZXDQ),
+        (sload8x8, &PMOVSXBW),
+        (sload16x4, &PMOVSXWD),
+        (sload32x2, &PMOVSXDQ),
+    ] {
+        let isap = Some(use_sse41_simd);
+        for recipe in &[&rec_fld, &rec_fldDisp8, &rec_fldDisp32] {
+            let template = recipe.opcodes(*opcodes);
+            e.enc32_maybe_isap(inst.bind(I32), template.clone(), isap);
+            e.enc64_maybe_isap(inst.bind(I64), template, isap);
+        }
+    }
+
     // SIMD integer addition
     for (ty, opcodes) in &[(I8, &PADDB), (I16, &PADDW), (I32, &PADDD), (I64, &PADDQ)] {
         let iadd = iadd.bind(vector(*ty, sse_vector_size));
@@ -1892,7 +2032,7 @@ fn define_simd(
     );
 
     // SIMD integer multiplication: the x86 ISA does not have instructions for multiplying I8x16
-    // and I64x2 and these are (at the time of writing) not necessary for WASM SIMD.
+    // and I64x2; these are legalized using PMULLW and PMULUDQ (see legalize.rs).
     for (ty, opcodes, isap) in &[
         (I16, &PMULLW[..], None),
         (I32, &PMULLD[..], Some(use_sse41_simd)),
@@ -1901,6 +2041,42 @@ fn define_simd(
         e.enc_32_64_maybe_isap(imul, rec_fa.opcodes(opcodes), *isap);
     }
 
+    // SIMD multiplication of the low 32 bits of each 64-bit lane, for legalizing imul.i64x2.
+    e.enc_32_64(x86_pmuludq, rec_fa.opcodes(&PMULUDQ));
+
+    // SIMD integer average with rounding.
+    for (ty, opcodes) in &[(I8, &PAVGB[..]), (I16, &PAVGW[..])] {
+        let avg_round = avg_round.bind(vector(*ty, sse_vector_size));
+        e.enc_32_64(avg_round, rec_fa.opcodes(opcodes));
+    }
+
+    // SIMD narrow: PACKSS*/PACKUS* saturate the lanes of both operands into the lanes of the
+    // result, the first operand filling the low half.
+    for (ty, opcodes, isap) in &[(I16, &PACKSSWB[..], None), (I32, &PACKSSDW[..], None)] {
+        let snarrow = snarrow.bind(vector(*ty, sse_vector_size));
+        e.enc_32_64_maybe_isap(snarrow, rec_fa.opcodes(opcodes), *isap);
+    }
+    for (ty, opcodes, isap) in &[
+        (I16, &PACKUSWB[..], None),
+        (I32, &PACKUSDW[..], Some(use_sse41_simd)),
+    ] {
+        let unarrow = unarrow.bind(vector(*ty, sse_vector_size));
+        e.enc_32_64_maybe_isap(unarrow, rec_fa.opcodes(opcodes), *isap);
+    }
+
+    // SIMD widen: PMOVSX*/PMOVZX* extend the low lanes; the high lanes are moved to the low lanes
+    // first (see legalize.rs).
+    for (ty, swiden_opcodes, uwiden_opcodes) in &[
+        (I8, &PMOVSXBW[..], &PMOVZXBW[..]),
+        (I16, &PMOVSXWD[..], &PMOVZXWD[..]),
+    ] {
+        let isap = Some(use_sse41_simd);
+        let swiden_low = swiden_low.bind(vector(*ty, sse_vector_size));
+        e.enc_32_64_maybe_isap(swiden_low, rec_furm.opcodes(swiden_opcodes), isap);
+        let uwiden_low = uwiden_low.bind(vector(*ty, sse_vector_size));
+        e.enc_32_64_maybe_isap(uwiden_low, rec_furm.opcodes(uwiden_opcodes), isap);
+    }
+
     // SIMD logical operations
     let band = shared.by_name("band");
     let band_not = shared.by_name("band_not");
@@ -2042,6 +2218,31 @@ fn define_simd(
         let inst = inst.bind(vector(*ty, sse_vector_size));
         e.enc_both(inst, rec_furm.opcodes(opcodes));
     }
+
+    // SIMD float minimum/maximum with x86 semantics, for legalizing saturating conversions.
+    for (ty, inst, opcodes) in &[
+        (F32, x86_fmin, &MINPS[..]),
+        (F64, x86_fmin, &MINPD[..]),
+        (F32, x86_fmax, &MAXPS[..]),
+        (F64, x86_fmax, &MAXPD[..]),
+    ] {
+        let inst = inst.bind(vector(*ty, sse_vector_size));
+        e.enc_both(inst, rec_fa.opcodes(opcodes));
+    }
+
+    // SIMD conversions between i32x4 and f32x4; the remaining conversions are legalized.
+    e.enc_both(
+        fcvt_from_sint
+            .bind(vector(F32, sse_vector_size))
+            .bind(vector(I32, sse_vector_size)),
+        rec_furm.opcodes(&CVTDQ2PS),
+    );
+    e.enc_both(
+        x86_cvtt2si
+            .bind(vector(I32, sse_vector_size))
+            .bind(vector(F32, sse_vector_size)),
+        rec_furm.opcodes(&CVTTPS2DQ),
+    );
 }
 
 #[inline(never)]
@@ -2049,6 +2250,7 @@ fn define_entity_ref(
     e: &mut PerCpuModeEncodings,
     shared_defs: &SharedDefinitions,
     settings: &SettingGroup,
+    x86: &InstructionGroup,
     r: &RecipeGroup,
 ) {
     let shared = &shared_defs.instructions;
@@ -2058,9 +2260,14 @@ fn define_entity_ref(
     let func_addr = shared.by_name("func_addr");
     let stack_addr = shared.by_name("stack_addr");
     let symbol_value = shared.by_name("symbol_value");
+    let x86_elf_tls_get_addr = x86.by_name("x86_elf_tls_get_addr");
+    let x86_elf_tls_ie_addr = x86.by_name("x86_elf_tls_ie_addr");
+    let x86_macho_tls_get_addr = x86.by_name("x86_macho_tls_get_addr");
 
     // Shorthands for recipes.
     let rec_allones_fnaddr4 = r.template("allones_fnaddr4");
+    let rec_elf_tls_get_addr = r.recipe("elf_tls_get_addr");
+    let rec_elf_tls_ie_addr = r.recipe("elf_tls_ie_addr");
     let rec_allones_fnaddr8 = r.template("allones_fnaddr8");
     let rec_fnaddr4 = r.template("fnaddr4");
     let rec_fnaddr8 = r.template("fnaddr8");
@@ -2068,6 +2275,7 @@ fn define_entity_ref(
     let rec_got_gvaddr8 = r.template("got_gvaddr8");
     let rec_gvaddr4 = r.template("gvaddr4");
     let rec_gvaddr8 = r.template("gvaddr8");
+    let rec_macho_tls_get_addr = r.recipe("macho_tls_get_addr");
     let rec_pcrel_fnaddr8 = r.template("pcrel_fnaddr8");
     let rec_pcrel_gvaddr8 = r.template("pcrel_gvaddr8");
     let rec_spaddr4_id = r.template("spaddr4_id");
@@ -2155,6 +2363,11 @@ fn define_entity_ref(
         is_pic,
     );
 
+    // Thread local storage addresses.
+    e.enc64_rec(x86_elf_tls_get_addr, rec_elf_tls_get_addr, 0);
+    e.enc64_rec(x86_elf_tls_ie_addr, rec_elf_tls_ie_addr, 0);
+    e.enc64_rec(x86_macho_tls_get_addr, rec_macho_tls_get_addr, 0);
+
     // Stack addresses.
     //
     // TODO: Add encoding rules for stack_load and stack_store, so that they
@@ -2183,10 +2396,14 @@ fn define_control_flow(
     let call_indirect = shared.by_name("call_indirect");
     let debugtrap = shared.by_name("debugtrap");
     let indirect_jump_table_br = shared.by_name("indirect_jump_table_br");
+    let invoke = shared.by_name("invoke");
+    let invoke_indirect = shared.by_name("invoke_indirect");
     let jump = shared.by_name("jump");
     let jump_table_base = shared.by_name("jump_table_base");
     let jump_table_entry = shared.by_name("jump_table_entry");
     let return_ = shared.by_name("return");
+    let return_call = shared.by_name("return_call");
+    let return_call_indirect = shared.by_name("return_call_indirect");
     let trap = shared.by_name("trap");
     let trapff = shared.by_name("trapff");
     let trapif = shared.by_name("trapif");
@@ -2202,12 +2419,18 @@ fn define_control_flow(
     let rec_call_r = r.template("call_r");
     let rec_debugtrap = r.recipe("debugtrap");
     let rec_indirect_jmp = r.template("indirect_jmp");
+    let rec_invoke_id = r.template("invoke_id");
+    let rec_invoke_plt_id = r.template("invoke_plt_id");
+    let rec_invoke_r = r.template("invoke_r");
     let rec_jmpb = r.template("jmpb");
     let rec_jmpd = r.template("jmpd");
     let rec_jt_base = r.template("jt_base");
     let rec_jt_entry = r.template("jt_entry");
     let rec_ret = r.template("ret");
     let rec_t8jccb_abcd = r.template("t8jccb_abcd");
+    let rec_tail_call_id = r.template("tail_call_id");
+    let rec_tail_call_plt_id = r.template("tail_call_plt_id");
+    let rec_tail_call_r = r.template("tail_call_r");
     let rec_t8jccd_abcd = r.template("t8jccd_abcd");
     let rec_t8jccd_long = r.template("t8jccd_long");
     let rec_tjccb = r.template("tjccb");
@@ -2246,9 +2469,50 @@ fn define_control_flow(
         rec_call_r.opcodes(&JUMP_ABSOLUTE).rrr(2),
     );
 
+    // Invokes use the same code models as calls.
+    e.enc32(invoke, rec_invoke_id.opcodes(&CALL_RELATIVE));
+    let is_colocated_func =
+        InstructionPredicate::new_is_colocated_func(&*formats.invoke, "func_ref");
+    e.enc64_instp(
+        invoke,
+        rec_invoke_id.opcodes(&CALL_RELATIVE),
+        is_colocated_func,
+    );
+    e.enc64_isap(invoke, rec_invoke_plt_id.opcodes(&CALL_RELATIVE), is_pic);
+
+    e.enc32(
+        invoke_indirect.bind(I32),
+        rec_invoke_r.opcodes(&JUMP_ABSOLUTE).rrr(2),
+    );
+    e.enc64(
+        invoke_indirect.bind(I64),
+        rec_invoke_r.opcodes(&JUMP_ABSOLUTE).rrr(2).rex(),
+    );
+    e.enc64(
+        invoke_indirect.bind(I64),
+        rec_invoke_r.opcodes(&JUMP_ABSOLUTE).rrr(2),
+    );
+
     e.enc32(return_, rec_ret.opcodes(&RET_NEAR));
     e.enc64(return_, rec_ret.opcodes(&RET_NEAR));
 
+    // Tail calls are only supported in 64-bit mode. They use the same code models as calls above.
+    let is_colocated_func = InstructionPredicate::new_is_colocated_func(&*formats.call, "func_ref");
+    e.enc64_instp(
+        return_call,
+        rec_tail_call_id.opcodes(&JUMP_NEAR_RELATIVE),
+        is_colocated_func,
+    );
+    e.enc64_isap(
+        return_call,
+        rec_tail_call_plt_id.opcodes(&JUMP_NEAR_RELATIVE),
+        is_pic,
+    );
+    e.enc64(
+        return_call_indirect.bind(I64),
+        rec_tail_call_r.opcodes(&JUMP_ABSOLUTE).rrr(4).rex(),
+    );
+
     // Branches.
     e.enc32(jump, rec_jmpb.opcodes(&JUMP_SHORT));
     e.enc64(jump, rec_jmpb.opcodes(&JUMP_SHORT));
@@ -2361,12 +2625,13 @@ pub(crate) fn define(
 
     define_moves(&mut e, shared_defs, r);
     define_memory(&mut e, shared_defs, x86, r);
+    define_atomics(&mut e, shared_defs, r);
     define_fpu_moves(&mut e, shared_defs, r);
     define_fpu_memory(&mut e, shared_defs, r);
     define_fpu_ops(&mut e, shared_defs, settings, x86, r);
     define_alu(&mut e, shared_defs, settings, x86, r);
     define_simd(&mut e, shared_defs, settings, x86, r);
-    define_entity_ref(&mut e, shared_defs, settings, r);
+    define_entity_ref(&mut e, shared_defs, settings, x86, r);
     define_control_flow(&mut e, shared_defs, settings, r);
     define_reftypes(&mut e, shared_defs, r);
 
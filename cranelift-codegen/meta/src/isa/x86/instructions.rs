@@ -4,9 +4,10 @@ use crate::cdsl::instructions::{
     AllInstructions, InstructionBuilder as Inst, InstructionGroup, InstructionGroupBuilder,
 };
 use crate::cdsl::operands::Operand;
-use crate::cdsl::types::ValueType;
+use crate::cdsl::types::{LaneType, ValueType};
 use crate::cdsl::typevar::{Interval, TypeSetBuilder, TypeVar};
 
+use crate::shared::entities::EntityRefs;
 use crate::shared::formats::Formats;
 use crate::shared::immediates::Immediates;
 use crate::shared::types;
@@ -16,6 +17,7 @@ pub(crate) fn define(
     mut all_instructions: &mut AllInstructions,
     formats: &Formats,
     immediates: &Immediates,
+    entities: &EntityRefs,
 ) -> InstructionGroup {
     let mut ig = InstructionGroupBuilder::new(&mut all_instructions);
 
@@ -225,6 +227,43 @@ pub(crate) fn define(
         .can_load(true),
     );
 
+    ig.push(
+        Inst::new(
+            "x86_stack_probe",
+            r#"
+    Touches the word at the top of the stack.
+
+    This is used by inline stack probes to make sure the guard page is hit
+    before the stack pointer moves past it. The contents of the stack are left
+    unchanged.
+    "#,
+            &formats.nullary,
+        )
+        .other_side_effects(true)
+        .can_load(true)
+        .can_store(true),
+    );
+
+    let Size = &Operand::new("Size", &immediates.imm64).with_doc("Number of bytes to allocate");
+
+    ig.push(
+        Inst::new(
+            "x86_stack_probe_loop",
+            r#"
+    Allocates ``Size`` bytes of stack, probing each page in turn.
+
+    The stack pointer is moved down by one guard region, as given by the
+    `probestack_size_log2` setting, at a time, and the new top of the stack is
+    touched each time. ``Size`` must be a multiple of the guard region size.
+    "#,
+            &formats.unary_imm,
+        )
+        .operands_in(vec![Size])
+        .other_side_effects(true)
+        .can_load(true)
+        .can_store(true),
+    );
+
     let y = &Operand::new("y", iWord);
     let rflags = &Operand::new("rflags", iflags);
 
@@ -542,5 +581,68 @@ pub(crate) fn define(
         .operands_out(vec![a]),
     );
 
+    let x = &Operand::new("x", I64x2);
+    let y = &Operand::new("y", I64x2);
+    let a = &Operand::new("a", I64x2);
+    ig.push(
+        Inst::new(
+            "x86_pmuludq",
+            r#"
+        Multiply Packed Unsigned Doubleword Integers -- Multiply the low 32 bits of each 64-bit
+        lane in the first and second operand as unsigned integers and return the full 64-bit
+        products.
+        "#,
+            &formats.binary,
+        )
+        .operands_in(vec![x, y])
+        .operands_out(vec![a]),
+    );
+
+    let i64_t: &TypeVar = &ValueType::from(LaneType::from(types::Int::I64)).into();
+    let GV = &Operand::new("GV", &entities.global_value);
+    let addr = &Operand::new("addr", i64_t);
+
+    ig.push(
+        Inst::new(
+            "x86_elf_tls_get_addr",
+            r#"
+        Elf tls get addr -- This implements the general dynamic TLS model for
+        ELF, by calling `__tls_get_addr`.
+        "#,
+            &formats.unary_global_value,
+        )
+        .operands_in(vec![GV])
+        .operands_out(vec![addr])
+        .clobbers_all_regs(true),
+    );
+
+    ig.push(
+        Inst::new(
+            "x86_elf_tls_ie_addr",
+            r#"
+        Elf tls initial exec addr -- This implements the initial exec TLS model
+        for ELF, by adding the variable's offset from the GOT to the thread
+        pointer in `%fs`.
+        "#,
+            &formats.unary_global_value,
+        )
+        .operands_in(vec![GV])
+        .operands_out(vec![addr]),
+    );
+
+    ig.push(
+        Inst::new(
+            "x86_macho_tls_get_addr",
+            r#"
+        Mach-O tls get addr -- This implements TLS access for Mach-O, by
+        calling the getter in the variable's descriptor.
+        "#,
+            &formats.unary_global_value,
+        )
+        .operands_in(vec![GV])
+        .operands_out(vec![addr])
+        .clobbers_all_regs(true),
+    );
+
     ig.build()
 }
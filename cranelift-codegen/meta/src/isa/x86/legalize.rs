@@ -2,6 +2,7 @@ use crate::cdsl::ast::{constant, var, ExprBuilder, Literal};
 use crate::cdsl::instructions::{vector, Bindable, InstructionGroup};
 use crate::cdsl::types::{LaneType, ValueType};
 use crate::cdsl::xform::TransformGroupBuilder;
+use crate::shared::types::Bool::B32;
 use crate::shared::types::Float::{F32, F64};
 use crate::shared::types::Int::{I16, I32, I64, I8};
 use crate::shared::Definitions as SharedDefinitions;
@@ -31,6 +32,8 @@ pub(crate) fn define(shared: &mut SharedDefinitions, x86_instructions: &Instruct
     let ctz = insts.by_name("ctz");
     let extractlane = insts.by_name("extractlane");
     let fcmp = insts.by_name("fcmp");
+    let fadd = insts.by_name("fadd");
+    let fcvt_from_sint = insts.by_name("fcvt_from_sint");
     let fcvt_from_uint = insts.by_name("fcvt_from_uint");
     let fcvt_to_sint = insts.by_name("fcvt_to_sint");
     let fcvt_to_uint = insts.by_name("fcvt_to_uint");
@@ -40,6 +43,7 @@ pub(crate) fn define(shared: &mut SharedDefinitions, x86_instructions: &Instruct
     let fmax = insts.by_name("fmax");
     let fmin = insts.by_name("fmin");
     let fneg = insts.by_name("fneg");
+    let fsub = insts.by_name("fsub");
     let iadd = insts.by_name("iadd");
     let icmp = insts.by_name("icmp");
     let iconst = insts.by_name("iconst");
@@ -49,7 +53,6 @@ pub(crate) fn define(shared: &mut SharedDefinitions, x86_instructions: &Instruct
     let ishl = insts.by_name("ishl");
     let ishl_imm = insts.by_name("ishl_imm");
     let isub = insts.by_name("isub");
-    let popcnt = insts.by_name("popcnt");
     let raw_bitcast = insts.by_name("raw_bitcast");
     let scalar_to_vector = insts.by_name("scalar_to_vector");
     let sdiv = insts.by_name("sdiv");
@@ -58,22 +61,34 @@ pub(crate) fn define(shared: &mut SharedDefinitions, x86_instructions: &Instruct
     let splat = insts.by_name("splat");
     let shuffle = insts.by_name("shuffle");
     let srem = insts.by_name("srem");
+    let snarrow = insts.by_name("snarrow");
     let sshr = insts.by_name("sshr");
+    let swiden_high = insts.by_name("swiden_high");
+    let swiden_low = insts.by_name("swiden_low");
+    let swizzle = insts.by_name("swizzle");
+    let tls_value = insts.by_name("tls_value");
     let trueif = insts.by_name("trueif");
+    let uadd_sat = insts.by_name("uadd_sat");
     let udiv = insts.by_name("udiv");
     let umulhi = insts.by_name("umulhi");
     let ushr_imm = insts.by_name("ushr_imm");
+    let unarrow = insts.by_name("unarrow");
     let urem = insts.by_name("urem");
     let ushr = insts.by_name("ushr");
+    let uwiden_high = insts.by_name("uwiden_high");
+    let uwiden_low = insts.by_name("uwiden_low");
     let vconst = insts.by_name("vconst");
     let vall_true = insts.by_name("vall_true");
     let vany_true = insts.by_name("vany_true");
 
     let x86_bsf = x86_instructions.by_name("x86_bsf");
     let x86_bsr = x86_instructions.by_name("x86_bsr");
+    let x86_cvtt2si = x86_instructions.by_name("x86_cvtt2si");
+    let x86_fmax = x86_instructions.by_name("x86_fmax");
     let x86_pmaxu = x86_instructions.by_name("x86_pmaxu");
     let x86_pmins = x86_instructions.by_name("x86_pmins");
     let x86_pminu = x86_instructions.by_name("x86_pminu");
+    let x86_pmuludq = x86_instructions.by_name("x86_pmuludq");
     let x86_pshufb = x86_instructions.by_name("x86_pshufb");
     let x86_pshufd = x86_instructions.by_name("x86_pshufd");
     let x86_psll = x86_instructions.by_name("x86_psll");
@@ -175,6 +190,9 @@ pub(crate) fn define(shared: &mut SharedDefinitions, x86_instructions: &Instruct
     group.custom_legalize(fcvt_to_sint_sat, "expand_fcvt_to_sint_sat");
     group.custom_legalize(fcvt_to_uint_sat, "expand_fcvt_to_uint_sat");
 
+    // The access sequence for thread local values depends on the TLS model.
+    group.custom_legalize(tls_value, "expand_tls_value");
+
     // Count leading and trailing zeroes, for baseline x86_64
     let c_minus_one = var("c_minus_one");
     let c_thirty_one = var("c_thirty_one");
@@ -231,94 +249,6 @@ pub(crate) fn define(shared: &mut SharedDefinitions, x86_instructions: &Instruct
         ],
     );
 
-    // Population count for baseline x86_64
-    let x = var("x");
-    let r = var("r");
-
-    let qv3 = var("qv3");
-    let qv4 = var("qv4");
-    let qv5 = var("qv5");
-    let qv6 = var("qv6");
-    let qv7 = var("qv7");
-    let qv8 = var("qv8");
-    let qv9 = var("qv9");
-    let qv10 = var("qv10");
-    let qv11 = var("qv11");
-    let qv12 = var("qv12");
-    let qv13 = var("qv13");
-    let qv14 = var("qv14");
-    let qv15 = var("qv15");
-    let qc77 = var("qc77");
-    #[allow(non_snake_case)]
-    let qc0F = var("qc0F");
-    let qc01 = var("qc01");
-
-    let imm64_1 = Literal::constant(&imm.imm64, 1);
-    let imm64_4 = Literal::constant(&imm.imm64, 4);
-    group.legalize(
-        def!(r = popcnt.I64(x)),
-        vec![
-            def!(qv3 = ushr_imm(x, imm64_1)),
-            def!(qc77 = iconst(Literal::constant(&imm.imm64, 0x7777_7777_7777_7777))),
-            def!(qv4 = band(qv3, qc77)),
-            def!(qv5 = isub(x, qv4)),
-            def!(qv6 = ushr_imm(qv4, imm64_1)),
-            def!(qv7 = band(qv6, qc77)),
-            def!(qv8 = isub(qv5, qv7)),
-            def!(qv9 = ushr_imm(qv7, imm64_1)),
-            def!(qv10 = band(qv9, qc77)),
-            def!(qv11 = isub(qv8, qv10)),
-            def!(qv12 = ushr_imm(qv11, imm64_4)),
-            def!(qv13 = iadd(qv11, qv12)),
-            def!(qc0F = iconst(Literal::constant(&imm.imm64, 0x0F0F_0F0F_0F0F_0F0F))),
-            def!(qv14 = band(qv13, qc0F)),
-            def!(qc01 = iconst(Literal::constant(&imm.imm64, 0x0101_0101_0101_0101))),
-            def!(qv15 = imul(qv14, qc01)),
-            def!(r = ushr_imm(qv15, Literal::constant(&imm.imm64, 56))),
-        ],
-    );
-
-    let lv3 = var("lv3");
-    let lv4 = var("lv4");
-    let lv5 = var("lv5");
-    let lv6 = var("lv6");
-    let lv7 = var("lv7");
-    let lv8 = var("lv8");
-    let lv9 = var("lv9");
-    let lv10 = var("lv10");
-    let lv11 = var("lv11");
-    let lv12 = var("lv12");
-    let lv13 = var("lv13");
-    let lv14 = var("lv14");
-    let lv15 = var("lv15");
-    let lc77 = var("lc77");
-    #[allow(non_snake_case)]
-    let lc0F = var("lc0F");
-    let lc01 = var("lc01");
-
-    group.legalize(
-        def!(r = popcnt.I32(x)),
-        vec![
-            def!(lv3 = ushr_imm(x, imm64_1)),
-            def!(lc77 = iconst(Literal::constant(&imm.imm64, 0x7777_7777))),
-            def!(lv4 = band(lv3, lc77)),
-            def!(lv5 = isub(x, lv4)),
-            def!(lv6 = ushr_imm(lv4, imm64_1)),
-            def!(lv7 = band(lv6, lc77)),
-            def!(lv8 = isub(lv5, lv7)),
-            def!(lv9 = ushr_imm(lv7, imm64_1)),
-            def!(lv10 = band(lv9, lc77)),
-            def!(lv11 = isub(lv8, lv10)),
-            def!(lv12 = ushr_imm(lv11, imm64_4)),
-            def!(lv13 = iadd(lv11, lv12)),
-            def!(lc0F = iconst(Literal::constant(&imm.imm64, 0x0F0F_0F0F))),
-            def!(lv14 = band(lv13, lc0F)),
-            def!(lc01 = iconst(Literal::constant(&imm.imm64, 0x0101_0101))),
-            def!(lv15 = imul(lv14, lc01)),
-            def!(r = ushr_imm(lv15, Literal::constant(&imm.imm64, 24))),
-        ],
-    );
-
     group.build_and_add_to(&mut shared.transform_groups);
 
     let mut narrow = TransformGroupBuilder::new(
@@ -340,6 +270,9 @@ pub(crate) fn define(shared: &mut SharedDefinitions, x86_instructions: &Instruct
     let c = var("c");
     let d = var("d");
     let e = var("e");
+    let f = var("f");
+    let g = var("g");
+    let h = var("h");
 
     // SIMD vector size: eventually multiple vector sizes may be supported but for now only SSE-sized vectors are available
     let sse_vector_size: u64 = 128;
@@ -434,8 +367,8 @@ pub(crate) fn define(shared: &mut SharedDefinitions, x86_instructions: &Instruct
         );
     }
 
-    // SIMD shift left (arithmetic)
-    for ty in &[I16, I32, I64] {
+    // SIMD shift right (arithmetic)
+    for ty in &[I16, I32] {
         let sshr = sshr.bind(vector(*ty, sse_vector_size));
         let bitcast = bitcast.bind(vector(I64, sse_vector_size));
         narrow.legalize(
@@ -444,6 +377,72 @@ pub(crate) fn define(shared: &mut SharedDefinitions, x86_instructions: &Instruct
         );
     }
 
+    // SIMD shift right (arithmetic, 64-bit): there is no PSRAQ before AVX-512, so shift
+    // logically and then sign-extend from the shifted sign bit using `(r ^ m) - m`, where `m` is
+    // the sign bit shifted by the same amount.
+    {
+        let sshr_i64x2 = sshr.bind(vector(I64, sse_vector_size));
+        let u128_sign_bits = constant(vec![0, 0, 0, 0, 0, 0, 0, 0x80, 0, 0, 0, 0, 0, 0, 0, 0x80]);
+        narrow.legalize(
+            def!(a = sshr_i64x2(x, y)),
+            vec![
+                def!(b = vconst(u128_sign_bits)),
+                def!(c = ushr(b, y)),
+                def!(d = ushr(x, y)),
+                def!(e = bxor(d, c)),
+                def!(a = isub(e, c)),
+            ],
+        );
+    }
+
+    // SIMD 8-bit shifts: x86 has no byte-sized shifts so the lanes are widened to 16 bits,
+    // shifted, and narrowed again. The narrowing cannot saturate: left-shifted lanes are masked
+    // back to 8 bits and right-shifted lanes already fit.
+    {
+        let ishl_i8x16 = ishl.bind(vector(I8, sse_vector_size));
+        let vconst_i16x8 = vconst.bind(vector(I16, sse_vector_size));
+        let u128_low_bytes = constant(vec![
+            0xff, 0, 0xff, 0, 0xff, 0, 0xff, 0, 0xff, 0, 0xff, 0, 0xff, 0, 0xff, 0,
+        ]);
+        narrow.legalize(
+            def!(a = ishl_i8x16(x, y)),
+            vec![
+                def!(b = uwiden_low(x)),
+                def!(c = uwiden_high(x)),
+                def!(d = ishl(b, y)),
+                def!(e = ishl(c, y)),
+                def!(f = vconst_i16x8(u128_low_bytes)),
+                def!(g = band(d, f)),
+                def!(h = band(e, f)),
+                def!(a = unarrow(g, h)),
+            ],
+        );
+
+        let ushr_i8x16 = ushr.bind(vector(I8, sse_vector_size));
+        narrow.legalize(
+            def!(a = ushr_i8x16(x, y)),
+            vec![
+                def!(b = uwiden_low(x)),
+                def!(c = uwiden_high(x)),
+                def!(d = ushr(b, y)),
+                def!(e = ushr(c, y)),
+                def!(a = unarrow(d, e)),
+            ],
+        );
+
+        let sshr_i8x16 = sshr.bind(vector(I8, sse_vector_size));
+        narrow.legalize(
+            def!(a = sshr_i8x16(x, y)),
+            vec![
+                def!(b = swiden_low(x)),
+                def!(c = swiden_high(x)),
+                def!(d = sshr(b, y)),
+                def!(e = sshr(c, y)),
+                def!(a = snarrow(d, e)),
+            ],
+        );
+    }
+
     // SIMD select
     for ty in ValueType::all_lane_types().filter(allowed_simd_type) {
         let bitselect = bitselect.bind(vector(ty, sse_vector_size)); // must bind both x/y and c
@@ -606,6 +605,243 @@ pub(crate) fn define(shared: &mut SharedDefinitions, x86_instructions: &Instruct
         );
     }
 
+    // SIMD widen high: move the high 64 bits of the vector to the low 64 bits with PSHUFD and
+    // reuse the PMOVSX/PMOVZX encodings of the low variants.
+    let uimm8_high_to_low = Literal::constant(&imm.uimm8, 0xee);
+    for ty in &[I8, I16] {
+        for (widen_high, widen_low) in &[(swiden_high, swiden_low), (uwiden_high, uwiden_low)] {
+            let widen_high = widen_high.bind(vector(*ty, sse_vector_size));
+            let widen_low = *widen_low;
+            let raw_bitcast_to_i32x4 = raw_bitcast
+                .bind(vector(I32, sse_vector_size))
+                .bind(vector(*ty, sse_vector_size));
+            let raw_bitcast_from_i32x4 = raw_bitcast
+                .bind(vector(*ty, sse_vector_size))
+                .bind(vector(I32, sse_vector_size));
+            narrow.legalize(
+                def!(a = widen_high(x)),
+                vec![
+                    def!(b = raw_bitcast_to_i32x4(x)),
+                    def!(c = x86_pshufd(b, uimm8_high_to_low)),
+                    def!(d = raw_bitcast_from_i32x4(c)),
+                    def!(a = widen_low(d)),
+                ],
+            );
+        }
+    }
+
+    // SIMD swizzle: PSHUFB zeroes a lane when the top bit of its index is set. Adding 0x70 with
+    // unsigned saturation keeps indices 0-15 intact in the low bits and sets the top bit of every
+    // index greater than 15.
+    {
+        let swizzle = swizzle.bind(vector(I8, sse_vector_size));
+        let u128_0x70 = constant(vec![0x70; 16]);
+        narrow.legalize(
+            def!(a = swizzle(x, y)),
+            vec![
+                def!(b = vconst(u128_0x70)),
+                def!(c = uadd_sat(y, b)),
+                def!(a = x86_pshufb(x, c)),
+            ],
+        );
+    }
+
+    // SIMD imul (8-bit): multiply as 16-bit lanes twice, once for the even bytes (the low byte of
+    // each product is correct) and once for the odd bytes (shifted into the high byte of each
+    // product), then merge the two.
+    {
+        let imul_i8x16 = imul.bind(vector(I8, sse_vector_size));
+        let raw_bitcast_x_to_i16x8 = raw_bitcast
+            .bind(vector(I16, sse_vector_size))
+            .bind(vector(I8, sse_vector_size));
+        let raw_bitcast_y_to_i16x8 = raw_bitcast
+            .bind(vector(I16, sse_vector_size))
+            .bind(vector(I8, sse_vector_size));
+        let raw_bitcast_from_i16x8 = raw_bitcast
+            .bind(vector(I8, sse_vector_size))
+            .bind(vector(I16, sse_vector_size));
+        let uimm8_eight = Literal::constant(&imm.uimm8, 8);
+        let u128_low_bytes = constant(vec![
+            0xff, 0, 0xff, 0, 0xff, 0, 0xff, 0, 0xff, 0, 0xff, 0, 0xff, 0, 0xff, 0,
+        ]);
+        let i = var("i");
+        let j = var("j");
+        let k = var("k");
+        narrow.legalize(
+            def!(a = imul_i8x16(x, y)),
+            vec![
+                def!(b = raw_bitcast_x_to_i16x8(x)),
+                def!(c = raw_bitcast_y_to_i16x8(y)),
+                def!(d = imul(b, c)),
+                def!(e = ushr_imm(b, uimm8_eight)),
+                def!(f = ushr_imm(c, uimm8_eight)),
+                def!(g = ishl_imm(f, uimm8_eight)),
+                def!(h = imul(e, g)),
+                def!(i = vconst(u128_low_bytes)),
+                def!(j = band(d, i)),
+                def!(k = bor(j, h)),
+                def!(a = raw_bitcast_from_i16x8(k)),
+            ],
+        );
+    }
+
+    // SIMD imul (64-bit): there is no PMULLQ before AVX-512 so the product is assembled from
+    // 32-bit halves: `lo(x) * lo(y) + ((hi(x) * lo(y) + lo(x) * hi(y)) << 32)`.
+    {
+        let imul_i64x2 = imul.bind(vector(I64, sse_vector_size));
+        let uimm8_thirty_two = Literal::constant(&imm.uimm8, 32);
+        narrow.legalize(
+            def!(a = imul_i64x2(x, y)),
+            vec![
+                def!(b = ushr_imm(x, uimm8_thirty_two)),
+                def!(c = x86_pmuludq(b, y)),
+                def!(d = ushr_imm(y, uimm8_thirty_two)),
+                def!(e = x86_pmuludq(x, d)),
+                def!(f = iadd(c, e)),
+                def!(g = ishl_imm(f, uimm8_thirty_two)),
+                def!(h = x86_pmuludq(x, y)),
+                def!(a = iadd(h, g)),
+            ],
+        );
+    }
+
+    // SIMD fcvt_from_uint (32-bit): CVTDQ2PS only converts signed integers, so the low 16 bits
+    // and the (halved) high 16 bits are converted separately; both conversions are exact and
+    // only the final addition rounds.
+    {
+        let fcvt_from_uint = fcvt_from_uint
+            .bind(vector(F32, sse_vector_size))
+            .bind(vector(I32, sse_vector_size));
+        let fcvt_from_sint_high = fcvt_from_sint
+            .bind(vector(F32, sse_vector_size))
+            .bind(vector(I32, sse_vector_size));
+        let fcvt_from_sint_low = fcvt_from_sint
+            .bind(vector(F32, sse_vector_size))
+            .bind(vector(I32, sse_vector_size));
+        let uimm8_sixteen = Literal::constant(&imm.uimm8, 16);
+        narrow.legalize(
+            def!(a = fcvt_from_uint(x)),
+            vec![
+                def!(b = ishl_imm(x, uimm8_sixteen)),
+                def!(c = ushr_imm(b, uimm8_sixteen)), // the low 16 bits
+                def!(d = isub(x, c)),                 // the high 16 bits
+                def!(e = ushr_imm(d, uimm8_one)),     // halved so that the lane is non-negative
+                def!(f = fcvt_from_sint_high(e)),
+                def!(g = fadd(f, f)),
+                def!(h = fcvt_from_sint_low(c)),
+                def!(a = fadd(g, h)),
+            ],
+        );
+    }
+
+    // SIMD fcvt_to_sint_sat (32-bit): CVTTPS2DQ returns 0x80000000 for NaN and out-of-range
+    // lanes; zero the NaN lanes beforehand and flip the positive overflows to 0x7fffffff after.
+    let eq = Literal::enumerator_for(&imm.floatcc, "eq");
+    let le = Literal::enumerator_for(&imm.floatcc, "le");
+    let u128_f32_two_pow_31 = constant(vec![
+        0, 0, 0, 0x4f, 0, 0, 0, 0x4f, 0, 0, 0, 0x4f, 0, 0, 0, 0x4f,
+    ]);
+    {
+        let fcvt_to_sint_sat = fcvt_to_sint_sat
+            .bind(vector(I32, sse_vector_size))
+            .bind(vector(F32, sse_vector_size));
+        let x86_cvtt2si = x86_cvtt2si
+            .bind(vector(I32, sse_vector_size))
+            .bind(vector(F32, sse_vector_size));
+        let raw_bitcast_to_f32x4 = raw_bitcast
+            .bind(vector(F32, sse_vector_size))
+            .bind(vector(B32, sse_vector_size));
+        let raw_bitcast_to_i32x4 = raw_bitcast
+            .bind(vector(I32, sse_vector_size))
+            .bind(vector(B32, sse_vector_size));
+        narrow.legalize(
+            def!(a = fcvt_to_sint_sat(x)),
+            vec![
+                def!(b = fcmp(eq, x, x)),
+                def!(c = raw_bitcast_to_f32x4(b)),
+                def!(d = band(x, c)), // NaN lanes are now 0.0
+                def!(e = x86_cvtt2si(d)),
+                def!(f = vconst(u128_f32_two_pow_31)),
+                def!(g = fcmp(le, f, d)),
+                def!(h = raw_bitcast_to_i32x4(g)),
+                def!(a = bxor(e, h)),
+            ],
+        );
+    }
+
+    // SIMD fcvt_to_uint_sat (32-bit): clamp NaN and negative lanes to 0.0 with MAXPS, convert
+    // lanes below 2^31 directly and lanes above after subtracting 2^31 (CVTTPS2DQ of the former
+    // then supplies the top bit), and saturate lanes above 2^32.
+    {
+        let fcvt_to_uint_sat = fcvt_to_uint_sat
+            .bind(vector(I32, sse_vector_size))
+            .bind(vector(F32, sse_vector_size));
+        let x86_cvtt2si_low = x86_cvtt2si
+            .bind(vector(I32, sse_vector_size))
+            .bind(vector(F32, sse_vector_size));
+        let x86_cvtt2si_high = x86_cvtt2si
+            .bind(vector(I32, sse_vector_size))
+            .bind(vector(F32, sse_vector_size));
+        let raw_bitcast_high_to_i32x4 = raw_bitcast
+            .bind(vector(I32, sse_vector_size))
+            .bind(vector(B32, sse_vector_size));
+        let raw_bitcast_overflow_to_i32x4 = raw_bitcast
+            .bind(vector(I32, sse_vector_size))
+            .bind(vector(B32, sse_vector_size));
+        let i = var("i");
+        let j = var("j");
+        let k = var("k");
+        let l = var("l");
+        let m = var("m");
+        let n = var("n");
+        narrow.legalize(
+            def!(a = fcvt_to_uint_sat(x)),
+            vec![
+                def!(b = vconst(u128_zeroes)),
+                def!(c = x86_fmax(x, b)), // NaN and negative lanes are now 0.0
+                def!(d = vconst(u128_f32_two_pow_31)),
+                def!(e = fsub(c, d)),
+                def!(f = x86_cvtt2si_low(c)), // 0x80000000 for lanes >= 2^31
+                def!(g = x86_cvtt2si_high(e)),
+                def!(h = fcmp(le, d, c)),
+                def!(i = raw_bitcast_high_to_i32x4(h)),
+                def!(j = band(g, i)),
+                def!(k = bor(f, j)),
+                def!(l = fadd(d, d)),
+                def!(m = fcmp(le, l, c)),
+                def!(n = raw_bitcast_overflow_to_i32x4(m)),
+                def!(a = bor(k, n)),
+            ],
+        );
+    }
+
+    // SIMD conversions with 64-bit lanes: SSE has no packed conversions between 64-bit integers
+    // and floats, so convert each lane with the scalar instructions.
+    for (inst, from, to) in &[
+        (fcvt_from_sint, LaneType::from(I64), LaneType::from(F64)),
+        (fcvt_from_uint, LaneType::from(I64), LaneType::from(F64)),
+        (fcvt_to_sint_sat, LaneType::from(F64), LaneType::from(I64)),
+        (fcvt_to_uint_sat, LaneType::from(F64), LaneType::from(I64)),
+    ] {
+        let vector_inst = inst
+            .bind(vector(*to, sse_vector_size))
+            .bind(vector(*from, sse_vector_size));
+        let scalar_inst_low = inst.bind(*to).bind(*from);
+        let scalar_inst_high = inst.bind(*to).bind(*from);
+        let scalar_to_vector = scalar_to_vector.bind(vector(*to, sse_vector_size));
+        narrow.legalize(
+            def!(a = vector_inst(x)),
+            vec![
+                def!(b = extractlane(x, uimm8_zero)),
+                def!(c = extractlane(x, uimm8_one)),
+                def!(d = scalar_inst_low(b)),
+                def!(e = scalar_inst_high(c)),
+                def!(f = scalar_to_vector(d)),
+                def!(a = insertlane(f, uimm8_one, e)),
+            ],
+        );
+    }
+
     narrow.custom_legalize(shuffle, "convert_shuffle");
     narrow.custom_legalize(extractlane, "convert_extractlane");
     narrow.custom_legalize(insertlane, "convert_insertlane");
@@ -383,6 +383,7 @@ pub(crate) fn define<'shared>(
     let reg_rax = Register::new(gpr, regs.regunit_by_name(gpr, "rax"));
     let reg_rcx = Register::new(gpr, regs.regunit_by_name(gpr, "rcx"));
     let reg_rdx = Register::new(gpr, regs.regunit_by_name(gpr, "rdx"));
+    let reg_r11 = Register::new(gpr, regs.regunit_by_name(gpr, "r11"));
     let reg_r15 = Register::new(gpr, regs.regunit_by_name(gpr, "r15"));
 
     // Stack operand with a 32-bit signed displacement from either RBP or RSP.
@@ -1141,6 +1142,87 @@ pub(crate) fn define<'shared>(
             ),
     );
 
+    // XX /n with SIB addressing of the stack pointer and a zero imm8, for `or $0, (%rsp)`.
+    recipes.add_template_recipe(
+        EncodingRecipeBuilder::new("stack_probe", &formats.nullary, 3).emit(
+            r#"
+                    sink.trap(TrapCode::StackOverflow, func.srclocs[inst]);
+                    {{PUT_OP}}(bits, rex1(RU::rsp.into()), sink);
+                    modrm_sib(bits >> 12, sink);
+                    sib_noindex(RU::rsp.into(), sink);
+                    sink.put1(0);
+                "#,
+        ),
+    );
+
+    // Inline stack probe loops. These use %rax as a scratch register, which is free in the
+    // prologue under all of the calling conventions that probe the stack.
+    //
+    // mov %esp, %eax; sub $size, %eax
+    // loop: sub $probe_size, %esp; or $0, (%esp); cmp %eax, %esp; jne loop
+    recipes.add_recipe(
+        EncodingRecipeBuilder::new("stack_probe_loop32", &formats.unary_imm, 22).emit(
+            r#"
+                    let size: i64 = imm.into();
+                    let probe_size: i64 = 1 << isa.flags().probestack_size_log2();
+
+                    sink.put1(0x89);
+                    modrm_rr(RU::rax.into(), RU::rsp.into(), sink);
+                    sink.put1(0x81);
+                    modrm_rr(RU::rax.into(), 5, sink);
+                    sink.put4(size as u32);
+
+                    sink.put1(0x81);
+                    modrm_rr(RU::rsp.into(), 5, sink);
+                    sink.put4(probe_size as u32);
+                    sink.trap(TrapCode::StackOverflow, func.srclocs[inst]);
+                    sink.put1(0x83);
+                    modrm_sib(1, sink);
+                    sib_noindex(RU::rsp.into(), sink);
+                    sink.put1(0);
+                    sink.put1(0x39);
+                    modrm_rr(RU::rsp.into(), RU::rax.into(), sink);
+                    sink.put1(0x75);
+                    sink.put1(-14i8 as u8);
+                "#,
+        ),
+    );
+
+    // mov %rsp, %rax; sub $size, %rax
+    // loop: sub $probe_size, %rsp; or $0, (%rsp); cmp %rax, %rsp; jne loop
+    recipes.add_recipe(
+        EncodingRecipeBuilder::new("stack_probe_loop64", &formats.unary_imm, 27).emit(
+            r#"
+                    let size: i64 = imm.into();
+                    let probe_size: i64 = 1 << isa.flags().probestack_size_log2();
+
+                    sink.put1(0x48);
+                    sink.put1(0x89);
+                    modrm_rr(RU::rax.into(), RU::rsp.into(), sink);
+                    sink.put1(0x48);
+                    sink.put1(0x81);
+                    modrm_rr(RU::rax.into(), 5, sink);
+                    sink.put4(size as u32);
+
+                    sink.put1(0x48);
+                    sink.put1(0x81);
+                    modrm_rr(RU::rsp.into(), 5, sink);
+                    sink.put4(probe_size as u32);
+                    sink.trap(TrapCode::StackOverflow, func.srclocs[inst]);
+                    sink.put1(0x48);
+                    sink.put1(0x83);
+                    modrm_sib(1, sink);
+                    sib_noindex(RU::rsp.into(), sink);
+                    sink.put1(0);
+                    sink.put1(0x48);
+                    sink.put1(0x39);
+                    modrm_rr(RU::rsp.into(), RU::rax.into(), sink);
+                    sink.put1(0x75);
+                    sink.put1(-17i8 as u8);
+                "#,
+        ),
+    );
+
     recipes.add_template_recipe(
         EncodingRecipeBuilder::new("popq", &formats.nullary, 0)
             .operands_out(vec![gpr])
@@ -1378,6 +1460,80 @@ pub(crate) fn define<'shared>(
             ),
     );
 
+    // Thread local storage addresses. These have fixed output registers and sequences, since the
+    // linker may rewrite them to access sequences of a cheaper TLS model.
+
+    // data16 lea gv@tlsgd(%rip), %rdi; data16 data16 rex.w call __tls_get_addr@plt
+    recipes.add_recipe(
+        EncodingRecipeBuilder::new("elf_tls_get_addr", &formats.unary_global_value, 16)
+            .operands_out(vec![reg_rax])
+            .emit(
+                r#"
+                    sink.put1(0x66); // data16
+                    sink.put1(0x48); // rex.w
+                    sink.put1(0x8d); // lea
+                    modrm_riprel(RU::rdi.into(), sink);
+                    sink.reloc_external(Reloc::ElfX86_64TlsGd,
+                                        &func.global_values[global_value].symbol_name(),
+                                        -4);
+                    sink.put4(0);
+
+                    sink.put1(0x66); // data16
+                    sink.put1(0x66); // data16
+                    sink.put1(0x48); // rex.w
+                    sink.put1(0xe8); // call
+                    sink.reloc_external(Reloc::X86CallPLTRel4,
+                                        &ExternalName::LibCall(LibCall::ElfTlsGetAddr),
+                                        -4);
+                    sink.put4(0);
+                "#,
+            ),
+    );
+
+    // mov %fs:0, %rax; add gv@gottpoff(%rip), %rax
+    recipes.add_recipe(
+        EncodingRecipeBuilder::new("elf_tls_ie_addr", &formats.unary_global_value, 16)
+            .operands_out(vec![reg_rax])
+            .emit(
+                r#"
+                    sink.put1(0x64); // %fs segment override
+                    sink.put1(0x48); // rex.w
+                    sink.put1(0x8b); // mov
+                    modrm_sib(RU::rax.into(), sink);
+                    sib_noindex(0b101, sink); // no base, disp32
+                    sink.put4(0);
+
+                    sink.put1(0x48); // rex.w
+                    sink.put1(0x03); // add
+                    modrm_riprel(RU::rax.into(), sink);
+                    sink.reloc_external(Reloc::ElfX86_64GotTpOff,
+                                        &func.global_values[global_value].symbol_name(),
+                                        -4);
+                    sink.put4(0);
+                "#,
+            ),
+    );
+
+    // mov gv@tlv(%rip), %rdi; call *(%rdi)
+    recipes.add_recipe(
+        EncodingRecipeBuilder::new("macho_tls_get_addr", &formats.unary_global_value, 9)
+            .operands_out(vec![reg_rax])
+            .emit(
+                r#"
+                    sink.put1(0x48); // rex.w
+                    sink.put1(0x8b); // mov
+                    modrm_riprel(RU::rdi.into(), sink);
+                    sink.reloc_external(Reloc::MachOX86_64Tlv,
+                                        &func.global_values[global_value].symbol_name(),
+                                        -4);
+                    sink.put4(0);
+
+                    sink.put1(0xff); // call
+                    sink.put1(0x17); // *(%rdi)
+                "#,
+            ),
+    );
+
     // Stack addresses.
     //
     // TODO Alternative forms for 8-bit immediates, when applicable.
@@ -2280,6 +2436,80 @@ pub(crate) fn define<'shared>(
         );
     }
 
+    // Atomic memory accesses with a register-indirect address and no offset. Atomic loads use
+    // the plain load recipes above.
+    {
+        // F0 XX /r locked read-modify-write. The old value in memory replaces the register
+        // operand. The prefix is redundant for `xchg`, which is always locked.
+        recipes.add_template_recipe(
+            EncodingRecipeBuilder::new("lock_rmw", &formats.atomic_rmw, 2)
+                .operands_in(vec![gpr, gpr])
+                .operands_out(vec![1])
+                .compute_size("size_plus_maybe_sib_or_offset_for_inreg_0")
+                .emit(
+                    r#"
+                        if !flags.notrap() {
+                            sink.trap(TrapCode::HeapOutOfBounds, func.srclocs[inst]);
+                        }
+                        sink.put1(0xf0);
+                        {{PUT_OP}}(bits, rex2(in_reg0, in_reg1), sink);
+                        if needs_sib_byte(in_reg0) {
+                            modrm_sib(in_reg1, sink);
+                            sib_noindex(in_reg0, sink);
+                        } else if needs_offset(in_reg0) {
+                            modrm_disp8(in_reg0, in_reg1, sink);
+                            sink.put1(0);
+                        } else {
+                            modrm_rm(in_reg0, in_reg1, sink);
+                        }
+                    "#,
+                ),
+        );
+
+        // F0 XX /r locked compare and exchange. The expected value is in %rax, which receives
+        // the old value in memory.
+        recipes.add_template_recipe(
+            EncodingRecipeBuilder::new("lock_cas", &formats.atomic_cas, 2)
+                .operands_in(vec![
+                    OperandConstraint::RegClass(gpr),
+                    OperandConstraint::FixedReg(reg_rax),
+                    OperandConstraint::RegClass(gpr),
+                ])
+                .operands_out(vec![reg_rax])
+                .compute_size("size_plus_maybe_sib_or_offset_for_inreg_0")
+                .emit(
+                    r#"
+                        if !flags.notrap() {
+                            sink.trap(TrapCode::HeapOutOfBounds, func.srclocs[inst]);
+                        }
+                        sink.put1(0xf0);
+                        {{PUT_OP}}(bits, rex2(in_reg0, in_reg2), sink);
+                        if needs_sib_byte(in_reg0) {
+                            modrm_sib(in_reg2, sink);
+                            sib_noindex(in_reg0, sink);
+                        } else if needs_offset(in_reg0) {
+                            modrm_disp8(in_reg0, in_reg2, sink);
+                            sink.put1(0);
+                        } else {
+                            modrm_rm(in_reg0, in_reg2, sink);
+                        }
+                    "#,
+                ),
+        );
+
+        // XX /6 with the fixed ModR/M byte of the fence instructions.
+        recipes.add_template_recipe(
+            EncodingRecipeBuilder::new("fence", &formats.nullary, 1)
+                .clobbers_flags(false)
+                .emit(
+                    r#"
+                        {{PUT_OP}}(bits, BASE_REX, sink);
+                        sink.put1(0xf0);
+                    "#,
+                ),
+        );
+    }
+
     // Unary fill with SIB and 32-bit displacement.
     recipes.add_template_recipe(
         EncodingRecipeBuilder::new("fillSib32", &formats.unary, 6)
@@ -2390,9 +2620,103 @@ pub(crate) fn define<'shared>(
             ),
     );
 
+    // Invokes are emitted exactly like calls. Their landing pad is found by the unwinder through
+    // the landing pad table, not through the instruction.
+    recipes.add_template_recipe(
+        EncodingRecipeBuilder::new("invoke_id", &formats.invoke, 4).emit(
+            r#"
+            sink.trap(TrapCode::StackOverflow, func.srclocs[inst]);
+            {{PUT_OP}}(bits, BASE_REX, sink);
+            sink.reloc_external(Reloc::X86CallPCRel4,
+                                &func.dfg.ext_funcs[func_ref].name,
+                                -4);
+            sink.put4(0);
+        "#,
+        ),
+    );
+
+    recipes.add_template_recipe(
+        EncodingRecipeBuilder::new("invoke_plt_id", &formats.invoke, 4).emit(
+            r#"
+            sink.trap(TrapCode::StackOverflow, func.srclocs[inst]);
+            {{PUT_OP}}(bits, BASE_REX, sink);
+            sink.reloc_external(Reloc::X86CallPLTRel4,
+                                &func.dfg.ext_funcs[func_ref].name,
+                                -4);
+            sink.put4(0);
+        "#,
+        ),
+    );
+
+    recipes.add_template_recipe(
+        EncodingRecipeBuilder::new("invoke_r", &formats.invoke_indirect, 1)
+            .operands_in(vec![gpr])
+            .emit(
+                r#"
+                    sink.trap(TrapCode::StackOverflow, func.srclocs[inst]);
+                    {{PUT_OP}}(bits, rex1(in_reg0), sink);
+                    modrm_r_bits(in_reg0, bits, sink);
+                "#,
+            ),
+    );
+
+    // Tail calls jump to the callee after the epilogue has torn down the frame. Unlike calls, they
+    // don't push anything so they can't overflow the stack.
+    recipes.add_template_recipe(
+        EncodingRecipeBuilder::new("tail_call_id", &formats.call, 4).emit(
+            r#"
+            {{PUT_OP}}(bits, BASE_REX, sink);
+            sink.reloc_external(Reloc::X86CallPCRel4,
+                                &func.dfg.ext_funcs[func_ref].name,
+                                -4);
+            sink.put4(0);
+        "#,
+        ),
+    );
+
+    recipes.add_template_recipe(
+        EncodingRecipeBuilder::new("tail_call_plt_id", &formats.call, 4).emit(
+            r#"
+            {{PUT_OP}}(bits, BASE_REX, sink);
+            sink.reloc_external(Reloc::X86CallPLTRel4,
+                                &func.dfg.ext_funcs[func_ref].name,
+                                -4);
+            sink.put4(0);
+        "#,
+        ),
+    );
+
+    // The callee of an indirect tail call lives in %r11, which is neither an argument register nor
+    // callee-saved, so it survives the epilogue.
+    recipes.add_template_recipe(
+        EncodingRecipeBuilder::new("tail_call_r", &formats.call_indirect, 1)
+            .operands_in(vec![OperandConstraint::FixedReg(reg_r11)])
+            .emit(
+                r#"
+                    let callee = RU::r11 as RegUnit;
+                    {{PUT_OP}}(bits, rex1(callee), sink);
+                    modrm_r_bits(callee, bits, sink);
+                "#,
+            ),
+    );
+
+    // Functions whose calling convention makes the callee pop its stack arguments return with
+    // `ret imm16` instead.
     recipes.add_template_recipe(
         EncodingRecipeBuilder::new("ret", &formats.multiary, 0)
-            .emit("{{PUT_OP}}(bits, BASE_REX, sink);"),
+            .compute_size("size_plus_callee_pop_imm")
+            .emit(
+                r#"
+                    let pop_size = callee_pop_size(&func.signature);
+                    if pop_size == 0 {
+                        {{PUT_OP}}(bits, BASE_REX, sink);
+                    } else {
+                        // RET imm16.
+                        sink.put1(0xc2);
+                        sink.put2(pop_size);
+                    }
+                "#,
+            ),
     );
 
     // Branches.
@@ -3,6 +3,8 @@ use crate::shared::{entities::EntityRefs, immediates::Immediates};
 use std::rc::Rc;
 
 pub(crate) struct Formats {
+    pub(crate) atomic_cas: Rc<InstructionFormat>,
+    pub(crate) atomic_rmw: Rc<InstructionFormat>,
     pub(crate) binary: Rc<InstructionFormat>,
     pub(crate) binary_imm: Rc<InstructionFormat>,
     pub(crate) branch: Rc<InstructionFormat>,
@@ -30,6 +32,8 @@ pub(crate) struct Formats {
     pub(crate) int_cond: Rc<InstructionFormat>,
     pub(crate) int_cond_trap: Rc<InstructionFormat>,
     pub(crate) int_select: Rc<InstructionFormat>,
+    pub(crate) invoke: Rc<InstructionFormat>,
+    pub(crate) invoke_indirect: Rc<InstructionFormat>,
     pub(crate) jump: Rc<InstructionFormat>,
     pub(crate) load: Rc<InstructionFormat>,
     pub(crate) load_complex: Rc<InstructionFormat>,
@@ -203,6 +207,19 @@ impl Formats {
                 .varargs()
                 .build(),
 
+            invoke: Builder::new("Invoke")
+                .imm(&entities.func_ref)
+                .imm(&entities.ebb)
+                .varargs()
+                .build(),
+
+            invoke_indirect: Builder::new("InvokeIndirect")
+                .imm(&entities.sig_ref)
+                .imm(&entities.ebb)
+                .value()
+                .varargs()
+                .build(),
+
             func_addr: Builder::new("FuncAddr").imm(&entities.func_ref).build(),
 
             load: Builder::new("Load")
@@ -231,6 +248,23 @@ impl Formats {
                 .imm(&imm.offset32)
                 .build(),
 
+            // Atomic memory accesses, controlled by the type of the value operand.
+            atomic_rmw: Builder::new("AtomicRmw")
+                .imm(&imm.memflags)
+                .imm(&imm.atomic_rmw_op)
+                .value()
+                .value()
+                .typevar_operand(1)
+                .build(),
+
+            atomic_cas: Builder::new("AtomicCas")
+                .imm(&imm.memflags)
+                .value()
+                .value()
+                .value()
+                .typevar_operand(1)
+                .build(),
+
             stack_load: Builder::new("StackLoad")
                 .imm(&entities.stack_slot)
                 .imm(&imm.offset32)
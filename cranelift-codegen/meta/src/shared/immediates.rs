@@ -64,6 +64,12 @@ pub(crate) struct Immediates {
     /// Flags for memory operations like `load` and `store`.
     pub memflags: OperandKind,
 
+    /// The operation performed by an atomic read-modify-write instruction.
+    ///
+    /// This enumerated operand kind is used for the `atomic_rmw` instruction and corresponds to
+    /// the `ir::AtomicRmwOp` Rust type.
+    pub atomic_rmw_op: OperandKind,
+
     /// A register unit in the current target ISA.
     pub regunit: OperandKind,
 
@@ -146,6 +152,17 @@ impl Immediates {
             },
 
             memflags: new_imm("flags", "ir::MemFlags").with_doc("Memory operation flags"),
+            atomic_rmw_op: {
+                let mut atomic_rmw_op_values = HashMap::new();
+                atomic_rmw_op_values.insert("add", "Add");
+                atomic_rmw_op_values.insert("sub", "Sub");
+                atomic_rmw_op_values.insert("and", "And");
+                atomic_rmw_op_values.insert("or", "Or");
+                atomic_rmw_op_values.insert("xor", "Xor");
+                atomic_rmw_op_values.insert("xchg", "Xchg");
+                new_enum("op", "ir::AtomicRmwOp", atomic_rmw_op_values)
+                    .with_doc("An atomic read-modify-write operation.")
+            },
             regunit: new_imm("regunit", "isa::RegUnit")
                 .with_doc("A register unit in the target ISA"),
             trapcode: {
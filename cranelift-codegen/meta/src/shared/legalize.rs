@@ -132,6 +132,8 @@ pub(crate) fn define(insts: &InstructionGroup, imm: &Immediates) -> TransformGro
 
     // Custom expansions for calls.
     expand.custom_legalize(insts.by_name("call"), "expand_call");
+    expand.custom_legalize(insts.by_name("return_call"), "expand_call");
+    expand.custom_legalize(insts.by_name("invoke"), "expand_call");
 
     // Custom expansions that need to change the CFG.
     // TODO: Add sufficient XForm syntax that we don't need to hand-code these.
@@ -154,6 +156,15 @@ pub(crate) fn define(insts: &InstructionGroup, imm: &Immediates) -> TransformGro
     widen.custom_legalize(insts.by_name("stack_load"), "expand_stack_load");
     widen.custom_legalize(insts.by_name("stack_store"), "expand_stack_store");
 
+    // Custom expansions of atomic operations in terms of `atomic_cas`. The read-modify-write
+    // operations become loops, which need to change the CFG.
+    let atomic_rmw = insts.by_name("atomic_rmw");
+    let atomic_store = insts.by_name("atomic_store");
+    expand.custom_legalize(atomic_rmw, "expand_atomic_rmw");
+    expand.custom_legalize(atomic_store, "expand_atomic_store");
+    widen.custom_legalize(atomic_rmw, "expand_atomic_rmw");
+    widen.custom_legalize(atomic_store, "expand_atomic_store");
+
     // List of variables to reuse in patterns.
     let x = var("x");
     let y = var("y");
@@ -904,6 +915,89 @@ pub(crate) fn define(insts: &InstructionGroup, imm: &Immediates) -> TransformGro
         ],
     );
 
+    // Population count, by adding up the bits in parallel.
+    let qv3 = var("qv3");
+    let qv4 = var("qv4");
+    let qv5 = var("qv5");
+    let qv6 = var("qv6");
+    let qv7 = var("qv7");
+    let qv8 = var("qv8");
+    let qv9 = var("qv9");
+    let qv10 = var("qv10");
+    let qv11 = var("qv11");
+    let qv12 = var("qv12");
+    let qv13 = var("qv13");
+    let qv14 = var("qv14");
+    let qv15 = var("qv15");
+    let qc77 = var("qc77");
+    #[allow(non_snake_case)]
+    let qc0F = var("qc0F");
+    let qc01 = var("qc01");
+
+    expand.legalize(
+        def!(a = popcnt.I64(x)),
+        vec![
+            def!(qv3 = ushr_imm(x, imm64_1)),
+            def!(qc77 = iconst(Literal::constant(&imm.imm64, 0x7777_7777_7777_7777))),
+            def!(qv4 = band(qv3, qc77)),
+            def!(qv5 = isub(x, qv4)),
+            def!(qv6 = ushr_imm(qv4, imm64_1)),
+            def!(qv7 = band(qv6, qc77)),
+            def!(qv8 = isub(qv5, qv7)),
+            def!(qv9 = ushr_imm(qv7, imm64_1)),
+            def!(qv10 = band(qv9, qc77)),
+            def!(qv11 = isub(qv8, qv10)),
+            def!(qv12 = ushr_imm(qv11, imm64_4)),
+            def!(qv13 = iadd(qv11, qv12)),
+            def!(qc0F = iconst(Literal::constant(&imm.imm64, 0x0F0F_0F0F_0F0F_0F0F))),
+            def!(qv14 = band(qv13, qc0F)),
+            def!(qc01 = iconst(Literal::constant(&imm.imm64, 0x0101_0101_0101_0101))),
+            def!(qv15 = imul(qv14, qc01)),
+            def!(a = ushr_imm(qv15, Literal::constant(&imm.imm64, 56))),
+        ],
+    );
+
+    let lv3 = var("lv3");
+    let lv4 = var("lv4");
+    let lv5 = var("lv5");
+    let lv6 = var("lv6");
+    let lv7 = var("lv7");
+    let lv8 = var("lv8");
+    let lv9 = var("lv9");
+    let lv10 = var("lv10");
+    let lv11 = var("lv11");
+    let lv12 = var("lv12");
+    let lv13 = var("lv13");
+    let lv14 = var("lv14");
+    let lv15 = var("lv15");
+    let lc77 = var("lc77");
+    #[allow(non_snake_case)]
+    let lc0F = var("lc0F");
+    let lc01 = var("lc01");
+
+    expand.legalize(
+        def!(a = popcnt.I32(x)),
+        vec![
+            def!(lv3 = ushr_imm(x, imm64_1)),
+            def!(lc77 = iconst(Literal::constant(&imm.imm64, 0x7777_7777))),
+            def!(lv4 = band(lv3, lc77)),
+            def!(lv5 = isub(x, lv4)),
+            def!(lv6 = ushr_imm(lv4, imm64_1)),
+            def!(lv7 = band(lv6, lc77)),
+            def!(lv8 = isub(lv5, lv7)),
+            def!(lv9 = ushr_imm(lv7, imm64_1)),
+            def!(lv10 = band(lv9, lc77)),
+            def!(lv11 = isub(lv8, lv10)),
+            def!(lv12 = ushr_imm(lv11, imm64_4)),
+            def!(lv13 = iadd(lv11, lv12)),
+            def!(lc0F = iconst(Literal::constant(&imm.imm64, 0x0F0F_0F0F))),
+            def!(lv14 = band(lv13, lc0F)),
+            def!(lc01 = iconst(Literal::constant(&imm.imm64, 0x0101_0101))),
+            def!(lv15 = imul(lv14, lc01)),
+            def!(a = ushr_imm(lv15, Literal::constant(&imm.imm64, 24))),
+        ],
+    );
+
     // Floating-point sign manipulations.
     for &(ty, const_inst, minus_zero) in &[
         (F32, f32const, &Literal::bits(&imm.ieee32, 0x8000_0000)),
@@ -36,6 +36,20 @@ pub(crate) fn define() -> SettingGroup {
         false,
     );
 
+    settings.add_enum(
+        "tls_model",
+        r#"
+        Defines the model used to perform TLS accesses.
+
+        - none: TLS accesses aren't supported.
+        - elf_gd: The general dynamic model for ELF, which calls `__tls_get_addr`.
+        - elf_ie: The initial exec model for ELF, which can only be used for
+          variables in the executable or in libraries loaded at startup.
+        - macho: The thread local variable descriptors of Mach-O.
+        "#,
+        vec!["none", "elf_gd", "elf_ie", "macho"],
+    );
+
     settings.add_bool(
         "colocated_libcalls",
         r#"
@@ -202,13 +216,26 @@ pub(crate) fn define() -> SettingGroup {
         false,
     );
 
+    settings.add_enum(
+        "probestack_strategy",
+        r#"
+            Defines how stack probes are emitted.
+
+            - outline: Call the probestack function from the prologue.
+            - inline: Probe the stack directly in the prologue, with an unrolled
+              sequence of probes for moderately sized frames and a loop for
+              larger ones. No probestack function is needed.
+            "#,
+        vec!["outline", "inline"],
+    );
+
     settings.add_num(
         "probestack_size_log2",
         r#"
             The log2 of the size of the stack guard region.
 
             Stack frames larger than this size will have stack overflow checked
-            by calling the probestack function.
+            by probing the stack, as selected by `probestack_strategy`.
 
             The default is 12, which translates to a size of 4096.
             "#,
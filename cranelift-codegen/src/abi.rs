@@ -3,7 +3,7 @@
 //! This module provides functions and data structures that are useful for implementing the
 //! `TargetIsa::legalize_signature()` method.
 
-use crate::ir::{AbiParam, ArgumentExtension, ArgumentLoc, Type};
+use crate::ir::{AbiParam, ArgumentExtension, ArgumentLoc, ArgumentPurpose, Signature, Type};
 use alloc::borrow::Cow;
 use alloc::vec::Vec;
 use core::cmp::Ordering;
@@ -126,6 +126,83 @@ pub fn legalize_args<AA: ArgAssigner>(args: &[AbiParam], aa: &mut AA) -> Option<
     }
 }
 
+/// Legalize the return values of `sig` using the return value assigner `rets`.
+///
+/// When a signature returns multiple values and they don't all fit in return registers, the
+/// callee writes the normal return values to memory instead. The address of that memory is passed
+/// in a `StructReturn` parameter assigned by `args`, so this must be called before the other
+/// parameters are legalized. If `return_ptr` is set, the address is also returned in the first
+/// return register.
+///
+/// The legalizer then rewrites calls and returns to use the return area; see
+/// `legalizer::boundary`.
+pub fn legalize_returns<AA: ArgAssigner + Clone>(
+    sig: &mut Cow<Signature>,
+    pointer_type: Type,
+    args: &mut AA,
+    rets: &mut AA,
+    return_ptr: bool,
+) {
+    // If the return values don't fit, we need to start assigning their locations all over again
+    // with a different strategy, so keep a copy of the original assigner.
+    let backup_rets = if sig.is_multi_return() {
+        Some(rets.clone())
+    } else {
+        None
+    };
+
+    let new_returns = match legalize_args(&sig.returns, rets) {
+        Some(new_returns) => new_returns,
+        None => return,
+    };
+
+    let fits_in_regs = new_returns
+        .iter()
+        .filter(|r| r.purpose == ArgumentPurpose::Normal)
+        .all(|r| r.location.is_reg());
+    let mut rets = match backup_rets {
+        Some(rets) if !fits_in_regs => rets,
+        _ => {
+            sig.to_mut().returns = new_returns;
+            return;
+        }
+    };
+
+    debug_assert!(!sig.uses_struct_return_param());
+    let mut ret_ptr_param = AbiParam::special(pointer_type, ArgumentPurpose::StructReturn);
+    match args.assign(&ret_ptr_param) {
+        ArgAction::Assign(loc) => ret_ptr_param.location = loc,
+        ArgAction::Convert(_) => unreachable!("return pointer should not need conversion"),
+    }
+    sig.to_mut().params.push(ret_ptr_param);
+
+    if return_ptr {
+        let mut ret_ptr_return = AbiParam::special(pointer_type, ArgumentPurpose::StructReturn);
+        match rets.assign(&ret_ptr_return) {
+            ArgAction::Assign(ArgumentLoc::Reg(reg)) => {
+                ret_ptr_return.location = ArgumentLoc::Reg(reg);
+            }
+            _ => unreachable!("return pointer should always get a register assignment"),
+        }
+        sig.to_mut().returns.push(ret_ptr_return);
+    }
+
+    sig.to_mut().returns.retain(|ret| {
+        // Either this is the return pointer, in which case we want to keep it, or else assume
+        // that it is assigned for a reason and doesn't conflict with our return pointer
+        // legalization.
+        debug_assert_eq!(
+            ret.location.is_assigned(),
+            ret.purpose != ArgumentPurpose::Normal
+        );
+        ret.location.is_assigned()
+    });
+
+    if let Some(new_returns) = legalize_args(&sig.returns, &mut rets) {
+        sig.to_mut().returns = new_returns;
+    }
+}
+
 /// Determine the right action to take when passing a `have` value type to a call signature where
 /// the next argument is `arg` which has a different value type.
 ///
@@ -56,6 +56,16 @@ pub enum Reloc {
     Arm64Call,
     /// RISC-V call target
     RiscvCall,
+
+    /// Elf x86_64 32 bit signed PC relative offset to the GOT entry of a thread local variable's
+    /// `tls_index`, for the general dynamic TLS model.
+    ElfX86_64TlsGd,
+    /// Elf x86_64 32 bit signed PC relative offset to the GOT entry holding the TP-relative offset
+    /// of a thread local variable, for the initial exec TLS model.
+    ElfX86_64GotTpOff,
+
+    /// Mach-O x86_64 32 bit signed PC relative offset to a `__thread_vars` entry.
+    MachOX86_64Tlv,
 }
 
 impl fmt::Display for Reloc {
@@ -71,6 +81,10 @@ impl fmt::Display for Reloc {
             Self::X86CallPLTRel4 => write!(f, "CallPLTRel4"),
             Self::X86GOTPCRel4 => write!(f, "GOTPCRel4"),
             Self::Arm32Call | Self::Arm64Call | Self::RiscvCall => write!(f, "Call"),
+
+            Self::ElfX86_64TlsGd => write!(f, "ElfX86_64TlsGd"),
+            Self::ElfX86_64GotTpOff => write!(f, "ElfX86_64GotTpOff"),
+            Self::MachOX86_64Tlv => write!(f, "MachOX86_64Tlv"),
         }
     }
 }
@@ -183,6 +197,26 @@ pub trait FrameUnwindSink {
 
     /// Specified offset to main structure.
     fn set_entry_offset(&mut self, _: FrameUnwindOffset);
+
+    /// Whether the sink can resolve PC-relative relocations, in which case the FDE refers to the
+    /// function with a `DW_EH_PE_pcrel | DW_EH_PE_sdata4` pointer relocated with
+    /// `Reloc::X86PCRel4`, as is usual in object files. Otherwise the FDE refers to it with an
+    /// absolute pointer, which is what the default implementation asks for.
+    fn pc_relative(&self) -> bool {
+        false
+    }
+
+    /// Add the language-specific data area describing the landing pads of `invoke` instructions.
+    /// It doesn't belong with the unwind information, but in a separate section such as
+    /// `.gcc_except_table`.
+    fn lsda(&mut self, _: &[u8]) {}
+
+    /// Add a relocation entry against the personality routine, which must understand GCC-style
+    /// language-specific data areas, like `__gcc_personality_v0`.
+    fn reloc_personality(&mut self, _: Reloc, _: FrameUnwindOffset) {}
+
+    /// Add a relocation entry against the start of the language-specific data area.
+    fn reloc_lsda(&mut self, _: Reloc, _: FrameUnwindOffset) {}
 }
 
 /// Report a bad encoding error.
@@ -26,6 +26,12 @@
 //!     jump ebb17
 //! ebb23:
 //! ```
+//!
+//! ISAs can also provide such a sequence as an encoding of its own, so relaxation only has to
+//! pick a larger encoding. Thumb-2 has 16-bit branches with a range of a few hundred bytes, and
+//! 32-bit conditional branches reaching 1 MB. Its short encodings are listed first, so every
+//! branch starts out short and is only extended when its destination is out of range, up to a
+//! conditional branch over an unconditional 32-bit branch.
 
 use crate::binemit::{CodeInfo, CodeOffset};
 use crate::cursor::{Cursor, FuncCursor};
@@ -168,6 +174,11 @@ fn try_fold_redundant_jump(
         }
     };
 
+    // The arguments of an `invoke` belong to the callee, so they can't be rewritten below.
+    if func.dfg[first_inst].opcode().is_call() {
+        return false;
+    }
+
     // For the moment, only attempt to fold a branch to an ebb that is parameterless.
     // These blocks are mainly produced by critical edge splitting.
     //
@@ -0,0 +1,187 @@
+//! Runtime values of Cranelift IR types.
+//!
+//! A `DataValue` holds a concrete value of some Cranelift type. It is the currency used when
+//! executing IR outside of generated code, e.g. by an interpreter or when passing arguments to and
+//! from a function under test.
+
+use crate::ir::immediates::{Ieee32, Ieee64};
+use crate::ir::{types, Type};
+use core::fmt::{self, Display, Formatter};
+
+/// A concrete value of a Cranelift type.
+///
+/// Integers are stored in their signed representation; the signedness of an operation is decided
+/// by the instruction consuming the value, not by the value itself. Vectors are stored as their
+/// little-endian bytes since their lane layout is only known from the instruction's controlling
+/// type.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DataValue {
+    /// A boolean of any width.
+    B(bool),
+    /// An 8-bit integer.
+    I8(i8),
+    /// A 16-bit integer.
+    I16(i16),
+    /// A 32-bit integer.
+    I32(i32),
+    /// A 64-bit integer.
+    I64(i64),
+    /// A 128-bit integer.
+    I128(i128),
+    /// A 32-bit IEEE float, stored as its bit pattern so that NaN payloads are preserved.
+    F32(Ieee32),
+    /// A 64-bit IEEE float, stored as its bit pattern so that NaN payloads are preserved.
+    F64(Ieee64),
+    /// A 128-bit vector of any lane type.
+    V128([u8; 16]),
+}
+
+impl DataValue {
+    /// Build a `DataValue` of type `ty` from the integer `imm`, truncating it to the width of
+    /// `ty`. Returns `None` if `ty` is not an integer or boolean type.
+    pub fn from_integer(imm: i64, ty: Type) -> Option<Self> {
+        Some(match ty {
+            types::I8 => DataValue::I8(imm as i8),
+            types::I16 => DataValue::I16(imm as i16),
+            types::I32 => DataValue::I32(imm as i32),
+            types::I64 => DataValue::I64(imm),
+            types::I128 => DataValue::I128(i128::from(imm)),
+            _ if ty.is_bool() => DataValue::B(imm != 0),
+            _ => return None,
+        })
+    }
+
+    /// Return the Cranelift IR type of this value. Booleans are reported as `b1` and vectors as
+    /// `i8x16`, since their actual width or lane layout is not recorded in the value.
+    pub fn ty(&self) -> Type {
+        match self {
+            DataValue::B(_) => types::B1,
+            DataValue::I8(_) => types::I8,
+            DataValue::I16(_) => types::I16,
+            DataValue::I32(_) => types::I32,
+            DataValue::I64(_) => types::I64,
+            DataValue::I128(_) => types::I128,
+            DataValue::F32(_) => types::F32,
+            DataValue::F64(_) => types::F64,
+            DataValue::V128(_) => types::I8X16,
+        }
+    }
+
+    /// Return true if this value is a boolean.
+    pub fn is_bool(&self) -> bool {
+        match self {
+            DataValue::B(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Return true if this value is a vector.
+    pub fn is_vector(&self) -> bool {
+        match self {
+            DataValue::V128(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Return the value as a signed integer, if it is one.
+    pub fn as_i128(&self) -> Option<i128> {
+        match *self {
+            DataValue::I8(x) => Some(i128::from(x)),
+            DataValue::I16(x) => Some(i128::from(x)),
+            DataValue::I32(x) => Some(i128::from(x)),
+            DataValue::I64(x) => Some(i128::from(x)),
+            DataValue::I128(x) => Some(x),
+            _ => None,
+        }
+    }
+}
+
+macro_rules! build_conversion_impl {
+    ( $rust_ty:ty, $data_value_ty:ident ) => {
+        impl From<$rust_ty> for DataValue {
+            fn from(data: $rust_ty) -> Self {
+                DataValue::$data_value_ty(data)
+            }
+        }
+    };
+}
+build_conversion_impl!(bool, B);
+build_conversion_impl!(i8, I8);
+build_conversion_impl!(i16, I16);
+build_conversion_impl!(i32, I32);
+build_conversion_impl!(i64, I64);
+build_conversion_impl!(i128, I128);
+build_conversion_impl!(Ieee32, F32);
+build_conversion_impl!(Ieee64, F64);
+build_conversion_impl!([u8; 16], V128);
+
+impl From<f32> for DataValue {
+    fn from(x: f32) -> Self {
+        DataValue::F32(Ieee32::with_float(x))
+    }
+}
+
+impl From<f64> for DataValue {
+    fn from(x: f64) -> Self {
+        DataValue::F64(Ieee64::with_float(x))
+    }
+}
+
+impl Display for DataValue {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            DataValue::B(b) => write!(f, "{}", b),
+            DataValue::I8(i) => write!(f, "{}", i),
+            DataValue::I16(i) => write!(f, "{}", i),
+            DataValue::I32(i) => write!(f, "{}", i),
+            DataValue::I64(i) => write!(f, "{}", i),
+            DataValue::I128(i) => write!(f, "{}", i),
+            DataValue::F32(x) => write!(f, "{}", x),
+            DataValue::F64(x) => write!(f, "{}", x),
+            // Vectors are printed like `vconst` immediates: most significant byte first.
+            DataValue::V128(bytes) => {
+                write!(f, "0x")?;
+                for b in bytes.iter().rev() {
+                    write!(f, "{:02x}", b)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn from_integer() {
+        assert_eq!(
+            DataValue::from_integer(0x1ff, types::I8),
+            Some(DataValue::I8(-1))
+        );
+        assert_eq!(
+            DataValue::from_integer(-1, types::I128),
+            Some(DataValue::I128(-1))
+        );
+        assert_eq!(
+            DataValue::from_integer(2, types::B32),
+            Some(DataValue::B(true))
+        );
+        assert_eq!(DataValue::from_integer(0, types::F32), None);
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(DataValue::I32(-42).to_string(), "-42");
+        assert_eq!(DataValue::from(1.5f32).to_string(), "0x1.800000p0");
+        let mut bytes = [0; 16];
+        bytes[0] = 0x01;
+        bytes[15] = 0xff;
+        assert_eq!(
+            DataValue::V128(bytes).to_string(),
+            "0xff000000000000000000000000000001"
+        );
+    }
+}
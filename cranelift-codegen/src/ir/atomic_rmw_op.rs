@@ -0,0 +1,81 @@
+//! Operations performed by the `atomic_rmw` instruction.
+
+use core::fmt::{self, Display, Formatter};
+use core::str::FromStr;
+#[cfg(feature = "enable-serde")]
+use serde::{Deserialize, Serialize};
+
+/// The operation combining the value in memory with the operand of an `atomic_rmw` instruction.
+///
+/// The result stored in memory is `op(old, x)`, where `old` is the value loaded from memory and
+/// `x` is the operand of the instruction.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+#[cfg_attr(feature = "enable-serde", derive(Serialize, Deserialize))]
+pub enum AtomicRmwOp {
+    /// Wrapping addition.
+    Add,
+    /// Wrapping subtraction.
+    Sub,
+    /// Bitwise and.
+    And,
+    /// Bitwise or.
+    Or,
+    /// Bitwise exclusive or.
+    Xor,
+    /// Replace the value in memory with `x`.
+    Xchg,
+}
+
+impl Display for AtomicRmwOp {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        use self::AtomicRmwOp::*;
+        f.write_str(match *self {
+            Add => "add",
+            Sub => "sub",
+            And => "and",
+            Or => "or",
+            Xor => "xor",
+            Xchg => "xchg",
+        })
+    }
+}
+
+impl FromStr for AtomicRmwOp {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use self::AtomicRmwOp::*;
+        match s {
+            "add" => Ok(Add),
+            "sub" => Ok(Sub),
+            "and" => Ok(And),
+            "or" => Ok(Or),
+            "xor" => Ok(Xor),
+            "xchg" => Ok(Xchg),
+            _ => Err(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    const OPS: [AtomicRmwOp; 6] = [
+        AtomicRmwOp::Add,
+        AtomicRmwOp::Sub,
+        AtomicRmwOp::And,
+        AtomicRmwOp::Or,
+        AtomicRmwOp::Xor,
+        AtomicRmwOp::Xchg,
+    ];
+
+    #[test]
+    fn display() {
+        for &op in &OPS {
+            assert_eq!(op.to_string().parse(), Ok(op));
+        }
+        assert_eq!("nand".parse::<AtomicRmwOp>(), Err(()));
+    }
+}
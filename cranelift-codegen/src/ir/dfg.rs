@@ -530,8 +530,8 @@ impl DataFlowGraph {
 
         self.results[inst].clear(&mut self.value_lists);
 
-        // Get the call signature if this is a function call.
-        if let Some(sig) = self.call_signature(inst) {
+        // Get the call signature if this is a function call that produces results.
+        if let Some(sig) = self.call_result_signature(inst) {
             // Create result values corresponding to the call return types.
             debug_assert_eq!(
                 self.insts[inst].opcode().constraints().num_fixed_results(),
@@ -690,6 +690,18 @@ impl DataFlowGraph {
         }
     }
 
+    /// Get the signature providing the result types of a call instruction.
+    ///
+    /// Tail calls are terminators which never return to the current function, so they have no
+    /// results even when the callee's signature has return values.
+    pub fn call_result_signature(&self, inst: Inst) -> Option<SigRef> {
+        if self.insts[inst].opcode().is_terminator() {
+            None
+        } else {
+            self.call_signature(inst)
+        }
+    }
+
     /// Check if `inst` is a branch.
     pub fn analyze_branch(&self, inst: Inst) -> BranchInfo {
         self.insts[inst].analyze_branch(&self.value_lists)
@@ -716,7 +728,7 @@ impl DataFlowGraph {
         }
 
         // Not a fixed result, try to extract a return type from the call signature.
-        self.call_signature(inst).and_then(|sigref| {
+        self.call_result_signature(inst).and_then(|sigref| {
             self.signatures[sigref]
                 .returns
                 .get(result_idx - num_fixed_results)
@@ -987,8 +999,8 @@ impl DataFlowGraph {
         ctrl_typevar: Type,
         reuse: &[Value],
     ) -> usize {
-        // Get the call signature if this is a function call.
-        if let Some(sig) = self.call_signature(inst) {
+        // Get the call signature if this is a function call that produces results.
+        if let Some(sig) = self.call_result_signature(inst) {
             assert_eq!(
                 self.insts[inst].opcode().constraints().num_fixed_results(),
                 0
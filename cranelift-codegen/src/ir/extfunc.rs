@@ -113,6 +113,24 @@ impl Signature {
             > 1
     }
 
+    /// Get the number of bytes of stack space used by the parameters of a
+    /// legalized signature.
+    ///
+    /// This is the end of the highest `ArgumentLoc::Stack` parameter, or 0 if
+    /// all parameters are passed in registers.
+    pub fn stack_arg_size(&self) -> u32 {
+        self.params
+            .iter()
+            .filter_map(|arg| match arg.location {
+                ArgumentLoc::Stack(offset) if offset >= 0 => {
+                    Some(offset as u32 + arg.value_type.bytes())
+                }
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
     /// Collect the normal parameter types of the signature; see `[ArgumentPurpose::Normal]`.
     pub fn param_types(&self) -> Vec<Type> {
         self.params
@@ -344,6 +362,10 @@ pub enum ArgumentPurpose {
     ///
     /// This is a pointer to a stack limit. It is used to check the current stack pointer
     /// against. Can only appear once in a signature.
+    ///
+    /// The prologue traps unless the stack pointer stays above the limit after the frame,
+    /// including the saved registers and the local stack slots, has been allocated. The check
+    /// is emitted even for functions without any stack slots.
     StackLimit,
 }
 
@@ -450,6 +472,7 @@ mod tests {
             CallConv::WindowsFastcall,
             CallConv::BaldrdashSystemV,
             CallConv::BaldrdashWindows,
+            CallConv::Tail,
         ] {
             assert_eq!(Ok(cc), cc.to_string().parse())
         }
@@ -472,8 +495,10 @@ mod tests {
         );
 
         // Order does not matter.
+        assert_eq!(sig.stack_arg_size(), 0);
         sig.params[0].location = ArgumentLoc::Stack(24);
         sig.params[1].location = ArgumentLoc::Stack(8);
+        assert_eq!(sig.stack_arg_size(), 28);
 
         // Writing ABI-annotated signatures.
         assert_eq!(
@@ -99,6 +99,17 @@ pub struct Function {
     /// are saved in the frame. This information is created during the prologue and epilogue
     /// passes.
     pub frame_layout: Option<FrameLayout>,
+
+    /// An optional global value which represents an expression evaluating to
+    /// the stack limit for this function. This `GlobalValue` will be
+    /// interpreted in the prologue, if necessary, to insert a stack check to
+    /// ensure that a trap happens if the stack pointer goes below the
+    /// threshold specified here.
+    ///
+    /// The check works like the one for an `ArgumentPurpose::StackLimit` parameter, which can't be
+    /// used together with this. The global value can only be computed from the vmctx, which must
+    /// be passed in a register, with `load` and `iadd_imm`.
+    pub stack_limit: Option<ir::GlobalValue>,
 }
 
 impl Function {
@@ -123,6 +134,7 @@ impl Function {
             srclocs: SecondaryMap::new(),
             prologue_end: None,
             frame_layout: None,
+            stack_limit: None,
         }
     }
 
@@ -144,6 +156,7 @@ impl Function {
         self.srclocs.clear();
         self.prologue_end = None;
         self.frame_layout = None;
+        self.stack_limit = None;
     }
 
     /// Create a new empty, anonymous function with a Fast calling convention.
@@ -20,7 +20,7 @@ pub struct HeapData {
     /// Heap style, with additional style-specific info.
     pub style: HeapStyle,
 
-    /// The index type for the heap.
+    /// The index type for the heap. The index of a dynamic heap can't be wider than a pointer.
     pub index_type: Type,
 }
 
@@ -193,6 +193,10 @@ impl InstructionData {
                 ref args,
                 ..
             } => BranchInfo::SingleDest(destination, &args.as_slice(pool)[2..]),
+            // The arguments of an invoke go to the callee. Landing pads don't take any.
+            Self::Invoke { destination, .. } | Self::InvokeIndirect { destination, .. } => {
+                BranchInfo::SingleDest(destination, &[])
+            }
             Self::BranchTable {
                 table, destination, ..
             } => BranchInfo::Table(table, Some(destination)),
@@ -214,7 +218,9 @@ impl InstructionData {
             | Self::Branch { destination, .. }
             | Self::BranchInt { destination, .. }
             | Self::BranchFloat { destination, .. }
-            | Self::BranchIcmp { destination, .. } => Some(destination),
+            | Self::BranchIcmp { destination, .. }
+            | Self::Invoke { destination, .. }
+            | Self::InvokeIndirect { destination, .. } => Some(destination),
             Self::BranchTable { .. } | Self::IndirectJump { .. } => None,
             _ => {
                 debug_assert!(!self.opcode().is_branch());
@@ -248,6 +254,14 @@ impl InstructionData {
             | Self::BranchIcmp {
                 ref mut destination,
                 ..
+            }
+            | Self::Invoke {
+                ref mut destination,
+                ..
+            }
+            | Self::InvokeIndirect {
+                ref mut destination,
+                ..
             } => Some(destination),
             Self::BranchTable { .. } => None,
             _ => {
@@ -264,9 +278,15 @@ impl InstructionData {
         match *self {
             Self::Call {
                 func_ref, ref args, ..
+            }
+            | Self::Invoke {
+                func_ref, ref args, ..
             } => CallInfo::Direct(func_ref, args.as_slice(pool)),
             Self::CallIndirect {
                 sig_ref, ref args, ..
+            }
+            | Self::InvokeIndirect {
+                sig_ref, ref args, ..
             } => CallInfo::Indirect(sig_ref, &args.as_slice(pool)[1..]),
             _ => {
                 debug_assert!(!self.opcode().is_call());
@@ -518,6 +538,12 @@ enum OperandConstraint {
 
     /// This operand is `ctrlType.double_vector()`.
     DoubleVector,
+
+    /// This operand is `ctrlType.split_lanes()`.
+    SplitLanes,
+
+    /// This operand is `ctrlType.merge_lanes()`.
+    MergeLanes,
 }
 
 impl OperandConstraint {
@@ -544,6 +570,16 @@ impl OperandConstraint {
                     .expect("invalid type for half_vector"),
             ),
             DoubleVector => Bound(ctrl_type.by(2).expect("invalid type for double_vector")),
+            SplitLanes => Bound(
+                ctrl_type
+                    .split_lanes()
+                    .expect("invalid type for split_lanes"),
+            ),
+            MergeLanes => Bound(
+                ctrl_type
+                    .merge_lanes()
+                    .expect("invalid type for merge_lanes"),
+            ),
         }
     }
 }
@@ -282,6 +282,24 @@ impl StackSlots {
         self.push(data)
     }
 
+    /// Get a stack slot representing an incoming function argument.
+    ///
+    /// This reuses an existing incoming argument slot with the requested offset and size, or
+    /// creates a new one. Tail calls use this to store their stack arguments in the area where the
+    /// current function received its own.
+    pub fn get_incoming_arg(&mut self, ty: Type, offset: StackOffset) -> StackSlot {
+        let size = ty.bytes();
+        if let Some(ss) = self.keys().find(|&ss| {
+            let data = &self[ss];
+            data.kind == StackSlotKind::IncomingArg
+                && data.offset == Some(offset)
+                && data.size == size
+        }) {
+            return ss;
+        }
+        self.make_incoming_arg(ty, offset)
+    }
+
     /// Get a stack slot representing an outgoing argument.
     ///
     /// This may create a new stack slot, or reuse an existing outgoing stack slot with the
@@ -394,6 +412,21 @@ mod tests {
         assert_eq!(sss.get_outgoing_arg(types::I64, 8), ss2);
     }
 
+    #[test]
+    fn incoming() {
+        let mut sss = StackSlots::new();
+
+        let ss0 = sss.make_incoming_arg(types::I32, 8);
+        let ss1 = sss.get_incoming_arg(types::I64, 8);
+
+        assert_eq!(sss[ss1].kind, StackSlotKind::IncomingArg);
+        assert_eq!(sss[ss1].offset, Some(8));
+        assert_eq!(sss[ss1].size, 8);
+
+        assert_eq!(sss.get_incoming_arg(types::I32, 8), ss0);
+        assert_eq!(sss.get_incoming_arg(types::I64, 8), ss1);
+    }
+
     #[test]
     fn alignment() {
         let slot = StackSlotData::new(StackSlotKind::SpillSlot, 8);
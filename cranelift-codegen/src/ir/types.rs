@@ -279,6 +279,27 @@ impl Type {
         }
     }
 
+    /// Split the lane width in half and double the number of lanes to maintain the same bit-width.
+    ///
+    /// If this is a scalar type of `n` bits, it produces a SIMD vector type of `(n/2)x2`.
+    pub fn split_lanes(self) -> Option<Self> {
+        match self.half_width() {
+            Some(half_width) => half_width.by(2),
+            None => None,
+        }
+    }
+
+    /// Merge lanes to half the number of lanes and double the lane width to maintain the same
+    /// bit-width.
+    ///
+    /// If this is a scalar type, it will return `None`.
+    pub fn merge_lanes(self) -> Option<Self> {
+        match self.double_width() {
+            Some(double_width) => double_width.half_vector(),
+            None => None,
+        }
+    }
+
     /// Index of this type, for use with hash tables etc.
     pub fn index(self) -> usize {
         usize::from(self.0)
@@ -458,6 +479,13 @@ mod tests {
         assert_eq!(I32.half_vector(), None);
         assert_eq!(INVALID.half_vector(), None);
 
+        assert_eq!(I16X8.split_lanes(), Some(I8X16));
+        assert_eq!(I32X4.split_lanes(), Some(I16X8));
+        assert_eq!(I8X16.split_lanes(), None);
+        assert_eq!(I8X16.merge_lanes(), Some(I16X8));
+        assert_eq!(I16X8.merge_lanes(), Some(I32X4));
+        assert_eq!(I64.merge_lanes(), None);
+
         // Check that the generated constants match the computed vector types.
         assert_eq!(I32.by(4), Some(I32X4));
         assert_eq!(F64.by(8), Some(F64X8));
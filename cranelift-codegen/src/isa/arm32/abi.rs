@@ -1,88 +1,170 @@
-//! ARM ABI implementation.
-//! This is from the RISC-V target and will need to be updated for ARM32.
+//! ARM 32 ABI implementation.
+//!
+//! This module implements the hard float variant of the Procedure Call Standard for the ARM
+//! Architecture (AAPCS-VFP), for Thumb-2 code:
+//!
+//! - Integer arguments and return values are passed in `r0`-`r3`. 64-bit integers are passed in an
+//!   even-odd register pair, or on the stack with an 8-byte alignment.
+//! - Floating point arguments and return values are allocated to the first free registers of
+//!   `s0`-`s15`, where a double precision value occupies two consecutive single precision
+//!   registers starting at an even one. Once a floating point argument is passed on the stack, so
+//!   are all the following ones.
+//! - Remaining arguments are passed on the stack in 4-byte slots.
+//! - Registers `r4`-`r11` and `d8`-`d15` are callee-saved.
+//! - The prologue stores the frame pointer `r7` and the link register `r14` as a frame record at
+//!   the top of the frame, and points `r7` at it.
 
-use super::registers::{D, GPR, Q, S};
-use crate::abi::{legalize_args, ArgAction, ArgAssigner, ValueConversion};
-use crate::ir::{self, AbiParam, ArgumentExtension, ArgumentLoc, Type};
-use crate::isa::RegClass;
+use super::registers::{D, GPR, Q, RU, S};
+use crate::abi::{legalize_args, legalize_returns, ArgAction, ArgAssigner, ValueConversion};
+use crate::cursor::{Cursor, CursorPosition, EncCursor};
+use crate::ir;
+use crate::ir::immediates::Imm64;
+use crate::ir::types::{F64, I32};
+use crate::ir::{AbiParam, ArgumentExtension, ArgumentLoc, ArgumentPurpose, InstBuilder, Type};
+use crate::isa::{CallConv, RegClass, RegUnit, TargetIsa};
 use crate::regalloc::RegisterSet;
+use crate::result::{CodegenError, CodegenResult};
+use crate::settings as shared_settings;
+use crate::stack_layout::layout_stack;
 use alloc::borrow::Cow;
+use alloc::vec::Vec;
 use core::i32;
-use target_lexicon::Triple;
 
+/// The stack pointer must be 8-byte aligned at public interfaces.
+const STACK_ALIGNMENT: u32 = 8;
+
+/// Number of general purpose argument and return value registers.
+const GPR_ARG_LIMIT: usize = 4;
+
+/// Number of single precision argument and return value registers.
+const VFP_ARG_LIMIT: usize = 16;
+
+/// Callee-saved general purpose registers, except for the frame pointer `r7`.
+static CALLEE_SAVED_GPRS: [RU; 7] = [RU::r4, RU::r5, RU::r6, RU::r8, RU::r9, RU::r10, RU::r11];
+
+/// Callee-saved double precision registers `d8`-`d15`, named by their first register unit.
+static CALLEE_SAVED_DPRS: [RU; 8] = [
+    RU::s16,
+    RU::s18,
+    RU::s20,
+    RU::s22,
+    RU::s24,
+    RU::s26,
+    RU::s28,
+    RU::s30,
+];
+
+#[derive(Clone)]
 struct Args {
-    pointer_bits: u8,
-    pointer_bytes: u8,
-    pointer_type: Type,
-    regs: u32,
-    reg_limit: u32,
+    gpr_used: usize,
+    /// Bit mask of the single precision registers in use.
+    vfp_mask: u32,
+    /// Has a floating point value been assigned to the stack?
+    vfp_stacked: bool,
     offset: u32,
 }
 
 impl Args {
-    fn new(bits: u8) -> Self {
+    fn new() -> Self {
         Self {
-            pointer_bits: bits,
-            pointer_bytes: bits / 8,
-            pointer_type: Type::int(u16::from(bits)).unwrap(),
-            regs: 0,
-            reg_limit: 8,
+            gpr_used: 0,
+            vfp_mask: 0,
+            vfp_stacked: false,
             offset: 0,
         }
     }
+
+    /// Allocate `count` consecutive single precision registers, starting at a multiple of
+    /// `count`. Return the first allocated register index.
+    fn alloc_vfp(&mut self, count: usize) -> Option<usize> {
+        if self.vfp_stacked {
+            return None;
+        }
+        let mask = (1 << count) - 1;
+        let index = (0..VFP_ARG_LIMIT)
+            .step_by(count)
+            .find(|&i| self.vfp_mask & (mask << i) == 0)?;
+        self.vfp_mask |= mask << index;
+        Some(index)
+    }
+
+    /// Assign a stack location to a value of `bytes` bytes.
+    fn alloc_stack(&mut self, bytes: u32) -> ArgAction {
+        self.offset = align(self.offset, bytes);
+        let loc = ArgumentLoc::Stack(self.offset as i32);
+        self.offset += bytes;
+        debug_assert!(self.offset <= i32::MAX as u32);
+        loc.into()
+    }
+}
+
+fn align(value: u32, to: u32) -> u32 {
+    (value + to - 1) & !(to - 1)
 }
 
 impl ArgAssigner for Args {
     fn assign(&mut self, arg: &AbiParam) -> ArgAction {
-        fn align(value: u32, to: u32) -> u32 {
-            (value + to - 1) & !(to - 1)
-        }
-
         let ty = arg.value_type;
 
-        // Check for a legal type.
-        // SIMD instructions are currently no implemented, so break down vectors
+        // No vector registers are supported yet, so break all vectors down.
         if ty.is_vector() {
             return ValueConversion::VectorSplit.into();
         }
 
-        // Large integers and booleans are broken down to fit in a register.
-        if !ty.is_float() && ty.bits() > u16::from(self.pointer_bits) {
-            // Align registers and stack to a multiple of two pointers.
-            self.regs = align(self.regs, 2);
-            self.offset = align(self.offset, 2 * u32::from(self.pointer_bytes));
+        // Large integers and booleans are broken down to fit in a register. Their halves are
+        // passed in an even-odd register pair, or in an 8-byte aligned stack slot.
+        if !ty.is_float() && ty.bits() > 32 {
+            self.gpr_used = align(self.gpr_used as u32, 2) as usize;
+            self.offset = align(self.offset, 8);
             return ValueConversion::IntSplit.into();
         }
 
         // Small integers are extended to the size of a pointer register.
-        if ty.is_int() && ty.bits() < u16::from(self.pointer_bits) {
+        if ty.is_int() && ty.bits() < 32 {
             match arg.extension {
                 ArgumentExtension::None => {}
-                ArgumentExtension::Uext => return ValueConversion::Uext(self.pointer_type).into(),
-                ArgumentExtension::Sext => return ValueConversion::Sext(self.pointer_type).into(),
+                ArgumentExtension::Uext => return ValueConversion::Uext(I32).into(),
+                ArgumentExtension::Sext => return ValueConversion::Sext(I32).into(),
             }
         }
 
-        if self.regs < self.reg_limit {
-            // Assign to a register.
-            let reg = GPR.unit(10 + self.regs as usize);
-            self.regs += 1;
-            ArgumentLoc::Reg(reg).into()
-        } else {
-            // Assign a stack location.
-            let loc = ArgumentLoc::Stack(self.offset as i32);
-            self.offset += u32::from(self.pointer_bytes);
-            debug_assert!(self.offset <= i32::MAX as u32);
-            loc.into()
+        if ty.is_float() {
+            let bytes = ty.bytes();
+            if let Some(index) = self.alloc_vfp(bytes as usize / 4) {
+                // Both the single and the double precision register classes number their
+                // registers by the first single precision register unit they occupy.
+                let class = if bytes == 4 { S } else { D };
+                return ArgumentLoc::Reg(class.first + index as RegUnit).into();
+            }
+            // There is no back-filling of the argument registers once the stack has been used.
+            self.vfp_stacked = true;
+            return self.alloc_stack(bytes);
+        }
+
+        if self.gpr_used < GPR_ARG_LIMIT {
+            let reg = GPR.unit(self.gpr_used);
+            self.gpr_used += 1;
+            return ArgumentLoc::Reg(reg).into();
         }
+
+        // All the following integer arguments go on the stack too.
+        self.gpr_used = GPR_ARG_LIMIT;
+        self.alloc_stack(4)
     }
 }
 
 /// Legalize `sig`.
-pub fn legalize_signature(sig: &mut Cow<ir::Signature>, triple: &Triple, _current: bool) {
-    let bits = triple.pointer_width().unwrap().bits();
+pub fn legalize_signature(
+    sig: &mut Cow<ir::Signature>,
+    _flags: &shared_settings::Flags,
+    _current: bool,
+) {
+    let mut args = Args::new();
+    let mut rets = Args::new();
+
+    // The struct return pointer is passed in `r0`, and not returned.
+    legalize_returns(sig, I32, &mut args, &mut rets, false);
 
-    let mut args = Args::new(bits);
     if let Some(new_params) = legalize_args(&sig.params, &mut args) {
         sig.to_mut().params = new_params;
     }
@@ -90,7 +172,7 @@ pub fn legalize_signature(sig: &mut Cow<ir::Signature>, triple: &Triple, _curren
 
 /// Get register class for a type appearing in a legalized signature.
 pub fn regclass_for_abi_type(ty: ir::Type) -> RegClass {
-    if ty.is_int() {
+    if ty.is_int() || ty.is_bool() {
         GPR
     } else {
         match ty.bits() {
@@ -104,5 +186,230 @@ pub fn regclass_for_abi_type(ty: ir::Type) -> RegClass {
 
 /// Get the set of allocatable registers for `func`.
 pub fn allocatable_registers(_func: &ir::Function) -> RegisterSet {
-    unimplemented!()
+    let mut regs = RegisterSet::new();
+    // Frame pointer.
+    regs.take(GPR, RU::r7 as RegUnit);
+    // Scratch register used by the instruction encodings.
+    regs.take(GPR, RU::r12 as RegUnit);
+    // Stack pointer, link register and program counter.
+    regs.take(GPR, RU::r13 as RegUnit);
+    regs.take(GPR, RU::r14 as RegUnit);
+    regs.take(GPR, RU::r15 as RegUnit);
+    // Many Cortex-A cores only implement `d0`-`d15`.
+    for unit in (RU::s32 as RegUnit..=RU::s62 as RegUnit).step_by(2) {
+        regs.take(D, unit);
+    }
+    regs
+}
+
+/// Get the register of `class` containing the register unit `ru`, if any.
+fn containing_reg(class: RegClass, ru: RegUnit) -> Option<RegUnit> {
+    if ru < class.first {
+        return None;
+    }
+    let reg = ru - (ru - class.first) % RegUnit::from(class.width);
+    if class.contains(reg) {
+        Some(reg)
+    } else {
+        None
+    }
+}
+
+/// Get the callee-saved registers of `class` that are used in `func`.
+///
+/// Single precision registers are accounted for in the double precision register containing them.
+fn callee_saved_regs_used(
+    func: &ir::Function,
+    class: RegClass,
+    callee_saved: &[RU],
+) -> Vec<RegUnit> {
+    let mut used = RegisterSet::empty();
+    let mut mark_used = |ru: RegUnit| {
+        if let Some(reg) = containing_reg(class, ru) {
+            if !used.is_avail(class, reg) {
+                used.free(class, reg);
+            }
+        }
+    };
+
+    for value_loc in func.locations.values() {
+        if let ir::ValueLoc::Reg(ru) = *value_loc {
+            mark_used(ru);
+        }
+    }
+
+    // regmove and regfill instructions may temporarily divert values into other registers,
+    // and these are not reflected in `func.locations`.
+    for ebb in &func.layout {
+        for inst in func.layout.ebb_insts(ebb) {
+            match func.dfg[inst] {
+                ir::instructions::InstructionData::RegMove { dst, .. }
+                | ir::instructions::InstructionData::RegFill { dst, .. } => mark_used(dst),
+                _ => (),
+            }
+        }
+    }
+
+    callee_saved
+        .iter()
+        .map(|&reg| reg as RegUnit)
+        .filter(|&reg| used.is_avail(class, reg))
+        .collect()
+}
+
+/// Insert a prologue and epilogues, using the AAPCS frame layout.
+pub fn prologue_epilogue(func: &mut ir::Function, isa: &dyn TargetIsa) -> CodegenResult<()> {
+    match func.signature.call_conv {
+        CallConv::Fast | CallConv::Cold | CallConv::SystemV => aapcs_prologue_epilogue(func, isa),
+        call_conv => Err(CodegenError::Unsupported(format!(
+            "the {} calling convention on arm32",
+            call_conv
+        ))),
+    }
+}
+
+fn aapcs_prologue_epilogue(func: &mut ir::Function, isa: &dyn TargetIsa) -> CodegenResult<()> {
+    let gprs = callee_saved_regs_used(func, GPR, &CALLEE_SAVED_GPRS);
+    let dprs = callee_saved_regs_used(func, D, &CALLEE_SAVED_DPRS);
+
+    // The reserved stack area is composed of the frame record and all the callee-saved registers.
+    // General purpose registers are stored in pairs, and a leftover one in its own 8-byte slot, to
+    // keep the stack pointer aligned.
+    let slots = 1 + (gprs.len() + 1) / 2 + dprs.len();
+    let csr_stack_size = (slots * 8) as i32;
+    func.create_stack_slot(ir::StackSlotData {
+        kind: ir::StackSlotKind::IncomingArg,
+        size: csr_stack_size as u32,
+        offset: Some(-csr_stack_size),
+    });
+
+    let is_leaf = func.is_leaf();
+    let total_stack_size = layout_stack(&mut func.stack_slots, is_leaf, STACK_ALIGNMENT)? as i32;
+    let local_stack_size = i64::from(total_stack_size - csr_stack_size);
+
+    // Add the frame record and the CSRs to the function signature.
+    let fp_arg = AbiParam::special_reg(I32, ArgumentPurpose::FramePointer, RU::r7 as RegUnit);
+    let lr_arg = AbiParam::special_reg(I32, ArgumentPurpose::Link, RU::r14 as RegUnit);
+    func.signature.params.push(fp_arg);
+    func.signature.returns.push(fp_arg);
+    func.signature.params.push(lr_arg);
+    func.signature.returns.push(lr_arg);
+
+    let csrs: Vec<(Type, RegUnit)> = gprs
+        .iter()
+        .map(|&reg| (I32, reg))
+        .chain(dprs.iter().map(|&reg| (F64, reg)))
+        .collect();
+    for &(ty, reg) in &csrs {
+        let csr_arg = AbiParam::special_reg(ty, ArgumentPurpose::CalleeSaved, reg);
+        func.signature.params.push(csr_arg);
+        func.signature.returns.push(csr_arg);
+    }
+
+    // Set up the cursor and insert the prologue.
+    let entry_ebb = func.layout.entry_block().expect("missing entry block");
+    let mut pos = EncCursor::new(func, isa).at_first_insertion_point(entry_ebb);
+    insert_prologue(&mut pos, local_stack_size, &gprs, &dprs);
+
+    // Reset the cursor and insert the epilogues.
+    let mut pos = pos.at_position(CursorPosition::Nowhere);
+    while let Some(ebb) = pos.next_ebb() {
+        pos.goto_last_inst(ebb);
+        if let Some(inst) = pos.current_inst() {
+            if pos.func.dfg[inst].opcode().is_return() {
+                insert_epilogue(inst, local_stack_size, &mut pos, &gprs, &dprs);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Append an entry block parameter living in `reg`.
+fn append_reg_param(pos: &mut EncCursor, ty: Type, reg: RegUnit) -> ir::Value {
+    let ebb = pos.current_ebb().expect("missing ebb under cursor");
+    let value = pos.func.dfg.append_ebb_param(ebb, ty);
+    pos.func.locations[value] = ir::ValueLoc::Reg(reg);
+    value
+}
+
+/// Insert the prologue for a given function.
+fn insert_prologue(pos: &mut EncCursor, stack_size: i64, gprs: &[RegUnit], dprs: &[RegUnit]) {
+    // Store the frame record, and point the frame pointer to it.
+    let fp = append_reg_param(pos, I32, RU::r7 as RegUnit);
+    let lr = append_reg_param(pos, I32, RU::r14 as RegUnit);
+    pos.ins().arm32_push_pair(fp, lr);
+    pos.ins()
+        .copy_special(RU::r13 as RegUnit, RU::r7 as RegUnit);
+
+    // Push the general purpose registers in pairs, with a single push for the last register when
+    // their number is odd.
+    for chunk in gprs.chunks(2) {
+        let x = append_reg_param(pos, I32, chunk[0]);
+        if let Some(&reg) = chunk.get(1) {
+            let y = append_reg_param(pos, I32, reg);
+            pos.ins().arm32_push_pair(x, y);
+        } else {
+            pos.ins().arm32_push(x);
+        }
+    }
+
+    for &reg in dprs {
+        let x = append_reg_param(pos, F64, reg);
+        pos.ins().arm32_push(x);
+    }
+
+    // Allocate stack frame storage.
+    if stack_size > 0 {
+        pos.func.prologue_end = Some(pos.ins().adjust_sp_down_imm(Imm64::new(stack_size)));
+    }
+}
+
+/// Restore the values popped into `regs` as arguments of the return instruction `inst`.
+fn restore_regs(inst: ir::Inst, pos: &mut EncCursor, popped: &[ir::Value], regs: &[RegUnit]) {
+    for (&value, &reg) in popped.iter().zip(regs) {
+        pos.func.locations[value] = ir::ValueLoc::Reg(reg);
+        pos.func.dfg.append_inst_arg(inst, value);
+    }
+}
+
+/// Insert an epilogue given a specific `return` instruction.
+fn insert_epilogue(
+    inst: ir::Inst,
+    stack_size: i64,
+    pos: &mut EncCursor,
+    gprs: &[RegUnit],
+    dprs: &[RegUnit],
+) {
+    if stack_size > 0 {
+        pos.ins().adjust_sp_up_imm(Imm64::new(stack_size));
+    }
+
+    // The pops are inserted right before the return, stepping backward each time, so they are
+    // emitted in the reverse order they are inserted in.
+    let (fp, lr) = pos.ins().arm32_pop_pair(I32);
+    pos.prev_inst();
+    restore_regs(
+        inst,
+        pos,
+        &[fp, lr],
+        &[RU::r7 as RegUnit, RU::r14 as RegUnit],
+    );
+
+    for chunk in gprs.chunks(2) {
+        let popped = if chunk.len() == 2 {
+            let (x, y) = pos.ins().arm32_pop_pair(I32);
+            vec![x, y]
+        } else {
+            vec![pos.ins().arm32_pop(I32)]
+        };
+        pos.prev_inst();
+        restore_regs(inst, pos, &popped, chunk);
+    }
+
+    for &reg in dprs {
+        let x = pos.ins().arm32_pop(F64);
+        pos.prev_inst();
+        restore_regs(inst, pos, &[x], &[reg]);
+    }
 }
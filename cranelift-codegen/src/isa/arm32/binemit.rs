@@ -1,8 +1,799 @@
 //! Emitting binary ARM32 machine code.
+//!
+//! Only the T32 instruction set (Thumb-2) is supported. Its instructions are either 16 or 32 bits
+//! wide, and the 32-bit ones are emitted as two halfwords, most significant first.
 
-use crate::binemit::{bad_encoding, CodeSink};
-use crate::ir::{Function, Inst};
-use crate::isa::TargetIsa;
+use super::registers::RU;
+use crate::binemit::{bad_encoding, CodeSink, Reloc};
+use crate::ir::condcodes::{FloatCC, IntCC};
+use crate::ir::{Ebb, Function, Inst, InstructionData, Opcode, SourceLoc, TrapCode};
+use crate::isa::{RegUnit, StackBaseMask, StackRef, TargetIsa};
 use crate::regalloc::RegDiversions;
 
 include!(concat!(env!("OUT_DIR"), "/binemit-arm32.rs"));
+
+/// A permanently undefined instruction: `udf #0`.
+const UDF: u16 = 0xde00;
+
+/// A breakpoint instruction: `bkpt #0`.
+const BKPT: u16 = 0xbe00;
+
+/// A no-op, used for aligning literals: `nop`.
+const NOP: u16 = 0xbf00;
+
+/// The `eq` and `ne` condition codes.
+const COND_EQ: u32 = 0b0000;
+const COND_NE: u32 = 0b0001;
+
+/// The `mi` condition code, "negative", or "less than" after a floating point comparison.
+const COND_MI: u32 = 0b0100;
+
+/// The `vs` condition code, "overflow", or "unordered" after a floating point comparison.
+const COND_VS: u32 = 0b0110;
+
+/// Encoding bits of the instructions used by multi-instruction sequences, in the formats
+/// expected by `put_modimm` and `put_rrr`.
+const AND: u16 = 0xf000;
+const TST: u16 = 0xf010;
+const BIC: u16 = 0xf020;
+const ORR: u16 = 0xf040;
+const ORN: u16 = 0xf060;
+const ADD: u16 = 0xf100;
+const CMN: u16 = 0xf110;
+const CMP: u16 = 0xf1b0;
+const RSB: u16 = 0xf1c0;
+const CLZ: u16 = 0xfab8;
+const MLS: u16 = 0xfb01;
+
+/// VFP instructions used by multi-instruction sequences, in the format expected by `put_vfp`.
+/// These are the single precision forms, the `sz` bit is bit 10.
+const VADD: u16 = 0x0030;
+const VMOV: u16 = 0x01b0;
+const VABS: u16 = 0x03b0;
+const VNEG: u16 = 0x01b1;
+const VCMP: u16 = 0x01b4;
+
+/// The modified immediate encoding of `0x8000_0000`, the sign bit of a single precision value.
+const SIGN_BIT: u32 = 0x400;
+
+/// The scratch register used by multi-instruction sequences, ip.
+const SCRATCH: RegUnit = RU::r12 as RegUnit;
+
+/// The program counter, which is also used as the zero register field of some instructions.
+const PC: RegUnit = RU::r15 as RegUnit;
+
+fn reg(r: RegUnit) -> u32 {
+    u32::from(r) & 0xf
+}
+
+/// The 5-bit register field of a single precision register, `Vd:D` in VFP instructions.
+///
+/// It is returned as `D:Vd` like the double precision fields, so `put_vfp` can handle both.
+fn sreg(r: RegUnit) -> u32 {
+    let s = u32::from(r);
+    ((s & 1) << 4) | (s >> 1)
+}
+
+/// The 5-bit register field of a double precision register, `D:Vd` in VFP instructions.
+///
+/// The double precision registers use two register units each.
+fn dreg(r: RegUnit) -> u32 {
+    u32::from(r) / 2
+}
+
+/// Convert an integer condition code to the T32 `cond` field, as read after a `cmp`.
+fn icc2cond(cond: IntCC) -> u32 {
+    use crate::ir::condcodes::IntCC::*;
+    match cond {
+        Equal => 0b0000,                      // eq
+        NotEqual => 0b0001,                   // ne
+        UnsignedGreaterThanOrEqual => 0b0010, // hs
+        UnsignedLessThan => 0b0011,           // lo
+        Overflow => 0b0110,                   // vs
+        NotOverflow => 0b0111,                // vc
+        UnsignedGreaterThan => 0b1000,        // hi
+        UnsignedLessThanOrEqual => 0b1001,    // ls
+        SignedGreaterThanOrEqual => 0b1010,   // ge
+        SignedLessThan => 0b1011,             // lt
+        SignedGreaterThan => 0b1100,          // gt
+        SignedLessThanOrEqual => 0b1101,      // le
+    }
+}
+
+/// Convert a floating point condition code to the T32 `cond` field, as read after a `vcmp`
+/// followed by a `vmrs APSR_nzcv, fpscr`.
+///
+/// An unordered comparison sets the C and V flags, which the condition codes below take into
+/// account. `one` and `ueq` can't be represented by a single condition code.
+fn fcc2cond(cond: FloatCC) -> u32 {
+    use crate::ir::condcodes::FloatCC::*;
+    match cond {
+        Equal => 0b0000,                         // eq
+        NotEqual => 0b0001,                      // ne
+        UnorderedOrGreaterThanOrEqual => 0b0101, // pl
+        LessThan => 0b0100,                      // mi
+        Unordered => 0b0110,                     // vs
+        Ordered => 0b0111,                       // vc
+        UnorderedOrGreaterThan => 0b1000,        // hi
+        LessThanOrEqual => 0b1001,               // ls
+        GreaterThanOrEqual => 0b1010,            // ge
+        UnorderedOrLessThan => 0b1011,           // lt
+        GreaterThan => 0b1100,                   // gt
+        UnorderedOrLessThanOrEqual => 0b1101,    // le
+        OrderedNotEqual | UnorderedOrEqual => panic!("{} needs two T32 conditions", cond),
+    }
+}
+
+/// Compute the displacement to `destination` from a branch at the current offset. The PC reads
+/// as the address of the branch plus 4.
+fn branch_disp<CS: CodeSink + ?Sized>(destination: Ebb, func: &Function, sink: &CS) -> i32 {
+    func.offsets[destination].wrapping_sub(sink.offset() + 4) as i32
+}
+
+/// Emit a 32-bit instruction.
+fn put_t32<CS: CodeSink + ?Sized>(hw1: u32, hw2: u32, sink: &mut CS) {
+    sink.put2(hw1 as u16);
+    sink.put2(hw2 as u16);
+}
+
+/// Data processing instructions with a shifted register operand, using a shift amount of zero.
+///
+///   15    9  4 3    15 14   11 7   5  3
+///   1110101 op S Rn   0 imm3 Rd imm2 ty Rm
+///
+/// Encoding bits: the first halfword, with the Rn field clear.
+fn put_dp_reg<CS: CodeSink + ?Sized>(
+    bits: u16,
+    rn: RegUnit,
+    rm: RegUnit,
+    rd: RegUnit,
+    sink: &mut CS,
+) {
+    put_t32(u32::from(bits) | reg(rn), (reg(rd) << 8) | reg(rm), sink);
+}
+
+/// Data processing instructions with a modified immediate operand.
+///
+///   15   10 9  8  4 3    15 14   11 7
+///   11110 i 0 op S Rn   0 imm3 Rd imm8
+///
+/// The immediate is given as the `i:imm3:imm8` field computed by `t32_modimm`.
+///
+/// Encoding bits: the first halfword, with the `i` and Rn fields clear.
+fn put_modimm<CS: CodeSink + ?Sized>(
+    bits: u16,
+    rn: RegUnit,
+    imm12: u32,
+    rd: RegUnit,
+    sink: &mut CS,
+) {
+    let hw1 = 0xf000 | (u32::from(bits) & 0x1f0) | (((imm12 >> 11) & 1) << 10) | reg(rn);
+    let hw2 = (((imm12 >> 8) & 7) << 12) | (reg(rd) << 8) | (imm12 & 0xff);
+    put_t32(hw1, hw2, sink);
+}
+
+/// Encode `value` as a T32 modified immediate: a byte replicated in some of the bytes of a word,
+/// or a byte with its top bit set rotated right by 8 to 31 bits.
+fn t32_modimm(value: u32) -> Option<u32> {
+    let low = value & 0xff;
+    let second = (value >> 8) & 0xff;
+    if value == low {
+        Some(low)
+    } else if value == low * 0x0001_0001 {
+        Some(0x100 | low)
+    } else if value == second * 0x0100_0100 {
+        Some(0x200 | second)
+    } else if value == low * 0x0101_0101 {
+        Some(0x300 | low)
+    } else {
+        (8..32)
+            .map(|rot| (rot, value.rotate_left(rot)))
+            .find(|&(_, byte)| byte <= 0xff && byte & 0x80 != 0)
+            .map(|(rot, byte)| (rot << 7) | (byte & 0x7f))
+    }
+}
+
+/// The instruction used by `put_dp_imm` for an immediate operand.
+enum DpImm {
+    /// Encoding bits for `put_modimm`, possibly of a related instruction, and the modified
+    /// immediate.
+    Modified(u16, u32),
+    /// First halfword of `addw` or `subw`, and the 12-bit immediate.
+    Plain(u32, u32),
+    /// The immediate doesn't fit, and needs to be materialized in ip.
+    Register,
+}
+
+fn dp_imm_form(bits: u16, imm: i64) -> DpImm {
+    let value = imm as u32;
+    if let Some(imm12) = t32_modimm(value) {
+        return DpImm::Modified(bits, imm12);
+    }
+
+    // Try a related instruction with the inverted or negated immediate. `cmp` and `cmn` set the
+    // same flags with negated operands as long as the operand is neither 0 nor the minimum
+    // integer, which are both valid modified immediates.
+    let op = (bits >> 5) & 0xf;
+    let set_flags = bits & 0x10 != 0;
+    let related = match op {
+        0b0000 if !set_flags => Some((0b0001, !value)), // and -> bic
+        0b0001 if !set_flags => Some((0b0000, !value)), // bic -> and
+        0b0010 if !set_flags => Some((0b0011, !value)), // orr -> orn
+        0b0011 if !set_flags => Some((0b0010, !value)), // orn -> orr
+        0b1000 => Some((0b1101, value.wrapping_neg())), // add -> sub
+        0b1101 => Some((0b1000, value.wrapping_neg())), // sub -> add
+        _ => None,
+    };
+    if let Some((op, value)) = related {
+        if let Some(imm12) = t32_modimm(value) {
+            return DpImm::Modified((bits & !0x1e0) | (op << 5), imm12);
+        }
+    }
+
+    // Additions and subtractions which don't set the flags also have a plain 12-bit immediate
+    // form.
+    let signed = value as i32;
+    if !set_flags && (op == 0b1000 || op == 0b1101) && signed > -4096 && signed < 4096 {
+        let subtract = (op == 0b1101) != (signed < 0);
+        let hw1 = if subtract { 0xf2a0 } else { 0xf200 };
+        return DpImm::Plain(hw1, signed.abs() as u32);
+    }
+
+    DpImm::Register
+}
+
+/// Number of bytes emitted by `put_dp_imm`.
+pub fn dp_imm_size(bits: u16, imm: i64) -> u8 {
+    match dp_imm_form(bits, imm) {
+        DpImm::Modified(..) | DpImm::Plain(..) => 4,
+        DpImm::Register => movimm_size(imm) + 4,
+    }
+}
+
+/// Data processing with an arbitrary immediate operand. Immediates which aren't valid modified
+/// immediates use a related instruction with the inverted or negated immediate, the 12-bit
+/// immediate forms of `add` and `sub`, or are materialized in ip.
+///
+/// Encoding bits: same as `put_modimm`.
+fn put_dp_imm<CS: CodeSink + ?Sized>(bits: u16, rn: RegUnit, imm: i64, rd: RegUnit, sink: &mut CS) {
+    match dp_imm_form(bits, imm) {
+        DpImm::Modified(bits, imm12) => put_modimm(bits, rn, imm12, rd, sink),
+        DpImm::Plain(hw1, imm12) => {
+            let hw1 = hw1 | (((imm12 >> 11) & 1) << 10) | reg(rn);
+            let hw2 = (((imm12 >> 8) & 7) << 12) | (reg(rd) << 8) | (imm12 & 0xff);
+            put_t32(hw1, hw2, sink);
+        }
+        DpImm::Register => {
+            put_movimm(SCRATCH, imm, sink);
+            // The shifted register form has the same `op` and `S` fields.
+            let reg_bits = 0xea00 | (bits & 0x1f0);
+            put_dp_reg(reg_bits, rn, SCRATCH, rd, sink);
+        }
+    }
+}
+
+/// Register-controlled data processing, multiplications and divisions.
+///
+///   15      4 3    15 11 7   3
+///   op[11:0] Rn   Ra Rd op2 Rm
+///
+/// Encoding bits: `op[11:0] << 4 | op2`.
+fn put_rrrr<CS: CodeSink + ?Sized>(
+    bits: u16,
+    rn: RegUnit,
+    rm: RegUnit,
+    ra: RegUnit,
+    rd: RegUnit,
+    sink: &mut CS,
+) {
+    let bits = u32::from(bits);
+    let hw1 = (bits & 0xfff0) | reg(rn);
+    let hw2 = (reg(ra) << 12) | (reg(rd) << 8) | ((bits & 0xf) << 4) | reg(rm);
+    put_t32(hw1, hw2, sink);
+}
+
+/// Same as `put_rrrr`, for the instructions without an Ra operand, which have 0b1111 there.
+fn put_rrr<CS: CodeSink + ?Sized>(bits: u16, rn: RegUnit, rm: RegUnit, rd: RegUnit, sink: &mut CS) {
+    put_rrrr(bits, rn, rm, PC, rd, sink);
+}
+
+/// Shift or rotate by an immediate: `mov.w rd, rm, <type> #amount`.
+///
+/// Encoding bits: the shift type.
+fn put_shift_imm<CS: CodeSink + ?Sized>(
+    bits: u16,
+    rm: RegUnit,
+    amount: i64,
+    rd: RegUnit,
+    sink: &mut CS,
+) {
+    let amount = (amount & 31) as u32;
+    // A zero amount means 32 for the right shifts, and a rotation with extend for `ror`.
+    let ty = if amount == 0 { 0 } else { u32::from(bits) & 3 };
+    let hw2 = ((amount >> 2) << 12) | (reg(rd) << 8) | ((amount & 3) << 6) | (ty << 4) | reg(rm);
+    put_t32(0xea4f, hw2, sink);
+}
+
+/// Number of bytes emitted by `put_movimm`.
+pub fn movimm_size(value: i64) -> u8 {
+    let value = value as u32;
+    if t32_modimm(value).is_some() || t32_modimm(!value).is_some() || value <= 0xffff {
+        4
+    } else {
+        8
+    }
+}
+
+/// Materialize an integer constant with a `mov.w` or `mvn` of a modified immediate, a `movw`, or
+/// a `movw` and `movt` pair.
+fn put_movimm<CS: CodeSink + ?Sized>(rd: RegUnit, value: i64, sink: &mut CS) {
+    let value = value as u32;
+    if let Some(imm12) = t32_modimm(value) {
+        // mov.w is an alias of orr with r15.
+        put_modimm(ORR, PC, imm12, rd, sink);
+    } else if let Some(imm12) = t32_modimm(!value) {
+        // mvn is an alias of orn with r15.
+        put_modimm(ORN, PC, imm12, rd, sink);
+    } else {
+        put_mov16(0xf240, rd, value & 0xffff, sink);
+        if value > 0xffff {
+            put_mov16(0xf2c0, rd, value >> 16, sink);
+        }
+    }
+}
+
+/// Materialize a 32-bit constant with a `movw` and `movt` pair, regardless of its value.
+fn put_movw_movt<CS: CodeSink + ?Sized>(rd: RegUnit, value: u32, sink: &mut CS) {
+    put_mov16(0xf240, rd, value & 0xffff, sink);
+    put_mov16(0xf2c0, rd, value >> 16, sink);
+}
+
+/// Move a 16-bit immediate to the low or high half of a register: `movw` and `movt`.
+///
+///   15   10 9      3    15 14   11 7
+///   11110 i 10 op 0 imm4 0 imm3 Rd imm8
+fn put_mov16<CS: CodeSink + ?Sized>(hw1: u32, rd: RegUnit, imm16: u32, sink: &mut CS) {
+    let hw1 = hw1 | (((imm16 >> 11) & 1) << 10) | (imm16 >> 12);
+    let hw2 = (((imm16 >> 8) & 7) << 12) | (reg(rd) << 8) | (imm16 & 0xff);
+    put_t32(hw1, hw2, sink);
+}
+
+/// Register copy with the 16-bit `mov`, which can access all the registers and doesn't set the
+/// flags.
+fn put_mov<CS: CodeSink + ?Sized>(rm: RegUnit, rd: RegUnit, sink: &mut CS) {
+    let rd = reg(rd);
+    sink.put2((0x4600 | ((rd >> 3) << 7) | (reg(rm) << 3) | (rd & 7)) as u16);
+}
+
+/// `add rd, pc`, which reads the PC as the address of the instruction plus 4.
+fn put_add_pc<CS: CodeSink + ?Sized>(rd: RegUnit, sink: &mut CS) {
+    let rd = reg(rd);
+    sink.put2((0x4400 | ((rd >> 3) << 7) | (15 << 3) | (rd & 7)) as u16);
+}
+
+/// If-then-else block: the next instruction executes if `cond` holds, the one after if it
+/// doesn't.
+fn put_ite<CS: CodeSink + ?Sized>(cond: u32, sink: &mut CS) {
+    sink.put2((0xbf04 | (cond << 4) | (((cond & 1) ^ 1) << 3)) as u16);
+}
+
+/// If-then block: the next instruction executes if `cond` holds.
+fn put_it<CS: CodeSink + ?Sized>(cond: u32, sink: &mut CS) {
+    sink.put2((0xbf08 | (cond << 4)) as u16);
+}
+
+/// Set `rd` to 1 if `cond` holds, 0 otherwise: `ite cond; mov.w rd, #1; mov.w rd, #0`.
+fn put_cset<CS: CodeSink + ?Sized>(cond: u32, rd: RegUnit, sink: &mut CS) {
+    put_ite(cond, sink);
+    put_modimm(ORR, PC, 1, rd, sink);
+    put_modimm(ORR, PC, 0, rd, sink);
+}
+
+/// Materialize the `one` and `ueq` float conditions, which need two T32 conditions.
+fn put_cset_double_fcc<CS: CodeSink + ?Sized>(cond: FloatCC, rd: RegUnit, sink: &mut CS) {
+    let (first, second) = match cond {
+        // Less than or greater than.
+        FloatCC::OrderedNotEqual => (0b0100, 0b1100), // mi, gt
+        // Equal or unordered.
+        FloatCC::UnorderedOrEqual => (0b0000, 0b0110), // eq, vs
+        _ => panic!("{} is a single T32 condition", cond),
+    };
+    put_cset(first, rd, sink);
+    put_it(second, sink);
+    put_modimm(ORR, PC, 1, rd, sink);
+}
+
+// Branches. The displacements are relative to the address of the branch plus 4, and are
+// multiples of 2.
+
+/// Unconditional branch with an 11-bit halfword offset: `b.n`.
+fn put_b_n<CS: CodeSink + ?Sized>(disp: i32, sink: &mut CS) {
+    debug_assert!(disp & 1 == 0 && disp >= -(1 << 11) && disp < 1 << 11);
+    sink.put2(0xe000 | ((disp >> 1) as u16 & 0x7ff));
+}
+
+/// Conditional branch with an 8-bit halfword offset: `b<c>.n`.
+fn put_bcond_n<CS: CodeSink + ?Sized>(cond: u32, disp: i32, sink: &mut CS) {
+    debug_assert!(disp & 1 == 0 && disp >= -(1 << 8) && disp < 1 << 8);
+    sink.put2((0xd000 | (cond << 8) | ((disp >> 1) as u32 & 0xff)) as u16);
+}
+
+/// Conditional branch with a 20-bit halfword offset: `b<c>.w`.
+///
+///   15   10 9    5    15 13 12 11 10
+///   11110 S cond imm6 10 J1 0  J2 imm11
+fn put_bcond_w<CS: CodeSink + ?Sized>(cond: u32, disp: i32, sink: &mut CS) {
+    debug_assert!(disp & 1 == 0 && disp >= -(1 << 20) && disp < 1 << 20);
+    let imm = (disp >> 1) as u32;
+    let s = (imm >> 19) & 1;
+    let j2 = (imm >> 18) & 1;
+    let j1 = (imm >> 17) & 1;
+    let hw1 = 0xf000 | (s << 10) | (cond << 6) | ((imm >> 11) & 0x3f);
+    let hw2 = 0x8000 | (j1 << 13) | (j2 << 11) | (imm & 0x7ff);
+    put_t32(hw1, hw2, sink);
+}
+
+/// Branch with a 24-bit halfword offset, where the second halfword holds the opcode bits.
+///
+///   15   10 9     15 13 12 11 10
+///   11110 S imm10 1 op J1 1 J2 imm11
+///
+/// J1 and J2 are the inverted bits 22 and 21 of the offset, xor'ed with its sign.
+fn put_branch24<CS: CodeSink + ?Sized>(op: u32, disp: i32, sink: &mut CS) {
+    debug_assert!(disp & 1 == 0 && disp >= -(1 << 24) && disp < 1 << 24);
+    let imm = (disp >> 1) as u32;
+    let s = (imm >> 23) & 1;
+    let j1 = ((imm >> 22) & 1) ^ 1 ^ s;
+    let j2 = ((imm >> 21) & 1) ^ 1 ^ s;
+    let hw1 = 0xf000 | (s << 10) | ((imm >> 11) & 0x3ff);
+    let hw2 = op | (j1 << 13) | (j2 << 11) | (imm & 0x7ff);
+    put_t32(hw1, hw2, sink);
+}
+
+/// Unconditional branch with a 24-bit halfword offset: `b.w`.
+fn put_b_w<CS: CodeSink + ?Sized>(disp: i32, sink: &mut CS) {
+    put_branch24(0x9000, disp, sink);
+}
+
+/// Branch with link: `bl`.
+fn put_bl<CS: CodeSink + ?Sized>(disp: i32, sink: &mut CS) {
+    put_branch24(0xd000, disp, sink);
+}
+
+/// Branches to a register: `bx` and `blx`.
+///
+/// Encoding bits: the instruction with the Rm field clear.
+fn put_branch_reg<CS: CodeSink + ?Sized>(bits: u16, rm: RegUnit, sink: &mut CS) {
+    sink.put2(bits | (reg(rm) << 3) as u16);
+}
+
+/// Load a 32-bit literal placed right after the instruction, and branch over it:
+/// `ldr.w rt, [pc, #4]; b.n`. The caller emits the literal, and a `nop` after it when this
+/// function returns `false`.
+///
+/// The literal must be word-aligned, so a `nop` is emitted before or after it, keeping the size of
+/// the sequence constant. Returns whether the `nop` was emitted before the literal.
+fn put_ldr_literal_skip<CS: CodeSink + ?Sized>(rt: RegUnit, sink: &mut CS) -> bool {
+    // The PC reads as the word-aligned address of the `ldr` plus 4, which is where the literal
+    // starts, or 2 bytes before.
+    put_t32(0xf8df, (reg(rt) << 12) | 4, sink);
+    // Skip the literal and the `nop`.
+    put_b_n(4, sink);
+    let padded = sink.offset() % 4 != 0;
+    if padded {
+        sink.put2(NOP);
+    }
+    padded
+}
+
+/// Emit the `nop` after a literal, when `needed`.
+fn put_literal_padding<CS: CodeSink + ?Sized>(needed: bool, sink: &mut CS) {
+    if needed {
+        sink.put2(NOP);
+    }
+}
+
+/// Trap with `IntegerDivisionByZero` when `rm` is zero: `cmp.w rm, #0; bne.n; udf`.
+fn put_div_by_zero_check<CS: CodeSink + ?Sized>(rm: RegUnit, srcloc: SourceLoc, sink: &mut CS) {
+    put_modimm(CMP, rm, 0, PC, sink);
+    put_bcond_n(COND_NE, 0, sink);
+    sink.trap(TrapCode::IntegerDivisionByZero, srcloc);
+    sink.put2(UDF);
+}
+
+/// Trap with `IntegerOverflow` when dividing the minimum integer by -1.
+fn put_sdiv_overflow_check<CS: CodeSink + ?Sized>(
+    rn: RegUnit,
+    rm: RegUnit,
+    srcloc: SourceLoc,
+    sink: &mut CS,
+) {
+    // cmn.w rm, #1
+    put_modimm(CMN, rm, 1, PC, sink);
+    // When rm is -1, overflows only when rn is the minimum integer: cmpeq.w rn, #0x80000000.
+    put_it(COND_EQ, sink);
+    put_modimm(CMP, rn, t32_modimm(0x8000_0000).unwrap(), PC, sink);
+    put_bcond_n(COND_NE, 0, sink);
+    sink.trap(TrapCode::IntegerOverflow, srcloc);
+    sink.put2(UDF);
+}
+
+// Loads and stores of a general purpose register.
+//
+// The encoding bits are the first halfword of the 12-bit immediate form, with the Rn field clear:
+//
+//   15      8 7 6  4 3    15 11
+//   1111100 S 1 sz L Rn   Rt imm12
+//
+// Clearing bit 7 gives the register offset form and the 8-bit immediate forms.
+
+/// Number of bytes emitted by `put_ldst`.
+pub fn ldst_size(offset: i64) -> u8 {
+    if offset > -256 && offset < 4096 {
+        4
+    } else {
+        movimm_size(offset) + 4
+    }
+}
+
+/// Load or store `rt` at address `rn + offset`, using the 12-bit immediate form for positive
+/// offsets and the 8-bit immediate form for small negative ones. Other offsets are materialized
+/// in ip.
+///
+/// When `trap` is given, it is recorded for the memory access.
+fn put_ldst<CS: CodeSink + ?Sized>(
+    bits: u16,
+    rt: RegUnit,
+    rn: RegUnit,
+    offset: i64,
+    trap: Option<(TrapCode, SourceLoc)>,
+    sink: &mut CS,
+) {
+    if offset > -256 && offset < 4096 {
+        if let Some((code, srcloc)) = trap {
+            sink.trap(code, srcloc);
+        }
+        let bits = u32::from(bits);
+        if offset >= 0 {
+            put_t32(bits | reg(rn), (reg(rt) << 12) | offset as u32, sink);
+        } else {
+            // P = 1, U = 0, W = 0.
+            let imm8 = (-offset) as u32;
+            put_t32(
+                (bits & !0x80) | reg(rn),
+                (reg(rt) << 12) | 0xc00 | imm8,
+                sink,
+            );
+        }
+    } else {
+        put_movimm(SCRATCH, offset, sink);
+        if let Some((code, srcloc)) = trap {
+            sink.trap(code, srcloc);
+        }
+        put_ldst_reg(bits, rt, rn, SCRATCH, 0, sink);
+    }
+}
+
+/// Load or store `rt` at address `rn + (rm << shift)`.
+fn put_ldst_reg<CS: CodeSink + ?Sized>(
+    bits: u16,
+    rt: RegUnit,
+    rn: RegUnit,
+    rm: RegUnit,
+    shift: u32,
+    sink: &mut CS,
+) {
+    let hw1 = (u32::from(bits) & !0x80) | reg(rn);
+    put_t32(hw1, (reg(rt) << 12) | (shift << 4) | reg(rm), sink);
+}
+
+/// Load or store `rt` relative to `sp`, with writeback. `mode` holds the `P U W imm8` fields of
+/// the 8-bit immediate form.
+///
+/// Encoding bits: the first halfword, including Rn.
+fn put_ldst_sp_writeback<CS: CodeSink + ?Sized>(bits: u16, rt: RegUnit, mode: u32, sink: &mut CS) {
+    put_t32(u32::from(bits), (reg(rt) << 12) | mode, sink);
+}
+
+/// Store or load two registers with `stmdb sp!` or `ldmia sp!`. The lowest numbered register is
+/// at the lowest address.
+///
+/// Encoding bits: the first halfword, including Rn.
+fn put_ldstm<CS: CodeSink + ?Sized>(bits: u16, r1: RegUnit, r2: RegUnit, sink: &mut CS) {
+    put_t32(u32::from(bits), (1 << reg(r1)) | (1 << reg(r2)), sink);
+}
+
+/// Store or load one double precision register with `vstmdb sp!` or `vldmia sp!`.
+///
+/// Encoding bits: the first halfword, including Rn.
+fn put_vldstm<CS: CodeSink + ?Sized>(bits: u16, vd: u32, sink: &mut CS) {
+    let hw1 = u32::from(bits) | ((vd >> 4) << 6);
+    put_t32(hw1, ((vd & 0xf) << 12) | 0xb02, sink);
+}
+
+// Loads and stores of a VFP register.
+//
+//   15      7 6 5  3    15 11   8  7
+//   11101101 U D 0 L Rn   Vd 101 sz imm8
+//
+// The encoding bits are the first halfword with the U, D and Rn fields clear, and `sz` in bit 0.
+
+fn fits_vldst_imm(offset: i64) -> bool {
+    offset & 3 == 0 && offset >= -1020 && offset <= 1020
+}
+
+/// Number of bytes emitted by `put_vldst`.
+pub fn vldst_size(offset: i64) -> u8 {
+    if fits_vldst_imm(offset) {
+        4
+    } else {
+        dp_imm_size(ADD, offset) + 4
+    }
+}
+
+/// Load or store the VFP register `vd` at address `rn + offset`. Offsets which aren't multiples
+/// of 4 up to 1020 are added to `rn` in ip first.
+///
+/// When `trap` is given, it is recorded for the memory access.
+fn put_vldst<CS: CodeSink + ?Sized>(
+    bits: u16,
+    vd: u32,
+    rn: RegUnit,
+    offset: i64,
+    trap: Option<(TrapCode, SourceLoc)>,
+    sink: &mut CS,
+) {
+    let (rn, offset) = if fits_vldst_imm(offset) {
+        (rn, offset)
+    } else {
+        put_dp_imm(ADD, rn, offset, SCRATCH, sink);
+        (SCRATCH, 0)
+    };
+    if let Some((code, srcloc)) = trap {
+        sink.trap(code, srcloc);
+    }
+    let bits = u32::from(bits);
+    let up = if offset >= 0 { 1 } else { 0 };
+    let imm8 = (offset.abs() >> 2) as u32;
+    let hw1 = (bits & 0xfff0) | (up << 7) | ((vd >> 4) << 6) | reg(rn);
+    let hw2 = ((vd & 0xf) << 12) | 0xa00 | ((bits & 1) << 8) | imm8;
+    put_t32(hw1, hw2, sink);
+}
+
+/// VFP data processing instructions.
+///
+///   15     7 6 5  3    15 11   8  7 6 5 4 3
+///   11101110 o1 D o2 Vn   Vd 101 sz N o3 M 0 Vm
+///
+/// The register fields are given as `D:Vd`, `N:Vn` and `M:Vm`, see `sreg` and `dreg`. The
+/// instructions with fewer operands use some of the register fields as opcode fields, and must
+/// pass 0 in them.
+///
+/// Encoding bits: `(o1 << 6) | (o2 << 4) | Vn`, then `(N << 9) | (o3 << 8) | (sz << 10)`.
+fn put_vfp<CS: CodeSink + ?Sized>(bits: u16, vd: u32, vn: u32, vm: u32, sink: &mut CS) {
+    let bits = u32::from(bits);
+    let hw1 = 0xee00 | (bits & 0xbf) | ((vd >> 4) << 6) | (vn & 0xf);
+    let hw2 = ((vd & 0xf) << 12)
+        | 0xa00
+        | (((bits >> 10) & 1) << 8)
+        | (((bits >> 8) & 3) << 6)
+        | ((vn >> 4) << 7)
+        | ((vm >> 4) << 5)
+        | (vm & 0xf);
+    put_t32(hw1, hw2, sink);
+}
+
+/// Move between a general purpose register and a single precision register: `vmov rt, sn` when
+/// `to_core`, `vmov sn, rt` otherwise.
+fn put_vmov_core<CS: CodeSink + ?Sized>(to_core: bool, sn: u32, rt: RegUnit, sink: &mut CS) {
+    let op = if to_core { 1 } else { 0 };
+    let hw1 = 0xee00 | (op << 4) | (sn & 0xf);
+    let hw2 = (reg(rt) << 12) | 0xa10 | ((sn >> 4) << 7);
+    put_t32(hw1, hw2, sink);
+}
+
+/// Convert the VFP register `vm` to an integer in `rd`, with the conversion instruction in
+/// `bits`.
+///
+/// The conversion needs a single precision register for its result, and borrows s0 or s2,
+/// whichever doesn't overlap the input register unit `unit`. Its value is saved in ip.
+fn put_vcvt_to_int<CS: CodeSink + ?Sized>(
+    bits: u16,
+    vm: u32,
+    unit: RegUnit,
+    rd: RegUnit,
+    sink: &mut CS,
+) {
+    let tmp = sreg(if unit == 0 { 2 } else { 0 });
+    put_vmov_core(true, tmp, SCRATCH, sink);
+    put_vfp(bits, tmp, 0, vm, sink);
+    put_vmov_core(true, tmp, rd, sink);
+    put_vmov_core(false, tmp, SCRATCH, sink);
+}
+
+/// Compare `vm` with itself to detect NaN, with the precision taken from the conversion
+/// instruction in `bits`.
+fn put_vcmp_self<CS: CodeSink + ?Sized>(bits: u16, vm: u32, sink: &mut CS) {
+    put_vfp(VCMP | (bits & 0x400), vm, 0, vm, sink);
+    put_vmrs_apsr_nzcv(sink);
+}
+
+/// Get the single precision register holding the sign bit of the VFP register `r`, whose
+/// precision is given by the `sz` bit in `bits`.
+fn sign_sreg(bits: u16, r: RegUnit) -> u32 {
+    sreg(r + ((bits >> 10) & 1))
+}
+
+/// Copy the sign of `vm` to the magnitude of `vn` into `vd`. The sign is read from `sign` first,
+/// since `vd` may overlap `vm`.
+fn put_vcopysign<CS: CodeSink + ?Sized>(bits: u16, vn: u32, sign: u32, vd: u32, sink: &mut CS) {
+    let sz = bits & 0x400;
+    put_vmov_core(true, sign, SCRATCH, sink);
+    put_vfp(VABS | sz, vd, 0, vn, sink);
+    put_modimm(TST, SCRATCH, SIGN_BIT, PC, sink);
+    put_it(COND_NE, sink);
+    put_vfp(VNEG | sz, vd, 0, vd, sink);
+}
+
+/// Compute the minimum or maximum of `vn` and `vm` into `vd`, where `vfirst` is the operand to
+/// pick when `vn` is less than `vm`, and `vsecond` the other one. `sign` holds the sign of `vn`.
+///
+/// Equal operands may be zeros of different signs, so `vfirst` is picked when `vn` is negative,
+/// which gives -0.0 for the minimum and +0.0 for the maximum. NaNs are propagated by adding the
+/// operands.
+#[allow(clippy::too_many_arguments)]
+fn put_vminmax<CS: CodeSink + ?Sized>(
+    bits: u16,
+    vn: u32,
+    vm: u32,
+    vfirst: u32,
+    vsecond: u32,
+    sign: u32,
+    vd: u32,
+    sink: &mut CS,
+) {
+    let sz = bits & 0x400;
+    put_vfp(VCMP | sz, vn, 0, vm, sink);
+    put_vmrs_apsr_nzcv(sink);
+    put_bcond_n(COND_VS, 32, sink);
+    put_bcond_n(COND_EQ, 10, sink);
+    // Ordered and not equal.
+    put_ite(COND_MI, sink);
+    put_vfp(VMOV | sz, vd, 0, vfirst, sink);
+    put_vfp(VMOV | sz, vd, 0, vsecond, sink);
+    put_b_n(22, sink);
+    // Equal.
+    put_vmov_core(true, sign, SCRATCH, sink);
+    put_modimm(TST, SCRATCH, SIGN_BIT, PC, sink);
+    put_ite(COND_NE, sink);
+    put_vfp(VMOV | sz, vd, 0, vfirst, sink);
+    put_vfp(VMOV | sz, vd, 0, vsecond, sink);
+    put_b_n(2, sink);
+    // Unordered.
+    put_vfp(VADD | sz, vd, vn, vm, sink);
+}
+
+/// Copy the VFP comparison flags to the APSR: `vmrs APSR_nzcv, fpscr`.
+fn put_vmrs_apsr_nzcv<CS: CodeSink + ?Sized>(sink: &mut CS) {
+    put_t32(0xeef1, 0xfa10, sink);
+}
+
+/// Clear the cumulative invalid operation flag in the FPSCR, using `rd` as a temporary.
+fn put_clear_ioc<CS: CodeSink + ?Sized>(rd: RegUnit, sink: &mut CS) {
+    // vmrs rd, fpscr
+    put_t32(0xeef1, (reg(rd) << 12) | 0xa10, sink);
+    put_modimm(BIC, rd, 1, rd, sink);
+    // vmsr fpscr, rd
+    put_t32(0xeee1, (reg(rd) << 12) | 0xa10, sink);
+}
+
+/// Trap with `IntegerOverflow` when the last conversion raised an invalid operation exception.
+fn put_ioc_check<CS: CodeSink + ?Sized>(srcloc: SourceLoc, sink: &mut CS) {
+    // vmrs ip, fpscr
+    put_t32(0xeef1, (reg(SCRATCH) << 12) | 0xa10, sink);
+    // tst.w ip, #1: IOC is bit 0.
+    put_modimm(TST, SCRATCH, 1, PC, sink);
+    put_bcond_n(COND_EQ, 0, sink);
+    sink.trap(TrapCode::IntegerOverflow, srcloc);
+    sink.put2(UDF);
+}
@@ -1,10 +1,280 @@
 //! Encoding tables for ARM32 ISA.
 
-use crate::ir;
+use super::binemit::{dp_imm_size, ldst_size, movimm_size, vldst_size};
+use super::registers::*;
+use crate::ir::{self, Function, Inst, InstructionData};
 use crate::isa;
 use crate::isa::constraints::*;
 use crate::isa::enc_tables::*;
-use crate::isa::encoding::RecipeSizing;
+use crate::isa::encoding::{base_size, Encoding, RecipeSizing};
+use crate::isa::StackRef;
+use crate::predicates;
+use crate::regalloc::RegDiversions;
 
 include!(concat!(env!("OUT_DIR"), "/encoding-arm32.rs"));
 include!(concat!(env!("OUT_DIR"), "/legalize-arm32.rs"));
+
+/// Size of an integer constant, which needs a `movw` and `movt` pair when it isn't a 16-bit or a
+/// modified immediate.
+fn size_for_movimm(
+    _sizing: &RecipeSizing,
+    _enc: Encoding,
+    inst: Inst,
+    _divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    match func.dfg[inst] {
+        InstructionData::UnaryImm { imm, .. } => movimm_size(imm.into()),
+        _ => panic!("Expected UnaryImm: {}", func.dfg.display_inst(inst, None)),
+    }
+}
+
+/// Size of a floating point constant, built in a general purpose register one word at a time.
+fn size_for_vconst(
+    _sizing: &RecipeSizing,
+    _enc: Encoding,
+    inst: Inst,
+    _divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    match func.dfg[inst] {
+        InstructionData::UnaryIeee32 { imm, .. } => movimm_size(imm.bits().into()) + 4,
+        InstructionData::UnaryIeee64 { imm, .. } => {
+            let bits = imm.bits();
+            movimm_size(bits as u32 as i64) + movimm_size((bits >> 32) as i64) + 8
+        }
+        _ => panic!("Expected UnaryIeee: {}", func.dfg.display_inst(inst, None)),
+    }
+}
+
+/// Size of a data processing instruction with an immediate, which takes more instructions when
+/// the immediate needs to be materialized.
+fn size_for_alu_imm(
+    _sizing: &RecipeSizing,
+    enc: Encoding,
+    inst: Inst,
+    _divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    match func.dfg[inst] {
+        InstructionData::BinaryImm { imm, .. } => dp_imm_size(enc.bits(), imm.into()),
+        _ => panic!("Expected BinaryImm: {}", func.dfg.display_inst(inst, None)),
+    }
+}
+
+/// Size of a comparison with an immediate, followed by a conditional set.
+fn size_for_icscc_imm(
+    sizing: &RecipeSizing,
+    enc: Encoding,
+    inst: Inst,
+    _divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    match func.dfg[inst] {
+        InstructionData::IntCompareImm { imm, .. } => {
+            // The base size assumes a single instruction for the comparison.
+            sizing.base_size - 4 + dp_imm_size(enc.bits(), imm.into())
+        }
+        _ => panic!(
+            "Expected IntCompareImm: {}",
+            func.dfg.display_inst(inst, None)
+        ),
+    }
+}
+
+/// Size of the address of a stack slot.
+fn size_for_spaddr(
+    _sizing: &RecipeSizing,
+    enc: Encoding,
+    inst: Inst,
+    _divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    match func.dfg[inst] {
+        InstructionData::StackLoad {
+            stack_slot, offset, ..
+        } => {
+            let sp = StackRef::sp(stack_slot, &func.stack_slots);
+            let offset: i32 = offset.into();
+            dp_imm_size(enc.bits(), i64::from(sp.offset + offset))
+        }
+        _ => panic!("Expected StackLoad: {}", func.dfg.display_inst(inst, None)),
+    }
+}
+
+/// Size of a stack pointer adjustment.
+fn size_for_adjustsp(
+    _sizing: &RecipeSizing,
+    enc: Encoding,
+    inst: Inst,
+    _divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    match func.dfg[inst] {
+        InstructionData::UnaryImm { imm, .. } => dp_imm_size(enc.bits(), imm.into()),
+        _ => panic!("Expected UnaryImm: {}", func.dfg.display_inst(inst, None)),
+    }
+}
+
+/// Offset of a load or store, which needs to be materialized when it doesn't fit in the
+/// immediate forms.
+fn ldst_offset(inst: Inst, func: &Function) -> i64 {
+    match func.dfg[inst] {
+        InstructionData::Load { offset, .. } | InstructionData::Store { offset, .. } => {
+            offset.into()
+        }
+        _ => panic!(
+            "Expected Load or Store: {}",
+            func.dfg.display_inst(inst, None)
+        ),
+    }
+}
+
+fn size_for_load(
+    _sizing: &RecipeSizing,
+    _enc: Encoding,
+    inst: Inst,
+    _divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    ldst_size(ldst_offset(inst, func))
+}
+
+fn size_for_store(
+    _sizing: &RecipeSizing,
+    _enc: Encoding,
+    inst: Inst,
+    _divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    ldst_size(ldst_offset(inst, func))
+}
+
+fn size_for_vload(
+    _sizing: &RecipeSizing,
+    _enc: Encoding,
+    inst: Inst,
+    _divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    vldst_size(ldst_offset(inst, func))
+}
+
+fn size_for_vstore(
+    _sizing: &RecipeSizing,
+    _enc: Encoding,
+    inst: Inst,
+    _divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    vldst_size(ldst_offset(inst, func))
+}
+
+/// Offset from the stack pointer of the stack slot `ss`.
+fn stack_offset(ss: ir::StackSlot, func: &Function) -> i64 {
+    StackRef::sp(ss, &func.stack_slots).offset.into()
+}
+
+fn spill_offset(inst: Inst, divert: &RegDiversions, func: &Function) -> i64 {
+    let result = func.dfg.first_result(inst);
+    stack_offset(divert.stack(result, &func.locations), func)
+}
+
+fn fill_offset(inst: Inst, divert: &RegDiversions, func: &Function) -> i64 {
+    let arg = func.dfg.inst_args(inst)[0];
+    stack_offset(divert.stack(arg, &func.locations), func)
+}
+
+fn regspill_offset(inst: Inst, func: &Function) -> i64 {
+    match func.dfg[inst] {
+        InstructionData::RegSpill { dst, .. } => stack_offset(dst, func),
+        _ => panic!("Expected RegSpill: {}", func.dfg.display_inst(inst, None)),
+    }
+}
+
+fn regfill_offset(inst: Inst, func: &Function) -> i64 {
+    match func.dfg[inst] {
+        InstructionData::RegFill { src, .. } => stack_offset(src, func),
+        _ => panic!("Expected RegFill: {}", func.dfg.display_inst(inst, None)),
+    }
+}
+
+fn size_for_spill(
+    _sizing: &RecipeSizing,
+    _enc: Encoding,
+    inst: Inst,
+    divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    ldst_size(spill_offset(inst, divert, func))
+}
+
+fn size_for_fill(
+    _sizing: &RecipeSizing,
+    _enc: Encoding,
+    inst: Inst,
+    divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    ldst_size(fill_offset(inst, divert, func))
+}
+
+fn size_for_regspill(
+    _sizing: &RecipeSizing,
+    _enc: Encoding,
+    inst: Inst,
+    _divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    ldst_size(regspill_offset(inst, func))
+}
+
+fn size_for_regfill(
+    _sizing: &RecipeSizing,
+    _enc: Encoding,
+    inst: Inst,
+    _divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    ldst_size(regfill_offset(inst, func))
+}
+
+fn size_for_vspill(
+    _sizing: &RecipeSizing,
+    _enc: Encoding,
+    inst: Inst,
+    divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    vldst_size(spill_offset(inst, divert, func))
+}
+
+fn size_for_vfill(
+    _sizing: &RecipeSizing,
+    _enc: Encoding,
+    inst: Inst,
+    divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    vldst_size(fill_offset(inst, divert, func))
+}
+
+fn size_for_vregspill(
+    _sizing: &RecipeSizing,
+    _enc: Encoding,
+    inst: Inst,
+    _divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    vldst_size(regspill_offset(inst, func))
+}
+
+fn size_for_vregfill(
+    _sizing: &RecipeSizing,
+    _enc: Encoding,
+    inst: Inst,
+    _divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    vldst_size(regfill_offset(inst, func))
+}
@@ -15,6 +15,8 @@ use crate::isa::enc_tables::{self as shared_enc_tables, lookup_enclist, Encoding
 use crate::isa::Builder as IsaBuilder;
 use crate::isa::{EncInfo, RegClass, RegInfo, TargetIsa};
 use crate::regalloc;
+use crate::result::CodegenResult;
+use crate::timing;
 use alloc::borrow::Cow;
 use alloc::boxed::Box;
 use core::fmt;
@@ -43,13 +45,7 @@ fn isa_constructor(
     builder: shared_settings::Builder,
 ) -> Box<dyn TargetIsa> {
     let level1 = match triple.architecture {
-        Architecture::Arm(arm) => {
-            if arm.is_thumb() {
-                &enc_tables::LEVEL1_T32[..]
-            } else {
-                &enc_tables::LEVEL1_A32[..]
-            }
-        }
+        Architecture::Arm(arm) if arm.is_thumb() => &enc_tables::LEVEL1_T32[..],
         _ => panic!(),
     };
     Box::new(Isa {
@@ -73,6 +69,10 @@ impl TargetIsa for Isa {
         &self.shared_flags
     }
 
+    fn uses_cpu_flags(&self) -> bool {
+        true
+    }
+
     fn register_info(&self) -> RegInfo {
         registers::INFO.clone()
     }
@@ -102,7 +102,7 @@ impl TargetIsa for Isa {
     }
 
     fn legalize_signature(&self, sig: &mut Cow<ir::Signature>, current: bool) {
-        abi::legalize_signature(sig, &self.triple, current)
+        abi::legalize_signature(sig, &self.shared_flags, current)
     }
 
     fn regclass_for_abi_type(&self, ty: ir::Type) -> RegClass {
@@ -113,6 +113,11 @@ impl TargetIsa for Isa {
         abi::allocatable_registers(func)
     }
 
+    fn prologue_epilogue(&self, func: &mut ir::Function) -> CodegenResult<()> {
+        let _tt = timing::prologue_epilogue();
+        abi::prologue_epilogue(func, self)
+    }
+
     #[cfg(feature = "testing_hooks")]
     fn emit_inst(
         &self,
@@ -129,11 +134,13 @@ impl TargetIsa for Isa {
     }
 
     fn unsigned_add_overflow_condition(&self) -> ir::condcodes::IntCC {
-        ir::condcodes::IntCC::UnsignedLessThan
+        // Carry set.
+        ir::condcodes::IntCC::UnsignedGreaterThanOrEqual
     }
 
     fn unsigned_sub_overflow_condition(&self) -> ir::condcodes::IntCC {
-        ir::condcodes::IntCC::UnsignedGreaterThanOrEqual
+        // Carry clear, meaning a borrow.
+        ir::condcodes::IntCC::UnsignedLessThan
     }
 }
 
@@ -1,24 +1,147 @@
 //! ARM 64 ABI implementation.
+//!
+//! This module implements the Procedure Call Standard for the ARM 64-bit Architecture (AAPCS64):
+//!
+//! - Integer arguments and return values are passed in `x0`-`x7`, floating point ones in
+//!   `v0`-`v7`. Remaining arguments are passed on the stack in 8-byte slots.
+//! - The address of a struct return area is passed in `x8`.
+//! - Registers `x19`-`x28` and the low 64 bits of `v8`-`v15` are callee-saved.
+//! - The prologue stores the frame pointer `x29` and the link register `x30` as a frame record at
+//!   the top of the frame, and points `x29` at it.
 
-use super::registers::{FPR, GPR};
+use super::registers::{FPR, GPR, RU};
+use crate::abi::{legalize_args, legalize_returns, ArgAction, ArgAssigner, ValueConversion};
+use crate::cursor::{Cursor, CursorPosition, EncCursor};
 use crate::ir;
-use crate::isa::RegClass;
+use crate::ir::immediates::Imm64;
+use crate::ir::types::{F64, I64};
+use crate::ir::{AbiParam, ArgumentExtension, ArgumentLoc, ArgumentPurpose, InstBuilder, Type};
+use crate::isa::{CallConv, RegClass, RegUnit, TargetIsa};
 use crate::regalloc::RegisterSet;
+use crate::result::{CodegenError, CodegenResult};
 use crate::settings as shared_settings;
+use crate::stack_layout::layout_stack;
 use alloc::borrow::Cow;
+use alloc::vec::Vec;
+use core::i32;
+
+/// The stack pointer must be 16-byte aligned at all times.
+const STACK_ALIGNMENT: u32 = 16;
+
+/// Number of argument and return value registers in each register class.
+const ARG_REG_LIMIT: usize = 8;
+
+/// Callee-saved general purpose registers.
+static CALLEE_SAVED_GPRS: [RU; 10] = [
+    RU::x19,
+    RU::x20,
+    RU::x21,
+    RU::x22,
+    RU::x23,
+    RU::x24,
+    RU::x25,
+    RU::x26,
+    RU::x27,
+    RU::x28,
+];
+
+/// Callee-saved floating point registers. Only their low 64 bits need to be preserved.
+static CALLEE_SAVED_FPRS: [RU; 8] = [
+    RU::v8,
+    RU::v9,
+    RU::v10,
+    RU::v11,
+    RU::v12,
+    RU::v13,
+    RU::v14,
+    RU::v15,
+];
+
+#[derive(Clone)]
+struct Args {
+    gpr_used: usize,
+    fpr_used: usize,
+    offset: u32,
+}
+
+impl Args {
+    fn new() -> Self {
+        Self {
+            gpr_used: 0,
+            fpr_used: 0,
+            offset: 0,
+        }
+    }
+}
+
+impl ArgAssigner for Args {
+    fn assign(&mut self, arg: &AbiParam) -> ArgAction {
+        let ty = arg.value_type;
+
+        // No vector registers are supported yet, so break all vectors down.
+        if ty.is_vector() {
+            return ValueConversion::VectorSplit.into();
+        }
+
+        // Large integers and booleans are broken down to fit in a register.
+        if !ty.is_float() && ty.bits() > 64 {
+            return ValueConversion::IntSplit.into();
+        }
+
+        // Small integers are extended to the size of a pointer register.
+        if ty.is_int() && ty.bits() < 64 {
+            match arg.extension {
+                ArgumentExtension::None => {}
+                ArgumentExtension::Uext => return ValueConversion::Uext(I64).into(),
+                ArgumentExtension::Sext => return ValueConversion::Sext(I64).into(),
+            }
+        }
+
+        // The struct return address has a dedicated register.
+        if arg.purpose == ArgumentPurpose::StructReturn {
+            return ArgumentLoc::Reg(RU::x8 as RegUnit).into();
+        }
+
+        if ty.is_float() {
+            if self.fpr_used < ARG_REG_LIMIT {
+                let reg = FPR.unit(self.fpr_used);
+                self.fpr_used += 1;
+                return ArgumentLoc::Reg(reg).into();
+            }
+        } else if self.gpr_used < ARG_REG_LIMIT {
+            let reg = GPR.unit(self.gpr_used);
+            self.gpr_used += 1;
+            return ArgumentLoc::Reg(reg).into();
+        }
+
+        // Assign a stack location.
+        let loc = ArgumentLoc::Stack(self.offset as i32);
+        self.offset += 8;
+        debug_assert!(self.offset <= i32::MAX as u32);
+        loc.into()
+    }
+}
 
 /// Legalize `sig`.
 pub fn legalize_signature(
-    _sig: &mut Cow<ir::Signature>,
+    sig: &mut Cow<ir::Signature>,
     _flags: &shared_settings::Flags,
     _current: bool,
 ) {
-    unimplemented!()
+    let mut args = Args::new();
+    let mut rets = Args::new();
+
+    // The struct return pointer is passed in `x8`, and not returned.
+    legalize_returns(sig, I64, &mut args, &mut rets, false);
+
+    if let Some(new_params) = legalize_args(&sig.params, &mut args) {
+        sig.to_mut().params = new_params;
+    }
 }
 
 /// Get register class for a type appearing in a legalized signature.
 pub fn regclass_for_abi_type(ty: ir::Type) -> RegClass {
-    if ty.is_int() {
+    if ty.is_int() || ty.is_bool() {
         GPR
     } else {
         FPR
@@ -27,5 +150,204 @@ pub fn regclass_for_abi_type(ty: ir::Type) -> RegClass {
 
 /// Get the set of allocatable registers for `func`.
 pub fn allocatable_registers(_func: &ir::Function) -> RegisterSet {
-    unimplemented!()
+    let mut regs = RegisterSet::new();
+    // Scratch registers used by the instruction encodings.
+    regs.take(GPR, RU::x16 as RegUnit);
+    regs.take(GPR, RU::x17 as RegUnit);
+    regs.take(FPR, RU::v31 as RegUnit);
+    // Platform register.
+    regs.take(GPR, RU::x18 as RegUnit);
+    // Frame pointer and link register.
+    regs.take(GPR, RU::x29 as RegUnit);
+    regs.take(GPR, RU::x30 as RegUnit);
+    // Stack pointer or zero register, depending on the instruction.
+    regs.take(GPR, RU::x31 as RegUnit);
+    regs
+}
+
+/// Get the callee-saved registers of `class` that are used in `func`.
+fn callee_saved_regs_used(
+    func: &ir::Function,
+    class: RegClass,
+    callee_saved: &[RU],
+) -> Vec<RegUnit> {
+    let mut used = RegisterSet::empty();
+    for value_loc in func.locations.values() {
+        if let ir::ValueLoc::Reg(ru) = *value_loc {
+            if class.contains(ru) && !used.is_avail(class, ru) {
+                used.free(class, ru);
+            }
+        }
+    }
+
+    // regmove and regfill instructions may temporarily divert values into other registers,
+    // and these are not reflected in `func.locations`.
+    for ebb in &func.layout {
+        for inst in func.layout.ebb_insts(ebb) {
+            match func.dfg[inst] {
+                ir::instructions::InstructionData::RegMove { dst, .. }
+                | ir::instructions::InstructionData::RegFill { dst, .. }
+                    if class.contains(dst) && !used.is_avail(class, dst) =>
+                {
+                    used.free(class, dst);
+                }
+                _ => (),
+            }
+        }
+    }
+
+    callee_saved
+        .iter()
+        .map(|&reg| reg as RegUnit)
+        .filter(|&reg| used.is_avail(class, reg))
+        .collect()
+}
+
+/// Insert a prologue and epilogues, using the AAPCS64 frame layout.
+pub fn prologue_epilogue(func: &mut ir::Function, isa: &dyn TargetIsa) -> CodegenResult<()> {
+    match func.signature.call_conv {
+        CallConv::Fast | CallConv::Cold | CallConv::SystemV => aapcs64_prologue_epilogue(func, isa),
+        call_conv => Err(CodegenError::Unsupported(format!(
+            "the {} calling convention on arm64",
+            call_conv
+        ))),
+    }
+}
+
+fn aapcs64_prologue_epilogue(func: &mut ir::Function, isa: &dyn TargetIsa) -> CodegenResult<()> {
+    let gprs = callee_saved_regs_used(func, GPR, &CALLEE_SAVED_GPRS);
+    let fprs = callee_saved_regs_used(func, FPR, &CALLEE_SAVED_FPRS);
+
+    // The reserved stack area is composed of the frame record and all the callee-saved registers,
+    // stored in pairs to keep the stack pointer aligned.
+    let pairs = 1 + (gprs.len() + 1) / 2 + (fprs.len() + 1) / 2;
+    let csr_stack_size = (pairs * 16) as i32;
+    func.create_stack_slot(ir::StackSlotData {
+        kind: ir::StackSlotKind::IncomingArg,
+        size: csr_stack_size as u32,
+        offset: Some(-csr_stack_size),
+    });
+
+    let is_leaf = func.is_leaf();
+    let total_stack_size = layout_stack(&mut func.stack_slots, is_leaf, STACK_ALIGNMENT)? as i32;
+    let local_stack_size = i64::from(total_stack_size - csr_stack_size);
+
+    // Add the frame record and the CSRs to the function signature.
+    let fp_arg = AbiParam::special_reg(I64, ArgumentPurpose::FramePointer, RU::x29 as RegUnit);
+    let lr_arg = AbiParam::special_reg(I64, ArgumentPurpose::Link, RU::x30 as RegUnit);
+    func.signature.params.push(fp_arg);
+    func.signature.returns.push(fp_arg);
+    func.signature.params.push(lr_arg);
+    func.signature.returns.push(lr_arg);
+
+    let csrs: Vec<(Type, RegUnit)> = gprs
+        .iter()
+        .map(|&reg| (I64, reg))
+        .chain(fprs.iter().map(|&reg| (F64, reg)))
+        .collect();
+    for &(ty, reg) in &csrs {
+        let csr_arg = AbiParam::special_reg(ty, ArgumentPurpose::CalleeSaved, reg);
+        func.signature.params.push(csr_arg);
+        func.signature.returns.push(csr_arg);
+    }
+
+    // Set up the cursor and insert the prologue.
+    let entry_ebb = func.layout.entry_block().expect("missing entry block");
+    let mut pos = EncCursor::new(func, isa).at_first_insertion_point(entry_ebb);
+    insert_prologue(&mut pos, local_stack_size, &gprs, &fprs);
+
+    // Reset the cursor and insert the epilogues.
+    let mut pos = pos.at_position(CursorPosition::Nowhere);
+    while let Some(ebb) = pos.next_ebb() {
+        pos.goto_last_inst(ebb);
+        if let Some(inst) = pos.current_inst() {
+            if pos.func.dfg[inst].opcode().is_return() {
+                insert_epilogue(inst, local_stack_size, &mut pos, &gprs, &fprs);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Append an entry block parameter living in `reg`.
+fn append_reg_param(pos: &mut EncCursor, ty: Type, reg: RegUnit) -> ir::Value {
+    let ebb = pos.current_ebb().expect("missing ebb under cursor");
+    let value = pos.func.dfg.append_ebb_param(ebb, ty);
+    pos.func.locations[value] = ir::ValueLoc::Reg(reg);
+    value
+}
+
+/// Push `regs` in pairs, with a single push for the last register when their number is odd.
+fn push_regs(pos: &mut EncCursor, ty: Type, regs: &[RegUnit]) {
+    for chunk in regs.chunks(2) {
+        let x = append_reg_param(pos, ty, chunk[0]);
+        if let Some(&reg) = chunk.get(1) {
+            let y = append_reg_param(pos, ty, reg);
+            pos.ins().arm64_push_pair(x, y);
+        } else {
+            pos.ins().arm64_push(x);
+        }
+    }
+}
+
+/// Insert the prologue for a given function.
+fn insert_prologue(pos: &mut EncCursor, stack_size: i64, gprs: &[RegUnit], fprs: &[RegUnit]) {
+    // Store the frame record, and point the frame pointer to it.
+    let fp = append_reg_param(pos, I64, RU::x29 as RegUnit);
+    let lr = append_reg_param(pos, I64, RU::x30 as RegUnit);
+    pos.ins().arm64_push_pair(fp, lr);
+    pos.ins()
+        .copy_special(RU::x31 as RegUnit, RU::x29 as RegUnit);
+
+    push_regs(pos, I64, gprs);
+    push_regs(pos, F64, fprs);
+
+    // Allocate stack frame storage.
+    if stack_size > 0 {
+        pos.func.prologue_end = Some(pos.ins().adjust_sp_down_imm(Imm64::new(stack_size)));
+    }
+}
+
+/// Pop `regs` in the reverse order of `push_regs`, inserting the instructions in reverse order
+/// before the return instruction `inst` and appending the restored values to its arguments.
+fn pop_regs(inst: ir::Inst, pos: &mut EncCursor, ty: Type, regs: &[RegUnit]) {
+    for chunk in regs.chunks(2) {
+        let popped = if chunk.len() == 2 {
+            let (x, y) = pos.ins().arm64_pop_pair(ty);
+            vec![x, y]
+        } else {
+            vec![pos.ins().arm64_pop(ty)]
+        };
+        pos.prev_inst();
+        for (&value, &reg) in popped.iter().zip(chunk) {
+            pos.func.locations[value] = ir::ValueLoc::Reg(reg);
+            pos.func.dfg.append_inst_arg(inst, value);
+        }
+    }
+}
+
+/// Insert an epilogue given a specific `return` instruction.
+fn insert_epilogue(
+    inst: ir::Inst,
+    stack_size: i64,
+    pos: &mut EncCursor,
+    gprs: &[RegUnit],
+    fprs: &[RegUnit],
+) {
+    if stack_size > 0 {
+        pos.ins().adjust_sp_up_imm(Imm64::new(stack_size));
+    }
+
+    // The pops are inserted right before the return, stepping backward each time, so they are
+    // emitted in the reverse order they are inserted in.
+    let (fp, lr) = pos.ins().arm64_pop_pair(I64);
+    pos.prev_inst();
+    pos.func.locations[fp] = ir::ValueLoc::Reg(RU::x29 as RegUnit);
+    pos.func.locations[lr] = ir::ValueLoc::Reg(RU::x30 as RegUnit);
+    pos.func.dfg.append_inst_arg(inst, fp);
+    pos.func.dfg.append_inst_arg(inst, lr);
+
+    pop_regs(inst, pos, I64, gprs);
+    pop_regs(inst, pos, F64, fprs);
 }
@@ -1,8 +1,577 @@
 //! Emitting binary ARM64 machine code.
 
-use crate::binemit::{bad_encoding, CodeSink};
-use crate::ir::{Function, Inst};
-use crate::isa::TargetIsa;
+use super::registers::RU;
+use crate::binemit::{bad_encoding, CodeSink, Reloc};
+use crate::ir::condcodes::{CondCode, FloatCC, IntCC};
+use crate::ir::{Ebb, Function, Inst, InstructionData, SourceLoc, TrapCode};
+use crate::isa::{RegUnit, StackBaseMask, StackRef, TargetIsa};
 use crate::regalloc::RegDiversions;
 
 include!(concat!(env!("OUT_DIR"), "/binemit-arm64.rs"));
+
+/// A permanently undefined instruction: `udf #0`.
+const UDF: u32 = 0x0000_0000;
+
+/// A breakpoint instruction: `brk #0`.
+const BRK: u32 = 0xd420_0000;
+
+/// The `vc` condition code, "no overflow", or "ordered" after a floating point comparison.
+const COND_VC: u32 = 0b0111;
+
+/// Register number of the scratch register used by multi-instruction sequences.
+const SCRATCH: u32 = RU::x16 as u32;
+
+fn reg(r: RegUnit) -> u32 {
+    u32::from(r) & 0x1f
+}
+
+/// Convert an integer condition code to the A64 `cond` field, as read after a `subs`.
+fn icc2cond(cond: IntCC) -> u32 {
+    use crate::ir::condcodes::IntCC::*;
+    match cond {
+        Equal => 0b0000,                      // eq
+        NotEqual => 0b0001,                   // ne
+        UnsignedGreaterThanOrEqual => 0b0010, // hs
+        UnsignedLessThan => 0b0011,           // lo
+        Overflow => 0b0110,                   // vs
+        NotOverflow => 0b0111,                // vc
+        UnsignedGreaterThan => 0b1000,        // hi
+        UnsignedLessThanOrEqual => 0b1001,    // ls
+        SignedGreaterThanOrEqual => 0b1010,   // ge
+        SignedLessThan => 0b1011,             // lt
+        SignedGreaterThan => 0b1100,          // gt
+        SignedLessThanOrEqual => 0b1101,      // le
+    }
+}
+
+/// Convert a floating point condition code to the A64 `cond` field, as read after an `fcmp`.
+///
+/// An unordered comparison sets the C and V flags, which the A64 condition codes below take
+/// into account. `one` and `ueq` can't be represented by a single condition code.
+fn fcc2cond(cond: FloatCC) -> u32 {
+    use crate::ir::condcodes::FloatCC::*;
+    match cond {
+        Equal => 0b0000,                         // eq
+        NotEqual => 0b0001,                      // ne
+        UnorderedOrGreaterThanOrEqual => 0b0101, // pl
+        LessThan => 0b0100,                      // mi
+        Unordered => 0b0110,                     // vs
+        Ordered => 0b0111,                       // vc
+        UnorderedOrGreaterThan => 0b1000,        // hi
+        LessThanOrEqual => 0b1001,               // ls
+        GreaterThanOrEqual => 0b1010,            // ge
+        UnorderedOrLessThan => 0b1011,           // lt
+        GreaterThan => 0b1100,                   // gt
+        UnorderedOrLessThanOrEqual => 0b1101,    // le
+        OrderedNotEqual | UnorderedOrEqual => panic!("{} needs two A64 conditions", cond),
+    }
+}
+
+/// Compute the displacement to `destination` from the current offset.
+fn branch_disp<CS: CodeSink + ?Sized>(destination: Ebb, func: &Function, sink: &CS) -> i32 {
+    func.offsets[destination].wrapping_sub(sink.offset()) as i32
+}
+
+/// Instructions described by their top 11 bits and an opcode field at bits 14:10.
+///
+///   31         20 15    14      9  4
+///   op[10:0]   Rm 0     op2[4:0] Rn Rd
+///           21  16   15       10  5  0
+///
+/// This covers the data processing instructions with shifted register operands (using a shift
+/// amount of zero), with two sources, with one source, the add/subtract with carry instructions,
+/// the bitwise vector instructions, and the branches to a register.
+///
+/// Encoding bits: `op[10:0] | (op2 << 11)`.
+fn put_rrr<CS: CodeSink + ?Sized>(bits: u16, rn: RegUnit, rm: RegUnit, rd: RegUnit, sink: &mut CS) {
+    let bits = u32::from(bits);
+    let op = bits & 0x7ff;
+    let op2 = bits >> 11;
+    sink.put4((op << 21) | (reg(rm) << 16) | (op2 << 10) | (reg(rn) << 5) | reg(rd));
+}
+
+/// Data processing instructions with three source registers.
+///
+///   31         20 15 14 9  4
+///   op[10:0]   Rm o0 Ra Rn Rd
+///           21  16 15 10 5  0
+///
+/// Encoding bits: `op[10:0] | (o0 << 11)`.
+fn put_rrrr<CS: CodeSink + ?Sized>(
+    bits: u16,
+    rn: RegUnit,
+    rm: RegUnit,
+    ra: RegUnit,
+    rd: RegUnit,
+    sink: &mut CS,
+) {
+    let bits = u32::from(bits);
+    let op = bits & 0x7ff;
+    let o0 = (bits >> 11) & 1;
+    sink.put4(
+        (op << 21) | (reg(rm) << 16) | (o0 << 15) | (reg(ra) << 10) | (reg(rn) << 5) | reg(rd),
+    );
+}
+
+/// Branches to a register: `br`, `blr` and `ret`. The `op2` field is all ones.
+///
+/// Encoding bits: same as `put_rrr`.
+fn put_branch_reg<CS: CodeSink + ?Sized>(bits: u16, rn: RegUnit, sink: &mut CS) {
+    put_rrr(bits, rn, 0b11111, 0, sink);
+}
+
+/// Add/subtract with a 12-bit unsigned immediate.
+///
+///   31         21    9  4
+///   op[9:0] sh imm12 Rn Rd
+///        22 21    10 5  0
+///
+/// Encoding bits: the same top 11 bits as `put_rrr`, with `sh` clear.
+fn put_addsub_imm<CS: CodeSink + ?Sized>(
+    bits: u16,
+    rn: RegUnit,
+    imm: i64,
+    rd: RegUnit,
+    sink: &mut CS,
+) {
+    debug_assert!(imm >= 0 && imm < 1 << 12, "add/sub immediate out of range");
+    let op = u32::from(bits) & 0x7ff;
+    sink.put4((op << 21) | ((imm as u32 & 0xfff) << 10) | (reg(rn) << 5) | reg(rd));
+}
+
+/// Number of instructions emitted by `put_add_large_imm`.
+pub fn add_large_imm_size(imm: i64) -> u8 {
+    if imm & 0xfff == 0 || imm < 1 << 12 {
+        4
+    } else {
+        8
+    }
+}
+
+/// Add/subtract with an unsigned immediate of up to 24 bits. The immediate is split in two
+/// instructions, with the first one using a left shift of 12 bits when needed.
+///
+/// Encoding bits: same as `put_addsub_imm`.
+fn put_add_large_imm<CS: CodeSink + ?Sized>(
+    bits: u16,
+    rn: RegUnit,
+    imm: i64,
+    rd: RegUnit,
+    sink: &mut CS,
+) {
+    assert!(imm >= 0 && imm < 1 << 24, "immediate {} out of range", imm);
+    let lo = imm & 0xfff;
+    let hi = imm >> 12;
+    if hi == 0 {
+        put_addsub_imm(bits, rn, lo, rd, sink);
+    } else {
+        // Set the `sh` bit to shift the immediate left by 12.
+        let op = (u32::from(bits) & 0x7ff) | 0b10;
+        sink.put4((op << 21) | ((hi as u32) << 10) | (reg(rn) << 5) | reg(rd));
+        if lo != 0 {
+            put_addsub_imm(bits, rd, lo, rd, sink);
+        }
+    }
+}
+
+/// The 16-bit chunks of a `movz`/`movn` + `movk` sequence materializing `value`.
+///
+/// Returns whether the sequence starts with `movn`, and the chunks that need to be set as a
+/// bitmask of half-word indices.
+fn movimm_chunks(sf: bool, value: u64) -> (bool, u32) {
+    let count = if sf { 4 } else { 2 };
+    let mut zeros = 0;
+    let mut ones = 0;
+    for i in 0..count {
+        match (value >> (16 * i)) & 0xffff {
+            0 => zeros |= 1 << i,
+            0xffff => ones |= 1 << i,
+            _ => {}
+        }
+    }
+    let all = (1 << count) - 1;
+    // Use `movn` when more chunks are all ones than all zeros.
+    let invert = u32::count_ones(ones) > u32::count_ones(zeros);
+    let mut set = all & !(if invert { ones } else { zeros });
+    if set == 0 {
+        // All the chunks are the same, still need one instruction.
+        set = 1;
+    }
+    (invert, set)
+}
+
+/// Number of bytes emitted by `put_movimm`.
+pub fn movimm_size(sf: bool, value: i64) -> u8 {
+    let (_, set) = movimm_chunks(sf, value as u64);
+    4 * set.count_ones() as u8
+}
+
+/// Materialize an integer constant with a `movz` or `movn`, followed by `movk` instructions
+/// for the remaining 16-bit chunks.
+///
+///   31  28     22 20    4
+///   sf opc 100101 hw imm16 Rd
+///   31  29     23 21     5  0
+///
+/// `sf` selects a 64-bit register.
+fn put_movimm<CS: CodeSink + ?Sized>(sf: bool, rd: RegUnit, value: i64, sink: &mut CS) {
+    let value = if sf {
+        value as u64
+    } else {
+        value as u64 & 0xffff_ffff
+    };
+    let (invert, set) = movimm_chunks(sf, value);
+    let sf_bit = if sf { 1 << 31 } else { 0 };
+    let mut first = true;
+    for hw in 0..4 {
+        if set & (1 << hw) == 0 {
+            continue;
+        }
+        let chunk = ((value >> (16 * hw)) & 0xffff) as u32;
+        let (opc, imm16) = if !first {
+            (0b11, chunk) // movk
+        } else if invert {
+            (0b00, !chunk & 0xffff) // movn
+        } else {
+            (0b10, chunk) // movz
+        };
+        first = false;
+        sink.put4(sf_bit | (opc << 29) | (0b100101 << 23) | (hw << 21) | (imm16 << 5) | reg(rd));
+    }
+}
+
+/// Logical shift right by an immediate: `lsr xd, xn, #shift`, an alias of `ubfm`.
+fn put_lsr_imm<CS: CodeSink + ?Sized>(shift: u32, rn: RegUnit, rd: RegUnit, sink: &mut CS) {
+    debug_assert!(shift < 64);
+    sink.put4(0xd340_fc00 | (shift << 16) | (reg(rn) << 5) | reg(rd));
+}
+
+/// Convert a boolean to an integer: `and wd, wn, #1`.
+fn put_and_one<CS: CodeSink + ?Sized>(rn: RegUnit, rd: RegUnit, sink: &mut CS) {
+    sink.put4(0x1200_0000 | (reg(rn) << 5) | reg(rd));
+}
+
+/// Count the bits set in the low 64 bits of v31, and move the result to `rd`.
+fn put_popcnt_v31<CS: CodeSink + ?Sized>(rd: RegUnit, sink: &mut CS) {
+    let v31 = reg(RU::v31 as RegUnit);
+    // cnt v31.8b, v31.8b
+    sink.put4(0x0e20_5800 | (v31 << 5) | v31);
+    // addv b31, v31.8b
+    sink.put4(0x0e31_b800 | (v31 << 5) | v31);
+    // umov wd, v31.b[0]
+    sink.put4(0x0e01_3c00 | (v31 << 5) | reg(rd));
+}
+
+/// Set `rd` to 1 if `cond` holds, 0 otherwise: `cset wd, cond`, an alias of `csinc` with the
+/// inverted condition.
+fn put_cset<CS: CodeSink + ?Sized>(cond: u32, rd: RegUnit, sink: &mut CS) {
+    sink.put4(0x1a9f_07e0 | ((cond ^ 1) << 12) | reg(rd));
+}
+
+/// Materialize the `one` and `ueq` float conditions, which need two A64 conditions.
+fn put_cset_double_fcc<CS: CodeSink + ?Sized>(cond: FloatCC, rd: RegUnit, sink: &mut CS) {
+    let (first, second) = match cond {
+        // Less than or greater than.
+        FloatCC::OrderedNotEqual => (0b0100, 0b1100), // mi, gt
+        // Equal or unordered.
+        FloatCC::UnorderedOrEqual => (0b0000, 0b0110), // eq, vs
+        _ => panic!("{} is a single A64 condition", cond),
+    };
+    put_cset(first, rd, sink);
+    // csinc wd, wd, wzr, !second: keep the first result unless the second condition holds.
+    sink.put4(0x1a9f_0400 | ((second ^ 1) << 12) | (reg(rd) << 5) | reg(rd));
+}
+
+/// Conditional select.
+///
+///   31       20 15   11 9  4
+///   op[10:0] Rm cond 00 Rn Rd
+///
+/// Encoding bits: same as `put_rrr`.
+fn put_csel<CS: CodeSink + ?Sized>(
+    bits: u16,
+    rn: RegUnit,
+    rm: RegUnit,
+    cond: u32,
+    rd: RegUnit,
+    sink: &mut CS,
+) {
+    let op = u32::from(bits) & 0x7ff;
+    sink.put4((op << 21) | (reg(rm) << 16) | (cond << 12) | (reg(rn) << 5) | reg(rd));
+}
+
+/// Unconditional branch with a 26-bit word offset: `b` and `bl`.
+///
+/// Encoding bits: same as `put_rrr`.
+fn put_b<CS: CodeSink + ?Sized>(bits: u16, disp: i32, sink: &mut CS) {
+    debug_assert_eq!(disp & 3, 0);
+    let op = u32::from(bits) & 0x7ff;
+    sink.put4((op << 21) | ((disp >> 2) as u32 & 0x03ff_ffff));
+}
+
+/// Conditional branch with a 19-bit word offset: `b.cond`.
+fn put_bcond<CS: CodeSink + ?Sized>(cond: u32, disp: i32, sink: &mut CS) {
+    debug_assert_eq!(disp & 3, 0);
+    sink.put4(0x5400_0000 | (((disp >> 2) as u32 & 0x7ffff) << 5) | cond);
+}
+
+/// Compare a register with zero and branch: `cbz` and `cbnz`.
+///
+/// Encoding bits: same as `put_rrr`.
+fn put_cbz<CS: CodeSink + ?Sized>(bits: u16, rt: RegUnit, disp: i32, sink: &mut CS) {
+    debug_assert_eq!(disp & 3, 0);
+    let op = u32::from(bits) & 0x7ff;
+    sink.put4((op << 21) | (((disp >> 2) as u32 & 0x7ffff) << 5) | reg(rt));
+}
+
+/// PC-relative address: `adr`.
+fn put_adr<CS: CodeSink + ?Sized>(rd: RegUnit, disp: i32, sink: &mut CS) {
+    assert!(disp >= -(1 << 20) && disp < 1 << 20, "adr out of range");
+    let disp = disp as u32;
+    sink.put4(0x1000_0000 | ((disp & 3) << 29) | (((disp >> 2) & 0x7ffff) << 5) | reg(rd));
+}
+
+/// Load a 64-bit literal placed right after the instruction, and branch over it. The caller
+/// emits the literal.
+fn put_ldr_literal_skip<CS: CodeSink + ?Sized>(rt: RegUnit, sink: &mut CS) {
+    // ldr xt, #8
+    sink.put4(0x5800_0000 | ((8 >> 2) << 5) | reg(rt));
+    // b #12
+    sink.put4(0x1400_0000 | (12 >> 2));
+}
+
+/// Trap with `IntegerDivisionByZero` when `rm` is zero: `cbnz rm, #8; udf`.
+///
+/// The `sf` bit of the division in `bits` selects the width of the comparison.
+fn put_div_by_zero_check<CS: CodeSink + ?Sized>(
+    bits: u16,
+    rm: RegUnit,
+    srcloc: SourceLoc,
+    sink: &mut CS,
+) {
+    let sf = (u32::from(bits) & 0x400) << 21;
+    sink.put4(sf | 0x3500_0000 | ((8 >> 2) << 5) | reg(rm));
+    sink.trap(TrapCode::IntegerDivisionByZero, srcloc);
+    sink.put4(UDF);
+}
+
+/// Trap with `IntegerOverflow` when dividing the minimum integer by -1.
+fn put_sdiv_overflow_check<CS: CodeSink + ?Sized>(
+    bits: u16,
+    rn: RegUnit,
+    rm: RegUnit,
+    srcloc: SourceLoc,
+    sink: &mut CS,
+) {
+    let sf = (u32::from(bits) & 0x400) << 21;
+    // cmn rm, #1
+    sink.put4(sf | 0x3100_041f | (reg(rm) << 5));
+    // ccmp rn, #1, #0, eq: when rm is -1, overflows only when rn is the minimum integer.
+    sink.put4(sf | 0x7a41_0800 | (reg(rn) << 5));
+    put_bcond(COND_VC, 8, sink);
+    sink.trap(TrapCode::IntegerOverflow, srcloc);
+    sink.put4(UDF);
+}
+
+/// Scalar floating point instructions, including the conversions to and from general purpose
+/// registers.
+///
+///   31 30   24    20 15     9  4
+///   sf 0011 op[14:0]        Rn Rd
+///   31 30   24              5  0
+///
+/// The register fields Rm at bits 20:16 and Ra at bits 14:10 are or'ed into `op`, so the
+/// instructions which use these bits as opcode fields must pass 0.
+///
+/// Encoding bits: `op[14:0] | (sf << 15)`.
+fn put_fp<CS: CodeSink + ?Sized>(
+    bits: u16,
+    rn: RegUnit,
+    rm: RegUnit,
+    ra: RegUnit,
+    rd: RegUnit,
+    sink: &mut CS,
+) {
+    let bits = u32::from(bits);
+    let sf = bits >> 15;
+    let op = bits & 0x7fff;
+    sink.put4(
+        (sf << 31)
+            | 0x1e00_0000
+            | (op << 10)
+            | (reg(rm) << 16)
+            | (reg(ra) << 10)
+            | (reg(rn) << 5)
+            | reg(rd),
+    );
+}
+
+/// Compare `rn` with itself to detect NaN, with the floating point type taken from the
+/// conversion instruction in `bits`.
+fn put_fcmp_self<CS: CodeSink + ?Sized>(bits: u16, rn: RegUnit, sink: &mut CS) {
+    let ty = (u32::from(bits) >> 12) & 0b11;
+    sink.put4(0x1e20_2000 | (ty << 22) | (reg(rn) << 16) | (reg(rn) << 5));
+}
+
+/// Clear the cumulative floating point exception flags: `msr fpsr, xzr`.
+fn put_clear_fpsr<CS: CodeSink + ?Sized>(sink: &mut CS) {
+    sink.put4(0xd51b_443f);
+}
+
+/// Trap with `IntegerOverflow` when the last conversion raised an invalid operation exception.
+fn put_ioc_check<CS: CodeSink + ?Sized>(srcloc: SourceLoc, sink: &mut CS) {
+    // mrs x16, fpsr
+    sink.put4(0xd53b_4420 | SCRATCH);
+    // tbz w16, #0, #8: IOC is bit 0.
+    sink.put4(0x3600_0000 | ((8 >> 2) << 5) | SCRATCH);
+    sink.trap(TrapCode::IntegerOverflow, srcloc);
+    sink.put4(UDF);
+}
+
+// Loads and stores.
+//
+// The encoding bits for loads and stores of a single register are `(size << 3) | (V << 2) | opc`.
+// These fields are common to all the addressing modes:
+//
+//   31   29  26 25 23  21
+//   size 111 V  mo opc ...
+
+fn ldst_fields(bits: u16) -> (u32, u32) {
+    let bits = u32::from(bits);
+    let size = bits >> 3;
+    let v = (bits >> 2) & 1;
+    let opc = bits & 3;
+    ((size << 30) | (0b111 << 27) | (v << 26) | (opc << 22), size)
+}
+
+/// Number of bytes emitted by `put_ldst`.
+pub fn ldst_size(bits: u16, offset: i64) -> u8 {
+    let (_, scale) = ldst_fields(bits);
+    if fits_scaled_imm12(offset, scale) || fits_simm9(offset) {
+        4
+    } else {
+        movimm_size(true, offset) + 4
+    }
+}
+
+fn fits_scaled_imm12(offset: i64, scale: u32) -> bool {
+    offset >= 0 && offset & ((1 << scale) - 1) == 0 && offset >> scale < 1 << 12
+}
+
+fn fits_simm9(offset: i64) -> bool {
+    offset >= -256 && offset < 256
+}
+
+/// Load or store `rt` at address `rn + offset`, using the unsigned scaled 12-bit immediate form
+/// when possible, then the unscaled signed 9-bit form. Other offsets are materialized in x16.
+fn put_ldst<CS: CodeSink + ?Sized>(
+    bits: u16,
+    rt: RegUnit,
+    rn: RegUnit,
+    offset: i64,
+    sink: &mut CS,
+) {
+    let (base, scale) = ldst_fields(bits);
+    let regs = (reg(rn) << 5) | reg(rt);
+    if fits_scaled_imm12(offset, scale) {
+        let imm12 = (offset >> scale) as u32;
+        sink.put4(base | (1 << 24) | (imm12 << 10) | regs);
+    } else if fits_simm9(offset) {
+        // ldur/stur.
+        sink.put4(base | ((offset as u32 & 0x1ff) << 12) | regs);
+    } else {
+        put_movimm(true, RU::x16 as RegUnit, offset, sink);
+        put_ldst_reg(bits, rt, rn, RU::x16 as RegUnit, false, sink);
+    }
+}
+
+/// Load or store `rt` at address `rn + rm`, optionally scaling `rm` by the access size.
+fn put_ldst_reg<CS: CodeSink + ?Sized>(
+    bits: u16,
+    rt: RegUnit,
+    rn: RegUnit,
+    rm: RegUnit,
+    scaled: bool,
+    sink: &mut CS,
+) {
+    let (base, _) = ldst_fields(bits);
+    // Option 011 (lsl) at bits 15:13, then S at bit 12.
+    let s = if scaled { 1 } else { 0 };
+    sink.put4(
+        base | (1 << 21)
+            | (reg(rm) << 16)
+            | (0b011 << 13)
+            | (s << 12)
+            | (0b10 << 10)
+            | (reg(rn) << 5)
+            | reg(rt),
+    );
+}
+
+/// Store `rt` at `sp + offset`, writing the address back to `sp` first.
+fn put_ldst_pre<CS: CodeSink + ?Sized>(bits: u16, rt: RegUnit, offset: i32, sink: &mut CS) {
+    let (base, _) = ldst_fields(bits);
+    sink.put4(base | ((offset as u32 & 0x1ff) << 12) | (0b11 << 10) | (31 << 5) | reg(rt));
+}
+
+/// Load `rt` from `sp`, then add `offset` to `sp`.
+fn put_ldst_post<CS: CodeSink + ?Sized>(bits: u16, rt: RegUnit, offset: i32, sink: &mut CS) {
+    let (base, _) = ldst_fields(bits);
+    sink.put4(base | ((offset as u32 & 0x1ff) << 12) | (0b01 << 10) | (31 << 5) | reg(rt));
+}
+
+// Loads and stores of register pairs, addressed relative to the stack pointer.
+//
+//   31  29  26 25   22 21   14  9  4
+//   opc 101 V  mode L  imm7 Rt2 Rn Rt
+//
+// The encoding bits are `(opc << 3) | (V << 2) | L`. The registers are 8 bytes in all the pairs
+// used here, which scales `imm7`.
+
+fn ldst_pair<CS: CodeSink + ?Sized>(
+    bits: u16,
+    mode: u32,
+    rt: RegUnit,
+    rt2: RegUnit,
+    offset: i32,
+    sink: &mut CS,
+) {
+    let bits = u32::from(bits);
+    let opc = bits >> 3;
+    let v = (bits >> 2) & 1;
+    let l = bits & 1;
+    let imm7 = (offset >> 3) as u32 & 0x7f;
+    sink.put4(
+        (opc << 30)
+            | (0b101 << 27)
+            | (v << 26)
+            | (mode << 23)
+            | (l << 22)
+            | (imm7 << 15)
+            | (reg(rt2) << 10)
+            | (31 << 5)
+            | reg(rt),
+    );
+}
+
+/// `stp rt, rt2, [sp, #offset]!`
+fn put_ldst_pair_pre<CS: CodeSink + ?Sized>(
+    bits: u16,
+    rt: RegUnit,
+    rt2: RegUnit,
+    offset: i32,
+    sink: &mut CS,
+) {
+    ldst_pair(bits, 0b011, rt, rt2, offset, sink);
+}
+
+/// `ldp rt, rt2, [sp], #offset`
+fn put_ldst_pair_post<CS: CodeSink + ?Sized>(
+    bits: u16,
+    rt: RegUnit,
+    rt2: RegUnit,
+    offset: i32,
+    sink: &mut CS,
+) {
+    ldst_pair(bits, 0b001, rt, rt2, offset, sink);
+}
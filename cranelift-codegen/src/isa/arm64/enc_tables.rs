@@ -1,10 +1,150 @@
 //! Encoding tables for ARM64 ISA.
 
-use crate::ir;
+use super::binemit::{add_large_imm_size, ldst_size, movimm_size};
+use super::registers::*;
+use crate::ir::{self, Function, Inst, InstructionData};
 use crate::isa;
 use crate::isa::constraints::*;
 use crate::isa::enc_tables::*;
-use crate::isa::encoding::RecipeSizing;
+use crate::isa::encoding::{base_size, Encoding, RecipeSizing};
+use crate::isa::StackRef;
+use crate::predicates;
+use crate::regalloc::RegDiversions;
 
+// Include the generated encoding tables:
+// - `LEVEL1_A64`
+// - `LEVEL2`
+// - `ENCLIST`
+// - `INFO`
 include!(concat!(env!("OUT_DIR"), "/encoding-arm64.rs"));
 include!(concat!(env!("OUT_DIR"), "/legalize-arm64.rs"));
+
+/// Size of an integer constant, which needs one instruction per non-trivial 16-bit chunk.
+fn size_for_movimm(
+    _sizing: &RecipeSizing,
+    enc: Encoding,
+    inst: Inst,
+    _divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    match func.dfg[inst] {
+        InstructionData::UnaryImm { imm, .. } => movimm_size(enc.bits() != 0, imm.into()),
+        _ => panic!("Expected UnaryImm: {}", func.dfg.display_inst(inst, None)),
+    }
+}
+
+/// Size of the address of a stack slot, which takes a second instruction for large offsets.
+fn size_for_spaddr(
+    _sizing: &RecipeSizing,
+    _enc: Encoding,
+    inst: Inst,
+    _divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    match func.dfg[inst] {
+        InstructionData::StackLoad {
+            stack_slot, offset, ..
+        } => {
+            let sp = StackRef::sp(stack_slot, &func.stack_slots);
+            let offset: i32 = offset.into();
+            add_large_imm_size(i64::from(sp.offset + offset))
+        }
+        _ => panic!("Expected StackLoad: {}", func.dfg.display_inst(inst, None)),
+    }
+}
+
+/// Size of a stack pointer adjustment.
+fn size_for_adjustsp(
+    _sizing: &RecipeSizing,
+    _enc: Encoding,
+    inst: Inst,
+    _divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    match func.dfg[inst] {
+        InstructionData::UnaryImm { imm, .. } => add_large_imm_size(imm.into()),
+        _ => panic!("Expected UnaryImm: {}", func.dfg.display_inst(inst, None)),
+    }
+}
+
+/// Size of a load, which needs to materialize offsets that don't fit in the immediate forms.
+fn size_for_load(
+    _sizing: &RecipeSizing,
+    enc: Encoding,
+    inst: Inst,
+    _divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    match func.dfg[inst] {
+        InstructionData::Load { offset, .. } => ldst_size(enc.bits(), offset.into()),
+        _ => panic!("Expected Load: {}", func.dfg.display_inst(inst, None)),
+    }
+}
+
+/// Size of a store, which needs to materialize offsets that don't fit in the immediate forms.
+fn size_for_store(
+    _sizing: &RecipeSizing,
+    enc: Encoding,
+    inst: Inst,
+    _divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    match func.dfg[inst] {
+        InstructionData::Store { offset, .. } => ldst_size(enc.bits(), offset.into()),
+        _ => panic!("Expected Store: {}", func.dfg.display_inst(inst, None)),
+    }
+}
+
+/// Size of an access to the stack slot `ss`.
+fn size_for_stack_access(enc: Encoding, ss: ir::StackSlot, func: &Function) -> u8 {
+    let sp = StackRef::sp(ss, &func.stack_slots);
+    ldst_size(enc.bits(), sp.offset.into())
+}
+
+fn size_for_spill(
+    _sizing: &RecipeSizing,
+    enc: Encoding,
+    inst: Inst,
+    divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    let result = func.dfg.first_result(inst);
+    size_for_stack_access(enc, divert.stack(result, &func.locations), func)
+}
+
+fn size_for_fill(
+    _sizing: &RecipeSizing,
+    enc: Encoding,
+    inst: Inst,
+    divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    let arg = func.dfg.inst_args(inst)[0];
+    size_for_stack_access(enc, divert.stack(arg, &func.locations), func)
+}
+
+fn size_for_regspill(
+    _sizing: &RecipeSizing,
+    enc: Encoding,
+    inst: Inst,
+    _divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    match func.dfg[inst] {
+        InstructionData::RegSpill { dst, .. } => size_for_stack_access(enc, dst, func),
+        _ => panic!("Expected RegSpill: {}", func.dfg.display_inst(inst, None)),
+    }
+}
+
+fn size_for_regfill(
+    _sizing: &RecipeSizing,
+    enc: Encoding,
+    inst: Inst,
+    _divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    match func.dfg[inst] {
+        InstructionData::RegFill { src, .. } => size_for_stack_access(enc, src, func),
+        _ => panic!("Expected RegFill: {}", func.dfg.display_inst(inst, None)),
+    }
+}
@@ -15,6 +15,8 @@ use crate::isa::enc_tables::{lookup_enclist, Encodings};
 use crate::isa::Builder as IsaBuilder;
 use crate::isa::{EncInfo, RegClass, RegInfo, TargetIsa};
 use crate::regalloc;
+use crate::result::CodegenResult;
+use crate::timing;
 use alloc::borrow::Cow;
 use alloc::boxed::Box;
 use core::fmt;
@@ -61,6 +63,10 @@ impl TargetIsa for Isa {
         &self.shared_flags
     }
 
+    fn uses_cpu_flags(&self) -> bool {
+        true
+    }
+
     fn register_info(&self) -> RegInfo {
         registers::INFO.clone()
     }
@@ -101,6 +107,11 @@ impl TargetIsa for Isa {
         abi::allocatable_registers(func)
     }
 
+    fn prologue_epilogue(&self, func: &mut ir::Function) -> CodegenResult<()> {
+        let _tt = timing::prologue_epilogue();
+        abi::prologue_epilogue(func, self)
+    }
+
     #[cfg(feature = "testing_hooks")]
     fn emit_inst(
         &self,
@@ -117,11 +128,13 @@ impl TargetIsa for Isa {
     }
 
     fn unsigned_add_overflow_condition(&self) -> ir::condcodes::IntCC {
-        ir::condcodes::IntCC::UnsignedLessThan
+        // Carry set.
+        ir::condcodes::IntCC::UnsignedGreaterThanOrEqual
     }
 
     fn unsigned_sub_overflow_condition(&self) -> ir::condcodes::IntCC {
-        ir::condcodes::IntCC::UnsignedGreaterThanOrEqual
+        // Carry clear, meaning a borrow.
+        ir::condcodes::IntCC::UnsignedLessThan
     }
 }
 
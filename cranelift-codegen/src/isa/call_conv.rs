@@ -21,6 +21,9 @@ pub enum CallConv {
     BaldrdashWindows,
     /// Specialized convention for the probestack function
     Probestack,
+    /// System V-style convention where the callee pops its stack arguments,
+    /// which allows guaranteed tail calls between functions using it
+    Tail,
 }
 
 impl CallConv {
@@ -57,6 +60,14 @@ impl CallConv {
         }
     }
 
+    /// Does the callee pop its own stack arguments before returning?
+    pub fn callee_pops_stack_args(self) -> bool {
+        match self {
+            Self::Tail => true,
+            _ => false,
+        }
+    }
+
     /// Is the calling convention extending the Baldrdash ABI?
     pub fn extends_baldrdash(self) -> bool {
         match self {
@@ -76,6 +87,7 @@ impl fmt::Display for CallConv {
             Self::BaldrdashSystemV => "baldrdash_system_v",
             Self::BaldrdashWindows => "baldrdash_windows",
             Self::Probestack => "probestack",
+            Self::Tail => "tail",
         })
     }
 }
@@ -91,6 +103,7 @@ impl str::FromStr for CallConv {
             "baldrdash_system_v" => Ok(Self::BaldrdashSystemV),
             "baldrdash_windows" => Ok(Self::BaldrdashWindows),
             "probestack" => Ok(Self::Probestack),
+            "tail" => Ok(Self::Tail),
             _ => Err(()),
         }
     }
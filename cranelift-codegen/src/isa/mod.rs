@@ -109,7 +109,8 @@ pub fn lookup(triple: Triple) -> Result<Builder, LookupError> {
         Architecture::I386 | Architecture::I586 | Architecture::I686 | Architecture::X86_64 => {
             isa_builder!(x86, "x86", triple)
         }
-        Architecture::Arm { .. } => isa_builder!(arm32, "arm32", triple),
+        // Only the Thumb2 instruction set has encodings, there are none for A32 yet.
+        Architecture::Arm(arm) if arm.is_thumb() => isa_builder!(arm32, "arm32", triple),
         Architecture::Aarch64 { .. } => isa_builder!(arm64, "arm64", triple),
         _ => Err(LookupError::Unsupported),
     }
@@ -7,7 +7,7 @@
 
 use super::registers::{FPR, GPR};
 use super::settings;
-use crate::abi::{legalize_args, ArgAction, ArgAssigner, ValueConversion};
+use crate::abi::{legalize_args, legalize_returns, ArgAction, ArgAssigner, ValueConversion};
 use crate::ir::{self, AbiParam, ArgumentExtension, ArgumentLoc, ArgumentPurpose, Type};
 use crate::isa::RegClass;
 use crate::regalloc::RegisterSet;
@@ -15,6 +15,7 @@ use alloc::borrow::Cow;
 use core::i32;
 use target_lexicon::Triple;
 
+#[derive(Clone)]
 struct Args {
     pointer_bits: u8,
     pointer_bytes: u8,
@@ -97,15 +98,16 @@ pub fn legalize_signature(
     let bits = triple.pointer_width().unwrap().bits();
 
     let mut args = Args::new(bits, isa_flags.enable_e());
+    let mut rets = Args::new(bits, isa_flags.enable_e());
+
+    // The struct return pointer is passed in the first argument register, and not returned.
+    let pointer_type = args.pointer_type;
+    legalize_returns(sig, pointer_type, &mut args, &mut rets, false);
+
     if let Some(new_params) = legalize_args(&sig.params, &mut args) {
         sig.to_mut().params = new_params;
     }
 
-    let mut rets = Args::new(bits, isa_flags.enable_e());
-    if let Some(new_returns) = legalize_args(&sig.returns, &mut rets) {
-        sig.to_mut().returns = new_returns;
-    }
-
     if current {
         let ptr = Type::int(u16::from(bits)).unwrap();
 
@@ -1,7 +1,7 @@
 //! Emitting binary RISC-V machine code.
 
 use crate::binemit::{bad_encoding, CodeSink, Reloc};
-use crate::ir::{Function, Inst, InstructionData};
+use crate::ir::{Function, Inst, InstructionData, TrapCode};
 use crate::isa::{RegUnit, StackBaseMask, StackRef, TargetIsa};
 use crate::predicates::is_signed_int;
 use crate::regalloc::RegDiversions;
@@ -119,6 +119,33 @@ fn put_u<CS: CodeSink + ?Sized>(bits: u16, imm: i64, rd: RegUnit, sink: &mut CS)
     sink.put4(i);
 }
 
+/// S-type store instructions.
+///
+///   31  24  19  14     11  6
+///   imm rs2 rs1 funct3 imm opcode
+///    25  20  15     12   7      0
+///
+/// Encoding bits: `opcode[6:2] | (funct3 << 5)`
+fn put_s<CS: CodeSink + ?Sized>(bits: u16, imm: i64, rs1: RegUnit, rs2: RegUnit, sink: &mut CS) {
+    let bits = u32::from(bits);
+    let opcode5 = bits & 0x1f;
+    let funct3 = (bits >> 5) & 0x7;
+    let rs1 = u32::from(rs1) & 0x1f;
+    let rs2 = u32::from(rs2) & 0x1f;
+    let imm = imm as u32;
+
+    // 0-6: opcode
+    let mut i = 0x3;
+    i |= opcode5 << 2;
+    i |= (imm & 0x1f) << 7;
+    i |= funct3 << 12;
+    i |= rs1 << 15;
+    i |= rs2 << 20;
+    i |= ((imm >> 5) & 0x7f) << 25;
+
+    sink.put4(i);
+}
+
 /// SB-type branch instructions.
 ///
 ///   31  24  19  14     11  6
@@ -7,7 +7,7 @@ use super::registers::{FPR, GPR, RU};
 use super::settings as isa_settings;
 #[cfg(feature = "unwind")]
 use super::unwind::UnwindInfo;
-use crate::abi::{legalize_args, ArgAction, ArgAssigner, ValueConversion};
+use crate::abi::{legalize_args, legalize_returns, ArgAction, ArgAssigner, ValueConversion};
 #[cfg(feature = "unwind")]
 use crate::binemit::{FrameUnwindKind, FrameUnwindSink};
 use crate::cursor::{Cursor, CursorPosition, EncCursor};
@@ -20,7 +20,7 @@ use crate::ir::{
 };
 use crate::isa::{CallConv, RegClass, RegUnit, TargetIsa};
 use crate::regalloc::RegisterSet;
-use crate::result::CodegenResult;
+use crate::result::{CodegenError, CodegenResult};
 use crate::stack_layout::layout_stack;
 use alloc::borrow::Cow;
 use core::i32;
@@ -62,6 +62,14 @@ const WIN_SHADOW_STACK_SPACE: i32 = 32;
 /// higher up in the stack.
 const STACK_ALIGNMENT: u32 = 16;
 
+/// Frames at least this large check the stack pointer against the stack limit before adding the
+/// frame size to the limit, which could overflow.
+const STACK_CHECK_OVERFLOW_THRESHOLD: i64 = 32 * 1024;
+
+/// The largest number of guard regions that inline stack probes are unrolled for. Larger frames
+/// are probed with a loop.
+const PROBESTACK_MAX_UNROLL: i64 = 4;
+
 #[derive(Clone)]
 struct Args {
     pointer_bytes: u8,
@@ -251,80 +259,9 @@ pub fn legalize_signature(
         isa_flags,
     );
 
-    let sig_is_multi_return = sig.is_multi_return();
-
-    // If this is a multi-value return and we don't have enough available return
-    // registers to fit all of the return values, we need to backtrack and start
-    // assigning locations all over again with a different strategy. In order to
-    // do that, we need a copy of the original assigner for the returns.
-    let backup_rets_for_struct_return = if sig_is_multi_return {
-        Some(rets.clone())
-    } else {
-        None
-    };
-
-    if let Some(new_returns) = legalize_args(&sig.returns, &mut rets) {
-        if sig.is_multi_return()
-            && new_returns
-                .iter()
-                .filter(|r| r.purpose == ArgumentPurpose::Normal)
-                .any(|r| !r.location.is_reg())
-        {
-            // The return values couldn't all fit into available return
-            // registers. Introduce the use of a struct-return parameter.
-            debug_assert!(!sig.uses_struct_return_param());
-
-            // We're using the first register for the return pointer parameter.
-            let mut ret_ptr_param = AbiParam {
-                value_type: args.pointer_type,
-                purpose: ArgumentPurpose::StructReturn,
-                extension: ArgumentExtension::None,
-                location: ArgumentLoc::Unassigned,
-            };
-            match args.assign(&ret_ptr_param) {
-                ArgAction::Assign(ArgumentLoc::Reg(reg)) => {
-                    ret_ptr_param.location = ArgumentLoc::Reg(reg);
-                    sig.to_mut().params.push(ret_ptr_param);
-                }
-                _ => unreachable!("return pointer should always get a register assignment"),
-            }
-
-            let mut backup_rets = backup_rets_for_struct_return.unwrap();
-
-            // We're using the first return register for the return pointer (like
-            // sys v does).
-            let mut ret_ptr_return = AbiParam {
-                value_type: args.pointer_type,
-                purpose: ArgumentPurpose::StructReturn,
-                extension: ArgumentExtension::None,
-                location: ArgumentLoc::Unassigned,
-            };
-            match backup_rets.assign(&ret_ptr_return) {
-                ArgAction::Assign(ArgumentLoc::Reg(reg)) => {
-                    ret_ptr_return.location = ArgumentLoc::Reg(reg);
-                    sig.to_mut().returns.push(ret_ptr_return);
-                }
-                _ => unreachable!("return pointer should always get a register assignment"),
-            }
-
-            sig.to_mut().returns.retain(|ret| {
-                // Either this is the return pointer, in which case we want to keep
-                // it, or else assume that it is assigned for a reason and doesn't
-                // conflict with our return pointering legalization.
-                debug_assert_eq!(
-                    ret.location.is_assigned(),
-                    ret.purpose != ArgumentPurpose::Normal
-                );
-                ret.location.is_assigned()
-            });
-
-            if let Some(new_returns) = legalize_args(&sig.returns, &mut backup_rets) {
-                sig.to_mut().returns = new_returns;
-            }
-        } else {
-            sig.to_mut().returns = new_returns;
-        }
-    }
+    // Both the System V and the Windows x64 ABIs return the struct return pointer in `%rax`.
+    let pointer_type = args.pointer_type;
+    legalize_returns(sig, pointer_type, &mut args, &mut rets, true);
 
     if let Some(new_params) = legalize_args(&sig.params, &mut args) {
         sig.to_mut().params = new_params;
@@ -438,6 +375,8 @@ fn callee_saved_gprs_used(isa: &dyn TargetIsa, func: &ir::Function) -> RegisterS
 }
 
 pub fn prologue_epilogue(func: &mut ir::Function, isa: &dyn TargetIsa) -> CodegenResult<()> {
+    insert_callee_pop_adjustments(func, isa);
+
     match func.signature.call_conv {
         // For now, just translate fast and cold as system_v.
         CallConv::Fast | CallConv::Cold | CallConv::SystemV => {
@@ -448,6 +387,57 @@ pub fn prologue_epilogue(func: &mut ir::Function, isa: &dyn TargetIsa) -> Codege
             baldrdash_prologue_epilogue(func, isa)
         }
         CallConv::Probestack => unimplemented!("probestack calling convention"),
+        CallConv::Tail => {
+            if isa.triple().pointer_width().unwrap() != PointerWidth::U64 {
+                return Err(CodegenError::Unsupported(
+                    "the tail calling convention on x86-32".into(),
+                ));
+            }
+            // The tail convention only differs from System V in who pops the stack arguments,
+            // which is handled when emitting `return`.
+            system_v_prologue_epilogue(func, isa)
+        }
+    }
+}
+
+/// Get the number of bytes of stack arguments that a function with signature `sig` pops when it
+/// returns.
+///
+/// This is zero unless the calling convention makes the callee pop its stack arguments. Stack
+/// arguments occupy full 8-byte slots, since the conventions that do this are x86-64 only.
+pub fn callee_pop_size(sig: &ir::Signature) -> u16 {
+    if !sig.call_conv.callee_pops_stack_args() {
+        return 0;
+    }
+    let size = (sig.stack_arg_size() + 7) & !7;
+    debug_assert!(size <= u32::from(u16::max_value()));
+    size as u16
+}
+
+/// Re-adjust the stack pointer after calls to functions that pop their own stack arguments.
+///
+/// The outgoing arguments area is part of the caller's fixed frame, so the stack pointer must be
+/// restored once the callee has popped it.
+fn insert_callee_pop_adjustments(func: &mut ir::Function, isa: &dyn TargetIsa) {
+    let mut pos = EncCursor::new(func, isa);
+    while let Some(_ebb) = pos.next_ebb() {
+        while let Some(inst) = pos.next_inst() {
+            let opcode = pos.func.dfg[inst].opcode();
+            if opcode.is_terminator() {
+                continue;
+            }
+            let pop_size = match pos.func.dfg.call_signature(inst) {
+                Some(sig_ref) => callee_pop_size(&pos.func.dfg.signatures[sig_ref]),
+                None => continue,
+            };
+            if pop_size > 0 {
+                pos.goto_after_inst(inst);
+                let adjust = pos
+                    .ins()
+                    .adjust_sp_down_imm(Imm64::new(i64::from(pop_size)));
+                pos.goto_inst(adjust);
+            }
+        }
     }
 }
 
@@ -641,18 +631,22 @@ fn insert_common_prologue(
     isa: &dyn TargetIsa,
 ) -> Option<CFAState> {
     let word_size = isa.pointer_bytes() as isize;
-    if stack_size > 0 {
-        // Check if there is a special stack limit parameter. If so insert stack check.
-        if let Some(stack_limit_arg) = pos.func.special_param(ArgumentPurpose::StackLimit) {
-            // Total stack size is the size of all stack area used by the function, including
-            // pushed CSRs, frame pointer.
-            // Also, the size of a return address, implicitly pushed by a x86 `call` instruction,
-            // also should be accounted for.
-            // TODO: Check if the function body actually contains a `call` instruction.
-            let total_stack_size = (csrs.iter(GPR).len() + 1 + 1) as i64 * word_size as i64;
-
-            insert_stack_check(pos, total_stack_size, stack_limit_arg);
-        }
+
+    // Check if there is a special stack limit parameter, or failing that a stack limit global
+    // value. If so insert stack check.
+    let stack_limit = match pos.func.special_param(ArgumentPurpose::StackLimit) {
+        Some(stack_limit_arg) => Some(stack_limit_arg),
+        None => pos.func.stack_limit.map(|gv| interpret_gv(pos, gv)),
+    };
+    if let Some(stack_limit) = stack_limit {
+        // Total stack size is the size of all stack area used by the function, including
+        // pushed CSRs, frame pointer and the local frame.
+        // Also, the size of a return address, implicitly pushed by a x86 `call` instruction,
+        // also should be accounted for.
+        let total_stack_size =
+            (csrs.iter(GPR).len() + 1 + 1) as i64 * word_size as i64 + stack_size;
+
+        insert_stack_check(pos, total_stack_size, stack_limit);
     }
 
     let mut cfa_state = if let Some(ref mut frame_layout) = pos.func.frame_layout {
@@ -754,9 +748,13 @@ fn insert_common_prologue(
 
     // Allocate stack frame storage.
     if stack_size > 0 {
-        if isa.flags().probestack_enabled()
-            && stack_size > (1 << isa.flags().probestack_size_log2())
+        let needs_probe = isa.flags().probestack_enabled()
+            && stack_size > (1 << isa.flags().probestack_size_log2());
+        if needs_probe
+            && isa.flags().probestack_strategy() == shared_settings::ProbestackStrategy::Inline
         {
+            insert_inline_stack_probes(pos, stack_size, isa);
+        } else if needs_probe {
             // Emit a stack probe.
             let rax = RU::rax as RegUnit;
             let rax_val = ir::ValueLoc::Reg(rax);
@@ -800,15 +798,112 @@ fn insert_common_prologue(
     cfa_state
 }
 
+/// Allocate `stack_size` bytes of stack frame storage, touching every guard region on the way
+/// down so that a stack overflow always hits the guard page instead of skipping past it.
+///
+/// Frames of up to `PROBESTACK_MAX_UNROLL` guard regions are probed with an unrolled sequence,
+/// larger ones with a loop.
+fn insert_inline_stack_probes(pos: &mut EncCursor, stack_size: i64, isa: &dyn TargetIsa) {
+    let probe_size = 1 << isa.flags().probestack_size_log2();
+    let probe_count = stack_size / probe_size;
+
+    let mut last_inst = if probe_count <= PROBESTACK_MAX_UNROLL {
+        let mut last_inst = None;
+        for _ in 0..probe_count {
+            pos.ins().adjust_sp_down_imm(Imm64::new(probe_size));
+            last_inst = Some(pos.ins().x86_stack_probe());
+        }
+        last_inst
+    } else {
+        Some(
+            pos.ins()
+                .x86_stack_probe_loop(Imm64::new(probe_count * probe_size)),
+        )
+    };
+
+    // The rest is smaller than a guard region, so it doesn't need to be probed.
+    let remainder = stack_size % probe_size;
+    if remainder > 0 {
+        last_inst = Some(pos.ins().adjust_sp_down_imm(Imm64::new(remainder)));
+    }
+
+    pos.func.prologue_end = last_inst;
+}
+
+/// Materialize the stack limit global value `gv` at the start of the prologue.
+///
+/// Only the global values that can be computed without any registers other than %rax are
+/// supported: the vmctx, passed in a register, and loads and additions based on it.
+fn interpret_gv(pos: &mut EncCursor, gv: ir::GlobalValue) -> ir::Value {
+    let rax_val = ir::ValueLoc::Reg(RU::rax as RegUnit);
+    match pos.func.global_values[gv] {
+        ir::GlobalValueData::VMContext => {
+            let vmctx = pos
+                .func
+                .special_param(ArgumentPurpose::VMContext)
+                .expect("no vmctx parameter found");
+            match pos.func.locations[vmctx] {
+                ir::ValueLoc::Reg(_) => vmctx,
+                _ => panic!("stack limit requires the vmctx to be passed in a register"),
+            }
+        }
+        ir::GlobalValueData::Load {
+            base,
+            offset,
+            global_type,
+            ..
+        } => {
+            let base = interpret_gv(pos, base);
+            let value = pos
+                .ins()
+                .load(global_type, ir::MemFlags::trusted(), base, offset);
+            pos.func.locations[value] = rax_val;
+            value
+        }
+        ir::GlobalValueData::IAddImm { base, offset, .. } => {
+            let base = interpret_gv(pos, base);
+            let base = copy_to_rax(pos, base);
+            let value = pos.ins().iadd_imm(base, offset);
+            pos.func.locations[value] = rax_val;
+            value
+        }
+        ref other => panic!("global value for stack limit not supported: {}", other),
+    }
+}
+
+/// Copy `value` to %rax, unless it is already there.
+fn copy_to_rax(pos: &mut EncCursor, value: ir::Value) -> ir::Value {
+    let rax_val = ir::ValueLoc::Reg(RU::rax as RegUnit);
+    if pos.func.locations[value] == rax_val {
+        return value;
+    }
+    let copy = pos.ins().copy(value);
+    pos.func.locations[copy] = rax_val;
+    copy
+}
+
 /// Insert a check that generates a trap if the stack pointer goes
 /// below a value in `stack_limit_arg`.
 fn insert_stack_check(pos: &mut EncCursor, stack_size: i64, stack_limit_arg: ir::Value) {
     use crate::ir::condcodes::IntCC;
 
+    // Calculating the SP threshold below might overflow when the stack limit is set to a value
+    // close to the top of the address space to force a trap, which embedders commonly do. For
+    // large frames, first check that the stack pointer isn't already below the limit, so that
+    // they only need to keep the limit a small distance away from overflowing.
+    if stack_size >= STACK_CHECK_OVERFLOW_THRESHOLD {
+        let cflags = pos.ins().ifcmp_sp(stack_limit_arg);
+        pos.func.locations[cflags] = ir::ValueLoc::Reg(RU::rflags as RegUnit);
+        pos.ins().trapif(
+            IntCC::UnsignedGreaterThanOrEqual,
+            cflags,
+            ir::TrapCode::StackOverflow,
+        );
+    }
+
     // Copy `stack_limit_arg` into a %rax and use it for calculating
     // a SP threshold.
-    let stack_limit_copy = pos.ins().copy(stack_limit_arg);
-    pos.func.locations[stack_limit_copy] = ir::ValueLoc::Reg(RU::rax as RegUnit);
+    let stack_limit_copy = copy_to_rax(pos, stack_limit_arg);
     let sp_threshold = pos.ins().iadd_imm(stack_limit_copy, stack_size);
     pos.func.locations[sp_threshold] = ir::ValueLoc::Reg(RU::rax as RegUnit);
 
@@ -823,7 +918,7 @@ fn insert_stack_check(pos: &mut EncCursor, stack_size: i64, stack_limit_arg: ir:
     );
 }
 
-/// Find all `return` instructions and insert epilogues before them.
+/// Find all `return` and tail call instructions and insert epilogues before them.
 fn insert_common_epilogues(
     pos: &mut EncCursor,
     stack_size: i64,
@@ -835,7 +930,8 @@ fn insert_common_epilogues(
     while let Some(ebb) = pos.next_ebb() {
         pos.goto_last_inst(ebb);
         if let Some(inst) = pos.current_inst() {
-            if pos.func.dfg[inst].opcode().is_return() {
+            let opcode = pos.func.dfg[inst].opcode();
+            if opcode.is_return() || opcode.is_call() {
                 if let (Some(ref mut frame_layout), ref func_layout) =
                     (pos.func.frame_layout.as_mut(), &pos.func.layout)
                 {
@@ -869,7 +965,7 @@ fn insert_common_epilogues(
     }
 }
 
-/// Insert an epilogue given a specific `return` instruction.
+/// Insert an epilogue given a specific `return` or tail call instruction.
 /// This is used by common calling conventions such as System V.
 fn insert_common_epilogue(
     inst: ir::Inst,
@@ -881,6 +977,9 @@ fn insert_common_epilogue(
     mut cfa_state: Option<CFAState>,
 ) {
     let word_size = isa.pointer_bytes() as isize;
+    // The callee of a tail call restores the callee-saved registers itself, so they are only
+    // returned by a `return`.
+    let is_return = pos.func.dfg[inst].opcode().is_return();
     if stack_size > 0 {
         pos.ins().adjust_sp_up_imm(Imm64::new(stack_size));
     }
@@ -903,7 +1002,9 @@ fn insert_common_epilogue(
     pos.prev_inst();
 
     pos.func.locations[fp_ret] = ir::ValueLoc::Reg(RU::rbp as RegUnit);
-    pos.func.dfg.append_inst_arg(inst, fp_ret);
+    if is_return {
+        pos.func.dfg.append_inst_arg(inst, fp_ret);
+    }
 
     for reg in csrs.iter(GPR) {
         let csr_ret = pos.ins().x86_pop(reg_type);
@@ -917,7 +1018,9 @@ fn insert_common_epilogue(
         pos.prev_inst();
 
         pos.func.locations[csr_ret] = ir::ValueLoc::Reg(reg);
-        pos.func.dfg.append_inst_arg(inst, csr_ret);
+        if is_return {
+            pos.func.dfg.append_inst_arg(inst, csr_ret);
+        }
     }
 
     if let Some(ref mut frame_layout) = pos.func.frame_layout {
@@ -973,3 +1076,38 @@ pub fn emit_unwind_info(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::cursor::{Cursor, FuncCursor};
+    use crate::ir::{ExternalName, Function, InstBuilder, Signature};
+    use crate::isa::{lookup, CallConv};
+    use crate::result::CodegenError;
+    use crate::settings::{builder, Flags};
+    use crate::Context;
+    use std::str::FromStr;
+    use target_lexicon::triple;
+
+    #[test]
+    fn tail_call_conv_on_x86_32() {
+        let isa = lookup(triple!("i686"))
+            .expect("expect x86 ISA")
+            .finish(Flags::new(builder()));
+
+        let mut func =
+            Function::with_name_signature(ExternalName::user(0, 0), Signature::new(CallConv::Tail));
+        let ebb0 = func.dfg.make_ebb();
+        let mut pos = FuncCursor::new(&mut func);
+        pos.insert_ebb(ebb0);
+        pos.ins().return_(&[]);
+
+        let mut context = Context::for_function(func);
+        match context.compile(&*isa) {
+            Err(CodegenError::Unsupported(feature)) => {
+                assert_eq!(feature, "the tail calling convention on x86-32")
+            }
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok(_) => panic!("expected the tail calling convention to be rejected"),
+        }
+    }
+}
@@ -1,5 +1,6 @@
 //! Encoding tables for x86 ISAs.
 
+use super::abi::callee_pop_size;
 use super::registers::*;
 use crate::bitset::BitSet;
 use crate::cursor::{Cursor, FuncCursor};
@@ -123,6 +124,19 @@ fn size_plus_maybe_sib_or_offset_for_inreg_1(
     sizing.base_size + if needs_sib_or_offset { 1 } else { 0 }
 }
 
+/// A `return` pops the stack arguments with a 3-byte `ret imm16` when the calling convention
+/// requires it.
+fn size_plus_callee_pop_imm(
+    sizing: &RecipeSizing,
+    _enc: Encoding,
+    _inst: Inst,
+    _divert: &RegDiversions,
+    func: &Function,
+) -> u8 {
+    let has_imm16 = callee_pop_size(&func.signature) > 0;
+    sizing.base_size + if has_imm16 { 2 } else { 0 }
+}
+
 /// Infers whether a dynamic REX prefix will be emitted, for use with one input reg.
 ///
 /// A REX prefix is known to be emitted if either:
@@ -1236,3 +1250,43 @@ fn convert_ineg(
         }
     }
 }
+
+/// Expand a `tls_value` instruction into the access sequence of the configured TLS model.
+fn expand_tls_value(
+    inst: ir::Inst,
+    func: &mut ir::Function,
+    _cfg: &mut ControlFlowGraph,
+    isa: &dyn TargetIsa,
+) {
+    use crate::settings::TlsModel;
+
+    assert!(
+        isa.pointer_bits() == 64,
+        "Not yet implemented for {:?}",
+        isa.triple(),
+    );
+
+    if let ir::InstructionData::UnaryGlobalValue {
+        opcode: ir::Opcode::TlsValue,
+        global_value,
+    } = func.dfg[inst]
+    {
+        let ctrl_typevar = func.dfg.ctrl_typevar(inst);
+        assert_eq!(ctrl_typevar, ir::types::I64);
+
+        match isa.flags().tls_model() {
+            TlsModel::None => panic!("tls_model flag is not set."),
+            TlsModel::ElfGd => {
+                func.dfg.replace(inst).x86_elf_tls_get_addr(global_value);
+            }
+            TlsModel::ElfIe => {
+                func.dfg.replace(inst).x86_elf_tls_ie_addr(global_value);
+            }
+            TlsModel::Macho => {
+                func.dfg.replace(inst).x86_macho_tls_get_addr(global_value);
+            }
+        }
+    } else {
+        unreachable!();
+    }
+}
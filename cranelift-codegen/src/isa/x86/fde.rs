@@ -6,19 +6,30 @@ use crate::isa::{CallConv, RegUnit, TargetIsa};
 use alloc::vec::Vec;
 use core::convert::TryInto;
 use gimli::write::{
-    Address, CallFrameInstruction, CommonInformationEntry, EhFrame, EndianVec,
+    Address, CallFrameInstruction, CommonInformationEntry, EhFrame, EndianVec, Error,
     FrameDescriptionEntry, FrameTable, Result, Writer,
 };
-use gimli::{Encoding, Format, LittleEndian, Register, X86_64};
+use gimli::{constants, DwEhPe, Encoding, Format, LittleEndian, Register, X86_64};
 use std::ptr;
 
-pub type FDERelocEntry = (FrameUnwindOffset, Reloc);
+/// Relocation at an offset, against one of the symbols of the addresses below.
+pub type FDERelocEntry = (FrameUnwindOffset, Reloc, usize);
 
 const FUNCTION_ENTRY_ADDRESS: Address = Address::Symbol {
     symbol: 0,
     addend: 0,
 };
 
+const PERSONALITY_ADDRESS: Address = Address::Symbol {
+    symbol: 1,
+    addend: 0,
+};
+
+const LSDA_ADDRESS: Address = Address::Symbol {
+    symbol: 2,
+    addend: 0,
+};
+
 #[derive(Clone)]
 struct FDEWriter {
     vec: EndianVec<LittleEndian>,
@@ -54,8 +65,8 @@ impl Writer for FDEWriter {
     fn write_address(&mut self, address: Address, size: u8) -> Result<()> {
         match address {
             Address::Constant(_) => self.vec.write_address(address, size),
-            Address::Symbol { .. } => {
-                assert_eq!(address, FUNCTION_ENTRY_ADDRESS);
+            Address::Symbol { symbol, addend } => {
+                assert_eq!(addend, 0);
                 let rt = match size {
                     4 => Reloc::Abs4,
                     8 => Reloc::Abs8,
@@ -63,11 +74,39 @@ impl Writer for FDEWriter {
                         panic!("Unexpected address size at FDEWriter::write_address");
                     }
                 };
-                self.relocs.push((self.vec.len().try_into().unwrap(), rt));
+                self.relocs
+                    .push((self.vec.len().try_into().unwrap(), rt, symbol));
                 self.vec.write_udata(0, size)
             }
         }
     }
+    fn write_eh_pointer(&mut self, address: Address, eh_pe: DwEhPe, size: u8) -> Result<()> {
+        match address {
+            Address::Constant(_) => self.vec.write_eh_pointer(address, eh_pe, size),
+            Address::Symbol { symbol, addend } => {
+                // PC-relative pointers are relative to the pointer itself, so the relocation
+                // needs no addend.
+                if eh_pe.application() == constants::DW_EH_PE_pcrel
+                    && eh_pe.format() == constants::DW_EH_PE_sdata4
+                {
+                    assert_eq!(addend, 0);
+                    self.relocs.push((self.vec.len(), Reloc::X86PCRel4, symbol));
+                    return self.vec.write_udata(0, 4);
+                }
+                // Otherwise, only absolute pointers are relocated.
+                if eh_pe.application() != constants::DW_EH_PE_absptr {
+                    return Err(Error::UnsupportedPointerEncoding(eh_pe));
+                }
+                let size = match eh_pe.format() {
+                    constants::DW_EH_PE_absptr => size,
+                    constants::DW_EH_PE_udata4 => 4,
+                    constants::DW_EH_PE_udata8 => 8,
+                    _ => return Err(Error::UnsupportedPointerEncoding(eh_pe)),
+                };
+                self.write_address(address, size)
+            }
+        }
+    }
 }
 
 fn return_address_reg(isa: &dyn TargetIsa) -> Register {
@@ -190,10 +229,17 @@ pub fn emit_fde(func: &Function, isa: &dyn TargetIsa, sink: &mut dyn FrameUnwind
     let encinfo = isa.encoding_info();
     let mut last_offset = 0;
     let mut changes = Vec::new();
+    let mut call_sites = Vec::new();
+    let mut has_landing_pads = false;
     for ebb in ebbs {
         for (offset, inst, size) in func.inst_offsets(ebb, &encinfo) {
             let address_offset = (offset + size) as usize;
             assert!(last_offset <= address_offset);
+            if func.dfg[inst].opcode().is_call() {
+                let landing_pad = func.dfg[inst].branch_destination();
+                has_landing_pads |= landing_pad.is_some();
+                call_sites.push((offset, size, landing_pad.map_or(0, |ebb| func.offsets[ebb])));
+            }
             if let Some(cmds) = frame_layout.instructions.get(&inst) {
                 for cmd in cmds.iter() {
                     changes.push((address_offset, cmd.clone()));
@@ -228,10 +274,26 @@ pub fn emit_fde(func: &Function, isa: &dyn TargetIsa, sink: &mut dyn FrameUnwind
             cie.add_instruction(cfi);
         }
     }
+    if sink.pc_relative() {
+        cie.fde_address_encoding =
+            DwEhPe(constants::DW_EH_PE_pcrel.0 | constants::DW_EH_PE_sdata4.0);
+    }
+    if has_landing_pads {
+        // Unwinders only look for the LSDA pointer in FDEs with a 'zPLR' augmentation, even
+        // though the FDE addresses are encoded the same way as without it.
+        cie.personality = Some((constants::DW_EH_PE_udata8, PERSONALITY_ADDRESS));
+        cie.lsda_encoding = Some(constants::DW_EH_PE_udata8);
+        if cie.fde_address_encoding == constants::DW_EH_PE_absptr {
+            cie.fde_address_encoding = constants::DW_EH_PE_udata8;
+        }
+    }
 
     let cie_id = frames.add_cie(cie);
 
     let mut fde = FrameDescriptionEntry::new(FUNCTION_ENTRY_ADDRESS, len);
+    if has_landing_pads {
+        fde.lsda = Some(LSDA_ADDRESS);
+    }
 
     for (addr, ch) in changes.iter() {
         if let Some(cfi) = to_cfi(isa, ch, &mut cfa_def_reg, &mut cfa_def_offset) {
@@ -249,14 +311,51 @@ pub fn emit_fde(func: &Function, isa: &dyn TargetIsa, sink: &mut dyn FrameUnwind
     let unwind_start = sink.len();
     sink.bytes(&bytes);
 
-    for (off, r) in relocs {
-        sink.reloc(r, off + unwind_start);
+    for (off, r, symbol) in relocs {
+        let off = off + unwind_start;
+        match symbol {
+            0 => sink.reloc(r, off),
+            1 => sink.reloc_personality(r, off),
+            _ => sink.reloc_lsda(r, off),
+        }
     }
     let fde_offset = unsafe { ptr::read::<u32>(bytes.as_ptr() as *const u32) } as usize + 4;
     sink.set_entry_offset(unwind_start + fde_offset);
 
     // Need 0 marker for GCC unwind to end FDE "list".
     sink.bytes(&[0, 0, 0, 0]);
+
+    if has_landing_pads {
+        sink.lsda(&lsda_bytes(&call_sites));
+    }
+}
+
+/// Creates a GCC-style LSDA with a call-site entry for every call in the function.
+///
+/// Calls without a landing pad are given an entry too, since the personality routine
+/// terminates the process when it can't find the call site. All actions are cleanups; the
+/// landing pad decides whether to resume unwinding, so the personality routine doesn't need
+/// to know about the language throwing the exception.
+fn lsda_bytes(call_sites: &[(u32, u32, u32)]) -> Vec<u8> {
+    const DW_EH_PE_OMIT: u8 = 0xff;
+    const DW_EH_PE_ULEB128: u8 = 0x01;
+
+    let mut table = EndianVec::new(LittleEndian);
+    for &(start, len, landing_pad) in call_sites {
+        table.write_uleb128(start.into()).unwrap();
+        table.write_uleb128(len.into()).unwrap();
+        table.write_uleb128(landing_pad.into()).unwrap();
+        table.write_uleb128(0).unwrap(); // No action record.
+    }
+    let table = table.into_vec();
+
+    let mut lsda = EndianVec::new(LittleEndian);
+    lsda.write_u8(DW_EH_PE_OMIT).unwrap(); // Landing pads are relative to the function start.
+    lsda.write_u8(DW_EH_PE_OMIT).unwrap(); // No type table.
+    lsda.write_u8(DW_EH_PE_ULEB128).unwrap();
+    lsda.write_uleb128(table.len() as u64).unwrap();
+    lsda.write(&table).unwrap();
+    lsda.into_vec()
 }
 
 #[cfg(test)]
@@ -264,14 +363,24 @@ mod tests {
     use super::*;
     use crate::binemit::{FrameUnwindOffset, Reloc};
     use crate::cursor::{Cursor, FuncCursor};
-    use crate::ir::{ExternalName, InstBuilder, Signature, StackSlotData, StackSlotKind};
+    use crate::ir::{
+        ExtFuncData, ExternalName, InstBuilder, Signature, StackSlotData, StackSlotKind,
+    };
     use crate::isa::{lookup, CallConv};
     use crate::settings::{builder, Flags};
     use crate::Context;
     use std::str::FromStr;
     use target_lexicon::triple;
 
-    struct SimpleUnwindSink(pub Vec<u8>, pub usize, pub Vec<(Reloc, usize)>);
+    #[derive(Default)]
+    struct SimpleUnwindSink(
+        pub Vec<u8>,
+        pub usize,
+        pub Vec<(Reloc, usize)>,
+        pub Vec<u8>,
+        pub Vec<(Reloc, usize)>,
+        pub Vec<(Reloc, usize)>,
+    );
     impl FrameUnwindSink for SimpleUnwindSink {
         fn len(&self) -> FrameUnwindOffset {
             self.0.len()
@@ -285,6 +394,15 @@ mod tests {
         fn set_entry_offset(&mut self, off: FrameUnwindOffset) {
             self.1 = off;
         }
+        fn lsda(&mut self, b: &[u8]) {
+            self.3.extend_from_slice(b);
+        }
+        fn reloc_personality(&mut self, r: Reloc, off: FrameUnwindOffset) {
+            self.4.push((r, off));
+        }
+        fn reloc_lsda(&mut self, r: Reloc, off: FrameUnwindOffset) {
+            self.5.push((r, off));
+        }
     }
 
     #[test]
@@ -301,7 +419,7 @@ mod tests {
 
         context.compile(&*isa).expect("expected compilation");
 
-        let mut sink = SimpleUnwindSink(Vec::new(), 0, Vec::new());
+        let mut sink = SimpleUnwindSink::default();
         emit_fde(&context.func, &*isa, &mut sink);
 
         assert_eq!(
@@ -334,6 +452,76 @@ mod tests {
         );
         assert_eq!(sink.1, 24);
         assert_eq!(sink.2.len(), 1);
+        assert!(sink.3.is_empty());
+        assert!(sink.4.is_empty());
+        assert!(sink.5.is_empty());
+    }
+
+    #[test]
+    fn test_invoke_lsda() {
+        let isa = lookup(triple!("x86_64"))
+            .expect("expect x86 ISA")
+            .finish(Flags::new(builder()));
+
+        let mut context = Context::for_function(create_invoke_function());
+        context.func.collect_frame_layout_info();
+
+        context.compile(&*isa).expect("expected compilation");
+
+        let mut sink = SimpleUnwindSink::default();
+        emit_fde(&context.func, &*isa, &mut sink);
+
+        assert_eq!(
+            sink.0,
+            vec![
+                36, 0, 0, 0, // CIE len
+                0, 0, 0, 0, // CIE marker
+                1, // version
+                b'z', b'L', b'P', b'R', 0,   // augmentation string
+                1,   // code aligment = 1
+                120, // data alignment = -8
+                16,  // RA = r16
+                11,  // augmentation length
+                4,   // LSDA encoding udata8
+                4,   // personality encoding udata8
+                0, 0, 0, 0, 0, 0, 0, 0, // personality reloc
+                4, // FDE address encoding udata8
+                0x0c, 0x07, 0x08, // DW_CFA_def_cfa r7, 8
+                0x90, 0x01, //  DW_CFA_offset r16, -8 * 1
+                0, 0, 0, 0, 0, 0, // padding
+                44, 0, 0, 0, // FDE len
+                44, 0, 0, 0, // CIE offset
+                0, 0, 0, 0, 0, 0, 0, 0, // addr reloc
+                16, 0, 0, 0, 0, 0, 0, 0, // function length
+                8, // augmentation length
+                0, 0, 0, 0, 0, 0, 0, 0,    // LSDA reloc
+                0x42, // DW_CFA_advance_loc 2
+                0x0e, 0x10, // DW_CFA_def_cfa_offset 16
+                0x86, 0x02, // DW_CFA_offset r6, -8 * 2
+                0x43, // DW_CFA_advance_loc 3
+                0x0d, 0x06, // DW_CFA_def_cfa_register
+                0x47, // DW_CFA_advance_loc 7
+                0x0c, 0x07, 0x08, // DW_CFA_def_cfa r7, 8
+                0, 0, 0, // padding
+                0, 0, 0, 0, // End of FDEs
+            ]
+        );
+        assert_eq!(sink.1, 40);
+        assert_eq!(sink.2, vec![(Reloc::Abs8, 48)]);
+        assert_eq!(sink.4, vec![(Reloc::Abs8, 20)]);
+        assert_eq!(sink.5, vec![(Reloc::Abs8, 65)]);
+        assert_eq!(
+            sink.3,
+            vec![
+                0xff, // LPStart omitted
+                0xff, // TType omitted
+                0x01, // call-site encoding uleb128
+                4,    // call-site table length
+                5, 5,  // invoke offset and length
+                13, // landing pad
+                0,  // action
+            ]
+        );
     }
 
     fn create_function(call_conv: CallConv, stack_slot: Option<StackSlotData>) -> Function {
@@ -351,4 +539,31 @@ mod tests {
 
         func
     }
+
+    fn create_invoke_function() -> Function {
+        let mut func = Function::with_name_signature(
+            ExternalName::user(0, 0),
+            Signature::new(CallConv::SystemV),
+        );
+        let sig = func.import_signature(Signature::new(CallConv::SystemV));
+        let callee = func.import_function(ExtFuncData {
+            name: ExternalName::user(0, 1),
+            signature: sig,
+            colocated: true,
+        });
+
+        let ebb0 = func.dfg.make_ebb();
+        let ebb1 = func.dfg.make_ebb();
+        let ebb2 = func.dfg.make_ebb();
+        let mut pos = FuncCursor::new(&mut func);
+        pos.insert_ebb(ebb0);
+        pos.ins().invoke(callee, ebb2, &[]);
+        pos.ins().jump(ebb1, &[]);
+        pos.insert_ebb(ebb1);
+        pos.ins().return_(&[]);
+        pos.insert_ebb(ebb2);
+        pos.ins().return_(&[]);
+
+        func
+    }
 }
@@ -189,7 +189,7 @@ impl UnwindInfo {
                             // instruction (which will adjust via the register assigned to this instruction).
                             stack_size = Some(imm as u32);
                         }
-                        Opcode::AdjustSpDownImm => {
+                        Opcode::AdjustSpDownImm | Opcode::X86StackProbeLoop => {
                             let imm: i64 = imm.into();
                             assert!(imm <= core::u32::MAX as i64);
 
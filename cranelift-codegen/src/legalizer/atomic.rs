@@ -0,0 +1,128 @@
+//! Legalization of atomic memory operations.
+//!
+//! This module exports the `expand_atomic_rmw` and `expand_atomic_store` functions, which
+//! express the atomic operations that an ISA doesn't support directly in terms of `atomic_cas`.
+
+use crate::cursor::{Cursor, FuncCursor};
+use crate::flowgraph::ControlFlowGraph;
+use crate::ir::condcodes::IntCC;
+use crate::ir::{self, AtomicRmwOp, InstBuilder};
+use crate::isa::TargetIsa;
+
+/// Expand an `atomic_rmw` instruction into a compare-and-swap loop.
+///
+/// A subtraction is turned into the addition of the negated operand first, since ISAs are more
+/// likely to support the addition directly.
+pub fn expand_atomic_rmw(
+    inst: ir::Inst,
+    func: &mut ir::Function,
+    cfg: &mut ControlFlowGraph,
+    _isa: &dyn TargetIsa,
+) {
+    let (flags, op, ptr, x) = match func.dfg[inst] {
+        ir::InstructionData::AtomicRmw {
+            opcode,
+            flags,
+            op,
+            args,
+        } => {
+            debug_assert_eq!(opcode, ir::Opcode::AtomicRmw);
+            (flags, op, args[0], args[1])
+        }
+        _ => panic!("Wanted atomic_rmw: {}", func.dfg.display_inst(inst, None)),
+    };
+    let ty = func.dfg.ctrl_typevar(inst);
+
+    if op == AtomicRmwOp::Sub {
+        let mut pos = FuncCursor::new(func).at_inst(inst);
+        pos.use_srcloc(inst);
+        let zero = pos.ins().iconst(ty, 0);
+        let neg = pos.ins().isub(zero, x);
+        pos.func
+            .dfg
+            .replace(inst)
+            .atomic_rmw(flags, AtomicRmwOp::Add, ptr, neg);
+        return;
+    }
+
+    // Replace `result = atomic_rmw op p, x` with:
+    //
+    //     first = load p
+    //     jump loop_ebb(first)
+    //   loop_ebb(old):
+    //     new = op old, x
+    //     prev = atomic_cas p, old, new
+    //     ok = icmp eq prev, old
+    //     brz ok, loop_ebb(prev)
+    //     jump done_ebb(old)
+    //   done_ebb(result):
+    //
+    // The initial load doesn't need to be atomic, since the compare-and-swap fails and returns
+    // the current value when it is stale.
+    let old_ebb = func.layout.pp_ebb(inst);
+    let loop_ebb = func.dfg.make_ebb();
+    let done_ebb = func.dfg.make_ebb();
+    let result = func.dfg.first_result(inst);
+    func.dfg.clear_results(inst);
+    func.dfg.attach_ebb_param(done_ebb, result);
+    let old = func.dfg.append_ebb_param(loop_ebb, ty);
+
+    let mut pos = FuncCursor::new(func).at_inst(inst);
+    pos.use_srcloc(inst);
+    let first = pos.ins().load(ty, flags, ptr, 0);
+    pos.func.dfg.replace(inst).jump(loop_ebb, &[first]);
+
+    pos.goto_after_inst(inst);
+    pos.insert_ebb(loop_ebb);
+    let new = match op {
+        AtomicRmwOp::Add => pos.ins().iadd(old, x),
+        AtomicRmwOp::And => pos.ins().band(old, x),
+        AtomicRmwOp::Or => pos.ins().bor(old, x),
+        AtomicRmwOp::Xor => pos.ins().bxor(old, x),
+        AtomicRmwOp::Xchg => x,
+        AtomicRmwOp::Sub => unreachable!(),
+    };
+    let prev = pos.ins().atomic_cas(flags, ptr, old, new);
+    let ok = pos.ins().icmp(IntCC::Equal, prev, old);
+    pos.ins().brz(ok, loop_ebb, &[prev]);
+    pos.ins().jump(done_ebb, &[old]);
+    pos.insert_ebb(done_ebb);
+
+    cfg.recompute_ebb(pos.func, old_ebb);
+    cfg.recompute_ebb(pos.func, loop_ebb);
+    cfg.recompute_ebb(pos.func, done_ebb);
+}
+
+/// Expand an `atomic_store` instruction into an `atomic_rmw` exchange whose result is unused.
+pub fn expand_atomic_store(
+    inst: ir::Inst,
+    func: &mut ir::Function,
+    _cfg: &mut ControlFlowGraph,
+    _isa: &dyn TargetIsa,
+) {
+    let (flags, x, ptr, offset) = match func.dfg[inst] {
+        ir::InstructionData::Store {
+            opcode,
+            flags,
+            args,
+            offset,
+        } => {
+            debug_assert_eq!(opcode, ir::Opcode::AtomicStore);
+            (flags, args[0], args[1], offset)
+        }
+        _ => panic!("Wanted atomic_store: {}", func.dfg.display_inst(inst, None)),
+    };
+
+    let mut pos = FuncCursor::new(func).at_inst(inst);
+    pos.use_srcloc(inst);
+    let offset: i64 = offset.into();
+    let addr = if offset == 0 {
+        ptr
+    } else {
+        pos.ins().iadd_imm(ptr, offset)
+    };
+    pos.func
+        .dfg
+        .replace(inst)
+        .atomic_rmw(flags, AtomicRmwOp::Xchg, addr, x);
+}
@@ -426,6 +426,25 @@ fn legalize_sret_call(isa: &dyn TargetIsa, pos: &mut FuncCursor, sig_ref: SigRef
     pos.func.dfg.old_signatures[sig_ref] = Some(old_sig);
 }
 
+/// Legalize a tail call to a function that returns its values through an `sret` pointer.
+///
+/// The callee writes its return values to memory owned by our caller, so we simply forward the
+/// `sret` pointer that the current function received.
+fn legalize_sret_tail_call(pos: &mut FuncCursor, sig_ref: SigRef, call: Inst) {
+    pos.func.dfg.old_signatures[sig_ref] = None;
+
+    let idx = pos
+        .func
+        .signature
+        .special_param_index(ArgumentPurpose::StructReturn)
+        .expect("tail call to an sret function requires an sret parameter in the caller");
+    let sret = pos
+        .func
+        .dfg
+        .ebb_params(pos.func.layout.entry_block().unwrap())[idx];
+    pos.func.dfg.append_inst_arg(call, sret);
+}
+
 /// Compute original value of type `ty` from the legalized ABI arguments.
 ///
 /// The conversion is recursive, controlled by the `get_arg` closure which is called to retrieve an
@@ -586,9 +605,11 @@ fn check_call_signature(dfg: &DataFlowGraph, inst: Inst) -> Result<(), SigRef> {
     };
     let sig = &dfg.signatures[sig_ref];
 
-    if check_arg_types(dfg, args, &sig.params[..])
-        && check_arg_types(dfg, dfg.inst_results(inst), &sig.returns[..])
-    {
+    // Tail calls have no results of their own; the callee returns directly to our caller.
+    let returns_ok = dfg[inst].opcode().is_terminator()
+        || check_arg_types(dfg, dfg.inst_results(inst), &sig.returns[..]);
+
+    if check_arg_types(dfg, args, &sig.params[..]) && returns_ok {
         // All types check out.
         Ok(())
     } else {
@@ -750,12 +771,26 @@ pub fn handle_call_abi(
     let sig = &pos.func.dfg.signatures[sig_ref];
     let old_sig = &pos.func.dfg.old_signatures[sig_ref];
 
+    let is_tail_call = pos.func.dfg[inst].opcode().is_terminator();
+
     if sig.uses_struct_return_param()
         && old_sig
             .as_ref()
             .map_or(false, |s| !s.uses_struct_return_param())
     {
-        legalize_sret_call(isa, pos, sig_ref, inst);
+        if is_tail_call {
+            legalize_sret_tail_call(pos, sig_ref, inst);
+        } else {
+            legalize_sret_call(isa, pos, sig_ref, inst);
+        }
+
+        // The remaining arguments may still need to be split or extended, but the results are
+        // only the special-purpose returns now.
+        pos.goto_inst(inst);
+        let abi_args = pos.func.dfg.signatures[sig_ref].params.len();
+        legalize_inst_arguments(pos, cfg, abi_args, |func, abi_arg| {
+            func.dfg.signatures[sig_ref].params[abi_arg]
+        });
     } else {
         // OK, we need to fix the call arguments to match the ABI signature.
         let abi_args = pos.func.dfg.signatures[sig_ref].params.len();
@@ -763,7 +798,7 @@ pub fn handle_call_abi(
             func.dfg.signatures[sig_ref].params[abi_arg]
         });
 
-        if !pos.func.dfg.signatures[sig_ref].returns.is_empty() {
+        if !is_tail_call && !pos.func.dfg.signatures[sig_ref].returns.is_empty() {
             inst = legalize_inst_results(pos, |func, abi_res| {
                 func.dfg.signatures[sig_ref].returns[abi_res]
             });
@@ -819,15 +854,14 @@ pub fn handle_return_abi(inst: Inst, func: &mut Function, cfg: &ControlFlowGraph
     // Append special return arguments for any `sret`, `link`, and `vmctx` return values added to
     // the legalized signature. These values should simply be propagated from the entry block
     // arguments.
+    let mut vlist = pos.func.dfg[inst].take_value_list().unwrap();
+    let mut sret = None;
     if special_args > 0 {
         debug!(
             "Adding {} special-purpose arguments to {}",
             special_args,
             pos.func.dfg.display_inst(inst, None)
         );
-        let mut vlist = pos.func.dfg[inst].take_value_list().unwrap();
-        let mut sret = None;
-
         for arg in &pos.func.signature.returns[abi_args..] {
             match arg.purpose {
                 ArgumentPurpose::Link
@@ -859,35 +893,47 @@ pub fn handle_return_abi(inst: Inst, func: &mut Function, cfg: &ControlFlowGraph
                 sret = Some(val);
             }
         }
+    }
 
-        // Store all the regular returns into the retptr space and remove them
-        // from the `return` instruction's value list.
-        if let Some(sret) = sret {
-            let mut offset = 0;
-            let num_regular_rets = vlist.len(&pos.func.dfg.value_lists) - special_args;
-            for i in 0..num_regular_rets {
-                debug_assert_eq!(
-                    pos.func.old_signature.as_ref().unwrap().returns[i].purpose,
-                    ArgumentPurpose::Normal,
-                );
+    // Some calling conventions pass the `sret` pointer without returning it. In that case, the
+    // regular returns that didn't fit in registers still need to be stored through the pointer we
+    // received as an entry block argument.
+    let num_regular_rets = vlist.len(&pos.func.dfg.value_lists) - special_args;
+    if sret.is_none() && num_regular_rets > abi_args {
+        let entry = pos.func.layout.entry_block().unwrap();
+        sret = pos
+            .func
+            .signature
+            .special_param_index(ArgumentPurpose::StructReturn)
+            .map(|idx| pos.func.dfg.ebb_params(entry)[idx]);
+    }
 
-                // The next return value to process is always at `0`, since the
-                // list is emptied as we iterate.
-                let v = vlist.get(0, &pos.func.dfg.value_lists).unwrap();
-                let ty = pos.func.dfg.value_type(v);
-                let (v, ty) = legalize_type_for_sret_store(pos, v, ty);
+    // Store all the regular returns into the retptr space and remove them
+    // from the `return` instruction's value list.
+    if let Some(sret) = sret {
+        let mut offset = 0;
+        for i in 0..num_regular_rets {
+            debug_assert_eq!(
+                pos.func.old_signature.as_ref().unwrap().returns[i].purpose,
+                ArgumentPurpose::Normal,
+            );
 
-                let size = ty.bytes();
-                offset = round_up_to_multiple_of_type_align(offset, ty);
+            // The next return value to process is always at `0`, since the
+            // list is emptied as we iterate.
+            let v = vlist.get(0, &pos.func.dfg.value_lists).unwrap();
+            let ty = pos.func.dfg.value_type(v);
+            let (v, ty) = legalize_type_for_sret_store(pos, v, ty);
 
-                pos.ins().store(MemFlags::trusted(), v, sret, offset as i32);
-                vlist.remove(0, &mut pos.func.dfg.value_lists);
+            let size = ty.bytes();
+            offset = round_up_to_multiple_of_type_align(offset, ty);
 
-                offset += size;
-            }
+            pos.ins().store(MemFlags::trusted(), v, sret, offset as i32);
+            vlist.remove(0, &mut pos.func.dfg.value_lists);
+
+            offset += size;
         }
-        pos.func.dfg[inst].put_value_list(vlist);
     }
+    pos.func.dfg[inst].put_value_list(vlist);
 
     debug_assert_eq!(
         pos.func.dfg.inst_variable_args(inst).len(),
@@ -972,6 +1018,9 @@ fn spill_entry_params(func: &mut Function, entry: Ebb) {
 /// Values that are passed to a called function on the stack must be assigned to a matching
 /// `OutgoingArg` stack slot. The assignment must happen immediately before the call.
 ///
+/// Tail calls reuse the current function's incoming argument area instead, so their stack
+/// arguments are assigned to `IncomingArg` stack slots.
+///
 /// TODO: The outgoing stack slots can be written a bit earlier, as long as there are no branches
 /// or calls between writing the stack slots and the call instruction. Writing the slots earlier
 /// could help reduce register pressure before the call.
@@ -984,6 +1033,7 @@ fn spill_call_arguments(pos: &mut FuncCursor) -> bool {
         .dfg
         .call_signature(inst)
         .expect("Call instruction expected.");
+    let is_tail_call = pos.func.dfg[inst].opcode().is_terminator();
 
     // Start by building a list of stack slots and arguments to be replaced.
     // This requires borrowing `pos.func.dfg`, so we can't change anything.
@@ -1002,7 +1052,11 @@ fn spill_call_arguments(pos: &mut FuncCursor) -> bool {
                         // Assign `arg` to a new stack slot, unless it's already in the correct
                         // slot. The legalization needs to be idempotent, so we should see a
                         // correct outgoing slot on the second pass.
-                        let ss = stack_slots.get_outgoing_arg(abi.value_type, offset);
+                        let ss = if is_tail_call {
+                            stack_slots.get_incoming_arg(abi.value_type, offset)
+                        } else {
+                            stack_slots.get_outgoing_arg(abi.value_type, offset)
+                        };
                         if locations[arg] != ValueLoc::Stack(ss) {
                             Some((idx, arg, ss))
                         } else {
@@ -1019,6 +1073,17 @@ fn spill_call_arguments(pos: &mut FuncCursor) -> bool {
         return false;
     }
 
+    // The incoming argument slots written by a tail call may still hold values that are passed
+    // as other arguments. Copy all of the stack arguments before writing any of the slots.
+    let arglist = if is_tail_call {
+        arglist
+            .into_iter()
+            .map(|(idx, arg, ss)| (idx, pos.ins().copy(arg), ss))
+            .collect()
+    } else {
+        arglist
+    };
+
     // Insert the spill instructions and rewrite call arguments.
     for (idx, arg, ss) in arglist {
         let stack_val = pos.ins().spill(arg);
@@ -1,15 +1,18 @@
 //! Legalization of calls.
 //!
 //! This module exports the `expand_call` function which transforms a `call`
-//! instruction into `func_addr` and `call_indirect` instructions.
+//! instruction into `func_addr` and `call_indirect` instructions, a
+//! `return_call` instruction into `func_addr` and `return_call_indirect`, and
+//! an `invoke` instruction into `func_addr` and `invoke_indirect`.
 
 use crate::cursor::{Cursor, FuncCursor};
 use crate::flowgraph::ControlFlowGraph;
 use crate::ir::{self, InstBuilder};
 use crate::isa::TargetIsa;
 
-/// Expand a `call` instruction. This lowers it to a `call_indirect`, which
-/// is only done if the ABI doesn't support direct calls.
+/// Expand a `call`, `return_call` or `invoke` instruction. This lowers it to a
+/// `call_indirect`, `return_call_indirect` or `invoke_indirect`, which is only
+/// done if the ABI doesn't support direct calls.
 pub fn expand_call(
     inst: ir::Inst,
     func: &mut ir::Function,
@@ -17,15 +20,21 @@ pub fn expand_call(
     isa: &dyn TargetIsa,
 ) {
     // Unpack the instruction.
-    let (func_ref, old_args) = match func.dfg[inst] {
+    let (opcode, func_ref, landing_pad, old_args) = match func.dfg[inst] {
         ir::InstructionData::Call {
             opcode,
             ref args,
             func_ref,
         } => {
-            debug_assert_eq!(opcode, ir::Opcode::Call);
-            (func_ref, args.clone())
+            debug_assert!(opcode == ir::Opcode::Call || opcode == ir::Opcode::ReturnCall);
+            (opcode, func_ref, None, args.clone())
         }
+        ir::InstructionData::Invoke {
+            opcode,
+            ref args,
+            func_ref,
+            destination,
+        } => (opcode, func_ref, Some(destination), args.clone()),
         _ => panic!("Wanted call: {}", func.dfg.display_inst(inst, None)),
     };
 
@@ -48,7 +57,22 @@ pub fn expand_call(
         );
     }
 
+    if let Some(landing_pad) = landing_pad {
+        func.dfg.replace(inst).InvokeIndirect(
+            ir::Opcode::InvokeIndirect,
+            ptr_ty,
+            sig,
+            landing_pad,
+            new_args,
+        );
+        return;
+    }
+
+    let new_opcode = match opcode {
+        ir::Opcode::ReturnCall => ir::Opcode::ReturnCallIndirect,
+        _ => ir::Opcode::CallIndirect,
+    };
     func.dfg
         .replace(inst)
-        .CallIndirect(ir::Opcode::CallIndirect, ptr_ty, sig, new_args);
+        .CallIndirect(new_opcode, ptr_ty, sig, new_args);
 }
@@ -102,14 +102,14 @@ fn static_addr(
     isa: &dyn TargetIsa,
     inst: ir::Inst,
     heap: ir::Heap,
-    offset: ir::Value,
+    mut offset: ir::Value,
     access_size: u32,
     bound: u64,
     func: &mut ir::Function,
     cfg: &mut ControlFlowGraph,
 ) {
     let access_size = u64::from(access_size);
-    let offset_ty = func.dfg.value_type(offset);
+    let mut offset_ty = func.dfg.value_type(offset);
     let addr_ty = func.dfg.value_type(func.dfg.first_result(inst));
     let mut pos = FuncCursor::new(func).at_inst(inst);
     pos.use_srcloc(inst);
@@ -129,6 +129,15 @@ fn static_addr(
         return;
     }
 
+    // An index that is wider than the address type, like an i64 index on a 32-bit target, is out
+    // of bounds unless its high half is zero. Continue with the low half.
+    if offset_ty.bits() > addr_ty.bits() {
+        let (lo, hi) = pos.ins().isplit(offset);
+        pos.ins().trapnz(hi, ir::TrapCode::HeapOutOfBounds);
+        offset = lo;
+        offset_ty = addr_ty;
+    }
+
     // Check `offset > limit` which is now known non-negative.
     let limit = bound - access_size;
 
@@ -24,6 +24,7 @@ use crate::timing;
 use alloc::collections::BTreeSet;
 use alloc::vec::Vec;
 
+mod atomic;
 mod boundary;
 mod call;
 mod globalvalue;
@@ -32,6 +33,7 @@ mod libcall;
 mod split;
 mod table;
 
+use self::atomic::{expand_atomic_rmw, expand_atomic_store};
 use self::call::expand_call;
 use self::globalvalue::expand_global_value;
 use self::heap::expand_heap_addr;
@@ -608,7 +610,9 @@ fn narrow_load(
         small_ty,
         flags,
         ptr,
-        offset.try_add_i64(8).expect("load offset overflow"),
+        offset
+            .try_add_i64(i64::from(small_ty.bytes()))
+            .expect("load offset overflow"),
     );
     pos.func.dfg.replace(inst).iconcat(al, ah);
 }
@@ -633,13 +637,22 @@ fn narrow_store(
         _ => panic!("Expected store: {}", pos.func.dfg.display_inst(inst, None)),
     };
 
+    let small_ty = pos
+        .func
+        .dfg
+        .value_type(val)
+        .half_width()
+        .expect("Can't narrow store");
+
     let (al, ah) = pos.ins().isplit(val);
     pos.ins().store(flags, al, ptr, offset);
     pos.ins().store(
         flags,
         ah,
         ptr,
-        offset.try_add_i64(8).expect("store offset overflow"),
+        offset
+            .try_add_i64(i64::from(small_ty.bytes()))
+            .expect("store offset overflow"),
     );
     pos.remove_inst();
 }
@@ -597,6 +597,8 @@ impl RedundantReloadRemover {
             | InstructionData::RegFill { .. }
             | InstructionData::Call { .. }
             | InstructionData::CallIndirect { .. }
+            | InstructionData::Invoke { .. }
+            | InstructionData::InvokeIndirect { .. }
             | InstructionData::StackLoad { .. }
             | InstructionData::StackStore { .. }
             | InstructionData::Unary {
@@ -616,7 +618,7 @@ impl RedundantReloadRemover {
                 // track, but for which the effort-to-benefit ratio seems too low to bother.  So
                 // play safe: forget everything we know.
                 //
-                // For Call/CallIndirect, we could do better when compiling for calling
+                // For calls and invokes, we could do better when compiling for calling
                 // conventions that have callee-saved registers, since bindings for them would
                 // remain valid across the call.
                 avail_env.invalidate_all();
@@ -76,6 +76,8 @@ impl<'a> Context<'a> {
             | InstructionData::BranchIcmp { destination, .. }
             | InstructionData::BranchInt { destination, .. }
             | InstructionData::BranchFloat { destination, .. } => destination,
+            // Landing pads don't have parameters.
+            InstructionData::Invoke { .. } | InstructionData::InvokeIndirect { .. } => return,
             _ => panic!("Unexpected instruction in visit_conditional_branch"),
         };
 
@@ -526,7 +526,7 @@ impl<'a> Context<'a> {
             }
         }
 
-        if let Some(sig) = call_sig {
+        if let Some(sig) = self.cur.func.dfg.call_result_signature(inst) {
             self.program_output_abi(
                 sig,
                 defs,
@@ -188,7 +188,7 @@ pub struct GenericLiveRange<PO: ProgramOrder> {
     /// defined.
     liveins: SmallVec<[Interval; 2]>,
 
-    po: PhantomData<*const PO>,
+    po: PhantomData<PO>,
 }
 
 /// A simple helper macro to make comparisons more natural to read.
@@ -289,7 +289,7 @@ mod tests {
         let shared_builder = settings::builder();
         let shared_flags = settings::Flags::new(shared_builder);
 
-        isa::lookup(triple!("arm"))
+        isa::lookup(triple!("thumbv7a"))
             .ok()
             .map(|b| b.finish(shared_flags))
     }
@@ -261,13 +261,29 @@ impl<'a> Context<'a> {
         // Update the live value tracker with this instruction.
         let (throughs, kills, defs) = tracker.process_inst(inst, &self.cur.func.dfg, self.liveness);
 
+        // Values that are live in to the landing pad of an `invoke` are killed by it, but they must
+        // survive the call all the same. The unwinder doesn't restore caller-saved registers.
+        if call_sig.is_some() {
+            if let Some(landing_pad) = self.cur.func.dfg[inst].branch_destination() {
+                for lv in kills {
+                    if lv.affinity.is_reg()
+                        && !self.spills.contains(&lv.value)
+                        && self.liveness[lv.value].is_livein(landing_pad, &self.cur.func.layout)
+                    {
+                        self.spill_reg(lv.value);
+                    }
+                }
+            }
+        }
+
         // Remove kills from the pressure tracker.
         self.free_regs(kills);
 
         // If inst is a call, spill all register values that are live across the call.
         // This means that we don't currently take advantage of callee-saved registers.
         // TODO: Be more sophisticated.
-        if call_sig.is_some() {
+        let opcode = self.cur.func.dfg[inst].opcode();
+        if call_sig.is_some() || opcode.clobbers_all_regs() {
             for lv in throughs {
                 if lv.affinity.is_reg() && !self.spills.contains(&lv.value) {
                     self.spill_reg(lv.value);
@@ -455,7 +471,8 @@ impl<'a> Context<'a> {
                     // at the branch destination. It is also necessary since there can be
                     // arbitrarily many EBB arguments.
                     match {
-                        let args = if self.cur.func.dfg[inst].opcode().is_branch() {
+                        let opcode = self.cur.func.dfg[inst].opcode();
+                        let args = if opcode.is_branch() && !opcode.is_call() {
                             self.cur.func.dfg.inst_fixed_args(inst)
                         } else {
                             self.cur.func.dfg.inst_args(inst)
@@ -3,6 +3,7 @@
 use crate::dbg::DisplayList;
 use crate::dominator_tree::{DominatorTree, DominatorTreePreorder};
 use crate::flowgraph::{BasicBlock, ControlFlowGraph};
+use crate::ir::instructions::BranchInfo;
 use crate::ir::{ExpandedProgramPoint, Function};
 use crate::regalloc::liveness::Liveness;
 use crate::regalloc::virtregs::VirtRegs;
@@ -145,7 +146,10 @@ impl<'a> CssaVerifier<'a> {
         for ebb in self.func.layout.ebbs() {
             let ebb_params = self.func.dfg.ebb_params(ebb);
             for BasicBlock { inst: pred, .. } in self.cfg.pred_iter(ebb) {
-                let pred_args = self.func.dfg.inst_variable_args(pred);
+                let pred_args = match self.func.dfg.analyze_branch(pred) {
+                    BranchInfo::SingleDest(_, args) => args,
+                    _ => self.func.dfg.inst_variable_args(pred),
+                };
                 // This should have been caught by an earlier verifier pass.
                 assert_eq!(
                     ebb_params.len(),
@@ -153,13 +153,22 @@ impl<'a> LocationVerifier<'a> {
         let varargs = self.func.dfg.inst_variable_args(inst);
         let results = self.func.dfg.inst_results(inst);
 
+        // Tail calls pass stack arguments in the current function's incoming argument area.
+        let is_tail_call = self.func.dfg[inst].opcode().is_terminator();
+        let want_kind = if is_tail_call {
+            self.check_tail_call_stack_args(inst, sig, errors)?;
+            ir::StackSlotKind::IncomingArg
+        } else {
+            ir::StackSlotKind::OutgoingArg
+        };
+
         for (abi, &value) in sig.params.iter().zip(varargs) {
             self.check_abi_location(
                 inst,
                 value,
                 abi,
                 divert.get(value, &self.func.locations),
-                ir::StackSlotKind::OutgoingArg,
+                want_kind,
                 errors,
             )?;
         }
@@ -178,6 +187,46 @@ impl<'a> LocationVerifier<'a> {
         Ok(())
     }
 
+    /// Check that the callee of a tail call fits in the current function's incoming argument area,
+    /// and that it pops the same number of bytes off the stack as our caller expects us to.
+    fn check_tail_call_stack_args(
+        &self,
+        inst: ir::Inst,
+        sig: &ir::Signature,
+        errors: &mut VerifierErrors,
+    ) -> VerifierStepResult<()> {
+        let callee_size = sig.stack_arg_size();
+        let caller_size = self.func.signature.stack_arg_size();
+        if callee_size > caller_size {
+            return errors.fatal((
+                inst,
+                format!(
+                    "tail call needs {} bytes of stack arguments, but the function only has {}",
+                    callee_size, caller_size
+                ),
+            ));
+        }
+
+        let pop_size = |sig: &ir::Signature, size| {
+            if sig.call_conv.callee_pops_stack_args() {
+                size
+            } else {
+                0
+            }
+        };
+        if pop_size(sig, callee_size) != pop_size(&self.func.signature, caller_size) {
+            return errors.fatal((
+                inst,
+                format!(
+                    "tail call from {} to {} would not pop the caller's stack arguments",
+                    self.func.signature.call_conv, sig.call_conv
+                ),
+            ));
+        }
+
+        Ok(())
+    }
+
     /// Check the ABI argument locations for a return.
     fn check_return_abi(
         &self,
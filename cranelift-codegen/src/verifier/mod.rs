@@ -65,13 +65,14 @@ use crate::ir;
 use crate::ir::entities::AnyEntity;
 use crate::ir::instructions::{BranchInfo, CallInfo, InstructionFormat, ResolvedConstraint};
 use crate::ir::{
-    types, ArgumentLoc, Ebb, FuncRef, Function, GlobalValue, Inst, InstructionData, JumpTable,
-    Opcode, SigRef, StackSlot, StackSlotKind, Type, Value, ValueDef, ValueList, ValueLoc,
+    types, AbiParam, ArgumentLoc, ArgumentPurpose, Ebb, FuncRef, Function, GlobalValue, Inst,
+    InstructionData, JumpTable, Opcode, SigRef, StackSlot, StackSlotKind, Type, Value, ValueDef,
+    ValueList, ValueLoc,
 };
 use crate::isa::TargetIsa;
 use crate::iterators::IteratorExtras;
 use crate::print_errors::pretty_verifier_error;
-use crate::settings::FlagsOrIsa;
+use crate::settings::{self, FlagsOrIsa};
 use crate::timing;
 use alloc::collections::BTreeSet;
 use alloc::string::{String, ToString};
@@ -406,6 +407,82 @@ impl<'a> Verifier<'a> {
         Ok(())
     }
 
+    fn verify_stack_limit(&self, errors: &mut VerifierErrors) -> VerifierStepResult<()> {
+        if let Some(limit) = self.func.stack_limit {
+            if !self.func.global_values.is_valid(limit) {
+                return errors.fatal((limit, format!("invalid stack limit {}", limit)));
+            }
+
+            if self
+                .func
+                .special_param(ir::ArgumentPurpose::StackLimit)
+                .is_some()
+            {
+                return errors.nonfatal((
+                    limit,
+                    "stack limit global value and stack_limit parameter are both present",
+                ));
+            }
+
+            // The prologue computes the stack limit before any register is saved, so it can only
+            // use loads and additions based on the vmctx, which must be passed in a register.
+            let mut seen = SparseSet::new();
+            let mut cur = limit;
+            loop {
+                match self.func.global_values[cur] {
+                    ir::GlobalValueData::VMContext => break,
+                    ir::GlobalValueData::Load { base, .. }
+                    | ir::GlobalValueData::IAddImm { base, .. } => {
+                        // Cycles are reported by `verify_global_values`.
+                        if seen.insert(base).is_some() {
+                            return Ok(());
+                        }
+                        cur = base;
+                    }
+                    ref other => {
+                        return errors.nonfatal((
+                            limit,
+                            format!(
+                                "stack limit {} depends on {} = {}, but only vmctx, load and \
+                                 iadd_imm global values are supported",
+                                limit, cur, other
+                            ),
+                        ));
+                    }
+                }
+            }
+
+            if let Some(isa) = self.isa {
+                let limit_type = self.func.global_values[limit].global_type(isa);
+                let pointer_type = isa.pointer_type();
+                if limit_type != pointer_type {
+                    return errors.nonfatal((
+                        limit,
+                        format!(
+                            "stack limit {} has type {}, which is not the pointer type {}",
+                            limit, limit_type, pointer_type
+                        ),
+                    ));
+                }
+
+                // Locations are only known after register allocation.
+                if let Some(vmctx) = self.func.special_param(ir::ArgumentPurpose::VMContext) {
+                    if let ir::ValueLoc::Stack(_) = self.func.locations[vmctx] {
+                        return errors.nonfatal((
+                            limit,
+                            format!(
+                                "stack limit {} requires the vmctx to be passed in a register",
+                                limit
+                            ),
+                        ));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     fn verify_heaps(&self, errors: &mut VerifierErrors) -> VerifierStepResult<()> {
         if let Some(isa) = self.isa {
             for (heap, heap_data) in &self.func.heaps {
@@ -433,6 +510,16 @@ impl<'a> Verifier<'a> {
                     }
 
                     let index_type = heap_data.index_type;
+                    if index_type.bits() > pointer_type.bits() {
+                        errors.report((
+                            heap,
+                            format!(
+                                "dynamic heap index type {} is wider than the pointer type {}",
+                                index_type, pointer_type
+                            ),
+                        ));
+                    }
+
                     let bound_type = self.func.global_values[bound_gv].global_type(isa);
                     if index_type != bound_type {
                         errors.report((
@@ -581,9 +668,9 @@ impl<'a> Verifier<'a> {
         }
 
         let num_fixed_results = inst_data.opcode().constraints().num_fixed_results();
-        // var_results is 0 if we aren't a call instruction
+        // var_results is 0 if we aren't a call instruction, or if we are a tail call
         let var_results = dfg
-            .call_signature(inst)
+            .call_result_signature(inst)
             .map_or(0, |sig| dfg.signatures[sig].returns.len());
         let total_results = num_fixed_results + var_results;
 
@@ -683,6 +770,26 @@ impl<'a> Verifier<'a> {
                 self.verify_sig_ref(inst, sig_ref, errors)?;
                 self.verify_value_list(inst, args, errors)?;
             }
+            Invoke {
+                func_ref,
+                destination,
+                ref args,
+                ..
+            } => {
+                self.verify_func_ref(inst, func_ref, errors)?;
+                self.verify_ebb(inst, destination, errors)?;
+                self.verify_value_list(inst, args, errors)?;
+            }
+            InvokeIndirect {
+                sig_ref,
+                destination,
+                ref args,
+                ..
+            } => {
+                self.verify_sig_ref(inst, sig_ref, errors)?;
+                self.verify_ebb(inst, destination, errors)?;
+                self.verify_value_list(inst, args, errors)?;
+            }
             FuncAddr { func_ref, .. } => {
                 self.verify_func_ref(inst, func_ref, errors)?;
             }
@@ -763,6 +870,8 @@ impl<'a> Verifier<'a> {
             | IntSelect { .. }
             | Load { .. }
             | Store { .. }
+            | AtomicRmw { .. }
+            | AtomicCas { .. }
             | RegMove { .. }
             | CopySpecial { .. }
             | CopyToSsa { .. }
@@ -989,6 +1098,7 @@ impl<'a> Verifier<'a> {
                             format!("uses value {} from itself", v),
                         ));
                     }
+                    self.verify_invoke_result_use(loc_inst, v, def_inst, errors)?;
                 }
             }
             ValueDef::Param(ebb, _) => {
@@ -1025,6 +1135,43 @@ impl<'a> Verifier<'a> {
         Ok(())
     }
 
+    /// The results of an `invoke` are only defined when the call returns normally, so they can
+    /// only be used by the following jump or in code dominated by the normal successor.
+    fn verify_invoke_result_use(
+        &self,
+        loc_inst: Inst,
+        v: Value,
+        def_inst: Inst,
+        errors: &mut VerifierErrors,
+    ) -> VerifierStepResult<()> {
+        let dfg = &self.func.dfg;
+        let opcode = dfg[def_inst].opcode();
+        if !opcode.is_call() || !opcode.is_branch() {
+            return Ok(());
+        }
+        let next = match self.func.layout.next_inst(def_inst) {
+            Some(next) if next != loc_inst => next,
+            _ => return Ok(()),
+        };
+        if let Some(normal) = dfg[next].branch_destination() {
+            if normal == dfg[def_inst].branch_destination().unwrap()
+                || !self
+                    .expected_domtree
+                    .dominates(normal, loc_inst, &self.func.layout)
+            {
+                return errors.fatal((
+                    loc_inst,
+                    self.context(loc_inst),
+                    format!(
+                        "uses value {} returned by {} on its exceptional path",
+                        v, def_inst
+                    ),
+                ));
+            }
+        }
+        Ok(())
+    }
+
     fn verify_inst_result(
         &self,
         loc_inst: Inst,
@@ -1296,6 +1443,33 @@ impl<'a> Verifier<'a> {
         errors: &mut VerifierErrors,
     ) -> VerifierStepResult<()> {
         match self.func.dfg.analyze_branch(inst) {
+            BranchInfo::SingleDest(ebb, _) if self.func.dfg[inst].opcode().is_call() => {
+                // The variable arguments of an invoke go to the callee. The unwinder can't pass
+                // any arguments to the landing pad.
+                let arg_count = self.func.dfg.num_ebb_params(ebb);
+                if arg_count != 0 {
+                    return errors.nonfatal((
+                        inst,
+                        self.context(inst),
+                        format!(
+                            "landing pad {} can't take arguments, but has {}",
+                            ebb, arg_count
+                        ),
+                    ));
+                }
+                // Calls to functions that pop their stack arguments are followed by a stack
+                // adjustment, which can't be placed between an invoke and its jump.
+                if let Some(sig_ref) = self.func.dfg.call_signature(inst) {
+                    let call_conv = self.func.dfg.signatures[sig_ref].call_conv;
+                    if call_conv.callee_pops_stack_args() {
+                        return errors.nonfatal((
+                            inst,
+                            self.context(inst),
+                            format!("can't invoke a function using the {} convention", call_conv),
+                        ));
+                    }
+                }
+            }
             BranchInfo::SingleDest(ebb, _) => {
                 let iter = self
                     .func
@@ -1406,7 +1580,8 @@ impl<'a> Verifier<'a> {
     /// Check the locations assigned to outgoing call arguments.
     ///
     /// When a signature has been legalized, all values passed as outgoing arguments on the stack
-    /// must be assigned to a matching `OutgoingArg` stack slot.
+    /// must be assigned to a matching `OutgoingArg` stack slot. Tail calls pass their stack
+    /// arguments in the current function's `IncomingArg` stack slots instead.
     fn check_outgoing_args(
         &self,
         inst: Inst,
@@ -1414,6 +1589,11 @@ impl<'a> Verifier<'a> {
         errors: &mut VerifierErrors,
     ) -> VerifierStepResult<()> {
         let sig = &self.func.dfg.signatures[sig_ref];
+        let want_kind = if self.func.dfg[inst].opcode().is_terminator() {
+            StackSlotKind::IncomingArg
+        } else {
+            StackSlotKind::OutgoingArg
+        };
 
         let args = self.func.dfg.inst_variable_args(inst);
         let expected_args = &sig.params[..];
@@ -1426,7 +1606,7 @@ impl<'a> Verifier<'a> {
                     // Argument value is assigned to a stack slot as expected.
                     self.verify_stack_slot(inst, ss, errors)?;
                     let slot = &self.func.stack_slots[ss];
-                    if slot.kind != StackSlotKind::OutgoingArg {
+                    if slot.kind != want_kind {
                         return errors.fatal((
                             inst,
                             self.context(inst),
@@ -1474,7 +1654,41 @@ impl<'a> Verifier<'a> {
     }
 
     fn typecheck_return(&self, inst: Inst, errors: &mut VerifierErrors) -> VerifierStepResult<()> {
-        if self.func.dfg[inst].opcode().is_return() {
+        let opcode = self.func.dfg[inst].opcode();
+        if opcode.is_call() && opcode.is_terminator() {
+            // The callee of a tail call returns directly to our caller, so it must return the
+            // same types as this function. The registers restored by the epilogue are not part of
+            // that.
+            let is_abi_return = |ret: &&AbiParam| {
+                ret.purpose != ArgumentPurpose::FramePointer
+                    && ret.purpose != ArgumentPurpose::CalleeSaved
+            };
+            let sig_ref = self.func.dfg.call_signature(inst).unwrap();
+            let callee_returns = self.func.dfg.signatures[sig_ref]
+                .returns
+                .iter()
+                .filter(is_abi_return)
+                .map(|ret| ret.value_type);
+            let expected_returns = self
+                .func
+                .signature
+                .returns
+                .iter()
+                .filter(is_abi_return)
+                .map(|ret| ret.value_type);
+            if !callee_returns.eq(expected_returns) {
+                return errors.nonfatal((
+                    inst,
+                    self.context(inst),
+                    format!(
+                        "tail call to {} must have the same return types as the function \
+                         signature",
+                        sig_ref
+                    ),
+                ));
+            }
+        }
+        if opcode.is_return() {
             let args = self.func.dfg.inst_variable_args(inst);
             let expected_types = &self.func.signature.returns;
             if args.len() != expected_types.len() {
@@ -1928,6 +2142,44 @@ impl<'a> Verifier<'a> {
         Ok(())
     }
 
+    fn verify_tls_model_set(
+        &self,
+        inst: Inst,
+        errors: &mut VerifierErrors,
+    ) -> VerifierStepResult<()> {
+        if let Some(isa) = self.isa {
+            if isa.flags().tls_model() != settings::TlsModel::None {
+                return Ok(());
+            }
+            let uses_tls = match self.func.dfg[inst] {
+                ir::InstructionData::UnaryGlobalValue {
+                    opcode: Opcode::TlsValue,
+                    ..
+                } => true,
+                ir::InstructionData::UnaryGlobalValue {
+                    opcode: Opcode::GlobalValue,
+                    global_value,
+                }
+                | ir::InstructionData::UnaryGlobalValue {
+                    opcode: Opcode::SymbolValue,
+                    global_value,
+                } => match self.func.global_values[global_value] {
+                    ir::GlobalValueData::Symbol { tls, .. } => tls,
+                    _ => false,
+                },
+                _ => false,
+            };
+            if uses_tls {
+                return errors.fatal((
+                    inst,
+                    self.context(inst),
+                    "thread local values cannot be used when the tls_model flag is not set.",
+                ));
+            }
+        }
+        Ok(())
+    }
+
     fn typecheck_function_signature(&self, errors: &mut VerifierErrors) -> VerifierStepResult<()> {
         self.func
             .signature
@@ -1964,6 +2216,7 @@ impl<'a> Verifier<'a> {
 
     pub fn run(&self, errors: &mut VerifierErrors) -> VerifierStepResult<()> {
         self.verify_global_values(errors)?;
+        self.verify_stack_limit(errors)?;
         self.verify_heaps(errors)?;
         self.verify_tables(errors)?;
         self.verify_jump_tables(errors)?;
@@ -1978,6 +2231,7 @@ impl<'a> Verifier<'a> {
                 self.ebb_integrity(ebb, inst, errors)?;
                 self.instruction_integrity(inst, errors)?;
                 self.verify_safepoint_unused(inst, errors)?;
+                self.verify_tls_model_set(inst, errors)?;
                 self.typecheck(inst, errors)?;
                 self.verify_encoding(inst, errors)?;
                 self.immediate_constraints(inst, errors)?;
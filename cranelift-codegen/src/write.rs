@@ -102,6 +102,11 @@ pub trait FuncWriter {
             self.write_entity_definition(w, func, jt.into(), jt_data)?;
         }
 
+        if let Some(limit) = func.stack_limit {
+            any = true;
+            writeln!(w, "    stack_limit = {}", limit)?;
+        }
+
         Ok(any)
     }
 
@@ -600,6 +605,34 @@ pub fn write_operands(
                 DisplayValues(&args[1..])
             )
         }
+        Invoke {
+            func_ref,
+            destination,
+            ref args,
+            ..
+        } => write!(
+            w,
+            " {}({}), {}",
+            func_ref,
+            DisplayValues(args.as_slice(pool)),
+            destination
+        ),
+        InvokeIndirect {
+            sig_ref,
+            destination,
+            ref args,
+            ..
+        } => {
+            let args = args.as_slice(pool);
+            write!(
+                w,
+                " {}, {}({}), {}",
+                sig_ref,
+                args[0],
+                DisplayValues(&args[1..]),
+                destination
+            )
+        }
         FuncAddr { func_ref, .. } => write!(w, " {}", func_ref),
         StackLoad {
             stack_slot, offset, ..
@@ -636,6 +669,12 @@ pub fn write_operands(
             offset,
             ..
         } => write!(w, "{} {}, {}{}", flags, args[0], args[1], offset),
+        AtomicRmw {
+            flags, op, args, ..
+        } => write!(w, "{} {} {}, {}", flags, op, args[0], args[1]),
+        AtomicCas { flags, args, .. } => {
+            write!(w, "{} {}, {}, {}", flags, args[0], args[1], args[2])
+        }
         StoreComplex {
             flags,
             ref args,
@@ -135,11 +135,18 @@ impl Backend for FaerieBackend {
         name: &str,
         linkage: Linkage,
         writable: bool,
+        tls: bool,
         align: Option<u8>,
-    ) {
+    ) -> ModuleResult<()> {
+        if tls {
+            return Err(ModuleError::Backend(
+                "faerie doesn't support thread local data".to_owned(),
+            ));
+        }
         self.artifact
             .declare(name, translate_data_linkage(linkage, writable, align))
             .expect("inconsistent declarations");
+        Ok(())
     }
 
     fn define_function(
@@ -205,6 +212,7 @@ impl Backend for FaerieBackend {
         _id: DataId,
         name: &str,
         _writable: bool,
+        _tls: bool,
         _align: Option<u8>,
         data_ctx: &DataContext,
         namespace: &ModuleNamespace<Self>,
@@ -339,12 +347,12 @@ impl FaerieProduct {
 
     /// Call `emit` on the faerie `Artifact`, producing bytes in memory.
     pub fn emit(&self) -> Result<Vec<u8>, Error> {
-        self.artifact.emit()
+        Ok(self.artifact.emit()?)
     }
 
     /// Call `write` on the faerie `Artifact`, writing to a file.
     pub fn write(&self, sink: File) -> Result<(), Error> {
-        self.artifact.write(sink)
+        Ok(self.artifact.write(sink)?)
     }
 }
 
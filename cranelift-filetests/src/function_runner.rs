@@ -1,10 +1,24 @@
 use core::mem;
 use cranelift_codegen::binemit::{NullRelocSink, NullStackmapSink, NullTrapSink};
-use cranelift_codegen::ir::Function;
+use cranelift_codegen::cursor::{Cursor, FuncCursor};
+use cranelift_codegen::data_value::DataValue;
+use cranelift_codegen::ir::immediates::{Ieee32, Ieee64};
+use cranelift_codegen::ir::{
+    types, AbiParam, ExternalName, Function, InstBuilder, MemFlags, Signature, Type,
+};
 use cranelift_codegen::isa::TargetIsa;
 use cranelift_codegen::{settings, Context};
 use cranelift_native::builder as host_isa_builder;
-use memmap::MmapMut;
+use memmap::{Mmap, MmapMut};
+use std::cmp::max;
+use std::convert::TryInto;
+
+/// Each argument and result is passed through a slot of this many bytes, enough to hold any of
+/// the supported types.
+const SLOT_SIZE: usize = 16;
+
+/// The signature of a trampoline built by `make_trampoline`.
+type Trampoline = unsafe extern "C" fn(callee: *const u8, values: *mut u8);
 
 /// Run a function on a host
 pub struct FunctionRunner {
@@ -31,59 +45,282 @@ impl FunctionRunner {
         Self::with_host_isa(function, flags)
     }
 
+    /// Compile the function, along with a trampoline used to call it with `DataValue` arguments.
+    pub fn compile(&self) -> Result<CompiledFunction, String> {
+        let signature = &self.function.signature;
+        if signature.call_conv != self.isa.default_call_conv() {
+            return Err(String::from(
+                "Functions only run on the host's default calling convention; remove the specified calling convention in the function signature to use the host's default.",
+            ));
+        }
+        for param in signature.params.iter().chain(signature.returns.iter()) {
+            let ty = param.value_type;
+            if !(ty.is_int() || ty.is_float() || ty.is_bool() || ty.bits() == 128) {
+                return Err(format!(
+                    "Values of type {} cannot be passed to or from a function under test",
+                    ty
+                ));
+            }
+        }
+
+        let isa = self.isa.as_ref();
+        Ok(CompiledFunction {
+            signature: signature.clone(),
+            code: compile(self.function.clone(), isa)?,
+            trampoline: compile(make_trampoline(signature, isa), isa)?,
+        })
+    }
+
     /// Compile and execute a single function, expecting a boolean to be returned; a 'true' value is
     /// interpreted as a successful test execution and mapped to Ok whereas a 'false' value is
     /// interpreted as a failed test and mapped to Err.
     pub fn run(&self) -> Result<(), String> {
-        let func = self.function.clone();
-        if !(func.signature.params.is_empty()
-            && func.signature.returns.len() == 1
-            && func.signature.returns.first().unwrap().value_type.is_bool())
+        let signature = &self.function.signature;
+        if !(signature.params.is_empty()
+            && signature.returns.len() == 1
+            && signature.returns.first().unwrap().value_type.is_bool())
         {
             return Err(String::from(
                 "Functions must have a signature like: () -> boolean",
             ));
         }
 
-        if func.signature.call_conv != self.isa.default_call_conv() {
-            return Err(String::from(
-                "Functions only run on the host's default calling convention; remove the specified calling convention in the function signature to use the host's default.",
+        if self.compile()?.call(&[])? == [DataValue::B(true)] {
+            Ok(())
+        } else {
+            Err(format!("Failed: {}", self.function.name))
+        }
+    }
+}
+
+/// A function compiled for the host by a `FunctionRunner`.
+pub struct CompiledFunction {
+    signature: Signature,
+    code: Mmap,
+    trampoline: Mmap,
+}
+
+impl CompiledFunction {
+    /// Call the function with `args` and return its results. Fails if the arguments do not match
+    /// the function's signature or if the function traps.
+    pub fn call(&self, args: &[DataValue]) -> Result<Vec<DataValue>, String> {
+        if args.len() != self.signature.params.len() {
+            return Err(format!(
+                "expected {} arguments but found {}",
+                self.signature.params.len(),
+                args.len()
             ));
         }
 
-        // set up the context
-        let mut context = Context::new();
-        context.func = func;
-
-        // compile and encode the result to machine code
-        let relocs = &mut NullRelocSink {};
-        let traps = &mut NullTrapSink {};
-        let stackmaps = &mut NullStackmapSink {};
-        let code_info = context
-            .compile(self.isa.as_ref())
-            .map_err(|e| e.to_string())?;
-        let mut code_page =
-            MmapMut::map_anon(code_info.total_size as usize).map_err(|e| e.to_string())?;
-
-        unsafe {
-            context.emit_to_memory(
-                self.isa.as_ref(),
-                code_page.as_mut_ptr(),
-                relocs,
-                traps,
-                stackmaps,
-            );
-        };
-
-        let code_page = code_page.make_exec().map_err(|e| e.to_string())?;
-        let callable_fn: fn() -> bool = unsafe { mem::transmute(code_page.as_ptr()) };
-
-        // execute
-        if callable_fn() {
-            Ok(())
-        } else {
-            Err(format!("Failed: {}", context.func.name.to_string()))
+        let slots = max(self.signature.params.len(), self.signature.returns.len());
+        let mut values = MmapMut::map_anon(max(slots, 1) * SLOT_SIZE).map_err(|e| e.to_string())?;
+        for (i, (arg, param)) in args.iter().zip(&self.signature.params).enumerate() {
+            write_value(arg, param.value_type, &mut values[i * SLOT_SIZE..])?;
         }
+
+        let trampoline: Trampoline = unsafe { mem::transmute(self.trampoline.as_ptr()) };
+        invoke(trampoline, self.code.as_ptr(), values.as_mut_ptr())?;
+
+        Ok(self
+            .signature
+            .returns
+            .iter()
+            .enumerate()
+            .map(|(i, ret)| read_value(ret.value_type, &values[i * SLOT_SIZE..]))
+            .collect())
+    }
+}
+
+/// Compile `function` and copy its machine code to executable memory.
+fn compile(function: Function, isa: &dyn TargetIsa) -> Result<Mmap, String> {
+    let mut context = Context::new();
+    context.func = function;
+
+    // compile and encode the result to machine code
+    let relocs = &mut NullRelocSink {};
+    let traps = &mut NullTrapSink {};
+    let stackmaps = &mut NullStackmapSink {};
+    let code_info = context.compile(isa).map_err(|e| e.to_string())?;
+    let mut code_page =
+        MmapMut::map_anon(code_info.total_size as usize).map_err(|e| e.to_string())?;
+
+    unsafe {
+        context.emit_to_memory(isa, code_page.as_mut_ptr(), relocs, traps, stackmaps);
+    };
+
+    code_page.make_exec().map_err(|e| e.to_string())
+}
+
+/// The type a value of type `ty` is loaded and stored as by the trampoline. Booleans are passed
+/// as integers of the same width: loads and stores only accept integers, while both live in the
+/// same registers.
+fn slot_type(ty: Type) -> Type {
+    if ty.lane_type().is_bool() {
+        let lane = Type::int(max(u16::from(ty.lane_bits()), 8)).unwrap();
+        lane.by(ty.lane_count()).unwrap()
+    } else {
+        ty
+    }
+}
+
+/// Build a trampoline with the signature `Trampoline`, which loads the arguments of a function
+/// with `signature` from the slots of `values`, calls `callee` and stores its results back into
+/// the slots.
+fn make_trampoline(signature: &Signature, isa: &dyn TargetIsa) -> Function {
+    let pointer_type = isa.pointer_type();
+    let mut trampoline_signature = Signature::new(isa.default_call_conv());
+    trampoline_signature
+        .params
+        .push(AbiParam::new(pointer_type));
+    trampoline_signature
+        .params
+        .push(AbiParam::new(pointer_type));
+    let mut func =
+        Function::with_name_signature(ExternalName::testcase("trampoline"), trampoline_signature);
+
+    let mut callee_signature = signature.clone();
+    for param in callee_signature
+        .params
+        .iter_mut()
+        .chain(callee_signature.returns.iter_mut())
+    {
+        param.value_type = slot_type(param.value_type);
+    }
+    let callee_signature = func.import_signature(callee_signature);
+
+    let ebb = func.dfg.make_ebb();
+    let callee = func.dfg.append_ebb_param(ebb, pointer_type);
+    let values = func.dfg.append_ebb_param(ebb, pointer_type);
+    func.layout.append_ebb(ebb);
+
+    let mut pos = FuncCursor::new(&mut func).at_bottom(ebb);
+    let flags = MemFlags::trusted();
+    let args: Vec<_> = signature
+        .params
+        .iter()
+        .enumerate()
+        .map(|(i, param)| {
+            let offset = (i * SLOT_SIZE) as i32;
+            pos.ins()
+                .load(slot_type(param.value_type), flags, values, offset)
+        })
+        .collect();
+    let call = pos.ins().call_indirect(callee_signature, callee, &args);
+    let results = pos.func.dfg.inst_results(call).to_vec();
+    for (i, result) in results.into_iter().enumerate() {
+        pos.ins()
+            .store(flags, result, values, (i * SLOT_SIZE) as i32);
+    }
+    pos.ins().return_(&[]);
+    func
+}
+
+/// Write `value` to `slot` in the layout the trampoline loads a value of type `ty` from.
+fn write_value(value: &DataValue, ty: Type, slot: &mut [u8]) -> Result<(), String> {
+    match (value, ty) {
+        (DataValue::B(b), _) if ty.is_bool() && !ty.is_vector() => slot[0] = *b as u8,
+        (DataValue::I8(x), types::I8) => slot[..1].copy_from_slice(&x.to_ne_bytes()),
+        (DataValue::I16(x), types::I16) => slot[..2].copy_from_slice(&x.to_ne_bytes()),
+        (DataValue::I32(x), types::I32) => slot[..4].copy_from_slice(&x.to_ne_bytes()),
+        (DataValue::I64(x), types::I64) => slot[..8].copy_from_slice(&x.to_ne_bytes()),
+        (DataValue::I128(x), types::I128) => slot[..16].copy_from_slice(&x.to_ne_bytes()),
+        (DataValue::F32(x), types::F32) => slot[..4].copy_from_slice(&x.bits().to_ne_bytes()),
+        (DataValue::F64(x), types::F64) => slot[..8].copy_from_slice(&x.bits().to_ne_bytes()),
+        (DataValue::V128(bytes), _) if ty.is_vector() => slot[..16].copy_from_slice(bytes),
+        _ => {
+            return Err(format!(
+                "expected an argument of type {} but found {}",
+                ty, value
+            ))
+        }
+    }
+    Ok(())
+}
+
+/// Read a value of type `ty` from `slot`, where the trampoline stored it.
+fn read_value(ty: Type, slot: &[u8]) -> DataValue {
+    match ty {
+        types::I8 => DataValue::I8(i8::from_ne_bytes(slot[..1].try_into().unwrap())),
+        types::I16 => DataValue::I16(i16::from_ne_bytes(slot[..2].try_into().unwrap())),
+        types::I32 => DataValue::I32(i32::from_ne_bytes(slot[..4].try_into().unwrap())),
+        types::I64 => DataValue::I64(i64::from_ne_bytes(slot[..8].try_into().unwrap())),
+        types::I128 => DataValue::I128(i128::from_ne_bytes(slot[..16].try_into().unwrap())),
+        types::F32 => DataValue::F32(Ieee32::with_bits(u32::from_ne_bytes(
+            slot[..4].try_into().unwrap(),
+        ))),
+        types::F64 => DataValue::F64(Ieee64::with_bits(u64::from_ne_bytes(
+            slot[..8].try_into().unwrap(),
+        ))),
+        _ if ty.is_vector() => DataValue::V128(slot[..16].try_into().unwrap()),
+        _ if ty.is_bool() => {
+            let bytes = slot_type(ty).bytes() as usize;
+            DataValue::B(slot[..bytes].iter().any(|&b| b != 0))
+        }
+        _ => panic!("unsupported result type {}", ty),
+    }
+}
+
+/// Call `trampoline` in a child process, so that a trap in the compiled code is reported as an
+/// error instead of bringing down the test runner. The anonymous mapping backing `values` is
+/// shared with the child, which is how the results are passed back.
+#[cfg(unix)]
+fn invoke(trampoline: Trampoline, callee: *const u8, values: *mut u8) -> Result<(), String> {
+    match unsafe { libc::fork() } {
+        -1 => Err(format!(
+            "unable to fork: {}",
+            std::io::Error::last_os_error()
+        )),
+        0 => unsafe {
+            // The test runner is multi-threaded, so the child must stick to async-signal-safe
+            // operations: don't leave core dumps behind for traps, run the code and exit.
+            let no_core = libc::rlimit {
+                rlim_cur: 0,
+                rlim_max: 0,
+            };
+            libc::setrlimit(libc::RLIMIT_CORE, &no_core);
+            trampoline(callee, values);
+            libc::_exit(0)
+        },
+        pid => {
+            let mut status = 0;
+            while unsafe { libc::waitpid(pid, &mut status, 0) } == -1 {
+                let error = std::io::Error::last_os_error();
+                if error.kind() != std::io::ErrorKind::Interrupted {
+                    return Err(format!("unable to wait for the child process: {}", error));
+                }
+            }
+            if libc::WIFSIGNALED(status) {
+                Err(format!(
+                    "trapped with {}",
+                    signal_name(libc::WTERMSIG(status))
+                ))
+            } else if libc::WIFEXITED(status) && libc::WEXITSTATUS(status) == 0 {
+                Ok(())
+            } else {
+                Err(format!("exited unexpectedly with status {}", status))
+            }
+        }
+    }
+}
+
+/// Without a way to isolate the compiled code, call the trampoline directly; a trap will abort
+/// the test runner.
+#[cfg(not(unix))]
+fn invoke(trampoline: Trampoline, callee: *const u8, values: *mut u8) -> Result<(), String> {
+    unsafe { trampoline(callee, values) };
+    Ok(())
+}
+
+/// Name the signals raised by Cranelift traps.
+#[cfg(unix)]
+fn signal_name(signal: libc::c_int) -> String {
+    match signal {
+        libc::SIGILL => "SIGILL".to_string(),
+        libc::SIGFPE => "SIGFPE".to_string(),
+        libc::SIGSEGV => "SIGSEGV".to_string(),
+        libc::SIGBUS => "SIGBUS".to_string(),
+        libc::SIGTRAP => "SIGTRAP".to_string(),
+        _ => format!("signal {}", signal),
     }
 }
 
@@ -114,4 +351,67 @@ mod test {
         let runner = FunctionRunner::with_default_host_isa(function);
         runner.run().unwrap() // will panic if execution fails
     }
+
+    #[test]
+    fn arguments() {
+        let code = String::from(
+            "
+            test run
+            function %test(i32, f64, b1) -> i32, f64, b1 {
+            ebb0(v0: i32, v1: f64, v2: b1):
+                v3 = iadd_imm v0, 1
+                v4 = fneg v1
+                v5 = select v2, v3, v0
+                v6 = icmp_imm eq v5, 42
+                return v5, v4, v6
+            }",
+        );
+        let test_file = parse_test(code.as_str(), ParseOptions::default()).unwrap();
+        let function = test_file.functions[0].0.clone();
+
+        let compiled = FunctionRunner::with_default_host_isa(function)
+            .compile()
+            .unwrap();
+        assert_eq!(
+            compiled.call(&[
+                DataValue::I32(41),
+                DataValue::from(1.5f64),
+                DataValue::B(true)
+            ]),
+            Ok(vec![
+                DataValue::I32(42),
+                DataValue::from(-1.5f64),
+                DataValue::B(true)
+            ])
+        );
+        assert!(compiled.call(&[DataValue::I32(1)]).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn trap() {
+        let code = String::from(
+            "
+            test run
+            function %test(i32) -> i32 {
+            ebb0(v0: i32):
+                trapz v0, user0
+                return v0
+            }",
+        );
+        let test_file = parse_test(code.as_str(), ParseOptions::default()).unwrap();
+        let function = test_file.functions[0].0.clone();
+
+        let compiled = FunctionRunner::with_default_host_isa(function)
+            .compile()
+            .unwrap();
+        assert_eq!(
+            compiled.call(&[DataValue::I32(1)]),
+            Ok(vec![DataValue::I32(1)])
+        );
+        assert_eq!(
+            compiled.call(&[DataValue::I32(0)]),
+            Err("trapped with SIGILL".to_string())
+        );
+    }
 }
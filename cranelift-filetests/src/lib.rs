@@ -23,7 +23,7 @@
     )
 )]
 
-pub use crate::function_runner::FunctionRunner;
+pub use crate::function_runner::{CompiledFunction, FunctionRunner};
 use crate::runner::TestRunner;
 use cranelift_codegen::timing;
 use cranelift_reader::TestCommand;
@@ -43,6 +43,7 @@ mod test_compile;
 mod test_dce;
 mod test_domtree;
 mod test_fde;
+mod test_interpret;
 mod test_legalizer;
 mod test_licm;
 mod test_postopt;
@@ -125,6 +126,7 @@ fn new_subtest(parsed: &TestCommand) -> subtest::SubtestResult<Box<dyn subtest::
         "rodata" => test_rodata::subtest(parsed),
         "dce" => test_dce::subtest(parsed),
         "domtree" => test_domtree::subtest(parsed),
+        "interpret" => test_interpret::subtest(parsed),
         "legalizer" => test_legalizer::subtest(parsed),
         "licm" => test_licm::subtest(parsed),
         "postopt" => test_postopt::subtest(parsed),
@@ -0,0 +1,57 @@
+//! Test command for interpreting CLIF files and verifying their results
+//!
+//! The `interpret` test command executes each function with the Cranelift interpreter, so unlike
+//! `test run` it does not depend on the host having a native backend.
+
+use crate::subtest::{Context, SubTest, SubtestResult};
+use cranelift_codegen::ir;
+use cranelift_interpreter::{ControlFlow, Environment, Interpreter};
+use cranelift_reader::{parse_run_command, TestCommand};
+use std::borrow::Cow;
+
+struct TestInterpret;
+
+pub fn subtest(parsed: &TestCommand) -> SubtestResult<Box<dyn SubTest>> {
+    assert_eq!(parsed.command, "interpret");
+    if !parsed.options.is_empty() {
+        Err(format!("No options allowed on {}", parsed))
+    } else {
+        Ok(Box::new(TestInterpret))
+    }
+}
+
+impl SubTest for TestInterpret {
+    fn name(&self) -> &'static str {
+        "interpret"
+    }
+
+    fn is_mutating(&self) -> bool {
+        false
+    }
+
+    fn needs_isa(&self) -> bool {
+        false
+    }
+
+    fn run(&self, func: Cow<ir::Function>, context: &Context) -> SubtestResult<()> {
+        let name = func.name.to_string();
+        for comment in context.details.comments.iter() {
+            if let Some(command) = parse_run_command(comment.text, &func)
+                .map_err(|e| format!("{}: {}", comment.text, e))?
+            {
+                let env = Environment::from(func.clone().into_owned());
+                let mut interpreter = Interpreter::new(env);
+                command.run(|args| {
+                    match interpreter
+                        .call_by_name(&name, args)
+                        .map_err(|e| e.to_string())?
+                    {
+                        ControlFlow::Return(results) => Ok(results),
+                        ControlFlow::Trap(code) => Err(format!("trapped with {}", code)),
+                    }
+                })?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,12 +1,12 @@
 //! Test command for running CLIF files and verifying their results
 //!
-//! The `run` test command compiles each function on the host machine and executes it
+//! The `run` test command compiles each function on the host machine and executes it once for
+//! each of its run commands, e.g. `; run: %fn(1, 2) == 3`, checking the results.
 
 use crate::function_runner::FunctionRunner;
 use crate::subtest::{Context, SubTest, SubtestResult};
-use cranelift_codegen;
 use cranelift_codegen::ir;
-use cranelift_reader::TestCommand;
+use cranelift_reader::{parse_run_command, TestCommand};
 use std::borrow::Cow;
 
 struct TestRun;
@@ -34,13 +34,23 @@ impl SubTest for TestRun {
     }
 
     fn run(&self, func: Cow<ir::Function>, context: &Context) -> SubtestResult<()> {
+        let mut commands = Vec::new();
         for comment in context.details.comments.iter() {
-            if comment.text.contains("run") {
-                let runner =
-                    FunctionRunner::with_host_isa(func.clone().into_owned(), context.flags.clone());
-                runner.run()?
+            if let Some(command) = parse_run_command(comment.text, &func)
+                .map_err(|e| format!("{}: {}", comment.text, e))?
+            {
+                commands.push(command);
             }
         }
+        if commands.is_empty() {
+            return Ok(());
+        }
+
+        let runner = FunctionRunner::with_host_isa(func.into_owned(), context.flags.clone());
+        let compiled = runner.compile()?;
+        for command in commands {
+            command.run(|args| compiled.call(args))?;
+        }
         Ok(())
     }
 }
@@ -1019,7 +1019,8 @@ mod tests {
         let shared_builder = settings::builder();
         let shared_flags = settings::Flags::new(shared_builder);
 
-        let triple = ::target_lexicon::Triple::from_str("arm").expect("Couldn't create arm triple");
+        let triple =
+            ::target_lexicon::Triple::from_str("thumbv7a").expect("Couldn't create thumb triple");
 
         let target = isa::lookup(triple)
             .ok()
@@ -1080,7 +1081,8 @@ ebb0:
         let shared_builder = settings::builder();
         let shared_flags = settings::Flags::new(shared_builder);
 
-        let triple = ::target_lexicon::Triple::from_str("arm").expect("Couldn't create arm triple");
+        let triple =
+            ::target_lexicon::Triple::from_str("thumbv7a").expect("Couldn't create thumb triple");
 
         let target = isa::lookup(triple)
             .ok()
@@ -1137,7 +1139,8 @@ ebb0:
         let shared_builder = settings::builder();
         let shared_flags = settings::Flags::new(shared_builder);
 
-        let triple = ::target_lexicon::Triple::from_str("arm").expect("Couldn't create arm triple");
+        let triple =
+            ::target_lexicon::Triple::from_str("thumbv7a").expect("Couldn't create thumb triple");
 
         let target = isa::lookup(triple)
             .ok()
@@ -1197,7 +1200,8 @@ ebb0:
         let shared_builder = settings::builder();
         let shared_flags = settings::Flags::new(shared_builder);
 
-        let triple = ::target_lexicon::Triple::from_str("arm").expect("Couldn't create arm triple");
+        let triple =
+            ::target_lexicon::Triple::from_str("thumbv7a").expect("Couldn't create thumb triple");
 
         let target = isa::lookup(triple)
             .ok()
@@ -1249,7 +1253,8 @@ ebb0:
         let shared_builder = settings::builder();
         let shared_flags = settings::Flags::new(shared_builder);
 
-        let triple = ::target_lexicon::Triple::from_str("arm").expect("Couldn't create arm triple");
+        let triple =
+            ::target_lexicon::Triple::from_str("thumbv7a").expect("Couldn't create thumb triple");
 
         let target = isa::lookup(triple)
             .ok()
@@ -687,6 +687,16 @@ impl SSABuilder {
             }
             // For a single destination appending a jump argument to the instruction
             // is sufficient.
+            // The arguments of an invoke go to the callee, and landing pads can't take any. Like
+            // with jump tables below, we have to split the edge.
+            BranchInfo::SingleDest(_, _) if func.dfg[jump_inst].opcode().is_call() => {
+                let (middle_ebb, middle_block) = self.split_edge(func, jump_inst_block, jump_inst);
+                func.change_branch_destination(jump_inst, middle_ebb);
+                let mut cur = FuncCursor::new(func).at_bottom(middle_ebb);
+                let middle_jump_inst = cur.ins().jump(dest_ebb, &[val]);
+                self.def_var(var, val, middle_block);
+                Some((middle_ebb, middle_block, middle_jump_inst))
+            }
             BranchInfo::SingleDest(_, _) => {
                 func.dfg.append_inst_arg(jump_inst, val);
                 None
@@ -695,11 +705,7 @@ impl SSABuilder {
                 // In the case of a jump table, the situation is tricky because br_table doesn't
                 // support arguments.
                 // We have to split the critical edge
-                let middle_ebb = func.dfg.make_ebb();
-                func.layout.append_ebb(middle_ebb);
-                let middle_block = self.declare_ebb_header_block(middle_ebb);
-                self.blocks[middle_block].add_predecessor(jump_inst_block, jump_inst);
-                self.mark_ebb_header_block_sealed(middle_block);
+                let (middle_ebb, middle_block) = self.split_edge(func, jump_inst_block, jump_inst);
 
                 if let Some(default_ebb) = default_ebb {
                     if dest_ebb == default_ebb {
@@ -728,6 +734,22 @@ impl SSABuilder {
         }
     }
 
+    /// Creates a sealed EBB whose only predecessor is `jump_inst`, to be used as its new
+    /// destination.
+    fn split_edge(
+        &mut self,
+        func: &mut Function,
+        jump_inst_block: Block,
+        jump_inst: Inst,
+    ) -> (Ebb, Block) {
+        let middle_ebb = func.dfg.make_ebb();
+        func.layout.append_ebb(middle_ebb);
+        let middle_block = self.declare_ebb_header_block(middle_ebb);
+        self.blocks[middle_block].add_predecessor(jump_inst_block, jump_inst);
+        self.mark_ebb_header_block_sealed(middle_block);
+        (middle_ebb, middle_block)
+    }
+
     /// Returns the list of `Ebb`s that have been declared as predecessors of the argument.
     fn predecessors(&self, ebb: Ebb) -> &[PredBlock] {
         let block = self.header_block(ebb);
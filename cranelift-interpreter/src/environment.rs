@@ -0,0 +1,88 @@
+//! The set of functions an interpreter can call.
+
+use cranelift_codegen::ir::{FuncRef, Function};
+use cranelift_entity::{entity_impl, PrimaryMap};
+use std::collections::HashMap;
+
+/// An opaque reference to a function in an [Environment].
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FuncIndex(u32);
+entity_impl!(FuncIndex, "fn");
+
+/// A collection of functions, indexed by their name.
+///
+/// Calls from an interpreted function are resolved by looking up the name of the called external
+/// function here, so every function that may be called must be added to the environment.
+pub struct Environment {
+    functions: PrimaryMap<FuncIndex, Function>,
+    function_name_to_index: HashMap<String, FuncIndex>,
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Self {
+            functions: PrimaryMap::new(),
+            function_name_to_index: HashMap::new(),
+        }
+    }
+}
+
+impl From<Function> for Environment {
+    fn from(function: Function) -> Self {
+        let mut env = Self::default();
+        env.add(function);
+        env
+    }
+}
+
+impl Environment {
+    /// Add a function to the environment, keyed by the textual form of its name (e.g. `%foo`).
+    /// A previously added function with the same name is shadowed.
+    pub fn add(&mut self, function: Function) -> FuncIndex {
+        let name = function.name.to_string();
+        let index = self.functions.push(function);
+        self.function_name_to_index.insert(name, index);
+        index
+    }
+
+    /// Retrieve the index of the function with the given name.
+    pub fn index_of(&self, name: &str) -> Option<FuncIndex> {
+        self.function_name_to_index.get(name).cloned()
+    }
+
+    /// Retrieve a function by its index.
+    pub fn get_by_index(&self, index: FuncIndex) -> Option<&Function> {
+        self.functions.get(index)
+    }
+
+    /// Retrieve a function by its name.
+    pub fn get_by_name(&self, name: &str) -> Option<&Function> {
+        self.index_of(name)
+            .and_then(|index| self.get_by_index(index))
+    }
+
+    /// Retrieve the function called through `func_ref` from within `caller`.
+    pub fn get_by_func_ref(&self, func_ref: FuncRef, caller: &Function) -> Option<&Function> {
+        let name = caller.dfg.ext_funcs[func_ref].name.to_string();
+        self.get_by_name(&name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cranelift_codegen::ir::{ExternalName, Signature};
+    use cranelift_codegen::isa::CallConv;
+
+    #[test]
+    fn addition() {
+        let mut env = Environment::default();
+        let name = ExternalName::testcase("test");
+        let signature = Signature::new(CallConv::Fast);
+        let func = Function::with_name_signature(name, signature);
+        let index = env.add(func);
+        assert_eq!(env.index_of("%test"), Some(index));
+        assert!(env.get_by_name("%test").is_some());
+        assert!(env.get_by_name("%missing").is_none());
+    }
+}
@@ -0,0 +1,58 @@
+//! The state of a single function activation.
+
+use cranelift_codegen::data_value::DataValue;
+use cranelift_codegen::ir::{Function, StackSlot, Value};
+use cranelift_entity::SecondaryMap;
+use std::collections::HashMap;
+
+/// The values and stack slots of one function invocation.
+///
+/// Values are looked up after resolving aliases, so an interpreted function behaves the same
+/// whether or not its aliases have been eliminated.
+pub struct Frame<'a> {
+    /// The function being executed.
+    pub function: &'a Function,
+    registers: HashMap<Value, DataValue>,
+    stack_slots: SecondaryMap<StackSlot, u64>,
+}
+
+impl<'a> Frame<'a> {
+    /// Construct a new frame for `function`.
+    pub fn new(function: &'a Function) -> Self {
+        Self {
+            function,
+            registers: HashMap::new(),
+            stack_slots: SecondaryMap::new(),
+        }
+    }
+
+    /// Retrieve the current value of `name`, if it has been assigned.
+    pub fn get(&self, name: Value) -> Option<&DataValue> {
+        let name = self.function.dfg.resolve_aliases(name);
+        self.registers.get(&name)
+    }
+
+    /// Assign `value` to `name`, returning the previously assigned value, if any.
+    pub fn set(&mut self, name: Value, value: DataValue) -> Option<DataValue> {
+        let name = self.function.dfg.resolve_aliases(name);
+        self.registers.insert(name, value)
+    }
+
+    /// Assign each of `values` to the corresponding entry of `names`.
+    pub fn set_all(&mut self, names: &[Value], values: Vec<DataValue>) {
+        debug_assert_eq!(names.len(), values.len());
+        for (&name, value) in names.iter().zip(values) {
+            self.set(name, value);
+        }
+    }
+
+    /// Record the interpreter memory address at which `slot` was allocated.
+    pub fn set_stack_slot_address(&mut self, slot: StackSlot, address: u64) {
+        self.stack_slots[slot] = address;
+    }
+
+    /// Retrieve the interpreter memory address of `slot`.
+    pub fn stack_slot_address(&self, slot: StackSlot) -> u64 {
+        self.stack_slots[slot]
+    }
+}
@@ -0,0 +1,1343 @@
+//! Cranelift IR interpreter.
+//!
+//! The interpreter executes the instructions of an `ir::Function` one at a time, keeping the
+//! value of every SSA value in a [Frame]. Memory accesses are confined to a sandboxed [Memory]
+//! and calls are resolved by name through an [Environment].
+
+use crate::environment::{Environment, FuncIndex};
+use crate::frame::Frame;
+use crate::memory::Memory;
+use crate::value::{
+    from_bits, from_lanes, from_le_bytes, is_true, mask, sign_extend, to_bits, to_lanes,
+    to_le_bytes, to_signed, ValueError,
+};
+use cranelift_codegen::data_value::DataValue;
+use cranelift_codegen::ir::condcodes::{FloatCC, IntCC};
+use cranelift_codegen::ir::immediates::Offset32;
+use cranelift_codegen::ir::{
+    types, Ebb, Function, Inst, InstructionData, Opcode, TrapCode, Type, Value as ValueRef,
+};
+use log::trace;
+use thiserror::Error;
+
+/// The maximum number of nested calls. Deeper recursion traps with `TrapCode::StackOverflow`,
+/// like it would in native code, rather than overflowing the interpreter's own stack.
+pub const MAX_CALL_DEPTH: usize = 1000;
+
+/// The outcome of calling a function.
+#[derive(Debug, PartialEq)]
+pub enum ControlFlow {
+    /// The function returned these values.
+    Return(Vec<DataValue>),
+    /// The function, or one of the functions it called, trapped.
+    Trap(TrapCode),
+}
+
+/// An error that prevents the interpreter from executing a function.
+///
+/// Unlike a trap, which is a legitimate outcome of executing valid code, an error means the
+/// function is malformed or uses a feature the interpreter does not implement.
+#[derive(Error, Debug)]
+pub enum InterpreterError {
+    /// No function with this name has been added to the environment.
+    #[error("unknown function: {0}")]
+    UnknownFunction(String),
+    /// A function was called with the wrong number of arguments.
+    #[error("{name} expects {expected} arguments but was called with {actual}")]
+    WrongArgumentCount {
+        /// The called function.
+        name: String,
+        /// The number of parameters of its entry block.
+        expected: usize,
+        /// The number of arguments passed.
+        actual: usize,
+    },
+    /// The called function has no instructions.
+    #[error("{0} has no entry block")]
+    NoEntryBlock(String),
+    /// A value was used before any instruction defined it.
+    #[error("{0} is used before it is defined")]
+    UndefinedValue(ValueRef),
+    /// Execution reached the end of an EBB without a terminator.
+    #[error("{0} ends without a terminator")]
+    MissingTerminator(Ebb),
+    /// The interpreter cannot execute this instruction.
+    #[error("the interpreter does not support `{0}`")]
+    Unsupported(Opcode),
+    /// A value had the wrong type for its use.
+    #[error("{0}")]
+    Value(#[from] ValueError),
+}
+
+/// Apply a floating point operation lane-wise, instantiating the closure body for both `f32`
+/// and `f64` lanes.
+macro_rules! float_op {
+    ($ty:expr, $args:expr, |$($arg:ident),+| $body:expr) => {
+        lanewise($ty, $ty, &$args, |lt, lanes| {
+            let mut operands = lanes.iter();
+            Ok(match lt {
+                types::F32 => {
+                    $(let $arg = float_value(operands.next().unwrap(), lt)? as f32;)+
+                    DataValue::from($body)
+                }
+                _ => {
+                    $(let $arg = float_value(operands.next().unwrap(), lt)?;)+
+                    DataValue::from($body)
+                }
+            })
+        })
+    };
+}
+
+/// Executes functions from an [Environment].
+pub struct Interpreter {
+    env: Environment,
+    memory: Memory,
+}
+
+impl Interpreter {
+    /// Create an interpreter for the functions in `env`, with a default-sized memory.
+    pub fn new(env: Environment) -> Self {
+        Self::with_memory(env, Memory::default())
+    }
+
+    /// Create an interpreter for the functions in `env` that uses `memory` for heap and stack
+    /// accesses.
+    pub fn with_memory(env: Environment, memory: Memory) -> Self {
+        Self { env, memory }
+    }
+
+    /// The memory accessed by interpreted functions.
+    pub fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    /// The memory accessed by interpreted functions, mutably.
+    pub fn memory_mut(&mut self) -> &mut Memory {
+        &mut self.memory
+    }
+
+    /// Call the function named `name` (e.g. `%foo`) with `arguments`.
+    pub fn call_by_name(
+        &mut self,
+        name: &str,
+        arguments: &[DataValue],
+    ) -> Result<ControlFlow, InterpreterError> {
+        let index = self
+            .env
+            .index_of(name)
+            .ok_or_else(|| InterpreterError::UnknownFunction(name.to_string()))?;
+        self.call_by_index(index, arguments)
+    }
+
+    /// Call the function at `index` with `arguments`.
+    pub fn call_by_index(
+        &mut self,
+        index: FuncIndex,
+        arguments: &[DataValue],
+    ) -> Result<ControlFlow, InterpreterError> {
+        let function = self
+            .env
+            .get_by_index(index)
+            .ok_or_else(|| InterpreterError::UnknownFunction(index.to_string()))?;
+        let mut executor = Executor {
+            env: &self.env,
+            memory: &mut self.memory,
+        };
+        match executor.call(function, arguments) {
+            Ok(results) => Ok(ControlFlow::Return(results)),
+            Err(Stop::Trap(code)) => Ok(ControlFlow::Trap(code)),
+            Err(Stop::Error(e)) => Err(e),
+        }
+    }
+}
+
+/// The reason execution of a function stopped before it returned.
+enum Stop {
+    Trap(TrapCode),
+    Error(InterpreterError),
+}
+
+impl From<TrapCode> for Stop {
+    fn from(code: TrapCode) -> Self {
+        Stop::Trap(code)
+    }
+}
+
+impl From<InterpreterError> for Stop {
+    fn from(e: InterpreterError) -> Self {
+        Stop::Error(e)
+    }
+}
+
+impl From<ValueError> for Stop {
+    fn from(e: ValueError) -> Self {
+        Stop::Error(e.into())
+    }
+}
+
+/// Where execution continues after an instruction.
+enum Next<'a> {
+    /// The next instruction in the EBB.
+    Continue,
+    /// The start of an EBB, with these EBB arguments.
+    Jump(Ebb, Vec<DataValue>),
+    /// The entry block of a called function, with these arguments. Execution resumes after the
+    /// call instruction once the callee returns.
+    Call(&'a Function, Vec<DataValue>),
+    /// The caller, with these return values.
+    Return(Vec<DataValue>),
+}
+
+/// A function invocation on the interpreter's call stack.
+struct Activation<'a> {
+    frame: Frame<'a>,
+    /// The instruction to execute next, or the call instruction this activation is waiting on.
+    position: Option<Inst>,
+    /// The EBB containing `position`.
+    ebb: Ebb,
+    /// The interpreter stack pointer before this activation allocated its stack slots.
+    stack_pointer: usize,
+}
+
+/// The state of an in-progress call to `Interpreter::call_by_index`.
+///
+/// Calls made by interpreted code are kept on an explicit stack rather than by recursing, so
+/// deep recursion in the interpreted program can't overflow the interpreter's own stack.
+struct Executor<'a> {
+    env: &'a Environment,
+    memory: &'a mut Memory,
+}
+
+impl<'a> Executor<'a> {
+    fn call(
+        &mut self,
+        function: &'a Function,
+        arguments: &[DataValue],
+    ) -> Result<Vec<DataValue>, Stop> {
+        let stack_pointer = self.memory.stack_pointer();
+        let result = self.run(function, arguments.to_vec());
+        self.memory.restore_stack_pointer(stack_pointer);
+        result
+    }
+
+    fn run(
+        &mut self,
+        function: &'a Function,
+        arguments: Vec<DataValue>,
+    ) -> Result<Vec<DataValue>, Stop> {
+        let mut stack = vec![self.enter(function, arguments)?];
+        loop {
+            let activation = stack.last_mut().unwrap();
+            let inst = activation
+                .position
+                .ok_or(InterpreterError::MissingTerminator(activation.ebb))?;
+            let layout = &activation.frame.function.layout;
+            match self.inst(&mut activation.frame, inst)? {
+                Next::Continue => activation.position = layout.next_inst(inst),
+                Next::Jump(ebb, arguments) => {
+                    trace!("Ebb: {}", ebb);
+                    let params = activation.frame.function.dfg.ebb_params(ebb);
+                    activation.frame.set_all(params, arguments);
+                    activation.position = layout.first_inst(ebb);
+                    activation.ebb = ebb;
+                }
+                Next::Call(callee, arguments) => {
+                    if stack.len() == MAX_CALL_DEPTH {
+                        return Err(TrapCode::StackOverflow.into());
+                    }
+                    let callee = self.enter(callee, arguments)?;
+                    stack.push(callee);
+                }
+                Next::Return(results) => {
+                    let callee = stack.pop().unwrap();
+                    self.memory.restore_stack_pointer(callee.stack_pointer);
+                    let caller = match stack.last_mut() {
+                        Some(caller) => caller,
+                        None => return Ok(results),
+                    };
+                    let call = caller.position.unwrap();
+                    let dfg = &caller.frame.function.dfg;
+                    caller.frame.set_all(dfg.inst_results(call), results);
+                    caller.position = caller.frame.function.layout.next_inst(call);
+                }
+            }
+        }
+    }
+
+    /// Check the arguments of a call to `function` and set up its activation.
+    fn enter(
+        &mut self,
+        function: &'a Function,
+        arguments: Vec<DataValue>,
+    ) -> Result<Activation<'a>, Stop> {
+        trace!("Call: {}({:?})", function.name, arguments);
+        let entry = function
+            .layout
+            .entry_block()
+            .ok_or_else(|| InterpreterError::NoEntryBlock(function.name.to_string()))?;
+        let params = function.dfg.ebb_params(entry);
+        if params.len() != arguments.len() {
+            return Err(InterpreterError::WrongArgumentCount {
+                name: function.name.to_string(),
+                expected: params.len(),
+                actual: arguments.len(),
+            }
+            .into());
+        }
+        for (&param, argument) in params.iter().zip(&arguments) {
+            to_bits(argument, function.dfg.value_type(param))?;
+        }
+
+        let stack_pointer = self.memory.stack_pointer();
+        let mut frame = Frame::new(function);
+        for (slot, data) in function.stack_slots.iter() {
+            let address = self.memory.allocate_stack(data.size as usize)?;
+            frame.set_stack_slot_address(slot, address);
+        }
+        frame.set_all(params, arguments);
+        Ok(Activation {
+            frame,
+            position: function.layout.first_inst(entry),
+            ebb: entry,
+            stack_pointer,
+        })
+    }
+
+    fn inst(&mut self, frame: &mut Frame<'a>, inst: Inst) -> Result<Next<'a>, Stop> {
+        let function = frame.function;
+        let dfg = &function.dfg;
+        trace!("Inst: {}", dfg.display_inst(inst, None));
+
+        let data = &dfg[inst];
+        let get = |value: ValueRef| {
+            frame
+                .get(value)
+                .cloned()
+                .ok_or(Stop::Error(InterpreterError::UndefinedValue(value)))
+        };
+        let mut args = dfg
+            .inst_fixed_args(inst)
+            .iter()
+            .map(|&v| get(v))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut arg_types = dfg
+            .inst_fixed_args(inst)
+            .iter()
+            .map(|&v| dfg.value_type(v))
+            .collect::<Vec<_>>();
+        let varargs = dfg
+            .inst_variable_args(inst)
+            .iter()
+            .map(|&v| get(v))
+            .collect::<Result<Vec<_>, _>>()?;
+        let vararg_types = dfg
+            .inst_variable_args(inst)
+            .iter()
+            .map(|&v| dfg.value_type(v))
+            .collect::<Vec<_>>();
+        let results = dfg.inst_results(inst);
+        let result_type = results
+            .first()
+            .map_or(types::INVALID, |&r| dfg.value_type(r));
+        let ctrl_type = dfg.ctrl_typevar(inst);
+
+        // Instructions with an immediate operand behave like their two-operand forms.
+        let mut opcode = data.opcode();
+        match *data {
+            InstructionData::BinaryImm { imm, .. } | InstructionData::IntCompareImm { imm, .. } => {
+                let imm: i64 = imm.into();
+                // Vector operations apply the immediate to every lane.
+                let imm_type = ctrl_type.lane_type();
+                let imm = from_bits(imm as u128, imm_type);
+                if opcode == Opcode::IrsubImm {
+                    args.insert(0, imm);
+                    arg_types.insert(0, imm_type);
+                } else {
+                    args.push(imm);
+                    arg_types.push(imm_type);
+                }
+                opcode = binary_form(opcode);
+            }
+            _ => {}
+        }
+
+        let values = match opcode {
+            Opcode::Nop | Opcode::Debugtrap => vec![],
+            Opcode::Copy | Opcode::Spill | Opcode::Fill => vec![args[0].clone()],
+
+            // Constants.
+            Opcode::Iconst => match *data {
+                InstructionData::UnaryImm { imm, .. } => {
+                    let imm: i64 = imm.into();
+                    vec![from_bits(imm as u128, result_type)]
+                }
+                _ => unreachable!(),
+            },
+            Opcode::F32const => match *data {
+                InstructionData::UnaryIeee32 { imm, .. } => vec![DataValue::F32(imm)],
+                _ => unreachable!(),
+            },
+            Opcode::F64const => match *data {
+                InstructionData::UnaryIeee64 { imm, .. } => vec![DataValue::F64(imm)],
+                _ => unreachable!(),
+            },
+            Opcode::Bconst => match *data {
+                InstructionData::UnaryBool { imm, .. } => vec![DataValue::B(imm)],
+                _ => unreachable!(),
+            },
+            Opcode::Vconst => match *data {
+                InstructionData::UnaryConst {
+                    constant_handle, ..
+                } => {
+                    let bytes = dfg.constants.get(constant_handle).iter().cloned();
+                    vec![from_le_bytes(&bytes.collect::<Vec<_>>(), result_type)]
+                }
+                _ => unreachable!(),
+            },
+
+            // Control flow.
+            Opcode::Jump | Opcode::Fallthrough => {
+                let destination = data.branch_destination().unwrap();
+                return Ok(Next::Jump(destination, varargs));
+            }
+            Opcode::Brz | Opcode::Brnz | Opcode::BrIcmp => {
+                let taken = match *data {
+                    InstructionData::BranchIcmp { cond, .. } => {
+                        icmp(cond, arg_types[0], &args[0], &args[1])?
+                    }
+                    _ => is_true(&args[0]) == (opcode == Opcode::Brnz),
+                };
+                if taken {
+                    let destination = data.branch_destination().unwrap();
+                    return Ok(Next::Jump(destination, varargs));
+                }
+                vec![]
+            }
+            Opcode::BrTable => match *data {
+                InstructionData::BranchTable {
+                    destination, table, ..
+                } => {
+                    let index = to_bits(&args[0], arg_types[0])?;
+                    let entries = function.jump_tables[table].as_slice();
+                    let destination = entries.get(index as usize).map_or(destination, |&e| e);
+                    return Ok(Next::Jump(destination, vec![]));
+                }
+                _ => unreachable!(),
+            },
+            Opcode::Return | Opcode::FallthroughReturn => return Ok(Next::Return(varargs)),
+            Opcode::Trap | Opcode::ResumableTrap => match *data {
+                InstructionData::Trap { code, .. } => return Err(code.into()),
+                _ => unreachable!(),
+            },
+            Opcode::Trapz | Opcode::Trapnz => match *data {
+                InstructionData::CondTrap { code, .. } => {
+                    if is_true(&args[0]) == (opcode == Opcode::Trapnz) {
+                        return Err(code.into());
+                    }
+                    vec![]
+                }
+                _ => unreachable!(),
+            },
+            // An `invoke` can't unwind here since the interpreter has no exceptions, so it
+            // behaves like a `call`.
+            Opcode::Call | Opcode::Invoke => {
+                let func_ref = match *data {
+                    InstructionData::Call { func_ref, .. }
+                    | InstructionData::Invoke { func_ref, .. } => func_ref,
+                    _ => unreachable!(),
+                };
+                let callee = self
+                    .env
+                    .get_by_func_ref(func_ref, function)
+                    .ok_or_else(|| {
+                        InterpreterError::UnknownFunction(dfg.ext_funcs[func_ref].name.to_string())
+                    })?;
+                return Ok(Next::Call(callee, varargs));
+            }
+
+            // Memory.
+            Opcode::Load
+            | Opcode::LoadComplex
+            | Opcode::Uload8
+            | Opcode::Uload8Complex
+            | Opcode::Sload8
+            | Opcode::Sload8Complex
+            | Opcode::Uload16
+            | Opcode::Uload16Complex
+            | Opcode::Sload16
+            | Opcode::Sload16Complex
+            | Opcode::Uload32
+            | Opcode::Uload32Complex
+            | Opcode::Sload32
+            | Opcode::Sload32Complex => {
+                let offset = match *data {
+                    InstructionData::Load { offset, .. }
+                    | InstructionData::LoadComplex { offset, .. } => offset,
+                    _ => unreachable!(),
+                };
+                let operands = args.iter().chain(&varargs);
+                let address = address(operands.zip(arg_types.iter().chain(&vararg_types)), offset)?;
+                vec![self.load(opcode, address, result_type)?]
+            }
+            Opcode::Store
+            | Opcode::StoreComplex
+            | Opcode::Istore8
+            | Opcode::Istore8Complex
+            | Opcode::Istore16
+            | Opcode::Istore16Complex
+            | Opcode::Istore32
+            | Opcode::Istore32Complex => {
+                let offset = match *data {
+                    InstructionData::Store { offset, .. }
+                    | InstructionData::StoreComplex { offset, .. } => offset,
+                    _ => unreachable!(),
+                };
+                let operands = args[1..].iter().chain(&varargs);
+                let address = address(
+                    operands.zip(arg_types[1..].iter().chain(&vararg_types)),
+                    offset,
+                )?;
+                self.store(opcode, address, &args[0], arg_types[0])?;
+                vec![]
+            }
+            Opcode::StackLoad => match *data {
+                InstructionData::StackLoad {
+                    stack_slot, offset, ..
+                } => {
+                    let address = offset_address(frame.stack_slot_address(stack_slot), offset);
+                    vec![self.load(opcode, address, result_type)?]
+                }
+                _ => unreachable!(),
+            },
+            Opcode::StackStore => match *data {
+                InstructionData::StackStore {
+                    stack_slot, offset, ..
+                } => {
+                    let address = offset_address(frame.stack_slot_address(stack_slot), offset);
+                    self.store(opcode, address, &args[0], arg_types[0])?;
+                    vec![]
+                }
+                _ => unreachable!(),
+            },
+            Opcode::StackAddr => match *data {
+                InstructionData::StackLoad {
+                    stack_slot, offset, ..
+                } => {
+                    let address = offset_address(frame.stack_slot_address(stack_slot), offset);
+                    vec![from_bits(u128::from(address), result_type)]
+                }
+                _ => unreachable!(),
+            },
+
+            // Selection.
+            Opcode::Select => {
+                let choice = if is_true(&args[0]) {
+                    &args[1]
+                } else {
+                    &args[2]
+                };
+                vec![choice.clone()]
+            }
+            Opcode::Vselect => vec![lanewise(ctrl_type, ctrl_type, &args, |_, l| {
+                Ok(if is_true(&l[0]) {
+                    l[1].clone()
+                } else {
+                    l[2].clone()
+                })
+            })?],
+            Opcode::Bitselect => {
+                let c = to_bits(&args[0], ctrl_type)?;
+                let x = to_bits(&args[1], ctrl_type)?;
+                let y = to_bits(&args[2], ctrl_type)?;
+                vec![from_bits((c & x) | (!c & y), ctrl_type)]
+            }
+
+            // Integer arithmetic.
+            Opcode::Iadd => vec![int_op(
+                ctrl_type,
+                &args,
+                |_, v| Ok(v[0].wrapping_add(v[1])),
+            )?],
+            Opcode::Isub => vec![int_op(
+                ctrl_type,
+                &args,
+                |_, v| Ok(v[0].wrapping_sub(v[1])),
+            )?],
+            Opcode::Imul => vec![int_op(
+                ctrl_type,
+                &args,
+                |_, v| Ok(v[0].wrapping_mul(v[1])),
+            )?],
+            Opcode::Ineg => vec![int_op(ctrl_type, &args, |_, v| {
+                Ok(0u128.wrapping_sub(v[0]))
+            })?],
+            Opcode::Umulhi | Opcode::Smulhi => {
+                if ctrl_type.lane_bits() > 64 {
+                    return Err(InterpreterError::Unsupported(opcode).into());
+                }
+                vec![int_op(ctrl_type, &args, |w, v| {
+                    Ok(if opcode == Opcode::Umulhi {
+                        (v[0] * v[1]) >> w
+                    } else {
+                        ((sign_extend(v[0], w) * sign_extend(v[1], w)) >> w) as u128
+                    })
+                })?]
+            }
+            Opcode::Udiv | Opcode::Urem => vec![int_op(ctrl_type, &args, |_, v| {
+                if v[1] == 0 {
+                    Err(TrapCode::IntegerDivisionByZero.into())
+                } else if opcode == Opcode::Udiv {
+                    Ok(v[0] / v[1])
+                } else {
+                    Ok(v[0] % v[1])
+                }
+            })?],
+            Opcode::Sdiv | Opcode::Srem => vec![int_op(ctrl_type, &args, |w, v| {
+                let (x, y) = (sign_extend(v[0], w), sign_extend(v[1], w));
+                let (min, _) = signed_range(w);
+                if y == 0 {
+                    Err(TrapCode::IntegerDivisionByZero.into())
+                } else if opcode == Opcode::Srem {
+                    Ok(x.wrapping_rem(y) as u128)
+                } else if x == min && y == -1 {
+                    Err(TrapCode::IntegerOverflow.into())
+                } else {
+                    Ok(x.wrapping_div(y) as u128)
+                }
+            })?],
+            Opcode::UaddSat => vec![int_op(ctrl_type, &args, |w, v| {
+                Ok(v[0].checked_add(v[1]).map_or(mask(w), |s| s.min(mask(w))))
+            })?],
+            Opcode::UsubSat => {
+                vec![int_op(ctrl_type, &args, |_, v| {
+                    Ok(v[0].saturating_sub(v[1]))
+                })?]
+            }
+            Opcode::SaddSat | Opcode::SsubSat => vec![int_op(ctrl_type, &args, |w, v| {
+                let (x, y) = (sign_extend(v[0], w), sign_extend(v[1], w));
+                let (min, max) = signed_range(w);
+                let result = if opcode == Opcode::SaddSat {
+                    x.checked_add(y)
+                } else {
+                    x.checked_sub(y)
+                };
+                let result = result.unwrap_or(if (y < 0) == (opcode == Opcode::SaddSat) {
+                    min
+                } else {
+                    max
+                });
+                Ok(result.max(min).min(max) as u128)
+            })?],
+            Opcode::AvgRound => vec![int_op(ctrl_type, &args, |_, v| {
+                Ok((v[0] >> 1) + (v[1] >> 1) + ((v[0] | v[1]) & 1))
+            })?],
+            Opcode::IaddCin
+            | Opcode::IaddCout
+            | Opcode::IaddCarry
+            | Opcode::IsubBin
+            | Opcode::IsubBout
+            | Opcode::IsubBorrow => {
+                let w = u32::from(ctrl_type.bits());
+                let x = to_bits(&args[0], ctrl_type)?;
+                let y = to_bits(&args[1], ctrl_type)?;
+                let c = args.get(2).map_or(0, |c| is_true(c) as u128);
+                let (value, carry) = match opcode {
+                    Opcode::IaddCin | Opcode::IaddCout | Opcode::IaddCarry => {
+                        let (sum, overflow1) = x.overflowing_add(y);
+                        let (sum, overflow2) = sum.overflowing_add(c);
+                        (sum, overflow1 || overflow2 || sum > mask(w))
+                    }
+                    _ => {
+                        let (difference, borrow1) = x.overflowing_sub(y);
+                        let (difference, borrow2) = difference.overflowing_sub(c);
+                        (difference, borrow1 || borrow2)
+                    }
+                };
+                let value = from_bits(value, ctrl_type);
+                if results.len() == 2 {
+                    vec![value, DataValue::B(carry)]
+                } else {
+                    vec![value]
+                }
+            }
+
+            // Bitwise operations, which also apply to booleans and floats.
+            Opcode::Band => vec![int_op(ctrl_type, &args, |_, v| Ok(v[0] & v[1]))?],
+            Opcode::Bor => vec![int_op(ctrl_type, &args, |_, v| Ok(v[0] | v[1]))?],
+            Opcode::Bxor => vec![int_op(ctrl_type, &args, |_, v| Ok(v[0] ^ v[1]))?],
+            Opcode::Bnot => vec![int_op(ctrl_type, &args, |_, v| Ok(!v[0]))?],
+            Opcode::BandNot => vec![int_op(ctrl_type, &args, |_, v| Ok(v[0] & !v[1]))?],
+            Opcode::BorNot => vec![int_op(ctrl_type, &args, |_, v| Ok(v[0] | !v[1]))?],
+            Opcode::BxorNot => vec![int_op(ctrl_type, &args, |_, v| Ok(v[0] ^ !v[1]))?],
+            Opcode::Ishl | Opcode::Ushr | Opcode::Sshr | Opcode::Rotl | Opcode::Rotr => {
+                vec![lanewise(ctrl_type, ctrl_type, &args, |lt, l| {
+                    let w = u32::from(lt.bits());
+                    let x = to_bits(&l[0], lt)?;
+                    let amount = l[1].as_i128().ok_or_else(|| ValueError {
+                        ty: lt,
+                        value: l[1].clone(),
+                    })? as u32
+                        & (w - 1);
+                    let bits = match opcode {
+                        Opcode::Ishl => x << amount,
+                        Opcode::Ushr => x >> amount,
+                        Opcode::Sshr => (sign_extend(x, w) >> amount) as u128,
+                        _ if amount == 0 => x,
+                        Opcode::Rotl => (x << amount) | (x >> (w - amount)),
+                        _ => (x >> amount) | (x << (w - amount)),
+                    };
+                    Ok(from_bits(bits, lt))
+                })?]
+            }
+            Opcode::Bitrev => vec![int_op(ctrl_type, &args, |w, v| {
+                Ok(v[0].reverse_bits() >> (128 - w))
+            })?],
+            Opcode::Clz => vec![int_op(ctrl_type, &args, |w, v| {
+                Ok(u128::from(v[0].leading_zeros() - (128 - w)))
+            })?],
+            Opcode::Cls => vec![int_op(ctrl_type, &args, |w, v| {
+                let x = sign_extend(v[0], w);
+                let x = if x < 0 { !x } else { x };
+                Ok(u128::from(x.leading_zeros() - (128 - w) - 1))
+            })?],
+            Opcode::Ctz => vec![int_op(ctrl_type, &args, |w, v| {
+                Ok(u128::from(v[0].trailing_zeros().min(w)))
+            })?],
+            Opcode::Popcnt => {
+                vec![int_op(ctrl_type, &args, |_, v| {
+                    Ok(u128::from(v[0].count_ones()))
+                })?]
+            }
+
+            // Comparisons.
+            Opcode::Icmp => {
+                let cond = match *data {
+                    InstructionData::IntCompare { cond, .. }
+                    | InstructionData::IntCompareImm { cond, .. } => cond,
+                    _ => unreachable!(),
+                };
+                vec![lanewise(ctrl_type, result_type, &args, |lt, l| {
+                    Ok(DataValue::B(icmp(cond, lt, &l[0], &l[1])?))
+                })?]
+            }
+            Opcode::Fcmp => {
+                let cond = match *data {
+                    InstructionData::FloatCompare { cond, .. } => cond,
+                    _ => unreachable!(),
+                };
+                vec![lanewise(ctrl_type, result_type, &args, |lt, l| {
+                    let x = float_value(&l[0], lt)?;
+                    let y = float_value(&l[1], lt)?;
+                    Ok(DataValue::B(fcmp(cond, x, y)))
+                })?]
+            }
+
+            // Floating point arithmetic.
+            Opcode::Fadd => vec![float_op!(ctrl_type, args, |a, b| a + b)?],
+            Opcode::Fsub => vec![float_op!(ctrl_type, args, |a, b| a - b)?],
+            Opcode::Fmul => vec![float_op!(ctrl_type, args, |a, b| a * b)?],
+            Opcode::Fdiv => vec![float_op!(ctrl_type, args, |a, b| a / b)?],
+            Opcode::Fma => vec![float_op!(ctrl_type, args, |a, b, c| a.mul_add(b, c))?],
+            Opcode::Sqrt => vec![float_op!(ctrl_type, args, |a| a.sqrt())?],
+            Opcode::Fneg => vec![float_op!(ctrl_type, args, |a| -a)?],
+            Opcode::Fabs => vec![float_op!(ctrl_type, args, |a| a.abs())?],
+            Opcode::Fcopysign => vec![float_op!(ctrl_type, args, |a, b| a.copysign(b))?],
+            Opcode::Ceil => vec![float_op!(ctrl_type, args, |a| a.ceil())?],
+            Opcode::Floor => vec![float_op!(ctrl_type, args, |a| a.floor())?],
+            Opcode::Trunc => vec![float_op!(ctrl_type, args, |a| a.trunc())?],
+            // Round to nearest, ties to even.
+            Opcode::Nearest => vec![float_op!(ctrl_type, args, |a| {
+                if (a - a.trunc()).abs() == 0.5 {
+                    2.0 * (a / 2.0).round()
+                } else {
+                    a.round()
+                }
+            })?],
+            // NaNs propagate and -0.0 is ordered before +0.0.
+            Opcode::Fmin => vec![float_op!(ctrl_type, args, |a, b| {
+                if a.is_nan() || b.is_nan() {
+                    a + b
+                } else if a == b {
+                    if a.is_sign_negative() {
+                        a
+                    } else {
+                        b
+                    }
+                } else {
+                    a.min(b)
+                }
+            })?],
+            Opcode::Fmax => vec![float_op!(ctrl_type, args, |a, b| {
+                if a.is_nan() || b.is_nan() {
+                    a + b
+                } else if a == b {
+                    if a.is_sign_negative() {
+                        b
+                    } else {
+                        a
+                    }
+                } else {
+                    a.max(b)
+                }
+            })?],
+
+            // Conversions.
+            Opcode::Bitcast | Opcode::RawBitcast => {
+                vec![from_bits(to_bits(&args[0], arg_types[0])?, result_type)]
+            }
+            Opcode::Ireduce | Opcode::Uextend | Opcode::Breduce | Opcode::Bextend => {
+                vec![convert(
+                    arg_types[0],
+                    result_type,
+                    &args[0],
+                    |lt, out, x| Ok(from_bits(to_bits(x, lt)?, out)),
+                )?]
+            }
+            Opcode::Sextend => vec![convert(
+                arg_types[0],
+                result_type,
+                &args[0],
+                |lt, out, x| Ok(from_bits(to_signed(x, lt)? as u128, out)),
+            )?],
+            Opcode::Bint => vec![convert(
+                arg_types[0],
+                result_type,
+                &args[0],
+                |_, out, x| Ok(from_bits(is_true(x) as u128, out)),
+            )?],
+            Opcode::Bmask => vec![convert(
+                arg_types[0],
+                result_type,
+                &args[0],
+                |_, out, x| {
+                    Ok(from_bits(
+                        if is_true(x) { u128::max_value() } else { 0 },
+                        out,
+                    ))
+                },
+            )?],
+            Opcode::Fpromote | Opcode::Fdemote => {
+                vec![convert(
+                    arg_types[0],
+                    result_type,
+                    &args[0],
+                    |lt, out, x| {
+                        let x = float_value(x, lt)?;
+                        Ok(if out == types::F32 {
+                            DataValue::from(x as f32)
+                        } else {
+                            DataValue::from(x)
+                        })
+                    },
+                )?]
+            }
+            Opcode::FcvtToUint
+            | Opcode::FcvtToUintSat
+            | Opcode::FcvtToSint
+            | Opcode::FcvtToSintSat => {
+                let signed = opcode == Opcode::FcvtToSint || opcode == Opcode::FcvtToSintSat;
+                let saturate = opcode == Opcode::FcvtToUintSat || opcode == Opcode::FcvtToSintSat;
+                vec![convert(
+                    arg_types[0],
+                    result_type,
+                    &args[0],
+                    |lt, out, x| fcvt_to_int(float_value(x, lt)?, out, signed, saturate),
+                )?]
+            }
+            Opcode::FcvtFromUint | Opcode::FcvtFromSint => {
+                vec![convert(
+                    arg_types[0],
+                    result_type,
+                    &args[0],
+                    |lt, out, x| {
+                        let bits = to_bits(x, lt)?;
+                        let signed = sign_extend(bits, u32::from(lt.bits()));
+                        Ok(match (opcode, out) {
+                            (Opcode::FcvtFromUint, types::F32) => DataValue::from(bits as f32),
+                            (Opcode::FcvtFromUint, _) => DataValue::from(bits as f64),
+                            (_, types::F32) => DataValue::from(signed as f32),
+                            (_, _) => DataValue::from(signed as f64),
+                        })
+                    },
+                )?]
+            }
+            Opcode::Isplit => {
+                let bits = to_bits(&args[0], arg_types[0])?;
+                let half = u32::from(result_type.bits());
+                vec![
+                    from_bits(bits, result_type),
+                    from_bits(bits >> half, result_type),
+                ]
+            }
+            Opcode::Iconcat => {
+                let low = to_bits(&args[0], arg_types[0])?;
+                let high = to_bits(&args[1], arg_types[1])?;
+                let half = u32::from(arg_types[0].bits());
+                vec![from_bits(low | (high << half), result_type)]
+            }
+
+            // Vector lane manipulation.
+            Opcode::Splat => {
+                let lanes = vec![args[0].clone(); usize::from(result_type.lane_count())];
+                vec![from_lanes(&lanes, result_type)?]
+            }
+            Opcode::ScalarToVector => {
+                let mut lanes = vec![
+                    from_bits(0, result_type.lane_type());
+                    usize::from(result_type.lane_count())
+                ];
+                lanes[0] = args[0].clone();
+                vec![from_lanes(&lanes, result_type)?]
+            }
+            Opcode::Insertlane => match *data {
+                InstructionData::InsertLane { lane, .. } => {
+                    let mut lanes = to_lanes(&args[0], arg_types[0])?;
+                    lanes[usize::from(lane)] = args[1].clone();
+                    vec![from_lanes(&lanes, arg_types[0])?]
+                }
+                _ => unreachable!(),
+            },
+            Opcode::Extractlane => match *data {
+                InstructionData::ExtractLane { lane, .. } => {
+                    vec![to_lanes(&args[0], arg_types[0])?[usize::from(lane)].clone()]
+                }
+                _ => unreachable!(),
+            },
+            Opcode::VanyTrue => {
+                let lanes = to_lanes(&args[0], arg_types[0])?;
+                vec![DataValue::B(lanes.iter().any(is_true))]
+            }
+            Opcode::VallTrue => {
+                let lanes = to_lanes(&args[0], arg_types[0])?;
+                vec![DataValue::B(lanes.iter().all(is_true))]
+            }
+            Opcode::Shuffle | Opcode::Swizzle => {
+                let a = to_bits(&args[0], arg_types[0])?.to_le_bytes();
+                let (b, indices) = match *data {
+                    InstructionData::Shuffle { mask, .. } => {
+                        let b = to_bits(&args[1], arg_types[1])?.to_le_bytes();
+                        (b, dfg.immediates[mask].iter().cloned().collect::<Vec<_>>())
+                    }
+                    _ => (
+                        [0; 16],
+                        to_bits(&args[1], arg_types[1])?.to_le_bytes().to_vec(),
+                    ),
+                };
+                let bytes = indices
+                    .iter()
+                    .map(|&i| match i {
+                        0..=15 => a[usize::from(i)],
+                        16..=31 => b[usize::from(i) - 16],
+                        _ => 0,
+                    })
+                    .collect::<Vec<_>>();
+                vec![from_le_bytes(&bytes, result_type)]
+            }
+
+            _ => return Err(InterpreterError::Unsupported(opcode).into()),
+        };
+
+        frame.set_all(results, values);
+        Ok(Next::Continue)
+    }
+
+    /// Execute one of the load instructions, reading a value of type `ty` from `address`.
+    fn load(&self, opcode: Opcode, address: u64, ty: Type) -> Result<DataValue, Stop> {
+        let (size, signed) = match opcode {
+            Opcode::Uload8 | Opcode::Uload8Complex => (1, false),
+            Opcode::Sload8 | Opcode::Sload8Complex => (1, true),
+            Opcode::Uload16 | Opcode::Uload16Complex => (2, false),
+            Opcode::Sload16 | Opcode::Sload16Complex => (2, true),
+            Opcode::Uload32 | Opcode::Uload32Complex => (4, false),
+            Opcode::Sload32 | Opcode::Sload32Complex => (4, true),
+            _ => (ty.bytes() as usize, false),
+        };
+        let mut buffer = [0; 16];
+        buffer[..size].copy_from_slice(self.memory.load(address, size)?);
+        let bits = u128::from_le_bytes(buffer);
+        if signed {
+            Ok(from_bits(sign_extend(bits, size as u32 * 8) as u128, ty))
+        } else {
+            Ok(from_bits(bits, ty))
+        }
+    }
+
+    /// Execute one of the store instructions, writing `value` of type `ty` to `address`.
+    fn store(
+        &mut self,
+        opcode: Opcode,
+        address: u64,
+        value: &DataValue,
+        ty: Type,
+    ) -> Result<(), Stop> {
+        let bytes = to_le_bytes(value, ty)?;
+        let size = match opcode {
+            Opcode::Istore8 | Opcode::Istore8Complex => 1,
+            Opcode::Istore16 | Opcode::Istore16Complex => 2,
+            Opcode::Istore32 | Opcode::Istore32Complex => 4,
+            _ => bytes.len(),
+        };
+        Ok(self.memory.store(address, &bytes[..size])?)
+    }
+}
+
+/// Map an instruction with an immediate operand to the equivalent two-operand instruction.
+fn binary_form(opcode: Opcode) -> Opcode {
+    match opcode {
+        Opcode::IaddImm => Opcode::Iadd,
+        Opcode::ImulImm => Opcode::Imul,
+        Opcode::UdivImm => Opcode::Udiv,
+        Opcode::SdivImm => Opcode::Sdiv,
+        Opcode::UremImm => Opcode::Urem,
+        Opcode::SremImm => Opcode::Srem,
+        Opcode::IrsubImm => Opcode::Isub,
+        Opcode::BandImm => Opcode::Band,
+        Opcode::BorImm => Opcode::Bor,
+        Opcode::BxorImm => Opcode::Bxor,
+        Opcode::RotlImm => Opcode::Rotl,
+        Opcode::RotrImm => Opcode::Rotr,
+        Opcode::IshlImm => Opcode::Ishl,
+        Opcode::UshrImm => Opcode::Ushr,
+        Opcode::SshrImm => Opcode::Sshr,
+        Opcode::IcmpImm => Opcode::Icmp,
+        _ => opcode,
+    }
+}
+
+/// Evaluate `f` on each lane of `args`, which are values of type `ty`, assembling the results
+/// into a value of type `out`. Scalar operands of a vector operation, such as shift amounts, are
+/// passed unchanged to every lane.
+fn lanewise<F>(ty: Type, out: Type, args: &[DataValue], f: F) -> Result<DataValue, Stop>
+where
+    F: Fn(Type, &[DataValue]) -> Result<DataValue, Stop>,
+{
+    let lane_count = usize::from(ty.lane_count());
+    let args = args
+        .iter()
+        .map(|arg| {
+            if ty.is_vector() && arg.is_vector() {
+                to_lanes(arg, ty)
+            } else {
+                Ok(vec![arg.clone(); lane_count])
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let lanes = (0..lane_count)
+        .map(|i| {
+            let operands = args.iter().map(|a| a[i].clone()).collect::<Vec<_>>();
+            f(ty.lane_type(), &operands)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(from_lanes(&lanes, out)?)
+}
+
+/// Evaluate an integer or bitwise operation lane-wise on the bits of its operands. The closure
+/// receives the lane width and the zero-extended bits of each operand.
+fn int_op<F>(ty: Type, args: &[DataValue], f: F) -> Result<DataValue, Stop>
+where
+    F: Fn(u32, &[u128]) -> Result<u128, Stop>,
+{
+    lanewise(ty, ty, args, |lt, lanes| {
+        let bits = lanes
+            .iter()
+            .map(|lane| to_bits(lane, lt))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(from_bits(f(u32::from(lt.bits()), &bits)?, lt))
+    })
+}
+
+/// Convert a value of type `ty` to type `out` lane-wise. The closure receives the lane types of
+/// the input and output and the input lane.
+fn convert<F>(ty: Type, out: Type, arg: &DataValue, f: F) -> Result<DataValue, Stop>
+where
+    F: Fn(Type, Type, &DataValue) -> Result<DataValue, Stop>,
+{
+    lanewise(ty, out, std::slice::from_ref(arg), |lt, lanes| {
+        f(lt, out.lane_type(), &lanes[0])
+    })
+}
+
+/// The smallest and largest signed integers of the given width.
+fn signed_range(width: u32) -> (i128, i128) {
+    (
+        sign_extend(1 << (width - 1), width),
+        (mask(width) >> 1) as i128,
+    )
+}
+
+/// Compute the address accessed by a load or store from its address operands, paired with their
+/// types, and its offset.
+fn address<'v, I>(operands: I, offset: Offset32) -> Result<u64, ValueError>
+where
+    I: Iterator<Item = (&'v DataValue, &'v Type)>,
+{
+    let mut base = 0u64;
+    for (operand, &ty) in operands {
+        base = base.wrapping_add(to_bits(operand, ty)? as u64);
+    }
+    Ok(offset_address(base, offset))
+}
+
+fn offset_address(base: u64, offset: Offset32) -> u64 {
+    let offset: i64 = offset.into();
+    base.wrapping_add(offset as u64)
+}
+
+/// Read a floating point value of type `ty`, widening `f32`s to `f64`.
+fn float_value(value: &DataValue, ty: Type) -> Result<f64, ValueError> {
+    match (value, ty) {
+        (DataValue::F32(x), types::F32) => Ok(f64::from(f32::from_bits(x.bits()))),
+        (DataValue::F64(x), types::F64) => Ok(f64::from_bits(x.bits())),
+        _ => Err(ValueError {
+            ty,
+            value: value.clone(),
+        }),
+    }
+}
+
+fn icmp(cond: IntCC, ty: Type, x: &DataValue, y: &DataValue) -> Result<bool, ValueError> {
+    let w = u32::from(ty.bits());
+    let (ux, uy) = (to_bits(x, ty)?, to_bits(y, ty)?);
+    let (sx, sy) = (sign_extend(ux, w), sign_extend(uy, w));
+    let overflows = || match sx.checked_sub(sy) {
+        Some(difference) => sign_extend(difference as u128, w) != difference,
+        None => true,
+    };
+    Ok(match cond {
+        IntCC::Equal => ux == uy,
+        IntCC::NotEqual => ux != uy,
+        IntCC::SignedLessThan => sx < sy,
+        IntCC::SignedGreaterThanOrEqual => sx >= sy,
+        IntCC::SignedGreaterThan => sx > sy,
+        IntCC::SignedLessThanOrEqual => sx <= sy,
+        IntCC::UnsignedLessThan => ux < uy,
+        IntCC::UnsignedGreaterThanOrEqual => ux >= uy,
+        IntCC::UnsignedGreaterThan => ux > uy,
+        IntCC::UnsignedLessThanOrEqual => ux <= uy,
+        IntCC::Overflow => overflows(),
+        IntCC::NotOverflow => !overflows(),
+    })
+}
+
+fn fcmp(cond: FloatCC, x: f64, y: f64) -> bool {
+    let unordered = x.is_nan() || y.is_nan();
+    match cond {
+        FloatCC::Ordered => !unordered,
+        FloatCC::Unordered => unordered,
+        FloatCC::Equal => x == y,
+        FloatCC::NotEqual => x != y,
+        FloatCC::OrderedNotEqual => !unordered && x != y,
+        FloatCC::UnorderedOrEqual => unordered || x == y,
+        FloatCC::LessThan => x < y,
+        FloatCC::LessThanOrEqual => x <= y,
+        FloatCC::GreaterThan => x > y,
+        FloatCC::GreaterThanOrEqual => x >= y,
+        FloatCC::UnorderedOrLessThan => unordered || x < y,
+        FloatCC::UnorderedOrLessThanOrEqual => unordered || x <= y,
+        FloatCC::UnorderedOrGreaterThan => unordered || x > y,
+        FloatCC::UnorderedOrGreaterThanOrEqual => unordered || x >= y,
+    }
+}
+
+/// Convert a float to an integer of type `ty`, trapping or saturating when it is out of range.
+fn fcvt_to_int(x: f64, ty: Type, signed: bool, saturate: bool) -> Result<DataValue, Stop> {
+    let w = u32::from(ty.bits());
+    if x.is_nan() {
+        return if saturate {
+            Ok(from_bits(0, ty))
+        } else {
+            Err(TrapCode::BadConversionToInteger.into())
+        };
+    }
+    let x = x.trunc();
+    let (low, high, min, max) = if signed {
+        let (min, max) = signed_range(w);
+        (
+            -(2f64.powi(w as i32 - 1)),
+            2f64.powi(w as i32 - 1),
+            min as u128,
+            max as u128,
+        )
+    } else {
+        (0.0, 2f64.powi(w as i32), 0, mask(w))
+    };
+    let bits = if x < low {
+        if !saturate {
+            return Err(TrapCode::IntegerOverflow.into());
+        }
+        min
+    } else if x >= high {
+        if !saturate {
+            return Err(TrapCode::IntegerOverflow.into());
+        }
+        max
+    } else if signed {
+        x as i128 as u128
+    } else {
+        x as u128
+    };
+    Ok(from_bits(bits, ty))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cranelift_reader::parse_functions;
+
+    fn interpret(code: &str, name: &str, args: &[DataValue]) -> ControlFlow {
+        let mut env = Environment::default();
+        for function in parse_functions(code).unwrap() {
+            env.add(function);
+        }
+        Interpreter::new(env).call_by_name(name, args).unwrap()
+    }
+
+    #[test]
+    fn sanity() {
+        let code = "function %test() -> b1 {
+        ebb0:
+            v0 = iconst.i32 1
+            v1 = iadd_imm v0, 1
+            v2 = irsub_imm v1, 44  ; 44 - 2 == 42
+            v3 = icmp_imm eq v2, 42
+            return v3
+        }";
+        assert_eq!(
+            interpret(code, "%test", &[]),
+            ControlFlow::Return(vec![DataValue::B(true)])
+        );
+    }
+
+    #[test]
+    fn loops_and_calls() {
+        let code = "
+        function %fib(i32) -> i32 {
+        ebb0(v0: i32):
+            v1 = iconst.i32 0
+            v2 = iconst.i32 1
+            jump ebb1(v0, v1, v2)
+        ebb1(v3: i32, v4: i32, v5: i32):
+            brz v3, ebb2
+            v6 = iadd v4, v5
+            v7 = iadd_imm v3, -1
+            jump ebb1(v7, v5, v6)
+        ebb2:
+            return v4
+        }
+
+        function %call(i32) -> i32 {
+            fn0 = %fib(i32) -> i32
+        ebb0(v0: i32):
+            v1 = call fn0(v0)
+            return v1
+        }";
+        assert_eq!(
+            interpret(code, "%call", &[DataValue::I32(10)]),
+            ControlFlow::Return(vec![DataValue::I32(55)])
+        );
+    }
+
+    #[test]
+    fn traps() {
+        let code = "
+        function %div(i64, i64) -> i64 {
+        ebb0(v0: i64, v1: i64):
+            v2 = sdiv v0, v1
+            return v2
+        }
+
+        function %recurse() {
+            fn0 = %recurse()
+        ebb0:
+            call fn0()
+            return
+        }";
+        assert_eq!(
+            interpret(code, "%div", &[DataValue::I64(1), DataValue::I64(0)]),
+            ControlFlow::Trap(TrapCode::IntegerDivisionByZero)
+        );
+        assert_eq!(
+            interpret(
+                code,
+                "%div",
+                &[DataValue::I64(i64::min_value()), DataValue::I64(-1)]
+            ),
+            ControlFlow::Trap(TrapCode::IntegerOverflow)
+        );
+        assert_eq!(
+            interpret(code, "%recurse", &[]),
+            ControlFlow::Trap(TrapCode::StackOverflow)
+        );
+    }
+
+    #[test]
+    fn memory() {
+        let code = "function %test(i64) -> i32, i32 {
+            ss0 = explicit_slot 4
+        ebb0(v0: i64):
+            v1 = iconst.i32 -2
+            istore8 v1, v0+4
+            v2 = sload8.i32 v0+4
+            stack_store v2, ss0
+            v3 = stack_load.i32 ss0
+            v4 = load.i32 v0+4
+            return v3, v4
+        }";
+        assert_eq!(
+            interpret(code, "%test", &[DataValue::I64(16)]),
+            ControlFlow::Return(vec![DataValue::I32(-2), DataValue::I32(0xfe)])
+        );
+        assert_eq!(
+            interpret(code, "%test", &[DataValue::I64(1 << 40)]),
+            ControlFlow::Trap(TrapCode::HeapOutOfBounds)
+        );
+    }
+
+    #[test]
+    fn floats() {
+        let code = "function %test(f64, f64) -> f64, b1, i32 {
+        ebb0(v0: f64, v1: f64):
+            v2 = fmin v0, v1
+            v3 = fcmp uno v0, v1
+            v4 = nearest v0
+            v5 = fcvt_to_sint_sat.i32 v4
+            return v2, v3, v5
+        }";
+        assert_eq!(
+            interpret(
+                code,
+                "%test",
+                &[DataValue::from(2.5), DataValue::from(-0.0)]
+            ),
+            ControlFlow::Return(vec![
+                DataValue::from(-0.0),
+                DataValue::B(false),
+                DataValue::I32(2)
+            ])
+        );
+    }
+
+    #[test]
+    fn simd() {
+        let code = "function %test() -> i32x4, b1 {
+        ebb0:
+            v0 = vconst.i32x4 [1 2 3 4]
+            v2 = iconst.i32 10
+            v3 = splat.i32x4 v2
+            v4 = iadd v0, v3
+            v5 = ishl_imm v4, 1
+            v6 = icmp sgt v5, v3
+            v7 = vall_true v6
+            return v5, v7
+        }";
+        let mut expected = [0; 16];
+        for (i, x) in [22, 24, 26, 28].iter().enumerate() {
+            expected[i * 4] = *x;
+        }
+        assert_eq!(
+            interpret(code, "%test", &[]),
+            ControlFlow::Return(vec![DataValue::V128(expected), DataValue::B(true)])
+        );
+    }
+}
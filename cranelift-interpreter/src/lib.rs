@@ -0,0 +1,38 @@
+//! Cranelift IR interpreter.
+//!
+//! The `cranelift_interpreter` library executes Cranelift IR directly, without generating machine
+//! code. It is used to run CLIF tests on any host and as a reference implementation when testing
+//! the code generators.
+
+#![deny(
+    missing_docs,
+    trivial_numeric_casts,
+    unused_extern_crates,
+    unstable_features
+)]
+#![warn(unused_import_braces)]
+#![cfg_attr(feature = "clippy", plugin(clippy(conf_file = "../../clippy.toml")))]
+#![cfg_attr(feature = "cargo-clippy", allow(clippy::new_without_default))]
+#![cfg_attr(
+    feature = "cargo-clippy",
+    warn(
+        clippy::mut_mut,
+        clippy::nonminimal_bool,
+        clippy::option_map_unwrap_or,
+        clippy::option_map_unwrap_or_else,
+        clippy::print_stdout,
+        clippy::unicode_not_nfc,
+        clippy::use_self
+    )
+)]
+
+pub use crate::environment::{Environment, FuncIndex};
+pub use crate::interpreter::{ControlFlow, Interpreter, InterpreterError, MAX_CALL_DEPTH};
+pub use crate::memory::Memory;
+pub use crate::value::ValueError;
+
+mod environment;
+mod frame;
+mod interpreter;
+pub mod memory;
+mod value;
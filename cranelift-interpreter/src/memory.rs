@@ -0,0 +1,130 @@
+//! The sandboxed memory accessible to interpreted code.
+
+use cranelift_codegen::ir::TrapCode;
+
+/// The default size of the heap region: one WebAssembly page.
+pub const DEFAULT_HEAP_SIZE: usize = 0x1_0000;
+
+/// The default size of the stack region.
+pub const DEFAULT_STACK_SIZE: usize = 0x10_0000;
+
+/// A linear memory holding both the heap and the stack of interpreted functions.
+///
+/// Addresses are offsets into this memory: the heap occupies `[0, heap_size)` and the stack
+/// occupies the addresses above it, growing downwards from the end of the memory as stack slots
+/// are allocated. Any access that falls outside of the memory traps with
+/// `TrapCode::HeapOutOfBounds` instead of touching host memory, and running out of stack space
+/// traps with `TrapCode::StackOverflow`.
+pub struct Memory {
+    bytes: Vec<u8>,
+    heap_size: usize,
+    stack_pointer: usize,
+}
+
+impl Default for Memory {
+    fn default() -> Self {
+        Self::new(DEFAULT_HEAP_SIZE, DEFAULT_STACK_SIZE)
+    }
+}
+
+impl Memory {
+    /// Create a zero-initialized memory with the given heap and stack sizes, in bytes.
+    pub fn new(heap_size: usize, stack_size: usize) -> Self {
+        let size = heap_size + stack_size;
+        Self {
+            bytes: vec![0; size],
+            heap_size,
+            stack_pointer: size,
+        }
+    }
+
+    /// The heap region, e.g. for initializing it before a call or inspecting it afterwards.
+    pub fn heap(&self) -> &[u8] {
+        &self.bytes[..self.heap_size]
+    }
+
+    /// The heap region, mutably.
+    pub fn heap_mut(&mut self) -> &mut [u8] {
+        &mut self.bytes[..self.heap_size]
+    }
+
+    /// Read `size` bytes starting at `address`.
+    pub fn load(&self, address: u64, size: usize) -> Result<&[u8], TrapCode> {
+        let range = self.range(address, size)?;
+        Ok(&self.bytes[range])
+    }
+
+    /// Write `bytes` starting at `address`.
+    pub fn store(&mut self, address: u64, bytes: &[u8]) -> Result<(), TrapCode> {
+        let range = self.range(address, bytes.len())?;
+        self.bytes[range].copy_from_slice(bytes);
+        Ok(())
+    }
+
+    fn range(&self, address: u64, size: usize) -> Result<std::ops::Range<usize>, TrapCode> {
+        let start = address as usize;
+        match start.checked_add(size) {
+            Some(end) if address <= usize::max_value() as u64 && end <= self.bytes.len() => {
+                Ok(start..end)
+            }
+            _ => Err(TrapCode::HeapOutOfBounds),
+        }
+    }
+
+    /// The current top of the stack; pass it to `restore_stack_pointer` to release everything
+    /// allocated after this point.
+    pub(crate) fn stack_pointer(&self) -> usize {
+        self.stack_pointer
+    }
+
+    /// Release the stack allocations made since `stack_pointer` was returned by
+    /// `stack_pointer()`.
+    pub(crate) fn restore_stack_pointer(&mut self, stack_pointer: usize) {
+        debug_assert!(stack_pointer >= self.stack_pointer);
+        self.stack_pointer = stack_pointer;
+    }
+
+    /// Allocate `size` zeroed bytes on the stack, returning their address.
+    pub(crate) fn allocate_stack(&mut self, size: usize) -> Result<u64, TrapCode> {
+        // Keep every allocation 16-byte aligned, like a native stack frame.
+        let size = (size + 15) & !15;
+        match self.stack_pointer.checked_sub(size) {
+            Some(sp) if sp >= self.heap_size => {
+                self.stack_pointer = sp;
+                for b in &mut self.bytes[sp..sp + size] {
+                    *b = 0;
+                }
+                Ok(sp as u64)
+            }
+            _ => Err(TrapCode::StackOverflow),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounds() {
+        let mut memory = Memory::new(16, 32);
+        assert_eq!(memory.store(12, &[1, 2, 3, 4]), Ok(()));
+        assert_eq!(memory.load(12, 4), Ok(&[1, 2, 3, 4][..]));
+        assert_eq!(memory.load(46, 4), Err(TrapCode::HeapOutOfBounds));
+        assert_eq!(
+            memory.load(u64::max_value(), 1),
+            Err(TrapCode::HeapOutOfBounds)
+        );
+    }
+
+    #[test]
+    fn stack() {
+        let mut memory = Memory::new(16, 32);
+        let sp = memory.stack_pointer();
+        assert_eq!(memory.allocate_stack(4), Ok(32));
+        assert_eq!(memory.allocate_stack(16), Ok(16));
+        assert_eq!(memory.allocate_stack(1), Err(TrapCode::StackOverflow));
+        memory.restore_stack_pointer(sp);
+        assert_eq!(memory.allocate_stack(32), Ok(16));
+    }
+}
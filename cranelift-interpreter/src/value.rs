@@ -0,0 +1,184 @@
+//! Conversions between `DataValue`s and their bit patterns.
+//!
+//! The interpreter implements most instructions on the raw bits of a value, with the width and
+//! interpretation of those bits given by the Cranelift type the instruction operates on. Vector
+//! instructions are evaluated one lane at a time.
+
+use cranelift_codegen::data_value::DataValue;
+use cranelift_codegen::ir::immediates::{Ieee32, Ieee64};
+use cranelift_codegen::ir::{types, Type};
+use thiserror::Error;
+
+/// A value did not match the type it was used as.
+#[derive(Error, Debug, PartialEq)]
+#[error("expected a value of type {ty} but found {value:?}")]
+pub struct ValueError {
+    /// The expected type.
+    pub ty: Type,
+    /// The offending value.
+    pub value: DataValue,
+}
+
+/// A mask selecting the low `width` bits.
+pub fn mask(width: u32) -> u128 {
+    if width >= 128 {
+        u128::max_value()
+    } else {
+        (1 << width) - 1
+    }
+}
+
+/// Sign-extend the low `width` bits of `bits`.
+pub fn sign_extend(bits: u128, width: u32) -> i128 {
+    let shift = 128 - width;
+    ((bits << shift) as i128) >> shift
+}
+
+/// Return the bits of `value`, interpreted as a value of type `ty`. True booleans have all of
+/// their bits set.
+pub fn to_bits(value: &DataValue, ty: Type) -> Result<u128, ValueError> {
+    let bits = match (value, ty) {
+        (DataValue::B(b), _) if ty.is_bool() => {
+            if *b {
+                u128::max_value()
+            } else {
+                0
+            }
+        }
+        (DataValue::I8(x), types::I8) => *x as u128,
+        (DataValue::I16(x), types::I16) => *x as u128,
+        (DataValue::I32(x), types::I32) => *x as u128,
+        (DataValue::I64(x), types::I64) => *x as u128,
+        (DataValue::I128(x), types::I128) => *x as u128,
+        (DataValue::F32(x), types::F32) => u128::from(x.bits()),
+        (DataValue::F64(x), types::F64) => u128::from(x.bits()),
+        (DataValue::V128(bytes), _) if ty.is_vector() => u128::from_le_bytes(*bytes),
+        _ => {
+            return Err(ValueError {
+                ty,
+                value: value.clone(),
+            })
+        }
+    };
+    Ok(bits & mask(u32::from(ty.bits())))
+}
+
+/// Return the bits of `value` sign-extended from the width of `ty`.
+pub fn to_signed(value: &DataValue, ty: Type) -> Result<i128, ValueError> {
+    Ok(sign_extend(to_bits(value, ty)?, u32::from(ty.bits())))
+}
+
+/// Build a value of type `ty` from the low bits of `bits`.
+pub fn from_bits(bits: u128, ty: Type) -> DataValue {
+    let bits = bits & mask(u32::from(ty.bits()));
+    match ty {
+        types::I8 => DataValue::I8(bits as i8),
+        types::I16 => DataValue::I16(bits as i16),
+        types::I32 => DataValue::I32(bits as i32),
+        types::I64 => DataValue::I64(bits as i64),
+        types::I128 => DataValue::I128(bits as i128),
+        types::F32 => DataValue::F32(Ieee32::with_bits(bits as u32)),
+        types::F64 => DataValue::F64(Ieee64::with_bits(bits as u64)),
+        _ if ty.is_bool() => DataValue::B(bits != 0),
+        _ if ty.is_vector() => DataValue::V128(bits.to_le_bytes()),
+        _ => panic!("no runtime representation for values of type {}", ty),
+    }
+}
+
+/// Decide whether `value` is true, as tested by e.g. `brnz` or `select`: true booleans and
+/// non-zero integers are.
+pub fn is_true(value: &DataValue) -> bool {
+    match value {
+        DataValue::B(b) => *b,
+        DataValue::F32(x) => x.bits() != 0,
+        DataValue::F64(x) => x.bits() != 0,
+        DataValue::V128(bytes) => bytes.iter().any(|&b| b != 0),
+        _ => value.as_i128() != Some(0),
+    }
+}
+
+/// Split a value of type `ty` into its lanes. A scalar has a single lane.
+pub fn to_lanes(value: &DataValue, ty: Type) -> Result<Vec<DataValue>, ValueError> {
+    if !ty.is_vector() {
+        to_bits(value, ty)?;
+        return Ok(vec![value.clone()]);
+    }
+    let bits = to_bits(value, ty)?;
+    let lane_type = ty.lane_type();
+    let lane_bits = u32::from(lane_type.bits());
+    Ok((0..u32::from(ty.lane_count()))
+        .map(|i| from_bits(bits >> (i * lane_bits), lane_type))
+        .collect())
+}
+
+/// Assemble a value of type `ty` from its lanes, the inverse of `to_lanes`.
+pub fn from_lanes(lanes: &[DataValue], ty: Type) -> Result<DataValue, ValueError> {
+    debug_assert_eq!(lanes.len(), usize::from(ty.lane_count()));
+    if !ty.is_vector() {
+        return Ok(lanes[0].clone());
+    }
+    let lane_type = ty.lane_type();
+    let lane_bits = u32::from(lane_type.bits());
+    let mut bits = 0;
+    for (i, lane) in lanes.iter().enumerate() {
+        bits |= to_bits(lane, lane_type)? << (i as u32 * lane_bits);
+    }
+    Ok(from_bits(bits, ty))
+}
+
+/// Encode a value of type `ty` as the little-endian bytes it occupies in memory.
+pub fn to_le_bytes(value: &DataValue, ty: Type) -> Result<Vec<u8>, ValueError> {
+    let bytes = to_bits(value, ty)?.to_le_bytes();
+    Ok(bytes[..ty.bytes() as usize].to_vec())
+}
+
+/// Decode a value of type `ty` from its little-endian bytes in memory.
+pub fn from_le_bytes(bytes: &[u8], ty: Type) -> DataValue {
+    let mut buffer = [0; 16];
+    buffer[..bytes.len()].copy_from_slice(bytes);
+    from_bits(u128::from_le_bytes(buffer), ty)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bits() {
+        assert_eq!(to_bits(&DataValue::I8(-1), types::I8), Ok(0xff));
+        assert_eq!(to_signed(&DataValue::I16(-2), types::I16), Ok(-2));
+        assert_eq!(to_bits(&DataValue::B(true), types::B16), Ok(0xffff));
+        assert_eq!(from_bits(0x1_0000_0001, types::I32), DataValue::I32(1));
+        assert!(to_bits(&DataValue::I32(0), types::I64).is_err());
+    }
+
+    #[test]
+    fn lanes() {
+        let value = from_bits(0x0000_0004_0000_0003_0000_0002_0000_0001, types::I32X4);
+        let lanes = to_lanes(&value, types::I32X4).unwrap();
+        assert_eq!(
+            lanes,
+            vec![
+                DataValue::I32(1),
+                DataValue::I32(2),
+                DataValue::I32(3),
+                DataValue::I32(4)
+            ]
+        );
+        assert_eq!(from_lanes(&lanes, types::I32X4), Ok(value));
+        assert_eq!(
+            to_lanes(&DataValue::I64(7), types::I64),
+            Ok(vec![DataValue::I64(7)])
+        );
+    }
+
+    #[test]
+    fn memory_bytes() {
+        let bytes = to_le_bytes(&DataValue::I32(0x0102_0304), types::I32).unwrap();
+        assert_eq!(bytes, vec![4, 3, 2, 1]);
+        assert_eq!(
+            from_le_bytes(&bytes, types::I32),
+            DataValue::I32(0x0102_0304)
+        );
+    }
+}
@@ -4,6 +4,7 @@ use crate::DataContext;
 use crate::DataId;
 use crate::FuncId;
 use crate::Linkage;
+use crate::ModuleError;
 use crate::ModuleNamespace;
 use crate::ModuleResult;
 use core::marker;
@@ -61,14 +62,35 @@ where
     fn declare_function(&mut self, id: FuncId, name: &str, linkage: Linkage);
 
     /// Declare a data object.
+    ///
+    /// Backends which don't support thread local data return `ModuleError::Backend` if `tls` is
+    /// set.
     fn declare_data(
         &mut self,
         id: DataId,
         name: &str,
         linkage: Linkage,
         writable: bool,
+        tls: bool,
         align: Option<u8>,
-    );
+    ) -> ModuleResult<()>;
+
+    /// Compile the function in `ctx` for `isa`, before it is defined with `define_function`.
+    ///
+    /// This is an associated function rather than a method because `Module::define_functions`
+    /// calls it on several threads at once, and backends don't have to be `Sync`; the SimpleJIT
+    /// backend, for one, holds `Rc`s and raw pointers. Compiling only needs `isa` and `ctx`.
+    /// Everything that needs the backend is left to `define_function`, which is called serially.
+    /// The default implementation runs the whole Cranelift pipeline.
+    ///
+    /// Returns the size of the function's code and constant data.
+    fn compile_function(
+        isa: &dyn TargetIsa,
+        ctx: &mut Context,
+    ) -> ModuleResult<binemit::CodeOffset> {
+        let binemit::CodeInfo { total_size, .. } = ctx.compile(isa)?;
+        Ok(total_size)
+    }
 
     /// Define a function, producing the function body from the given `Context`.
     ///
@@ -82,6 +104,60 @@ where
         code_size: u32,
     ) -> ModuleResult<Self::CompiledFunction>;
 
+    /// Define a new body for a function which was already defined and finalized, producing it
+    /// from the given `Context`.
+    ///
+    /// Existing callers and pointers to the function must switch to the new body when it is
+    /// finalized. Backends which can't do that return `ModuleError::DuplicateDefinition`, which is
+    /// what the default implementation does.
+    fn redefine_function(
+        &mut self,
+        _id: FuncId,
+        name: &str,
+        _ctx: &Context,
+        _namespace: &ModuleNamespace<Self>,
+        _code_size: u32,
+    ) -> ModuleResult<Self::CompiledFunction> {
+        Err(ModuleError::DuplicateDefinition(name.to_owned()))
+    }
+
+    /// Define a function whose body is produced by `produce` and compiled when the function is
+    /// first called, rather than now.
+    ///
+    /// Backends which can't do that return `ModuleError::Backend`, which is what the default
+    /// implementation does.
+    fn define_function_lazily(
+        &mut self,
+        _id: FuncId,
+        _name: &str,
+        _signature: &ir::Signature,
+        _produce: Box<dyn FnOnce() -> ir::Function>,
+        _namespace: &ModuleNamespace<Self>,
+    ) -> ModuleResult<Self::CompiledFunction> {
+        Err(ModuleError::Backend(
+            "lazy compilation isn't supported by this backend".to_owned(),
+        ))
+    }
+
+    /// Remove the definition of a function, freeing what the backend allocated for it.
+    ///
+    /// Backends which can't do that return `ModuleError::Backend`, which is what the default
+    /// implementation does.
+    ///
+    /// # Safety
+    ///
+    /// See `Module::remove_function`.
+    unsafe fn remove_function(
+        &mut self,
+        _id: FuncId,
+        _name: &str,
+        _func: &Self::CompiledFunction,
+    ) -> ModuleResult<()> {
+        Err(ModuleError::Backend(
+            "removing functions isn't supported by this backend".to_owned(),
+        ))
+    }
+
     /// Define a zero-initialized data object of the given size.
     ///
     /// Data objects must be declared before being defined.
@@ -90,6 +166,7 @@ where
         id: DataId,
         name: &str,
         writable: bool,
+        tls: bool,
         align: Option<u8>,
         data_ctx: &DataContext,
         namespace: &ModuleNamespace<Self>,
@@ -162,5 +239,7 @@ pub fn default_libcall_names() -> Box<dyn Fn(ir::LibCall) -> String> {
         ir::LibCall::Memcpy => "memcpy".to_owned(),
         ir::LibCall::Memset => "memset".to_owned(),
         ir::LibCall::Memmove => "memmove".to_owned(),
+
+        ir::LibCall::ElfTlsGetAddr => "__tls_get_addr".to_owned(),
     })
 }
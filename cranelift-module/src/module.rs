@@ -8,11 +8,14 @@
 use super::HashMap;
 use crate::data_context::DataContext;
 use crate::Backend;
-use cranelift_codegen::binemit::{self, CodeInfo};
-use cranelift_codegen::entity::{entity_impl, PrimaryMap};
+use cranelift_codegen::binemit;
+use cranelift_codegen::entity::{entity_impl, PrimaryMap, SecondaryMap};
 use cranelift_codegen::{ir, isa, CodegenError, Context};
 use log::info;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 use std::borrow::ToOwned;
+use std::boxed::Box;
 use std::string::String;
 use std::vec::Vec;
 use thiserror::Error;
@@ -136,6 +139,9 @@ pub enum ModuleError {
     /// Indicates an identifier was defined more than once
     #[error("Duplicate definition of identifier: {0}")]
     DuplicateDefinition(String),
+    /// Indicates an identifier was redefined or removed, but wasn't defined
+    #[error("Identifier isn't defined: {0}")]
+    Undefined(String),
     /// Indicates an identifier was defined, but was declared as an import
     #[error("Invalid to define identifier declared as an import: {0}")]
     InvalidImportDefinition(String),
@@ -183,6 +189,7 @@ pub struct DataDeclaration {
     pub name: String,
     pub linkage: Linkage,
     pub writable: bool,
+    pub tls: bool,
     pub align: Option<u8>,
 }
 
@@ -201,10 +208,21 @@ impl<B> ModuleData<B>
 where
     B: Backend,
 {
-    fn merge(&mut self, linkage: Linkage, writable: bool, align: Option<u8>) {
+    fn merge(
+        &mut self,
+        linkage: Linkage,
+        writable: bool,
+        tls: bool,
+        align: Option<u8>,
+    ) -> ModuleResult<()> {
+        // A data object can't change between thread local and global.
+        if self.decl.tls != tls {
+            return Err(ModuleError::IncompatibleDeclaration(self.decl.name.clone()));
+        }
         self.decl.linkage = Linkage::merge(self.decl.linkage, linkage);
         self.decl.writable = self.decl.writable || writable;
         self.decl.align = self.decl.align.max(align);
+        Ok(())
     }
 }
 
@@ -450,11 +468,15 @@ where
     }
 
     /// Declare a data object in this module.
+    ///
+    /// If `tls` is set, the data object is a thread local variable, and each thread gets its own
+    /// copy of its contents.
     pub fn declare_data(
         &mut self,
         name: &str,
         linkage: Linkage,
         writable: bool,
+        tls: bool,
         align: Option<u8>, // An alignment bigger than 128 is unlikely
     ) -> ModuleResult<DataId> {
         // TODO: Can we avoid allocating names so often?
@@ -463,14 +485,15 @@ where
             Occupied(entry) => match *entry.get() {
                 FuncOrDataId::Data(id) => {
                     let existing = &mut self.contents.data_objects[id];
-                    existing.merge(linkage, writable, align);
+                    existing.merge(linkage, writable, tls, align)?;
                     self.backend.declare_data(
                         id,
                         name,
                         existing.decl.linkage,
                         existing.decl.writable,
+                        existing.decl.tls,
                         existing.decl.align,
-                    );
+                    )?;
                     Ok(id)
                 }
 
@@ -479,18 +502,21 @@ where
                 }
             },
             Vacant(entry) => {
-                let id = self.contents.data_objects.push(ModuleData {
+                // Let the backend reject the declaration before it's recorded.
+                let id = self.contents.data_objects.next_key();
+                self.backend
+                    .declare_data(id, name, linkage, writable, tls, align)?;
+                self.contents.data_objects.push(ModuleData {
                     decl: DataDeclaration {
                         name: name.to_owned(),
                         linkage,
                         writable,
+                        tls,
                         align,
                     },
                     compiled: None,
                 });
                 entry.insert(FuncOrDataId::Data(id));
-                self.backend
-                    .declare_data(id, name, linkage, writable, align);
                 Ok(id)
             }
         }
@@ -521,6 +547,7 @@ where
             name: ir::ExternalName::user(1, data.as_u32()),
             offset: ir::immediates::Imm64::new(0),
             colocated,
+            tls: decl.tls,
         })
     }
 
@@ -549,7 +576,7 @@ where
             func,
             ctx.func.display(self.backend.isa())
         );
-        let CodeInfo { total_size, .. } = ctx.compile(self.backend.isa())?;
+        let total_size = B::compile_function(self.backend.isa(), ctx)?;
         let info = &self.contents.functions[func];
         if info.compiled.is_some() {
             return Err(ModuleError::DuplicateDefinition(info.decl.name.clone()));
@@ -573,6 +600,167 @@ where
         Ok(total_size)
     }
 
+    /// Define many functions, producing their bodies from the given `Context`s.
+    ///
+    /// With the `parallel` feature, the functions are compiled on several threads. They are
+    /// then defined in the order they are given, so the result doesn't depend on how the
+    /// compilation was scheduled. If any function fails to compile, the error of the first one
+    /// is returned and none of them is defined. If the backend fails to define one of them, the
+    /// functions before it stay defined.
+    ///
+    /// Returns the sizes of the functions' code and constant data, in the same order.
+    ///
+    /// Note: After calling this function the given `Context`s will contain the compiled
+    /// functions.
+    pub fn define_functions(
+        &mut self,
+        funcs: &mut [(FuncId, Context)],
+    ) -> ModuleResult<Vec<binemit::CodeOffset>> {
+        info!("defining {} functions", funcs.len());
+        let mut defined = SecondaryMap::new();
+        for &(func, _) in funcs.iter() {
+            let info = &self.contents.functions[func];
+            if info.compiled.is_some() || defined[func] {
+                return Err(ModuleError::DuplicateDefinition(info.decl.name.clone()));
+            }
+            if !info.decl.linkage.is_definable() {
+                return Err(ModuleError::InvalidImportDefinition(info.decl.name.clone()));
+            }
+            defined[func] = true;
+        }
+
+        let isa = self.backend.isa();
+        let compile = |&mut (_, ref mut ctx): &mut (FuncId, Context)| B::compile_function(isa, ctx);
+        #[cfg(feature = "parallel")]
+        let sizes: Vec<_> = funcs.par_iter_mut().map(compile).collect();
+        #[cfg(not(feature = "parallel"))]
+        let sizes: Vec<_> = funcs.iter_mut().map(compile).collect();
+        let sizes = sizes.into_iter().collect::<ModuleResult<Vec<_>>>()?;
+
+        for (&(func, ref ctx), &total_size) in funcs.iter().zip(&sizes) {
+            let info = &self.contents.functions[func];
+            let compiled = Some(self.backend.define_function(
+                func,
+                &info.decl.name,
+                ctx,
+                &ModuleNamespace::<B> {
+                    contents: &self.contents,
+                },
+                total_size,
+            )?);
+
+            self.contents.functions[func].compiled = compiled;
+            self.functions_to_finalize.push(func);
+        }
+        Ok(sizes)
+    }
+
+    /// Replace the definition of a function that was defined and finalized before, producing
+    /// the new body from the given `Context`.
+    ///
+    /// The new body is used by callers and function pointers once it is finalized by
+    /// `finalize_definitions`; until then, the previous body keeps being used. Backends
+    /// which don't support this return `ModuleError::DuplicateDefinition`.
+    ///
+    /// Returns the size of the function's code and constant data.
+    ///
+    /// Note: After calling this function the given `Context` will contain the compiled function.
+    pub fn redefine_function(
+        &mut self,
+        func: FuncId,
+        ctx: &mut Context,
+    ) -> ModuleResult<binemit::CodeOffset> {
+        info!(
+            "redefining function {}: {}",
+            func,
+            ctx.func.display(self.backend.isa())
+        );
+        let total_size = B::compile_function(self.backend.isa(), ctx)?;
+        let info = &self.contents.functions[func];
+        if !info.decl.linkage.is_definable() {
+            return Err(ModuleError::InvalidImportDefinition(info.decl.name.clone()));
+        }
+        if info.compiled.is_none() {
+            return Err(ModuleError::Undefined(info.decl.name.clone()));
+        }
+        if self.functions_to_finalize.contains(&func) {
+            return Err(ModuleError::DuplicateDefinition(info.decl.name.clone()));
+        }
+
+        let compiled = Some(self.backend.redefine_function(
+            func,
+            &info.decl.name,
+            ctx,
+            &ModuleNamespace::<B> {
+                contents: &self.contents,
+            },
+            total_size,
+        )?);
+
+        self.contents.functions[func].compiled = compiled;
+        self.functions_to_finalize.push(func);
+        Ok(total_size)
+    }
+
+    /// Define a function whose body is only produced and compiled when it is first called.
+    ///
+    /// `produce` is called at that point to produce the function's IR, which must refer to
+    /// other functions and data of this module the way `declare_func_in_func` and
+    /// `declare_data_in_func` do. The function is finalized by `finalize_definitions` like
+    /// other definitions. Backends which don't support this return `ModuleError::Backend`.
+    pub fn define_function_lazily(
+        &mut self,
+        func: FuncId,
+        produce: Box<dyn FnOnce() -> ir::Function>,
+    ) -> ModuleResult<()> {
+        info!("defining function {} lazily", func);
+        let info = &self.contents.functions[func];
+        if info.compiled.is_some() {
+            return Err(ModuleError::DuplicateDefinition(info.decl.name.clone()));
+        }
+        if !info.decl.linkage.is_definable() {
+            return Err(ModuleError::InvalidImportDefinition(info.decl.name.clone()));
+        }
+
+        let compiled = Some(self.backend.define_function_lazily(
+            func,
+            &info.decl.name,
+            &info.decl.signature,
+            produce,
+            &ModuleNamespace::<B> {
+                contents: &self.contents,
+            },
+        )?);
+
+        self.contents.functions[func].compiled = compiled;
+        self.functions_to_finalize.push(func);
+        Ok(())
+    }
+
+    /// Remove the definition of a function, letting the backend reclaim its memory.
+    ///
+    /// The function stays declared, and may be defined again. Backends which don't
+    /// support this return `ModuleError::Backend`.
+    ///
+    /// # Safety
+    ///
+    /// The function must not be running, and neither it nor the functions and data
+    /// referring to it may be used afterwards.
+    pub unsafe fn remove_function(&mut self, func: FuncId) -> ModuleResult<()> {
+        info!("removing function {}", func);
+        let info = &self.contents.functions[func];
+        let compiled = match info.compiled {
+            Some(ref compiled) => compiled,
+            None => return Err(ModuleError::Undefined(info.decl.name.clone())),
+        };
+        self.backend
+            .remove_function(func, &info.decl.name, compiled)?;
+
+        self.contents.functions[func].compiled = None;
+        self.functions_to_finalize.retain(|&id| id != func);
+        Ok(())
+    }
+
     /// Define a data object, producing the data contents from the given `DataContext`.
     pub fn define_data(&mut self, data: DataId, data_ctx: &DataContext) -> ModuleResult<()> {
         let compiled = {
@@ -587,6 +775,7 @@ where
                 data,
                 &info.decl.name,
                 info.decl.writable,
+                info.decl.tls,
                 info.decl.align,
                 data_ctx,
                 &ModuleNamespace::<B> {
@@ -1,22 +1,32 @@
 //! Defines `ObjectBackend`.
 
+use crate::link::{self, Sections};
 use crate::traps::{ObjectTrapSink, ObjectTrapSite};
 use cranelift_codegen::binemit::{
-    Addend, CodeOffset, NullStackmapSink, NullTrapSink, Reloc, RelocSink,
+    Addend, CodeOffset, FrameUnwindKind, FrameUnwindOffset, FrameUnwindSink, NullStackmapSink,
+    NullTrapSink, Reloc, RelocSink,
 };
 use cranelift_codegen::entity::SecondaryMap;
 use cranelift_codegen::isa::TargetIsa;
 use cranelift_codegen::{self, binemit, ir};
 use cranelift_module::{
-    Backend, DataContext, DataDescription, DataId, FuncId, Init, Linkage, ModuleNamespace,
-    ModuleResult,
+    Backend, DataContext, DataDescription, DataId, FuncId, Init, Linkage, ModuleError,
+    ModuleNamespace, ModuleResult,
 };
 use object::write::{
     Object, Relocation, SectionId, StandardSection, Symbol, SymbolId, SymbolSection,
 };
-use object::{RelocationEncoding, RelocationKind, SymbolFlags, SymbolKind, SymbolScope};
+use object::{
+    RelocationEncoding, RelocationKind, SectionKind, SymbolFlags, SymbolKind, SymbolScope,
+};
 use std::collections::HashMap;
-use target_lexicon::PointerWidth;
+use std::convert::TryInto;
+use target_lexicon::{Architecture, BinaryFormat, PointerWidth};
+
+// Thread-local storage relocation types, which `object` has no generic kinds for.
+const R_X86_64_TLSGD: u32 = 19;
+const R_X86_64_GOTTPOFF: u32 = 22;
+const X86_64_RELOC_TLV: u8 = 9;
 
 #[derive(Debug)]
 /// Setting to enable collection of traps. Setting this to `Enabled` in
@@ -28,6 +38,23 @@ pub enum ObjectTrapCollection {
     Enabled,
 }
 
+/// The kind of file written by `ObjectProduct::emit`, set with `ObjectBuilder::output`.
+#[derive(Clone, Debug)]
+pub enum ObjectOutput {
+    /// A relocatable object, to be linked by a system linker.
+    Relocatable,
+    /// A static archive containing the relocatable object.
+    Archive,
+    /// A shared object, linked without the help of a system linker.
+    SharedObject {
+        /// The name recorded as the `DT_SONAME` of the shared object, if any.
+        soname: Option<String>,
+        /// The shared libraries providing the imported symbols, recorded as `DT_NEEDED`
+        /// entries.
+        needed: Vec<String>,
+    },
+}
+
 /// A builder for `ObjectBackend`.
 pub struct ObjectBuilder {
     isa: Box<dyn TargetIsa>,
@@ -35,6 +62,8 @@ pub struct ObjectBuilder {
     collect_traps: ObjectTrapCollection,
     libcall_names: Box<dyn Fn(ir::LibCall) -> String>,
     function_alignment: u64,
+    output: ObjectOutput,
+    personality: String,
 }
 
 impl ObjectBuilder {
@@ -60,6 +89,8 @@ impl ObjectBuilder {
             collect_traps,
             libcall_names,
             function_alignment: 1,
+            output: ObjectOutput::Relocatable,
+            personality: "__gcc_personality_v0".to_string(),
         })
     }
 
@@ -68,14 +99,72 @@ impl ObjectBuilder {
         self.function_alignment = alignment;
         self
     }
+
+    /// Set the name of the personality routine referenced by the unwind information of functions
+    /// using `invoke`. It must understand the landing pad tables in `.gcc_except_table`, like the
+    /// default, `__gcc_personality_v0`.
+    pub fn personality(&mut self, name: String) -> &mut Self {
+        self.personality = name;
+        self
+    }
+
+    /// Set the kind of file written by `ObjectProduct::emit`.
+    ///
+    /// Archives are only supported for ELF targets, and shared objects only for x86-64 ELF
+    /// targets generating position-independent code.
+    pub fn output(&mut self, output: ObjectOutput) -> ModuleResult<&mut Self> {
+        let triple = self.isa.triple();
+        match output {
+            ObjectOutput::Relocatable => {}
+            ObjectOutput::Archive => {
+                if triple.binary_format != BinaryFormat::Elf {
+                    return Err(ModuleError::Backend(
+                        "archives are only supported for ELF".to_string(),
+                    ));
+                }
+            }
+            ObjectOutput::SharedObject { .. } => {
+                if triple.binary_format != BinaryFormat::Elf
+                    || triple.architecture != Architecture::X86_64
+                {
+                    return Err(ModuleError::Backend(
+                        "shared objects are only supported for x86-64 ELF".to_string(),
+                    ));
+                }
+                if !self.isa.flags().is_pic() {
+                    return Err(ModuleError::Backend(
+                        "shared objects require position-independent code".to_string(),
+                    ));
+                }
+            }
+        }
+        self.output = output;
+        Ok(self)
+    }
 }
 
 /// A `ObjectBackend` implements `Backend` and emits ".o" files using the `object` library.
 ///
+/// For ELF and Mach-O objects, the DWARF call frame information of functions that were compiled
+/// with `Function::collect_frame_layout_info` is collected into an `.eh_frame` section, so that
+/// debuggers, profilers and unwinders can walk through them. The landing pads of functions using
+/// `invoke` are listed in a `.gcc_except_table` section, for the personality routine set with
+/// `ObjectBuilder::personality`.
+///
 /// See the `ObjectBuilder` for a convenient way to construct `ObjectBackend` instances.
 pub struct ObjectBackend {
     isa: Box<dyn TargetIsa>,
+    name: String,
     object: Object,
+    /// The contents of the sections, kept when a shared object is to be linked from them.
+    sections: Option<Sections>,
+    output: ObjectOutput,
+    eh_frame: Option<SectionId>,
+    eh_frame_cies: HashMap<Vec<u8>, u64>,
+    gcc_except_table: Option<SectionId>,
+    personality_name: String,
+    personality: Option<SymbolId>,
+    lsdas: Vec<SymbolId>,
     functions: SecondaryMap<FuncId, Option<SymbolId>>,
     data_objects: SecondaryMap<DataId, Option<SymbolId>>,
     traps: SecondaryMap<FuncId, Vec<ObjectTrapSite>>,
@@ -103,9 +192,22 @@ impl Backend for ObjectBackend {
         let triple = builder.isa.triple();
         let mut object = Object::new(triple.binary_format, triple.architecture);
         object.add_file_symbol(builder.name.as_bytes().to_vec());
+        let sections = match builder.output {
+            ObjectOutput::SharedObject { .. } => Some(Sections::new()),
+            _ => None,
+        };
         Self {
             isa: builder.isa,
+            name: builder.name,
             object,
+            sections,
+            output: builder.output,
+            eh_frame: None,
+            eh_frame_cies: HashMap::new(),
+            gcc_except_table: None,
+            personality_name: builder.personality,
+            personality: None,
+            lsdas: Vec::new(),
             functions: SecondaryMap::new(),
             data_objects: SecondaryMap::new(),
             traps: SecondaryMap::new(),
@@ -148,8 +250,9 @@ impl Backend for ObjectBackend {
         name: &str,
         linkage: Linkage,
         _writable: bool,
+        tls: bool,
         _align: Option<u8>,
-    ) {
+    ) -> ModuleResult<()> {
         let (scope, weak) = translate_linkage(linkage);
 
         if let Some(data) = self.data_objects[id] {
@@ -161,7 +264,11 @@ impl Backend for ObjectBackend {
                 name: name.as_bytes().to_vec(),
                 value: 0,
                 size: 0,
-                kind: SymbolKind::Data,
+                kind: if tls {
+                    SymbolKind::Tls
+                } else {
+                    SymbolKind::Data
+                },
                 scope,
                 weak,
                 section: SymbolSection::Undefined,
@@ -169,6 +276,7 @@ impl Backend for ObjectBackend {
             });
             self.data_objects[id] = Some(symbol_id);
         }
+        Ok(())
     }
 
     fn define_function(
@@ -209,9 +317,14 @@ impl Backend for ObjectBackend {
 
         let symbol = self.functions[func_id].unwrap();
         let section = self.object.section_id(StandardSection::Text);
-        let offset = self
-            .object
-            .add_symbol_data(symbol, section, &code, self.function_alignment);
+        let offset = self.add_symbol_data(
+            symbol,
+            section,
+            SectionKind::Text,
+            &code,
+            self.function_alignment,
+        );
+        self.define_unwind_info(symbol, ctx)?;
         self.traps[func_id] = trap_sink.sites;
         Ok(ObjectCompiledFunction {
             offset,
@@ -226,6 +339,7 @@ impl Backend for ObjectBackend {
         data_id: DataId,
         _name: &str,
         writable: bool,
+        tls: bool,
         align: Option<u8>,
         data_ctx: &DataContext,
         _namespace: &ModuleNamespace<Self>,
@@ -280,16 +394,38 @@ impl Backend for ObjectBackend {
         }
 
         let symbol = self.data_objects[data_id].unwrap();
-        let section = self.object.section_id(if writable {
-            StandardSection::Data
+        let align = u64::from(align.unwrap_or(1));
+
+        // Zero-initialized thread local data doesn't need to be stored in the file, so put it in
+        // `.tbss` instead of `.tdata`.
+        if tls && relocs.is_empty() {
+            if let Init::Zeros { .. } = *init {
+                let section = self.object.section_id(StandardSection::UninitializedTls);
+                let offset = self
+                    .object
+                    .add_symbol_bss(symbol, section, size as u64, align);
+                return Ok(ObjectCompiledData {
+                    offset,
+                    section,
+                    relocs,
+                });
+            }
+        }
+
+        let (section, kind) = if tls {
+            (StandardSection::Tls, SectionKind::Tls)
+        } else if writable {
+            (StandardSection::Data, SectionKind::Data)
         } else if relocs.is_empty() {
-            StandardSection::ReadOnlyData
+            (StandardSection::ReadOnlyData, SectionKind::ReadOnlyData)
         } else {
-            StandardSection::ReadOnlyDataWithRel
-        });
-        let offset =
-            self.object
-                .add_symbol_data(symbol, section, &data, u64::from(align.unwrap_or(1)));
+            (
+                StandardSection::ReadOnlyDataWithRel,
+                SectionKind::ReadOnlyData,
+            )
+        };
+        let section = self.object.section_id(section);
+        let offset = self.add_symbol_data(symbol, section, kind, &data, align);
         Ok(ObjectCompiledData {
             offset,
             section,
@@ -333,19 +469,17 @@ impl Backend for ObjectBackend {
         {
             let offset = func.offset + u64::from(offset);
             let symbol = self.get_symbol(namespace, name);
-            self.object
-                .add_relocation(
-                    func.section,
-                    Relocation {
-                        offset,
-                        size,
-                        kind,
-                        encoding,
-                        symbol,
-                        addend,
-                    },
-                )
-                .unwrap();
+            self.add_relocation(
+                func.section,
+                Relocation {
+                    offset,
+                    size,
+                    kind,
+                    encoding,
+                    symbol,
+                    addend,
+                },
+            );
         }
     }
 
@@ -370,19 +504,17 @@ impl Backend for ObjectBackend {
         {
             let offset = data.offset + u64::from(offset);
             let symbol = self.get_symbol(namespace, name);
-            self.object
-                .add_relocation(
-                    data.section,
-                    Relocation {
-                        offset,
-                        size,
-                        kind,
-                        encoding,
-                        symbol,
-                        addend,
-                    },
-                )
-                .unwrap();
+            self.add_relocation(
+                data.section,
+                Relocation {
+                    offset,
+                    size,
+                    kind,
+                    encoding,
+                    symbol,
+                    addend,
+                },
+            );
         }
     }
 
@@ -400,11 +532,230 @@ impl Backend for ObjectBackend {
             functions: self.functions,
             data_objects: self.data_objects,
             traps: self.traps,
+            name: self.name,
+            output: self.output,
+            sections: self.sections,
+            libcalls: self.libcalls,
+            personality: self.personality,
+            lsdas: self.lsdas,
         }
     }
 }
 
 impl ObjectBackend {
+    /// Append the call frame information of the function defined by `symbol` to the
+    /// `.eh_frame` section.
+    ///
+    /// The FDE of every function refers to the first identical CIE in the section, so functions
+    /// only add a CIE of their own when it differs from those before, such as when they have
+    /// landing pads.
+    fn define_unwind_info(
+        &mut self,
+        symbol: SymbolId,
+        ctx: &cranelift_codegen::Context,
+    ) -> ModuleResult<()> {
+        let (segment, name) = match self.isa.triple().binary_format {
+            BinaryFormat::Elf => (&b""[..], &b".eh_frame"[..]),
+            BinaryFormat::Macho => (&b"__TEXT"[..], &b"__eh_frame"[..]),
+            // COFF uses `.pdata` and `.xdata` instead.
+            _ => return Ok(()),
+        };
+
+        let mut sink = ObjectUnwindSink::default();
+        ctx.emit_unwind_info(&*self.isa, FrameUnwindKind::Libunwind, &mut sink);
+        if sink.data.is_empty() {
+            return Ok(());
+        }
+
+        // The CIE and FDE are followed by a terminator for the unwinder's benefit. Only the
+        // entries themselves belong in the section, since linkers expect the terminator to be at
+        // the end of the whole section.
+        let read_u32 = |data: &[u8], offset: usize| {
+            u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+        };
+        let fde_start = sink.entry_offset;
+        let fde_end = fde_start + 4 + read_u32(&sink.data, fde_start) as usize;
+        let (cie, fde) = sink.data[..fde_end].split_at(fde_start);
+
+        let eh_frame = match self.eh_frame {
+            Some(section) => section,
+            None => {
+                let section = self.object.add_section(
+                    segment.to_vec(),
+                    name.to_vec(),
+                    SectionKind::ReadOnlyData,
+                );
+                if let Some(ref mut sections) = self.sections {
+                    sections.set_eh_frame(section);
+                }
+                self.eh_frame = Some(section);
+                section
+            }
+        };
+        let personality = if sink.personality_relocs.is_empty() {
+            None
+        } else {
+            Some(self.personality_symbol())
+        };
+        let lsda = if sink.lsda.is_empty() {
+            None
+        } else {
+            Some(self.define_lsda(&sink.lsda))
+        };
+
+        // The entries are padded to the pointer size, so there are no gaps between them.
+        let align = u64::from(self.isa.pointer_bytes());
+        let mut relocs = Vec::new();
+        let cie_offset = match self.eh_frame_cies.get(cie) {
+            Some(&offset) => offset,
+            None => {
+                let offset = self.append_eh_frame_data(eh_frame, cie, align);
+                self.eh_frame_cies.insert(cie.to_vec(), offset);
+                relocs.extend(
+                    sink.personality_relocs
+                        .iter()
+                        .map(|&(reloc, off)| (reloc, offset + off as u64, personality.unwrap())),
+                );
+                offset
+            }
+        };
+
+        // The CIE pointer of an FDE is the distance back from the pointer to its CIE, so the
+        // offset of the FDE is needed before appending it.
+        let mut fde = fde.to_vec();
+        let fde_offset = self.object.section_mut(eh_frame).append_data(&[], align);
+        let cie_pointer = fde_offset + 4 - cie_offset;
+        fde[4..8].copy_from_slice(&(cie_pointer as u32).to_le_bytes());
+        self.append_eh_frame_data(eh_frame, &fde, align);
+        let fde_reloc = |&(reloc, off): &(Reloc, FrameUnwindOffset)| {
+            (reloc, fde_offset + (off - fde_start) as u64)
+        };
+        relocs.extend(
+            sink.relocs
+                .iter()
+                .map(fde_reloc)
+                .map(|(reloc, off)| (reloc, off, symbol)),
+        );
+        relocs.extend(
+            sink.lsda_relocs
+                .iter()
+                .map(fde_reloc)
+                .map(|(reloc, off)| (reloc, off, lsda.unwrap())),
+        );
+
+        for (reloc, offset, symbol) in relocs {
+            let (kind, size) = match reloc {
+                Reloc::Abs4 => (RelocationKind::Absolute, 32),
+                Reloc::Abs8 => (RelocationKind::Absolute, 64),
+                Reloc::X86PCRel4 => (RelocationKind::Relative, 32),
+                _ => {
+                    return Err(ModuleError::Backend(format!(
+                        "unhandled relocation {} in the unwind information",
+                        reloc
+                    )))
+                }
+            };
+            self.add_relocation(
+                eh_frame,
+                Relocation {
+                    offset,
+                    size,
+                    kind,
+                    encoding: RelocationEncoding::Generic,
+                    symbol,
+                    addend: 0,
+                },
+            );
+        }
+        Ok(())
+    }
+
+    /// Append `data` to the `.eh_frame` section, and return its offset.
+    fn append_eh_frame_data(&mut self, eh_frame: SectionId, data: &[u8], align: u64) -> u64 {
+        let offset = self.object.section_mut(eh_frame).append_data(data, align);
+        if let Some(ref mut sections) = self.sections {
+            sections.add_data(eh_frame, SectionKind::ReadOnlyData, offset, data, align);
+        }
+        offset
+    }
+
+    /// Get the symbol of the personality routine referenced by the unwind information of
+    /// functions with landing pads.
+    fn personality_symbol(&mut self) -> SymbolId {
+        if let Some(symbol) = self.object.symbol_id(self.personality_name.as_bytes()) {
+            return symbol;
+        }
+        let symbol = self.object.add_symbol(Symbol {
+            name: self.personality_name.as_bytes().to_vec(),
+            value: 0,
+            size: 0,
+            kind: SymbolKind::Text,
+            scope: SymbolScope::Unknown,
+            weak: false,
+            section: SymbolSection::Undefined,
+            flags: SymbolFlags::None,
+        });
+        self.personality = Some(symbol);
+        symbol
+    }
+
+    /// Append the language-specific data area `lsda` of a function to the `.gcc_except_table`
+    /// section, and return the local symbol defining it.
+    fn define_lsda(&mut self, lsda: &[u8]) -> SymbolId {
+        let section = match self.gcc_except_table {
+            Some(section) => section,
+            None => {
+                let (segment, name) = match self.isa.triple().binary_format {
+                    BinaryFormat::Macho => (&b"__TEXT"[..], &b"__gcc_except_tab"[..]),
+                    _ => (&b""[..], &b".gcc_except_table"[..]),
+                };
+                let section = self.object.add_section(
+                    segment.to_vec(),
+                    name.to_vec(),
+                    SectionKind::ReadOnlyData,
+                );
+                self.gcc_except_table = Some(section);
+                section
+            }
+        };
+        let symbol = self.object.add_symbol(Symbol {
+            name: format!("GCC_except_table{}", self.lsdas.len()).into_bytes(),
+            value: 0,
+            size: 0,
+            kind: SymbolKind::Data,
+            scope: SymbolScope::Compilation,
+            weak: false,
+            section: SymbolSection::Undefined,
+            flags: SymbolFlags::None,
+        });
+        self.lsdas.push(symbol);
+        self.add_symbol_data(symbol, section, SectionKind::ReadOnlyData, lsda, 4);
+        symbol
+    }
+
+    /// Add `data` defining `symbol` to `section`, whose kind is `kind`.
+    fn add_symbol_data(
+        &mut self,
+        symbol: SymbolId,
+        section: SectionId,
+        kind: SectionKind,
+        data: &[u8],
+        align: u64,
+    ) -> u64 {
+        let offset = self.object.add_symbol_data(symbol, section, data, align);
+        if let Some(ref mut sections) = self.sections {
+            sections.add_data(section, kind, offset, data, align);
+        }
+        offset
+    }
+
+    fn add_relocation(&mut self, section: SectionId, relocation: Relocation) {
+        if let Some(ref mut sections) = self.sections {
+            sections.add_relocation(section, &relocation);
+        }
+        self.object.add_relocation(section, relocation).unwrap();
+    }
+
     // This should only be called during finalization because it creates
     // symbols for missing libcalls.
     fn get_symbol(
@@ -487,6 +838,12 @@ pub struct ObjectProduct {
     pub data_objects: SecondaryMap<DataId, Option<SymbolId>>,
     /// Trap sites for defined functions.
     pub traps: SecondaryMap<FuncId, Vec<ObjectTrapSite>>,
+    name: String,
+    output: ObjectOutput,
+    sections: Option<Sections>,
+    libcalls: HashMap<ir::LibCall, SymbolId>,
+    personality: Option<SymbolId>,
+    lsdas: Vec<SymbolId>,
 }
 
 impl ObjectProduct {
@@ -502,10 +859,43 @@ impl ObjectProduct {
         self.data_objects[id].unwrap()
     }
 
-    /// Write the object bytes in memory.
-    #[inline]
+    /// Write the bytes of the kind of file set with `ObjectBuilder::output` in memory.
     pub fn emit(self) -> Result<Vec<u8>, String> {
-        self.object.write()
+        let symbols: Vec<_> = self
+            .functions
+            .values()
+            .chain(self.data_objects.values())
+            .filter_map(|&symbol| symbol)
+            .chain(self.libcalls.values().cloned())
+            .chain(self.personality)
+            .chain(self.lsdas.iter().cloned())
+            .collect();
+        match self.output {
+            ObjectOutput::Relocatable => self.object.write().map_err(|e| e.to_string()),
+            ObjectOutput::Archive => {
+                let defined: Vec<_> = symbols
+                    .iter()
+                    .map(|&id| self.object.symbol(id))
+                    .filter(|symbol| {
+                        symbol.section != SymbolSection::Undefined
+                            && symbol.scope != SymbolScope::Compilation
+                    })
+                    .map(|symbol| &symbol.name[..])
+                    .collect();
+                let bytes = self.object.write().map_err(|e| e.to_string())?;
+                Ok(link::archive(&self.name, &bytes, &defined))
+            }
+            ObjectOutput::SharedObject {
+                ref soname,
+                ref needed,
+            } => link::shared_object(
+                &self.object,
+                self.sections.as_ref().unwrap(),
+                &symbols,
+                soname.as_ref().map(String::as_str),
+                needed,
+            ),
+        }
     }
 }
 
@@ -519,6 +909,50 @@ struct RelocRecord {
     addend: Addend,
 }
 
+#[derive(Default)]
+struct ObjectUnwindSink {
+    data: Vec<u8>,
+    relocs: Vec<(Reloc, FrameUnwindOffset)>,
+    entry_offset: FrameUnwindOffset,
+    lsda: Vec<u8>,
+    personality_relocs: Vec<(Reloc, FrameUnwindOffset)>,
+    lsda_relocs: Vec<(Reloc, FrameUnwindOffset)>,
+}
+
+impl FrameUnwindSink for ObjectUnwindSink {
+    fn len(&self) -> FrameUnwindOffset {
+        self.data.len()
+    }
+
+    fn bytes(&mut self, b: &[u8]) {
+        self.data.extend_from_slice(b);
+    }
+
+    fn reloc(&mut self, r: Reloc, off: FrameUnwindOffset) {
+        self.relocs.push((r, off));
+    }
+
+    fn set_entry_offset(&mut self, off: FrameUnwindOffset) {
+        self.entry_offset = off;
+    }
+
+    fn pc_relative(&self) -> bool {
+        true
+    }
+
+    fn lsda(&mut self, b: &[u8]) {
+        self.lsda.extend_from_slice(b);
+    }
+
+    fn reloc_personality(&mut self, r: Reloc, off: FrameUnwindOffset) {
+        self.personality_relocs.push((r, off));
+    }
+
+    fn reloc_lsda(&mut self, r: Reloc, off: FrameUnwindOffset) {
+        self.lsda_relocs.push((r, off));
+    }
+}
+
 #[derive(Default)]
 struct ObjectRelocSink {
     relocs: Vec<RelocRecord>,
@@ -534,7 +968,7 @@ impl RelocSink for ObjectRelocSink {
         offset: CodeOffset,
         reloc: Reloc,
         name: &ir::ExternalName,
-        addend: Addend,
+        mut addend: Addend,
     ) {
         let (kind, encoding, size) = match reloc {
             Reloc::Abs4 => (RelocationKind::Absolute, RelocationEncoding::Generic, 32),
@@ -549,6 +983,28 @@ impl RelocSink for ObjectRelocSink {
                 32,
             ),
             Reloc::X86GOTPCRel4 => (RelocationKind::GotRelative, RelocationEncoding::Generic, 32),
+            Reloc::ElfX86_64TlsGd => (
+                RelocationKind::Elf(R_X86_64_TLSGD),
+                RelocationEncoding::Generic,
+                32,
+            ),
+            Reloc::ElfX86_64GotTpOff => (
+                RelocationKind::Elf(R_X86_64_GOTTPOFF),
+                RelocationEncoding::Generic,
+                32,
+            ),
+            Reloc::MachOX86_64Tlv => {
+                // X86_64_RELOC_TLV already implies the -4 addend of a PC-relative reference.
+                addend += 4;
+                (
+                    RelocationKind::MachO {
+                        value: X86_64_RELOC_TLV,
+                        relative: true,
+                    },
+                    RelocationEncoding::Generic,
+                    32,
+                )
+            }
             // FIXME
             _ => unimplemented!(),
         };
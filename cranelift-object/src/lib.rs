@@ -26,9 +26,12 @@
 )]
 
 mod backend;
+mod link;
 mod traps;
 
-pub use crate::backend::{ObjectBackend, ObjectBuilder, ObjectProduct, ObjectTrapCollection};
+pub use crate::backend::{
+    ObjectBackend, ObjectBuilder, ObjectOutput, ObjectProduct, ObjectTrapCollection,
+};
 pub use crate::traps::{ObjectTrapSink, ObjectTrapSite};
 
 /// Version number of this crate.
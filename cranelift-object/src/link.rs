@@ -0,0 +1,964 @@
+//! Linking of the output of `ObjectBackend` into static archives and shared objects.
+//!
+//! Shared objects are written without the help of a system linker, for x86-64 ELF only. All
+//! their symbols are bound when they are loaded: imported and preemptible symbols are reached
+//! through `.got` entries filled in by the dynamic linker, and calls to them go through `.plt`
+//! entries jumping through those. Pointers stored in data are relocated by the dynamic linker
+//! too, so read-only data containing them is only protected once that's done.
+//!
+//! Following `Linkage`, only symbols with `Linkage::Preemptible` may be preempted; other
+//! exported symbols are protected.
+
+use object::write::{Object, Relocation, SectionId, SymbolId, SymbolSection};
+use object::{RelocationEncoding, RelocationKind, SectionKind, SymbolKind, SymbolScope};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+/// The contents of the sections of an object, which `object` doesn't give access to.
+pub struct Sections {
+    sections: Vec<InputSection>,
+    eh_frame: Option<SectionId>,
+}
+
+struct InputSection {
+    id: SectionId,
+    kind: SectionKind,
+    data: Vec<u8>,
+    align: u64,
+    relocations: Vec<InputRelocation>,
+}
+
+struct InputRelocation {
+    offset: u64,
+    size: u8,
+    kind: RelocationKind,
+    encoding: RelocationEncoding,
+    symbol: SymbolId,
+    addend: i64,
+}
+
+impl Sections {
+    pub fn new() -> Self {
+        Self {
+            sections: Vec::new(),
+            eh_frame: None,
+        }
+    }
+
+    /// Record that `section` is the `.eh_frame` section.
+    pub fn set_eh_frame(&mut self, section: SectionId) {
+        self.eh_frame = Some(section);
+    }
+
+    /// Record `data`, which was added at `offset` in `section`.
+    pub fn add_data(
+        &mut self,
+        section: SectionId,
+        kind: SectionKind,
+        offset: u64,
+        data: &[u8],
+        align: u64,
+    ) {
+        let section = self.section_mut(section, kind);
+        section.align = section.align.max(align);
+        section.data.resize(offset as usize, 0);
+        section.data.extend_from_slice(data);
+    }
+
+    /// Record `relocation`, which was added to `section`.
+    pub fn add_relocation(&mut self, section: SectionId, relocation: &Relocation) {
+        let section = self
+            .sections
+            .iter_mut()
+            .find(|s| s.id == section)
+            .expect("relocation in a section without data");
+        section.relocations.push(InputRelocation {
+            offset: relocation.offset,
+            size: relocation.size,
+            kind: relocation.kind,
+            encoding: relocation.encoding,
+            symbol: relocation.symbol,
+            addend: relocation.addend,
+        });
+    }
+
+    fn section_mut(&mut self, id: SectionId, kind: SectionKind) -> &mut InputSection {
+        let index = match self.sections.iter().position(|s| s.id == id) {
+            Some(index) => index,
+            None => {
+                self.sections.push(InputSection {
+                    id,
+                    kind,
+                    data: Vec::new(),
+                    align: 1,
+                    relocations: Vec::new(),
+                });
+                self.sections.len() - 1
+            }
+        };
+        &mut self.sections[index]
+    }
+}
+
+/// Write a GNU `ar` archive containing `object` as a member called `name`, indexed by the
+/// names of the global symbols it defines.
+pub fn archive(name: &str, object: &[u8], symbols: &[&[u8]]) -> Vec<u8> {
+    let member_name = if name.ends_with(".o") {
+        name.to_string()
+    } else {
+        format!("{}.o", name)
+    };
+
+    // The symbol index gives the offset of the member defining each symbol.
+    let mut names = Vec::new();
+    for symbol in symbols {
+        names.extend_from_slice(symbol);
+        names.push(0);
+    }
+    let index_size = 4 + 4 * symbols.len() + names.len();
+    let mut member_offset = 8 + 60 + index_size + index_size % 2;
+
+    // Names longer than 15 bytes are stored in a table of long names.
+    let long_names = if member_name.len() > 15 {
+        let long_names = format!("{}/\n", member_name);
+        member_offset += 60 + long_names.len() + long_names.len() % 2;
+        Some(long_names)
+    } else {
+        None
+    };
+
+    let mut archive = b"!<arch>\n".to_vec();
+    archive_member(&mut archive, "/", "0", &{
+        let mut index = Vec::with_capacity(index_size);
+        index.extend_from_slice(&(symbols.len() as u32).to_be_bytes());
+        for _ in symbols {
+            index.extend_from_slice(&(member_offset as u32).to_be_bytes());
+        }
+        index.extend_from_slice(&names);
+        index
+    });
+    match long_names {
+        Some(ref long_names) => {
+            archive_member(&mut archive, "//", "0", long_names.as_bytes());
+            archive_member(&mut archive, "/0", "644", object);
+        }
+        None => archive_member(&mut archive, &format!("{}/", member_name), "644", object),
+    }
+    archive
+}
+
+fn archive_member(archive: &mut Vec<u8>, name: &str, mode: &str, contents: &[u8]) {
+    archive.extend_from_slice(
+        format!(
+            "{:<16}{:<12}{:<6}{:<6}{:<8}{:<10}`\n",
+            name,
+            0,
+            0,
+            0,
+            mode,
+            contents.len()
+        )
+        .as_bytes(),
+    );
+    archive.extend_from_slice(contents);
+    if contents.len() % 2 != 0 {
+        archive.push(b'\n');
+    }
+}
+
+const PAGE_SIZE: u64 = 0x1000;
+
+const SHT_PROGBITS: u32 = 1;
+const SHT_SYMTAB: u32 = 2;
+const SHT_STRTAB: u32 = 3;
+const SHT_RELA: u32 = 4;
+const SHT_HASH: u32 = 5;
+const SHT_DYNAMIC: u32 = 6;
+const SHT_DYNSYM: u32 = 11;
+
+const SHF_WRITE: u64 = 1;
+const SHF_ALLOC: u64 = 2;
+const SHF_EXECINSTR: u64 = 4;
+
+const PT_LOAD: u32 = 1;
+const PT_DYNAMIC: u32 = 2;
+const PT_GNU_EH_FRAME: u32 = 0x6474_e550;
+const PT_GNU_STACK: u32 = 0x6474_e551;
+const PT_GNU_RELRO: u32 = 0x6474_e552;
+
+const PF_X: u32 = 1;
+const PF_W: u32 = 2;
+const PF_R: u32 = 4;
+
+const DT_NULL: u64 = 0;
+const DT_NEEDED: u64 = 1;
+const DT_HASH: u64 = 4;
+const DT_STRTAB: u64 = 5;
+const DT_SYMTAB: u64 = 6;
+const DT_RELA: u64 = 7;
+const DT_RELASZ: u64 = 8;
+const DT_RELAENT: u64 = 9;
+const DT_STRSZ: u64 = 10;
+const DT_SYMENT: u64 = 11;
+const DT_SONAME: u64 = 14;
+const DT_FLAGS: u64 = 30;
+const DT_RELACOUNT: u64 = 0x6fff_fff9;
+const DT_FLAGS_1: u64 = 0x6fff_fffb;
+const DF_BIND_NOW: u64 = 8;
+const DF_1_NOW: u64 = 1;
+
+const R_X86_64_64: u32 = 1;
+const R_X86_64_GLOB_DAT: u32 = 6;
+const R_X86_64_RELATIVE: u32 = 8;
+
+const STB_LOCAL: u8 = 0;
+const STB_GLOBAL: u8 = 1;
+const STB_WEAK: u8 = 2;
+const STT_NOTYPE: u8 = 0;
+const STT_OBJECT: u8 = 1;
+const STT_FUNC: u8 = 2;
+const STV_DEFAULT: u8 = 0;
+const STV_PROTECTED: u8 = 3;
+
+/// The size of a `.plt` entry, which is an indirect jump through a `.got` entry.
+const PLT_ENTRY_SIZE: u64 = 8;
+
+/// The sections of a shared object, numbered like their section headers.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Out {
+    Hash = 1,
+    DynSym,
+    DynStr,
+    RelaDyn,
+    Rodata,
+    EhFrameHdr,
+    Text,
+    Plt,
+    EhFrame,
+    DataRelRo,
+    Dynamic,
+    Got,
+    Data,
+    SymTab,
+    StrTab,
+    ShStrTab,
+}
+
+impl Out {
+    fn index(self) -> usize {
+        self as usize - 1
+    }
+}
+
+/// The sections of a shared object, in the order of their section headers. They are also laid
+/// out in this order, the ones up to `.data` being loaded.
+const OUTPUT_SECTIONS: [(Out, &str); 16] = [
+    (Out::Hash, ".hash"),
+    (Out::DynSym, ".dynsym"),
+    (Out::DynStr, ".dynstr"),
+    (Out::RelaDyn, ".rela.dyn"),
+    (Out::Rodata, ".rodata"),
+    (Out::EhFrameHdr, ".eh_frame_hdr"),
+    (Out::Text, ".text"),
+    (Out::Plt, ".plt"),
+    (Out::EhFrame, ".eh_frame"),
+    (Out::DataRelRo, ".data.rel.ro"),
+    (Out::Dynamic, ".dynamic"),
+    (Out::Got, ".got"),
+    (Out::Data, ".data"),
+    (Out::SymTab, ".symtab"),
+    (Out::StrTab, ".strtab"),
+    (Out::ShStrTab, ".shstrtab"),
+];
+
+struct OutputSection {
+    data: Vec<u8>,
+    align: u64,
+    offset: u64,
+}
+
+impl OutputSection {
+    fn append(&mut self, data: &[u8], align: u64) -> u64 {
+        self.align = self.align.max(align);
+        let offset = align_to(self.data.len() as u64, align);
+        self.data.resize(offset as usize, 0);
+        self.data.extend_from_slice(data);
+        offset
+    }
+
+    fn size(&self) -> u64 {
+        self.data.len() as u64
+    }
+}
+
+struct LinkSymbol<'a> {
+    name: &'a [u8],
+    kind: SymbolKind,
+    scope: SymbolScope,
+    weak: bool,
+    size: u64,
+    /// The output section and offset within it of the definition of the symbol.
+    definition: Option<(Out, u64)>,
+}
+
+impl<'a> LinkSymbol<'a> {
+    /// Whether references to the symbol must be resolved by the dynamic linker.
+    fn is_dynamic(&self) -> bool {
+        self.definition.is_none() || self.weak
+    }
+
+    fn is_exported(&self) -> bool {
+        self.definition.is_some() && self.scope == SymbolScope::Dynamic
+    }
+
+    fn info(&self) -> u8 {
+        let binding = if self.scope == SymbolScope::Compilation {
+            STB_LOCAL
+        } else if self.weak {
+            STB_WEAK
+        } else {
+            STB_GLOBAL
+        };
+        let kind = match self.kind {
+            SymbolKind::Text => STT_FUNC,
+            SymbolKind::Data => STT_OBJECT,
+            _ => STT_NOTYPE,
+        };
+        (binding << 4) | kind
+    }
+
+    /// The visibility of the symbol. Exported symbols which can't be preempted are protected,
+    /// as references to them from within the object are bound to their definition.
+    fn visibility(&self) -> u8 {
+        if self.is_exported() && !self.weak {
+            STV_PROTECTED
+        } else {
+            STV_DEFAULT
+        }
+    }
+}
+
+struct DynamicRelocation {
+    offset: u64,
+    kind: u32,
+    symbol: u32,
+    addend: i64,
+}
+
+/// Link the sections of `object`, whose symbols are `symbols`, into an x86-64 ELF shared
+/// object.
+pub fn shared_object(
+    object: &Object,
+    sections: &Sections,
+    symbols: &[SymbolId],
+    soname: Option<&str>,
+    needed: &[String],
+) -> Result<Vec<u8>, String> {
+    let mut outputs: Vec<_> = OUTPUT_SECTIONS
+        .iter()
+        .map(|_| OutputSection {
+            data: Vec::new(),
+            align: 1,
+            offset: 0,
+        })
+        .collect();
+
+    // Merge the input sections into the output sections. Read-only data needing dynamic
+    // relocations goes in `.data.rel.ro`, which is only made read-only once they are applied.
+    let mut placements = Vec::with_capacity(sections.sections.len());
+    for section in &sections.sections {
+        let out = match section.kind {
+            SectionKind::Text => Out::Text,
+            SectionKind::Data => Out::Data,
+            SectionKind::ReadOnlyData if Some(section.id) == sections.eh_frame => Out::EhFrame,
+            SectionKind::ReadOnlyData => {
+                if section
+                    .relocations
+                    .iter()
+                    .any(|r| r.kind == RelocationKind::Absolute)
+                {
+                    Out::DataRelRo
+                } else {
+                    Out::Rodata
+                }
+            }
+            kind => {
+                return Err(format!(
+                    "{:?} sections aren't supported in shared objects",
+                    kind
+                ))
+            }
+        };
+        let offset = outputs[out.index()].append(&section.data, section.align);
+        placements.push((out, offset));
+    }
+    // Unwinders expect a terminator after the last entry.
+    let fde_count = count_fdes(&outputs[Out::EhFrame.index()].data);
+    if fde_count != 0 {
+        outputs[Out::EhFrame.index()].append(&[0; 4], 4);
+    }
+
+    let mut link_symbols = Vec::with_capacity(symbols.len());
+    let mut symbol_indices = HashMap::new();
+    for &id in symbols {
+        let symbol = object.symbol(id);
+        if symbol.kind == SymbolKind::Tls {
+            return Err("thread local data isn't supported in shared objects".to_string());
+        }
+        let definition = match symbol.section {
+            SymbolSection::Section(section) => {
+                let index = sections
+                    .sections
+                    .iter()
+                    .position(|s| s.id == section)
+                    .unwrap();
+                let (out, offset) = placements[index];
+                Some((out, offset + symbol.value))
+            }
+            _ => None,
+        };
+        symbol_indices.insert(id, link_symbols.len());
+        link_symbols.push(LinkSymbol {
+            name: &symbol.name,
+            kind: symbol.kind,
+            scope: symbol.scope,
+            weak: symbol.weak,
+            size: symbol.size,
+            definition,
+        });
+    }
+
+    // Find out which symbols are referenced, and which need `.got` and `.plt` entries.
+    let mut referenced = vec![false; link_symbols.len()];
+    let mut got = Vec::new();
+    let mut plt = Vec::new();
+    let mut got_entries = HashMap::new();
+    let mut plt_entries = HashMap::new();
+    let mut absolute_relocations = 0;
+    for reloc in sections.sections.iter().flat_map(|s| &s.relocations) {
+        let index = *symbol_indices
+            .get(&reloc.symbol)
+            .ok_or_else(|| "relocation against an unknown symbol".to_string())?;
+        let symbol = &link_symbols[index];
+        referenced[index] = true;
+        let (needs_got, needs_plt) = match (reloc.kind, reloc.encoding) {
+            (RelocationKind::Absolute, _) => {
+                absolute_relocations += 1;
+                (false, false)
+            }
+            (RelocationKind::Relative, RelocationEncoding::X86Branch) => {
+                let needs_plt = symbol.definition.is_none();
+                (needs_plt, needs_plt)
+            }
+            (RelocationKind::Relative, _) => (false, false),
+            (RelocationKind::PltRelative, _) => (symbol.is_dynamic(), symbol.is_dynamic()),
+            (RelocationKind::GotRelative, _) => (true, false),
+            (kind, _) => {
+                return Err(format!(
+                    "{:?} relocations aren't supported in shared objects",
+                    kind
+                ))
+            }
+        };
+        if needs_got && !got_entries.contains_key(&index) {
+            got_entries.insert(index, got.len() as u64);
+            got.push(index);
+        }
+        if needs_plt && !plt_entries.contains_key(&index) {
+            plt_entries.insert(index, plt.len() as u64);
+            plt.push(index);
+        }
+    }
+
+    // The dynamic symbol table holds the exported symbols and the referenced imports.
+    let mut dynstr = vec![0];
+    let soname = soname.map(|soname| add_string(&mut dynstr, soname.as_bytes()));
+    let needed: Vec<_> = needed
+        .iter()
+        .map(|library| add_string(&mut dynstr, library.as_bytes()))
+        .collect();
+    let mut dynsym = vec![0];
+    let mut dynsym_indices = HashMap::new();
+    for (index, symbol) in link_symbols.iter().enumerate() {
+        if symbol.is_exported() || (symbol.definition.is_none() && referenced[index]) {
+            dynsym_indices.insert(index, dynsym.len() as u32);
+            dynsym.push(index);
+        }
+    }
+    let dynsym_names: Vec<_> = dynsym[1..]
+        .iter()
+        .map(|&index| add_string(&mut dynstr, link_symbols[index].name))
+        .collect();
+    outputs[Out::DynStr.index()].data = dynstr;
+
+    // Every `.got` entry and absolute relocation needs a dynamic relocation.
+    let dynamic_relocation_count = got.len() + absolute_relocations;
+    let dynamic_entry_count = needed.len() + soname.iter().count() + 12;
+    let has_text = outputs[Out::Text.index()].size() != 0 || !plt.is_empty();
+    let phnum = 5 + u64::from(has_text) + u64::from(fde_count != 0);
+
+    // Lay out the sections, with the same addresses as offsets in the file.
+    let eh_frame_hdr_size = if fde_count == 0 {
+        0
+    } else {
+        12 + 8 * fde_count
+    };
+    for &(out, size, align) in &[
+        (Out::Hash, 4 * (2 + 2 * dynsym.len() as u64), 4),
+        (Out::DynSym, 24 * dynsym.len() as u64, 8),
+        (Out::RelaDyn, 24 * dynamic_relocation_count as u64, 8),
+        (Out::EhFrameHdr, eh_frame_hdr_size, 4),
+        (Out::Plt, PLT_ENTRY_SIZE * plt.len() as u64, 16),
+        (Out::Dynamic, 16 * dynamic_entry_count as u64, 8),
+        (Out::Got, 8 * got.len() as u64, 8),
+    ] {
+        outputs[out.index()].data.resize(size as usize, 0);
+        outputs[out.index()].align = align;
+    }
+    let mut offset = 64 + 56 * phnum;
+    for &(out, _) in &OUTPUT_SECTIONS[..Out::Data.index() + 1] {
+        // Each segment starts on a new page, as does the writable data after the RELRO part.
+        if out == Out::Text || out == Out::EhFrame || out == Out::Data {
+            offset = align_to(offset, PAGE_SIZE);
+        }
+        let section = &mut outputs[out.index()];
+        section.offset = align_to(offset, section.align);
+        offset = section.offset + section.size();
+    }
+    let addresses: Vec<_> = outputs.iter().map(|s| s.offset).collect();
+    let ends: Vec<_> = outputs.iter().map(|s| s.offset + s.size()).collect();
+    let address = |out: Out| addresses[out.index()];
+    let end = |out: Out| ends[out.index()];
+    let symbol_address = |index: usize| {
+        let (out, offset) = link_symbols[index].definition.unwrap();
+        address(out) + offset
+    };
+    let got_address = |index: usize| address(Out::Got) + 8 * got_entries[&index];
+    let plt_address = |index: usize| address(Out::Plt) + PLT_ENTRY_SIZE * plt_entries[&index];
+
+    // Apply the relocations, leaving the dynamic linker those depending on the load address.
+    let mut relative = Vec::new();
+    let mut symbolic = Vec::new();
+    for (section, &(out, section_offset)) in sections.sections.iter().zip(&placements) {
+        for reloc in &section.relocations {
+            let index = symbol_indices[&reloc.symbol];
+            let symbol = &link_symbols[index];
+            let place = address(out) + section_offset + reloc.offset;
+            let value = match (reloc.kind, reloc.size) {
+                (RelocationKind::Absolute, 64) => {
+                    if out == Out::Text {
+                        return Err(format!(
+                            "absolute reference to {} in code; code in shared objects must be \
+                             position independent",
+                            String::from_utf8_lossy(symbol.name)
+                        ));
+                    }
+                    // Unwind information describes the definition in this object, even if
+                    // it's preempted. It may still reference imported personality routines.
+                    if symbol.definition.is_none() || (symbol.weak && out != Out::EhFrame) {
+                        symbolic.push(DynamicRelocation {
+                            offset: place,
+                            kind: R_X86_64_64,
+                            symbol: dynsym_indices[&index],
+                            addend: reloc.addend,
+                        });
+                        0
+                    } else {
+                        let value = symbol_address(index).wrapping_add(reloc.addend as u64);
+                        relative.push(DynamicRelocation {
+                            offset: place,
+                            kind: R_X86_64_RELATIVE,
+                            symbol: 0,
+                            addend: value as i64,
+                        });
+                        value
+                    }
+                }
+                (RelocationKind::Relative, 32) => {
+                    let target = if symbol.definition.is_some() {
+                        symbol_address(index)
+                    } else if reloc.encoding == RelocationEncoding::X86Branch {
+                        plt_address(index)
+                    } else {
+                        return Err(format!(
+                            "PC-relative reference to the imported symbol {}, which must be \
+                             referenced through the GOT",
+                            String::from_utf8_lossy(symbol.name)
+                        ));
+                    };
+                    pc_relative(target, reloc.addend, place)?
+                }
+                (RelocationKind::PltRelative, 32) => {
+                    let target = if plt_entries.contains_key(&index) {
+                        plt_address(index)
+                    } else {
+                        symbol_address(index)
+                    };
+                    pc_relative(target, reloc.addend, place)?
+                }
+                (RelocationKind::GotRelative, 32) => {
+                    pc_relative(got_address(index), reloc.addend, place)?
+                }
+                (kind, size) => {
+                    return Err(format!(
+                        "{}-bit {:?} relocations aren't supported in shared objects",
+                        size, kind
+                    ))
+                }
+            };
+            let start = (section_offset + reloc.offset) as usize;
+            let size = usize::from(reloc.size / 8);
+            outputs[out.index()].data[start..start + size]
+                .copy_from_slice(&value.to_le_bytes()[..size]);
+        }
+    }
+
+    for (entry, &index) in got.iter().enumerate() {
+        let offset = address(Out::Got) + 8 * entry as u64;
+        if link_symbols[index].is_dynamic() {
+            symbolic.push(DynamicRelocation {
+                offset,
+                kind: R_X86_64_GLOB_DAT,
+                symbol: dynsym_indices[&index],
+                addend: 0,
+            });
+        } else {
+            let value = symbol_address(index);
+            outputs[Out::Got.index()].data[8 * entry..8 * entry + 8]
+                .copy_from_slice(&value.to_le_bytes());
+            relative.push(DynamicRelocation {
+                offset,
+                kind: R_X86_64_RELATIVE,
+                symbol: 0,
+                addend: value as i64,
+            });
+        }
+    }
+
+    for &index in &plt {
+        // jmp *got(%rip); xchg %ax, %ax
+        let entry = plt_address(index);
+        let displacement = pc_relative(got_address(index), 0, entry + 6)? as u32;
+        let start = (entry - address(Out::Plt)) as usize;
+        let code = &mut outputs[Out::Plt.index()].data[start..start + PLT_ENTRY_SIZE as usize];
+        code[..2].copy_from_slice(&[0xff, 0x25]);
+        code[2..6].copy_from_slice(&displacement.to_le_bytes());
+        code[6..].copy_from_slice(&[0x66, 0x90]);
+    }
+
+    // The relative relocations come first, as `DT_RELACOUNT` says.
+    let rela_dyn = &mut outputs[Out::RelaDyn.index()].data;
+    rela_dyn.clear();
+    for reloc in relative.iter().chain(&symbolic) {
+        let info = (u64::from(reloc.symbol) << 32) | u64::from(reloc.kind);
+        rela_dyn.extend_from_slice(&reloc.offset.to_le_bytes());
+        rela_dyn.extend_from_slice(&info.to_le_bytes());
+        rela_dyn.extend_from_slice(&reloc.addend.to_le_bytes());
+    }
+
+    let symbol_entry = |table: &mut Vec<u8>, name: u32, index: usize| {
+        let symbol = &link_symbols[index];
+        let (value, shndx) = match symbol.definition {
+            Some((out, _)) => (symbol_address(index), out as u16),
+            None => (0, 0),
+        };
+        table.extend_from_slice(&name.to_le_bytes());
+        table.push(symbol.info());
+        table.push(symbol.visibility());
+        table.extend_from_slice(&shndx.to_le_bytes());
+        table.extend_from_slice(&value.to_le_bytes());
+        table.extend_from_slice(&symbol.size.to_le_bytes());
+    };
+
+    let dynsym_table = &mut outputs[Out::DynSym.index()].data;
+    dynsym_table.truncate(24);
+    for (&index, &name) in dynsym[1..].iter().zip(&dynsym_names) {
+        symbol_entry(dynsym_table, name, index);
+    }
+
+    let bucket_count = dynsym.len();
+    let mut buckets = vec![0u32; bucket_count];
+    let mut chains = vec![0u32; dynsym.len()];
+    for (i, &index) in dynsym.iter().enumerate().skip(1) {
+        let bucket = elf_hash(link_symbols[index].name) as usize % bucket_count;
+        chains[i] = buckets[bucket];
+        buckets[bucket] = i as u32;
+    }
+    let hash = &mut outputs[Out::Hash.index()].data;
+    hash.clear();
+    hash.extend_from_slice(&(bucket_count as u32).to_le_bytes());
+    hash.extend_from_slice(&(chains.len() as u32).to_le_bytes());
+    for word in buckets.iter().chain(&chains) {
+        hash.extend_from_slice(&word.to_le_bytes());
+    }
+
+    let mut dynamic_entries: Vec<_> = needed
+        .iter()
+        .map(|&name| (DT_NEEDED, u64::from(name)))
+        .collect();
+    if let Some(soname) = soname {
+        dynamic_entries.push((DT_SONAME, u64::from(soname)));
+    }
+    dynamic_entries.extend_from_slice(&[
+        (DT_HASH, address(Out::Hash)),
+        (DT_STRTAB, address(Out::DynStr)),
+        (DT_SYMTAB, address(Out::DynSym)),
+        (DT_STRSZ, end(Out::DynStr) - address(Out::DynStr)),
+        (DT_SYMENT, 24),
+        (DT_RELA, address(Out::RelaDyn)),
+        (DT_RELASZ, end(Out::RelaDyn) - address(Out::RelaDyn)),
+        (DT_RELAENT, 24),
+        (DT_RELACOUNT, relative.len() as u64),
+        (DT_FLAGS, DF_BIND_NOW),
+        (DT_FLAGS_1, DF_1_NOW),
+        (DT_NULL, 0),
+    ]);
+    debug_assert_eq!(dynamic_entries.len(), dynamic_entry_count);
+    let dynamic = &mut outputs[Out::Dynamic.index()].data;
+    dynamic.clear();
+    for (tag, value) in dynamic_entries {
+        dynamic.extend_from_slice(&tag.to_le_bytes());
+        dynamic.extend_from_slice(&value.to_le_bytes());
+    }
+
+    if fde_count != 0 {
+        outputs[Out::EhFrameHdr.index()].data = eh_frame_hdr(
+            &outputs[Out::EhFrame.index()].data,
+            address(Out::EhFrame),
+            address(Out::EhFrameHdr),
+        );
+    }
+
+    // The static symbol table lists the local symbols too, for debuggers and profilers.
+    let mut strtab = vec![0];
+    let mut symtab = vec![0; 24];
+    let locals: Vec<_> = (0..link_symbols.len())
+        .filter(|&index| {
+            link_symbols[index].definition.is_some()
+                && link_symbols[index].scope == SymbolScope::Compilation
+        })
+        .collect();
+    for &index in locals.iter().chain(&dynsym[1..]) {
+        let name = add_string(&mut strtab, link_symbols[index].name);
+        symbol_entry(&mut symtab, name, index);
+    }
+    let first_global = locals.len() as u32 + 1;
+    outputs[Out::SymTab.index()].data = symtab;
+    outputs[Out::SymTab.index()].align = 8;
+    outputs[Out::StrTab.index()].data = strtab;
+
+    let mut shstrtab = vec![0];
+    let section_names: Vec<_> = OUTPUT_SECTIONS
+        .iter()
+        .map(|&(_, name)| add_string(&mut shstrtab, name.as_bytes()))
+        .collect();
+    outputs[Out::ShStrTab.index()].data = shstrtab;
+
+    // The sections which aren't loaded follow the loaded ones, and the section headers end the
+    // file.
+    for &(out, _) in &OUTPUT_SECTIONS[Out::SymTab.index()..] {
+        let section = &mut outputs[out.index()];
+        section.offset = align_to(offset, section.align);
+        offset = section.offset + section.size();
+    }
+    let shoff = align_to(offset, 8);
+
+    let mut file = Vec::with_capacity(shoff as usize + 64 * (OUTPUT_SECTIONS.len() + 1));
+    file.extend_from_slice(&[0x7f, b'E', b'L', b'F', 2, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+    file.extend_from_slice(&3u16.to_le_bytes()); // e_type: ET_DYN
+    file.extend_from_slice(&62u16.to_le_bytes()); // e_machine: EM_X86_64
+    file.extend_from_slice(&1u32.to_le_bytes()); // e_version
+    file.extend_from_slice(&0u64.to_le_bytes()); // e_entry
+    file.extend_from_slice(&64u64.to_le_bytes()); // e_phoff
+    file.extend_from_slice(&shoff.to_le_bytes());
+    file.extend_from_slice(&0u32.to_le_bytes()); // e_flags
+    file.extend_from_slice(&64u16.to_le_bytes()); // e_ehsize
+    file.extend_from_slice(&56u16.to_le_bytes()); // e_phentsize
+    file.extend_from_slice(&(phnum as u16).to_le_bytes());
+    file.extend_from_slice(&64u16.to_le_bytes()); // e_shentsize
+    file.extend_from_slice(&(OUTPUT_SECTIONS.len() as u16 + 1).to_le_bytes());
+    file.extend_from_slice(&(Out::ShStrTab as u16).to_le_bytes());
+
+    let segment = |file: &mut Vec<u8>, kind: u32, flags: u32, start: u64, end: u64, align: u64| {
+        file.extend_from_slice(&kind.to_le_bytes());
+        file.extend_from_slice(&flags.to_le_bytes());
+        file.extend_from_slice(&start.to_le_bytes()); // p_offset
+        file.extend_from_slice(&start.to_le_bytes()); // p_vaddr
+        file.extend_from_slice(&start.to_le_bytes()); // p_paddr
+        file.extend_from_slice(&(end - start).to_le_bytes()); // p_filesz
+        file.extend_from_slice(&(end - start).to_le_bytes()); // p_memsz
+        file.extend_from_slice(&align.to_le_bytes());
+    };
+    segment(&mut file, PT_LOAD, PF_R, 0, end(Out::EhFrameHdr), PAGE_SIZE);
+    if has_text {
+        let (start, end) = (address(Out::Text), end(Out::Plt));
+        segment(&mut file, PT_LOAD, PF_R | PF_X, start, end, PAGE_SIZE);
+    }
+    let (start, data_end) = (address(Out::EhFrame), end(Out::Data));
+    segment(&mut file, PT_LOAD, PF_R | PF_W, start, data_end, PAGE_SIZE);
+    let (dynamic_start, dynamic_end) = (address(Out::Dynamic), end(Out::Dynamic));
+    segment(
+        &mut file,
+        PT_DYNAMIC,
+        PF_R | PF_W,
+        dynamic_start,
+        dynamic_end,
+        8,
+    );
+    segment(&mut file, PT_GNU_RELRO, PF_R, start, end(Out::Got), 1);
+    segment(&mut file, PT_GNU_STACK, PF_R | PF_W, 0, 0, 16);
+    if fde_count != 0 {
+        let (start, end) = (address(Out::EhFrameHdr), end(Out::EhFrameHdr));
+        segment(&mut file, PT_GNU_EH_FRAME, PF_R, start, end, 4);
+    }
+
+    for section in &outputs {
+        file.resize(section.offset as usize, 0);
+        file.extend_from_slice(&section.data);
+    }
+    file.resize(shoff as usize, 0);
+
+    file.extend_from_slice(&[0; 64]);
+    for (&(out, _), &name) in OUTPUT_SECTIONS.iter().zip(&section_names) {
+        let (kind, flags, link, info, entsize) = match out {
+            Out::Hash => (SHT_HASH, SHF_ALLOC, Out::DynSym as u32, 0, 4),
+            Out::DynSym => (SHT_DYNSYM, SHF_ALLOC, Out::DynStr as u32, 1, 24),
+            Out::DynStr => (SHT_STRTAB, SHF_ALLOC, 0, 0, 0),
+            Out::RelaDyn => (SHT_RELA, SHF_ALLOC, Out::DynSym as u32, 0, 24),
+            Out::Rodata | Out::EhFrameHdr => (SHT_PROGBITS, SHF_ALLOC, 0, 0, 0),
+            Out::Text => (SHT_PROGBITS, SHF_ALLOC | SHF_EXECINSTR, 0, 0, 0),
+            Out::Plt => (
+                SHT_PROGBITS,
+                SHF_ALLOC | SHF_EXECINSTR,
+                0,
+                0,
+                PLT_ENTRY_SIZE,
+            ),
+            Out::EhFrame | Out::DataRelRo | Out::Data => {
+                (SHT_PROGBITS, SHF_ALLOC | SHF_WRITE, 0, 0, 0)
+            }
+            Out::Dynamic => (
+                SHT_DYNAMIC,
+                SHF_ALLOC | SHF_WRITE,
+                Out::DynStr as u32,
+                0,
+                16,
+            ),
+            Out::Got => (SHT_PROGBITS, SHF_ALLOC | SHF_WRITE, 0, 0, 8),
+            Out::SymTab => (SHT_SYMTAB, 0, Out::StrTab as u32, first_global, 24),
+            Out::StrTab | Out::ShStrTab => (SHT_STRTAB, 0, 0, 0, 0),
+        };
+        let section = &outputs[out.index()];
+        let address = if flags & SHF_ALLOC != 0 {
+            section.offset
+        } else {
+            0
+        };
+        file.extend_from_slice(&name.to_le_bytes());
+        file.extend_from_slice(&kind.to_le_bytes());
+        file.extend_from_slice(&flags.to_le_bytes());
+        file.extend_from_slice(&address.to_le_bytes());
+        file.extend_from_slice(&section.offset.to_le_bytes());
+        file.extend_from_slice(&section.size().to_le_bytes());
+        file.extend_from_slice(&link.to_le_bytes());
+        file.extend_from_slice(&info.to_le_bytes());
+        file.extend_from_slice(&section.align.to_le_bytes());
+        file.extend_from_slice(&entsize.to_le_bytes());
+    }
+    Ok(file)
+}
+
+fn align_to(offset: u64, align: u64) -> u64 {
+    (offset + align - 1) & !(align - 1)
+}
+
+fn add_string(table: &mut Vec<u8>, string: &[u8]) -> u32 {
+    let offset = table.len() as u32;
+    table.extend_from_slice(string);
+    table.push(0);
+    offset
+}
+
+/// Compute the 32-bit displacement from `place` to `target` plus `addend`.
+fn pc_relative(target: u64, addend: i64, place: u64) -> Result<u64, String> {
+    let displacement = (target as i64)
+        .wrapping_add(addend)
+        .wrapping_sub(place as i64);
+    i32::try_from(displacement)
+        .map(|displacement| u64::from(displacement as u32))
+        .map_err(|_| "PC-relative relocation out of range".to_string())
+}
+
+/// The hash function of `.hash` sections.
+fn elf_hash(name: &[u8]) -> u32 {
+    let mut hash = 0u32;
+    for &byte in name {
+        hash = (hash << 4).wrapping_add(u32::from(byte));
+        let high = hash & 0xf000_0000;
+        if high != 0 {
+            hash ^= high >> 24;
+        }
+        hash &= !high;
+    }
+    hash
+}
+
+/// Iterate over the offsets of the entries of `eh_frame`, and whether they are FDEs.
+fn eh_frame_entries(eh_frame: &[u8]) -> impl Iterator<Item = (usize, bool)> + '_ {
+    let read = move |offset: usize| {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(&eh_frame[offset..offset + 4]);
+        u32::from_le_bytes(bytes)
+    };
+    let mut offset = 0;
+    std::iter::from_fn(move || {
+        if offset + 4 > eh_frame.len() || read(offset) == 0 {
+            return None;
+        }
+        let entry = (offset, read(offset + 4) != 0);
+        offset += 4 + read(offset) as usize;
+        Some(entry)
+    })
+}
+
+fn count_fdes(eh_frame: &[u8]) -> u64 {
+    eh_frame_entries(eh_frame).filter(|&(_, fde)| fde).count() as u64
+}
+
+/// Build the `.eh_frame_hdr` section, which lets unwinders binary search the FDEs of the
+/// relocated `eh_frame`.
+fn eh_frame_hdr(eh_frame: &[u8], eh_frame_address: u64, hdr_address: u64) -> Vec<u8> {
+    const DW_EH_PE_UDATA4: u8 = 0x03;
+    const DW_EH_PE_SDATA4: u8 = 0x0b;
+    const DW_EH_PE_PCREL: u8 = 0x10;
+    const DW_EH_PE_DATAREL: u8 = 0x30;
+
+    // The FDEs start with their length, their CIE pointer and the PC-relative address of their
+    // function.
+    let mut table: Vec<_> = eh_frame_entries(eh_frame)
+        .filter(|&(_, fde)| fde)
+        .map(|(offset, _)| {
+            let mut bytes = [0; 4];
+            bytes.copy_from_slice(&eh_frame[offset + 8..offset + 12]);
+            let pc = eh_frame_address + offset as u64 + 8;
+            let function = pc.wrapping_add(i32::from_le_bytes(bytes) as u64);
+            (function, eh_frame_address + offset as u64)
+        })
+        .collect();
+    table.sort();
+
+    let mut hdr = vec![
+        1, // version
+        DW_EH_PE_PCREL | DW_EH_PE_SDATA4,
+        DW_EH_PE_UDATA4,
+        DW_EH_PE_DATAREL | DW_EH_PE_SDATA4,
+    ];
+    let eh_frame_ptr = eh_frame_address as i64 - (hdr_address + 4) as i64;
+    hdr.extend_from_slice(&(eh_frame_ptr as i32).to_le_bytes());
+    hdr.extend_from_slice(&(table.len() as u32).to_le_bytes());
+    for (function, fde) in table {
+        hdr.extend_from_slice(&((function as i64 - hdr_address as i64) as i32).to_le_bytes());
+        hdr.extend_from_slice(&((fde as i64 - hdr_address as i64) as i32).to_le_bytes());
+    }
+    hdr
+}
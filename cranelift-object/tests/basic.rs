@@ -0,0 +1,433 @@
+// The outputs other than relocatable objects are only supported for ELF.
+#![cfg(target_os = "linux")]
+
+use cranelift_codegen::ir::*;
+use cranelift_codegen::isa::{self, CallConv};
+use cranelift_codegen::settings::{self, Configurable};
+use cranelift_codegen::Context;
+use cranelift_frontend::*;
+use cranelift_module::*;
+use cranelift_object::*;
+
+fn object_builder(output: ObjectOutput) -> ObjectBuilder {
+    object_builder_for(cranelift_native::builder().unwrap(), output)
+}
+
+fn object_builder_for(isa_builder: isa::Builder, output: ObjectOutput) -> ObjectBuilder {
+    let mut flag_builder = settings::builder();
+    flag_builder.enable("is_pic").unwrap();
+    let isa = isa_builder.finish(settings::Flags::new(flag_builder));
+    let mut builder = ObjectBuilder::new(
+        isa,
+        "basic".to_string(),
+        ObjectTrapCollection::Disabled,
+        default_libcall_names(),
+    )
+    .unwrap();
+    builder.output(output).unwrap();
+    builder
+}
+
+fn define_function<F>(
+    module: &mut Module<ObjectBackend>,
+    name: &str,
+    linkage: Linkage,
+    sig: Signature,
+    body: F,
+) -> FuncId
+where
+    F: FnOnce(&mut Module<ObjectBackend>, &mut FunctionBuilder, &[Value]) -> Vec<Value>,
+{
+    let func_id = module.declare_function(name, linkage, &sig).unwrap();
+    let mut ctx = Context::new();
+    ctx.func = Function::with_name_signature(ExternalName::user(0, func_id.as_u32()), sig);
+    ctx.func.collect_frame_layout_info();
+    let mut func_ctx = FunctionBuilderContext::new();
+    {
+        let mut bcx = FunctionBuilder::new(&mut ctx.func, &mut func_ctx);
+        let ebb = bcx.create_ebb();
+        bcx.switch_to_block(ebb);
+        bcx.append_ebb_params_for_function_params(ebb);
+        let params = bcx.ebb_params(ebb).to_vec();
+        let results = body(module, &mut bcx, &params);
+        bcx.ins().return_(&results);
+        bcx.seal_all_blocks();
+        bcx.finalize();
+    }
+    module.define_function(func_id, &mut ctx).unwrap();
+    func_id
+}
+
+fn signature(params: &[Type], returns: &[Type]) -> Signature {
+    Signature {
+        params: params.iter().map(|&ty| AbiParam::new(ty)).collect(),
+        returns: returns.iter().map(|&ty| AbiParam::new(ty)).collect(),
+        call_conv: CallConv::SystemV,
+    }
+}
+
+#[test]
+#[cfg(target_arch = "x86_64")]
+fn shared_object() {
+    use std::ffi::CString;
+    use std::{fs, mem, process};
+
+    let mut module: Module<ObjectBackend> =
+        Module::new(object_builder(ObjectOutput::SharedObject {
+            soname: Some("libbasic.so".to_string()),
+            needed: vec![],
+        }));
+
+    // An imported function, called through the PLT.
+    let abs = module
+        .declare_function(
+            "abs",
+            Linkage::Import,
+            &signature(&[types::I32], &[types::I32]),
+        )
+        .unwrap();
+    let double = define_function(
+        &mut module,
+        "double",
+        Linkage::Local,
+        signature(&[types::I32], &[types::I32]),
+        |_, bcx, params| vec![bcx.ins().iadd(params[0], params[0])],
+    );
+    define_function(
+        &mut module,
+        "double_abs",
+        Linkage::Export,
+        signature(&[types::I32], &[types::I32]),
+        |module, bcx, params| {
+            let abs = module.declare_func_in_func(abs, bcx.func);
+            let call = bcx.ins().call(abs, &[params[0]]);
+            let value = bcx.inst_results(call)[0];
+            let double = module.declare_func_in_func(double, bcx.func);
+            let call = bcx.ins().call(double, &[value]);
+            vec![bcx.inst_results(call)[0]]
+        },
+    );
+
+    // A preemptible function, called through the PLT too.
+    let seven = define_function(
+        &mut module,
+        "seven",
+        Linkage::Preemptible,
+        signature(&[], &[types::I32]),
+        |_, bcx, _| vec![bcx.ins().iconst(types::I32, 7)],
+    );
+    define_function(
+        &mut module,
+        "call_seven",
+        Linkage::Export,
+        signature(&[], &[types::I32]),
+        |module, bcx, _| {
+            let seven = module.declare_func_in_func(seven, bcx.func);
+            let call = bcx.ins().call(seven, &[]);
+            vec![bcx.inst_results(call)[0]]
+        },
+    );
+
+    // Writable data, and read-only data holding a pointer which must be relocated.
+    let counter = module
+        .declare_data("counter", Linkage::Export, true, false, Some(8))
+        .unwrap();
+    let mut data_ctx = DataContext::new();
+    data_ctx.define(41u64.to_le_bytes().to_vec().into_boxed_slice());
+    module.define_data(counter, &data_ctx).unwrap();
+    let increment = define_function(
+        &mut module,
+        "increment",
+        Linkage::Export,
+        signature(&[], &[types::I64]),
+        |module, bcx, _| {
+            let counter = module.declare_data_in_func(counter, bcx.func);
+            let address = bcx.ins().symbol_value(types::I64, counter);
+            let value = bcx.ins().load(types::I64, MemFlags::trusted(), address, 0);
+            let value = bcx.ins().iadd_imm(value, 1);
+            bcx.ins().store(MemFlags::trusted(), value, address, 0);
+            vec![value]
+        },
+    );
+    let table = module
+        .declare_data("table", Linkage::Export, false, false, Some(8))
+        .unwrap();
+    let mut data_ctx = DataContext::new();
+    data_ctx.define_zeroinit(8);
+    let increment_ref = data_ctx.import_function(ExternalName::user(0, increment.as_u32()));
+    data_ctx.write_function_addr(0, increment_ref);
+    module.define_data(table, &data_ctx).unwrap();
+
+    let bytes = module.finish().emit().unwrap();
+    let path = std::env::temp_dir().join(format!("libbasic-{}.so", process::id()));
+    fs::write(&path, bytes).unwrap();
+
+    unsafe {
+        let path_c = CString::new(path.to_str().unwrap()).unwrap();
+        let handle = libc::dlopen(path_c.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL);
+        fs::remove_file(&path).unwrap();
+        assert!(!handle.is_null(), "dlopen failed");
+        let symbol = |name: &str| {
+            let name = CString::new(name).unwrap();
+            let address = libc::dlsym(handle, name.as_ptr());
+            assert!(!address.is_null(), "{:?} isn't exported", name);
+            address
+        };
+
+        let double_abs: extern "C" fn(i32) -> i32 = mem::transmute(symbol("double_abs"));
+        assert_eq!(double_abs(-21), 42);
+        let call_seven: extern "C" fn() -> i32 = mem::transmute(symbol("call_seven"));
+        assert_eq!(call_seven(), 7);
+
+        let counter = symbol("counter") as *const u64;
+        let table = symbol("table") as *const extern "C" fn() -> u64;
+        assert_eq!((*table)(), 42);
+        assert_eq!(*counter, 42);
+        libc::dlclose(handle);
+    }
+}
+
+#[test]
+#[cfg(target_arch = "x86_64")]
+fn landing_pads() {
+    use std::ffi::CString;
+    use std::{fs, mem, process};
+
+    let mut module: Module<ObjectBackend> =
+        Module::new(object_builder(ObjectOutput::SharedObject {
+            soname: None,
+            needed: vec!["libgcc_s.so.1".to_string()],
+        }));
+
+    // Only Cranelift functions are unwound through, so that the landing pad of `catch` is found
+    // by `__gcc_personality_v0`.
+    let forced_unwind = module
+        .declare_function(
+            "_Unwind_ForcedUnwind",
+            Linkage::Import,
+            &signature(&[types::I64, types::I64, types::I64], &[types::I32]),
+        )
+        .unwrap();
+    let throw = define_function(
+        &mut module,
+        "throw",
+        Linkage::Local,
+        signature(&[types::I64, types::I64], &[]),
+        |module, bcx, params| {
+            let forced_unwind = module.declare_func_in_func(forced_unwind, bcx.func);
+            let null = bcx.ins().iconst(types::I64, 0);
+            bcx.ins().call(forced_unwind, &[params[0], params[1], null]);
+            vec![]
+        },
+    );
+    define_function(
+        &mut module,
+        "catch",
+        Linkage::Export,
+        signature(&[types::I64, types::I64], &[types::I32]),
+        |module, bcx, params| {
+            let throw = module.declare_func_in_func(throw, bcx.func);
+            let normal = bcx.create_ebb();
+            let landing_pad = bcx.create_ebb();
+            bcx.ins()
+                .invoke(throw, landing_pad, &[params[0], params[1]]);
+            bcx.ins().jump(normal, &[]);
+            bcx.switch_to_block(landing_pad);
+            let one = bcx.ins().iconst(types::I32, 1);
+            bcx.ins().return_(&[one]);
+            bcx.switch_to_block(normal);
+            vec![bcx.ins().iconst(types::I32, 0)]
+        },
+    );
+
+    let bytes = module.finish().emit().unwrap();
+    let path = std::env::temp_dir().join(format!("libinvoke-{}.so", process::id()));
+    fs::write(&path, bytes).unwrap();
+
+    #[repr(C, align(16))]
+    struct UnwindException([u64; 4]);
+    type Stop = extern "C" fn(i32, i32, u64, *mut UnwindException, *mut u8, *mut u8) -> i32;
+
+    extern "C" fn stop(
+        _version: i32,
+        actions: i32,
+        _class: u64,
+        _exception: *mut UnwindException,
+        _context: *mut u8,
+        _arg: *mut u8,
+    ) -> i32 {
+        const _UA_END_OF_STACK: i32 = 16;
+        if actions & _UA_END_OF_STACK != 0 {
+            process::abort();
+        }
+        0 // _URC_NO_REASON
+    }
+
+    unsafe {
+        let path_c = CString::new(path.to_str().unwrap()).unwrap();
+        let handle = libc::dlopen(path_c.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL);
+        fs::remove_file(&path).unwrap();
+        assert!(!handle.is_null(), "dlopen failed");
+        let name = CString::new("catch").unwrap();
+        let catch = libc::dlsym(handle, name.as_ptr());
+        assert!(!catch.is_null(), "catch isn't exported");
+
+        let catch: extern "C" fn(*mut UnwindException, Stop) -> i32 = mem::transmute(catch);
+        let mut exception = UnwindException([u64::from_le_bytes(*b"CLIFTEST"), 0, 0, 0]);
+        assert_eq!(catch(&mut exception, stop), 1);
+        libc::dlclose(handle);
+    }
+}
+
+#[test]
+fn personality() {
+    use object::{Object, ObjectSection, RelocationTarget};
+
+    let isa_builder = isa::lookup_by_name("x86_64-unknown-linux-gnu").unwrap();
+    let mut builder = object_builder_for(isa_builder, ObjectOutput::Relocatable);
+    builder.personality("my_personality".to_string());
+    let mut module: Module<ObjectBackend> = Module::new(builder);
+    let callee = module
+        .declare_function("callee", Linkage::Import, &signature(&[], &[]))
+        .unwrap();
+    define_function(
+        &mut module,
+        "caller",
+        Linkage::Export,
+        signature(&[], &[]),
+        |module, bcx, _| {
+            let callee = module.declare_func_in_func(callee, bcx.func);
+            let normal = bcx.create_ebb();
+            let landing_pad = bcx.create_ebb();
+            bcx.ins().invoke(callee, landing_pad, &[]);
+            bcx.ins().jump(normal, &[]);
+            bcx.switch_to_block(landing_pad);
+            bcx.ins().return_(&[]);
+            bcx.switch_to_block(normal);
+            vec![]
+        },
+    );
+    let bytes = module.finish().emit().unwrap();
+
+    // The CIE references the configured personality routine instead of `__gcc_personality_v0`.
+    let file = object::File::parse(&bytes).unwrap();
+    let eh_frame = file.section_by_name(".eh_frame").unwrap();
+    let targets = eh_frame
+        .relocations()
+        .filter_map(|(_, reloc)| match reloc.target() {
+            RelocationTarget::Symbol(index) => file.symbol_by_index(index).unwrap().name(),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert!(targets.contains(&"my_personality"));
+    assert!(file
+        .symbols()
+        .all(|(_, symbol)| symbol.name() != Some("__gcc_personality_v0")));
+}
+
+#[test]
+fn archive() {
+    let mut module: Module<ObjectBackend> = Module::new(object_builder(ObjectOutput::Archive));
+    define_function(
+        &mut module,
+        "exported",
+        Linkage::Export,
+        signature(&[], &[]),
+        |_, _, _| vec![],
+    );
+    define_function(
+        &mut module,
+        "local",
+        Linkage::Local,
+        signature(&[], &[]),
+        |_, _, _| vec![],
+    );
+
+    let archive = module.finish().emit().unwrap();
+    assert!(archive.starts_with(b"!<arch>\n/               "));
+    // The symbol index only lists the global symbol, defined by the member following it.
+    assert_eq!(&archive[68..72], &1u32.to_be_bytes());
+    assert_eq!(&archive[76..85], b"exported\0");
+    assert_eq!(&archive[86..95], b"basic.o/ ");
+    let member = u32::from_be_bytes([archive[72], archive[73], archive[74], archive[75]]);
+    assert_eq!(member, 86);
+}
+
+#[test]
+fn eh_frame() {
+    use gimli::{BaseAddresses, CieOrFde, EhFrame, LittleEndian, UnwindSection};
+    use object::{Object, ObjectSection, RelocationKind, RelocationTarget};
+
+    let isa_builder = isa::lookup_by_name("x86_64-unknown-linux-gnu").unwrap();
+    let mut module: Module<ObjectBackend> =
+        Module::new(object_builder_for(isa_builder, ObjectOutput::Relocatable));
+    for name in &["first", "second"] {
+        define_function(
+            &mut module,
+            name,
+            Linkage::Export,
+            signature(&[types::I64], &[types::I64]),
+            |_, _, params| params.to_vec(),
+        );
+    }
+    let bytes = module.finish().emit().unwrap();
+
+    let file = object::File::parse(&bytes).unwrap();
+    let section = file.section_by_name(".eh_frame").unwrap();
+    let data = section.data().unwrap();
+
+    // Both FDEs share the CIE at the start of the section.
+    let eh_frame = EhFrame::new(data, LittleEndian);
+    let bases = BaseAddresses::default().set_eh_frame(0);
+    let mut entries = eh_frame.entries(&bases);
+    let mut fde_offsets = Vec::new();
+    while let Some(entry) = entries.next().unwrap() {
+        match entry {
+            CieOrFde::Cie(cie) => {
+                assert_eq!(cie.offset(), 0, "unexpected second CIE");
+                assert_eq!(
+                    cie.fde_address_encoding(),
+                    Some(gimli::DwEhPe(
+                        gimli::DW_EH_PE_pcrel.0 | gimli::DW_EH_PE_sdata4.0
+                    ))
+                );
+            }
+            CieOrFde::Fde(partial) => {
+                let fde = partial
+                    .parse(|_, bases, offset| eh_frame.cie_from_offset(bases, offset))
+                    .unwrap();
+                assert_eq!(fde.cie().offset(), 0);
+                fde_offsets.push(fde.offset() as u64);
+            }
+        }
+    }
+    assert_eq!(fde_offsets.len(), 2);
+
+    // The address of the function in each FDE is PC-relative, after its length and CIE pointer.
+    let address = |name| {
+        file.symbols()
+            .find(|(_, symbol)| symbol.name() == Some(name))
+            .unwrap()
+            .1
+            .address()
+    };
+    let relocs = section
+        .relocations()
+        .map(|(offset, reloc)| {
+            let symbol = match reloc.target() {
+                RelocationTarget::Symbol(index) => file.symbol_by_index(index).unwrap(),
+                target => panic!("unexpected relocation target {:?}", target),
+            };
+            assert_eq!(reloc.kind(), RelocationKind::Relative);
+            assert_eq!(reloc.size(), 32);
+            (offset, symbol.address() + reloc.addend() as u64)
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        relocs,
+        vec![
+            (fde_offsets[0] + 8, address("first")),
+            (fde_offsets[1] + 8, address("second")),
+        ]
+    );
+}
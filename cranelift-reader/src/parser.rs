@@ -3,9 +3,11 @@
 use crate::error::{Location, ParseError, ParseResult};
 use crate::isaspec;
 use crate::lexer::{LexError, Lexer, LocatedError, LocatedToken, Token};
+use crate::run_command::{Comparison, Invocation, RunCommand};
 use crate::sourcemap::SourceMap;
 use crate::testcommand::TestCommand;
 use crate::testfile::{Comment, Details, Feature, TestFile};
+use cranelift_codegen::data_value::DataValue;
 use cranelift_codegen::entity::EntityRef;
 use cranelift_codegen::ir;
 use cranelift_codegen::ir::entities::AnyEntity;
@@ -36,6 +38,21 @@ pub fn parse_functions(text: &str) -> ParseResult<Vec<Function>> {
         .map(|file| file.functions.into_iter().map(|(func, _)| func).collect())
 }
 
+/// Parse a run command from the `text` of a comment attached to `func`, e.g.
+/// `; run: %add(1, 2) == 3`.
+///
+/// Returns `None` if the comment is not a run command.
+pub fn parse_run_command(text: &str, func: &Function) -> ParseResult<Option<RunCommand>> {
+    let _tt = timing::parse_text();
+    // Comments include their leading `;`, which the lexer would skip as a comment.
+    let text = text.trim_start_matches(|c: char| c == ';' || c.is_whitespace());
+    let mut parser = Parser::new(text);
+    match parser.token() {
+        Some(Token::Identifier("run")) => parser.parse_run_command(func).map(Some),
+        _ => Ok(None),
+    }
+}
+
 /// Options for configuring the parsing of filetests.
 pub struct ParseOptions<'a> {
     /// Compiler passes to run on the parsed functions.
@@ -201,6 +218,7 @@ impl<'a> Context<'a> {
                 name: ExternalName::testcase(""),
                 offset: Imm64::new(0),
                 colocated: false,
+                tls: false,
             });
         }
         self.function.global_values[gv] = data;
@@ -325,6 +343,16 @@ impl<'a> Context<'a> {
         Ok(())
     }
 
+    // Set the stack limit of the function, which must not already be set.
+    fn add_stack_limit(&mut self, limit: GlobalValue, loc: Location) -> ParseResult<()> {
+        if self.function.stack_limit.is_some() {
+            return err!(loc, "stack limit defined twice");
+        }
+        self.check_gv(limit, loc)?;
+        self.function.stack_limit = Some(limit);
+        Ok(())
+    }
+
     // Resolve a reference to a jump table.
     fn check_jt(&self, jt: JumpTable, loc: Location) -> ParseResult<()> {
         if !self.map.contains_jt(jt) {
@@ -1296,6 +1324,7 @@ impl<'a> Parser<'a> {
     //                   * function-decl
     //                   * signature-decl
     //                   * jump-table-decl
+    //                   * stack-limit-decl
     //
     // The parsed decls are added to `ctx` rather than returned.
     fn parse_preamble(&mut self, ctx: &mut Context) -> ParseResult<()> {
@@ -1339,6 +1368,11 @@ impl<'a> Parser<'a> {
                     self.parse_jump_table_decl()
                         .and_then(|(jt, dat)| ctx.add_jt(jt, dat, self.loc))
                 }
+                Some(Token::Identifier("stack_limit")) => {
+                    self.start_gathering_comments();
+                    self.parse_stack_limit_decl()
+                        .and_then(|gv| ctx.add_stack_limit(gv, self.loc))
+                }
                 // More to come..
                 _ => return Ok(()),
             }?;
@@ -1391,7 +1425,7 @@ impl<'a> Parser<'a> {
     // global-val-desc ::= "vmctx"
     //                   | "load" "." type "notrap" "aligned" GlobalValue(base) [offset]
     //                   | "iadd_imm" "(" GlobalValue(base) ")" imm64
-    //                   | "symbol" ["colocated"] name + imm64
+    //                   | "symbol" ["colocated"] ["tls"] name + imm64
     //
     fn parse_global_value_decl(&mut self) -> ParseResult<(GlobalValue, GlobalValueData)> {
         let gv = self.match_gv("expected global value number: gv«n»")?;
@@ -1440,12 +1474,14 @@ impl<'a> Parser<'a> {
             }
             "symbol" => {
                 let colocated = self.optional(Token::Identifier("colocated"));
+                let tls = self.optional(Token::Identifier("tls"));
                 let name = self.parse_external_name()?;
                 let offset = self.optional_offset_imm64()?;
                 GlobalValueData::Symbol {
                     name,
                     offset,
                     colocated,
+                    tls,
                 }
             }
             other => return err!(self.loc, "Unknown global value kind '{}'", other),
@@ -1721,6 +1757,21 @@ impl<'a> Parser<'a> {
         Ok((jt, data))
     }
 
+    // Parse a stack limit decl.
+    //
+    // stack-limit-decl ::= * "stack_limit" "=" GlobalValue(gv)
+    fn parse_stack_limit_decl(&mut self) -> ParseResult<GlobalValue> {
+        self.match_identifier("stack_limit", "expected 'stack_limit'")?;
+        self.match_token(Token::Equal, "expected '=' in stack_limit decl")?;
+        let limit = self.match_gv("expected global value")?;
+
+        // Collect any trailing comments.
+        self.token();
+        self.claim_gathered_comments(AnyEntity::Function);
+
+        Ok(limit)
+    }
+
     // Parse a function body, add contents to `ctx`.
     //
     // function-body ::= * { extended-basic-block }
@@ -2579,6 +2630,38 @@ impl<'a> Parser<'a> {
                     args: args.into_value_list(&[callee], &mut ctx.function.dfg.value_lists),
                 }
             }
+            InstructionFormat::Invoke => {
+                let func_ref = self.match_fn("expected function reference")?;
+                ctx.check_fn(func_ref, self.loc)?;
+                self.match_token(Token::LPar, "expected '(' before arguments")?;
+                let args = self.parse_value_list()?;
+                self.match_token(Token::RPar, "expected ')' after arguments")?;
+                self.match_token(Token::Comma, "expected ',' between operands")?;
+                let ebb_num = self.match_ebb("expected landing pad EBB")?;
+                InstructionData::Invoke {
+                    opcode,
+                    func_ref,
+                    destination: ebb_num,
+                    args: args.into_value_list(&[], &mut ctx.function.dfg.value_lists),
+                }
+            }
+            InstructionFormat::InvokeIndirect => {
+                let sig_ref = self.match_sig("expected signature reference")?;
+                ctx.check_sig(sig_ref, self.loc)?;
+                self.match_token(Token::Comma, "expected ',' between operands")?;
+                let callee = self.match_value("expected SSA value callee operand")?;
+                self.match_token(Token::LPar, "expected '(' before arguments")?;
+                let args = self.parse_value_list()?;
+                self.match_token(Token::RPar, "expected ')' after arguments")?;
+                self.match_token(Token::Comma, "expected ',' between operands")?;
+                let ebb_num = self.match_ebb("expected landing pad EBB")?;
+                InstructionData::InvokeIndirect {
+                    opcode,
+                    sig_ref,
+                    destination: ebb_num,
+                    args: args.into_value_list(&[callee], &mut ctx.function.dfg.value_lists),
+                }
+            }
             InstructionFormat::FuncAddr => {
                 let func_ref = self.match_fn("expected function reference")?;
                 ctx.check_fn(func_ref, self.loc)?;
@@ -2671,6 +2754,32 @@ impl<'a> Parser<'a> {
                 }
             }
 
+            InstructionFormat::AtomicRmw => {
+                let flags = self.optional_memflags();
+                let op = self.match_enum("expected atomic rmw operation")?;
+                let addr = self.match_value("expected SSA value address")?;
+                self.match_token(Token::Comma, "expected ',' between operands")?;
+                let arg = self.match_value("expected SSA value operand")?;
+                InstructionData::AtomicRmw {
+                    opcode,
+                    flags,
+                    op,
+                    args: [addr, arg],
+                }
+            }
+            InstructionFormat::AtomicCas => {
+                let flags = self.optional_memflags();
+                let addr = self.match_value("expected SSA value address")?;
+                self.match_token(Token::Comma, "expected ',' between operands")?;
+                let expected = self.match_value("expected SSA value operand")?;
+                self.match_token(Token::Comma, "expected ',' between operands")?;
+                let replacement = self.match_value("expected SSA value operand")?;
+                InstructionData::AtomicCas {
+                    opcode,
+                    flags,
+                    args: [addr, expected, replacement],
+                }
+            }
             InstructionFormat::StoreComplex => {
                 let flags = self.optional_memflags();
                 let src = self.match_value("expected SSA value operand")?;
@@ -2775,6 +2884,157 @@ impl<'a> Parser<'a> {
         };
         Ok(idata)
     }
+
+    // Parse a run command attached to `func`.
+    //
+    // run-command ::= * "run" [":" invocation comparison expected]
+    //
+    fn parse_run_command(&mut self, func: &Function) -> ParseResult<RunCommand> {
+        self.match_identifier("run", "expected 'run'")?;
+        if self.optional(Token::Colon) {
+            let invocation = self.parse_run_invocation(func)?;
+            let comparison = self.parse_run_comparison()?;
+            let expected = self.parse_run_expected(&func.signature)?;
+            if self.token().is_some() {
+                return err!(self.loc, "unexpected token after run command");
+            }
+            Ok(RunCommand {
+                invocation,
+                comparison,
+                expected,
+            })
+        } else if self.token().is_some() {
+            err!(self.loc, "expected ':' after 'run'")
+        } else {
+            // A bare `run` calls a function without arguments and expects it to return true.
+            let sig = &func.signature;
+            if !(sig.params.is_empty()
+                && sig.returns.len() == 1
+                && sig.returns[0].value_type.is_bool())
+            {
+                return err!(
+                    self.loc,
+                    "a bare 'run' requires a signature like () -> b*; use 'run: {}(...) == ...'",
+                    func.name
+                );
+            }
+            Ok(RunCommand {
+                invocation: Invocation::new(func.name.clone(), Vec::new()),
+                comparison: Comparison::Equals,
+                expected: vec![DataValue::B(true)],
+            })
+        }
+    }
+
+    // Parse the function invocation of a run command.
+    //
+    // invocation ::= * name "(" [data-value {"," data-value}] ")"
+    //
+    fn parse_run_invocation(&mut self, func: &Function) -> ParseResult<Invocation> {
+        let name = self.parse_external_name()?;
+        if name != func.name {
+            return err!(
+                self.loc,
+                "run command invokes {} but is attached to {}",
+                name,
+                func.name
+            );
+        }
+        self.match_token(Token::LPar, "expected '(' before invocation arguments")?;
+        let mut args = Vec::with_capacity(func.signature.params.len());
+        for (i, param) in func.signature.params.iter().enumerate() {
+            if i > 0 {
+                self.match_token(Token::Comma, "expected ',' between invocation arguments")?;
+            }
+            args.push(self.parse_data_value(param.value_type)?);
+        }
+        self.match_token(Token::RPar, "expected ')' after invocation arguments")?;
+        Ok(Invocation::new(name, args))
+    }
+
+    // Parse the comparison of a run command.
+    //
+    // comparison ::= * "==" | "!="
+    //
+    fn parse_run_comparison(&mut self) -> ParseResult<Comparison> {
+        if self.optional(Token::Equal) {
+            self.match_token(Token::Equal, "expected '=='")?;
+            Ok(Comparison::Equals)
+        } else if self.optional(Token::Not) {
+            self.match_token(Token::Equal, "expected '!='")?;
+            Ok(Comparison::NotEquals)
+        } else {
+            err!(self.loc, "expected '==' or '!='")
+        }
+    }
+
+    // Parse the expected results of a run command. A single result is written on its own, any
+    // other number of results as a bracketed list.
+    //
+    // expected ::= * data-value | "[" [data-value {"," data-value}] "]"
+    //
+    fn parse_run_expected(&mut self, sig: &Signature) -> ParseResult<Vec<DataValue>> {
+        if sig.returns.len() == 1 {
+            return Ok(vec![self.parse_data_value(sig.returns[0].value_type)?]);
+        }
+        self.match_token(Token::LBracket, "expected '[' before the expected results")?;
+        let mut expected = Vec::with_capacity(sig.returns.len());
+        for (i, ret) in sig.returns.iter().enumerate() {
+            if i > 0 {
+                self.match_token(Token::Comma, "expected ',' between expected results")?;
+            }
+            expected.push(self.parse_data_value(ret.value_type)?);
+        }
+        self.match_token(Token::RBracket, "expected ']' after the expected results")?;
+        Ok(expected)
+    }
+
+    // Parse a run command value of type `ty`.
+    fn parse_data_value(&mut self, ty: Type) -> ParseResult<DataValue> {
+        let err_msg = format!("expected a value of type {}", ty);
+        if ty.is_vector() {
+            if ty.bits() != 128 {
+                return err!(
+                    self.loc,
+                    "run commands only support 128-bit vectors, not {}",
+                    ty
+                );
+            }
+            let data = self.match_constant_data(ty)?;
+            let mut bytes = [0; 16];
+            bytes.copy_from_slice(&data.into_vec());
+            // Constant data encodes true boolean lanes as 1, but the boolean vectors computed by
+            // Cranelift have all bits of a true lane set.
+            if ty.lane_type().is_bool() {
+                for lane in bytes.chunks_mut(ty.lane_type().bytes() as usize) {
+                    if lane.iter().any(|&b| b != 0) {
+                        lane.iter_mut().for_each(|b| *b = 0xff);
+                    }
+                }
+            }
+            Ok(DataValue::V128(bytes))
+        } else if ty.is_bool() {
+            Ok(DataValue::B(self.match_bool(&err_msg)?))
+        } else if ty == F32 {
+            Ok(DataValue::F32(self.match_ieee32(&err_msg)?))
+        } else if ty == F64 {
+            Ok(DataValue::F64(self.match_ieee64(&err_msg)?))
+        } else if ty.is_int() {
+            let imm: i64 = self.match_imm64(&err_msg)?.into();
+            // Accept both the signed and the unsigned interpretation of a value.
+            let bits = u32::from(ty.bits());
+            if bits < 64 && (imm < -(1 << (bits - 1)) || imm >= 1 << bits) {
+                return err!(self.loc, "value {} does not fit in {}", imm, ty);
+            }
+            Ok(DataValue::from_integer(imm, ty).expect("an integer type"))
+        } else {
+            err!(
+                self.loc,
+                "run commands do not support values of type {}",
+                ty
+            )
+        }
+    }
 }
 
 #[cfg(test)]
@@ -3024,6 +3284,40 @@ mod tests {
         assert!(!is_warning);
     }
 
+    #[test]
+    fn stack_limit() {
+        let func = Parser::new(
+            "function %foo() system_v {
+                gv0 = vmctx
+                gv1 = load.i64 notrap aligned gv0+8
+                stack_limit = gv1
+            ebb0:
+                return
+            }",
+        )
+        .parse_function(None)
+        .unwrap()
+        .0;
+        assert_eq!(func.stack_limit, Some(GlobalValue::with_number(1).unwrap()));
+
+        let ParseError {
+            location,
+            message,
+            is_warning,
+        } = Parser::new(
+            "function %foo() system_v {
+                gv0 = vmctx
+                stack_limit = gv0
+                stack_limit = gv0",
+        )
+        .parse_function(None)
+        .unwrap_err();
+
+        assert_eq!(location.line_number, 4);
+        assert_eq!(message, "stack limit defined twice");
+        assert!(!is_warning);
+    }
+
     #[test]
     fn duplicate_heap() {
         let ParseError {
@@ -3304,6 +3598,76 @@ mod tests {
         cannot_parse_as_constant_data!(" ", F32X4);
     }
 
+    #[test]
+    fn run_commands() {
+        let functions = parse_functions(
+            "function %add(i32, i32) -> i32 {
+            ebb0(v0: i32, v1: i32):
+                v2 = iadd v0, v1
+                return v2
+            }
+            function %test() -> b1 {
+            ebb0:
+                v0 = bconst.b1 true
+                return v0
+            }
+            function %lanes(i32x4) -> b32x4, f32 {
+            ebb0(v0: i32x4):
+                v1 = vconst.i32x4 0x00
+                v2 = icmp ne v0, v1
+                v3 = f32const 0.0
+                return v2, v3
+            }",
+        )
+        .unwrap();
+        let (add, test, lanes) = (&functions[0], &functions[1], &functions[2]);
+
+        let command = parse_run_command("; run: %add(1, -2) == -1", add)
+            .unwrap()
+            .unwrap();
+        assert_eq!(command.invocation.func, ExternalName::testcase("add"));
+        assert_eq!(
+            command.invocation.args,
+            [DataValue::I32(1), DataValue::I32(-2)]
+        );
+        assert_eq!(command.comparison, Comparison::Equals);
+        assert_eq!(command.expected, [DataValue::I32(-1)]);
+        assert_eq!(command.to_string(), "run: %add(1, -2) == -1");
+
+        let command = parse_run_command("; run: %add(0xffff_ffff, 0) != 0", add)
+            .unwrap()
+            .unwrap();
+        assert_eq!(command.comparison, Comparison::NotEquals);
+        assert_eq!(command.invocation.args[0], DataValue::I32(-1));
+
+        let command = parse_run_command(";run", test).unwrap().unwrap();
+        assert_eq!(command.to_string(), "run: %test() == true");
+
+        let command = parse_run_command(
+            "; run: %lanes([0 1 0 2]) == [[false true false true], 0x0.0]",
+            lanes,
+        )
+        .unwrap()
+        .unwrap();
+        let mut bytes = [0; 16];
+        bytes[4..8].copy_from_slice(&[0xff; 4]);
+        bytes[12..].copy_from_slice(&[0xff; 4]);
+        assert_eq!(
+            command.expected,
+            [DataValue::V128(bytes), DataValue::F32(Ieee32::with_bits(0))]
+        );
+
+        assert_eq!(parse_run_command("; check: run", add).unwrap(), None);
+        assert_eq!(parse_run_command("; running", add).unwrap(), None);
+        assert!(parse_run_command("; run", add).is_err());
+        assert!(parse_run_command("; run %add(1, 2) == 3", add).is_err());
+        assert!(parse_run_command("; run: %sub(1, 2) == 3", add).is_err());
+        assert!(parse_run_command("; run: %add(1) == 3", add).is_err());
+        assert!(parse_run_command("; run: %add(1, 2) = 3", add).is_err());
+        assert!(parse_run_command("; run: %add(1, 0x1_0000_0000) == 3", add).is_err());
+        assert!(parse_run_command("; run: %add(1, 2) == 3 4", add).is_err());
+    }
+
     #[test]
     fn parse_constant_from_booleans() {
         let c = Parser::new("true false true false")
@@ -0,0 +1,175 @@
+//! Run commands.
+//!
+//! Functions in a `.clif` file can have *run commands* appended to them that control how the
+//! function is invoked and checked by the `test run` and `test interpret` commands. The syntax is:
+//!
+//! - `; run`: invoke a function with a signature like `() -> b*` and expect it to return `true`.
+//! - `; run: %fn(42, 0x1.0p1) == false`: invoke `%fn` with the given arguments and compare its
+//!   results against the expected values; `!=` is also accepted. Functions returning zero or
+//!   several values use a bracketed list, e.g. `== [1, 2]`.
+
+use cranelift_codegen::data_value::DataValue;
+use cranelift_codegen::ir::ExternalName;
+use std::fmt::{self, Display, Formatter};
+
+/// A run command parsed from a comment following a function.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RunCommand {
+    /// The function invocation.
+    pub invocation: Invocation,
+    /// How the actual results are compared against `expected`.
+    pub comparison: Comparison,
+    /// The expected results of the invocation.
+    pub expected: Vec<DataValue>,
+}
+
+impl RunCommand {
+    /// Run the command: `invoke_fn` is called with the arguments of the invocation and must
+    /// return the results of the call, or a description of why the call failed (e.g. a trap).
+    /// The results are then compared against the expected values.
+    pub fn run<F>(&self, invoke_fn: F) -> Result<(), String>
+    where
+        F: FnOnce(&[DataValue]) -> Result<Vec<DataValue>, String>,
+    {
+        let actual = invoke_fn(&self.invocation.args)
+            .map_err(|e| format!("Failed: {} {}", self.invocation, e))?;
+        let matched = match self.comparison {
+            Comparison::Equals => actual == self.expected,
+            Comparison::NotEquals => actual != self.expected,
+        };
+        if matched {
+            Ok(())
+        } else {
+            Err(format!(
+                "Failed: {}, actual: {}",
+                self,
+                DisplayDataValues(&actual)
+            ))
+        }
+    }
+}
+
+impl Display for RunCommand {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "run: {} {} {}",
+            self.invocation,
+            self.comparison,
+            DisplayDataValues(&self.expected)
+        )
+    }
+}
+
+/// A call to a function with a list of arguments.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Invocation {
+    /// The name of the function to call.
+    pub func: ExternalName,
+    /// The arguments to pass to the function.
+    pub args: Vec<DataValue>,
+}
+
+impl Invocation {
+    /// Create a new invocation of `func` with `args`.
+    pub fn new(func: ExternalName, args: Vec<DataValue>) -> Self {
+        Self { func, args }
+    }
+}
+
+impl Display for Invocation {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}(", self.func)?;
+        write_data_values(f, &self.args)?;
+        write!(f, ")")
+    }
+}
+
+/// The comparison applied to the actual and expected results of an invocation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Comparison {
+    /// The results must be equal: `==`.
+    Equals,
+    /// The results must differ: `!=`.
+    NotEquals,
+}
+
+impl Display for Comparison {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Comparison::Equals => write!(f, "=="),
+            Comparison::NotEquals => write!(f, "!="),
+        }
+    }
+}
+
+/// Display a list of results: a single value is displayed on its own, any other number of values
+/// as a bracketed list.
+struct DisplayDataValues<'a>(&'a [DataValue]);
+
+impl Display for DisplayDataValues<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        if self.0.len() == 1 {
+            write!(f, "{}", self.0[0])
+        } else {
+            write!(f, "[")?;
+            write_data_values(f, self.0)?;
+            write!(f, "]")
+        }
+    }
+}
+
+fn write_data_values(f: &mut Formatter, values: &[DataValue]) -> fmt::Result {
+    for (i, value) in values.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", value)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(expected: Vec<DataValue>) -> RunCommand {
+        RunCommand {
+            invocation: Invocation::new(
+                ExternalName::testcase("add"),
+                vec![DataValue::I32(1), DataValue::I32(2)],
+            ),
+            comparison: Comparison::Equals,
+            expected,
+        }
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(
+            command(vec![DataValue::I32(3)]).to_string(),
+            "run: %add(1, 2) == 3"
+        );
+        assert_eq!(
+            command(vec![DataValue::I32(3), DataValue::B(true)]).to_string(),
+            "run: %add(1, 2) == [3, true]"
+        );
+    }
+
+    #[test]
+    fn run() {
+        let add = |args: &[DataValue]| match args {
+            [DataValue::I32(a), DataValue::I32(b)] => Ok(vec![DataValue::I32(a + b)]),
+            _ => Err("unexpected arguments".to_string()),
+        };
+        assert_eq!(command(vec![DataValue::I32(3)]).run(add), Ok(()));
+        assert_eq!(
+            command(vec![DataValue::I32(4)]).run(add),
+            Err("Failed: run: %add(1, 2) == 4, actual: 3".to_string())
+        );
+        assert_eq!(
+            command(vec![DataValue::I32(3)]).run(|_| Err("trapped".to_string())),
+            Err("Failed: %add(1, 2) trapped".to_string())
+        );
+    }
+}
@@ -153,6 +153,18 @@ pub enum SerInstData {
         args: Vec<String>,
         sig_ref: String,
     },
+    Invoke {
+        opcode: String,
+        args: Vec<String>,
+        func_ref: String,
+        destination: String,
+    },
+    InvokeIndirect {
+        opcode: String,
+        args: Vec<String>,
+        sig_ref: String,
+        destination: String,
+    },
     FuncAddr {
         opcode: String,
         func_ref: String,
@@ -181,6 +193,17 @@ pub enum SerInstData {
         flags: String,
         offset: String,
     },
+    AtomicRmw {
+        opcode: String,
+        args: [String; 2],
+        flags: String,
+        op: String,
+    },
+    AtomicCas {
+        opcode: String,
+        args: [String; 3],
+        flags: String,
+    },
     StackLoad {
         opcode: String,
         stack_slot: String,
@@ -559,6 +582,42 @@ pub fn get_inst_data(inst_index: Inst, func: &Function) -> SerInstData {
                 sig_ref: sig_ref.to_string(),
             }
         }
+        InstructionData::Invoke {
+            opcode,
+            ref args,
+            func_ref,
+            destination,
+        } => {
+            let mut hold_args = Vec::new();
+            let args_iter = args.as_slice(&func.dfg.value_lists);
+            for arg in args_iter {
+                hold_args.push(arg.to_string());
+            }
+            SerInstData::Invoke {
+                opcode: opcode.to_string(),
+                args: hold_args,
+                func_ref: func_ref.to_string(),
+                destination: destination.to_string(),
+            }
+        }
+        InstructionData::InvokeIndirect {
+            opcode,
+            ref args,
+            sig_ref,
+            destination,
+        } => {
+            let mut hold_args = Vec::new();
+            let args_iter = args.as_slice(&func.dfg.value_lists);
+            for arg in args_iter {
+                hold_args.push(arg.to_string());
+            }
+            SerInstData::InvokeIndirect {
+                opcode: opcode.to_string(),
+                args: hold_args,
+                sig_ref: sig_ref.to_string(),
+                destination: destination.to_string(),
+            }
+        }
         InstructionData::FuncAddr { opcode, func_ref } => SerInstData::FuncAddr {
             opcode: opcode.to_string(),
             func_ref: func_ref.to_string(),
@@ -624,6 +683,36 @@ pub fn get_inst_data(inst_index: Inst, func: &Function) -> SerInstData {
                 offset: offset.to_string(),
             }
         }
+        InstructionData::AtomicRmw {
+            opcode,
+            args,
+            flags,
+            op,
+        } => {
+            let hold_args = [args[0].to_string(), args[1].to_string()];
+            SerInstData::AtomicRmw {
+                opcode: opcode.to_string(),
+                args: hold_args,
+                flags: flags.to_string(),
+                op: op.to_string(),
+            }
+        }
+        InstructionData::AtomicCas {
+            opcode,
+            args,
+            flags,
+        } => {
+            let hold_args = [
+                args[0].to_string(),
+                args[1].to_string(),
+                args[2].to_string(),
+            ];
+            SerInstData::AtomicCas {
+                opcode: opcode.to_string(),
+                args: hold_args,
+                flags: flags.to_string(),
+            }
+        }
         InstructionData::StackLoad {
             opcode,
             stack_slot,
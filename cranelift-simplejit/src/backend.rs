@@ -1,35 +1,50 @@
 //! Defines `SimpleJITBackend`.
 
+use crate::gdb_jit::{self, GdbJitImages};
+use crate::lazy::LazyFunctions;
 use crate::memory::Memory;
+#[cfg(target_os = "linux")]
+use crate::perf::{ProfiledFunction, Profiler};
 use cranelift_codegen::binemit::{
-    Addend, CodeOffset, NullTrapSink, Reloc, RelocSink, Stackmap, StackmapSink,
+    Addend, CodeInfo, CodeOffset, NullTrapSink, Reloc, RelocSink, Stackmap, StackmapSink,
 };
 use cranelift_codegen::isa::TargetIsa;
 use cranelift_codegen::{self, ir, settings};
 use cranelift_module::{
-    Backend, DataContext, DataDescription, DataId, FuncId, Init, Linkage, ModuleNamespace,
-    ModuleResult,
+    Backend, DataContext, DataDescription, DataId, FuncId, FuncOrDataId, Init, Linkage,
+    ModuleError, ModuleNamespace, ModuleResult,
 };
 use cranelift_native;
 #[cfg(not(windows))]
 use libc;
+#[cfg(target_os = "linux")]
+use log::warn;
 use std::collections::HashMap;
 use std::ffi::CString;
-use std::io::Write;
-use std::ptr;
-use target_lexicon::PointerWidth;
+use std::mem;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::{iter, ptr};
+use target_lexicon::{Architecture, PointerWidth};
 #[cfg(windows)]
 use winapi;
 
-const EXECUTABLE_DATA_ALIGNMENT: u8 = 0x10;
+pub const EXECUTABLE_DATA_ALIGNMENT: u8 = 0x10;
 const WRITABLE_DATA_ALIGNMENT: u8 = 0x8;
 const READONLY_DATA_ALIGNMENT: u8 = 0x1;
 
+/// The size of a stub jumping to the current body of a function: a `movabs` of the address of
+/// its slot into `r11`, followed by a `jmp` through `r11`.
+const STUB_SIZE: usize = 13;
+
 /// A builder for `SimpleJITBackend`.
 pub struct SimpleJITBuilder {
     isa: Box<dyn TargetIsa>,
     symbols: HashMap<String, *const u8>,
     libcall_names: Box<dyn Fn(ir::LibCall) -> String>,
+    debug_info: bool,
+    profiling_strategy: ProfilingStrategy,
+    hotswap: bool,
 }
 
 impl SimpleJITBuilder {
@@ -70,6 +85,9 @@ impl SimpleJITBuilder {
             isa,
             symbols,
             libcall_names,
+            debug_info: false,
+            profiling_strategy: ProfilingStrategy::None,
+            hotswap: false,
         }
     }
 
@@ -108,6 +126,60 @@ impl SimpleJITBuilder {
         }
         self
     }
+
+    /// Register compiled functions with debuggers through the GDB JIT compilation interface.
+    ///
+    /// GDB and LLDB then show the names of the functions in backtraces, and can unwind through
+    /// functions compiled with `Function::collect_frame_layout_info`. Each instruction is given
+    /// the line number of its `ir::SourceLoc`, in a file named after its function.
+    ///
+    /// This is only supported on x86-64 so far, and is disabled by default.
+    pub fn debug_info(&mut self, enable: bool) -> &Self {
+        self.debug_info = enable;
+        self
+    }
+
+    /// Set how functions are described to `perf` when they are finalized.
+    ///
+    /// This is only supported on Linux, and jitdump output only on x86-64. If the files can't be
+    /// written, a warning is logged and nothing is described. The default is to describe nothing.
+    pub fn profiling_strategy(&mut self, strategy: ProfilingStrategy) -> &Self {
+        self.profiling_strategy = strategy;
+        self
+    }
+
+    /// Allow finalized functions to be redefined with `Module::redefine_function`.
+    ///
+    /// Every function is then called through a stub jumping to its current body, so that callers
+    /// and function pointers switch to a new body as soon as it is finalized. Previous bodies are
+    /// kept, as they may still be running, until the function is removed with
+    /// `Module::remove_function` or the memory is freed with
+    /// `SimpleJITMemoryHandle::free_memory`.
+    ///
+    /// This is only supported on x86-64, and is disabled by default.
+    pub fn hotswap(&mut self, enable: bool) -> &Self {
+        assert!(
+            !enable || self.isa.triple().architecture == Architecture::X86_64,
+            "hot code replacement is only supported on x86-64"
+        );
+        self.hotswap = enable;
+        self
+    }
+}
+
+/// The ways of describing compiled functions to `perf`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProfilingStrategy {
+    /// Describe nothing.
+    None,
+    /// Append the address, size and name of every function to `/tmp/perf-<pid>.map`.
+    PerfMap,
+    /// Write a perf map, and also write every function to `jit-<pid>.dump` in the current
+    /// directory.
+    ///
+    /// Record with `perf record -k mono` so that the timestamps of the samples match those in
+    /// the dump, then run `perf inject --jit` on the recorded data.
+    JitDump,
 }
 
 /// A `SimpleJITBackend` implements `Backend` and emits code and data into memory where it can be
@@ -115,14 +187,42 @@ impl SimpleJITBuilder {
 ///
 /// See the `SimpleJITBuilder` for a convenient way to construct `SimpleJITBackend` instances.
 pub struct SimpleJITBackend {
-    isa: Box<dyn TargetIsa>,
+    isa: Rc<dyn TargetIsa>,
+    symbols: Rc<Symbols>,
+    debug_info: bool,
+    #[cfg(target_os = "linux")]
+    profiler: Option<Profiler>,
+    hotswap: bool,
+    /// Stubs to point at new function bodies once they are executable.
+    stubs_to_update: Vec<(Stub, *const u8)>,
+    /// The previous bodies of redefined functions.
+    replaced_bodies: HashMap<FuncId, Vec<*mut u8>>,
+    memory: SimpleJITMemoryHandle,
+}
+
+/// The symbols which the functions and data of a module may refer to without defining them.
+pub struct Symbols {
     symbols: HashMap<String, *const u8>,
     libcall_names: Box<dyn Fn(ir::LibCall) -> String>,
-    memory: SimpleJITMemoryHandle,
+}
+
+impl Symbols {
+    /// Look up the symbol `name`.
+    pub fn lookup(&self, name: &str) -> *const u8 {
+        match self.symbols.get(name) {
+            Some(&ptr) => ptr,
+            None => lookup_with_dlsym(name),
+        }
+    }
+
+    /// Look up the symbol implementing `libcall`.
+    pub fn lookup_libcall(&self, libcall: ir::LibCall) -> *const u8 {
+        self.lookup(&(self.libcall_names)(libcall))
+    }
 }
 
 /// A record of a relocation to perform.
-struct RelocRecord {
+pub struct RelocRecord {
     offset: CodeOffset,
     reloc: Reloc,
     name: ir::ExternalName,
@@ -136,10 +236,31 @@ struct StackmapRecord {
     stackmap: Stackmap,
 }
 
+/// The indirection through which a function is called when hot code replacement is enabled,
+/// or when it is defined lazily.
+#[derive(Clone, Copy)]
+pub struct Stub {
+    code: *const u8,
+    slot: *mut u64,
+}
+
+impl Stub {
+    /// Make all callers of the stub jump to `target`.
+    pub fn update(self, target: *const u8) {
+        // The slot may be loaded by running code at any time, so it's updated atomically.
+        let slot = unsafe { &*(self.slot as *const AtomicU64) };
+        slot.store(target as u64, Ordering::Release);
+    }
+}
+
 pub struct SimpleJITCompiledFunction {
     code: *mut u8,
     size: usize,
+    stub: Option<Stub>,
     relocs: Vec<RelocRecord>,
+    debug_image: Option<Vec<u8>>,
+    #[cfg(target_os = "linux")]
+    profiled: Option<ProfiledFunction>,
 }
 
 pub struct SimpleJITCompiledData {
@@ -153,16 +274,21 @@ pub struct SimpleJITMemoryHandle {
     code: Memory,
     readonly: Memory,
     writable: Memory,
+    debug_images: GdbJitImages,
+    lazy: Rc<LazyFunctions>,
 }
 
-impl SimpleJITBackend {
-    fn lookup_symbol(&self, name: &str) -> *const u8 {
-        match self.symbols.get(name) {
-            Some(&ptr) => ptr,
-            None => lookup_with_dlsym(name),
+impl SimpleJITCompiledFunction {
+    /// The address that callers of the function use.
+    fn entry(&self) -> *const u8 {
+        match self.stub {
+            Some(stub) => stub.code,
+            None => self.code,
         }
     }
+}
 
+impl SimpleJITBackend {
     fn get_definition(
         &self,
         namespace: &ModuleNamespace<Self>,
@@ -173,24 +299,86 @@ impl SimpleJITBackend {
                 if namespace.is_function(name) {
                     let (def, name_str, _signature) = namespace.get_function_definition(&name);
                     match def {
-                        Some(compiled) => compiled.code,
-                        None => self.lookup_symbol(name_str),
+                        Some(compiled) => compiled.entry(),
+                        None => self.symbols.lookup(name_str),
                     }
                 } else {
                     let (def, name_str, _writable) = namespace.get_data_definition(&name);
                     match def {
                         Some(compiled) => compiled.storage,
-                        None => self.lookup_symbol(name_str),
+                        None => self.symbols.lookup(name_str),
                     }
                 }
             }
-            ir::ExternalName::LibCall(ref libcall) => {
-                let sym = (self.libcall_names)(*libcall);
-                self.lookup_symbol(&sym)
-            }
+            ir::ExternalName::LibCall(libcall) => self.symbols.lookup_libcall(libcall),
             _ => panic!("invalid ExternalName {}", name),
         }
     }
+
+    /// Allocate a stub jumping to `target`, and the slot it jumps through.
+    fn make_stub(&mut self, target: *const u8) -> ModuleResult<Stub> {
+        let slot = self
+            .memory
+            .writable
+            .allocate(8, WRITABLE_DATA_ALIGNMENT)
+            .map_err(ModuleError::Backend)? as *mut u64;
+        let code = self
+            .memory
+            .code
+            .allocate(STUB_SIZE, EXECUTABLE_DATA_ALIGNMENT)
+            .map_err(ModuleError::Backend)?;
+
+        let mut bytes = [0; STUB_SIZE];
+        // movabs r11, slot
+        bytes[..2].copy_from_slice(&[0x49, 0xbb]);
+        bytes[2..10].copy_from_slice(&(slot as u64).to_le_bytes());
+        // jmp qword ptr [r11]
+        bytes[10..].copy_from_slice(&[0x41, 0xff, 0x23]);
+        unsafe { ptr::copy_nonoverlapping(bytes.as_ptr(), code, STUB_SIZE) };
+
+        let stub = Stub { code, slot };
+        stub.update(target);
+        Ok(stub)
+    }
+
+    /// Compile the function in `ctx` into new memory, to be called through `stub` if any.
+    fn compile_function(
+        &mut self,
+        name: &str,
+        ctx: &cranelift_codegen::Context,
+        code_size: u32,
+        stub: Option<Stub>,
+    ) -> ModuleResult<SimpleJITCompiledFunction> {
+        let size = code_size as usize;
+        let ptr = self
+            .memory
+            .code
+            .allocate(size, EXECUTABLE_DATA_ALIGNMENT)
+            .map_err(ModuleError::Backend)?;
+
+        let relocs = emit_function(&*self.isa, ctx, ptr);
+
+        let debug_image = if self.debug_info {
+            gdb_jit::build_image(&*self.isa, name, ctx, ptr, code_size)
+        } else {
+            None
+        };
+        #[cfg(target_os = "linux")]
+        let profiled = self
+            .profiler
+            .as_ref()
+            .map(|profiler| profiler.describe(&*self.isa, name, ctx, ptr));
+
+        Ok(SimpleJITCompiledFunction {
+            code: ptr,
+            size,
+            stub,
+            relocs,
+            debug_image,
+            #[cfg(target_os = "linux")]
+            profiled,
+        })
+    }
 }
 
 impl<'simple_jit_backend> Backend for SimpleJITBackend {
@@ -205,7 +393,8 @@ impl<'simple_jit_backend> Backend for SimpleJITBackend {
 
     /// SimpleJIT emits code and data into memory, and provides raw pointers
     /// to them. They are valid for the remainder of the program's life, unless
-    /// [`free_memory`] is used.
+    /// [`free_memory`] is used. With hot code replacement enabled, function
+    /// pointers point to stubs which always jump to the latest published body.
     ///
     /// [`free_memory`]: #method.free_memory
     type FinalizedFunction = *const u8;
@@ -219,16 +408,34 @@ impl<'simple_jit_backend> Backend for SimpleJITBackend {
 
     /// Create a new `SimpleJITBackend`.
     fn new(builder: SimpleJITBuilder) -> Self {
+        let isa: Rc<dyn TargetIsa> = Rc::from(builder.isa);
+        let symbols = Rc::new(Symbols {
+            symbols: builder.symbols,
+            libcall_names: builder.libcall_names,
+        });
         let memory = SimpleJITMemoryHandle {
             code: Memory::new(),
             readonly: Memory::new(),
             writable: Memory::new(),
+            debug_images: GdbJitImages::new(),
+            lazy: Rc::new(LazyFunctions::new(isa.clone(), symbols.clone())),
         };
 
+        #[cfg(target_os = "linux")]
+        let profiler = Profiler::new(builder.profiling_strategy, &*isa).unwrap_or_else(|err| {
+            warn!("can't write profiling information: {}", err);
+            None
+        });
+
         Self {
-            isa: builder.isa,
-            symbols: builder.symbols,
-            libcall_names: builder.libcall_names,
+            isa,
+            symbols,
+            debug_info: builder.debug_info,
+            #[cfg(target_os = "linux")]
+            profiler,
+            hotswap: builder.hotswap,
+            stubs_to_update: Vec::new(),
+            replaced_bodies: HashMap::new(),
             memory,
         }
     }
@@ -237,77 +444,135 @@ impl<'simple_jit_backend> Backend for SimpleJITBackend {
         &*self.isa
     }
 
-    fn declare_function(&mut self, _id: FuncId, _name: &str, _linkage: Linkage) {
-        // Nothing to do.
+    fn declare_function(&mut self, id: FuncId, name: &str, _linkage: Linkage) {
+        self.memory.lazy.declare(FuncOrDataId::Func(id), name);
     }
 
     fn declare_data(
         &mut self,
-        _id: DataId,
-        _name: &str,
+        id: DataId,
+        name: &str,
         _linkage: Linkage,
         _writable: bool,
+        tls: bool,
         _align: Option<u8>,
-    ) {
-        // Nothing to do.
+    ) -> ModuleResult<()> {
+        if tls {
+            return Err(ModuleError::Backend(
+                "SimpleJIT doesn't support thread local data".to_owned(),
+            ));
+        }
+        self.memory.lazy.declare(FuncOrDataId::Data(id), name);
+        Ok(())
     }
 
     fn define_function(
         &mut self,
-        _id: FuncId,
+        id: FuncId,
         name: &str,
         ctx: &cranelift_codegen::Context,
         _namespace: &ModuleNamespace<Self>,
         code_size: u32,
     ) -> ModuleResult<Self::CompiledFunction> {
-        let size = code_size as usize;
-        let ptr = self
-            .memory
-            .code
-            .allocate(size, EXECUTABLE_DATA_ALIGNMENT)
-            .expect("TODO: handle OOM etc.");
+        let mut compiled = self.compile_function(name, ctx, code_size, None)?;
+        if self.hotswap {
+            compiled.stub = Some(self.make_stub(compiled.code)?);
+        }
+        self.memory
+            .lazy
+            .define(FuncOrDataId::Func(id), compiled.entry());
+        Ok(compiled)
+    }
 
-        if cfg!(target_os = "linux") && ::std::env::var_os("PERF_BUILDID_DIR").is_some() {
-            let mut map_file = ::std::fs::OpenOptions::new()
-                .create(true)
-                .append(true)
-                .open(format!("/tmp/perf-{}.map", ::std::process::id()))
-                .unwrap();
+    fn redefine_function(
+        &mut self,
+        id: FuncId,
+        name: &str,
+        ctx: &cranelift_codegen::Context,
+        namespace: &ModuleNamespace<Self>,
+        code_size: u32,
+    ) -> ModuleResult<Self::CompiledFunction> {
+        let (old, _name, _signature) = namespace.get_function_definition(&id.into());
+        let old = old.unwrap();
+        match old.stub {
+            // The previous body is still called until the new one is published.
+            Some(stub) => {
+                let compiled = self.compile_function(name, ctx, code_size, Some(stub))?;
+                self.memory.lazy.cancel(id);
+                self.replaced_bodies.entry(id).or_default().push(old.code);
+                Ok(compiled)
+            }
+            None => Err(ModuleError::DuplicateDefinition(name.to_owned())),
+        }
+    }
 
-            let _ = writeln!(map_file, "{:x} {:x} {}", ptr as usize, code_size, name);
+    /// The function is produced and compiled on the thread of the module when it is first called.
+    /// If that fails, the error is logged and the call traps with `LAZY_COMPILATION_TRAP`, as do
+    /// all later calls of the function.
+    fn define_function_lazily(
+        &mut self,
+        id: FuncId,
+        name: &str,
+        signature: &ir::Signature,
+        produce: Box<dyn FnOnce() -> ir::Function>,
+        _namespace: &ModuleNamespace<Self>,
+    ) -> ModuleResult<Self::CompiledFunction> {
+        if self.isa.triple().architecture != Architecture::X86_64 {
+            return Err(ModuleError::Backend(
+                "lazy compilation is only supported on x86-64".to_owned(),
+            ));
         }
 
-        let mut reloc_sink = SimpleJITRelocSink::new();
-        // Ignore traps for now. For now, frontends should just avoid generating code
-        // that traps.
-        let mut trap_sink = NullTrapSink {};
-        let mut stackmap_sink = SimpleJITStackmapSink::new();
-        unsafe {
-            ctx.emit_to_memory(
-                &*self.isa,
-                ptr,
-                &mut reloc_sink,
-                &mut trap_sink,
-                &mut stackmap_sink,
-            )
-        };
+        // The function is called through a stub jumping to the trampoline until it is compiled.
+        let trampoline = self.memory.lazy.trampoline(id, signature);
+        let mut ctx = cranelift_codegen::Context::for_function(trampoline);
+        let CodeInfo { total_size, .. } = ctx.compile(&*self.isa)?;
+        let mut compiled = self.compile_function(name, &ctx, total_size, None)?;
+        let stub = self.make_stub(compiled.code)?;
+        compiled.stub = Some(stub);
+
+        self.memory.lazy.add(id, produce, stub);
+        self.memory.lazy.define(FuncOrDataId::Func(id), stub.code);
+        Ok(compiled)
+    }
 
-        Ok(Self::CompiledFunction {
-            code: ptr,
-            size,
-            relocs: reloc_sink.relocs,
-        })
+    unsafe fn remove_function(
+        &mut self,
+        id: FuncId,
+        _name: &str,
+        func: &Self::CompiledFunction,
+    ) -> ModuleResult<()> {
+        self.memory.lazy.remove(id);
+        let replaced = self.replaced_bodies.remove(&id).unwrap_or_default();
+        for code in replaced.into_iter().chain(iter::once(func.code)) {
+            self.memory.debug_images.unregister(code);
+            self.memory.code.free(code);
+        }
+        if let Some(stub) = func.stub {
+            self.stubs_to_update
+                .retain(|&(pending, _)| pending.slot != stub.slot);
+            self.memory.code.free(stub.code);
+            self.memory.writable.free(stub.slot as *const u8);
+        }
+        Ok(())
     }
 
     fn define_data(
         &mut self,
-        _id: DataId,
+        id: DataId,
         _name: &str,
         writable: bool,
+        tls: bool,
         align: Option<u8>,
         data: &DataContext,
         _namespace: &ModuleNamespace<Self>,
     ) -> ModuleResult<Self::CompiledData> {
+        if tls {
+            return Err(ModuleError::Backend(
+                "SimpleJIT doesn't support thread local data".to_owned(),
+            ));
+        }
+
         let &DataDescription {
             ref init,
             ref function_decls,
@@ -365,6 +630,7 @@ impl<'simple_jit_backend> Backend for SimpleJITBackend {
             });
         }
 
+        self.memory.lazy.define(FuncOrDataId::Data(id), storage);
         Ok(Self::CompiledData {
             storage,
             size,
@@ -397,52 +663,26 @@ impl<'simple_jit_backend> Backend for SimpleJITBackend {
         func: &Self::CompiledFunction,
         namespace: &ModuleNamespace<Self>,
     ) -> Self::FinalizedFunction {
-        use std::ptr::write_unaligned;
-
-        for &RelocRecord {
-            reloc,
-            offset,
-            ref name,
-            addend,
-        } in &func.relocs
+        relocate(func.code, func.size, &func.relocs, |name| {
+            self.get_definition(namespace, name)
+        });
+        if let Some(ref image) = func.debug_image {
+            self.memory.debug_images.register(func.code, image.clone());
+        }
+        #[cfg(target_os = "linux")]
         {
-            let ptr = func.code;
-            debug_assert!((offset as usize) < func.size);
-            let at = unsafe { ptr.offset(offset as isize) };
-            let base = self.get_definition(namespace, name);
-            // TODO: Handle overflow.
-            let what = unsafe { base.offset(addend as isize) };
-            match reloc {
-                Reloc::Abs4 => {
-                    // TODO: Handle overflow.
-                    #[cfg_attr(feature = "cargo-clippy", allow(clippy::cast_ptr_alignment))]
-                    unsafe {
-                        write_unaligned(at as *mut u32, what as u32)
-                    };
-                }
-                Reloc::Abs8 => {
-                    #[cfg_attr(feature = "cargo-clippy", allow(clippy::cast_ptr_alignment))]
-                    unsafe {
-                        write_unaligned(at as *mut u64, what as u64)
-                    };
-                }
-                Reloc::X86PCRel4 | Reloc::X86CallPCRel4 => {
-                    // TODO: Handle overflow.
-                    let pcrel = ((what as isize) - (at as isize)) as i32;
-                    #[cfg_attr(feature = "cargo-clippy", allow(clippy::cast_ptr_alignment))]
-                    unsafe {
-                        write_unaligned(at as *mut i32, pcrel)
-                    };
-                }
-                Reloc::X86GOTPCRel4 | Reloc::X86CallPLTRel4 => panic!("unexpected PIC relocation"),
-                _ => unimplemented!(),
+            if let (Some(profiler), Some(profiled)) = (&mut self.profiler, &func.profiled) {
+                profiler.function_finalized(profiled, func.code, func.size);
             }
         }
-        func.code
+        if let Some(stub) = func.stub {
+            self.stubs_to_update.push((stub, func.code));
+        }
+        func.entry()
     }
 
     fn get_finalized_function(&self, func: &Self::CompiledFunction) -> Self::FinalizedFunction {
-        func.code
+        func.entry()
     }
 
     fn finalize_data(
@@ -498,6 +738,9 @@ impl<'simple_jit_backend> Backend for SimpleJITBackend {
         // Now that we're done patching, prepare the memory for execution!
         self.memory.readonly.set_readonly();
         self.memory.code.set_readable_and_executable();
+        for (stub, code) in self.stubs_to_update.drain(..) {
+            stub.update(code);
+        }
     }
 
     /// SimpleJIT emits code and data into memory as it processes them. This
@@ -555,8 +798,81 @@ fn lookup_with_dlsym(name: &str) -> *const u8 {
     }
 }
 
+/// Emit the function compiled in `ctx` to `code`, and return its relocations.
+pub fn emit_function(
+    isa: &dyn TargetIsa,
+    ctx: &cranelift_codegen::Context,
+    code: *mut u8,
+) -> Vec<RelocRecord> {
+    let mut reloc_sink = SimpleJITRelocSink::new();
+    // Ignore traps for now. For now, frontends should just avoid generating code
+    // that traps.
+    let mut trap_sink = NullTrapSink {};
+    let mut stackmap_sink = SimpleJITStackmapSink::new();
+    unsafe {
+        ctx.emit_to_memory(
+            isa,
+            code,
+            &mut reloc_sink,
+            &mut trap_sink,
+            &mut stackmap_sink,
+        )
+    };
+    reloc_sink.relocs
+}
+
+/// Apply the relocations `relocs` of the `size` bytes of code at `code`, using
+/// `get_definition` to find their targets.
+pub fn relocate<F>(code: *mut u8, size: usize, relocs: &[RelocRecord], get_definition: F)
+where
+    F: Fn(&ir::ExternalName) -> *const u8,
+{
+    use std::ptr::write_unaligned;
+
+    for &RelocRecord {
+        reloc,
+        offset,
+        ref name,
+        addend,
+    } in relocs
+    {
+        debug_assert!((offset as usize) < size);
+        let at = unsafe { code.offset(offset as isize) };
+        let base = get_definition(name);
+        // TODO: Handle overflow.
+        let what = unsafe { base.offset(addend as isize) };
+        match reloc {
+            Reloc::Abs4 => {
+                // TODO: Handle overflow.
+                #[cfg_attr(feature = "cargo-clippy", allow(clippy::cast_ptr_alignment))]
+                unsafe {
+                    write_unaligned(at as *mut u32, what as u32)
+                };
+            }
+            Reloc::Abs8 => {
+                #[cfg_attr(feature = "cargo-clippy", allow(clippy::cast_ptr_alignment))]
+                unsafe {
+                    write_unaligned(at as *mut u64, what as u64)
+                };
+            }
+            Reloc::X86PCRel4 | Reloc::X86CallPCRel4 => {
+                // TODO: Handle overflow.
+                let pcrel = ((what as isize) - (at as isize)) as i32;
+                #[cfg_attr(feature = "cargo-clippy", allow(clippy::cast_ptr_alignment))]
+                unsafe {
+                    write_unaligned(at as *mut i32, pcrel)
+                };
+            }
+            Reloc::X86GOTPCRel4 | Reloc::X86CallPLTRel4 => panic!("unexpected PIC relocation"),
+            _ => unimplemented!(),
+        }
+    }
+}
+
 impl SimpleJITMemoryHandle {
-    /// Free memory allocated for code and data segments of compiled functions.
+    /// Free memory allocated for code and data segments of compiled functions,
+    /// including the previous bodies of redefined functions and the bodies of
+    /// lazily defined functions.
     ///
     /// # Safety
     ///
@@ -565,12 +881,26 @@ impl SimpleJITMemoryHandle {
     /// from that module are currently executing and none of the`fn` pointers
     /// are called afterwards.
     pub unsafe fn free_memory(&mut self) {
+        self.debug_images.unregister_all();
+        self.lazy.free_memory();
         self.code.free_memory();
         self.readonly.free_memory();
         self.writable.free_memory();
     }
 }
 
+impl Drop for SimpleJITMemoryHandle {
+    fn drop(&mut self) {
+        // The trampolines of the functions which haven't been compiled yet hold a raw pointer to
+        // `lazy`, and may still be called since the code memory is leaked. So leak `lazy` too,
+        // along with the ISA, the symbols and the IR producers it owns. It is freed like the rest
+        // when no function is pending, or after `free_memory`.
+        if self.lazy.is_pending() {
+            mem::forget(Rc::clone(&self.lazy));
+        }
+    }
+}
+
 struct SimpleJITRelocSink {
     pub relocs: Vec<RelocRecord>,
 }
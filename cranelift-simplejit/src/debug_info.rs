@@ -0,0 +1,75 @@
+//! Information about compiled functions for debuggers and profilers.
+
+use cranelift_codegen::binemit::{
+    CodeOffset, FrameUnwindKind, FrameUnwindOffset, FrameUnwindSink, Reloc,
+};
+use cranelift_codegen::ir::{Function, SourceLoc};
+use cranelift_codegen::isa::{CallConv, TargetIsa};
+use cranelift_codegen::Context;
+
+/// Get the `.eh_frame` entries of the function, with their reference to the function resolved to
+/// `address`.
+pub fn eh_frame(isa: &dyn TargetIsa, ctx: &Context, address: u64) -> Option<Vec<u8>> {
+    match ctx.func.signature.call_conv {
+        CallConv::SystemV | CallConv::Fast | CallConv::Cold => {}
+        _ => return None,
+    }
+    let mut sink = UnwindSink::default();
+    ctx.emit_unwind_info(isa, FrameUnwindKind::Libunwind, &mut sink);
+    if sink.data.is_empty() {
+        return None;
+    }
+
+    // Debuggers and profilers don't call personality routines, so the references to them and
+    // to the landing pad tables are left null.
+    for (reloc, offset) in sink.relocs {
+        match reloc {
+            Reloc::Abs8 => sink.data[offset..offset + 8].copy_from_slice(&address.to_le_bytes()),
+            _ => panic!("Unhandled unwind reloc {}", reloc),
+        }
+    }
+    Some(sink.data)
+}
+
+/// Get the offsets at which the source location of the instructions of `func` changes, in
+/// increasing order, skipping instructions without a source location.
+pub fn source_locations(isa: &dyn TargetIsa, func: &Function) -> Vec<(CodeOffset, SourceLoc)> {
+    let encinfo = isa.encoding_info();
+    let mut ebbs = func.layout.ebbs().collect::<Vec<_>>();
+    ebbs.sort_by_key(|ebb| func.offsets[*ebb]);
+    let mut locations = Vec::new();
+    let mut last_srcloc = SourceLoc::default();
+    for ebb in ebbs {
+        for (offset, inst, size) in func.inst_offsets(ebb, &encinfo) {
+            let srcloc = func.srclocs[inst];
+            if size == 0 || srcloc.is_default() || srcloc == last_srcloc {
+                continue;
+            }
+            locations.push((offset, srcloc));
+            last_srcloc = srcloc;
+        }
+    }
+    locations
+}
+
+#[derive(Default)]
+struct UnwindSink {
+    data: Vec<u8>,
+    relocs: Vec<(Reloc, FrameUnwindOffset)>,
+}
+
+impl FrameUnwindSink for UnwindSink {
+    fn len(&self) -> FrameUnwindOffset {
+        self.data.len()
+    }
+
+    fn bytes(&mut self, b: &[u8]) {
+        self.data.extend_from_slice(b);
+    }
+
+    fn reloc(&mut self, r: Reloc, off: FrameUnwindOffset) {
+        self.relocs.push((r, off));
+    }
+
+    fn set_entry_offset(&mut self, _: FrameUnwindOffset) {}
+}
@@ -0,0 +1,412 @@
+//! Registration of compiled functions with debuggers through the GDB JIT compilation interface.
+//!
+//! Debuggers set a breakpoint on `__jit_debug_register_code` and, when it is hit, read the
+//! in-memory object file that was just added to or removed from the list rooted at
+//! `__jit_debug_descriptor`. See <https://sourceware.org/gdb/onlinedocs/gdb/JIT-Interface.html>;
+//! LLDB implements the same interface.
+//!
+//! For every function, we synthesize an ELF image containing:
+//!
+//! - a symbol for the function, so that backtraces show its name;
+//! - its `.eh_frame` entry, so that the debugger can unwind through it, if the function was
+//!   compiled with `Function::collect_frame_layout_info`;
+//! - a compilation unit whose line table maps each instruction to a line number equal to its
+//!   `ir::SourceLoc`.
+
+use crate::debug_info::{eh_frame, source_locations};
+use cranelift_codegen::isa::TargetIsa;
+use cranelift_codegen::Context;
+use gimli::write::{
+    Address, AttributeValue, DwarfUnit, EndianVec, LineProgram, LineString, Sections,
+};
+use gimli::{Encoding, Format, LineEncoding, LittleEndian};
+use std::collections::HashMap;
+use std::mem;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use target_lexicon::Architecture;
+
+const JIT_REGISTER_FN: u32 = 1;
+const JIT_UNREGISTER_FN: u32 = 2;
+
+#[repr(C)]
+struct JITCodeEntry {
+    next_entry: *mut JITCodeEntry,
+    prev_entry: *mut JITCodeEntry,
+    symfile_addr: *const u8,
+    symfile_size: u64,
+}
+
+#[repr(C)]
+struct JITDescriptor {
+    version: u32,
+    action_flag: u32,
+    relevant_entry: *mut JITCodeEntry,
+    first_entry: *mut JITCodeEntry,
+}
+
+#[no_mangle]
+static mut __jit_debug_descriptor: JITDescriptor = JITDescriptor {
+    version: 1,
+    action_flag: 0,
+    relevant_entry: ptr::null_mut(),
+    first_entry: ptr::null_mut(),
+};
+
+#[no_mangle]
+#[inline(never)]
+extern "C" fn __jit_debug_register_code() {
+    // The volatile read prevents calls to this function from being optimized away.
+    unsafe { ptr::read_volatile(&__jit_debug_descriptor.action_flag) };
+}
+
+/// Serializes accesses to `__jit_debug_descriptor` by the threads of this process.
+static DESCRIPTOR_LOCK: AtomicBool = AtomicBool::new(false);
+
+/// Update the list of images with `f` and notify the debugger.
+fn update_descriptor<F: FnOnce(&mut JITDescriptor)>(f: F) {
+    while DESCRIPTOR_LOCK
+        .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+        .is_err()
+    {
+        std::thread::yield_now();
+    }
+    unsafe {
+        f(&mut __jit_debug_descriptor);
+        __jit_debug_register_code();
+    }
+    DESCRIPTOR_LOCK.store(false, Ordering::Release);
+}
+
+/// An ELF image that is registered with the debugger as long as this is alive.
+struct Registration {
+    entry: Box<JITCodeEntry>,
+    _image: Box<[u8]>,
+}
+
+impl Registration {
+    fn new(image: Vec<u8>) -> Self {
+        let image = image.into_boxed_slice();
+        let mut entry = Box::new(JITCodeEntry {
+            next_entry: ptr::null_mut(),
+            prev_entry: ptr::null_mut(),
+            symfile_addr: image.as_ptr(),
+            symfile_size: image.len() as u64,
+        });
+        let entry_ptr: *mut JITCodeEntry = &mut *entry;
+        update_descriptor(|descriptor| unsafe {
+            entry.next_entry = descriptor.first_entry;
+            if !descriptor.first_entry.is_null() {
+                (*descriptor.first_entry).prev_entry = entry_ptr;
+            }
+            descriptor.first_entry = entry_ptr;
+            descriptor.relevant_entry = entry_ptr;
+            descriptor.action_flag = JIT_REGISTER_FN;
+        });
+        Self {
+            entry,
+            _image: image,
+        }
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        let entry = &mut *self.entry;
+        update_descriptor(|descriptor| unsafe {
+            if entry.prev_entry.is_null() {
+                descriptor.first_entry = entry.next_entry;
+            } else {
+                (*entry.prev_entry).next_entry = entry.next_entry;
+            }
+            if !entry.next_entry.is_null() {
+                (*entry.next_entry).prev_entry = entry.prev_entry;
+            }
+            descriptor.relevant_entry = entry;
+            descriptor.action_flag = JIT_UNREGISTER_FN;
+        });
+    }
+}
+
+/// The images registered for the functions of a module. Like the functions they describe, they
+/// are leaked by default.
+pub struct GdbJitImages {
+    /// The registrations, indexed by the address of the function they describe.
+    registrations: HashMap<usize, Registration>,
+}
+
+impl GdbJitImages {
+    pub fn new() -> Self {
+        Self {
+            registrations: HashMap::new(),
+        }
+    }
+
+    /// Register `image`, describing the function at `code`, with the debugger.
+    pub fn register(&mut self, code: *const u8, image: Vec<u8>) {
+        self.registrations
+            .insert(code as usize, Registration::new(image));
+    }
+
+    /// Unregister the image describing the function at `code`, if any, before it is freed.
+    pub fn unregister(&mut self, code: *const u8) {
+        self.registrations.remove(&(code as usize));
+    }
+
+    /// Unregister all images, before the functions they describe are freed.
+    pub fn unregister_all(&mut self) {
+        self.registrations.clear();
+    }
+}
+
+impl Drop for GdbJitImages {
+    fn drop(&mut self) {
+        // leak the images to keep describing functions that may still be called
+        mem::take(&mut self.registrations)
+            .into_iter()
+            .for_each(|(_, registration)| mem::forget(registration));
+    }
+}
+
+/// Build an ELF image describing the function compiled in `ctx`, named `name` and emitted at
+/// `code`.
+///
+/// Returns `None` if images can't be built for the target.
+pub fn build_image(
+    isa: &dyn TargetIsa,
+    name: &str,
+    ctx: &Context,
+    code: *const u8,
+    code_size: u32,
+) -> Option<Vec<u8>> {
+    if isa.triple().architecture != Architecture::X86_64 {
+        return None;
+    }
+    let address = code as u64;
+
+    let mut sections = vec![Section {
+        name: ".text",
+        kind: SHT_NOBITS,
+        flags: SHF_ALLOC | SHF_EXECINSTR,
+        addr: address,
+        size: code_size.into(),
+        data: Vec::new(),
+        link: 0,
+        info: 0,
+        align: 16,
+        entsize: 0,
+    }];
+    if let Some(eh_frame) = eh_frame(isa, ctx, address) {
+        sections.push(Section::data(".eh_frame", SHF_ALLOC, 8, eh_frame));
+    }
+
+    let dwarf = debug_sections(isa, name, ctx, address, code_size);
+    for &(name, data) in &[
+        (".debug_info", dwarf.debug_info.slice()),
+        (".debug_abbrev", dwarf.debug_abbrev.slice()),
+        (".debug_line", dwarf.debug_line.slice()),
+        (".debug_str", dwarf.debug_str.slice()),
+    ] {
+        if !data.is_empty() {
+            sections.push(Section::data(name, 0, 1, data.to_vec()));
+        }
+    }
+
+    // The text section is section 1, and the string table follows the symbol table.
+    let mut strtab = vec![0];
+    let mut symtab = vec![0; SYMBOL_SIZE];
+    symtab.extend_from_slice(&add_string(&mut strtab, name).to_le_bytes());
+    symtab.push(STB_GLOBAL << 4 | STT_FUNC);
+    symtab.push(0);
+    symtab.extend_from_slice(&1u16.to_le_bytes());
+    symtab.extend_from_slice(&address.to_le_bytes());
+    symtab.extend_from_slice(&u64::from(code_size).to_le_bytes());
+    let strtab_index = sections.len() as u32 + 2;
+    sections.push(Section {
+        kind: SHT_SYMTAB,
+        link: strtab_index,
+        info: 1,
+        entsize: SYMBOL_SIZE as u64,
+        ..Section::data(".symtab", 0, 8, symtab)
+    });
+    sections.push(Section {
+        kind: SHT_STRTAB,
+        ..Section::data(".strtab", 0, 1, strtab)
+    });
+
+    Some(write_elf(sections))
+}
+
+/// Get the DWARF compilation unit describing the function.
+fn debug_sections(
+    isa: &dyn TargetIsa,
+    name: &str,
+    ctx: &Context,
+    address: u64,
+    code_size: u32,
+) -> Sections<EndianVec<LittleEndian>> {
+    let encoding = Encoding {
+        format: Format::Dwarf32,
+        version: 4,
+        address_size: 8,
+    };
+    let name = name.as_bytes();
+    let mut dwarf = DwarfUnit::new(encoding);
+
+    let mut line_program = LineProgram::new(
+        encoding,
+        LineEncoding::default(),
+        LineString::String(Vec::new()),
+        LineString::String(name.to_vec()),
+        None,
+    );
+    let dir = line_program.default_directory();
+    let file = line_program.add_file(LineString::String(name.to_vec()), dir, None);
+    line_program.begin_sequence(Some(Address::Constant(address)));
+    for (offset, srcloc) in source_locations(isa, &ctx.func) {
+        let row = line_program.row();
+        row.address_offset = offset.into();
+        row.file = file;
+        row.line = srcloc.bits().into();
+        line_program.generate_row();
+    }
+    line_program.end_sequence(code_size.into());
+    dwarf.unit.line_program = line_program;
+
+    let root = dwarf.unit.root();
+    let subprogram = dwarf.unit.add(root, gimli::DW_TAG_subprogram);
+    for &id in &[root, subprogram] {
+        let entry = dwarf.unit.get_mut(id);
+        entry.set(gimli::DW_AT_name, AttributeValue::String(name.to_vec()));
+        entry.set(
+            gimli::DW_AT_low_pc,
+            AttributeValue::Address(Address::Constant(address)),
+        );
+        entry.set(
+            gimli::DW_AT_high_pc,
+            AttributeValue::Udata(code_size.into()),
+        );
+    }
+
+    let mut sections = Sections::new(EndianVec::new(LittleEndian));
+    dwarf.write(&mut sections).unwrap();
+    sections
+}
+
+const ET_DYN: u16 = 3;
+const EM_X86_64: u16 = 62;
+const SHT_PROGBITS: u32 = 1;
+const SHT_SYMTAB: u32 = 2;
+const SHT_STRTAB: u32 = 3;
+const SHT_NOBITS: u32 = 8;
+const SHF_ALLOC: u64 = 0x2;
+const SHF_EXECINSTR: u64 = 0x4;
+const STB_GLOBAL: u8 = 1;
+const STT_FUNC: u8 = 2;
+const ELF_HEADER_SIZE: usize = 64;
+const SECTION_HEADER_SIZE: usize = 64;
+const SYMBOL_SIZE: usize = 24;
+
+/// A section of an ELF image.
+struct Section {
+    name: &'static str,
+    kind: u32,
+    flags: u64,
+    addr: u64,
+    size: u64,
+    data: Vec<u8>,
+    link: u32,
+    info: u32,
+    align: u64,
+    entsize: u64,
+}
+
+impl Section {
+    fn data(name: &'static str, flags: u64, align: u64, data: Vec<u8>) -> Self {
+        Self {
+            name,
+            kind: SHT_PROGBITS,
+            flags,
+            addr: 0,
+            size: data.len() as u64,
+            data,
+            link: 0,
+            info: 0,
+            align,
+            entsize: 0,
+        }
+    }
+}
+
+/// Append `s` to the string table `strtab` and return its offset.
+fn add_string(strtab: &mut Vec<u8>, s: &str) -> u32 {
+    let offset = strtab.len() as u32;
+    strtab.extend_from_slice(s.as_bytes());
+    strtab.push(0);
+    offset
+}
+
+/// Lay out a 64-bit little-endian ELF image with `sections`, which follow the null section.
+///
+/// The image is a shared object, since debuggers take the addresses in it to be the final ones.
+fn write_elf(mut sections: Vec<Section>) -> Vec<u8> {
+    let mut shstrtab = vec![0];
+    let mut names = sections
+        .iter()
+        .map(|section| add_string(&mut shstrtab, section.name))
+        .collect::<Vec<_>>();
+    names.push(add_string(&mut shstrtab, ".shstrtab"));
+    sections.push(Section {
+        kind: SHT_STRTAB,
+        ..Section::data(".shstrtab", 0, 1, shstrtab)
+    });
+
+    let mut out = vec![0; ELF_HEADER_SIZE];
+    let mut offsets = Vec::new();
+    for section in &sections {
+        while out.len() as u64 % section.align != 0 {
+            out.push(0);
+        }
+        offsets.push(out.len() as u64);
+        out.extend_from_slice(&section.data);
+    }
+    while out.len() % 8 != 0 {
+        out.push(0);
+    }
+    let shoff = out.len() as u64;
+
+    out.extend_from_slice(&[0; SECTION_HEADER_SIZE]);
+    for ((section, name), offset) in sections.iter().zip(names).zip(offsets) {
+        out.extend_from_slice(&name.to_le_bytes());
+        out.extend_from_slice(&section.kind.to_le_bytes());
+        out.extend_from_slice(&section.flags.to_le_bytes());
+        out.extend_from_slice(&section.addr.to_le_bytes());
+        out.extend_from_slice(&offset.to_le_bytes());
+        out.extend_from_slice(&section.size.to_le_bytes());
+        out.extend_from_slice(&section.link.to_le_bytes());
+        out.extend_from_slice(&section.info.to_le_bytes());
+        out.extend_from_slice(&section.align.to_le_bytes());
+        out.extend_from_slice(&section.entsize.to_le_bytes());
+    }
+
+    let shnum = sections.len() as u16 + 1;
+    let mut header = Vec::with_capacity(ELF_HEADER_SIZE);
+    // ELFCLASS64, ELFDATA2LSB, EV_CURRENT, ELFOSABI_NONE.
+    header.extend_from_slice(&[0x7f, b'E', b'L', b'F', 2, 1, 1, 0]);
+    header.extend_from_slice(&[0; 8]);
+    header.extend_from_slice(&ET_DYN.to_le_bytes());
+    header.extend_from_slice(&EM_X86_64.to_le_bytes());
+    header.extend_from_slice(&1u32.to_le_bytes()); // e_version
+    header.extend_from_slice(&0u64.to_le_bytes()); // e_entry
+    header.extend_from_slice(&0u64.to_le_bytes()); // e_phoff
+    header.extend_from_slice(&shoff.to_le_bytes());
+    header.extend_from_slice(&0u32.to_le_bytes()); // e_flags
+    header.extend_from_slice(&(ELF_HEADER_SIZE as u16).to_le_bytes());
+    header.extend_from_slice(&0u16.to_le_bytes()); // e_phentsize
+    header.extend_from_slice(&0u16.to_le_bytes()); // e_phnum
+    header.extend_from_slice(&(SECTION_HEADER_SIZE as u16).to_le_bytes());
+    header.extend_from_slice(&shnum.to_le_bytes());
+    header.extend_from_slice(&(shnum - 1).to_le_bytes()); // e_shstrndx
+    out[..ELF_HEADER_SIZE].copy_from_slice(&header);
+    out
+}
@@ -0,0 +1,247 @@
+//! Compilation of lazily defined functions.
+//!
+//! A lazily defined function is called through a stub which initially jumps to a trampoline.
+//! The trampoline calls `compile_lazy_function` to compile the function and point the stub at
+//! it, then calls it with its own arguments and returns its results. If the function can't be
+//! compiled, the error is logged and the trampoline traps with `LAZY_COMPILATION_TRAP` instead.
+
+use crate::backend::{emit_function, relocate, Stub, Symbols, EXECUTABLE_DATA_ALIGNMENT};
+use crate::memory::Memory;
+use cranelift_codegen::binemit::CodeInfo;
+use cranelift_codegen::cursor::{Cursor, FuncCursor};
+use cranelift_codegen::ir::{self, types, InstBuilder};
+use cranelift_codegen::isa::TargetIsa;
+use cranelift_codegen::Context;
+use cranelift_module::{DataId, FuncId, FuncOrDataId};
+use log::error;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::rc::Rc;
+use std::thread::{self, ThreadId};
+
+/// The trap code of calls to lazily defined functions which couldn't be compiled.
+pub const LAZY_COMPILATION_TRAP: ir::TrapCode = ir::TrapCode::User(0);
+
+/// What compiling the lazily defined functions of a module needs.
+pub struct LazyFunctions {
+    /// The thread the module was created on, the only one lazy functions may be compiled on.
+    thread: ThreadId,
+    isa: Rc<dyn TargetIsa>,
+    symbols: Rc<Symbols>,
+    state: RefCell<LazyState>,
+}
+
+struct LazyState {
+    /// The names of all the functions and data declared in the module.
+    names: HashMap<FuncOrDataId, String>,
+    /// The addresses of all the functions and data defined in the module.
+    definitions: HashMap<FuncOrDataId, *const u8>,
+    /// The lazily defined functions which haven't been compiled yet.
+    functions: HashMap<FuncId, LazyFunction>,
+    /// The code of the lazily defined functions which have been compiled.
+    bodies: HashMap<FuncId, *mut u8>,
+    /// Why the lazily defined functions which couldn't be compiled failed.
+    failures: HashMap<FuncId, String>,
+    memory: Memory,
+}
+
+struct LazyFunction {
+    produce: Box<dyn FnOnce() -> ir::Function>,
+    stub: Stub,
+}
+
+impl LazyFunctions {
+    pub fn new(isa: Rc<dyn TargetIsa>, symbols: Rc<Symbols>) -> Self {
+        Self {
+            thread: thread::current().id(),
+            isa,
+            symbols,
+            state: RefCell::new(LazyState {
+                names: HashMap::new(),
+                definitions: HashMap::new(),
+                functions: HashMap::new(),
+                bodies: HashMap::new(),
+                failures: HashMap::new(),
+                memory: Memory::new(),
+            }),
+        }
+    }
+
+    /// Record the name of a function or data object of the module.
+    pub fn declare(&self, id: FuncOrDataId, name: &str) {
+        self.state.borrow_mut().names.insert(id, name.to_string());
+    }
+
+    /// Record the address of a function or data object of the module.
+    pub fn define(&self, id: FuncOrDataId, address: *const u8) {
+        self.state.borrow_mut().definitions.insert(id, address);
+    }
+
+    /// Record that the function `id`, called through `stub`, is to be compiled from the IR
+    /// produced by `produce` when it is first called.
+    pub fn add(&self, id: FuncId, produce: Box<dyn FnOnce() -> ir::Function>, stub: Stub) {
+        self.state
+            .borrow_mut()
+            .functions
+            .insert(id, LazyFunction { produce, stub });
+    }
+
+    /// Don't compile the function `id` anymore, as it has been redefined.
+    pub fn cancel(&self, id: FuncId) {
+        self.state.borrow_mut().functions.remove(&id);
+    }
+
+    /// Forget about the function `id`, freeing its code if it has been compiled lazily.
+    pub unsafe fn remove(&self, id: FuncId) {
+        let mut state = self.state.borrow_mut();
+        state.functions.remove(&id);
+        state.definitions.remove(&FuncOrDataId::Func(id));
+        state.failures.remove(&id);
+        if let Some(body) = state.bodies.remove(&id) {
+            state.memory.free(body);
+        }
+    }
+
+    /// Whether some lazily defined functions may still be called before being compiled.
+    pub fn is_pending(&self) -> bool {
+        !self.state.borrow().functions.is_empty()
+    }
+
+    /// Free the code of all the functions compiled lazily.
+    pub unsafe fn free_memory(&self) {
+        let mut state = self.state.borrow_mut();
+        state.functions.clear();
+        state.bodies.clear();
+        state.failures.clear();
+        state.memory.free_memory();
+    }
+
+    /// Build the trampoline compiling the function `id`, whose signature is `signature`.
+    pub fn trampoline(&self, id: FuncId, signature: &ir::Signature) -> ir::Function {
+        let pointer_type = self.isa.pointer_type();
+        let mut func = ir::Function::with_name_signature(id.into(), signature.clone());
+        let ebb = func.dfg.make_ebb();
+        let params: Vec<_> = signature
+            .params
+            .iter()
+            .map(|param| func.dfg.append_ebb_param(ebb, param.value_type))
+            .collect();
+
+        let mut compile_signature = ir::Signature::new(self.isa.default_call_conv());
+        compile_signature
+            .params
+            .push(ir::AbiParam::new(pointer_type));
+        compile_signature.params.push(ir::AbiParam::new(types::I32));
+        compile_signature
+            .returns
+            .push(ir::AbiParam::new(pointer_type));
+        let compile_signature = func.import_signature(compile_signature);
+        let signature = func.import_signature(signature.clone());
+
+        let mut pos = FuncCursor::new(&mut func);
+        pos.insert_ebb(ebb);
+        let lazy = pos.ins().iconst(pointer_type, self as *const Self as i64);
+        let index = pos.ins().iconst(types::I32, i64::from(id.as_u32()));
+        let compile = pos
+            .ins()
+            .iconst(pointer_type, compile_lazy_function as *const u8 as i64);
+        let call = pos
+            .ins()
+            .call_indirect(compile_signature, compile, &[lazy, index]);
+        let code = pos.func.dfg.first_result(call);
+        pos.ins().trapz(code, LAZY_COMPILATION_TRAP);
+        let call = pos.ins().call_indirect(signature, code, &params);
+        let results = pos.func.dfg.inst_results(call).to_vec();
+        pos.ins().return_(&results);
+        func
+    }
+
+    /// Compile the function `id` if that hasn't been done yet, and return its code.
+    ///
+    /// Nothing is patched unless the function is compiled successfully.
+    fn compile(&self, id: FuncId) -> Result<*const u8, String> {
+        if thread::current().id() != self.thread {
+            return Err(
+                "lazily defined functions must be compiled on the thread of their module".into(),
+            );
+        }
+        let mut state = self.state.borrow_mut();
+        let LazyFunction { produce, stub } = match state.functions.remove(&id) {
+            Some(function) => function,
+            None => {
+                return match state.bodies.get(&id) {
+                    Some(&code) => Ok(code),
+                    None => Err(state.failures[&id].clone()),
+                }
+            }
+        };
+
+        let mut ctx = Context::for_function(produce());
+        let CodeInfo { total_size, .. } = ctx.compile(&*self.isa).map_err(|err| err.to_string())?;
+        let size = total_size as usize;
+        let code = state
+            .memory
+            .allocate(size, EXECUTABLE_DATA_ALIGNMENT)
+            .map_err(|err| format!("can't allocate its code: {}", err))?;
+        let relocs = emit_function(&*self.isa, &ctx, code);
+        relocate(code, size, &relocs, |name| {
+            state.resolve(&self.symbols, name)
+        });
+        state.memory.set_readable_and_executable();
+
+        stub.update(code);
+        state.bodies.insert(id, code);
+        Ok(code)
+    }
+
+    /// Record that the function `id` couldn't be compiled because of `error`.
+    fn fail(&self, id: FuncId, error: String) {
+        let mut state = self.state.borrow_mut();
+        error!(
+            "can't compile {}: {}",
+            state.names[&FuncOrDataId::Func(id)],
+            error
+        );
+        if !state.bodies.contains_key(&id) {
+            state.failures.insert(id, error);
+        }
+    }
+}
+
+impl LazyState {
+    fn resolve(&self, symbols: &Symbols, name: &ir::ExternalName) -> *const u8 {
+        match *name {
+            ir::ExternalName::User { namespace, index } => {
+                // See the conversions of `FuncId` and `DataId` to `ir::ExternalName`.
+                let id = match namespace {
+                    0 => FuncOrDataId::Func(FuncId::from_u32(index)),
+                    1 => FuncOrDataId::Data(DataId::from_u32(index)),
+                    _ => panic!("invalid ExternalName {}", name),
+                };
+                match self.definitions.get(&id) {
+                    Some(&address) => address,
+                    None => symbols.lookup(&self.names[&id]),
+                }
+            }
+            ir::ExternalName::LibCall(libcall) => symbols.lookup_libcall(libcall),
+            _ => panic!("invalid ExternalName {}", name),
+        }
+    }
+}
+
+/// Compile the function `index` of `lazy`, on behalf of its trampoline.
+///
+/// Returns a null pointer if the function couldn't be compiled, for the trampoline to trap.
+extern "C" fn compile_lazy_function(lazy: *const LazyFunctions, index: u32) -> *const u8 {
+    let lazy = unsafe { &*lazy };
+    let id = FuncId::from_u32(index);
+    // Unwinding into the trampoline isn't possible, so panics are reported like errors.
+    let result = panic::catch_unwind(AssertUnwindSafe(|| lazy.compile(id)))
+        .unwrap_or_else(|_| Err("panicked while producing or compiling the function".into()));
+    result.unwrap_or_else(|error| {
+        lazy.fail(id, error);
+        ptr::null()
+    })
+}
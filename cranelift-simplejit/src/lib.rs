@@ -24,9 +24,15 @@
 )]
 
 mod backend;
+mod debug_info;
+mod gdb_jit;
+mod lazy;
 mod memory;
+#[cfg(target_os = "linux")]
+mod perf;
 
-pub use crate::backend::{SimpleJITBackend, SimpleJITBuilder};
+pub use crate::backend::{ProfilingStrategy, SimpleJITBackend, SimpleJITBuilder};
+pub use crate::lazy::LAZY_COMPILATION_TRAP;
 
 /// Version number of this crate.
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -8,6 +8,8 @@ use libc;
 use memmap::MmapMut;
 
 use region;
+use std::cmp;
+use std::collections::BTreeMap;
 use std::mem;
 use std::ptr;
 
@@ -26,17 +28,6 @@ struct PtrLen {
 }
 
 impl PtrLen {
-    /// Create a new empty `PtrLen`.
-    fn new() -> Self {
-        Self {
-            #[cfg(feature = "selinux-fix")]
-            map: None,
-
-            ptr: ptr::null_mut(),
-            len: 0,
-        }
-    }
-
     /// Create a new `PtrLen` pointing to at least `size` bytes of memory,
     /// suitably sized and aligned for memory protection.
     #[cfg(all(not(target_os = "windows"), feature = "selinux-fix"))]
@@ -121,126 +112,147 @@ impl Drop for PtrLen {
 
 // TODO: add a `Drop` impl for `cfg(target_os = "windows")`
 
+/// A group of pages, with the number of allocations in it which haven't been freed.
+struct PageGroup {
+    memory: PtrLen,
+    live: usize,
+    protected: bool,
+}
+
 /// JIT memory manager. This manages pages of suitably aligned and
 /// accessible memory. Memory will be leaked by default to have
 /// function pointers remain valid for the remainder of the
 /// program's life.
+///
+/// Allocations are made in groups of pages, which are writable until they
+/// are protected and aren't allocated from anymore after that. A group is
+/// released once all the allocations in it have been freed.
 pub struct Memory {
-    allocations: Vec<PtrLen>,
-    executable: usize,
-    current: PtrLen,
+    /// The page groups, indexed by their address.
+    groups: BTreeMap<usize, PageGroup>,
+    /// The address of the group allocations are made from, if any.
+    current: Option<usize>,
     position: usize,
 }
 
 impl Memory {
     pub fn new() -> Self {
         Self {
-            allocations: Vec::new(),
-            executable: 0,
-            current: PtrLen::new(),
+            groups: BTreeMap::new(),
+            current: None,
             position: 0,
         }
     }
 
     fn finish_current(&mut self) {
-        self.allocations
-            .push(mem::replace(&mut self.current, PtrLen::new()));
+        if let Some(start) = self.current.take() {
+            if self.groups[&start].live == 0 {
+                self.groups.remove(&start);
+            }
+        }
         self.position = 0;
     }
 
     /// TODO: Use a proper error type.
     pub fn allocate(&mut self, size: usize, align: u8) -> Result<*mut u8, String> {
-        if self.position % align as usize != 0 {
-            self.position += align as usize - self.position % align as usize;
-            debug_assert!(self.position % align as usize == 0);
-        }
+        // Every allocation takes at least a byte, so that `free` can find its group.
+        let size = cmp::max(size, 1);
+
+        if let Some(start) = self.current {
+            if self.position % align as usize != 0 {
+                self.position += align as usize - self.position % align as usize;
+                debug_assert!(self.position % align as usize == 0);
+            }
 
-        if size <= self.current.len - self.position {
-            // TODO: Ensure overflow is not possible.
-            let ptr = unsafe { self.current.ptr.add(self.position) };
-            self.position += size;
-            return Ok(ptr);
+            let group = self.groups.get_mut(&start).unwrap();
+            if self.position <= group.memory.len && size <= group.memory.len - self.position {
+                let ptr = unsafe { group.memory.ptr.add(self.position) };
+                self.position += size;
+                group.live += 1;
+                return Ok(ptr);
+            }
         }
 
         self.finish_current();
 
         // TODO: Allocate more at a time.
-        self.current = PtrLen::with_size(size)?;
+        let memory = PtrLen::with_size(size)?;
+        let ptr = memory.ptr;
+        self.groups.insert(
+            ptr as usize,
+            PageGroup {
+                memory,
+                live: 1,
+                protected: false,
+            },
+        );
+        self.current = Some(ptr as usize);
         self.position = size;
-        Ok(self.current.ptr)
+        Ok(ptr)
     }
 
-    /// Set all memory allocated in this `Memory` up to now as readable and executable.
-    pub fn set_readable_and_executable(&mut self) {
-        self.finish_current();
-
-        #[cfg(feature = "selinux-fix")]
-        {
-            for &PtrLen { ref map, ptr, len } in &self.allocations[self.executable..] {
-                if len != 0 && map.is_some() {
-                    unsafe {
-                        region::protect(ptr, len, region::Protection::ReadExecute)
-                            .expect("unable to make memory readable+executable");
-                    }
-                }
-            }
+    /// Free the allocation at `ptr`, and release its group of pages if nothing else is
+    /// allocated in it.
+    ///
+    /// Likely to invalidate function pointers, causing unsafety.
+    pub unsafe fn free(&mut self, ptr: *const u8) {
+        let addr = ptr as usize;
+        let start = match self.groups.range(..=addr).next_back() {
+            Some((&start, group)) if addr < start + group.memory.len => start,
+            _ => panic!("freeing memory which isn't allocated"),
+        };
+        let group = self.groups.get_mut(&start).unwrap();
+        group.live -= 1;
+        if group.live == 0 && self.current != Some(start) {
+            self.groups.remove(&start);
         }
+    }
 
-        #[cfg(not(feature = "selinux-fix"))]
-        {
-            for &PtrLen { ptr, len } in &self.allocations[self.executable..] {
-                if len != 0 {
-                    unsafe {
-                        region::protect(ptr, len, region::Protection::ReadExecute)
-                            .expect("unable to make memory readable+executable");
-                    }
-                }
-            }
-        }
+    /// Set all memory allocated in this `Memory` up to now as readable and executable.
+    pub fn set_readable_and_executable(&mut self) {
+        self.protect(
+            region::Protection::ReadExecute,
+            "unable to make memory readable+executable",
+        );
     }
 
     /// Set all memory allocated in this `Memory` up to now as readonly.
     pub fn set_readonly(&mut self) {
-        self.finish_current();
+        self.protect(region::Protection::Read, "unable to make memory readonly");
+    }
 
-        #[cfg(feature = "selinux-fix")]
-        {
-            for &PtrLen { ref map, ptr, len } in &self.allocations[self.executable..] {
-                if len != 0 && map.is_some() {
-                    unsafe {
-                        region::protect(ptr, len, region::Protection::Read)
-                            .expect("unable to make memory readonly");
-                    }
-                }
-            }
-        }
+    /// Protect the groups which haven't been protected yet, so that nothing more is
+    /// allocated in them.
+    ///
+    /// This is one-way: a protected group is never made writable again, so its contents can't be
+    /// patched in place and the space of freed allocations in it isn't reused. Code that needs to
+    /// change, like the target of a stub, has to be reached through writable memory instead.
+    fn protect(&mut self, protection: region::Protection, error: &str) {
+        self.finish_current();
 
-        #[cfg(not(feature = "selinux-fix"))]
-        {
-            for &PtrLen { ptr, len } in &self.allocations[self.executable..] {
-                if len != 0 {
-                    unsafe {
-                        region::protect(ptr, len, region::Protection::Read)
-                            .expect("unable to make memory readonly");
-                    }
-                }
+        for group in self.groups.values_mut().filter(|group| !group.protected) {
+            unsafe {
+                region::protect(group.memory.ptr, group.memory.len, protection).expect(error);
             }
+            group.protected = true;
         }
     }
 
     /// Frees all allocated memory regions that would be leaked otherwise.
     /// Likely to invalidate existing function pointers, causing unsafety.
     pub unsafe fn free_memory(&mut self) {
-        self.allocations.clear();
+        self.groups.clear();
+        self.current = None;
+        self.position = 0;
     }
 }
 
 impl Drop for Memory {
     fn drop(&mut self) {
         // leak memory to guarantee validity of function pointers
-        mem::replace(&mut self.allocations, Vec::new())
+        mem::take(&mut self.groups)
             .into_iter()
-            .for_each(mem::forget);
+            .for_each(|(_, group)| mem::forget(group));
     }
 }
 
@@ -255,4 +267,25 @@ mod tests {
         assert_eq!(round_up_to_page_size(4096, 4096), 4096);
         assert_eq!(round_up_to_page_size(4097, 4096), 8192);
     }
+
+    #[test]
+    fn test_free_releases_page_groups() {
+        let mut memory = Memory::new();
+        let a = memory.allocate(10, 1).unwrap();
+        let b = memory.allocate(10, 1).unwrap();
+        memory.set_readonly();
+        let c = memory.allocate(10, 1).unwrap();
+        assert_eq!(memory.groups.len(), 2);
+
+        unsafe { memory.free(a) };
+        assert_eq!(memory.groups.len(), 2);
+        unsafe { memory.free(b) };
+        assert_eq!(memory.groups.len(), 1);
+
+        // The group allocations are made from is kept until it is protected.
+        unsafe { memory.free(c) };
+        assert_eq!(memory.groups.len(), 1);
+        memory.set_readonly();
+        assert!(memory.groups.is_empty());
+    }
 }
@@ -0,0 +1,261 @@
+//! Output of compiled functions for `perf`.
+//!
+//! Perf maps (`/tmp/perf-<pid>.map`) only let `perf report` name the functions. Jitdump files
+//! (`jit-<pid>.dump`, see `tools/perf/Documentation/jitdump-specification.txt` in the Linux
+//! sources) also contain the code of the functions, their source locations and their unwind
+//! information; `perf inject --jit` turns them into ELF images that `perf report` and
+//! `perf annotate` use like any other binary.
+
+use crate::backend::ProfilingStrategy;
+use crate::debug_info::{eh_frame, source_locations};
+use cranelift_codegen::isa::TargetIsa;
+use cranelift_codegen::Context;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::io::AsRawFd;
+use std::{process, ptr, slice};
+use target_lexicon::Architecture;
+
+/// What the profiler needs to know about a function that isn't in its code.
+pub struct ProfiledFunction {
+    name: String,
+    source_locations: Vec<(u64, u32)>,
+    eh_frame: Option<Vec<u8>>,
+}
+
+const JITDUMP_MAGIC: u32 = 0x4A69_5444;
+const JITDUMP_VERSION: u32 = 1;
+const JIT_CODE_LOAD: u32 = 0;
+const JIT_CODE_DEBUG_INFO: u32 = 2;
+const JIT_CODE_UNWINDING_INFO: u32 = 4;
+const EM_X86_64: u32 = 62;
+
+/// `perf inject` puts the code of a function after a 64-bit ELF header in the image it makes.
+const ELF_TEXT_OFFSET: u64 = 64;
+
+/// The size of the `.eh_frame_hdr` section describing a single function.
+const EH_FRAME_HDR_SIZE: usize = 20;
+
+/// Writer of the profiling information of the functions of a module.
+pub struct Profiler {
+    perf_map: File,
+    jitdump: Option<JitDump>,
+}
+
+struct JitDump {
+    file: File,
+    marker: *mut libc::c_void,
+    code_index: u64,
+}
+
+impl Profiler {
+    /// Open the files for `strategy`, or return `None` if nothing is to be written.
+    pub fn new(strategy: ProfilingStrategy, isa: &dyn TargetIsa) -> io::Result<Option<Self>> {
+        let pid = process::id();
+        let perf_map = match strategy {
+            ProfilingStrategy::None => return Ok(None),
+            ProfilingStrategy::PerfMap | ProfilingStrategy::JitDump => OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(format!("/tmp/perf-{}.map", pid))?,
+        };
+        let jitdump = match strategy {
+            ProfilingStrategy::JitDump => Some(JitDump::new(pid, isa)?),
+            _ => None,
+        };
+        Ok(Some(Self { perf_map, jitdump }))
+    }
+
+    /// Collect what we need to know about the function compiled in `ctx` besides its code.
+    pub fn describe(
+        &self,
+        isa: &dyn TargetIsa,
+        name: &str,
+        ctx: &Context,
+        code: *const u8,
+    ) -> ProfiledFunction {
+        let (source_locations, eh_frame) = if self.jitdump.is_some() {
+            let source_locations = source_locations(isa, &ctx.func)
+                .into_iter()
+                .map(|(offset, srcloc)| (code as u64 + u64::from(offset), srcloc.bits()))
+                .collect();
+            (source_locations, eh_frame(isa, ctx, ELF_TEXT_OFFSET))
+        } else {
+            (Vec::new(), None)
+        };
+        ProfiledFunction {
+            name: name.to_string(),
+            source_locations,
+            eh_frame,
+        }
+    }
+
+    /// Describe the function at `code`, whose relocations have been applied.
+    pub fn function_finalized(&mut self, func: &ProfiledFunction, code: *const u8, size: usize) {
+        let _ = writeln!(
+            self.perf_map,
+            "{:x} {:x} {}",
+            code as usize, size, func.name
+        );
+        if let Some(ref mut jitdump) = self.jitdump {
+            let code = unsafe { slice::from_raw_parts(code, size) };
+            // There's no use in failing the compilation if writing the dump fails.
+            let _ = jitdump.write_function(func, code);
+        }
+    }
+}
+
+impl JitDump {
+    fn new(pid: u32, isa: &dyn TargetIsa) -> io::Result<Self> {
+        let elf_mach = match isa.triple().architecture {
+            Architecture::X86_64 => EM_X86_64,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    "jitdump is only supported on x86-64",
+                ))
+            }
+        };
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(format!("jit-{}.dump", pid))?;
+
+        // `perf record` finds the dump through the executable mapping of its first page.
+        let marker = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                region::page::size(),
+                libc::PROT_READ | libc::PROT_EXEC,
+                libc::MAP_PRIVATE,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if marker == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut header = Vec::new();
+        header.extend_from_slice(&JITDUMP_MAGIC.to_ne_bytes());
+        header.extend_from_slice(&JITDUMP_VERSION.to_ne_bytes());
+        header.extend_from_slice(&40u32.to_ne_bytes()); // total_size
+        header.extend_from_slice(&elf_mach.to_ne_bytes());
+        header.extend_from_slice(&0u32.to_ne_bytes()); // pad1
+        header.extend_from_slice(&pid.to_ne_bytes());
+        header.extend_from_slice(&timestamp().to_ne_bytes());
+        header.extend_from_slice(&0u64.to_ne_bytes()); // flags
+        file.write_all(&header)?;
+
+        Ok(Self {
+            file,
+            marker,
+            code_index: 0,
+        })
+    }
+
+    fn write_function(&mut self, func: &ProfiledFunction, code: &[u8]) -> io::Result<()> {
+        let code_addr = code.as_ptr() as u64;
+        let timestamp = timestamp();
+
+        // The debug and unwinding information of a function precede its code.
+        if !func.source_locations.is_empty() {
+            let mut record = Vec::new();
+            record.extend_from_slice(&code_addr.to_ne_bytes());
+            record.extend_from_slice(&(func.source_locations.len() as u64).to_ne_bytes());
+            for &(addr, line) in &func.source_locations {
+                // `perf inject` expects the addresses to be offset like the code in its images.
+                record.extend_from_slice(&(addr + ELF_TEXT_OFFSET).to_ne_bytes());
+                record.extend_from_slice(&line.to_ne_bytes());
+                record.extend_from_slice(&0u32.to_ne_bytes()); // discrim
+                record.extend_from_slice(func.name.as_bytes());
+                record.push(0);
+            }
+            self.write_record(JIT_CODE_DEBUG_INFO, timestamp, &record)?;
+        }
+
+        if let Some(ref eh_frame) = func.eh_frame {
+            let unwinding_size = (eh_frame.len() + EH_FRAME_HDR_SIZE) as u64;
+            let mut record = Vec::new();
+            record.extend_from_slice(&unwinding_size.to_ne_bytes());
+            record.extend_from_slice(&(EH_FRAME_HDR_SIZE as u64).to_ne_bytes());
+            record.extend_from_slice(&unwinding_size.to_ne_bytes()); // mapped_size
+            record.extend_from_slice(eh_frame);
+            record.extend_from_slice(&eh_frame_hdr(eh_frame, code.len()));
+            while record.len() % 8 != 0 {
+                record.push(0);
+            }
+            self.write_record(JIT_CODE_UNWINDING_INFO, timestamp, &record)?;
+        }
+
+        let mut record = Vec::new();
+        record.extend_from_slice(&process::id().to_ne_bytes());
+        record
+            .extend_from_slice(&(unsafe { libc::syscall(libc::SYS_gettid) } as u32).to_ne_bytes());
+        record.extend_from_slice(&code_addr.to_ne_bytes()); // vma
+        record.extend_from_slice(&code_addr.to_ne_bytes());
+        record.extend_from_slice(&(code.len() as u64).to_ne_bytes());
+        record.extend_from_slice(&self.code_index.to_ne_bytes());
+        record.extend_from_slice(func.name.as_bytes());
+        record.push(0);
+        record.extend_from_slice(code);
+        self.write_record(JIT_CODE_LOAD, timestamp, &record)?;
+        self.code_index += 1;
+        Ok(())
+    }
+
+    fn write_record(&mut self, id: u32, timestamp: u64, body: &[u8]) -> io::Result<()> {
+        let mut record = Vec::with_capacity(16 + body.len());
+        record.extend_from_slice(&id.to_ne_bytes());
+        record.extend_from_slice(&(16 + body.len() as u32).to_ne_bytes());
+        record.extend_from_slice(&timestamp.to_ne_bytes());
+        record.extend_from_slice(body);
+        self.file.write_all(&record)
+    }
+}
+
+impl Drop for JitDump {
+    fn drop(&mut self) {
+        unsafe { libc::munmap(self.marker, region::page::size()) };
+    }
+}
+
+/// Build the `.eh_frame_hdr` section indexing the single FDE in `eh_frame`.
+///
+/// `perf inject` lays out the image with the code first, followed by `eh_frame`, followed by
+/// this section, so the pointers in it are relative to that layout.
+fn eh_frame_hdr(eh_frame: &[u8], code_size: usize) -> [u8; EH_FRAME_HDR_SIZE] {
+    const DW_EH_PE_UDATA4: u8 = 0x03;
+    const DW_EH_PE_SDATA4: u8 = 0x0b;
+    const DW_EH_PE_PCREL: u8 = 0x10;
+    const DW_EH_PE_DATAREL: u8 = 0x30;
+
+    // The CIE comes first, and its length field doesn't count itself.
+    let cie_len = u32::from_ne_bytes([eh_frame[0], eh_frame[1], eh_frame[2], eh_frame[3]]);
+    let fde_offset = cie_len as i32 + 4;
+    let eh_frame_len = eh_frame.len() as i32;
+    let code_size = code_size as i32;
+
+    let mut hdr = [0; EH_FRAME_HDR_SIZE];
+    hdr[0] = 1; // version
+    hdr[1] = DW_EH_PE_PCREL | DW_EH_PE_SDATA4; // eh_frame_ptr_enc
+    hdr[2] = DW_EH_PE_UDATA4; // fde_count_enc
+    hdr[3] = DW_EH_PE_DATAREL | DW_EH_PE_SDATA4; // table_enc
+    hdr[4..8].copy_from_slice(&(-(eh_frame_len + 4)).to_ne_bytes());
+    hdr[8..12].copy_from_slice(&1u32.to_ne_bytes());
+    hdr[12..16].copy_from_slice(&(-(code_size + eh_frame_len)).to_ne_bytes());
+    hdr[16..20].copy_from_slice(&(fde_offset - eh_frame_len).to_ne_bytes());
+    hdr
+}
+
+/// Get the time in the clock used by `perf record -k mono`.
+fn timestamp() -> u64 {
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut ts) };
+    ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64
+}
@@ -25,6 +25,29 @@ fn error_on_incompatible_sig_in_declare_function() {
         .unwrap(); // Make sure this is an error
 }
 
+#[test]
+fn error_on_tls_data() {
+    let mut module: Module<SimpleJITBackend> =
+        Module::new(SimpleJITBuilder::new(default_libcall_names()));
+    match module.declare_data("tls", Linkage::Local, true, true, None) {
+        Err(ModuleError::Backend(_)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn error_on_incompatible_tls_in_declare_data() {
+    let mut module: Module<SimpleJITBackend> =
+        Module::new(SimpleJITBuilder::new(default_libcall_names()));
+    module
+        .declare_data("abc", Linkage::Local, true, false, None)
+        .unwrap();
+    match module.declare_data("abc", Linkage::Local, true, true, None) {
+        Err(ModuleError::IncompatibleDeclaration(name)) => assert_eq!(name, "abc"),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
 fn define_simple_function(module: &mut Module<SimpleJITBackend>) -> FuncId {
     let sig = Signature {
         params: vec![],
@@ -195,3 +218,460 @@ fn libcall_function() {
 
     module.finalize_definitions();
 }
+
+fn define_constant_function(
+    module: &mut Module<SimpleJITBackend>,
+    func_id: FuncId,
+    value: i64,
+    redefine: bool,
+) {
+    let sig = Signature {
+        params: vec![],
+        returns: vec![AbiParam::new(types::I32)],
+        call_conv: CallConv::SystemV,
+    };
+    let mut ctx = Context::new();
+    ctx.func = Function::with_name_signature(ExternalName::user(0, func_id.as_u32()), sig);
+    let mut func_ctx = FunctionBuilderContext::new();
+    {
+        let mut bcx: FunctionBuilder = FunctionBuilder::new(&mut ctx.func, &mut func_ctx);
+        let ebb = bcx.create_ebb();
+        bcx.switch_to_block(ebb);
+        let v = bcx.ins().iconst(types::I32, value);
+        bcx.ins().return_(&[v]);
+        bcx.seal_all_blocks();
+    }
+    if redefine {
+        module.redefine_function(func_id, &mut ctx).unwrap();
+    } else {
+        module.define_function(func_id, &mut ctx).unwrap();
+    }
+}
+
+#[test]
+#[cfg(target_arch = "x86_64")]
+fn hotswap() {
+    let mut builder = SimpleJITBuilder::new(default_libcall_names());
+    builder.hotswap(true);
+    let mut module: Module<SimpleJITBackend> = Module::new(builder);
+
+    let sig = Signature {
+        params: vec![],
+        returns: vec![AbiParam::new(types::I32)],
+        call_conv: CallConv::SystemV,
+    };
+    let callee_id = module
+        .declare_function("callee", Linkage::Local, &sig)
+        .unwrap();
+    let caller_id = module
+        .declare_function("caller", Linkage::Local, &sig)
+        .unwrap();
+
+    define_constant_function(&mut module, callee_id, 1, false);
+    let mut ctx = Context::new();
+    ctx.func = Function::with_name_signature(ExternalName::user(0, caller_id.as_u32()), sig);
+    let mut func_ctx = FunctionBuilderContext::new();
+    {
+        let mut bcx: FunctionBuilder = FunctionBuilder::new(&mut ctx.func, &mut func_ctx);
+        let ebb = bcx.create_ebb();
+        bcx.switch_to_block(ebb);
+        let callee = module.declare_func_in_func(callee_id, &mut bcx.func);
+        let call = bcx.ins().call(callee, &[]);
+        let v = bcx.inst_results(call)[0];
+        bcx.ins().return_(&[v]);
+        bcx.seal_all_blocks();
+    }
+    module.define_function(caller_id, &mut ctx).unwrap();
+    module.finalize_definitions();
+
+    let callee: extern "C" fn() -> i32 =
+        unsafe { std::mem::transmute(module.get_finalized_function(callee_id)) };
+    let caller: extern "C" fn() -> i32 =
+        unsafe { std::mem::transmute(module.get_finalized_function(caller_id)) };
+    assert_eq!(callee(), 1);
+    assert_eq!(caller(), 1);
+
+    define_constant_function(&mut module, callee_id, 2, true);
+    // The new body isn't used before it is finalized.
+    assert_eq!(caller(), 1);
+    module.finalize_definitions();
+    assert_eq!(callee(), 2);
+    assert_eq!(caller(), 2);
+    assert_eq!(
+        module.get_finalized_function(callee_id),
+        callee as *const u8
+    );
+
+    unsafe { module.finish().free_memory() };
+}
+
+#[test]
+#[cfg(target_arch = "x86_64")]
+fn remove_function() {
+    let mut builder = SimpleJITBuilder::new(default_libcall_names());
+    builder.hotswap(true);
+    let mut module: Module<SimpleJITBackend> = Module::new(builder);
+
+    let sig = Signature {
+        params: vec![],
+        returns: vec![AbiParam::new(types::I32)],
+        call_conv: CallConv::SystemV,
+    };
+    let func_id = module
+        .declare_function("function", Linkage::Local, &sig)
+        .unwrap();
+
+    define_constant_function(&mut module, func_id, 1, false);
+    module.finalize_definitions();
+    define_constant_function(&mut module, func_id, 2, true);
+    module.finalize_definitions();
+    unsafe { module.remove_function(func_id).unwrap() };
+    unsafe { module.remove_function(func_id).unwrap_err() };
+
+    // A removed function can be defined again.
+    define_constant_function(&mut module, func_id, 3, false);
+    module.finalize_definitions();
+    let function: extern "C" fn() -> i32 =
+        unsafe { std::mem::transmute(module.get_finalized_function(func_id)) };
+    assert_eq!(function(), 3);
+}
+
+#[test]
+#[cfg(target_arch = "x86_64")]
+fn lazy_function() {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    let mut module: Module<SimpleJITBackend> =
+        Module::new(SimpleJITBuilder::new(default_libcall_names()));
+
+    let sig = Signature {
+        params: vec![AbiParam::new(types::I32)],
+        returns: vec![AbiParam::new(types::I32)],
+        call_conv: CallConv::SystemV,
+    };
+    let add_one_id = module
+        .declare_function("add_one", Linkage::Local, &sig)
+        .unwrap();
+    let lazy_id = module
+        .declare_function("lazy", Linkage::Local, &sig)
+        .unwrap();
+
+    let mut ctx = Context::new();
+    ctx.func =
+        Function::with_name_signature(ExternalName::user(0, add_one_id.as_u32()), sig.clone());
+    let mut func_ctx = FunctionBuilderContext::new();
+    {
+        let mut bcx: FunctionBuilder = FunctionBuilder::new(&mut ctx.func, &mut func_ctx);
+        let ebb = bcx.create_ebb();
+        bcx.append_ebb_params_for_function_params(ebb);
+        bcx.switch_to_block(ebb);
+        let x = bcx.ebb_params(ebb)[0];
+        let y = bcx.ins().iadd_imm(x, 1);
+        bcx.ins().return_(&[y]);
+        bcx.seal_all_blocks();
+    }
+    module.define_function(add_one_id, &mut ctx).unwrap();
+
+    // The lazy function doubles the result of `add_one`.
+    let produced = Rc::new(Cell::new(false));
+    let produce = {
+        let produced = produced.clone();
+        move || {
+            produced.set(true);
+            let mut func =
+                Function::with_name_signature(ExternalName::user(0, lazy_id.as_u32()), sig.clone());
+            let mut func_ctx = FunctionBuilderContext::new();
+            {
+                let mut bcx: FunctionBuilder = FunctionBuilder::new(&mut func, &mut func_ctx);
+                let signature = bcx.import_signature(sig);
+                let add_one = bcx.import_function(ExtFuncData {
+                    name: ExternalName::user(0, add_one_id.as_u32()),
+                    signature,
+                    colocated: true,
+                });
+                let ebb = bcx.create_ebb();
+                bcx.append_ebb_params_for_function_params(ebb);
+                bcx.switch_to_block(ebb);
+                let x = bcx.ebb_params(ebb)[0];
+                let call = bcx.ins().call(add_one, &[x]);
+                let y = bcx.inst_results(call)[0];
+                let z = bcx.ins().imul_imm(y, 2);
+                bcx.ins().return_(&[z]);
+                bcx.seal_all_blocks();
+            }
+            func
+        }
+    };
+    module
+        .define_function_lazily(lazy_id, Box::new(produce))
+        .unwrap();
+    module.finalize_definitions();
+    assert!(!produced.get());
+
+    let lazy: extern "C" fn(i32) -> i32 =
+        unsafe { std::mem::transmute(module.get_finalized_function(lazy_id)) };
+    assert_eq!(lazy(20), 42);
+    assert!(produced.get());
+    assert_eq!(lazy(1), 4);
+
+    unsafe { module.finish().free_memory() };
+}
+
+#[test]
+#[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+fn lazy_function_compilation_error() {
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::Command;
+
+    // The call traps, so it is made by a child process running only this test.
+    if std::env::var_os("SIMPLEJIT_LAZY_FUNCTION_CALL").is_none() {
+        let status = Command::new(std::env::current_exe().unwrap())
+            .args(&["--exact", "lazy_function_compilation_error"])
+            .env("SIMPLEJIT_LAZY_FUNCTION_CALL", "1")
+            .status()
+            .unwrap();
+        assert_eq!(status.signal(), Some(libc::SIGILL));
+        return;
+    }
+
+    let mut module: Module<SimpleJITBackend> =
+        Module::new(SimpleJITBuilder::new(default_libcall_names()));
+    let sig = Signature {
+        params: vec![],
+        returns: vec![AbiParam::new(types::I32)],
+        call_conv: CallConv::SystemV,
+    };
+    let lazy_id = module
+        .declare_function("lazy", Linkage::Local, &sig)
+        .unwrap();
+
+    // The produced function doesn't return the value its signature requires.
+    let produce = move || {
+        let mut func = Function::with_name_signature(ExternalName::user(0, lazy_id.as_u32()), sig);
+        let mut func_ctx = FunctionBuilderContext::new();
+        {
+            let mut bcx: FunctionBuilder = FunctionBuilder::new(&mut func, &mut func_ctx);
+            let ebb = bcx.create_ebb();
+            bcx.switch_to_block(ebb);
+            bcx.ins().return_(&[]);
+            bcx.seal_all_blocks();
+        }
+        func
+    };
+    module
+        .define_function_lazily(lazy_id, Box::new(produce))
+        .unwrap();
+    module.finalize_definitions();
+
+    let lazy: extern "C" fn() -> i32 =
+        unsafe { std::mem::transmute(module.get_finalized_function(lazy_id)) };
+    lazy();
+    unreachable!("the call to a function which can't be compiled returned");
+}
+
+#[test]
+fn define_functions() {
+    let mut module: Module<SimpleJITBackend> =
+        Module::new(SimpleJITBuilder::new(default_libcall_names()));
+
+    let sig = Signature {
+        params: vec![],
+        returns: vec![AbiParam::new(types::I32)],
+        call_conv: CallConv::SystemV,
+    };
+    let mut funcs = Vec::new();
+    for value in 0..8 {
+        let func_id = module
+            .declare_function(&format!("function{}", value), Linkage::Local, &sig)
+            .unwrap();
+        let mut ctx = Context::new();
+        ctx.func =
+            Function::with_name_signature(ExternalName::user(0, func_id.as_u32()), sig.clone());
+        let mut func_ctx = FunctionBuilderContext::new();
+        {
+            let mut bcx: FunctionBuilder = FunctionBuilder::new(&mut ctx.func, &mut func_ctx);
+            let ebb = bcx.create_ebb();
+            bcx.switch_to_block(ebb);
+            let v = bcx.ins().iconst(types::I32, value);
+            bcx.ins().return_(&[v]);
+            bcx.seal_all_blocks();
+        }
+        funcs.push((func_id, ctx));
+    }
+
+    let sizes = module.define_functions(&mut funcs).unwrap();
+    assert_eq!(sizes.len(), funcs.len());
+    module.finalize_definitions();
+    for (value, &(func_id, _)) in funcs.iter().enumerate() {
+        let function: extern "C" fn() -> i32 =
+            unsafe { std::mem::transmute(module.get_finalized_function(func_id)) };
+        assert_eq!(function(), value as i32);
+    }
+
+    // Functions which are already defined are rejected before anything is compiled.
+    match module.define_functions(&mut funcs[..1]) {
+        Err(ModuleError::DuplicateDefinition(name)) => assert_eq!(name, "function0"),
+        _ => panic!("expected a duplicate definition error"),
+    }
+}
+
+#[test]
+fn define_functions_compilation_error() {
+    let mut module: Module<SimpleJITBackend> =
+        Module::new(SimpleJITBuilder::new(default_libcall_names()));
+
+    let sig = Signature {
+        params: vec![],
+        returns: vec![AbiParam::new(types::I32)],
+        call_conv: CallConv::SystemV,
+    };
+    let func_ids: Vec<_> = (0..8)
+        .map(|value| {
+            module
+                .declare_function(&format!("function{}", value), Linkage::Local, &sig)
+                .unwrap()
+        })
+        .collect();
+    let build = |value: i64, ty: Type| {
+        let func_id = func_ids[value as usize];
+        let mut ctx = Context::new();
+        ctx.func =
+            Function::with_name_signature(ExternalName::user(0, func_id.as_u32()), sig.clone());
+        let mut func_ctx = FunctionBuilderContext::new();
+        {
+            let mut bcx: FunctionBuilder = FunctionBuilder::new(&mut ctx.func, &mut func_ctx);
+            let ebb = bcx.create_ebb();
+            bcx.switch_to_block(ebb);
+            let v = bcx.ins().iconst(ty, value);
+            bcx.ins().return_(&[v]);
+            bcx.seal_all_blocks();
+        }
+        (func_id, ctx)
+    };
+
+    // The fifth function returns a value of the wrong type, so the verifier rejects it.
+    let mut funcs: Vec<_> = (0..8)
+        .map(|value| build(value, if value == 4 { types::I64 } else { types::I32 }))
+        .collect();
+    match module.define_functions(&mut funcs) {
+        Err(ModuleError::Compilation(_)) => {}
+        _ => panic!("expected a compilation error"),
+    }
+
+    // None of the functions was defined, so the valid ones can still be defined.
+    let mut funcs: Vec<_> = (0..8).map(|value| build(value, types::I32)).collect();
+    module.define_functions(&mut funcs).unwrap();
+    module.finalize_definitions();
+    for (value, &(func_id, _)) in funcs.iter().enumerate() {
+        let function: extern "C" fn() -> i32 =
+            unsafe { std::mem::transmute(module.get_finalized_function(func_id)) };
+        assert_eq!(function(), value as i32);
+    }
+}
+
+#[test]
+#[should_panic(expected = "Result::unwrap()` on an `Err` value: DuplicateDefinition(\"abc\")")]
+fn panic_on_redefine_without_hotswap() {
+    let mut module: Module<SimpleJITBackend> =
+        Module::new(SimpleJITBuilder::new(default_libcall_names()));
+
+    let sig = Signature {
+        params: vec![],
+        returns: vec![AbiParam::new(types::I32)],
+        call_conv: CallConv::SystemV,
+    };
+    let func_id = module
+        .declare_function("abc", Linkage::Local, &sig)
+        .unwrap();
+    define_constant_function(&mut module, func_id, 1, false);
+    module.finalize_definitions();
+    define_constant_function(&mut module, func_id, 2, true);
+}
+
+#[test]
+#[cfg(target_arch = "x86_64")]
+fn gdb_jit_registration() {
+    #[repr(C)]
+    struct JITCodeEntry {
+        next_entry: *const JITCodeEntry,
+        prev_entry: *const JITCodeEntry,
+        symfile_addr: *const u8,
+        symfile_size: u64,
+    }
+
+    #[repr(C)]
+    struct JITDescriptor {
+        version: u32,
+        action_flag: u32,
+        relevant_entry: *const JITCodeEntry,
+        first_entry: *const JITCodeEntry,
+    }
+
+    extern "C" {
+        static __jit_debug_descriptor: JITDescriptor;
+    }
+
+    let mut builder = SimpleJITBuilder::new(default_libcall_names());
+    builder.debug_info(true);
+    let mut module: Module<SimpleJITBackend> = Module::new(builder);
+
+    let sig = Signature {
+        params: vec![AbiParam::new(types::I32)],
+        returns: vec![AbiParam::new(types::I32)],
+        call_conv: CallConv::SystemV,
+    };
+    let func_id = module
+        .declare_function("gdb_jit_function", Linkage::Local, &sig)
+        .unwrap();
+
+    let mut ctx = Context::new();
+    ctx.func = Function::with_name_signature(ExternalName::user(0, func_id.as_u32()), sig);
+    ctx.func.collect_frame_layout_info();
+    let mut func_ctx = FunctionBuilderContext::new();
+    {
+        let mut bcx: FunctionBuilder = FunctionBuilder::new(&mut ctx.func, &mut func_ctx);
+        let ebb = bcx.create_ebb();
+        bcx.append_ebb_params_for_function_params(ebb);
+        bcx.switch_to_block(ebb);
+        bcx.set_srcloc(SourceLoc::new(1));
+        let x = bcx.ebb_params(ebb)[0];
+        let y = bcx.ins().iadd_imm(x, 1);
+        bcx.set_srcloc(SourceLoc::new(2));
+        bcx.ins().return_(&[y]);
+        bcx.seal_all_blocks();
+    }
+    module.define_function(func_id, &mut ctx).unwrap();
+    module.finalize_definitions();
+
+    let image = unsafe {
+        assert_eq!(__jit_debug_descriptor.action_flag, 1); // JIT_REGISTER_FN
+        let entry = &*__jit_debug_descriptor.relevant_entry;
+        std::slice::from_raw_parts(entry.symfile_addr, entry.symfile_size as usize)
+    };
+    assert_eq!(&image[..4], b"\x7fELF");
+    let contains = |s: &[u8]| image.windows(s.len()).any(|w| w == s);
+    assert!(contains(b"gdb_jit_function\0"));
+    assert!(contains(b".eh_frame\0"));
+    assert!(contains(b".debug_line\0"));
+
+    unsafe { module.finish().free_memory() };
+    unsafe { assert_eq!(__jit_debug_descriptor.action_flag, 2) }; // JIT_UNREGISTER_FN
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn perf_map() {
+    let mut builder = SimpleJITBuilder::new(default_libcall_names());
+    builder.profiling_strategy(ProfilingStrategy::PerfMap);
+    let mut module: Module<SimpleJITBackend> = Module::new(builder);
+
+    let func_id = define_simple_function(&mut module);
+    module.finalize_definitions();
+    let code = module.get_finalized_function(func_id);
+
+    let map = std::fs::read_to_string(format!("/tmp/perf-{}.map", std::process::id())).unwrap();
+    assert!(map
+        .lines()
+        .any(|line| line.starts_with(&format!("{:x} ", code as usize)) && line.ends_with(" abc")));
+}
@@ -0,0 +1,46 @@
+// The profiling information is only written on Linux.
+#![cfg(target_os = "linux")]
+
+use cranelift_codegen::ir::*;
+use cranelift_codegen::isa::CallConv;
+use cranelift_codegen::Context;
+use cranelift_frontend::*;
+use cranelift_module::*;
+use cranelift_simplejit::*;
+use std::{fs, process};
+
+// This test has its own file, so that no other test of the same process writes the perf map.
+#[test]
+fn unwritable_perf_map() {
+    // A directory in the way of the perf map makes opening it fail.
+    let path = format!("/tmp/perf-{}.map", process::id());
+    fs::create_dir(&path).unwrap();
+
+    let mut builder = SimpleJITBuilder::new(default_libcall_names());
+    builder.profiling_strategy(ProfilingStrategy::PerfMap);
+    let mut module: Module<SimpleJITBackend> = Module::new(builder);
+
+    // Functions are still compiled, they just aren't described to `perf`.
+    let sig = Signature {
+        params: vec![],
+        returns: vec![],
+        call_conv: CallConv::SystemV,
+    };
+    let func_id = module
+        .declare_function("abc", Linkage::Local, &sig)
+        .unwrap();
+    let mut ctx = Context::new();
+    ctx.func = Function::with_name_signature(ExternalName::user(0, func_id.as_u32()), sig);
+    let mut func_ctx = FunctionBuilderContext::new();
+    {
+        let mut bcx: FunctionBuilder = FunctionBuilder::new(&mut ctx.func, &mut func_ctx);
+        let ebb = bcx.create_ebb();
+        bcx.switch_to_block(ebb);
+        bcx.ins().return_(&[]);
+    }
+    module.define_function(func_id, &mut ctx).unwrap();
+    module.finalize_definitions();
+    assert!(!module.get_finalized_function(func_id).is_null());
+
+    fs::remove_dir(&path).unwrap();
+}
@@ -32,12 +32,14 @@ use crate::translation_utils::{FuncIndex, MemoryIndex, SignatureIndex, TableInde
 use crate::wasm_unsupported;
 use core::{i32, u32};
 use cranelift_codegen::ir::condcodes::{FloatCC, IntCC};
+use cranelift_codegen::ir::immediates::Offset32;
 use cranelift_codegen::ir::types::*;
 use cranelift_codegen::ir::{
-    self, ConstantData, InstBuilder, JumpTableData, MemFlags, Value, ValueLabel,
+    self, AtomicRmwOp, ConstantData, InstBuilder, JumpTableData, MemFlags, Value, ValueLabel,
 };
 use cranelift_codegen::packed_option::ReservedValue;
 use cranelift_frontend::{FunctionBuilder, Variable};
+use std::vec::Vec;
 use wasmparser::{MemoryImmediate, Operator};
 
 // Clippy warns about "flags: _" but its important to document that the flags field is ignored
@@ -341,7 +343,15 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
         }
         Operator::BrIf { relative_depth } => translate_br_if(*relative_depth, builder, state),
         Operator::BrTable { table } => {
-            let (depths, default) = table.read_table()?;
+            let mut depths = Vec::with_capacity(table.len());
+            let mut default = None;
+            for target in table.targets() {
+                match target? {
+                    (depth, false) => depths.push(depth),
+                    (depth, true) => default = Some(depth),
+                }
+            }
+            let default = default.expect("br_table always has a default target");
             let mut min_depth = default;
             for depth in &*depths {
                 if *depth < min_depth {
@@ -512,145 +522,178 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
             state.popn(num_args);
             state.pushn(inst_results);
         }
+        /******************************* Tail calls ****************************************
+         * A tail call ends the function: the callee's results are returned to our caller.
+         ************************************************************************************/
+        Operator::ReturnCall { function_index } => {
+            let (fref, num_args) = state.get_direct_func(builder.func, *function_index, environ)?;
+
+            // Bitcast any vector arguments to their default type, I8X16, before calling.
+            let callee_signature =
+                &builder.func.dfg.signatures[builder.func.dfg.ext_funcs[fref].signature];
+            let args = state.peekn_mut(num_args);
+            bitcast_arguments(args, &callee_signature.param_types(), builder);
+
+            environ.translate_return_call(
+                builder,
+                FuncIndex::from_u32(*function_index),
+                fref,
+                args,
+            )?;
+            state.popn(num_args);
+            state.reachable = false;
+        }
+        Operator::ReturnCallIndirect { index, table_index } => {
+            let (sigref, num_args) = state.get_indirect_sig(builder.func, *index, environ)?;
+            let table = state.get_table(builder.func, *table_index, environ)?;
+            let callee = state.pop1();
+
+            // Bitcast any vector arguments to their default type, I8X16, before calling.
+            let callee_signature = &builder.func.dfg.signatures[sigref];
+            let args = state.peekn_mut(num_args);
+            bitcast_arguments(args, &callee_signature.param_types(), builder);
+
+            environ.translate_return_call_indirect(
+                builder,
+                TableIndex::from_u32(*table_index),
+                table,
+                SignatureIndex::from_u32(*index),
+                sigref,
+                callee,
+                state.peekn(num_args),
+            )?;
+            state.popn(num_args);
+            state.reachable = false;
+        }
         /******************************* Memory management ***********************************
          * Memory management is handled by environment. It is usually translated into calls to
          * special functions.
          ************************************************************************************/
-        Operator::MemoryGrow { reserved } => {
-            // The WebAssembly MVP only supports one linear memory, but we expect the reserved
-            // argument to be a memory index.
-            let heap_index = MemoryIndex::from_u32(*reserved);
-            let heap = state.get_heap(builder.func, *reserved, environ)?;
+        Operator::MemoryGrow { mem, .. } => {
+            let heap_index = MemoryIndex::from_u32(*mem);
+            let heap = state.get_heap(builder.func, *mem, environ)?;
             let val = state.pop1();
             state.push1(environ.translate_memory_grow(builder.cursor(), heap_index, heap, val)?)
         }
-        Operator::MemorySize { reserved } => {
-            let heap_index = MemoryIndex::from_u32(*reserved);
-            let heap = state.get_heap(builder.func, *reserved, environ)?;
+        Operator::MemorySize { mem, .. } => {
+            let heap_index = MemoryIndex::from_u32(*mem);
+            let heap = state.get_heap(builder.func, *mem, environ)?;
             state.push1(environ.translate_memory_size(builder.cursor(), heap_index, heap)?);
         }
         /******************************* Load instructions ***********************************
          * Wasm specifies an integer alignment flag but we drop it in Cranelift.
          * The memory base address is provided by the environment.
          ************************************************************************************/
-        Operator::I32Load8U {
-            memarg: MemoryImmediate { flags: _, offset },
-        } => {
-            translate_load(*offset, ir::Opcode::Uload8, I32, builder, state, environ)?;
-        }
-        Operator::I32Load16U {
-            memarg: MemoryImmediate { flags: _, offset },
-        } => {
-            translate_load(*offset, ir::Opcode::Uload16, I32, builder, state, environ)?;
-        }
-        Operator::I32Load8S {
-            memarg: MemoryImmediate { flags: _, offset },
-        } => {
-            translate_load(*offset, ir::Opcode::Sload8, I32, builder, state, environ)?;
-        }
-        Operator::I32Load16S {
-            memarg: MemoryImmediate { flags: _, offset },
-        } => {
-            translate_load(*offset, ir::Opcode::Sload16, I32, builder, state, environ)?;
-        }
-        Operator::I64Load8U {
-            memarg: MemoryImmediate { flags: _, offset },
-        } => {
-            translate_load(*offset, ir::Opcode::Uload8, I64, builder, state, environ)?;
-        }
-        Operator::I64Load16U {
-            memarg: MemoryImmediate { flags: _, offset },
-        } => {
-            translate_load(*offset, ir::Opcode::Uload16, I64, builder, state, environ)?;
-        }
-        Operator::I64Load8S {
-            memarg: MemoryImmediate { flags: _, offset },
-        } => {
-            translate_load(*offset, ir::Opcode::Sload8, I64, builder, state, environ)?;
-        }
-        Operator::I64Load16S {
-            memarg: MemoryImmediate { flags: _, offset },
-        } => {
-            translate_load(*offset, ir::Opcode::Sload16, I64, builder, state, environ)?;
-        }
-        Operator::I64Load32S {
-            memarg: MemoryImmediate { flags: _, offset },
-        } => {
-            translate_load(*offset, ir::Opcode::Sload32, I64, builder, state, environ)?;
-        }
-        Operator::I64Load32U {
-            memarg: MemoryImmediate { flags: _, offset },
-        } => {
-            translate_load(*offset, ir::Opcode::Uload32, I64, builder, state, environ)?;
-        }
-        Operator::I32Load {
-            memarg: MemoryImmediate { flags: _, offset },
-        } => {
-            translate_load(*offset, ir::Opcode::Load, I32, builder, state, environ)?;
-        }
-        Operator::F32Load {
-            memarg: MemoryImmediate { flags: _, offset },
-        } => {
-            translate_load(*offset, ir::Opcode::Load, F32, builder, state, environ)?;
-        }
-        Operator::I64Load {
-            memarg: MemoryImmediate { flags: _, offset },
-        } => {
-            translate_load(*offset, ir::Opcode::Load, I64, builder, state, environ)?;
-        }
-        Operator::F64Load {
-            memarg: MemoryImmediate { flags: _, offset },
-        } => {
-            translate_load(*offset, ir::Opcode::Load, F64, builder, state, environ)?;
-        }
-        Operator::V128Load {
-            memarg: MemoryImmediate { flags: _, offset },
-        } => {
-            translate_load(*offset, ir::Opcode::Load, I8X16, builder, state, environ)?;
+        Operator::I32Load8U { memarg } => {
+            translate_load(memarg, ir::Opcode::Uload8, I32, builder, state, environ)?;
         }
-        /****************************** Store instructions ***********************************
-         * Wasm specifies an integer alignment flag but we drop it in Cranelift.
-         * The memory base address is provided by the environment.
-         ************************************************************************************/
-        Operator::I32Store {
-            memarg: MemoryImmediate { flags: _, offset },
+        Operator::I32Load16U { memarg } => {
+            translate_load(memarg, ir::Opcode::Uload16, I32, builder, state, environ)?;
+        }
+        Operator::I32Load8S { memarg } => {
+            translate_load(memarg, ir::Opcode::Sload8, I32, builder, state, environ)?;
         }
-        | Operator::I64Store {
-            memarg: MemoryImmediate { flags: _, offset },
+        Operator::I32Load16S { memarg } => {
+            translate_load(memarg, ir::Opcode::Sload16, I32, builder, state, environ)?;
         }
-        | Operator::F32Store {
-            memarg: MemoryImmediate { flags: _, offset },
+        Operator::I64Load8U { memarg } => {
+            translate_load(memarg, ir::Opcode::Uload8, I64, builder, state, environ)?;
         }
-        | Operator::F64Store {
-            memarg: MemoryImmediate { flags: _, offset },
-        } => {
-            translate_store(*offset, ir::Opcode::Store, builder, state, environ)?;
+        Operator::I64Load16U { memarg } => {
+            translate_load(memarg, ir::Opcode::Uload16, I64, builder, state, environ)?;
         }
-        Operator::I32Store8 {
-            memarg: MemoryImmediate { flags: _, offset },
+        Operator::I64Load8S { memarg } => {
+            translate_load(memarg, ir::Opcode::Sload8, I64, builder, state, environ)?;
         }
-        | Operator::I64Store8 {
-            memarg: MemoryImmediate { flags: _, offset },
-        } => {
-            translate_store(*offset, ir::Opcode::Istore8, builder, state, environ)?;
+        Operator::I64Load16S { memarg } => {
+            translate_load(memarg, ir::Opcode::Sload16, I64, builder, state, environ)?;
         }
-        Operator::I32Store16 {
-            memarg: MemoryImmediate { flags: _, offset },
+        Operator::I64Load32S { memarg } => {
+            translate_load(memarg, ir::Opcode::Sload32, I64, builder, state, environ)?;
         }
-        | Operator::I64Store16 {
-            memarg: MemoryImmediate { flags: _, offset },
-        } => {
-            translate_store(*offset, ir::Opcode::Istore16, builder, state, environ)?;
+        Operator::I64Load32U { memarg } => {
+            translate_load(memarg, ir::Opcode::Uload32, I64, builder, state, environ)?;
         }
-        Operator::I64Store32 {
-            memarg: MemoryImmediate { flags: _, offset },
-        } => {
-            translate_store(*offset, ir::Opcode::Istore32, builder, state, environ)?;
+        Operator::I32Load { memarg } => {
+            translate_load(memarg, ir::Opcode::Load, I32, builder, state, environ)?;
         }
-        Operator::V128Store {
-            memarg: MemoryImmediate { flags: _, offset },
-        } => {
-            translate_store(*offset, ir::Opcode::Store, builder, state, environ)?;
+        Operator::F32Load { memarg } => {
+            translate_load(memarg, ir::Opcode::Load, F32, builder, state, environ)?;
+        }
+        Operator::I64Load { memarg } => {
+            translate_load(memarg, ir::Opcode::Load, I64, builder, state, environ)?;
+        }
+        Operator::F64Load { memarg } => {
+            translate_load(memarg, ir::Opcode::Load, F64, builder, state, environ)?;
+        }
+        Operator::V128Load { memarg } => {
+            translate_load(memarg, ir::Opcode::Load, I8X16, builder, state, environ)?;
+        }
+        Operator::V128Load8Splat { memarg } => {
+            translate_load(memarg, ir::Opcode::Uload8, I32, builder, state, environ)?;
+            let reduced = builder.ins().ireduce(I8, state.pop1());
+            state.push1(builder.ins().splat(I8X16, reduced))
+        }
+        Operator::V128Load16Splat { memarg } => {
+            translate_load(memarg, ir::Opcode::Uload16, I32, builder, state, environ)?;
+            let reduced = builder.ins().ireduce(I16, state.pop1());
+            state.push1(builder.ins().splat(I16X8, reduced))
+        }
+        Operator::V128Load32Splat { memarg } => {
+            translate_load(memarg, ir::Opcode::Load, I32, builder, state, environ)?;
+            let splatted = builder.ins().splat(I32X4, state.pop1());
+            state.push1(splatted)
+        }
+        Operator::V128Load64Splat { memarg } => {
+            translate_load(memarg, ir::Opcode::Load, I64, builder, state, environ)?;
+            let splatted = builder.ins().splat(I64X2, state.pop1());
+            state.push1(splatted)
+        }
+        Operator::V128Load8x8S { memarg } => {
+            let (flags, base, offset) = prepare_load(memarg, builder, state, environ)?;
+            state.push1(builder.ins().sload8x8(flags, base, offset))
+        }
+        Operator::V128Load8x8U { memarg } => {
+            let (flags, base, offset) = prepare_load(memarg, builder, state, environ)?;
+            state.push1(builder.ins().uload8x8(flags, base, offset))
+        }
+        Operator::V128Load16x4S { memarg } => {
+            let (flags, base, offset) = prepare_load(memarg, builder, state, environ)?;
+            state.push1(builder.ins().sload16x4(flags, base, offset))
+        }
+        Operator::V128Load16x4U { memarg } => {
+            let (flags, base, offset) = prepare_load(memarg, builder, state, environ)?;
+            state.push1(builder.ins().uload16x4(flags, base, offset))
+        }
+        Operator::V128Load32x2S { memarg } => {
+            let (flags, base, offset) = prepare_load(memarg, builder, state, environ)?;
+            state.push1(builder.ins().sload32x2(flags, base, offset))
+        }
+        Operator::V128Load32x2U { memarg } => {
+            let (flags, base, offset) = prepare_load(memarg, builder, state, environ)?;
+            state.push1(builder.ins().uload32x2(flags, base, offset))
+        }
+        /****************************** Store instructions ***********************************
+         * Wasm specifies an integer alignment flag but we drop it in Cranelift.
+         * The memory base address is provided by the environment.
+         ************************************************************************************/
+        Operator::I32Store { memarg }
+        | Operator::I64Store { memarg }
+        | Operator::F32Store { memarg }
+        | Operator::F64Store { memarg } => {
+            translate_store(memarg, ir::Opcode::Store, builder, state, environ)?;
+        }
+        Operator::I32Store8 { memarg } | Operator::I64Store8 { memarg } => {
+            translate_store(memarg, ir::Opcode::Istore8, builder, state, environ)?;
+        }
+        Operator::I32Store16 { memarg } | Operator::I64Store16 { memarg } => {
+            translate_store(memarg, ir::Opcode::Istore16, builder, state, environ)?;
+        }
+        Operator::I64Store32 { memarg } => {
+            translate_store(memarg, ir::Opcode::Istore32, builder, state, environ)?;
+        }
+        Operator::V128Store { memarg } => {
+            translate_store(memarg, ir::Opcode::Store, builder, state, environ)?;
         }
         /****************************** Nullary Operators ************************************/
         Operator::I32Const { value } => state.push1(builder.ins().iconst(I32, i64::from(*value))),
@@ -948,114 +991,273 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
             translate_fcmp(FloatCC::LessThanOrEqual, builder, state)
         }
         Operator::TypedSelect { .. } => {
-            return Err(wasm_unsupported!("proposed typed select operator {:?}", op))
+            // The type annotation only matters to validation, since `select` works on any type.
+            let (arg1, arg2, cond) = state.pop3();
+            state.push1(builder.ins().select(cond, arg1, arg2));
         }
-        Operator::RefNull => state.push1(builder.ins().null(environ.reference_type())),
+        Operator::RefNull { .. } => state.push1(builder.ins().null(environ.reference_type())),
         Operator::RefIsNull => {
             let arg = state.pop1();
             let val = builder.ins().is_null(arg);
-            state.push1(val);
+            state.push1(builder.ins().bint(I32, val));
+        }
+        Operator::RefFunc { function_index } => {
+            let func_index = FuncIndex::from_u32(*function_index);
+            state.push1(environ.translate_ref_func(builder.cursor(), func_index)?);
+        }
+        /******************************* Atomic instructions *********************************
+         * The threads proposal requires atomic accesses to be naturally aligned, which is
+         * checked dynamically. Narrow accesses zero-extend the value they load.
+         ************************************************************************************/
+        Operator::MemoryAtomicNotify { memarg } => {
+            let heap_index = MemoryIndex::from_u32(memarg.memory);
+            let heap = state.get_heap(builder.func, memarg.memory, environ)?;
+            let (addr, count) = state.pop2();
+            let addr = translate_atomic_addr(heap, addr, memarg.offset, I32, builder, environ)?;
+            state.push1(environ.translate_atomic_notify(
+                builder.cursor(),
+                heap_index,
+                heap,
+                addr,
+                count,
+            )?);
+        }
+        Operator::MemoryAtomicWait32 { memarg } | Operator::MemoryAtomicWait64 { memarg } => {
+            let heap_index = MemoryIndex::from_u32(memarg.memory);
+            let heap = state.get_heap(builder.func, memarg.memory, environ)?;
+            let (addr, expected, timeout) = state.pop3();
+            let access_ty = builder.func.dfg.value_type(expected);
+            let addr =
+                translate_atomic_addr(heap, addr, memarg.offset, access_ty, builder, environ)?;
+            state.push1(environ.translate_atomic_wait(
+                builder.cursor(),
+                heap_index,
+                heap,
+                addr,
+                expected,
+                timeout,
+            )?);
+        }
+        Operator::I32AtomicLoad { memarg } => {
+            translate_atomic_load(memarg, I32, I32, builder, state, environ)?;
+        }
+        Operator::I64AtomicLoad { memarg } => {
+            translate_atomic_load(memarg, I64, I64, builder, state, environ)?;
+        }
+        Operator::I32AtomicLoad8U { memarg } => {
+            translate_atomic_load(memarg, I8, I32, builder, state, environ)?;
+        }
+        Operator::I32AtomicLoad16U { memarg } => {
+            translate_atomic_load(memarg, I16, I32, builder, state, environ)?;
+        }
+        Operator::I64AtomicLoad8U { memarg } => {
+            translate_atomic_load(memarg, I8, I64, builder, state, environ)?;
+        }
+        Operator::I64AtomicLoad16U { memarg } => {
+            translate_atomic_load(memarg, I16, I64, builder, state, environ)?;
+        }
+        Operator::I64AtomicLoad32U { memarg } => {
+            translate_atomic_load(memarg, I32, I64, builder, state, environ)?;
+        }
+        Operator::I32AtomicStore { memarg } => {
+            translate_atomic_store(memarg, I32, builder, state, environ)?;
+        }
+        Operator::I64AtomicStore { memarg } => {
+            translate_atomic_store(memarg, I64, builder, state, environ)?;
+        }
+        Operator::I32AtomicStore8 { memarg } => {
+            translate_atomic_store(memarg, I8, builder, state, environ)?;
+        }
+        Operator::I32AtomicStore16 { memarg } => {
+            translate_atomic_store(memarg, I16, builder, state, environ)?;
+        }
+        Operator::I64AtomicStore8 { memarg } => {
+            translate_atomic_store(memarg, I8, builder, state, environ)?;
+        }
+        Operator::I64AtomicStore16 { memarg } => {
+            translate_atomic_store(memarg, I16, builder, state, environ)?;
+        }
+        Operator::I64AtomicStore32 { memarg } => {
+            translate_atomic_store(memarg, I32, builder, state, environ)?;
+        }
+        Operator::I32AtomicRmwAdd { memarg } => {
+            translate_atomic_rmw(memarg, AtomicRmwOp::Add, I32, builder, state, environ)?;
+        }
+        Operator::I64AtomicRmwAdd { memarg } => {
+            translate_atomic_rmw(memarg, AtomicRmwOp::Add, I64, builder, state, environ)?;
+        }
+        Operator::I32AtomicRmw8AddU { memarg } => {
+            translate_atomic_rmw(memarg, AtomicRmwOp::Add, I8, builder, state, environ)?;
+        }
+        Operator::I32AtomicRmw16AddU { memarg } => {
+            translate_atomic_rmw(memarg, AtomicRmwOp::Add, I16, builder, state, environ)?;
+        }
+        Operator::I64AtomicRmw8AddU { memarg } => {
+            translate_atomic_rmw(memarg, AtomicRmwOp::Add, I8, builder, state, environ)?;
+        }
+        Operator::I64AtomicRmw16AddU { memarg } => {
+            translate_atomic_rmw(memarg, AtomicRmwOp::Add, I16, builder, state, environ)?;
+        }
+        Operator::I64AtomicRmw32AddU { memarg } => {
+            translate_atomic_rmw(memarg, AtomicRmwOp::Add, I32, builder, state, environ)?;
+        }
+        Operator::I32AtomicRmwSub { memarg } => {
+            translate_atomic_rmw(memarg, AtomicRmwOp::Sub, I32, builder, state, environ)?;
+        }
+        Operator::I64AtomicRmwSub { memarg } => {
+            translate_atomic_rmw(memarg, AtomicRmwOp::Sub, I64, builder, state, environ)?;
+        }
+        Operator::I32AtomicRmw8SubU { memarg } => {
+            translate_atomic_rmw(memarg, AtomicRmwOp::Sub, I8, builder, state, environ)?;
+        }
+        Operator::I32AtomicRmw16SubU { memarg } => {
+            translate_atomic_rmw(memarg, AtomicRmwOp::Sub, I16, builder, state, environ)?;
+        }
+        Operator::I64AtomicRmw8SubU { memarg } => {
+            translate_atomic_rmw(memarg, AtomicRmwOp::Sub, I8, builder, state, environ)?;
+        }
+        Operator::I64AtomicRmw16SubU { memarg } => {
+            translate_atomic_rmw(memarg, AtomicRmwOp::Sub, I16, builder, state, environ)?;
+        }
+        Operator::I64AtomicRmw32SubU { memarg } => {
+            translate_atomic_rmw(memarg, AtomicRmwOp::Sub, I32, builder, state, environ)?;
+        }
+        Operator::I32AtomicRmwAnd { memarg } => {
+            translate_atomic_rmw(memarg, AtomicRmwOp::And, I32, builder, state, environ)?;
+        }
+        Operator::I64AtomicRmwAnd { memarg } => {
+            translate_atomic_rmw(memarg, AtomicRmwOp::And, I64, builder, state, environ)?;
+        }
+        Operator::I32AtomicRmw8AndU { memarg } => {
+            translate_atomic_rmw(memarg, AtomicRmwOp::And, I8, builder, state, environ)?;
+        }
+        Operator::I32AtomicRmw16AndU { memarg } => {
+            translate_atomic_rmw(memarg, AtomicRmwOp::And, I16, builder, state, environ)?;
+        }
+        Operator::I64AtomicRmw8AndU { memarg } => {
+            translate_atomic_rmw(memarg, AtomicRmwOp::And, I8, builder, state, environ)?;
+        }
+        Operator::I64AtomicRmw16AndU { memarg } => {
+            translate_atomic_rmw(memarg, AtomicRmwOp::And, I16, builder, state, environ)?;
+        }
+        Operator::I64AtomicRmw32AndU { memarg } => {
+            translate_atomic_rmw(memarg, AtomicRmwOp::And, I32, builder, state, environ)?;
+        }
+        Operator::I32AtomicRmwOr { memarg } => {
+            translate_atomic_rmw(memarg, AtomicRmwOp::Or, I32, builder, state, environ)?;
+        }
+        Operator::I64AtomicRmwOr { memarg } => {
+            translate_atomic_rmw(memarg, AtomicRmwOp::Or, I64, builder, state, environ)?;
+        }
+        Operator::I32AtomicRmw8OrU { memarg } => {
+            translate_atomic_rmw(memarg, AtomicRmwOp::Or, I8, builder, state, environ)?;
+        }
+        Operator::I32AtomicRmw16OrU { memarg } => {
+            translate_atomic_rmw(memarg, AtomicRmwOp::Or, I16, builder, state, environ)?;
+        }
+        Operator::I64AtomicRmw8OrU { memarg } => {
+            translate_atomic_rmw(memarg, AtomicRmwOp::Or, I8, builder, state, environ)?;
+        }
+        Operator::I64AtomicRmw16OrU { memarg } => {
+            translate_atomic_rmw(memarg, AtomicRmwOp::Or, I16, builder, state, environ)?;
+        }
+        Operator::I64AtomicRmw32OrU { memarg } => {
+            translate_atomic_rmw(memarg, AtomicRmwOp::Or, I32, builder, state, environ)?;
+        }
+        Operator::I32AtomicRmwXor { memarg } => {
+            translate_atomic_rmw(memarg, AtomicRmwOp::Xor, I32, builder, state, environ)?;
+        }
+        Operator::I64AtomicRmwXor { memarg } => {
+            translate_atomic_rmw(memarg, AtomicRmwOp::Xor, I64, builder, state, environ)?;
+        }
+        Operator::I32AtomicRmw8XorU { memarg } => {
+            translate_atomic_rmw(memarg, AtomicRmwOp::Xor, I8, builder, state, environ)?;
+        }
+        Operator::I32AtomicRmw16XorU { memarg } => {
+            translate_atomic_rmw(memarg, AtomicRmwOp::Xor, I16, builder, state, environ)?;
+        }
+        Operator::I64AtomicRmw8XorU { memarg } => {
+            translate_atomic_rmw(memarg, AtomicRmwOp::Xor, I8, builder, state, environ)?;
+        }
+        Operator::I64AtomicRmw16XorU { memarg } => {
+            translate_atomic_rmw(memarg, AtomicRmwOp::Xor, I16, builder, state, environ)?;
+        }
+        Operator::I64AtomicRmw32XorU { memarg } => {
+            translate_atomic_rmw(memarg, AtomicRmwOp::Xor, I32, builder, state, environ)?;
+        }
+        Operator::I32AtomicRmwXchg { memarg } => {
+            translate_atomic_rmw(memarg, AtomicRmwOp::Xchg, I32, builder, state, environ)?;
+        }
+        Operator::I64AtomicRmwXchg { memarg } => {
+            translate_atomic_rmw(memarg, AtomicRmwOp::Xchg, I64, builder, state, environ)?;
+        }
+        Operator::I32AtomicRmw8XchgU { memarg } => {
+            translate_atomic_rmw(memarg, AtomicRmwOp::Xchg, I8, builder, state, environ)?;
+        }
+        Operator::I32AtomicRmw16XchgU { memarg } => {
+            translate_atomic_rmw(memarg, AtomicRmwOp::Xchg, I16, builder, state, environ)?;
+        }
+        Operator::I64AtomicRmw8XchgU { memarg } => {
+            translate_atomic_rmw(memarg, AtomicRmwOp::Xchg, I8, builder, state, environ)?;
+        }
+        Operator::I64AtomicRmw16XchgU { memarg } => {
+            translate_atomic_rmw(memarg, AtomicRmwOp::Xchg, I16, builder, state, environ)?;
         }
-        Operator::RefFunc { .. } => {
-            return Err(wasm_unsupported!("proposed ref operator {:?}", op))
-        }
-        Operator::AtomicNotify { .. }
-        | Operator::I32AtomicWait { .. }
-        | Operator::I64AtomicWait { .. }
-        | Operator::I32AtomicLoad { .. }
-        | Operator::I64AtomicLoad { .. }
-        | Operator::I32AtomicLoad8U { .. }
-        | Operator::I32AtomicLoad16U { .. }
-        | Operator::I64AtomicLoad8U { .. }
-        | Operator::I64AtomicLoad16U { .. }
-        | Operator::I64AtomicLoad32U { .. }
-        | Operator::I32AtomicStore { .. }
-        | Operator::I64AtomicStore { .. }
-        | Operator::I32AtomicStore8 { .. }
-        | Operator::I32AtomicStore16 { .. }
-        | Operator::I64AtomicStore8 { .. }
-        | Operator::I64AtomicStore16 { .. }
-        | Operator::I64AtomicStore32 { .. }
-        | Operator::I32AtomicRmwAdd { .. }
-        | Operator::I64AtomicRmwAdd { .. }
-        | Operator::I32AtomicRmw8AddU { .. }
-        | Operator::I32AtomicRmw16AddU { .. }
-        | Operator::I64AtomicRmw8AddU { .. }
-        | Operator::I64AtomicRmw16AddU { .. }
-        | Operator::I64AtomicRmw32AddU { .. }
-        | Operator::I32AtomicRmwSub { .. }
-        | Operator::I64AtomicRmwSub { .. }
-        | Operator::I32AtomicRmw8SubU { .. }
-        | Operator::I32AtomicRmw16SubU { .. }
-        | Operator::I64AtomicRmw8SubU { .. }
-        | Operator::I64AtomicRmw16SubU { .. }
-        | Operator::I64AtomicRmw32SubU { .. }
-        | Operator::I32AtomicRmwAnd { .. }
-        | Operator::I64AtomicRmwAnd { .. }
-        | Operator::I32AtomicRmw8AndU { .. }
-        | Operator::I32AtomicRmw16AndU { .. }
-        | Operator::I64AtomicRmw8AndU { .. }
-        | Operator::I64AtomicRmw16AndU { .. }
-        | Operator::I64AtomicRmw32AndU { .. }
-        | Operator::I32AtomicRmwOr { .. }
-        | Operator::I64AtomicRmwOr { .. }
-        | Operator::I32AtomicRmw8OrU { .. }
-        | Operator::I32AtomicRmw16OrU { .. }
-        | Operator::I64AtomicRmw8OrU { .. }
-        | Operator::I64AtomicRmw16OrU { .. }
-        | Operator::I64AtomicRmw32OrU { .. }
-        | Operator::I32AtomicRmwXor { .. }
-        | Operator::I64AtomicRmwXor { .. }
-        | Operator::I32AtomicRmw8XorU { .. }
-        | Operator::I32AtomicRmw16XorU { .. }
-        | Operator::I64AtomicRmw8XorU { .. }
-        | Operator::I64AtomicRmw16XorU { .. }
-        | Operator::I64AtomicRmw32XorU { .. }
-        | Operator::I32AtomicRmwXchg { .. }
-        | Operator::I64AtomicRmwXchg { .. }
-        | Operator::I32AtomicRmw8XchgU { .. }
-        | Operator::I32AtomicRmw16XchgU { .. }
-        | Operator::I64AtomicRmw8XchgU { .. }
-        | Operator::I64AtomicRmw16XchgU { .. }
-        | Operator::I64AtomicRmw32XchgU { .. }
-        | Operator::I32AtomicRmwCmpxchg { .. }
-        | Operator::I64AtomicRmwCmpxchg { .. }
-        | Operator::I32AtomicRmw8CmpxchgU { .. }
-        | Operator::I32AtomicRmw16CmpxchgU { .. }
-        | Operator::I64AtomicRmw8CmpxchgU { .. }
-        | Operator::I64AtomicRmw16CmpxchgU { .. }
-        | Operator::I64AtomicRmw32CmpxchgU { .. }
-        | Operator::AtomicFence { .. } => {
-            return Err(wasm_unsupported!("proposed thread operator {:?}", op));
-        }
-        Operator::MemoryCopy => {
-            // The WebAssembly MVP only supports one linear memory and
-            // wasmparser will ensure that the memory indices specified are
-            // zero.
-            let heap_index = MemoryIndex::from_u32(0);
-            let heap = state.get_heap(builder.func, 0, environ)?;
+        Operator::I64AtomicRmw32XchgU { memarg } => {
+            translate_atomic_rmw(memarg, AtomicRmwOp::Xchg, I32, builder, state, environ)?;
+        }
+        Operator::I32AtomicRmwCmpxchg { memarg } => {
+            translate_atomic_cas(memarg, I32, builder, state, environ)?;
+        }
+        Operator::I64AtomicRmwCmpxchg { memarg } => {
+            translate_atomic_cas(memarg, I64, builder, state, environ)?;
+        }
+        Operator::I32AtomicRmw8CmpxchgU { memarg } => {
+            translate_atomic_cas(memarg, I8, builder, state, environ)?;
+        }
+        Operator::I32AtomicRmw16CmpxchgU { memarg } => {
+            translate_atomic_cas(memarg, I16, builder, state, environ)?;
+        }
+        Operator::I64AtomicRmw8CmpxchgU { memarg } => {
+            translate_atomic_cas(memarg, I8, builder, state, environ)?;
+        }
+        Operator::I64AtomicRmw16CmpxchgU { memarg } => {
+            translate_atomic_cas(memarg, I16, builder, state, environ)?;
+        }
+        Operator::I64AtomicRmw32CmpxchgU { memarg } => {
+            translate_atomic_cas(memarg, I32, builder, state, environ)?;
+        }
+        Operator::AtomicFence { flags: _ } => {
+            builder.ins().fence();
+        }
+        Operator::MemoryCopy { src, dst } => {
+            let dst_heap = state.get_heap(builder.func, *dst, environ)?;
+            let src_heap = state.get_heap(builder.func, *src, environ)?;
             let len = state.pop1();
-            let src = state.pop1();
-            let dest = state.pop1();
-            environ.translate_memory_copy(builder.cursor(), heap_index, heap, dest, src, len)?;
-        }
-        Operator::MemoryFill => {
-            // The WebAssembly MVP only supports one linear memory and
-            // wasmparser will ensure that the memory index specified is
-            // zero.
-            let heap_index = MemoryIndex::from_u32(0);
-            let heap = state.get_heap(builder.func, 0, environ)?;
+            let src_pos = state.pop1();
+            let dst_pos = state.pop1();
+            environ.translate_memory_copy(
+                builder.cursor(),
+                MemoryIndex::from_u32(*dst),
+                dst_heap,
+                MemoryIndex::from_u32(*src),
+                src_heap,
+                dst_pos,
+                src_pos,
+                len,
+            )?;
+        }
+        Operator::MemoryFill { mem } => {
+            let heap_index = MemoryIndex::from_u32(*mem);
+            let heap = state.get_heap(builder.func, *mem, environ)?;
             let len = state.pop1();
             let val = state.pop1();
             let dest = state.pop1();
             environ.translate_memory_fill(builder.cursor(), heap_index, heap, dest, val, len)?;
         }
-        Operator::MemoryInit { segment } => {
-            // The WebAssembly MVP only supports one linear memory and
-            // wasmparser will ensure that the memory index specified is
-            // zero.
-            let heap_index = MemoryIndex::from_u32(0);
-            let heap = state.get_heap(builder.func, 0, environ)?;
+        Operator::MemoryInit { segment, mem } => {
+            let heap_index = MemoryIndex::from_u32(*mem);
+            let heap = state.get_heap(builder.func, *mem, environ)?;
             let len = state.pop1();
             let src = state.pop1();
             let dest = state.pop1();
@@ -1118,17 +1320,52 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
                 len,
             )?;
         }
-        Operator::TableFill { .. } => {
-            return Err(wasm_unsupported!("proposed table operator {:?}", op));
+        Operator::TableFill { table: index } => {
+            let table = state.get_table(builder.func, *index, environ)?;
+            let (dst, val, len) = state.pop3();
+            environ.translate_table_fill(
+                builder.cursor(),
+                TableIndex::from_u32(*index),
+                table,
+                dst,
+                val,
+                len,
+            )?;
         }
         Operator::ElemDrop { segment } => {
             environ.translate_elem_drop(builder.cursor(), *segment)?;
         }
-        Operator::TableGet { .. } | Operator::TableSet { .. } | Operator::TableGrow { .. } => {
-            return Err(wasm_unsupported!(
-                "proposed reference types operator {:?}",
-                op
-            ));
+        Operator::TableGet { table: index } => {
+            let table = state.get_table(builder.func, *index, environ)?;
+            let elem_index = state.pop1();
+            state.push1(environ.translate_table_get(
+                builder.cursor(),
+                TableIndex::from_u32(*index),
+                table,
+                elem_index,
+            )?);
+        }
+        Operator::TableSet { table: index } => {
+            let table = state.get_table(builder.func, *index, environ)?;
+            let (elem_index, value) = state.pop2();
+            environ.translate_table_set(
+                builder.cursor(),
+                TableIndex::from_u32(*index),
+                table,
+                value,
+                elem_index,
+            )?;
+        }
+        Operator::TableGrow { table: index } => {
+            let table = state.get_table(builder.func, *index, environ)?;
+            let (init_value, delta) = state.pop2();
+            state.push1(environ.translate_table_grow(
+                builder.cursor(),
+                TableIndex::from_u32(*index),
+                table,
+                delta,
+                init_value,
+            )?);
         }
         Operator::V128Const { value } => {
             let data = value.bytes().to_vec().into();
@@ -1184,7 +1421,7 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
                 builder,
             ))
         }
-        Operator::V8x16Shuffle { lanes, .. } => {
+        Operator::I8x16Shuffle { lanes, .. } => {
             let (a, b) = pop2_with_bitcast(state, I8X16, builder);
             let lanes = ConstantData::from(lanes.as_ref());
             let mask = builder.func.dfg.immediates.push(lanes);
@@ -1199,11 +1436,11 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
             let (a, b) = pop2_with_bitcast(state, type_of(op), builder);
             state.push1(builder.ins().iadd(a, b))
         }
-        Operator::I8x16AddSaturateS | Operator::I16x8AddSaturateS => {
+        Operator::I8x16AddSatS | Operator::I16x8AddSatS => {
             let (a, b) = pop2_with_bitcast(state, type_of(op), builder);
             state.push1(builder.ins().sadd_sat(a, b))
         }
-        Operator::I8x16AddSaturateU | Operator::I16x8AddSaturateU => {
+        Operator::I8x16AddSatU | Operator::I16x8AddSatU => {
             let (a, b) = pop2_with_bitcast(state, type_of(op), builder);
             state.push1(builder.ins().uadd_sat(a, b))
         }
@@ -1211,11 +1448,11 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
             let (a, b) = pop2_with_bitcast(state, type_of(op), builder);
             state.push1(builder.ins().isub(a, b))
         }
-        Operator::I8x16SubSaturateS | Operator::I16x8SubSaturateS => {
+        Operator::I8x16SubSatS | Operator::I16x8SubSatS => {
             let (a, b) = pop2_with_bitcast(state, type_of(op), builder);
             state.push1(builder.ins().ssub_sat(a, b))
         }
-        Operator::I8x16SubSaturateU | Operator::I16x8SubSaturateU => {
+        Operator::I8x16SubSatU | Operator::I16x8SubSatU => {
             let (a, b) = pop2_with_bitcast(state, type_of(op), builder);
             state.push1(builder.ins().usub_sat(a, b))
         }
@@ -1223,7 +1460,7 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
             let a = pop1_with_bitcast(state, type_of(op), builder);
             state.push1(builder.ins().ineg(a))
         }
-        Operator::I16x8Mul | Operator::I32x4Mul => {
+        Operator::I16x8Mul | Operator::I32x4Mul | Operator::I64x2Mul => {
             let (a, b) = pop2_with_bitcast(state, type_of(op), builder);
             state.push1(builder.ins().imul(a, b))
         }
@@ -1235,28 +1472,28 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
             let a = state.pop1();
             state.push1(builder.ins().bnot(a));
         }
-        Operator::I16x8Shl | Operator::I32x4Shl | Operator::I64x2Shl => {
+        Operator::I8x16Shl | Operator::I16x8Shl | Operator::I32x4Shl | Operator::I64x2Shl => {
             let (a, b) = state.pop2();
             let bitcast_a = optionally_bitcast_vector(a, type_of(op), builder);
-            let bitwidth = i64::from(builder.func.dfg.value_type(a).bits());
+            let bitwidth = i64::from(type_of(op).lane_bits());
             // The spec expects to shift with `b mod lanewidth`; so, e.g., for 16 bit lane-width
             // we do `b AND 15`; this means fewer instructions than `iconst + urem`.
             let b_mod_bitwidth = builder.ins().band_imm(b, bitwidth - 1);
             state.push1(builder.ins().ishl(bitcast_a, b_mod_bitwidth))
         }
-        Operator::I16x8ShrU | Operator::I32x4ShrU | Operator::I64x2ShrU => {
+        Operator::I8x16ShrU | Operator::I16x8ShrU | Operator::I32x4ShrU | Operator::I64x2ShrU => {
             let (a, b) = state.pop2();
             let bitcast_a = optionally_bitcast_vector(a, type_of(op), builder);
-            let bitwidth = i64::from(builder.func.dfg.value_type(a).bits());
+            let bitwidth = i64::from(type_of(op).lane_bits());
             // The spec expects to shift with `b mod lanewidth`; so, e.g., for 16 bit lane-width
             // we do `b AND 15`; this means fewer instructions than `iconst + urem`.
             let b_mod_bitwidth = builder.ins().band_imm(b, bitwidth - 1);
             state.push1(builder.ins().ushr(bitcast_a, b_mod_bitwidth))
         }
-        Operator::I16x8ShrS | Operator::I32x4ShrS => {
+        Operator::I8x16ShrS | Operator::I16x8ShrS | Operator::I32x4ShrS | Operator::I64x2ShrS => {
             let (a, b) = state.pop2();
             let bitcast_a = optionally_bitcast_vector(a, type_of(op), builder);
-            let bitwidth = i64::from(builder.func.dfg.value_type(a).bits());
+            let bitwidth = i64::from(type_of(op).lane_bits());
             // The spec expects to shift with `b mod lanewidth`; so, e.g., for 16 bit lane-width
             // we do `b AND 15`; this means fewer instructions than `iconst + urem`.
             let b_mod_bitwidth = builder.ins().band_imm(b, bitwidth - 1);
@@ -1271,10 +1508,7 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
             // operands must match (hence the bitcast).
             state.push1(builder.ins().bitselect(bitcast_c, bitcast_a, bitcast_b))
         }
-        Operator::I8x16AnyTrue
-        | Operator::I16x8AnyTrue
-        | Operator::I32x4AnyTrue
-        | Operator::I64x2AnyTrue => {
+        Operator::V128AnyTrue => {
             let a = pop1_with_bitcast(state, type_of(op), builder);
             let bool_result = builder.ins().vany_true(a);
             state.push1(builder.ins().bint(I32, bool_result))
@@ -1374,47 +1608,55 @@ pub fn translate_operator<FE: FuncEnvironment + ?Sized>(
             let a = pop1_with_bitcast(state, type_of(op), builder);
             state.push1(builder.ins().fabs(a))
         }
-        Operator::I8x16Shl
-        | Operator::I8x16ShrS
-        | Operator::I8x16ShrU
-        | Operator::I8x16Mul
-        | Operator::I64x2Mul
-        | Operator::I64x2ShrS
-        | Operator::I32x4TruncSatF32x4S
-        | Operator::I32x4TruncSatF32x4U
-        | Operator::I64x2TruncSatF64x2S
-        | Operator::I64x2TruncSatF64x2U
-        | Operator::F32x4ConvertI32x4S
-        | Operator::F32x4ConvertI32x4U
-        | Operator::F64x2ConvertI64x2S
-        | Operator::F64x2ConvertI64x2U { .. }
-        | Operator::I8x16NarrowI16x8S { .. }
-        | Operator::I8x16NarrowI16x8U { .. }
-        | Operator::I16x8NarrowI32x4S { .. }
-        | Operator::I16x8NarrowI32x4U { .. }
-        | Operator::I16x8WidenLowI8x16S { .. }
-        | Operator::I16x8WidenHighI8x16S { .. }
-        | Operator::I16x8WidenLowI8x16U { .. }
-        | Operator::I16x8WidenHighI8x16U { .. }
-        | Operator::I32x4WidenLowI16x8S { .. }
-        | Operator::I32x4WidenHighI16x8S { .. }
-        | Operator::I32x4WidenLowI16x8U { .. }
-        | Operator::I32x4WidenHighI16x8U { .. }
-        | Operator::V8x16Swizzle
-        | Operator::V8x16LoadSplat { .. }
-        | Operator::V16x8LoadSplat { .. }
-        | Operator::V32x4LoadSplat { .. }
-        | Operator::V64x2LoadSplat { .. }
-        | Operator::I16x8Load8x8S { .. }
-        | Operator::I16x8Load8x8U { .. }
-        | Operator::I32x4Load16x4S { .. }
-        | Operator::I32x4Load16x4U { .. }
-        | Operator::I64x2Load32x2S { .. }
-        | Operator::I64x2Load32x2U { .. }
-        | Operator::I8x16RoundingAverageU { .. }
-        | Operator::I16x8RoundingAverageU { .. } => {
-            return Err(wasm_unsupported!("proposed SIMD operator {:?}", op));
+        Operator::I8x16RoundingAverageU | Operator::I16x8RoundingAverageU => {
+            let (a, b) = pop2_with_bitcast(state, type_of(op), builder);
+            state.push1(builder.ins().avg_round(a, b))
+        }
+        Operator::I8x16Swizzle => {
+            let (a, b) = pop2_with_bitcast(state, I8X16, builder);
+            state.push1(builder.ins().swizzle(a, b))
+        }
+        Operator::F32x4ConvertI32x4S => {
+            let a = pop1_with_bitcast(state, I32X4, builder);
+            state.push1(builder.ins().fcvt_from_sint(F32X4, a))
+        }
+        Operator::F32x4ConvertI32x4U => {
+            let a = pop1_with_bitcast(state, I32X4, builder);
+            state.push1(builder.ins().fcvt_from_uint(F32X4, a))
+        }
+        Operator::I32x4TruncSatF32x4S => {
+            let a = pop1_with_bitcast(state, F32X4, builder);
+            state.push1(builder.ins().fcvt_to_sint_sat(I32X4, a))
+        }
+        Operator::I32x4TruncSatF32x4U => {
+            let a = pop1_with_bitcast(state, F32X4, builder);
+            state.push1(builder.ins().fcvt_to_uint_sat(I32X4, a))
+        }
+        Operator::I8x16NarrowI16x8S | Operator::I16x8NarrowI32x4S => {
+            let (a, b) = pop2_with_bitcast(state, type_of(op), builder);
+            state.push1(builder.ins().snarrow(a, b))
+        }
+        Operator::I8x16NarrowI16x8U | Operator::I16x8NarrowI32x4U => {
+            let (a, b) = pop2_with_bitcast(state, type_of(op), builder);
+            state.push1(builder.ins().unarrow(a, b))
+        }
+        Operator::I16x8ExtendLowI8x16S | Operator::I32x4ExtendLowI16x8S => {
+            let a = pop1_with_bitcast(state, type_of(op), builder);
+            state.push1(builder.ins().swiden_low(a))
+        }
+        Operator::I16x8ExtendHighI8x16S | Operator::I32x4ExtendHighI16x8S => {
+            let a = pop1_with_bitcast(state, type_of(op), builder);
+            state.push1(builder.ins().swiden_high(a))
+        }
+        Operator::I16x8ExtendLowI8x16U | Operator::I32x4ExtendLowI16x8U => {
+            let a = pop1_with_bitcast(state, type_of(op), builder);
+            state.push1(builder.ins().uwiden_low(a))
         }
+        Operator::I16x8ExtendHighI8x16U | Operator::I32x4ExtendHighI16x8U => {
+            let a = pop1_with_bitcast(state, type_of(op), builder);
+            state.push1(builder.ins().uwiden_high(a))
+        }
+        op => return Err(wasm_unsupported!("operator {:?}", op)),
     };
     Ok(())
 }
@@ -1550,11 +1792,11 @@ fn translate_unreachable_operator<FE: FuncEnvironment + ?Sized>(
 /// Get the address+offset to use for a heap access.
 fn get_heap_addr(
     heap: ir::Heap,
-    addr32: ir::Value,
+    addr: ir::Value,
     offset: u32,
     addr_ty: Type,
     builder: &mut FunctionBuilder,
-) -> (ir::Value, i32) {
+) -> WasmResult<(ir::Value, i32)> {
     use core::cmp::min;
 
     let mut adjusted_offset = u64::from(offset);
@@ -1571,57 +1813,81 @@ fn get_heap_addr(
     // even if the access goes beyond the offset-guard pages. This is because the first byte
     // pointed to is inside the offset-guard pages.
     let check_size = min(u64::from(u32::MAX), 1 + adjusted_offset) as u32;
-    let base = builder.ins().heap_addr(addr_ty, heap, addr32, check_size);
+
+    // The environment may use a heap with a wider index type than the WebAssembly address, for
+    // example a 64-bit heap which is bounds checked instead of relying on a 4 GB reservation.
+    // The addresses of a 64-bit memory need a 64-bit heap.
+    let index_ty = builder.func.heaps[heap].index_type;
+    let wasm_ty = builder.func.dfg.value_type(addr);
+    let index = if wasm_ty == index_ty {
+        addr
+    } else if wasm_ty.bits() < index_ty.bits() {
+        builder.ins().uextend(index_ty, addr)
+    } else {
+        return Err(wasm_unsupported!(
+            "{} address of a heap with index type {}",
+            wasm_ty,
+            index_ty
+        ));
+    };
+    let base = builder.ins().heap_addr(addr_ty, heap, index, check_size);
 
     // Native load/store instructions take a signed `Offset32` immediate, so adjust the base
     // pointer if necessary.
     if offset > i32::MAX as u32 {
         // Offset doesn't fit in the load/store instruction.
         let adj = builder.ins().iadd_imm(base, i64::from(i32::MAX) + 1);
-        (adj, (offset - (i32::MAX as u32 + 1)) as i32)
+        Ok((adj, (offset - (i32::MAX as u32 + 1)) as i32))
     } else {
-        (base, offset as i32)
+        Ok((base, offset as i32))
     }
 }
 
+/// Pop the address of a load off the stack and compute the flags, base and offset to load from.
+fn prepare_load<FE: FuncEnvironment + ?Sized>(
+    memarg: &MemoryImmediate,
+    builder: &mut FunctionBuilder,
+    state: &mut FuncTranslationState,
+    environ: &mut FE,
+) -> WasmResult<(MemFlags, Value, Offset32)> {
+    let addr = state.pop1();
+    let heap = state.get_heap(builder.func, memarg.memory, environ)?;
+    let (base, offset) = get_heap_addr(heap, addr, memarg.offset, environ.pointer_type(), builder)?;
+    // Note that we don't set `is_aligned` here, even if the load instruction's
+    // alignment immediate says it's aligned, because WebAssembly's immediate
+    // field is just a hint, while Cranelift's aligned flag needs a guarantee.
+    let flags = MemFlags::new();
+    Ok((flags, base, offset.into()))
+}
+
 /// Translate a load instruction.
 fn translate_load<FE: FuncEnvironment + ?Sized>(
-    offset: u32,
+    memarg: &MemoryImmediate,
     opcode: ir::Opcode,
     result_ty: Type,
     builder: &mut FunctionBuilder,
     state: &mut FuncTranslationState,
     environ: &mut FE,
 ) -> WasmResult<()> {
-    let addr32 = state.pop1();
-    // We don't yet support multiple linear memories.
-    let heap = state.get_heap(builder.func, 0, environ)?;
-    let (base, offset) = get_heap_addr(heap, addr32, offset, environ.pointer_type(), builder);
-    // Note that we don't set `is_aligned` here, even if the load instruction's
-    // alignment immediate says it's aligned, because WebAssembly's immediate
-    // field is just a hint, while Cranelift's aligned flag needs a guarantee.
-    let flags = MemFlags::new();
-    let (load, dfg) = builder
-        .ins()
-        .Load(opcode, result_ty, flags, offset.into(), base);
+    let (flags, base, offset) = prepare_load(memarg, builder, state, environ)?;
+    let (load, dfg) = builder.ins().Load(opcode, result_ty, flags, offset, base);
     state.push1(dfg.first_result(load));
     Ok(())
 }
 
 /// Translate a store instruction.
 fn translate_store<FE: FuncEnvironment + ?Sized>(
-    offset: u32,
+    memarg: &MemoryImmediate,
     opcode: ir::Opcode,
     builder: &mut FunctionBuilder,
     state: &mut FuncTranslationState,
     environ: &mut FE,
 ) -> WasmResult<()> {
-    let (addr32, val) = state.pop2();
+    let (addr, val) = state.pop2();
     let val_ty = builder.func.dfg.value_type(val);
 
-    // We don't yet support multiple linear memories.
-    let heap = state.get_heap(builder.func, 0, environ)?;
-    let (base, offset) = get_heap_addr(heap, addr32, offset, environ.pointer_type(), builder);
+    let heap = state.get_heap(builder.func, memarg.memory, environ)?;
+    let (base, offset) = get_heap_addr(heap, addr, memarg.offset, environ.pointer_type(), builder)?;
     // See the comments in `translate_load` about the flags.
     let flags = MemFlags::new();
     builder
@@ -1630,6 +1896,143 @@ fn translate_store<FE: FuncEnvironment + ?Sized>(
     Ok(())
 }
 
+/// Compute the native address of an atomic heap access of type `access_ty`.
+///
+/// Atomic accesses trap when the WebAssembly address isn't a multiple of the access size. Heap
+/// bases are page aligned, so it's enough to check the alignment of the native address.
+fn translate_atomic_addr<FE: FuncEnvironment + ?Sized>(
+    heap: ir::Heap,
+    addr: ir::Value,
+    offset: u32,
+    access_ty: Type,
+    builder: &mut FunctionBuilder,
+    environ: &mut FE,
+) -> WasmResult<ir::Value> {
+    let (base, offset) = get_heap_addr(heap, addr, offset, environ.pointer_type(), builder)?;
+    let addr = if offset == 0 {
+        base
+    } else {
+        builder.ins().iadd_imm(base, i64::from(offset))
+    };
+    let align_mask = i64::from(access_ty.bytes() - 1);
+    if align_mask != 0 {
+        let misalignment = builder.ins().band_imm(addr, align_mask);
+        builder
+            .ins()
+            .trapnz(misalignment, ir::TrapCode::HeapMisaligned);
+    }
+    Ok(addr)
+}
+
+/// The memory flags of an atomic heap access, whose address has been checked by
+/// `translate_atomic_addr`.
+fn atomic_flags() -> MemFlags {
+    let mut flags = MemFlags::new();
+    flags.set_aligned();
+    flags
+}
+
+/// Zero-extend the result of a narrow atomic access to `result_ty`.
+fn extend_atomic_result(
+    value: ir::Value,
+    result_ty: Type,
+    builder: &mut FunctionBuilder,
+) -> ir::Value {
+    if builder.func.dfg.value_type(value) == result_ty {
+        value
+    } else {
+        builder.ins().uextend(result_ty, value)
+    }
+}
+
+/// Truncate the operand of a narrow atomic access to `access_ty`.
+fn reduce_atomic_operand(
+    value: ir::Value,
+    access_ty: Type,
+    builder: &mut FunctionBuilder,
+) -> ir::Value {
+    if builder.func.dfg.value_type(value) == access_ty {
+        value
+    } else {
+        builder.ins().ireduce(access_ty, value)
+    }
+}
+
+/// Translate an atomic load instruction.
+fn translate_atomic_load<FE: FuncEnvironment + ?Sized>(
+    memarg: &MemoryImmediate,
+    access_ty: Type,
+    result_ty: Type,
+    builder: &mut FunctionBuilder,
+    state: &mut FuncTranslationState,
+    environ: &mut FE,
+) -> WasmResult<()> {
+    let addr = state.pop1();
+    let heap = state.get_heap(builder.func, memarg.memory, environ)?;
+    let addr = translate_atomic_addr(heap, addr, memarg.offset, access_ty, builder, environ)?;
+    let loaded = builder
+        .ins()
+        .atomic_load(access_ty, atomic_flags(), addr, 0);
+    state.push1(extend_atomic_result(loaded, result_ty, builder));
+    Ok(())
+}
+
+/// Translate an atomic store instruction.
+fn translate_atomic_store<FE: FuncEnvironment + ?Sized>(
+    memarg: &MemoryImmediate,
+    access_ty: Type,
+    builder: &mut FunctionBuilder,
+    state: &mut FuncTranslationState,
+    environ: &mut FE,
+) -> WasmResult<()> {
+    let (addr, val) = state.pop2();
+    let heap = state.get_heap(builder.func, memarg.memory, environ)?;
+    let addr = translate_atomic_addr(heap, addr, memarg.offset, access_ty, builder, environ)?;
+    let val = reduce_atomic_operand(val, access_ty, builder);
+    builder.ins().atomic_store(atomic_flags(), val, addr, 0);
+    Ok(())
+}
+
+/// Translate an atomic read-modify-write instruction.
+fn translate_atomic_rmw<FE: FuncEnvironment + ?Sized>(
+    memarg: &MemoryImmediate,
+    op: AtomicRmwOp,
+    access_ty: Type,
+    builder: &mut FunctionBuilder,
+    state: &mut FuncTranslationState,
+    environ: &mut FE,
+) -> WasmResult<()> {
+    let (addr, val) = state.pop2();
+    let result_ty = builder.func.dfg.value_type(val);
+    let heap = state.get_heap(builder.func, memarg.memory, environ)?;
+    let addr = translate_atomic_addr(heap, addr, memarg.offset, access_ty, builder, environ)?;
+    let val = reduce_atomic_operand(val, access_ty, builder);
+    let old = builder.ins().atomic_rmw(atomic_flags(), op, addr, val);
+    state.push1(extend_atomic_result(old, result_ty, builder));
+    Ok(())
+}
+
+/// Translate an atomic compare-and-exchange instruction.
+fn translate_atomic_cas<FE: FuncEnvironment + ?Sized>(
+    memarg: &MemoryImmediate,
+    access_ty: Type,
+    builder: &mut FunctionBuilder,
+    state: &mut FuncTranslationState,
+    environ: &mut FE,
+) -> WasmResult<()> {
+    let (addr, expected, replacement) = state.pop3();
+    let result_ty = builder.func.dfg.value_type(expected);
+    let heap = state.get_heap(builder.func, memarg.memory, environ)?;
+    let addr = translate_atomic_addr(heap, addr, memarg.offset, access_ty, builder, environ)?;
+    let expected = reduce_atomic_operand(expected, access_ty, builder);
+    let replacement = reduce_atomic_operand(replacement, access_ty, builder);
+    let old = builder
+        .ins()
+        .atomic_cas(atomic_flags(), addr, expected, replacement);
+    state.push1(extend_atomic_result(old, result_ty, builder));
+    Ok(())
+}
+
 fn translate_icmp(cc: IntCC, builder: &mut FunctionBuilder, state: &mut FuncTranslationState) {
     let (arg0, arg1) = state.pop2();
     let val = builder.ins().icmp(cc, arg0, arg1);
@@ -1721,9 +2124,10 @@ fn type_of(operator: &Operator) -> Type {
         | Operator::V128AndNot
         | Operator::V128Or
         | Operator::V128Xor
-        | Operator::V128Bitselect => I8X16, // default type representing V128
+        | Operator::V128Bitselect
+        | Operator::V128AnyTrue => I8X16, // default type representing V128
 
-        Operator::V8x16Shuffle { .. }
+        Operator::I8x16Shuffle { .. }
         | Operator::I8x16Splat
         | Operator::I8x16ExtractLaneS { .. }
         | Operator::I8x16ExtractLaneU { .. }
@@ -1739,18 +2143,21 @@ fn type_of(operator: &Operator) -> Type {
         | Operator::I8x16GeS
         | Operator::I8x16GeU
         | Operator::I8x16Neg
-        | Operator::I8x16AnyTrue
         | Operator::I8x16AllTrue
         | Operator::I8x16Shl
         | Operator::I8x16ShrS
         | Operator::I8x16ShrU
         | Operator::I8x16Add
-        | Operator::I8x16AddSaturateS
-        | Operator::I8x16AddSaturateU
+        | Operator::I8x16AddSatS
+        | Operator::I8x16AddSatU
         | Operator::I8x16Sub
-        | Operator::I8x16SubSaturateS
-        | Operator::I8x16SubSaturateU
-        | Operator::I8x16Mul => I8X16,
+        | Operator::I8x16SubSatS
+        | Operator::I8x16SubSatU
+        | Operator::I8x16RoundingAverageU
+        | Operator::I16x8ExtendLowI8x16S
+        | Operator::I16x8ExtendHighI8x16S
+        | Operator::I16x8ExtendLowI8x16U
+        | Operator::I16x8ExtendHighI8x16U => I8X16,
 
         Operator::I16x8Splat
         | Operator::I16x8ExtractLaneS { .. }
@@ -1767,18 +2174,24 @@ fn type_of(operator: &Operator) -> Type {
         | Operator::I16x8GeS
         | Operator::I16x8GeU
         | Operator::I16x8Neg
-        | Operator::I16x8AnyTrue
         | Operator::I16x8AllTrue
         | Operator::I16x8Shl
         | Operator::I16x8ShrS
         | Operator::I16x8ShrU
         | Operator::I16x8Add
-        | Operator::I16x8AddSaturateS
-        | Operator::I16x8AddSaturateU
+        | Operator::I16x8AddSatS
+        | Operator::I16x8AddSatU
         | Operator::I16x8Sub
-        | Operator::I16x8SubSaturateS
-        | Operator::I16x8SubSaturateU
-        | Operator::I16x8Mul => I16X8,
+        | Operator::I16x8SubSatS
+        | Operator::I16x8SubSatU
+        | Operator::I16x8Mul
+        | Operator::I16x8RoundingAverageU
+        | Operator::I8x16NarrowI16x8S
+        | Operator::I8x16NarrowI16x8U
+        | Operator::I32x4ExtendLowI16x8S
+        | Operator::I32x4ExtendHighI16x8S
+        | Operator::I32x4ExtendLowI16x8U
+        | Operator::I32x4ExtendHighI16x8U => I16X8,
 
         Operator::I32x4Splat
         | Operator::I32x4ExtractLane { .. }
@@ -1794,7 +2207,6 @@ fn type_of(operator: &Operator) -> Type {
         | Operator::I32x4GeS
         | Operator::I32x4GeU
         | Operator::I32x4Neg
-        | Operator::I32x4AnyTrue
         | Operator::I32x4AllTrue
         | Operator::I32x4Shl
         | Operator::I32x4ShrS
@@ -1803,21 +2215,21 @@ fn type_of(operator: &Operator) -> Type {
         | Operator::I32x4Sub
         | Operator::I32x4Mul
         | Operator::F32x4ConvertI32x4S
-        | Operator::F32x4ConvertI32x4U => I32X4,
+        | Operator::F32x4ConvertI32x4U
+        | Operator::I16x8NarrowI32x4S
+        | Operator::I16x8NarrowI32x4U => I32X4,
 
         Operator::I64x2Splat
         | Operator::I64x2ExtractLane { .. }
         | Operator::I64x2ReplaceLane { .. }
         | Operator::I64x2Neg
-        | Operator::I64x2AnyTrue
         | Operator::I64x2AllTrue
         | Operator::I64x2Shl
         | Operator::I64x2ShrS
         | Operator::I64x2ShrU
         | Operator::I64x2Add
         | Operator::I64x2Sub
-        | Operator::F64x2ConvertI64x2S
-        | Operator::F64x2ConvertI64x2U => I64X2,
+        | Operator::I64x2Mul => I64X2,
 
         Operator::F32x4Splat
         | Operator::F32x4ExtractLane { .. }
@@ -1857,9 +2269,7 @@ fn type_of(operator: &Operator) -> Type {
         | Operator::F64x2Mul
         | Operator::F64x2Div
         | Operator::F64x2Min
-        | Operator::F64x2Max
-        | Operator::I64x2TruncSatF64x2S
-        | Operator::I64x2TruncSatF64x2U => F64X2,
+        | Operator::F64x2Max => F64X2,
 
         _ => unimplemented!(
             "Currently only SIMD instructions are mapped to their return type; the \
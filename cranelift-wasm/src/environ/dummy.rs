@@ -21,6 +21,7 @@ use cranelift_codegen::ir::types::*;
 use cranelift_codegen::ir::{self, InstBuilder};
 use cranelift_codegen::isa::TargetFrontendConfig;
 use cranelift_entity::{EntityRef, PrimaryMap, SecondaryMap};
+use cranelift_frontend::FunctionBuilder;
 use std::boxed::Box;
 use std::string::String;
 use std::vec::Vec;
@@ -192,6 +193,55 @@ impl<'dummy_environment> DummyFuncEnvironment<'dummy_environment> {
         ));
         sig
     }
+
+    // Build the argument list of an indirect call to the function at index `callee` of the
+    // table: the function pointer, `call_args` and the vmctx parameter.
+    fn indirect_call_args(
+        &self,
+        pos: &mut FuncCursor,
+        callee: ir::Value,
+        call_args: &[ir::Value],
+    ) -> ir::ValueList {
+        // The `callee` value is an index into a table of function pointers.
+        // Apparently, that table is stored at absolute address 0 in this dummy environment.
+        // TODO: Generate bounds checking code.
+        let ptr = self.pointer_type();
+        let callee_offset = if ptr == I32 {
+            pos.ins().imul_imm(callee, 4)
+        } else {
+            let ext = pos.ins().uextend(I64, callee);
+            pos.ins().imul_imm(ext, 4)
+        };
+        let mflags = ir::MemFlags::trusted();
+        let func_ptr = pos.ins().load(ptr, mflags, callee_offset, 0);
+
+        let mut args = direct_call_args(pos, call_args);
+        args.insert(0, func_ptr, &mut pos.func.dfg.value_lists);
+        args
+    }
+
+    // The type of the addresses of memory `index`, which its heap is indexed with.
+    fn memory_index_type(&self, index: MemoryIndex) -> ir::Type {
+        if self.mod_info.memories[index].entity.memory64 {
+            I64
+        } else {
+            I32
+        }
+    }
+}
+
+// Build the argument list of a direct call: `call_args` followed by the current function's vmctx
+// parameter, which is passed on to the callee.
+fn direct_call_args(pos: &mut FuncCursor, call_args: &[ir::Value]) -> ir::ValueList {
+    let vmctx = pos
+        .func
+        .special_param(ir::ArgumentPurpose::VMContext)
+        .expect("Missing vmctx parameter");
+
+    let mut args = ir::ValueList::default();
+    args.extend(call_args.iter().cloned(), &mut pos.func.dfg.value_lists);
+    args.push(vmctx, &mut pos.func.dfg.value_lists);
+    args
 }
 
 impl<'dummy_environment> TargetEnvironment for DummyFuncEnvironment<'dummy_environment> {
@@ -224,12 +274,13 @@ impl<'dummy_environment> FuncEnvironment for DummyFuncEnvironment<'dummy_environ
         })
     }
 
-    fn make_heap(&mut self, func: &mut ir::Function, _index: MemoryIndex) -> WasmResult<ir::Heap> {
-        // Create a static heap whose base address is stored at `vmctx+0`.
+    fn make_heap(&mut self, func: &mut ir::Function, index: MemoryIndex) -> WasmResult<ir::Heap> {
+        // Create a static heap whose base address is stored at `vmctx+index*pointer_bytes`.
+        let offset = i32::try_from(index.index() * usize::from(self.pointer_bytes())).unwrap();
         let addr = func.create_global_value(ir::GlobalValueData::VMContext);
         let gv = func.create_global_value(ir::GlobalValueData::Load {
             base: addr,
-            offset: Offset32::new(0),
+            offset: Offset32::new(offset),
             global_type: self.pointer_type(),
             readonly: true,
         });
@@ -241,7 +292,7 @@ impl<'dummy_environment> FuncEnvironment for DummyFuncEnvironment<'dummy_environ
             style: ir::HeapStyle::Static {
                 bound: 0x1_0000_0000.into(),
             },
-            index_type: I32,
+            index_type: self.memory_index_type(index),
         }))
     }
 
@@ -307,32 +358,7 @@ impl<'dummy_environment> FuncEnvironment for DummyFuncEnvironment<'dummy_environ
         callee: ir::Value,
         call_args: &[ir::Value],
     ) -> WasmResult<ir::Inst> {
-        // Pass the current function's vmctx parameter on to the callee.
-        let vmctx = pos
-            .func
-            .special_param(ir::ArgumentPurpose::VMContext)
-            .expect("Missing vmctx parameter");
-
-        // The `callee` value is an index into a table of function pointers.
-        // Apparently, that table is stored at absolute address 0 in this dummy environment.
-        // TODO: Generate bounds checking code.
-        let ptr = self.pointer_type();
-        let callee_offset = if ptr == I32 {
-            pos.ins().imul_imm(callee, 4)
-        } else {
-            let ext = pos.ins().uextend(I64, callee);
-            pos.ins().imul_imm(ext, 4)
-        };
-        let mflags = ir::MemFlags::trusted();
-        let func_ptr = pos.ins().load(ptr, mflags, callee_offset, 0);
-
-        // Build a value list for the indirect call instruction containing the callee, call_args,
-        // and the vmctx parameter.
-        let mut args = ir::ValueList::default();
-        args.push(func_ptr, &mut pos.func.dfg.value_lists);
-        args.extend(call_args.iter().cloned(), &mut pos.func.dfg.value_lists);
-        args.push(vmctx, &mut pos.func.dfg.value_lists);
-
+        let args = self.indirect_call_args(&mut pos, callee, call_args);
         Ok(pos
             .ins()
             .CallIndirect(ir::Opcode::CallIndirect, INVALID, sig_ref, args)
@@ -346,45 +372,67 @@ impl<'dummy_environment> FuncEnvironment for DummyFuncEnvironment<'dummy_environ
         callee: ir::FuncRef,
         call_args: &[ir::Value],
     ) -> WasmResult<ir::Inst> {
-        // Pass the current function's vmctx parameter on to the callee.
-        let vmctx = pos
-            .func
-            .special_param(ir::ArgumentPurpose::VMContext)
-            .expect("Missing vmctx parameter");
-
-        // Build a value list for the call instruction containing the call_args and the vmctx
-        // parameter.
-        let mut args = ir::ValueList::default();
-        args.extend(call_args.iter().cloned(), &mut pos.func.dfg.value_lists);
-        args.push(vmctx, &mut pos.func.dfg.value_lists);
-
+        let args = direct_call_args(&mut pos, call_args);
         Ok(pos.ins().Call(ir::Opcode::Call, INVALID, callee, args).0)
     }
 
+    fn translate_return_call_indirect(
+        &mut self,
+        builder: &mut FunctionBuilder,
+        _table_index: TableIndex,
+        _table: ir::Table,
+        _sig_index: SignatureIndex,
+        sig_ref: ir::SigRef,
+        callee: ir::Value,
+        call_args: &[ir::Value],
+    ) -> WasmResult<ir::Inst> {
+        let args = self.indirect_call_args(&mut builder.cursor(), callee, call_args);
+        Ok(builder
+            .ins()
+            .CallIndirect(ir::Opcode::ReturnCallIndirect, INVALID, sig_ref, args)
+            .0)
+    }
+
+    fn translate_return_call(
+        &mut self,
+        builder: &mut FunctionBuilder,
+        _callee_index: FuncIndex,
+        callee: ir::FuncRef,
+        call_args: &[ir::Value],
+    ) -> WasmResult<ir::Inst> {
+        let args = direct_call_args(&mut builder.cursor(), call_args);
+        Ok(builder
+            .ins()
+            .Call(ir::Opcode::ReturnCall, INVALID, callee, args)
+            .0)
+    }
+
     fn translate_memory_grow(
         &mut self,
         mut pos: FuncCursor,
-        _index: MemoryIndex,
+        index: MemoryIndex,
         _heap: ir::Heap,
         _val: ir::Value,
     ) -> WasmResult<ir::Value> {
-        Ok(pos.ins().iconst(I32, -1))
+        Ok(pos.ins().iconst(self.memory_index_type(index), -1))
     }
 
     fn translate_memory_size(
         &mut self,
         mut pos: FuncCursor,
-        _index: MemoryIndex,
+        index: MemoryIndex,
         _heap: ir::Heap,
     ) -> WasmResult<ir::Value> {
-        Ok(pos.ins().iconst(I32, -1))
+        Ok(pos.ins().iconst(self.memory_index_type(index), -1))
     }
 
     fn translate_memory_copy(
         &mut self,
         _pos: FuncCursor,
-        _index: MemoryIndex,
-        _heap: ir::Heap,
+        _dst_index: MemoryIndex,
+        _dst_heap: ir::Heap,
+        _src_index: MemoryIndex,
+        _src_heap: ir::Heap,
         _dst: ir::Value,
         _src: ir::Value,
         _len: ir::Value,
@@ -421,6 +469,61 @@ impl<'dummy_environment> FuncEnvironment for DummyFuncEnvironment<'dummy_environ
         Ok(())
     }
 
+    fn translate_atomic_wait(
+        &mut self,
+        mut pos: FuncCursor,
+        _index: MemoryIndex,
+        _heap: ir::Heap,
+        _addr: ir::Value,
+        _expected: ir::Value,
+        _timeout: ir::Value,
+    ) -> WasmResult<ir::Value> {
+        Ok(pos.ins().iconst(I32, -1))
+    }
+
+    fn translate_atomic_notify(
+        &mut self,
+        mut pos: FuncCursor,
+        _index: MemoryIndex,
+        _heap: ir::Heap,
+        _addr: ir::Value,
+        _count: ir::Value,
+    ) -> WasmResult<ir::Value> {
+        Ok(pos.ins().iconst(I32, 0))
+    }
+
+    fn translate_table_grow(
+        &mut self,
+        mut pos: FuncCursor,
+        _index: TableIndex,
+        _table: ir::Table,
+        _delta: ir::Value,
+        _init_value: ir::Value,
+    ) -> WasmResult<ir::Value> {
+        Ok(pos.ins().iconst(I32, -1))
+    }
+
+    fn translate_table_get(
+        &mut self,
+        mut pos: FuncCursor,
+        _table_index: TableIndex,
+        _table: ir::Table,
+        _index: ir::Value,
+    ) -> WasmResult<ir::Value> {
+        Ok(pos.ins().null(self.reference_type()))
+    }
+
+    fn translate_table_set(
+        &mut self,
+        _pos: FuncCursor,
+        _table_index: TableIndex,
+        _table: ir::Table,
+        _value: ir::Value,
+        _index: ir::Value,
+    ) -> WasmResult<()> {
+        Ok(())
+    }
+
     fn translate_table_size(
         &mut self,
         mut pos: FuncCursor,
@@ -444,6 +547,18 @@ impl<'dummy_environment> FuncEnvironment for DummyFuncEnvironment<'dummy_environ
         Ok(())
     }
 
+    fn translate_table_fill(
+        &mut self,
+        _pos: FuncCursor,
+        _table_index: TableIndex,
+        _table: ir::Table,
+        _dst: ir::Value,
+        _val: ir::Value,
+        _len: ir::Value,
+    ) -> WasmResult<()> {
+        Ok(())
+    }
+
     fn translate_table_init(
         &mut self,
         _pos: FuncCursor,
@@ -460,6 +575,14 @@ impl<'dummy_environment> FuncEnvironment for DummyFuncEnvironment<'dummy_environ
     fn translate_elem_drop(&mut self, _pos: FuncCursor, _seg_index: u32) -> WasmResult<()> {
         Ok(())
     }
+
+    fn translate_ref_func(
+        &mut self,
+        mut pos: FuncCursor,
+        _func_index: FuncIndex,
+    ) -> WasmResult<ir::Value> {
+        Ok(pos.ins().null(self.reference_type()))
+    }
 }
 
 impl TargetEnvironment for DummyEnvironment {
@@ -17,6 +17,7 @@ use cranelift_codegen::ir::{self, InstBuilder};
 use cranelift_codegen::isa::TargetFrontendConfig;
 use cranelift_frontend::FunctionBuilder;
 use std::boxed::Box;
+use std::string::String;
 use thiserror::Error;
 use wasmparser::BinaryReaderError;
 use wasmparser::Operator;
@@ -51,7 +52,7 @@ pub enum WasmError {
     #[error("Invalid input WebAssembly code at offset {offset}: {message}")]
     InvalidWebAssembly {
         /// A string describing the validation error.
-        message: &'static str,
+        message: String,
         /// The bytecode offset where the error occurred.
         offset: usize,
     },
@@ -86,8 +87,10 @@ macro_rules! wasm_unsupported {
 impl From<BinaryReaderError> for WasmError {
     /// Convert from a `BinaryReaderError` to a `WasmError`.
     fn from(e: BinaryReaderError) -> Self {
-        let BinaryReaderError { message, offset } = e;
-        Self::InvalidWebAssembly { message, offset }
+        Self::InvalidWebAssembly {
+            message: e.message().into(),
+            offset: e.offset(),
+        }
     }
 }
 
@@ -165,7 +168,10 @@ pub trait FuncEnvironment: TargetEnvironment {
     /// Set up the necessary preamble definitions in `func` to access the linear memory identified
     /// by `index`.
     ///
-    /// The index space covers both imported and locally declared memories.
+    /// The index space covers both imported and locally declared memories. Each memory should get
+    /// its own `ir::Heap`. The heap's index type may be wider than the 32-bit WebAssembly address,
+    /// in which case addresses are zero-extended before the `heap_addr` bounds check. The heap of a
+    /// 64-bit memory (see `Memory::memory64`) must be indexed with an `I64`.
     fn make_heap(&mut self, func: &mut ir::Function, index: MemoryIndex) -> WasmResult<ir::Heap>;
 
     /// Set up the necessary preamble definitions in `func` to access the table identified
@@ -244,6 +250,41 @@ pub trait FuncEnvironment: TargetEnvironment {
         Ok(pos.ins().call(callee, call_args))
     }
 
+    /// Translate a `return_call_indirect` WebAssembly instruction.
+    ///
+    /// This is like `translate_call_indirect`, except that the call must be a
+    /// `return_call_indirect` instruction, which ends the current block. It's inserted with
+    /// `builder` so that the builder knows that. The default implementation reports it as
+    /// unsupported.
+    // Takes the same arguments as `translate_call_indirect`, except for the builder.
+    #[allow(clippy::too_many_arguments)]
+    fn translate_return_call_indirect(
+        &mut self,
+        _builder: &mut FunctionBuilder,
+        _table_index: TableIndex,
+        _table: ir::Table,
+        _sig_index: SignatureIndex,
+        _sig_ref: ir::SigRef,
+        _callee: ir::Value,
+        _call_args: &[ir::Value],
+    ) -> WasmResult<ir::Inst> {
+        Err(wasm_unsupported!("return_call_indirect"))
+    }
+
+    /// Translate a `return_call` WebAssembly instruction.
+    ///
+    /// This is like `translate_call`, except that the call must be a `return_call` instruction,
+    /// which ends the current block. It's inserted with `builder` so that the builder knows that.
+    fn translate_return_call(
+        &mut self,
+        builder: &mut FunctionBuilder,
+        _callee_index: FuncIndex,
+        callee: ir::FuncRef,
+        call_args: &[ir::Value],
+    ) -> WasmResult<ir::Inst> {
+        Ok(builder.ins().return_call(callee, call_args))
+    }
+
     /// Translate a `memory.grow` WebAssembly instruction.
     ///
     /// The `index` provided identifies the linear memory to grow, and `heap` is the heap reference
@@ -251,7 +292,7 @@ pub trait FuncEnvironment: TargetEnvironment {
     ///
     /// The `val` value is the requested memory size in pages.
     ///
-    /// Returns the old size (in pages) of the memory.
+    /// Returns the old size (in pages) of the memory. Both are `i64`s for 64-bit memories.
     fn translate_memory_grow(
         &mut self,
         pos: FuncCursor,
@@ -265,7 +306,7 @@ pub trait FuncEnvironment: TargetEnvironment {
     /// The `index` provided identifies the linear memory to query, and `heap` is the heap reference
     /// returned by `make_heap` for the same index.
     ///
-    /// Returns the size in pages of the memory.
+    /// Returns the size in pages of the memory, as an `i64` for 64-bit memories.
     fn translate_memory_size(
         &mut self,
         pos: FuncCursor,
@@ -275,13 +316,17 @@ pub trait FuncEnvironment: TargetEnvironment {
 
     /// Translate a `memory.copy` WebAssembly instruction.
     ///
-    /// The `index` provided identifies the linear memory to query, and `heap` is the heap reference
-    /// returned by `make_heap` for the same index.
+    /// Copies `len` bytes from `src` in the source memory to `dst` in the destination memory. The
+    /// two memories may be different. Each `*_heap` is the heap reference returned by `make_heap`
+    /// for the corresponding `*_index`.
+    #[allow(clippy::too_many_arguments)]
     fn translate_memory_copy(
         &mut self,
         pos: FuncCursor,
-        index: MemoryIndex,
-        heap: ir::Heap,
+        dst_index: MemoryIndex,
+        dst_heap: ir::Heap,
+        src_index: MemoryIndex,
+        src_heap: ir::Heap,
         dst: ir::Value,
         src: ir::Value,
         len: ir::Value,
@@ -321,6 +366,97 @@ pub trait FuncEnvironment: TargetEnvironment {
     /// Translate a `data.drop` WebAssembly instruction.
     fn translate_data_drop(&mut self, pos: FuncCursor, seg_index: u32) -> WasmResult<()>;
 
+    /// Translate an `i32.atomic.wait` or `i64.atomic.wait` WebAssembly instruction.
+    ///
+    /// The `index` provided identifies the linear memory containing the waited on value, and
+    /// `heap` is the heap reference returned by `make_heap` for the same index. `addr` is the
+    /// native address of the value, which has already been checked to be in bounds and aligned.
+    ///
+    /// The type of `expected` is the type of the waited on value, and `timeout` is an `i64`
+    /// relative timeout in nanoseconds. A negative timeout waits forever.
+    ///
+    /// Returns an `i32` which is 0 if the thread was woken up, 1 if the value in memory wasn't
+    /// `expected`, and 2 if the wait timed out.
+    #[allow(clippy::too_many_arguments)]
+    fn translate_atomic_wait(
+        &mut self,
+        _pos: FuncCursor,
+        _index: MemoryIndex,
+        _heap: ir::Heap,
+        _addr: ir::Value,
+        _expected: ir::Value,
+        _timeout: ir::Value,
+    ) -> WasmResult<ir::Value> {
+        // By default, threads aren't supported.
+        Err(wasm_unsupported!("atomic.wait"))
+    }
+
+    /// Translate an `atomic.notify` WebAssembly instruction.
+    ///
+    /// The `index`, `heap` and `addr` arguments are the same as for `translate_atomic_wait`.
+    /// `count` is the maximum number of waiting threads to wake up.
+    ///
+    /// Returns the number of threads that were woken up, as an `i32`.
+    fn translate_atomic_notify(
+        &mut self,
+        _pos: FuncCursor,
+        _index: MemoryIndex,
+        _heap: ir::Heap,
+        _addr: ir::Value,
+        _count: ir::Value,
+    ) -> WasmResult<ir::Value> {
+        // By default, threads aren't supported.
+        Err(wasm_unsupported!("atomic.notify"))
+    }
+
+    /// Translate a `table.grow` WebAssembly instruction.
+    ///
+    /// The `index` provided identifies the table to grow, and `table` is the table reference
+    /// returned by `make_table` for the same index. The table grows by `delta` elements, which
+    /// are initialized with `init_value`.
+    ///
+    /// Returns the old size of the table, or -1 if it couldn't grow.
+    fn translate_table_grow(
+        &mut self,
+        _pos: FuncCursor,
+        _index: TableIndex,
+        _table: ir::Table,
+        _delta: ir::Value,
+        _init_value: ir::Value,
+    ) -> WasmResult<ir::Value> {
+        // By default, reference types aren't supported.
+        Err(wasm_unsupported!("table.grow"))
+    }
+
+    /// Translate a `table.get` WebAssembly instruction.
+    ///
+    /// Returns the reference stored at `index` in `table`, as a value of the `reference_type()`.
+    fn translate_table_get(
+        &mut self,
+        _pos: FuncCursor,
+        _table_index: TableIndex,
+        _table: ir::Table,
+        _index: ir::Value,
+    ) -> WasmResult<ir::Value> {
+        // By default, reference types aren't supported.
+        Err(wasm_unsupported!("table.get"))
+    }
+
+    /// Translate a `table.set` WebAssembly instruction.
+    ///
+    /// Stores the reference `value` at `index` in `table`.
+    fn translate_table_set(
+        &mut self,
+        _pos: FuncCursor,
+        _table_index: TableIndex,
+        _table: ir::Table,
+        _value: ir::Value,
+        _index: ir::Value,
+    ) -> WasmResult<()> {
+        // By default, reference types aren't supported.
+        Err(wasm_unsupported!("table.set"))
+    }
+
     /// Translate a `table.size` WebAssembly instruction.
     fn translate_table_size(
         &mut self,
@@ -343,6 +479,22 @@ pub trait FuncEnvironment: TargetEnvironment {
         len: ir::Value,
     ) -> WasmResult<()>;
 
+    /// Translate a `table.fill` WebAssembly instruction.
+    ///
+    /// Stores the reference `val` in the `len` elements of `table` starting at `dst`.
+    fn translate_table_fill(
+        &mut self,
+        _pos: FuncCursor,
+        _table_index: TableIndex,
+        _table: ir::Table,
+        _dst: ir::Value,
+        _val: ir::Value,
+        _len: ir::Value,
+    ) -> WasmResult<()> {
+        // By default, reference types aren't supported.
+        Err(wasm_unsupported!("table.fill"))
+    }
+
     /// Translate a `table.init` WebAssembly instruction.
     #[allow(clippy::too_many_arguments)]
     fn translate_table_init(
@@ -359,6 +511,18 @@ pub trait FuncEnvironment: TargetEnvironment {
     /// Translate a `elem.drop` WebAssembly instruction.
     fn translate_elem_drop(&mut self, pos: FuncCursor, seg_index: u32) -> WasmResult<()>;
 
+    /// Translate a `ref.func` WebAssembly instruction.
+    ///
+    /// Returns a reference to the function `func_index`, as a value of the `reference_type()`.
+    fn translate_ref_func(
+        &mut self,
+        _pos: FuncCursor,
+        _func_index: FuncIndex,
+    ) -> WasmResult<ir::Value> {
+        // By default, reference types aren't supported.
+        Err(wasm_unsupported!("ref.func"))
+    }
+
     /// Emit code at the beginning of every wasm loop.
     ///
     /// This can be used to insert explicit interrupt or safepoint checking at
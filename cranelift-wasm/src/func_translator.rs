@@ -5,7 +5,7 @@
 //! WebAssembly module and the runtime environment.
 
 use crate::code_translator::{bitcast_arguments, translate_operator};
-use crate::environ::{FuncEnvironment, ReturnMode, WasmResult};
+use crate::environ::{FuncEnvironment, ReturnMode, WasmError, WasmResult};
 use crate::state::{FuncTranslationState, ModuleTranslationState};
 use crate::translation_utils::get_vmctx_value_label;
 use crate::wasm_unsupported;
@@ -16,6 +16,9 @@ use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext, Variable};
 use log::info;
 use wasmparser::{self, BinaryReader};
 
+/// The maximum number of locals a function may declare, as enforced by wasmparser's validator.
+const MAX_FUNCTION_LOCALS: u32 = 50_000;
+
 /// WebAssembly to Cranelift IR function translator.
 ///
 /// A `FuncTranslator` is used to translate a binary WebAssembly function into Cranelift IR guided
@@ -163,12 +166,17 @@ fn parse_local_decls<FE: FuncEnvironment + ?Sized>(
     environ: &mut FE,
 ) -> WasmResult<()> {
     let mut next_local = num_params;
-    let local_count = reader.read_local_count()?;
+    let local_count = reader.read_var_u32()?;
 
-    let mut locals_total = 0;
+    let mut locals_total = 0u32;
     for _ in 0..local_count {
         builder.set_srcloc(cur_srcloc(reader));
-        let (count, ty) = reader.read_local_decl(&mut locals_total)?;
+        let count = reader.read_var_u32()?;
+        locals_total = match locals_total.checked_add(count) {
+            Some(total) if total <= MAX_FUNCTION_LOCALS => total,
+            _ => return Err(WasmError::ImplLimitExceeded),
+        };
+        let ty = reader.read_type()?;
         declare_locals(builder, count, ty, &mut next_local, environ)?;
     }
 
@@ -196,8 +204,7 @@ fn declare_locals<FE: FuncEnvironment + ?Sized>(
             let constant_handle = builder.func.dfg.constants.insert([0; 16].to_vec().into());
             builder.ins().vconst(ir::types::I8X16, constant_handle)
         }
-        AnyRef => builder.ins().null(environ.reference_type()),
-        AnyFunc => builder.ins().null(environ.reference_type()),
+        ExternRef | FuncRef => builder.ins().null(environ.reference_type()),
         ty => return Err(wasm_unsupported!("unsupported local type {:?}", ty)),
     };
 
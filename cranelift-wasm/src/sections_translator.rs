@@ -21,11 +21,11 @@ use cranelift_entity::packed_option::ReservedValue;
 use cranelift_entity::EntityRef;
 use std::vec::Vec;
 use wasmparser::{
-    self, CodeSectionReader, Data, DataKind, DataSectionReader, Element, ElementItem, ElementKind,
-    ElementSectionReader, Export, ExportSectionReader, ExternalKind, FuncType,
+    self, Data, DataKind, DataSectionReader, Element, ElementItem, ElementKind,
+    ElementSectionReader, Export, ExportSectionReader, ExternalKind, FuncType, FunctionBody,
     FunctionSectionReader, GlobalSectionReader, GlobalType, ImportSectionEntryType,
     ImportSectionReader, MemorySectionReader, MemoryType, NameSectionReader, Naming, NamingReader,
-    Operator, TableSectionReader, Type, TypeSectionReader,
+    Operator, TableSectionReader, Type, TypeDef, TypeSectionReader,
 };
 
 /// Parses the Type section of the wasm module.
@@ -40,11 +40,7 @@ pub fn parse_type_section(
 
     for entry in types {
         match entry? {
-            FuncType {
-                form: wasmparser::Type::Func,
-                params,
-                returns,
-            } => {
+            TypeDef::Func(FuncType { params, returns }) => {
                 let mut sig =
                     Signature::new(ModuleEnvironment::target_config(environ).default_call_conv);
                 sig.params.extend(params.iter().map(|ty| {
@@ -81,7 +77,15 @@ pub fn parse_import_section<'data>(
     for entry in imports {
         let import = entry?;
         let module_name = import.module;
-        let field_name = import.field;
+        let field_name = match import.field {
+            Some(field_name) => field_name,
+            None => {
+                return Err(wasm_unsupported!(
+                    "module linking imports of {:?} aren't supported",
+                    module_name
+                ))
+            }
+        };
 
         match import.ty {
             ImportSectionEntryType::Function(sig) => {
@@ -91,19 +95,8 @@ pub fn parse_import_section<'data>(
                     field_name,
                 )?;
             }
-            ImportSectionEntryType::Memory(MemoryType {
-                limits: ref memlimits,
-                shared,
-            }) => {
-                environ.declare_memory_import(
-                    Memory {
-                        minimum: memlimits.initial,
-                        maximum: memlimits.maximum,
-                        shared,
-                    },
-                    module_name,
-                    field_name,
-                )?;
+            ImportSectionEntryType::Memory(ty) => {
+                environ.declare_memory_import(memory(ty)?, module_name, field_name)?;
             }
             ImportSectionEntryType::Global(ref ty) => {
                 environ.declare_global_import(
@@ -130,6 +123,16 @@ pub fn parse_import_section<'data>(
                     field_name,
                 )?;
             }
+            ImportSectionEntryType::Event(_)
+            | ImportSectionEntryType::Module(_)
+            | ImportSectionEntryType::Instance(_) => {
+                return Err(wasm_unsupported!(
+                    "unsupported import of {}::{}: {:?}",
+                    module_name,
+                    field_name,
+                    import.ty
+                ))
+            }
         }
     }
 
@@ -188,17 +191,34 @@ pub fn parse_memory_section(
     environ.reserve_memories(memories.get_count())?;
 
     for entry in memories {
-        let memory = entry?;
-        environ.declare_memory(Memory {
-            minimum: memory.limits.initial,
-            maximum: memory.limits.maximum,
-            shared: memory.shared,
-        })?;
+        environ.declare_memory(memory(entry?)?)?;
     }
 
     Ok(())
 }
 
+/// Converts a wasmparser memory type.
+fn memory(ty: MemoryType) -> WasmResult<Memory> {
+    match ty {
+        MemoryType::M32 { limits, shared } => Ok(Memory {
+            minimum: limits.initial,
+            maximum: limits.maximum,
+            shared,
+            memory64: false,
+        }),
+        MemoryType::M64 { limits, shared } => {
+            // Page counts are kept as `u32`, which covers 256 TiB of 64 KiB pages.
+            let pages = |count| u32::try_from(count).map_err(|_| WasmError::ImplLimitExceeded);
+            Ok(Memory {
+                minimum: pages(limits.initial)?,
+                maximum: limits.maximum.map(pages).transpose()?,
+                shared,
+                memory64: true,
+            })
+        }
+    }
+}
+
 /// Parses the Global section of the wasm module.
 pub fn parse_global_section(
     globals: GlobalSectionReader,
@@ -223,7 +243,7 @@ pub fn parse_global_section(
             Operator::V128Const { value } => {
                 GlobalInit::V128Const(V128Imm::from(value.bytes().to_vec().as_slice()))
             }
-            Operator::RefNull => GlobalInit::RefNullConst,
+            Operator::RefNull { .. } => GlobalInit::RefNullConst,
             Operator::GlobalGet { global_index } => {
                 GlobalInit::GetGlobal(GlobalIndex::from_u32(global_index))
             }
@@ -272,6 +292,16 @@ pub fn parse_export_section<'data>(
             ExternalKind::Global => {
                 environ.declare_global_export(GlobalIndex::new(index), field)?
             }
+            ExternalKind::Event
+            | ExternalKind::Type
+            | ExternalKind::Module
+            | ExternalKind::Instance => {
+                return Err(wasm_unsupported!(
+                    "unsupported export of {}: {:?}",
+                    field,
+                    kind
+                ))
+            }
         }
     }
 
@@ -294,7 +324,7 @@ pub fn parse_element_section<'data>(
 
     for entry in elements {
         let Element { kind, items, ty } = entry?;
-        if ty != Type::AnyFunc {
+        if ty != Type::FuncRef {
             return Err(wasm_unsupported!(
                 "unsupported table element type: {:?}",
                 ty
@@ -322,7 +352,7 @@ pub fn parse_element_section<'data>(
             let mut elems = Vec::with_capacity(usize::try_from(items_reader.get_count()).unwrap());
             for item in items_reader {
                 let elem = match item? {
-                    ElementItem::Null => FuncIndex::reserved_value(),
+                    ElementItem::Null(_) => FuncIndex::reserved_value(),
                     ElementItem::Func(index) => FuncIndex::from_u32(index),
                 };
                 elems.push(elem);
@@ -340,18 +370,16 @@ pub fn parse_element_section<'data>(
     Ok(())
 }
 
-/// Parses the Code section of the wasm module.
-pub fn parse_code_section<'data>(
-    code: CodeSectionReader<'data>,
+/// Parses a function body of the Code section of the wasm module.
+pub fn parse_code_section_entry<'data>(
+    body: FunctionBody<'data>,
     module_translation_state: &ModuleTranslationState,
     environ: &mut dyn ModuleEnvironment<'data>,
 ) -> WasmResult<()> {
-    for body in code {
-        let mut reader = body?.get_binary_reader();
-        let size = reader.bytes_remaining();
-        let offset = reader.original_position();
-        environ.define_function_body(module_translation_state, reader.read_bytes(size)?, offset)?;
-    }
+    let mut reader = body.get_binary_reader();
+    let size = reader.bytes_remaining();
+    let offset = reader.original_position();
+    environ.define_function_body(module_translation_state, reader.read_bytes(size)?, offset)?;
     Ok(())
 }
 
@@ -418,7 +446,9 @@ pub fn parse_name_section<'data>(
                 }
                 return Ok(());
             }
-            wasmparser::Name::Local(_) | wasmparser::Name::Module(_) => {}
+            wasmparser::Name::Local(_)
+            | wasmparser::Name::Module(_)
+            | wasmparser::Name::Unknown { .. } => {}
         };
     }
     Ok(())
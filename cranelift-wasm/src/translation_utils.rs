@@ -118,6 +118,8 @@ pub struct Memory {
     pub maximum: Option<u32>,
     /// Whether the memory may be shared between multiple threads.
     pub shared: bool,
+    /// Whether the memory is indexed with 64-bit addresses rather than 32-bit ones.
+    pub memory64: bool,
 }
 
 /// Helper function translating wasmparser types to Cranelift types when possible.
@@ -131,7 +133,7 @@ pub fn type_to_type<PE: TargetEnvironment + ?Sized>(
         wasmparser::Type::F32 => Ok(ir::types::F32),
         wasmparser::Type::F64 => Ok(ir::types::F64),
         wasmparser::Type::V128 => Ok(ir::types::I8X16),
-        wasmparser::Type::AnyRef | wasmparser::Type::AnyFunc => Ok(environ.reference_type()),
+        wasmparser::Type::ExternRef | wasmparser::Type::FuncRef => Ok(environ.reference_type()),
         ty => Err(wasm_unsupported!("type_to_type: wasm type {:?}", ty)),
     }
 }
@@ -148,8 +150,8 @@ pub fn tabletype_to_type<PE: TargetEnvironment + ?Sized>(
         wasmparser::Type::F32 => Ok(Some(ir::types::F32)),
         wasmparser::Type::F64 => Ok(Some(ir::types::F64)),
         wasmparser::Type::V128 => Ok(Some(ir::types::I8X16)),
-        wasmparser::Type::AnyRef => Ok(Some(environ.reference_type())),
-        wasmparser::Type::AnyFunc => Ok(None),
+        wasmparser::Type::ExternRef => Ok(Some(environ.reference_type())),
+        wasmparser::Type::FuncRef => Ok(None),
         ty => Err(wasm_unsupported!(
             "tabletype_to_type: table wasm type {:?}",
             ty
@@ -169,8 +171,8 @@ pub fn blocktype_params_results(
             wasmparser::Type::F32 => (&[], &[wasmparser::Type::F32]),
             wasmparser::Type::F64 => (&[], &[wasmparser::Type::F64]),
             wasmparser::Type::V128 => (&[], &[wasmparser::Type::V128]),
-            wasmparser::Type::AnyRef => (&[], &[wasmparser::Type::AnyRef]),
-            wasmparser::Type::AnyFunc => (&[], &[wasmparser::Type::AnyFunc]),
+            wasmparser::Type::ExternRef => (&[], &[wasmparser::Type::ExternRef]),
+            wasmparser::Type::FuncRef => (&[], &[wasmparser::Type::FuncRef]),
             wasmparser::Type::EmptyBlockType => (&[], &[]),
             ty => return Err(wasm_unsupported!("blocktype_params_results: type {:?}", ty)),
         },
@@ -203,7 +205,7 @@ pub fn ebb_with_params<PE: TargetEnvironment + ?Sized>(
             wasmparser::Type::F64 => {
                 builder.append_ebb_param(ebb, ir::types::F64);
             }
-            wasmparser::Type::AnyRef | wasmparser::Type::AnyFunc => {
+            wasmparser::Type::ExternRef | wasmparser::Type::FuncRef => {
                 builder.append_ebb_param(ebb, environ.reference_type());
             }
             wasmparser::Type::V128 => {
@@ -1,8 +1,10 @@
+use cranelift_codegen::entity::EntityRef;
+use cranelift_codegen::ir::{types, Opcode};
 use cranelift_codegen::isa;
 use cranelift_codegen::print_errors::pretty_verifier_error;
 use cranelift_codegen::settings::{self, Flags};
 use cranelift_codegen::verifier;
-use cranelift_wasm::{translate_module, DummyEnvironment, FuncIndex, ReturnMode};
+use cranelift_wasm::{translate_module, DefinedFuncIndex, DummyEnvironment, FuncIndex, ReturnMode};
 use std::fs;
 use std::fs::File;
 use std::io;
@@ -69,6 +71,82 @@ fn use_name_section() {
     );
 }
 
+#[test]
+fn use_memory_indices() {
+    let data = wat::parse_str(
+        r#"
+        (module
+            (memory $mem32 1)
+            (memory $mem64 i64 1)
+            (func (param i32 i64)
+                (i32.store (memory $mem64) offset=4 (local.get 1) (i32.load (memory $mem32) (local.get 0)))
+                (drop (memory.size (memory $mem64)))
+            )
+        )"#,
+    )
+    .unwrap();
+
+    let flags = Flags::new(settings::builder());
+    let triple = triple!("riscv64");
+    let isa = isa::lookup(triple).unwrap().finish(flags.clone());
+    let return_mode = ReturnMode::NormalReturns;
+    let mut dummy_environ = DummyEnvironment::new(isa.frontend_config(), return_mode, false);
+
+    translate_module(data.as_ref(), &mut dummy_environ).unwrap();
+
+    let func = &dummy_environ.info.function_bodies[DefinedFuncIndex::new(0)];
+    verifier::verify_function(func, &*isa)
+        .map_err(|errors| panic!(pretty_verifier_error(func, Some(&*isa), None, errors)))
+        .unwrap();
+
+    // Each memory gets its own heap, and the 64-bit memory's addresses aren't truncated or
+    // extended on their way to `heap_addr`.
+    let index_types: Vec<_> = func.heaps.values().map(|heap| heap.index_type).collect();
+    assert_eq!(index_types, [types::I32, types::I64]);
+    let opcodes: Vec<_> = func
+        .layout
+        .ebbs()
+        .flat_map(|ebb| func.layout.ebb_insts(ebb))
+        .map(|inst| func.dfg[inst].opcode())
+        .collect();
+    assert_eq!(
+        opcodes
+            .iter()
+            .filter(|&&opcode| opcode == Opcode::HeapAddr)
+            .count(),
+        2
+    );
+    assert!(!opcodes.contains(&Opcode::Uextend));
+    assert!(!opcodes.contains(&Opcode::Ireduce));
+}
+
+#[test]
+fn use_memory_copy_between_memories() {
+    let data = wat::parse_str(
+        r#"
+        (module
+            (memory $a 1)
+            (memory $b 1)
+            (func (param i32 i32 i32)
+                (memory.copy $b $a (local.get 0) (local.get 1) (local.get 2))
+            )
+        )"#,
+    )
+    .unwrap();
+
+    let flags = Flags::new(settings::builder());
+    let triple = triple!("riscv64");
+    let isa = isa::lookup(triple).unwrap().finish(flags.clone());
+    let return_mode = ReturnMode::NormalReturns;
+    let mut dummy_environ = DummyEnvironment::new(isa.frontend_config(), return_mode, false);
+
+    translate_module(data.as_ref(), &mut dummy_environ).unwrap();
+
+    // Both the destination and the source memory get a heap.
+    let func = &dummy_environ.info.function_bodies[DefinedFuncIndex::new(0)];
+    assert_eq!(func.heaps.len(), 2);
+}
+
 fn read_file(path: &Path) -> io::Result<Vec<u8>> {
     let mut buf: Vec<u8> = Vec::new();
     let mut file = File::open(path)?;
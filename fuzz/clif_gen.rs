@@ -0,0 +1,728 @@
+//! Generation of random, verifier-clean Cranelift IR functions.
+//!
+//! Functions are built with a `FunctionBuilder`: the generator declares a few variables of random
+//! types, fills a random number of EBBs with instructions computing new values for those
+//! variables, and lets the SSA construction of `cranelift-frontend` take care of the EBB
+//! parameters. Unlike functions translated from wasm, generated functions use narrow and wide
+//! integers, flags, SIMD vectors and jump tables.
+//!
+//! The generator only emits instructions that the x86 backend knows how to legalize for the types
+//! they are used with, so that compilation failures point at bugs rather than at missing
+//! encodings. The functions are meant to be compiled, not executed: they may loop forever or trap.
+
+use arbitrary::{Result, Unstructured};
+use cranelift_codegen::entity::EntityRef;
+use cranelift_codegen::ir::condcodes::{FloatCC, IntCC};
+use cranelift_codegen::ir::immediates::{Ieee32, Ieee64};
+use cranelift_codegen::ir::types::*;
+use cranelift_codegen::ir::{
+    AbiParam, ConstantData, Ebb, ExtFuncData, ExternalName, Function, InstBuilder, JumpTableData,
+    Signature, StackSlot, StackSlotData, StackSlotKind, TrapCode, Type, Value,
+};
+use cranelift_codegen::isa::CallConv;
+use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext, Variable};
+
+const MAX_PARAMS: usize = 4;
+const MAX_RETURNS: usize = 2;
+const MAX_VARIABLES: usize = 8;
+const MAX_EBBS: usize = 8;
+const MAX_INSTRUCTIONS: usize = 12;
+const MAX_JUMP_TABLE_ENTRIES: usize = 6;
+const STACK_SLOT_SIZE: u32 = 16;
+
+/// The IR features a target supports, and therefore that generated functions may use.
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// The calling convention of the generated function and of the functions it calls.
+    pub call_conv: CallConv,
+    /// The scalar integer types that may be used, among `I8`, `I16`, `I32` and `I64`. Must
+    /// include `I32`.
+    pub int_types: Vec<Type>,
+    /// Compute intermediate `i128` values from `i64` ones. Requires `I64` in `int_types`.
+    pub i128: bool,
+    /// Use `f32` and `f64` values.
+    pub floats: bool,
+    /// Use 128-bit vectors.
+    pub simd: bool,
+    /// Use flags values, produced by `ifcmp` and `ffcmp`.
+    pub flags: bool,
+    /// Use calls to an external function.
+    pub calls: bool,
+}
+
+/// Generate a function named `name`.
+pub fn generate(u: &mut Unstructured, config: &Config, name: ExternalName) -> Result<Function> {
+    let mut generator = FunctionGenerator::new(config);
+    let signature = generator.signature(u)?;
+    let mut func = Function::with_name_signature(name, signature);
+    let mut builder_context = FunctionBuilderContext::new();
+    let mut builder = FunctionBuilder::new(&mut func, &mut builder_context);
+    generator.body(u, &mut builder)?;
+    builder.seal_all_blocks();
+    builder.finalize();
+    Ok(func)
+}
+
+/// Generates the signature and the body of a function.
+struct FunctionGenerator<'a> {
+    config: &'a Config,
+    /// The types that variables may have.
+    types: Vec<Type>,
+    /// The integer types that may be compared into flags, rotated, or converted from floats.
+    wide_int_types: Vec<Type>,
+    /// The types of the declared variables, indexed by variable number.
+    variables: Vec<Type>,
+    /// The EBBs of the function; the first one is the entry block.
+    ebbs: Vec<Ebb>,
+    stack_slot: Option<StackSlot>,
+}
+
+impl<'a> FunctionGenerator<'a> {
+    fn new(config: &'a Config) -> Self {
+        let mut types = config.int_types.clone();
+        types.push(B1);
+        if config.floats {
+            types.extend_from_slice(&[F32, F64]);
+        }
+        if config.simd {
+            types.extend_from_slice(&[I8X16, I16X8, I32X4, I64X2]);
+            if config.floats {
+                types.extend_from_slice(&[F32X4, F64X2]);
+            }
+        }
+        let wide_int_types = config
+            .int_types
+            .iter()
+            .cloned()
+            .filter(|ty| ty.bits() >= 32)
+            .collect();
+        Self {
+            config,
+            types,
+            wide_int_types,
+            variables: Vec::new(),
+            ebbs: Vec::new(),
+            stack_slot: None,
+        }
+    }
+
+    fn signature(&self, u: &mut Unstructured) -> Result<Signature> {
+        let abi_types: Vec<Type> = self
+            .types
+            .iter()
+            .cloned()
+            .filter(|ty| !ty.is_vector() && !ty.is_bool())
+            .collect();
+        let mut signature = Signature::new(self.config.call_conv);
+        for _ in 0..u.int_in_range(0..=MAX_PARAMS)? {
+            signature.params.push(AbiParam::new(*u.choose(&abi_types)?));
+        }
+        for _ in 0..u.int_in_range(0..=MAX_RETURNS)? {
+            signature
+                .returns
+                .push(AbiParam::new(*u.choose(&abi_types)?));
+        }
+        Ok(signature)
+    }
+
+    fn body(&mut self, u: &mut Unstructured, builder: &mut FunctionBuilder) -> Result<()> {
+        for _ in 0..u.int_in_range(1..=MAX_EBBS)? {
+            self.ebbs.push(builder.create_ebb());
+        }
+        let entry = self.ebbs[0];
+        builder.append_ebb_params_for_function_params(entry);
+        builder.switch_to_block(entry);
+
+        // Every variable is defined in the entry block, so that all uses have a definition: the
+        // function parameters initialize a variable each, and the others start as constants.
+        let params = builder.ebb_params(entry).to_vec();
+        for param in params {
+            let ty = builder.func.dfg.value_type(param);
+            let var = Variable::new(self.variables.len());
+            builder.declare_var(var, ty);
+            builder.def_var(var, param);
+            self.variables.push(ty);
+        }
+        for _ in 0..u.int_in_range(1..=MAX_VARIABLES)? {
+            let ty = *u.choose(&self.types)?;
+            let var = Variable::new(self.variables.len());
+            builder.declare_var(var, ty);
+            let value = self.constant(u, builder, ty)?;
+            builder.def_var(var, value);
+            self.variables.push(ty);
+        }
+        if u.arbitrary()? {
+            self.stack_slot = Some(builder.create_stack_slot(StackSlotData::new(
+                StackSlotKind::ExplicitSlot,
+                STACK_SLOT_SIZE,
+            )));
+        }
+
+        for index in 0..self.ebbs.len() {
+            if index > 0 {
+                builder.switch_to_block(self.ebbs[index]);
+            }
+            for _ in 0..u.int_in_range(0..=MAX_INSTRUCTIONS)? {
+                self.statement(u, builder)?;
+            }
+            self.terminator(u, builder, index)?;
+        }
+        Ok(())
+    }
+
+    /// Emit an instruction that updates a variable, stores to the stack, or may trap.
+    fn statement(&mut self, u: &mut Unstructured, builder: &mut FunctionBuilder) -> Result<()> {
+        match u.int_in_range(0..=9)? {
+            0 if self.stack_slot.is_some() => {
+                // Vectors can't be stored to stack slots.
+                let ty = *u.choose(&self.types)?;
+                if ty.is_bool() || ty.is_vector() {
+                    return Ok(());
+                }
+                let value = self.operand(u, builder, ty)?;
+                let offset = self.stack_offset(u, ty)?;
+                let slot = self.stack_slot.unwrap();
+                builder.ins().stack_store(value, slot, offset);
+            }
+            1 => {
+                let condition = self.operand(u, builder, B1)?;
+                if u.arbitrary()? {
+                    builder.ins().trapz(condition, TrapCode::User(0));
+                } else {
+                    builder.ins().trapnz(condition, TrapCode::User(0));
+                }
+            }
+            2 if self.config.flags => {
+                let cc = *u.choose(&INT_CCS)?;
+                let flags = self.int_flags(u, builder)?;
+                builder.ins().trapif(cc, flags, TrapCode::User(0));
+            }
+            3 if self.config.calls => self.call(u, builder)?,
+            _ => {
+                let index = u.int_in_range(0..=self.variables.len() - 1)?;
+                let value = self.value(u, builder, self.variables[index])?;
+                builder.def_var(Variable::new(index), value);
+            }
+        }
+        Ok(())
+    }
+
+    /// Emit a call to an external function with a random signature, assigning its results to
+    /// variables of matching types.
+    fn call(&mut self, u: &mut Unstructured, builder: &mut FunctionBuilder) -> Result<()> {
+        let signature = self.signature(u)?;
+        let mut args = Vec::with_capacity(signature.params.len());
+        for param in &signature.params {
+            args.push(self.operand(u, builder, param.value_type)?);
+        }
+        let signature = builder.import_signature(signature);
+        let callee = builder.import_function(ExtFuncData {
+            name: ExternalName::user(1, 0),
+            signature,
+            colocated: u.arbitrary()?,
+        });
+        let call = builder.ins().call(callee, &args);
+        let results = builder.inst_results(call).to_vec();
+        for result in results {
+            let ty = builder.func.dfg.value_type(result);
+            if let Some(index) = self.variables.iter().position(|&var| var == ty) {
+                builder.def_var(Variable::new(index), result);
+            }
+        }
+        Ok(())
+    }
+
+    /// Terminate the EBB with index `index`. Each EBB branches to the next one, so that all EBBs
+    /// are reachable, and the last EBB returns. The entry block is never a branch target.
+    fn terminator(
+        &mut self,
+        u: &mut Unstructured,
+        builder: &mut FunctionBuilder,
+        index: usize,
+    ) -> Result<()> {
+        if index + 1 == self.ebbs.len() {
+            let mut results = Vec::new();
+            for result in builder.func.signature.returns.clone() {
+                results.push(self.operand(u, builder, result.value_type)?);
+            }
+            builder.ins().return_(&results);
+            return Ok(());
+        }
+
+        let next = self.ebbs[index + 1];
+        let other = *u.choose(&self.ebbs[1..])?;
+        match u.int_in_range(0..=4)? {
+            0 => {
+                let condition = self.operand(u, builder, B1)?;
+                builder.ins().brnz(condition, other, &[]);
+            }
+            1 => {
+                let ty = *u.choose(&self.config.int_types)?;
+                let condition = self.operand(u, builder, ty)?;
+                builder.ins().brz(condition, other, &[]);
+            }
+            2 if self.config.flags => {
+                let cc = *u.choose(&INT_CCS)?;
+                let flags = self.int_flags(u, builder)?;
+                builder.ins().brif(cc, flags, other, &[]);
+            }
+            3 => {
+                let mut jump_table = JumpTableData::new();
+                for _ in 0..u.int_in_range(0..=MAX_JUMP_TABLE_ENTRIES)? {
+                    jump_table.push_entry(*u.choose(&self.ebbs[1..])?);
+                }
+                let jump_table = builder.create_jump_table(jump_table);
+                let index = self.operand(u, builder, I32)?;
+                builder.ins().br_table(index, next, jump_table);
+                return Ok(());
+            }
+            _ => {}
+        }
+        builder.ins().jump(next, &[]);
+        Ok(())
+    }
+
+    /// Produce an integer flags value by comparing two integers. The flags must be used right
+    /// away, before any other instruction can clobber them, so all other operands of the user
+    /// must be produced first.
+    fn int_flags(&mut self, u: &mut Unstructured, builder: &mut FunctionBuilder) -> Result<Value> {
+        let ty = *u.choose(&self.wide_int_types)?;
+        let x = self.operand(u, builder, ty)?;
+        Ok(if u.arbitrary()? {
+            let y = self.operand(u, builder, ty)?;
+            builder.ins().ifcmp(x, y)
+        } else {
+            let imm = self.immediate(u, ty)?;
+            builder.ins().ifcmp_imm(x, imm)
+        })
+    }
+
+    /// Produce an operand of type `ty`: the value of a variable of that type, if there is one,
+    /// or a constant.
+    fn operand(
+        &mut self,
+        u: &mut Unstructured,
+        builder: &mut FunctionBuilder,
+        ty: Type,
+    ) -> Result<Value> {
+        let candidates: Vec<usize> = (0..self.variables.len())
+            .filter(|&index| self.variables[index] == ty)
+            .collect();
+        if !candidates.is_empty() && u.int_in_range(0..=3)? != 0 {
+            let index = *u.choose(&candidates)?;
+            Ok(builder.use_var(Variable::new(index)))
+        } else {
+            self.constant(u, builder, ty)
+        }
+    }
+
+    /// Produce an immediate that fits in the scalar integer type `ty`.
+    fn immediate(&mut self, u: &mut Unstructured, ty: Type) -> Result<i64> {
+        let shift = 64 - ty.bits();
+        Ok((u.arbitrary::<i64>()? << shift) >> shift)
+    }
+
+    /// Produce a constant of type `ty`.
+    fn constant(
+        &mut self,
+        u: &mut Unstructured,
+        builder: &mut FunctionBuilder,
+        ty: Type,
+    ) -> Result<Value> {
+        Ok(match ty {
+            B1 => builder.ins().bconst(B1, u.arbitrary::<bool>()?),
+            F32 => builder
+                .ins()
+                .f32const(Ieee32::with_bits(u.arbitrary::<u32>()?)),
+            F64 => builder
+                .ins()
+                .f64const(Ieee64::with_bits(u.arbitrary::<u64>()?)),
+            _ if ty.is_vector() => {
+                let bytes = u.get_bytes(16)?.to_vec();
+                let constant = builder.func.dfg.constants.insert(ConstantData::from(bytes));
+                builder.ins().vconst(ty, constant)
+            }
+            _ => {
+                let imm = self.immediate(u, ty)?;
+                builder.ins().iconst(ty, imm)
+            }
+        })
+    }
+
+    /// Produce a new value of type `ty` by emitting an instruction of a random kind.
+    fn value(
+        &mut self,
+        u: &mut Unstructured,
+        builder: &mut FunctionBuilder,
+        ty: Type,
+    ) -> Result<Value> {
+        if u.int_in_range(0..=5)? == 0 {
+            return self.constant(u, builder, ty);
+        }
+        if ty.is_vector() {
+            self.vector_value(u, builder, ty)
+        } else if ty == B1 {
+            self.bool_value(u, builder)
+        } else if ty.is_float() {
+            self.float_value(u, builder, ty)
+        } else if ty == I64 && self.config.i128 && u.int_in_range(0..=4)? == 0 {
+            self.i128_value(u, builder)
+        } else {
+            self.int_value(u, builder, ty)
+        }
+    }
+
+    fn int_value(
+        &mut self,
+        u: &mut Unstructured,
+        builder: &mut FunctionBuilder,
+        ty: Type,
+    ) -> Result<Value> {
+        let wide = ty.bits() >= 32;
+        Ok(match u.int_in_range(0..=9)? {
+            0 => {
+                let x = self.operand(u, builder, ty)?;
+                let y = self.operand(u, builder, ty)?;
+                match u.int_in_range(0..=10)? {
+                    0 => builder.ins().iadd(x, y),
+                    1 => builder.ins().isub(x, y),
+                    2 => builder.ins().imul(x, y),
+                    3 => builder.ins().band(x, y),
+                    4 => builder.ins().bor(x, y),
+                    5 => builder.ins().bxor(x, y),
+                    6 => builder.ins().ishl(x, y),
+                    7 => builder.ins().ushr(x, y),
+                    8 => builder.ins().sshr(x, y),
+                    // Narrow rotates are not supported.
+                    9 if wide => builder.ins().rotl(x, y),
+                    _ if wide => builder.ins().rotr(x, y),
+                    _ => builder.ins().iadd(x, y),
+                }
+            }
+            1 => {
+                let x = self.operand(u, builder, ty)?;
+                let y = self.operand(u, builder, ty)?;
+                match u.int_in_range(0..=3)? {
+                    0 => builder.ins().udiv(x, y),
+                    1 => builder.ins().sdiv(x, y),
+                    2 => builder.ins().urem(x, y),
+                    _ => builder.ins().srem(x, y),
+                }
+            }
+            2 => {
+                let x = self.operand(u, builder, ty)?;
+                let imm = self.immediate(u, ty)?;
+                match u.int_in_range(0..=3)? {
+                    0 => builder.ins().iadd_imm(x, imm),
+                    1 => builder.ins().imul_imm(x, imm),
+                    2 => builder.ins().band_imm(x, imm),
+                    _ => builder.ins().ishl_imm(x, imm & i64::from(ty.bits() - 1)),
+                }
+            }
+            3 => {
+                let x = self.operand(u, builder, ty)?;
+                match u.int_in_range(0..=3)? {
+                    0 => builder.ins().bnot(x),
+                    1 => builder.ins().clz(x),
+                    2 => builder.ins().ctz(x),
+                    _ => builder.ins().popcnt(x),
+                }
+            }
+            4 => {
+                // Convert from another integer type.
+                let from = *u.choose(&self.config.int_types)?;
+                let x = self.operand(u, builder, from)?;
+                if from.bits() < ty.bits() {
+                    if u.arbitrary()? {
+                        builder.ins().uextend(ty, x)
+                    } else {
+                        builder.ins().sextend(ty, x)
+                    }
+                } else if from.bits() > ty.bits() {
+                    builder.ins().ireduce(ty, x)
+                } else {
+                    x
+                }
+            }
+            5 => {
+                let c = self.operand(u, builder, B1)?;
+                builder.ins().bint(ty, c)
+            }
+            6 if self.config.flags && wide => {
+                let cc = *u.choose(&INT_CCS)?;
+                let x = self.operand(u, builder, ty)?;
+                let y = self.operand(u, builder, ty)?;
+                let flags = self.int_flags(u, builder)?;
+                builder.ins().selectif(ty, cc, flags, x, y)
+            }
+            7 if self.config.floats && wide => {
+                let from = *u.choose(&[F32, F64])?;
+                let x = self.operand(u, builder, from)?;
+                match u.int_in_range(0..=3)? {
+                    0 => builder.ins().fcvt_to_sint(ty, x),
+                    1 => builder.ins().fcvt_to_uint(ty, x),
+                    2 => builder.ins().fcvt_to_sint_sat(ty, x),
+                    _ => builder.ins().fcvt_to_uint_sat(ty, x),
+                }
+            }
+            8 if self.config.simd => {
+                let vector_ty = ty.by(128 / ty.bits()).unwrap();
+                let x = self.operand(u, builder, vector_ty)?;
+                let lane = u.int_in_range(0..=vector_ty.lane_count() - 1)?;
+                builder.ins().extractlane(x, lane as u8)
+            }
+            9 if self.stack_slot.is_some() => {
+                let offset = self.stack_offset(u, ty)?;
+                let slot = self.stack_slot.unwrap();
+                builder.ins().stack_load(ty, slot, offset)
+            }
+            _ => self.select(u, builder, ty)?,
+        })
+    }
+
+    /// Produce an `i64` value by computing an `i128` value and taking one of its halves. `i128`
+    /// values are not kept in variables: they would become EBB parameters, and splitting those
+    /// isn't supported when a conditional branch passes them.
+    fn i128_value(&mut self, u: &mut Unstructured, builder: &mut FunctionBuilder) -> Result<Value> {
+        let mut operands = Vec::with_capacity(2);
+        for _ in 0..2 {
+            let lo = self.operand(u, builder, I64)?;
+            operands.push(match u.int_in_range(0..=2)? {
+                0 => builder.ins().uextend(I128, lo),
+                1 => builder.ins().sextend(I128, lo),
+                _ => {
+                    let hi = self.operand(u, builder, I64)?;
+                    builder.ins().iconcat(lo, hi)
+                }
+            });
+        }
+        let (x, y) = (operands[0], operands[1]);
+        let result = match u.int_in_range(0..=7)? {
+            0 => builder.ins().iadd(x, y),
+            1 => builder.ins().isub(x, y),
+            2 => builder.ins().imul(x, y),
+            3 => builder.ins().band(x, y),
+            4 => builder.ins().bor(x, y),
+            5 => builder.ins().bxor(x, y),
+            6 => builder.ins().bnot(x),
+            _ => {
+                let c = self.operand(u, builder, B1)?;
+                builder.ins().select(c, x, y)
+            }
+        };
+        let (lo, hi) = builder.ins().isplit(result);
+        Ok(if u.arbitrary()? { lo } else { hi })
+    }
+
+    fn bool_value(&mut self, u: &mut Unstructured, builder: &mut FunctionBuilder) -> Result<Value> {
+        Ok(match u.int_in_range(0..=4)? {
+            0 => {
+                let ty = *u.choose(&self.config.int_types)?;
+                let x = self.operand(u, builder, ty)?;
+                let cc = *u.choose(&INT_CCS)?;
+                if u.arbitrary()? {
+                    let y = self.operand(u, builder, ty)?;
+                    builder.ins().icmp(cc, x, y)
+                } else {
+                    let imm = self.immediate(u, ty)?;
+                    builder.ins().icmp_imm(cc, x, imm)
+                }
+            }
+            1 if self.config.floats => {
+                let ty = *u.choose(&[F32, F64])?;
+                let x = self.operand(u, builder, ty)?;
+                let y = self.operand(u, builder, ty)?;
+                let cc = *u.choose(&FLOAT_CCS)?;
+                if self.config.flags && u.arbitrary()? {
+                    let flags = builder.ins().ffcmp(x, y);
+                    builder.ins().trueff(cc, flags)
+                } else {
+                    builder.ins().fcmp(cc, x, y)
+                }
+            }
+            2 if self.config.flags => {
+                let cc = *u.choose(&INT_CCS)?;
+                let flags = self.int_flags(u, builder)?;
+                builder.ins().trueif(cc, flags)
+            }
+            3 => {
+                // `bnot` is not supported on `b1` values.
+                let x = self.operand(u, builder, B1)?;
+                let y = self.operand(u, builder, B1)?;
+                match u.int_in_range(0..=2)? {
+                    0 => builder.ins().band(x, y),
+                    1 => builder.ins().bor(x, y),
+                    _ => builder.ins().bxor(x, y),
+                }
+            }
+            _ => self.select(u, builder, B1)?,
+        })
+    }
+
+    fn float_value(
+        &mut self,
+        u: &mut Unstructured,
+        builder: &mut FunctionBuilder,
+        ty: Type,
+    ) -> Result<Value> {
+        Ok(match u.int_in_range(0..=6)? {
+            0 => {
+                let x = self.operand(u, builder, ty)?;
+                let y = self.operand(u, builder, ty)?;
+                match u.int_in_range(0..=6)? {
+                    0 => builder.ins().fadd(x, y),
+                    1 => builder.ins().fsub(x, y),
+                    2 => builder.ins().fmul(x, y),
+                    3 => builder.ins().fdiv(x, y),
+                    4 => builder.ins().fmin(x, y),
+                    5 => builder.ins().fmax(x, y),
+                    _ => builder.ins().fcopysign(x, y),
+                }
+            }
+            1 => {
+                let x = self.operand(u, builder, ty)?;
+                match u.int_in_range(0..=6)? {
+                    0 => builder.ins().fneg(x),
+                    1 => builder.ins().fabs(x),
+                    2 => builder.ins().sqrt(x),
+                    3 => builder.ins().ceil(x),
+                    4 => builder.ins().floor(x),
+                    5 => builder.ins().trunc(x),
+                    _ => builder.ins().nearest(x),
+                }
+            }
+            2 => {
+                if ty == F32 {
+                    let x = self.operand(u, builder, F64)?;
+                    builder.ins().fdemote(ty, x)
+                } else {
+                    let x = self.operand(u, builder, F32)?;
+                    builder.ins().fpromote(ty, x)
+                }
+            }
+            3 => {
+                let from = *u.choose(&self.wide_int_types)?;
+                let x = self.operand(u, builder, from)?;
+                if u.arbitrary()? {
+                    builder.ins().fcvt_from_sint(ty, x)
+                } else {
+                    builder.ins().fcvt_from_uint(ty, x)
+                }
+            }
+            4 if self.config.int_types.contains(&I64) => {
+                let int_ty = if ty == F32 { I32 } else { I64 };
+                let x = self.operand(u, builder, int_ty)?;
+                builder.ins().bitcast(ty, x)
+            }
+            5 if self.config.simd => {
+                let vector_ty = ty.by(128 / ty.bits()).unwrap();
+                let x = self.operand(u, builder, vector_ty)?;
+                let lane = u.int_in_range(0..=vector_ty.lane_count() - 1)?;
+                builder.ins().extractlane(x, lane as u8)
+            }
+            6 if self.stack_slot.is_some() => {
+                let offset = self.stack_offset(u, ty)?;
+                let slot = self.stack_slot.unwrap();
+                builder.ins().stack_load(ty, slot, offset)
+            }
+            _ => self.select(u, builder, ty)?,
+        })
+    }
+
+    /// Vector values can't be selected or loaded from stack slots.
+    fn vector_value(
+        &mut self,
+        u: &mut Unstructured,
+        builder: &mut FunctionBuilder,
+        ty: Type,
+    ) -> Result<Value> {
+        let lane_ty = ty.lane_type();
+        Ok(match u.int_in_range(0..=3)? {
+            0 => {
+                let x = self.operand(u, builder, ty)?;
+                let y = self.operand(u, builder, ty)?;
+                match u.int_in_range(0..=4)? {
+                    0 if lane_ty.is_float() => builder.ins().fadd(x, y),
+                    1 if lane_ty.is_float() => builder.ins().fsub(x, y),
+                    2 if lane_ty.is_float() => builder.ins().fmul(x, y),
+                    0 => builder.ins().iadd(x, y),
+                    1 => builder.ins().isub(x, y),
+                    2 => builder.ins().band(x, y),
+                    3 => builder.ins().bor(x, y),
+                    _ => builder.ins().bxor(x, y),
+                }
+            }
+            1 => {
+                let x = self.operand(u, builder, ty)?;
+                let y = self.operand(u, builder, lane_ty)?;
+                // Inserting into lane 0 of an `f32x4` is not supported.
+                let first = if ty == F32X4 { 1 } else { 0 };
+                let lane = u.int_in_range(first..=ty.lane_count() - 1)?;
+                builder.ins().insertlane(x, lane as u8, y)
+            }
+            2 => {
+                let others: Vec<Type> = self
+                    .types
+                    .iter()
+                    .cloned()
+                    .filter(|&other| other.is_vector() && other != ty)
+                    .collect();
+                let from = *u.choose(&others)?;
+                let x = self.operand(u, builder, from)?;
+                builder.ins().raw_bitcast(ty, x)
+            }
+            _ => {
+                let x = self.operand(u, builder, lane_ty)?;
+                builder.ins().splat(ty, x)
+            }
+        })
+    }
+
+    fn select(
+        &mut self,
+        u: &mut Unstructured,
+        builder: &mut FunctionBuilder,
+        ty: Type,
+    ) -> Result<Value> {
+        let c = self.operand(u, builder, B1)?;
+        let x = self.operand(u, builder, ty)?;
+        let y = self.operand(u, builder, ty)?;
+        Ok(builder.ins().select(c, x, y))
+    }
+
+    /// A naturally aligned offset for an access of type `ty` into the stack slot.
+    fn stack_offset(&mut self, u: &mut Unstructured, ty: Type) -> Result<i32> {
+        let bytes = ty.bytes() as i32;
+        Ok(u.int_in_range(0..=STACK_SLOT_SIZE as i32 / bytes - 1)? * bytes)
+    }
+}
+
+const INT_CCS: [IntCC; 10] = [
+    IntCC::Equal,
+    IntCC::NotEqual,
+    IntCC::SignedLessThan,
+    IntCC::SignedGreaterThanOrEqual,
+    IntCC::SignedGreaterThan,
+    IntCC::SignedLessThanOrEqual,
+    IntCC::UnsignedLessThan,
+    IntCC::UnsignedGreaterThanOrEqual,
+    IntCC::UnsignedGreaterThan,
+    IntCC::UnsignedLessThanOrEqual,
+];
+
+const FLOAT_CCS: [FloatCC; 14] = [
+    FloatCC::Ordered,
+    FloatCC::Unordered,
+    FloatCC::Equal,
+    FloatCC::NotEqual,
+    FloatCC::OrderedNotEqual,
+    FloatCC::UnorderedOrEqual,
+    FloatCC::LessThan,
+    FloatCC::LessThanOrEqual,
+    FloatCC::GreaterThan,
+    FloatCC::GreaterThanOrEqual,
+    FloatCC::UnorderedOrLessThan,
+    FloatCC::UnorderedOrLessThanOrEqual,
+    FloatCC::UnorderedOrGreaterThan,
+    FloatCC::UnorderedOrGreaterThanOrEqual,
+];
@@ -0,0 +1,122 @@
+//! Fuzzing of the code generator with random Cranelift IR functions.
+//!
+//! A function is generated from the fuzz input for each target (see `clif_gen.rs`) and compiled
+//! with the verifier enabled. Any verifier error or panic in the legalizer, the register
+//! allocator or the binary emission is a bug.
+//!
+//! The x86 and AArch64 backends are fuzzed. The 32-bit ARM backend is not, since it has no
+//! encodings at all, and neither is the RISC-V backend, which can't encode traps and leaves
+//! several integer instructions without an encoding, even with the M extension.
+
+#![no_main]
+
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate arbitrary;
+extern crate cranelift_codegen;
+extern crate cranelift_frontend;
+#[macro_use]
+extern crate target_lexicon;
+
+mod clif_gen;
+
+use arbitrary::Unstructured;
+use clif_gen::Config;
+use cranelift_codegen::ir::types::*;
+use cranelift_codegen::ir::ExternalName;
+use cranelift_codegen::isa::{self, CallConv};
+use cranelift_codegen::print_errors::pretty_error;
+use cranelift_codegen::settings::{self, Configurable};
+use cranelift_codegen::Context;
+use std::str::FromStr;
+
+fuzz_target!(|data: &[u8]| {
+    // The 32-bit backend doesn't support `i64` values, and supports neither floats nor vectors
+    // well enough to compile arbitrary uses of them.
+    let i686 = Config {
+        call_conv: CallConv::SystemV,
+        int_types: vec![I8, I16, I32],
+        i128: false,
+        floats: false,
+        simd: false,
+        flags: true,
+        calls: true,
+    };
+    // The AArch64 backend doesn't support vectors. It can't test float flags for conditions
+    // needing two checks, such as `one`, which the post-optimization pass introduces for float
+    // comparisons, nor spill flags values live across calls.
+    let aarch64 = Config {
+        call_conv: CallConv::SystemV,
+        int_types: vec![I8, I16, I32, I64],
+        i128: true,
+        floats: false,
+        simd: false,
+        flags: false,
+        calls: false,
+    };
+    compile(data, "x86_64", None);
+    compile(data, "i686", Some(i686));
+    compile(data, "aarch64", Some(aarch64));
+});
+
+/// Generate a function from `data` and compile it for `target`, with the features in `config`
+/// or, if there is none, with features chosen from `data`.
+fn compile(data: &[u8], target: &str, config: Option<Config>) {
+    let mut u = Unstructured::new(data);
+    let config = match config {
+        Some(config) => config,
+        None => match x86_64_config(&mut u) {
+            Ok(config) => config,
+            Err(_) => return,
+        },
+    };
+    let opt_level = match u.int_in_range(0..=2) {
+        Ok(0) => "none",
+        Ok(1) => "speed",
+        _ => "speed_and_size",
+    };
+    let func = match clif_gen::generate(&mut u, &config, ExternalName::user(0, 0)) {
+        Ok(func) => func,
+        Err(_) => return,
+    };
+
+    let mut flag_builder = settings::builder();
+    flag_builder.set("opt_level", opt_level).unwrap();
+    flag_builder.enable("enable_verifier").unwrap();
+    let mut isa_builder = isa::lookup(triple!(target)).unwrap();
+    if config.simd {
+        flag_builder.enable("enable_simd").unwrap();
+        for feature in &["has_sse3", "has_ssse3", "has_sse41", "has_sse42"] {
+            isa_builder.enable(feature).unwrap();
+        }
+    }
+    let isa = isa_builder.finish(settings::Flags::new(flag_builder));
+
+    let mut ctx = Context::for_function(func);
+    if let Err(err) = ctx.compile(&*isa) {
+        panic!(
+            "compilation for {} failed:\n{}",
+            target,
+            pretty_error(&ctx.func, Some(&*isa), err)
+        );
+    }
+}
+
+fn x86_64_config(u: &mut Unstructured) -> arbitrary::Result<Config> {
+    let mut int_types = vec![I32, I64];
+    if u.arbitrary()? {
+        int_types.push(I8);
+    }
+    if u.arbitrary()? {
+        int_types.push(I16);
+    }
+    Ok(Config {
+        call_conv: CallConv::SystemV,
+        int_types,
+        i128: u.arbitrary()?,
+        floats: u.arbitrary()?,
+        simd: u.arbitrary()?,
+        flags: u.arbitrary()?,
+        calls: u.arbitrary()?,
+    })
+}
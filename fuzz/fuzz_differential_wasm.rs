@@ -0,0 +1,299 @@
+//! Differential fuzzing of the wasm translator and code generator.
+//!
+//! A random module is generated from the fuzz input, translated with `cranelift-wasm`, and each
+//! of its functions is then both compiled with SimpleJIT and executed by the Cranelift IR
+//! interpreter with the same random arguments. The two executions must agree: either both
+//! return the same values, or both trap.
+
+#![no_main]
+
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate arbitrary;
+extern crate cranelift_codegen;
+extern crate cranelift_frontend;
+extern crate cranelift_interpreter;
+extern crate cranelift_module;
+extern crate cranelift_native;
+extern crate cranelift_simplejit;
+extern crate cranelift_wasm;
+extern crate libc;
+
+mod wasm_gen;
+
+use arbitrary::{Arbitrary, Unstructured};
+use cranelift_codegen::data_value::DataValue;
+use cranelift_codegen::ir::immediates::{Ieee32, Ieee64};
+use cranelift_codegen::ir::{types, AbiParam, ArgumentPurpose, Function, InstBuilder, MemFlags};
+use cranelift_codegen::ir::{Signature, Type};
+use cranelift_codegen::settings::{self, Configurable};
+use cranelift_codegen::Context;
+use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext};
+use cranelift_interpreter::{ControlFlow, Environment, Interpreter};
+use cranelift_module::{default_libcall_names, FuncId, Linkage, Module};
+use cranelift_simplejit::{SimpleJITBackend, SimpleJITBuilder};
+use cranelift_wasm::{translate_module, DummyEnvironment, ReturnMode};
+
+/// The size of the slot holding each argument and result in the memory passed to a trampoline.
+const SLOT_SIZE: usize = 8;
+
+/// A trampoline calling a compiled function with the arguments found in its slots and storing
+/// the results back into them.
+type Trampoline = unsafe extern "C" fn(*mut u64);
+
+fuzz_target!(|data: &[u8]| {
+    let mut u = Unstructured::new(data);
+    let wasm = match wasm_gen::generate(&mut u) {
+        Ok(wasm) => wasm,
+        Err(_) => return,
+    };
+    let opt_level = match u.int_in_range(0..=2) {
+        Ok(0) => "none",
+        Ok(1) => "speed",
+        _ => "speed_and_size",
+    };
+
+    let mut flag_builder = settings::builder();
+    flag_builder.set("opt_level", opt_level).unwrap();
+    flag_builder.enable("enable_verifier").unwrap();
+    let isa_builder = cranelift_native::builder().unwrap();
+    let isa = isa_builder.finish(settings::Flags::new(flag_builder));
+
+    let mut environ =
+        DummyEnvironment::new(isa.frontend_config(), ReturnMode::NormalReturns, false);
+    translate_module(&wasm, &mut environ).unwrap();
+    let functions: Vec<Function> = environ.info.function_bodies.values().cloned().collect();
+
+    let mut env = Environment::default();
+    for func in &functions {
+        env.add(func.clone());
+    }
+    let mut interpreter = Interpreter::new(env);
+
+    let mut module: Module<SimpleJITBackend> =
+        Module::new(SimpleJITBuilder::with_isa(isa, default_libcall_names()));
+    let ids = define_functions(&mut module, &functions);
+    let trampolines = define_trampolines(&mut module, &functions, &ids);
+    module.finalize_definitions();
+
+    for (func, trampoline) in functions.iter().zip(trampolines) {
+        let args = match arguments(&mut u, &func.signature) {
+            Ok(args) => args,
+            Err(_) => return,
+        };
+        let trampoline = unsafe {
+            std::mem::transmute::<*const u8, Trampoline>(module.get_finalized_function(trampoline))
+        };
+
+        let mut interpreter_args = args.clone();
+        interpreter_args.push(DataValue::I64(0)); // vmctx
+        let expected = match interpreter.call_by_name(&func.name.to_string(), &interpreter_args) {
+            Ok(result) => result,
+            // The interpreter does not support some of the translated code: nothing to compare.
+            Err(_) => return,
+        };
+
+        match expected {
+            ControlFlow::Return(expected) => {
+                let actual = call(trampoline, &args, &func.signature);
+                if !same_values(&expected, &actual) {
+                    panic!(
+                        "{} returned {:?} but the interpreter returned {:?}, with arguments {:?}:\n{}",
+                        func.name,
+                        actual,
+                        expected,
+                        args,
+                        func.display(None)
+                    );
+                }
+            }
+            ControlFlow::Trap(code) => {
+                if !traps(trampoline, &args, &func.signature) {
+                    panic!(
+                        "{} did not trap but the interpreter trapped with {}, with arguments {:?}:\n{}",
+                        func.name,
+                        code,
+                        args,
+                        func.display(None)
+                    );
+                }
+            }
+        }
+    }
+});
+
+/// Declare and define the translated functions in `module`. The function with index `i` gets
+/// `FuncId` `i`, so that the calls between them, which use `ExternalName::user(0, i)`, resolve
+/// to the right definitions.
+fn define_functions(module: &mut Module<SimpleJITBackend>, functions: &[Function]) -> Vec<FuncId> {
+    let ids: Vec<FuncId> = functions
+        .iter()
+        .enumerate()
+        .map(|(i, func)| {
+            module
+                .declare_function(&format!("f{}", i), Linkage::Local, &func.signature)
+                .unwrap()
+        })
+        .collect();
+    let mut ctx = Context::new();
+    for (func, &id) in functions.iter().zip(&ids) {
+        ctx.func = func.clone();
+        module.define_function(id, &mut ctx).unwrap();
+        ctx.clear();
+    }
+    ids
+}
+
+/// Define a trampoline for each of `functions`, which loads the arguments from an array of
+/// slots, passes a null vmctx, and stores the results in the same array.
+fn define_trampolines(
+    module: &mut Module<SimpleJITBackend>,
+    functions: &[Function],
+    ids: &[FuncId],
+) -> Vec<FuncId> {
+    let pointer_type = module.target_config().pointer_type();
+    let mut ctx = module.make_context();
+    let mut builder_ctx = FunctionBuilderContext::new();
+    let mut trampolines = Vec::with_capacity(functions.len());
+    for (i, (func, &id)) in functions.iter().zip(ids).enumerate() {
+        let mut signature = module.make_signature();
+        signature.params.push(AbiParam::new(pointer_type));
+        let trampoline = module
+            .declare_function(&format!("trampoline{}", i), Linkage::Local, &signature)
+            .unwrap();
+        ctx.func.signature = signature;
+
+        let mut builder = FunctionBuilder::new(&mut ctx.func, &mut builder_ctx);
+        let ebb = builder.create_ebb();
+        builder.append_ebb_params_for_function_params(ebb);
+        builder.switch_to_block(ebb);
+        builder.seal_block(ebb);
+        let values = builder.ebb_params(ebb)[0];
+        let flags = MemFlags::trusted();
+        let mut args = Vec::new();
+        for (slot, param) in func.signature.params.iter().enumerate() {
+            args.push(if param.purpose == ArgumentPurpose::VMContext {
+                builder.ins().iconst(pointer_type, 0)
+            } else {
+                let offset = (slot * SLOT_SIZE) as i32;
+                builder.ins().load(param.value_type, flags, values, offset)
+            });
+        }
+        let callee = module.declare_func_in_func(id, builder.func);
+        let call = builder.ins().call(callee, &args);
+        let results = builder.inst_results(call).to_vec();
+        for (slot, result) in results.into_iter().enumerate() {
+            let offset = (slot * SLOT_SIZE) as i32;
+            builder.ins().store(flags, result, values, offset);
+        }
+        builder.ins().return_(&[]);
+        builder.finalize();
+
+        module.define_function(trampoline, &mut ctx).unwrap();
+        module.clear_context(&mut ctx);
+        trampolines.push(trampoline);
+    }
+    trampolines
+}
+
+/// Generate arguments for the wasm parameters of `signature`.
+fn arguments(u: &mut Unstructured, signature: &Signature) -> arbitrary::Result<Vec<DataValue>> {
+    signature
+        .params
+        .iter()
+        .filter(|param| param.purpose == ArgumentPurpose::Normal)
+        .map(|param| {
+            Ok(match param.value_type {
+                types::I32 => DataValue::I32(i32::arbitrary(u)?),
+                types::I64 => DataValue::I64(i64::arbitrary(u)?),
+                types::F32 => DataValue::F32(Ieee32::with_bits(u32::arbitrary(u)?)),
+                types::F64 => DataValue::F64(Ieee64::with_bits(u64::arbitrary(u)?)),
+                ty => panic!("unexpected wasm parameter type {}", ty),
+            })
+        })
+        .collect()
+}
+
+/// Call a compiled function through its trampoline and return its results.
+fn call(trampoline: Trampoline, args: &[DataValue], signature: &Signature) -> Vec<DataValue> {
+    let mut slots = vec![0u64; signature.params.len().max(signature.returns.len())];
+    for (slot, arg) in slots.iter_mut().zip(args) {
+        *slot = match *arg {
+            DataValue::I32(x) => u64::from(x as u32),
+            DataValue::I64(x) => x as u64,
+            DataValue::F32(x) => u64::from(x.bits()),
+            DataValue::F64(x) => x.bits(),
+            ref value => panic!("unexpected argument {}", value),
+        };
+    }
+    unsafe { trampoline(slots.as_mut_ptr()) };
+    signature
+        .returns
+        .iter()
+        .zip(slots)
+        .map(|(result, slot)| read_value(result.value_type, slot))
+        .collect()
+}
+
+fn read_value(ty: Type, slot: u64) -> DataValue {
+    match ty {
+        types::I32 => DataValue::I32(slot as u32 as i32),
+        types::I64 => DataValue::I64(slot as i64),
+        types::F32 => DataValue::F32(Ieee32::with_bits(slot as u32)),
+        types::F64 => DataValue::F64(Ieee64::with_bits(slot)),
+        _ => panic!("unexpected wasm result type {}", ty),
+    }
+}
+
+/// Call a compiled function in a child process and report whether it was killed by a signal.
+fn traps(trampoline: Trampoline, args: &[DataValue], signature: &Signature) -> bool {
+    unsafe {
+        match libc::fork() {
+            -1 => panic!("fork failed: {}", std::io::Error::last_os_error()),
+            0 => {
+                // The fuzzer installs its own handlers for these signals: let the child die
+                // instead, without dumping core.
+                for &signal in &[
+                    libc::SIGILL,
+                    libc::SIGFPE,
+                    libc::SIGSEGV,
+                    libc::SIGBUS,
+                    libc::SIGTRAP,
+                ] {
+                    libc::signal(signal, libc::SIG_DFL);
+                }
+                let no_core = libc::rlimit {
+                    rlim_cur: 0,
+                    rlim_max: 0,
+                };
+                libc::setrlimit(libc::RLIMIT_CORE, &no_core);
+                call(trampoline, args, signature);
+                libc::_exit(0)
+            }
+            child => {
+                let mut status = 0;
+                while libc::waitpid(child, &mut status, 0) == -1 {
+                    let error = std::io::Error::last_os_error();
+                    if error.kind() != std::io::ErrorKind::Interrupted {
+                        panic!("waitpid failed: {}", error);
+                    }
+                }
+                libc::WIFSIGNALED(status)
+            }
+        }
+    }
+}
+
+/// Compare results, considering all NaNs equal: their bit patterns are not deterministic.
+fn same_values(expected: &[DataValue], actual: &[DataValue]) -> bool {
+    expected.len() == actual.len()
+        && expected.iter().zip(actual).all(|pair| match pair {
+            (DataValue::F32(a), DataValue::F32(b)) => {
+                a == b || (f32::from_bits(a.bits()).is_nan() && f32::from_bits(b.bits()).is_nan())
+            }
+            (DataValue::F64(a), DataValue::F64(b)) => {
+                a == b || (f64::from_bits(a.bits()).is_nan() && f64::from_bits(b.bits()).is_nan())
+            }
+            (a, b) => a == b,
+        })
+}
@@ -0,0 +1,539 @@
+//! Generation of random WebAssembly modules that are valid and always terminate.
+//!
+//! The generated modules only contain numeric code: functions over `i32`, `i64`, `f32` and `f64`
+//! values with locals, structured control flow and direct calls. There are no memories, tables,
+//! globals or imports, so the modules can be executed without any runtime support.
+//!
+//! Termination is guaranteed by construction: a function only calls functions with a higher
+//! index, branches only target the block they are emitted in, and every loop is driven by a
+//! counter local that nothing else writes to.
+
+use arbitrary::{Result, Unstructured};
+
+const MAX_FUNCS: usize = 4;
+const MAX_PARAMS: usize = 4;
+const MAX_LOCALS: usize = 4;
+const MAX_STATEMENTS: usize = 4;
+const MAX_DEPTH: usize = 5;
+const MAX_LOOP_NESTING: usize = 2;
+const MAX_LOOP_ITERATIONS: i32 = 4;
+
+/// A WebAssembly value type.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValType {
+    I32,
+    I64,
+    F32,
+    F64,
+}
+
+const VAL_TYPES: [ValType; 4] = [ValType::I32, ValType::I64, ValType::F32, ValType::F64];
+
+impl ValType {
+    fn encode(self) -> u8 {
+        match self {
+            ValType::I32 => 0x7f,
+            ValType::I64 => 0x7e,
+            ValType::F32 => 0x7d,
+            ValType::F64 => 0x7c,
+        }
+    }
+}
+
+/// The signature of a generated function. MVP functions return at most one value.
+#[derive(Clone, Debug)]
+pub struct FuncType {
+    pub params: Vec<ValType>,
+    pub result: Option<ValType>,
+}
+
+/// Generate a module and return its binary encoding. Function `i` is exported as `f{i}`.
+pub fn generate(u: &mut Unstructured) -> Result<Vec<u8>> {
+    let count = u.int_in_range(1..=MAX_FUNCS)?;
+    let mut types = Vec::with_capacity(count);
+    for _ in 0..count {
+        let params = u.int_in_range(0..=MAX_PARAMS)?;
+        let params = (0..params)
+            .map(|_| u.choose(&VAL_TYPES).map(|ty| *ty))
+            .collect::<Result<_>>()?;
+        let result = if u.arbitrary()? {
+            Some(*u.choose(&VAL_TYPES)?)
+        } else {
+            None
+        };
+        types.push(FuncType { params, result });
+    }
+
+    let mut bodies = Vec::with_capacity(count);
+    for index in 0..count {
+        bodies.push(FunctionGenerator::new(&types, index).generate(u)?);
+    }
+    Ok(encode(&types, &bodies))
+}
+
+/// The declared locals and code of a function.
+struct Body {
+    locals: Vec<ValType>,
+    code: Vec<u8>,
+}
+
+/// Generates the body of a single function.
+struct FunctionGenerator<'a> {
+    types: &'a [FuncType],
+    index: usize,
+    /// The types of the parameters followed by the declared locals.
+    locals: Vec<ValType>,
+    /// Locals that are loop counters and must not be written to by anything but their loop.
+    counters: Vec<u32>,
+    code: Vec<u8>,
+    depth: usize,
+    loop_nesting: usize,
+}
+
+impl<'a> FunctionGenerator<'a> {
+    fn new(types: &'a [FuncType], index: usize) -> Self {
+        Self {
+            types,
+            index,
+            locals: types[index].params.clone(),
+            counters: Vec::new(),
+            code: Vec::new(),
+            depth: 0,
+            loop_nesting: 0,
+        }
+    }
+
+    fn generate(mut self, u: &mut Unstructured) -> Result<Body> {
+        for _ in 0..u.int_in_range(0..=MAX_LOCALS)? {
+            self.locals.push(*u.choose(&VAL_TYPES)?);
+        }
+        self.statements(u)?;
+        if let Some(result) = self.types[self.index].result {
+            self.expr(u, result)?;
+        }
+        self.code.push(0x0b); // end
+
+        let params = self.types[self.index].params.len();
+        Ok(Body {
+            locals: self.locals.split_off(params),
+            code: self.code,
+        })
+    }
+
+    /// Emit a sequence of statements, which leave the operand stack unchanged.
+    fn statements(&mut self, u: &mut Unstructured) -> Result<()> {
+        if self.depth >= MAX_DEPTH {
+            return Ok(());
+        }
+        self.depth += 1;
+        for _ in 0..u.int_in_range(0..=MAX_STATEMENTS)? {
+            match u.int_in_range(0..=4)? {
+                0 => {
+                    let writable = self.writable_locals(None);
+                    if writable.is_empty() {
+                        self.drop_expr(u)?;
+                    } else {
+                        let local = *u.choose(&writable)?;
+                        self.expr(u, self.locals[local as usize])?;
+                        self.code.push(0x21); // local.set
+                        uleb(&mut self.code, local);
+                    }
+                }
+                1 => self.drop_expr(u)?,
+                2 => {
+                    self.expr(u, ValType::I32)?;
+                    self.code.extend_from_slice(&[0x04, 0x40]); // if with no result
+                    self.statements(u)?;
+                    self.code.push(0x0b); // end
+                }
+                3 if self.loop_nesting < MAX_LOOP_NESTING => self.counted_loop(u)?,
+                _ => self.call(u, None)?,
+            }
+        }
+        self.depth -= 1;
+        Ok(())
+    }
+
+    /// Emit an expression of any type and drop its value.
+    fn drop_expr(&mut self, u: &mut Unstructured) -> Result<()> {
+        let ty = *u.choose(&VAL_TYPES)?;
+        self.expr(u, ty)?;
+        self.code.push(0x1a); // drop
+        Ok(())
+    }
+
+    /// Emit a loop running its body a fixed number of times, counting down a fresh local.
+    fn counted_loop(&mut self, u: &mut Unstructured) -> Result<()> {
+        let counter = self.locals.len() as u32;
+        self.locals.push(ValType::I32);
+        self.counters.push(counter);
+
+        self.code.push(0x41); // i32.const
+        sleb(
+            &mut self.code,
+            i64::from(u.int_in_range(1..=MAX_LOOP_ITERATIONS)?),
+        );
+        self.code.push(0x21); // local.set
+        uleb(&mut self.code, counter);
+        self.code.extend_from_slice(&[0x03, 0x40]); // loop with no result
+        self.loop_nesting += 1;
+        self.statements(u)?;
+        self.loop_nesting -= 1;
+        self.code.push(0x20); // local.get
+        uleb(&mut self.code, counter);
+        self.code.extend_from_slice(&[0x41, 0x01, 0x6b]); // i32.const 1, i32.sub
+        self.code.push(0x22); // local.tee
+        uleb(&mut self.code, counter);
+        self.code.extend_from_slice(&[0x0d, 0x00, 0x0b]); // br_if 0, end
+        Ok(())
+    }
+
+    /// Emit an expression leaving a single value of type `ty` on the operand stack.
+    fn expr(&mut self, u: &mut Unstructured, ty: ValType) -> Result<()> {
+        if self.depth >= MAX_DEPTH {
+            return self.leaf(u, ty);
+        }
+        self.depth += 1;
+        match u.int_in_range(0..=9)? {
+            0 | 1 => self.leaf(u, ty)?,
+            2 => self.unary(u, ty)?,
+            3 | 4 => self.binary(u, ty)?,
+            5 => self.conversion(u, ty)?,
+            6 => {
+                self.expr(u, ty)?;
+                self.expr(u, ty)?;
+                self.expr(u, ValType::I32)?;
+                self.code.push(0x1b); // select
+            }
+            7 => {
+                self.expr(u, ValType::I32)?;
+                self.code.extend_from_slice(&[0x04, ty.encode()]); // if with a result
+                self.expr(u, ty)?;
+                self.code.push(0x05); // else
+                self.expr(u, ty)?;
+                self.code.push(0x0b); // end
+            }
+            8 => {
+                // A block that may exit early with the value of its first expression.
+                self.code.extend_from_slice(&[0x02, ty.encode()]); // block with a result
+                self.expr(u, ty)?;
+                self.expr(u, ValType::I32)?;
+                self.code.extend_from_slice(&[0x0d, 0x00, 0x1a]); // br_if 0, drop
+                self.statements(u)?;
+                self.expr(u, ty)?;
+                self.code.push(0x0b); // end
+            }
+            _ => self.call(u, Some(ty))?,
+        }
+        self.depth -= 1;
+        Ok(())
+    }
+
+    /// Emit a constant or a local access of type `ty`.
+    fn leaf(&mut self, u: &mut Unstructured, ty: ValType) -> Result<()> {
+        let readable: Vec<u32> = (0..self.locals.len() as u32)
+            .filter(|&local| self.locals[local as usize] == ty)
+            .collect();
+        let writable = self.writable_locals(Some(ty));
+        match u.int_in_range(0..=3)? {
+            1 | 2 if !readable.is_empty() => {
+                self.code.push(0x20); // local.get
+                uleb(&mut self.code, *u.choose(&readable)?);
+            }
+            3 if !writable.is_empty() => {
+                let local = *u.choose(&writable)?;
+                self.constant(u, ty)?;
+                self.code.push(0x22); // local.tee
+                uleb(&mut self.code, local);
+            }
+            _ => self.constant(u, ty)?,
+        }
+        Ok(())
+    }
+
+    /// Emit a constant of type `ty`, favoring edge cases.
+    fn constant(&mut self, u: &mut Unstructured, ty: ValType) -> Result<()> {
+        match ty {
+            ValType::I32 => {
+                let value: i32 = if u.arbitrary()? {
+                    *u.choose(&[0, 1, -1, i32::min_value(), i32::max_value()])?
+                } else {
+                    u.arbitrary()?
+                };
+                self.code.push(0x41);
+                sleb(&mut self.code, i64::from(value));
+            }
+            ValType::I64 => {
+                let value: i64 = if u.arbitrary()? {
+                    *u.choose(&[0, 1, -1, i64::min_value(), i64::max_value()])?
+                } else {
+                    u.arbitrary()?
+                };
+                self.code.push(0x42);
+                sleb(&mut self.code, value);
+            }
+            ValType::F32 => {
+                let bits: u32 = if u.arbitrary()? {
+                    *u.choose(&[0, 0x8000_0000, 0x3f80_0000, 0x7f80_0000, 0x7fc0_0000])?
+                } else {
+                    u.arbitrary()?
+                };
+                self.code.push(0x43);
+                self.code.extend_from_slice(&bits.to_le_bytes());
+            }
+            ValType::F64 => {
+                let bits: u64 = if u.arbitrary()? {
+                    *u.choose(&[
+                        0,
+                        0x8000_0000_0000_0000,
+                        0x3ff0_0000_0000_0000,
+                        0x7ff0_0000_0000_0000,
+                        0x7ff8_0000_0000_0000,
+                    ])?
+                } else {
+                    u.arbitrary()?
+                };
+                self.code.push(0x44);
+                self.code.extend_from_slice(&bits.to_le_bytes());
+            }
+        }
+        Ok(())
+    }
+
+    /// Emit a unary operator producing a value of type `ty`.
+    fn unary(&mut self, u: &mut Unstructured, ty: ValType) -> Result<()> {
+        let (opcode, operand) = *u.choose(match ty {
+            // clz, ctz, popcnt, eqz, i64.eqz
+            ValType::I32 => &[
+                (0x67, ValType::I32),
+                (0x68, ValType::I32),
+                (0x69, ValType::I32),
+                (0x45, ValType::I32),
+                (0x50, ValType::I64),
+            ][..],
+            // clz, ctz, popcnt
+            ValType::I64 => &[
+                (0x79, ValType::I64),
+                (0x7a, ValType::I64),
+                (0x7b, ValType::I64),
+            ][..],
+            // abs, neg, ceil, floor, trunc, nearest, sqrt
+            ValType::F32 => &[
+                (0x8b, ValType::F32),
+                (0x8c, ValType::F32),
+                (0x8d, ValType::F32),
+                (0x8e, ValType::F32),
+                (0x8f, ValType::F32),
+                (0x90, ValType::F32),
+                (0x91, ValType::F32),
+            ][..],
+            ValType::F64 => &[
+                (0x99, ValType::F64),
+                (0x9a, ValType::F64),
+                (0x9b, ValType::F64),
+                (0x9c, ValType::F64),
+                (0x9d, ValType::F64),
+                (0x9e, ValType::F64),
+                (0x9f, ValType::F64),
+            ][..],
+        })?;
+        self.expr(u, operand)?;
+        self.code.push(opcode);
+        Ok(())
+    }
+
+    /// Emit a binary operator or a comparison producing a value of type `ty`.
+    fn binary(&mut self, u: &mut Unstructured, ty: ValType) -> Result<()> {
+        let (opcode, operand) = match ty {
+            ValType::I32 => {
+                if u.arbitrary()? {
+                    // add .. rotr
+                    (u.int_in_range(0x6a..=0x78)?, ValType::I32)
+                } else {
+                    // Comparisons: i32.eq .. i32.ge_u, i64.eq .. i64.ge_u, f32.eq .. f32.ge and
+                    // f64.eq .. f64.ge.
+                    let operand = *u.choose(&VAL_TYPES)?;
+                    let opcode = match operand {
+                        ValType::I32 => u.int_in_range(0x46..=0x4f)?,
+                        ValType::I64 => u.int_in_range(0x51..=0x5a)?,
+                        ValType::F32 => u.int_in_range(0x5b..=0x60)?,
+                        ValType::F64 => u.int_in_range(0x61..=0x66)?,
+                    };
+                    (opcode, operand)
+                }
+            }
+            // add .. rotr
+            ValType::I64 => (u.int_in_range(0x7c..=0x8a)?, ValType::I64),
+            // add, sub, mul, div, min, max, copysign
+            ValType::F32 => (u.int_in_range(0x92..=0x98)?, ValType::F32),
+            ValType::F64 => (u.int_in_range(0xa0..=0xa6)?, ValType::F64),
+        };
+        self.expr(u, operand)?;
+        self.expr(u, operand)?;
+        self.code.push(opcode);
+        Ok(())
+    }
+
+    /// Emit a conversion producing a value of type `ty`. Reinterpretations are left out: they
+    /// would expose NaN bit patterns, which are not deterministic.
+    fn conversion(&mut self, u: &mut Unstructured, ty: ValType) -> Result<()> {
+        let (opcode, operand) = *u.choose(match ty {
+            // wrap_i64, trunc_f32_s, trunc_f32_u, trunc_f64_s, trunc_f64_u
+            ValType::I32 => &[
+                (0xa7, ValType::I64),
+                (0xa8, ValType::F32),
+                (0xa9, ValType::F32),
+                (0xaa, ValType::F64),
+                (0xab, ValType::F64),
+            ][..],
+            // extend_i32_s, extend_i32_u, trunc_f32_s, trunc_f32_u, trunc_f64_s, trunc_f64_u
+            ValType::I64 => &[
+                (0xac, ValType::I32),
+                (0xad, ValType::I32),
+                (0xae, ValType::F32),
+                (0xaf, ValType::F32),
+                (0xb0, ValType::F64),
+                (0xb1, ValType::F64),
+            ][..],
+            // convert_i32_s, convert_i32_u, convert_i64_s, convert_i64_u, demote_f64
+            ValType::F32 => &[
+                (0xb2, ValType::I32),
+                (0xb3, ValType::I32),
+                (0xb4, ValType::I64),
+                (0xb5, ValType::I64),
+                (0xb6, ValType::F64),
+            ][..],
+            // convert_i32_s, convert_i32_u, convert_i64_s, convert_i64_u, promote_f32
+            ValType::F64 => &[
+                (0xb7, ValType::I32),
+                (0xb8, ValType::I32),
+                (0xb9, ValType::I64),
+                (0xba, ValType::I64),
+                (0xbb, ValType::F32),
+            ][..],
+        })?;
+        self.expr(u, operand)?;
+        self.code.push(opcode);
+        Ok(())
+    }
+
+    /// Emit a call to a function with a higher index. With `Some(ty)`, the call must produce a
+    /// value of type `ty`; otherwise the result, if any, is dropped. Falls back to a leaf
+    /// expression or to nothing when there is no suitable callee.
+    fn call(&mut self, u: &mut Unstructured, ty: Option<ValType>) -> Result<()> {
+        let callees: Vec<usize> = (self.index + 1..self.types.len())
+            .filter(|&callee| ty.is_none() || self.types[callee].result == ty)
+            .collect();
+        if callees.is_empty() {
+            return match ty {
+                Some(ty) => self.leaf(u, ty),
+                None => Ok(()),
+            };
+        }
+        let callee = *u.choose(&callees)?;
+        for param in self.types[callee].params.clone() {
+            self.expr(u, param)?;
+        }
+        self.code.push(0x10); // call
+        uleb(&mut self.code, callee as u32);
+        if ty.is_none() && self.types[callee].result.is_some() {
+            self.code.push(0x1a); // drop
+        }
+        Ok(())
+    }
+
+    /// The locals of type `ty` (or of any type) that may be assigned to.
+    fn writable_locals(&self, ty: Option<ValType>) -> Vec<u32> {
+        (0..self.locals.len() as u32)
+            .filter(|local| !self.counters.contains(local))
+            .filter(|&local| ty.map_or(true, |ty| self.locals[local as usize] == ty))
+            .collect()
+    }
+}
+
+/// Encode a module with one function of each of `types`, with the corresponding `bodies`.
+fn encode(types: &[FuncType], bodies: &[Body]) -> Vec<u8> {
+    let mut module = b"\0asm\x01\0\0\0".to_vec();
+    let count = types.len() as u32;
+
+    // Type section: one type per function.
+    let mut section = Vec::new();
+    uleb(&mut section, count);
+    for ty in types {
+        section.push(0x60);
+        uleb(&mut section, ty.params.len() as u32);
+        section.extend(ty.params.iter().map(|param| param.encode()));
+        match ty.result {
+            Some(result) => section.extend_from_slice(&[1, result.encode()]),
+            None => section.push(0),
+        }
+    }
+    write_section(&mut module, 1, &section);
+
+    // Function section.
+    let mut section = Vec::new();
+    uleb(&mut section, count);
+    for index in 0..count {
+        uleb(&mut section, index);
+    }
+    write_section(&mut module, 3, &section);
+
+    // Export section.
+    let mut section = Vec::new();
+    uleb(&mut section, count);
+    for index in 0..count {
+        let name = format!("f{}", index);
+        uleb(&mut section, name.len() as u32);
+        section.extend_from_slice(name.as_bytes());
+        section.push(0x00); // function export
+        uleb(&mut section, index);
+    }
+    write_section(&mut module, 7, &section);
+
+    // Code section.
+    let mut section = Vec::new();
+    uleb(&mut section, count);
+    for body in bodies {
+        let mut function = Vec::new();
+        uleb(&mut function, body.locals.len() as u32);
+        for local in &body.locals {
+            function.extend_from_slice(&[1, local.encode()]);
+        }
+        function.extend_from_slice(&body.code);
+        uleb(&mut section, function.len() as u32);
+        section.extend_from_slice(&function);
+    }
+    write_section(&mut module, 10, &section);
+
+    module
+}
+
+fn write_section(module: &mut Vec<u8>, id: u8, contents: &[u8]) {
+    module.push(id);
+    uleb(module, contents.len() as u32);
+    module.extend_from_slice(contents);
+}
+
+fn uleb(bytes: &mut Vec<u8>, mut value: u32) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            bytes.push(byte);
+            return;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+fn sleb(bytes: &mut Vec<u8>, mut value: i64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0) {
+            bytes.push(byte);
+            return;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
@@ -42,7 +42,7 @@ echo git push origin v$version
 for crate in \
     entity bforest codegen/shared codegen/meta codegen frontend native \
     preopt \
-    reader wasm module \
+    reader interpreter wasm module \
     faerie umbrella simplejit object
 do
     echo cargo publish --manifest-path "cranelift-$crate/Cargo.toml"
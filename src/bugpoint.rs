@@ -1,27 +1,85 @@
-//! CLI tool to reduce Cranelift IR files crashing during compilation.
-
+//! CLI tool to reduce Cranelift IR files failing during compilation.
+//!
+//! A function is reduced by repeatedly mutating it and keeping the mutations after which it is
+//! still *interesting*, i.e. still fails in the way given by a [Predicate]: compilation panics,
+//! reports a verifier error, takes too long, or produces code whose results differ from the
+//! interpreter's.
+
+#[cfg(not(test))]
 use crate::disasm::{PrintRelocs, PrintStackmaps, PrintTraps};
 use crate::utils::{parse_sets_and_triple, read_to_string};
 use cranelift_codegen::cursor::{Cursor, FuncCursor};
+use cranelift_codegen::data_value::DataValue;
 use cranelift_codegen::flowgraph::ControlFlowGraph;
 use cranelift_codegen::ir::types::{F32, F64};
 use cranelift_codegen::ir::{
-    self, Ebb, FuncRef, Function, GlobalValueData, Inst, InstBuilder, InstructionData, StackSlots,
-    TrapCode,
+    self, Ebb, FuncRef, Function, GlobalValue, GlobalValueData, Inst, InstBuilder, InstructionData,
+    StackSlot, StackSlots, TrapCode,
 };
 use cranelift_codegen::isa::TargetIsa;
-use cranelift_codegen::Context;
-use cranelift_entity::PrimaryMap;
-use cranelift_reader::{parse_test, ParseOptions};
+use cranelift_codegen::{CodegenError, CodegenResult, Context};
+use cranelift_entity::{EntityRef, PrimaryMap};
+#[cfg(not(test))]
+use cranelift_filetests::FunctionRunner;
+use cranelift_interpreter::{ControlFlow, Environment, Interpreter};
+use cranelift_reader::{parse_run_command, parse_test, Invocation, ParseOptions};
 use std::collections::HashMap;
 use std::path::Path;
+use std::time::{Duration, Instant};
 
 use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
 
+/// How long a miscompiled function may run, natively or in the interpreter, before the check is
+/// abandoned. Mutations easily turn loops into infinite ones.
+const MISCOMPILE_TIME_LIMIT: Duration = Duration::from_secs(10);
+
+/// What makes a function interesting, i.e. the failure that must persist while reducing it.
+pub enum Predicate {
+    /// Compilation panics. If a text is given, the panic message must contain it.
+    Panic(Option<String>),
+    /// Compilation fails with a verifier error containing this text, e.g. because a pass
+    /// produced invalid code.
+    VerifierError(String),
+    /// The function's run commands give different results when the function is compiled for
+    /// the host than when it is interpreted. Beware that reducing may make the function read
+    /// uninitialized memory, whose contents legitimately differ between the two.
+    Miscompile,
+    /// Compilation takes longer than this.
+    Timeout(Duration),
+}
+
+impl Predicate {
+    /// Describe why a function that doesn't satisfy this predicate can't be reduced.
+    fn unsatisfied_message(&self) -> String {
+        match self {
+            Self::Panic(None) => {
+                "Given function compiled successfully or gave a verifier error.".to_string()
+            }
+            Self::Panic(Some(text)) => format!(
+                "Compiling the given function didn't panic with a message containing {:?}.",
+                text
+            ),
+            Self::VerifierError(text) => format!(
+                "Compiling the given function didn't give a verifier error containing {:?}.",
+                text
+            ),
+            Self::Miscompile => {
+                "Given function gave the interpreter's results for all its run commands."
+                    .to_string()
+            }
+            Self::Timeout(time_limit) => format!(
+                "Given function compiled in less than {} ms.",
+                time_limit.as_millis()
+            ),
+        }
+    }
+}
+
 pub fn run(
     filename: &str,
     flag_set: &[String],
     flag_isa: &str,
+    predicate: &Predicate,
     verbose: bool,
 ) -> Result<(), String> {
     let parsed = parse_sets_and_triple(flag_set, flag_isa)?;
@@ -33,9 +91,13 @@ pub fn run(
     let test_file =
         parse_test(&buffer, ParseOptions::default()).map_err(|e| format!("{}: {}", filename, e))?;
 
-    // If we have an isa from the command-line, use that. Otherwise if the
-    // file contains a unique isa, use that.
-    let isa = if let Some(isa) = fisa.isa {
+    // Miscompilations are found by running the code, so they need the host isa. Otherwise, if we
+    // have an isa from the command-line, use that; if the file contains a unique isa, use that.
+    let host_isa;
+    let isa = if let Predicate::Miscompile = predicate {
+        host_isa = cranelift_native::builder()?.finish(fisa.flags.clone());
+        &*host_isa
+    } else if let Some(isa) = fisa.isa {
         isa
     } else if let Some(isa) = test_file.isa_spec.unique_isa() {
         isa
@@ -45,10 +107,21 @@ pub fn run(
 
     std::env::set_var("RUST_BACKTRACE", "0"); // Disable backtraces to reduce verbosity
 
-    for (func, _) in test_file.functions {
+    for (func, details) in test_file.functions {
         let (orig_ebb_count, orig_inst_count) = (ebb_count(&func), inst_count(&func));
 
-        match reduce(isa, func, verbose) {
+        let mut invocations = Vec::new();
+        if let Predicate::Miscompile = predicate {
+            for comment in &details.comments {
+                if let Some(command) = parse_run_command(comment.text, &func)
+                    .map_err(|e| format!("{}: {}", comment.text, e))?
+                {
+                    invocations.push(command.invocation);
+                }
+            }
+        }
+
+        match reduce(isa, func, predicate, invocations, verbose) {
             Ok((func, crash_msg)) => {
                 println!("Crash message: {}", crash_msg);
                 println!("\n{}", func);
@@ -651,6 +724,260 @@ impl Mutator for MergeBlocks {
     }
 }
 
+/// Try to replace EBB parameters with constants, removing the matching branch arguments.
+struct ReplaceEbbParamWithConst {
+    ebb: Ebb,
+    param: usize,
+}
+
+impl ReplaceEbbParamWithConst {
+    fn new(func: &Function) -> Self {
+        // The parameters of the entry block are the function's parameters, which
+        // `SimplifySignature` takes care of: start right after them.
+        let first_ebb = func.layout.entry_block().unwrap();
+        Self {
+            ebb: first_ebb,
+            param: func.dfg.num_ebb_params(first_ebb),
+        }
+    }
+}
+
+impl Mutator for ReplaceEbbParamWithConst {
+    fn name(&self) -> &'static str {
+        "replace ebb param with const"
+    }
+
+    fn mutation_count(&self, func: &Function) -> usize {
+        func.layout
+            .ebbs()
+            .skip(1)
+            .map(|ebb| func.dfg.num_ebb_params(ebb))
+            .sum()
+    }
+
+    fn mutate(&mut self, mut func: Function) -> Option<(Function, String, ProgressStatus)> {
+        while self.param >= func.dfg.num_ebb_params(self.ebb) {
+            self.ebb = func.layout.next_ebb(self.ebb)?;
+            self.param = 0;
+        }
+        let (ebb, index) = (self.ebb, self.param);
+        self.param += 1;
+
+        let param = func.dfg.ebb_params(ebb)[index];
+        let ty = func.dfg.value_type(param);
+
+        let mut cfg = ControlFlowGraph::new();
+        cfg.compute(&func);
+        for pred in cfg.pred_iter(ebb) {
+            let num_fixed_args = func.dfg.inst_fixed_args(pred.inst).len();
+            let mut args = func.dfg[pred.inst].take_value_list().unwrap();
+            args.remove(num_fixed_args + index, &mut func.dfg.value_lists);
+            func.dfg[pred.inst].put_value_list(args);
+        }
+
+        // Once removed from the EBB, the parameter can become the result of the constant.
+        func.dfg.remove_ebb_param(param);
+        let mut pos = FuncCursor::new(&mut func).at_first_insertion_point(ebb);
+        let const_name =
+            ReplaceInstWithConst::const_for_type(pos.ins().with_results([Some(param)]), ty);
+
+        Some((
+            func,
+            format!("Replace param {} of {} with {}", param, ebb, const_name),
+            ProgressStatus::ExpandedOrShrinked,
+        ))
+    }
+
+    fn did_crash(&mut self) {
+        // The next parameter took the place of the removed one.
+        self.param -= 1;
+    }
+}
+
+/// Try to get rid of the uses of stack slots and global values: the instructions reading them
+/// or computing their address are replaced with constants, and stores to stack slots are
+/// removed. `RemoveUnusedEntities` then removes the entities themselves.
+struct RemoveEntityUses {
+    index: usize,
+}
+
+impl RemoveEntityUses {
+    fn new() -> Self {
+        Self { index: 0 }
+    }
+}
+
+impl Mutator for RemoveEntityUses {
+    fn name(&self) -> &'static str {
+        "remove entity uses"
+    }
+
+    fn mutation_count(&self, func: &Function) -> usize {
+        func.stack_slots.keys().count() + func.global_values.len()
+    }
+
+    fn mutate(&mut self, mut func: Function) -> Option<(Function, String, ProgressStatus)> {
+        let num_stack_slots = func.stack_slots.keys().count();
+        let index = self.index;
+        self.index += 1;
+
+        let mut uses = Vec::new();
+        let entity_name = if index < num_stack_slots {
+            let slot = StackSlot::new(index);
+            for ebb in func.layout.ebbs() {
+                for inst in func.layout.ebb_insts(ebb) {
+                    match func.dfg[inst] {
+                        InstructionData::StackLoad { stack_slot, .. }
+                        | InstructionData::StackStore { stack_slot, .. }
+                            if stack_slot == slot =>
+                        {
+                            uses.push(inst)
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            slot.to_string()
+        } else if index - num_stack_slots < func.global_values.len() {
+            let gv = GlobalValue::new(index - num_stack_slots);
+            for ebb in func.layout.ebbs() {
+                for inst in func.layout.ebb_insts(ebb) {
+                    match func.dfg[inst] {
+                        InstructionData::UnaryGlobalValue { global_value, .. }
+                            if global_value == gv =>
+                        {
+                            uses.push(inst)
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            gv.to_string()
+        } else {
+            return None;
+        };
+
+        if uses.is_empty() {
+            return Some((
+                func,
+                format!("{} is unused", entity_name),
+                ProgressStatus::Skip,
+            ));
+        }
+
+        let mut status = ProgressStatus::Changed;
+        for inst in uses {
+            if func.dfg.inst_results(inst).is_empty() {
+                func.layout.remove_inst(inst);
+                status = ProgressStatus::ExpandedOrShrinked;
+            } else {
+                let ty = func.dfg.value_type(func.dfg.first_result(inst));
+                ReplaceInstWithConst::const_for_type(func.dfg.replace(inst), ty);
+            }
+        }
+
+        Some((func, format!("Remove uses of {}", entity_name), status))
+    }
+}
+
+/// Try to remove parameters and return values from the function's signature, and parameters
+/// from the signatures of the functions it calls. Removed parameters of the function itself are
+/// replaced with constants.
+struct SimplifySignature {
+    index: usize,
+}
+
+impl SimplifySignature {
+    fn new() -> Self {
+        Self { index: 0 }
+    }
+}
+
+impl Mutator for SimplifySignature {
+    fn name(&self) -> &'static str {
+        "simplify signature"
+    }
+
+    fn mutation_count(&self, func: &Function) -> usize {
+        func.signature.returns.len()
+            + func.signature.params.len()
+            + func
+                .dfg
+                .signatures
+                .values()
+                .map(|sig| sig.params.len())
+                .sum::<usize>()
+    }
+
+    fn mutate(&mut self, mut func: Function) -> Option<(Function, String, ProgressStatus)> {
+        let mut index = self.index;
+        self.index += 1;
+
+        let msg = if index < func.signature.returns.len() {
+            for ebb in func.layout.ebbs() {
+                let inst = match func.layout.last_inst(ebb) {
+                    Some(inst) => inst,
+                    None => continue,
+                };
+                if func.dfg[inst].opcode().is_return() {
+                    let mut args = func.dfg[inst].take_value_list().unwrap();
+                    args.remove(index, &mut func.dfg.value_lists);
+                    func.dfg[inst].put_value_list(args);
+                }
+            }
+            func.signature.returns.remove(index);
+            format!("Remove return value {}", index)
+        } else if index - func.signature.returns.len() < func.signature.params.len() {
+            index -= func.signature.returns.len();
+            let entry = func.layout.entry_block().unwrap();
+            let param = func.dfg.ebb_params(entry)[index];
+            let ty = func.dfg.value_type(param);
+            func.dfg.remove_ebb_param(param);
+            let mut pos = FuncCursor::new(&mut func).at_first_insertion_point(entry);
+            ReplaceInstWithConst::const_for_type(pos.ins().with_results([Some(param)]), ty);
+            func.signature.params.remove(index);
+            format!("Remove param {}", param)
+        } else {
+            index -= func.signature.returns.len() + func.signature.params.len();
+            let (sig_ref, index) = func.dfg.signatures.iter().find_map(|(sig_ref, sig)| {
+                if index < sig.params.len() {
+                    Some((sig_ref, index))
+                } else {
+                    index -= sig.params.len();
+                    None
+                }
+            })?;
+            let calls: Vec<Inst> = func
+                .layout
+                .ebbs()
+                .flat_map(|ebb| func.layout.ebb_insts(ebb))
+                .filter(|&inst| match func.dfg[inst] {
+                    InstructionData::Call { func_ref, .. } => {
+                        func.dfg.ext_funcs[func_ref].signature == sig_ref
+                    }
+                    InstructionData::CallIndirect { sig_ref: sig, .. } => sig == sig_ref,
+                    _ => false,
+                })
+                .collect();
+            for inst in calls {
+                let num_fixed_args = func.dfg.inst_fixed_args(inst).len();
+                let mut args = func.dfg[inst].take_value_list().unwrap();
+                args.remove(num_fixed_args + index, &mut func.dfg.value_lists);
+                func.dfg[inst].put_value_list(args);
+            }
+            func.dfg.signatures[sig_ref].params.remove(index);
+            format!("Remove param {} of {}", index, sig_ref)
+        };
+
+        Some((func, msg, ProgressStatus::ExpandedOrShrinked))
+    }
+
+    fn did_crash(&mut self) {
+        // The next parameter or return value took the place of the removed one.
+        self.index -= 1;
+    }
+}
+
 fn next_inst_ret_prev(func: &Function, ebb: &mut Ebb, inst: &mut Inst) -> Option<(Ebb, Inst)> {
     let prev = (*ebb, *inst);
     if let Some(next_inst) = func.layout.next_inst(*inst) {
@@ -687,15 +1014,15 @@ fn resolve_aliases(func: &mut Function) {
 fn reduce(
     isa: &dyn TargetIsa,
     mut func: Function,
+    predicate: &Predicate,
+    invocations: Vec<Invocation>,
     verbose: bool,
 ) -> Result<(Function, String), String> {
-    let mut context = CrashCheckContext::new(isa);
+    let mut context = CrashCheckContext::new(isa, predicate, invocations);
 
     match context.check_for_crash(&func) {
         CheckResult::Succeed => {
-            return Err(
-                "Given function compiled successfully or gave a verifier error.".to_string(),
-            );
+            return Err(predicate.unsatisfied_message());
         }
         CheckResult::Crash(_) => {}
     }
@@ -717,8 +1044,11 @@ fn reduce(
                 1 => Box::new(ReplaceInstWithConst::new(&func)),
                 2 => Box::new(ReplaceInstWithTrap::new(&func)),
                 3 => Box::new(RemoveEbb::new(&func)),
-                4 => Box::new(RemoveUnusedEntities::new()),
-                5 => Box::new(MergeBlocks::new(&func)),
+                4 => Box::new(ReplaceEbbParamWithConst::new(&func)),
+                5 => Box::new(RemoveEntityUses::new()),
+                6 => Box::new(SimplifySignature::new()),
+                7 => Box::new(RemoveUnusedEntities::new()),
+                8 => Box::new(MergeBlocks::new(&func)),
                 _ => break,
             };
 
@@ -815,6 +1145,12 @@ struct CrashCheckContext<'a> {
 
     /// The target isa to compile for.
     isa: &'a dyn TargetIsa,
+
+    /// The failure to preserve.
+    predicate: &'a Predicate,
+
+    /// The invocations whose results are compared for `Predicate::Miscompile`.
+    invocations: Vec<Invocation>,
 }
 
 fn get_panic_string(panic: Box<dyn std::any::Any>) -> String {
@@ -831,23 +1167,25 @@ fn get_panic_string(panic: Box<dyn std::any::Any>) -> String {
 }
 
 enum CheckResult {
-    /// The function compiled fine, or the verifier noticed an error.
+    /// The function doesn't fail in the way given by the predicate: it compiled fine, the
+    /// verifier noticed an error in the input, or it failed in another way.
     Succeed,
 
-    /// The compilation of the function panicked.
+    /// The function still fails in the way given by the predicate, as described by the message.
     Crash(String),
 }
 
 impl<'a> CrashCheckContext<'a> {
-    fn new(isa: &'a dyn TargetIsa) -> Self {
+    fn new(isa: &'a dyn TargetIsa, predicate: &'a Predicate, invocations: Vec<Invocation>) -> Self {
         CrashCheckContext {
             context: Context::new(),
             code_memory: Vec::new(),
             isa,
+            predicate,
+            invocations,
         }
     }
 
-    #[cfg_attr(test, allow(unreachable_code))]
     fn check_for_crash(&mut self, func: &Function) -> CheckResult {
         self.context.clear();
         self.code_memory.clear();
@@ -868,53 +1206,297 @@ impl<'a> CrashCheckContext<'a> {
             Err(_) => return CheckResult::Succeed,
         }
 
-        #[cfg(test)]
-        {
-            // For testing purposes we emulate a panic caused by the existence of
-            // a `call` instruction.
-            let contains_call = func.layout.ebbs().any(|ebb| {
-                func.layout.ebb_insts(ebb).any(|inst| match func.dfg[inst] {
-                    InstructionData::Call { .. } => true,
-                    _ => false,
-                })
-            });
-            if contains_call {
-                return CheckResult::Crash("test crash".to_string());
-            } else {
-                return CheckResult::Succeed;
-            }
-        }
-
         let old_panic_hook = std::panic::take_hook();
         std::panic::set_hook(Box::new(|_| {})); // silence panics
 
-        let res = match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        let predicate = self.predicate;
+        let res = match *predicate {
+            Predicate::Panic(ref text) => match (self.compile(), text) {
+                (Err(panic_msg), None) => CheckResult::Crash(panic_msg),
+                (Err(panic_msg), Some(text)) if panic_msg.contains(text) => {
+                    CheckResult::Crash(panic_msg)
+                }
+                _ => CheckResult::Succeed,
+            },
+            Predicate::VerifierError(ref text) => match self.compile() {
+                Ok(Err(CodegenError::Verifier(errors))) if errors.to_string().contains(text) => {
+                    CheckResult::Crash(errors.to_string())
+                }
+                _ => CheckResult::Succeed,
+            },
+            Predicate::Miscompile => {
+                run_with_time_limit(MISCOMPILE_TIME_LIMIT, || self.check_for_miscompile(func))
+                    .unwrap_or(CheckResult::Succeed)
+            }
+            Predicate::Timeout(time_limit) => match run_with_time_limit(time_limit, || {
+                let _ = self.compile();
+                CheckResult::Succeed
+            }) {
+                Some(_) => CheckResult::Succeed,
+                None => CheckResult::Crash(format!(
+                    "compilation took more than {} ms",
+                    time_limit.as_millis()
+                )),
+            },
+        };
+
+        std::panic::set_hook(old_panic_hook);
+
+        res
+    }
+
+    /// Compile and emit the function in `self.context`. Returns the panic message if this
+    /// panicked.
+    #[cfg(not(test))]
+    fn compile(&mut self) -> Result<CodegenResult<()>, String> {
+        std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
             let mut relocs = PrintRelocs::new(false);
             let mut traps = PrintTraps::new(false);
             let mut stackmaps = PrintStackmaps::new(false);
 
-            let _ = self.context.compile_and_emit(
-                self.isa,
-                &mut self.code_memory,
-                &mut relocs,
-                &mut traps,
-                &mut stackmaps,
-            );
-        })) {
-            Ok(()) => CheckResult::Succeed,
-            Err(err) => CheckResult::Crash(get_panic_string(err)),
+            self.context
+                .compile_and_emit(
+                    self.isa,
+                    &mut self.code_memory,
+                    &mut relocs,
+                    &mut traps,
+                    &mut stackmaps,
+                )
+                .map(|_| ())
+        }))
+        .map_err(|err| get_panic_string(err))
+    }
+
+    /// For testing purposes we emulate compilation bugs triggered by some instructions: `call`
+    /// panics, `bitrev` gives a verifier error and `debugtrap` makes compilation hang.
+    #[cfg(test)]
+    fn compile(&mut self) -> Result<CodegenResult<()>, String> {
+        let func = &self.context.func;
+        let contains = |opcode| {
+            func.layout.ebbs().any(|ebb| {
+                func.layout
+                    .ebb_insts(ebb)
+                    .any(|inst| func.dfg[inst].opcode() == opcode)
+            })
         };
+        std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            if contains(ir::Opcode::Call) {
+                panic!("test crash");
+            }
+            if contains(ir::Opcode::Debugtrap) {
+                std::thread::sleep(Duration::from_secs(2));
+            }
+            if contains(ir::Opcode::Bitrev) {
+                return Err(CodegenError::Verifier(
+                    vec![cranelift_codegen::verifier::VerifierError {
+                        location: ir::entities::AnyEntity::Function,
+                        context: None,
+                        message: "test verifier error".to_string(),
+                    }]
+                    .into(),
+                ));
+            }
+            Ok(())
+        }))
+        .map_err(|err| get_panic_string(err))
+    }
 
-        std::panic::set_hook(old_panic_hook);
+    /// Compile the function for the host, returning a closure that runs it.
+    #[cfg(not(test))]
+    fn compile_for_host(&self, func: &Function) -> Result<Box<HostFunction>, String> {
+        let runner = FunctionRunner::with_host_isa(func.clone(), self.isa.flags().clone());
+        let compiled = runner.compile()?;
+        Ok(Box::new(move |args| compiled.call(args)))
+    }
 
-        res
+    /// For testing purposes we emulate a miscompilation of `bnot` as `copy`, running the
+    /// miscompiled function in the interpreter.
+    #[cfg(test)]
+    fn compile_for_host(&self, func: &Function) -> Result<Box<HostFunction>, String> {
+        let mut miscompiled = func.clone();
+        for ebb in func.layout.ebbs() {
+            for inst in func.layout.ebb_insts(ebb) {
+                if func.dfg[inst].opcode() == ir::Opcode::Bnot {
+                    let arg = func.dfg.inst_args(inst)[0];
+                    miscompiled.dfg.replace(inst).copy(arg);
+                }
+            }
+        }
+        let name = func.name.to_string();
+        Ok(Box::new(move |args| {
+            let mut env = Environment::default();
+            env.add(miscompiled.clone());
+            match Interpreter::new(env).call_by_name(&name, args) {
+                Ok(ControlFlow::Return(results)) => Ok(results),
+                Ok(ControlFlow::Trap(code)) => Err(format!("trapped with {}", code)),
+                Err(err) => Err(err.to_string()),
+            }
+        }))
+    }
+
+    /// Run the invocations both natively and in the interpreter, and report the first one whose
+    /// results differ. Invocations that trap in the interpreter, or that it can't execute, give
+    /// nothing to compare against and are skipped.
+    fn check_for_miscompile(&self, func: &Function) -> CheckResult {
+        let compiled = match self.compile_for_host(func) {
+            Ok(compiled) => compiled,
+            Err(_) => return CheckResult::Succeed,
+        };
+
+        let mut env = Environment::default();
+        env.add(func.clone());
+        let mut interpreter = Interpreter::new(env);
+
+        for invocation in &self.invocations {
+            let expected = match interpreter.call_by_name(&func.name.to_string(), &invocation.args)
+            {
+                Ok(ControlFlow::Return(results)) => results,
+                Ok(ControlFlow::Trap(_)) | Err(_) => continue,
+            };
+            match compiled(&invocation.args) {
+                Ok(ref actual) if same_results(actual, &expected) => {}
+                Ok(actual) => {
+                    return CheckResult::Crash(format!(
+                        "{} returned {:?} but the interpreter returned {:?}",
+                        invocation, actual, expected
+                    ))
+                }
+                Err(err) => {
+                    return CheckResult::Crash(format!(
+                        "{} failed ({}) but the interpreter returned {:?}",
+                        invocation, err, expected
+                    ))
+                }
+            }
+        }
+        CheckResult::Succeed
+    }
+}
+
+/// A function compiled for the host, called with its arguments.
+type HostFunction = dyn Fn(&[DataValue]) -> Result<Vec<DataValue>, String>;
+
+/// Compare results, considering all NaNs equal: their bit patterns may legitimately differ.
+fn same_results(actual: &[DataValue], expected: &[DataValue]) -> bool {
+    actual.len() == expected.len()
+        && actual.iter().zip(expected).all(|pair| match pair {
+            (DataValue::F32(a), DataValue::F32(b)) => {
+                a == b || (f32::from_bits(a.bits()).is_nan() && f32::from_bits(b.bits()).is_nan())
+            }
+            (DataValue::F64(a), DataValue::F64(b)) => {
+                a == b || (f64::from_bits(a.bits()).is_nan() && f64::from_bits(b.bits()).is_nan())
+            }
+            (a, b) => a == b,
+        })
+}
+
+/// The maximum length of the report a child process sends back: it must fit in the pipe buffer
+/// so that the child never blocks writing it.
+#[cfg(unix)]
+const MAX_REPORT_LEN: usize = 4096;
+
+/// Run `check` in a child process, killing it if it doesn't finish within `time_limit`, in which
+/// case `None` is returned. This is the only way to get out of a hang, and also protects bugpoint
+/// from crashes of the code being checked: a child that is killed by a signal, e.g. because the
+/// code being checked segfaulted, or that exits abnormally is reported as a crash.
+#[cfg(unix)]
+fn run_with_time_limit<F>(time_limit: Duration, check: F) -> Option<CheckResult>
+where
+    F: FnOnce() -> CheckResult,
+{
+    use std::io::{self, Read};
+    use std::os::unix::io::FromRawFd;
+
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+        panic!("unable to create a pipe: {}", io::Error::last_os_error());
+    }
+    let (read_fd, write_fd) = (fds[0], fds[1]);
+
+    match unsafe { libc::fork() } {
+        -1 => panic!("unable to fork: {}", io::Error::last_os_error()),
+        0 => unsafe {
+            libc::close(read_fd);
+            // The report is a status byte, followed by the message if the check crashed.
+            let report = match std::panic::catch_unwind(std::panic::AssertUnwindSafe(check)) {
+                Ok(CheckResult::Crash(msg)) => format!("1{}", msg),
+                Ok(CheckResult::Succeed) | Err(_) => "0".to_string(),
+            };
+            let len = report.len().min(MAX_REPORT_LEN);
+            libc::write(write_fd, report.as_ptr() as *const libc::c_void, len);
+            libc::_exit(0)
+        },
+        pid => {
+            unsafe { libc::close(write_fd) };
+            let mut output = unsafe { std::fs::File::from_raw_fd(read_fd) };
+
+            let start = Instant::now();
+            let mut status = 0;
+            loop {
+                match unsafe { libc::waitpid(pid, &mut status, libc::WNOHANG) } {
+                    0 if start.elapsed() < time_limit => {
+                        std::thread::sleep(Duration::from_millis(1))
+                    }
+                    0 => {
+                        unsafe {
+                            libc::kill(pid, libc::SIGKILL);
+                            libc::waitpid(pid, &mut status, 0);
+                        }
+                        return None;
+                    }
+                    -1 => {
+                        let error = io::Error::last_os_error();
+                        if error.kind() != io::ErrorKind::Interrupted {
+                            panic!("unable to wait for the child process: {}", error);
+                        }
+                    }
+                    _ => break,
+                }
+            }
+
+            if libc::WIFSIGNALED(status) {
+                return Some(CheckResult::Crash(format!(
+                    "the check was killed by signal {}",
+                    libc::WTERMSIG(status)
+                )));
+            }
+            if libc::WEXITSTATUS(status) != 0 {
+                return Some(CheckResult::Crash(format!(
+                    "the check exited with status {}",
+                    libc::WEXITSTATUS(status)
+                )));
+            }
+
+            let mut report = Vec::new();
+            output.read_to_end(&mut report).unwrap();
+            Some(match report.split_first() {
+                Some((b'1', msg)) => CheckResult::Crash(String::from_utf8_lossy(msg).into_owned()),
+                _ => CheckResult::Succeed,
+            })
+        }
+    }
+}
+
+/// Without a way to isolate `check`, run it in-process: this detects slow checks once they are
+/// done, but can't get out of a hang.
+#[cfg(not(unix))]
+fn run_with_time_limit<F>(time_limit: Duration, check: F) -> Option<CheckResult>
+where
+    F: FnOnce() -> CheckResult,
+{
+    let start = Instant::now();
+    let result = check();
+    if start.elapsed() < time_limit {
+        Some(result)
+    } else {
+        None
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use cranelift_reader::ParseOptions;
+    use cranelift_reader::{parse_functions, ParseOptions};
 
     #[test]
     fn test_reduce() {
@@ -928,12 +1510,14 @@ mod tests {
         let isa = test_file.isa_spec.unique_isa().expect("Unknown isa");
 
         for (func, _) in test_file.functions {
-            let (reduced_func, crash_msg) =
-                reduce(isa, func, false).expect("Couldn't reduce test case");
+            let predicate = Predicate::Panic(None);
+            let (reduced_func, crash_msg) = reduce(isa, func, &predicate, Vec::new(), false)
+                .expect("Couldn't reduce test case");
             assert_eq!(crash_msg, "test crash");
 
             let (func_reduced_twice, crash_msg) =
-                reduce(isa, reduced_func.clone(), false).expect("Couldn't re-reduce test case");
+                reduce(isa, reduced_func.clone(), &predicate, Vec::new(), false)
+                    .expect("Couldn't re-reduce test case");
             assert_eq!(crash_msg, "test crash");
 
             assert_eq!(
@@ -950,4 +1534,291 @@ mod tests {
             assert_eq!(format!("{}", reduced_func), EXPECTED.replace("\r\n", "\n"));
         }
     }
+
+    /// Reduce the single function of `test` for `predicate`, returning the crash message and
+    /// the reduced function.
+    fn reduce_test(test: &str, predicate: &Predicate) -> (String, String) {
+        let test_file = parse_test(test, ParseOptions::default()).unwrap();
+        let isa = test_file.isa_spec.unique_isa().expect("Unknown isa");
+        let (func, details) = test_file.functions.into_iter().next().unwrap();
+        let invocations = details
+            .comments
+            .iter()
+            .filter_map(|comment| parse_run_command(comment.text, &func).unwrap())
+            .map(|command| command.invocation)
+            .collect();
+        let (reduced_func, crash_msg) =
+            reduce(isa, func, predicate, invocations, false).expect("Couldn't reduce test case");
+        (crash_msg, reduced_func.to_string())
+    }
+
+    #[test]
+    fn test_reduce_verifier_error() {
+        let (crash_msg, reduced_func) = reduce_test(
+            "target x86_64
+            function %f(i32, i64) -> i32 {
+                ss0 = explicit_slot 4
+            ebb0(v0: i32, v1: i64):
+                stack_store v0, ss0
+                v2 = stack_load.i32 ss0
+                v3 = bitrev v2
+                brz v3, ebb1(v0)
+                jump ebb1(v3)
+            ebb1(v4: i32):
+                v5 = iadd_imm v4, 1
+                return v5
+            }",
+            &Predicate::VerifierError("test verifier".to_string()),
+        );
+        assert_eq!(crash_msg, "- function: test verifier error\n");
+        assert_eq!(
+            reduced_func,
+            "function %f() fast {
+ebb0:
+    v2 = iconst.i32 0
+    v3 = bitrev v2
+    trap user0
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_reduce_timeout() {
+        let (crash_msg, reduced_func) = reduce_test(
+            "target x86_64
+            function %f(i64) -> i64 {
+                gv0 = symbol %counter
+            ebb0(v0: i64):
+                v1 = global_value.i64 gv0
+                v2 = load.i64 v1
+                debugtrap
+                v3 = iadd v0, v2
+                store v3, v1
+                return v3
+            }",
+            &Predicate::Timeout(Duration::from_millis(100)),
+        );
+        assert_eq!(crash_msg, "compilation took more than 100 ms");
+        assert_eq!(
+            reduced_func,
+            "function %f() fast {
+ebb0:
+    debugtrap \n    return
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_reduce_miscompile() {
+        let (crash_msg, reduced_func) = reduce_test(
+            "target x86_64
+            function %f(i32, i32) -> i32, i32 {
+            ebb0(v0: i32, v1: i32):
+                v2 = iadd v0, v1
+                v3 = bnot v0
+                brz v1, ebb1(v2, v3)
+                jump ebb1(v3, v2)
+            ebb1(v4: i32, v5: i32):
+                v6 = imul_imm v4, 3
+                return v6, v5
+            }
+            ; run: %f(1, 0) == [-6, 1]",
+            &Predicate::Miscompile,
+        );
+        assert_eq!(
+            crash_msg,
+            "%f(1, 0) returned [I32(3)] but the interpreter returned [I32(-6)]"
+        );
+        assert_eq!(
+            reduced_func,
+            "function %f(i32, i32) -> i32 fast {
+ebb0(v0: i32, v1: i32):
+    v3 = bnot v0
+    v4 -> v3
+    v6 = imul_imm v4, 3
+    return v6
+}
+"
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_abnormal_exit_is_a_crash() {
+        match run_with_time_limit(Duration::from_secs(10), || std::process::abort()) {
+            Some(CheckResult::Crash(msg)) => assert_eq!(
+                msg,
+                format!("the check was killed by signal {}", libc::SIGABRT)
+            ),
+            _ => panic!("expected a crash"),
+        }
+    }
+
+    /// Apply each mutation of `mutator` to the function in `func`, returning the results of the
+    /// ones that changed it.
+    fn mutations(mut mutator: Box<dyn Mutator>, func: &Function) -> Vec<String> {
+        let mut mutated = Vec::new();
+        while let Some((mutated_func, _, status)) = mutator.mutate(func.clone()) {
+            if let ProgressStatus::Skip = status {
+                continue;
+            }
+            mutated.push(mutated_func.to_string());
+        }
+        mutated
+    }
+
+    #[test]
+    fn test_replace_ebb_param_with_const() {
+        let func = parse_functions(
+            "function %f(i32) -> i32, i32 {
+            ebb0(v0: i32):
+                jump ebb1(v0, v0)
+            ebb1(v1: i32, v2: i32):
+                return v1, v2
+            }",
+        )
+        .unwrap()
+        .remove(0);
+        let mutated = mutations(Box::new(ReplaceEbbParamWithConst::new(&func)), &func);
+        assert_eq!(
+            mutated,
+            [
+                "function %f(i32) -> i32, i32 fast {
+ebb0(v0: i32):
+    jump ebb1(v0)
+
+ebb1(v2: i32):
+    v1 = iconst.i32 0
+    return v1, v2
+}
+",
+                "function %f(i32) -> i32, i32 fast {
+ebb0(v0: i32):
+    jump ebb1(v0)
+
+ebb1(v1: i32):
+    v2 = iconst.i32 0
+    return v1, v2
+}
+",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_remove_entity_uses() {
+        let func = parse_functions(
+            "function %f(i32) -> i32 {
+                ss0 = explicit_slot 4
+                gv0 = symbol %counter
+            ebb0(v0: i32):
+                stack_store v0, ss0
+                v1 = stack_load.i32 ss0
+                v2 = global_value.i64 gv0
+                store v1, v2
+                return v1
+            }",
+        )
+        .unwrap()
+        .remove(0);
+        let mutated = mutations(Box::new(RemoveEntityUses::new()), &func);
+        assert_eq!(
+            mutated,
+            [
+                "function %f(i32) -> i32 fast {
+    ss0 = explicit_slot 4
+    gv0 = symbol %counter
+
+ebb0(v0: i32):
+    v1 = iconst.i32 0
+    v2 = global_value.i64 gv0
+    store v1, v2
+    return v1
+}
+",
+                "function %f(i32) -> i32 fast {
+    ss0 = explicit_slot 4
+    gv0 = symbol %counter
+
+ebb0(v0: i32):
+    stack_store v0, ss0
+    v1 = stack_load.i32 ss0
+    v2 = iconst.i64 0
+    store v1, v2
+    return v1
+}
+",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_simplify_signature() {
+        let func = parse_functions(
+            "function %f(i32, i64) -> i32 {
+                sig0 = (i32, i64)
+                fn0 = %g sig0
+            ebb0(v0: i32, v1: i64):
+                call fn0(v0, v1)
+                return v0
+            }",
+        )
+        .unwrap()
+        .remove(0);
+        let mutated = mutations(Box::new(SimplifySignature::new()), &func);
+        assert_eq!(
+            mutated,
+            [
+                "function %f(i32, i64) fast {
+    sig0 = (i32, i64) fast
+    fn0 = %g sig0
+
+ebb0(v0: i32, v1: i64):
+    call fn0(v0, v1)
+    return
+}
+",
+                "function %f(i64) -> i32 fast {
+    sig0 = (i32, i64) fast
+    fn0 = %g sig0
+
+ebb0(v1: i64):
+    v0 = iconst.i32 0
+    call fn0(v0, v1)
+    return v0
+}
+",
+                "function %f(i32) -> i32 fast {
+    sig0 = (i32, i64) fast
+    fn0 = %g sig0
+
+ebb0(v0: i32):
+    v1 = iconst.i64 0
+    call fn0(v0, v1)
+    return v0
+}
+",
+                "function %f(i32, i64) -> i32 fast {
+    sig0 = (i64) fast
+    fn0 = %g sig0
+
+ebb0(v0: i32, v1: i64):
+    call fn0(v1)
+    return v0
+}
+",
+                "function %f(i32, i64) -> i32 fast {
+    sig0 = (i32) fast
+    fn0 = %g sig0
+
+ebb0(v0: i32, v1: i64):
+    call fn0(v0)
+    return v0
+}
+",
+            ]
+        );
+    }
 }
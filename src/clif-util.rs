@@ -13,12 +13,13 @@
     )
 )]
 
-use clap::{App, Arg, SubCommand};
+use clap::{App, Arg, ArgGroup, SubCommand};
 use cranelift_codegen::dbg::LOG_FILENAME_PREFIX;
 use cranelift_codegen::VERSION;
 use std::io::{self, Write};
 use std::option::Option;
 use std::process;
+use std::time::Duration;
 
 mod bugpoint;
 mod cat;
@@ -210,11 +211,40 @@ fn main() {
         )
         .subcommand(
             SubCommand::with_name("bugpoint")
-                .about("Reduce size of clif file causing panic during compilation.")
+                .about("Reduce size of clif file failing during compilation.")
                 .arg(add_single_input_file_arg())
                 .arg(add_set_flag())
                 .arg(add_target_flag())
-                .arg(add_verbose_flag()),
+                .arg(add_verbose_flag())
+                .arg(
+                    Arg::with_name("panic-message")
+                        .long("panic-message")
+                        .takes_value(true)
+                        .help("Only consider panics whose message contains this text"),
+                )
+                .arg(
+                    Arg::with_name("verifier-error")
+                        .long("verifier-error")
+                        .takes_value(true)
+                        .help("Reduce a verifier error containing this text instead of a panic"),
+                )
+                .arg(Arg::with_name("miscompile").long("miscompile").help(
+                    "Reduce a function whose run commands give different results when compiled \
+                     for the host than when interpreted",
+                ))
+                .arg(
+                    Arg::with_name("timeout")
+                        .long("timeout")
+                        .takes_value(true)
+                        .value_name("ms")
+                        .help("Reduce a function taking longer than this to compile"),
+                )
+                .group(ArgGroup::with_name("predicate").args(&[
+                    "panic-message",
+                    "verifier-error",
+                    "miscompile",
+                    "timeout",
+                ])),
         );
 
     let res_util = match app_cmds.get_matches().subcommand() {
@@ -314,12 +344,29 @@ fn main() {
                 target_val = clap_target;
             }
 
-            bugpoint::run(
-                rest_cmd.value_of("single-file").unwrap(),
-                &get_vec(rest_cmd.values_of("set")),
-                target_val,
-                rest_cmd.is_present("verbose"),
-            )
+            let predicate = if let Some(text) = rest_cmd.value_of("verifier-error") {
+                Ok(bugpoint::Predicate::VerifierError(text.to_string()))
+            } else if rest_cmd.is_present("miscompile") {
+                Ok(bugpoint::Predicate::Miscompile)
+            } else if let Some(ms) = rest_cmd.value_of("timeout") {
+                ms.parse()
+                    .map(|ms| bugpoint::Predicate::Timeout(Duration::from_millis(ms)))
+                    .map_err(|err| format!("invalid timeout {}: {}", ms, err))
+            } else {
+                Ok(bugpoint::Predicate::Panic(
+                    rest_cmd.value_of("panic-message").map(str::to_string),
+                ))
+            };
+
+            predicate.and_then(|predicate| {
+                bugpoint::run(
+                    rest_cmd.value_of("single-file").unwrap(),
+                    &get_vec(rest_cmd.values_of("set")),
+                    target_val,
+                    &predicate,
+                    rest_cmd.is_present("verbose"),
+                )
+            })
         }
         _ => Err("Invalid subcommand.".to_owned()),
     };
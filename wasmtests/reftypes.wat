@@ -0,0 +1,25 @@
+(module
+  (table $t 1 externref)
+  (func $f)
+  (func (export "get") (param i32) (result externref)
+    (table.get $t (local.get 0))
+  )
+  (func (export "set") (param i32 externref)
+    (table.set $t (local.get 0) (local.get 1))
+  )
+  (func (export "grow") (param externref i32) (result i32)
+    (table.grow $t (local.get 0) (local.get 1))
+  )
+  (func (export "fill") (param i32 externref i32)
+    (table.fill $t (local.get 0) (local.get 1) (local.get 2))
+  )
+  (func (export "select") (param externref externref i32) (result externref)
+    (select (result externref) (local.get 0) (local.get 1) (local.get 2))
+  )
+  (func (export "is_null") (param externref) (result i32)
+    (ref.is_null (local.get 0))
+  )
+  (func (export "ref_func") (result funcref)
+    (ref.func $f)
+  )
+)
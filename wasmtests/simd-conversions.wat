@@ -0,0 +1,55 @@
+(module
+  (memory 1)
+
+  (func $test_narrow (param v128 v128) (result v128)
+    local.get 0
+    local.get 1
+    i8x16.narrow_i16x8_s
+    local.get 1
+    i16x8.narrow_i32x4_u
+    i16x8.extend_high_i8x16_u
+  )
+
+  (func $test_widen (param v128) (result v128)
+    local.get 0
+    i16x8.extend_low_i8x16_s
+    i32x4.extend_high_i16x8_s
+  )
+
+  (func $test_float_conversions (param v128) (result v128)
+    local.get 0
+    i32x4.trunc_sat_f32x4_u
+    f32x4.convert_i32x4_s
+  )
+
+  (func $test_swizzle (param v128 v128) (result v128)
+    local.get 0
+    local.get 1
+    i8x16.swizzle
+    local.get 1
+    i8x16.avgr_u
+  )
+
+  (func $test_i8x16_shift (param v128 i32) (result v128)
+    local.get 0
+    local.get 1
+    i8x16.shl
+    local.get 1
+    i8x16.shr_s
+  )
+
+  (func $test_loads (param i32) (result v128)
+    local.get 0
+    v128.load8x8_u
+    local.get 0
+    v128.load16_splat
+    i16x8.add
+  )
+
+  (export "test_narrow" (func $test_narrow))
+  (export "test_widen" (func $test_widen))
+  (export "test_float_conversions" (func $test_float_conversions))
+  (export "test_swizzle" (func $test_swizzle))
+  (export "test_i8x16_shift" (func $test_i8x16_shift))
+  (export "test_loads" (func $test_loads))
+)
@@ -0,0 +1,32 @@
+(module
+  (memory 1 1 shared)
+  (func (export "load") (param i32) (result i64)
+    (i64.add
+      (i64.extend_i32_u (i32.atomic.load (local.get 0)))
+      (i64.add
+        (i64.atomic.load8_u offset=3 (local.get 0))
+        (i64.atomic.load32_u offset=4 (local.get 0))))
+  )
+  (func (export "store") (param i32 i64)
+    (i32.atomic.store16 (local.get 0) (i32.wrap_i64 (local.get 1)))
+    (i64.atomic.store offset=8 (local.get 0) (local.get 1))
+    (atomic.fence)
+  )
+  (func (export "rmw") (param i32 i32) (result i32)
+    (drop (i32.atomic.rmw.add (local.get 0) (local.get 1)))
+    (drop (i32.atomic.rmw.sub (local.get 0) (local.get 1)))
+    (drop (i32.atomic.rmw8.and_u (local.get 0) (local.get 1)))
+    (drop (i64.atomic.rmw16.or_u (local.get 0) (i64.extend_i32_u (local.get 1))))
+    (drop (i64.atomic.rmw.xor (local.get 0) (i64.const 1)))
+    (i32.atomic.rmw.xchg (local.get 0) (local.get 1))
+  )
+  (func (export "cmpxchg") (param i32 i64 i64) (result i64)
+    (drop (i32.atomic.rmw.cmpxchg (local.get 0) (i32.const 0) (i32.const 1)))
+    (i64.atomic.rmw32.cmpxchg_u (local.get 0) (local.get 1) (local.get 2))
+  )
+  (func (export "wait_notify") (param i32) (result i32)
+    (drop (i32.atomic.wait (local.get 0) (i32.const 0) (i64.const -1)))
+    (drop (i64.atomic.wait (local.get 0) (i64.const 0) (i64.const 1000)))
+    (atomic.notify (local.get 0) (i32.const 1))
+  )
+)